@@ -0,0 +1,199 @@
+use crate::IceEvent;
+use std::collections::VecDeque;
+
+/// Queue of events returned by [`IceAgent::poll_event`](crate::IceAgent::poll_event).
+///
+/// With the `tracing` feature enabled, every event is also emitted as a tracing event inside a
+/// span identifying the agent.
+pub(crate) struct EventQueue {
+    events: VecDeque<IceEvent>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl EventQueue {
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn new(ufrag: &str, is_controlling: bool) -> Self {
+        Self {
+            events: VecDeque::new(),
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!("ice_agent", ufrag, controlling = is_controlling),
+        }
+    }
+
+    /// Update the fields of the agent's span after an ICE restart or role change
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn set_agent(&self, ufrag: &str, is_controlling: bool) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("ufrag", ufrag);
+            self.span.record("controlling", is_controlling);
+        }
+    }
+
+    pub(crate) fn push(&mut self, event: IceEvent) {
+        #[cfg(feature = "tracing")]
+        self.span.in_scope(|| trace(&event));
+
+        self.events.push_back(event);
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<IceEvent> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn trace(event: &IceEvent) {
+    match event {
+        IceEvent::GatheringStateChanged { old, new } => {
+            tracing::info!(?old, ?new, "gathering state changed")
+        }
+        IceEvent::ConnectionStateChanged { old, new } => {
+            tracing::info!(?old, ?new, "connection state changed")
+        }
+        IceEvent::NewLocalCandidate { candidate } => tracing::debug!(
+            kind = ?candidate.kind,
+            component = candidate.component,
+            addr = %candidate.addr,
+            protocol = ?candidate.protocol(),
+            "new local candidate"
+        ),
+        IceEvent::EndOfCandidates => tracing::debug!("end of candidates"),
+        IceEvent::PairStateChanged {
+            component,
+            local,
+            remote,
+            old,
+            new,
+        } => tracing::debug!(
+            component,
+            %local,
+            %remote,
+            ?old,
+            ?new,
+            "pair state changed"
+        ),
+        IceEvent::SelectedPairChanged {
+            component,
+            local,
+            remote,
+        } => tracing::info!(component, %local, %remote, "selected pair changed"),
+        IceEvent::NominationCompleted => tracing::info!("nomination completed"),
+        IceEvent::RelayedCandidateUnused { relayed } => {
+            tracing::debug!(%relayed, "relayed candidate unused")
+        }
+        IceEvent::StunServerFailed {
+            server,
+            local,
+            error,
+        } => tracing::warn!(%server, %local, ?error, "STUN server failed"),
+        IceEvent::ConsentExpired {
+            component,
+            local,
+            remote,
+        } => tracing::warn!(component, %local, %remote, "consent expired"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{exchange, peer, run, Peer};
+    use crate::{IceConnectionState, PairState};
+    use std::time::Instant;
+
+    /// Events of the agent that follow the progress of the connectivity checks
+    fn check_events(peer: &mut Peer) -> Vec<IceEvent> {
+        std::iter::from_fn(|| peer.agent.poll_event())
+            .filter(|event| {
+                matches!(
+                    event,
+                    IceEvent::PairStateChanged { .. }
+                        | IceEvent::SelectedPairChanged { .. }
+                        | IceEvent::NominationCompleted
+                        | IceEvent::ConnectionStateChanged { .. }
+                )
+            })
+            .collect()
+    }
+
+    fn pair_state(peer: &Peer, remote: &Peer, old: PairState, new: PairState) -> IceEvent {
+        IceEvent::PairStateChanged {
+            component: 1,
+            local: peer.addr,
+            remote: remote.addr,
+            old,
+            new,
+        }
+    }
+
+    #[test]
+    fn nomination_sequence() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        let checking = IceEvent::ConnectionStateChanged {
+            old: IceConnectionState::New,
+            new: IceConnectionState::Checking,
+        };
+        let connected = IceEvent::ConnectionStateChanged {
+            old: IceConnectionState::Checking,
+            new: IceConnectionState::Connected,
+        };
+
+        // The check of the controlling agent is still in progress when the check of the
+        // controlled agent arrives, which triggers a new check on the same pair
+        assert_eq!(
+            check_events(&mut a),
+            [
+                pair_state(&a, &b, PairState::Frozen, PairState::InProgress),
+                checking.clone(),
+                pair_state(&a, &b, PairState::InProgress, PairState::Waiting),
+                pair_state(&a, &b, PairState::Waiting, PairState::InProgress),
+                pair_state(&a, &b, PairState::InProgress, PairState::Succeeded),
+                IceEvent::SelectedPairChanged {
+                    component: 1,
+                    local: a.addr,
+                    remote: b.addr,
+                },
+                IceEvent::NominationCompleted,
+                connected.clone(),
+            ]
+        );
+
+        // The controlled agent selects the pair once it was nominated by the controlling agent
+        assert_eq!(
+            check_events(&mut b),
+            [
+                pair_state(&b, &a, PairState::Frozen, PairState::InProgress),
+                checking,
+                pair_state(&b, &a, PairState::InProgress, PairState::Succeeded),
+                IceEvent::SelectedPairChanged {
+                    component: 1,
+                    local: b.addr,
+                    remote: a.addr,
+                },
+                IceEvent::NominationCompleted,
+                connected,
+            ]
+        );
+
+        // No further events once nomination completed
+        let mut ticks = 0;
+        run(&mut now, &mut a, &mut b, |_, _| {
+            ticks += 1;
+            ticks == 300
+        });
+        assert_eq!(check_events(&mut a), []);
+        assert_eq!(check_events(&mut b), []);
+    }
+}
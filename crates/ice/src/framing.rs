@@ -0,0 +1,83 @@
+//! Framing of packets sent over TCP candidates
+//! ([RFC4571](https://datatracker.ietf.org/doc/html/rfc4571#section-2)), as required by
+//! [RFC6544](https://datatracker.ietf.org/doc/html/rfc6544#section-3)
+//!
+//! Each STUN or media packet is prefixed with its length as 16 bit big endian integer.
+
+/// Frame a packet to be sent over a TCP connection
+///
+/// # Panics
+///
+/// If the packet is larger than 65535 bytes
+pub fn frame(packet: &[u8]) -> Vec<u8> {
+    let len = u16::try_from(packet.len()).expect("packet too large for RFC4571 framing");
+
+    let mut framed = Vec::with_capacity(2 + packet.len());
+    framed.extend_from_slice(&len.to_be_bytes());
+    framed.extend_from_slice(packet);
+    framed
+}
+
+/// Splits the byte stream of a TCP connection into packets
+#[derive(Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append bytes received on the connection
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Take the next complete packet
+    pub fn next_packet(&mut self) -> Option<Vec<u8>> {
+        if self.buffer.len() < 2 {
+            return None;
+        }
+
+        let len = usize::from(u16::from_be_bytes([self.buffer[0], self.buffer[1]]));
+
+        if self.buffer.len() < 2 + len {
+            return None;
+        }
+
+        let packet = self.buffer[2..2 + len].to_vec();
+        self.buffer.drain(..2 + len);
+
+        Some(packet)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frame_and_decode() {
+        let mut stream = frame(b"hello");
+        stream.extend(frame(b""));
+        stream.extend(frame(b"world"));
+
+        let mut decoder = FrameDecoder::new();
+
+        // deliver the stream in small pieces
+        for chunk in stream.chunks(3) {
+            decoder.push(chunk);
+        }
+
+        assert_eq!(decoder.next_packet().unwrap(), b"hello");
+        assert_eq!(decoder.next_packet().unwrap(), b"");
+        assert_eq!(decoder.next_packet().unwrap(), b"world");
+        assert_eq!(decoder.next_packet(), None);
+
+        decoder.push(&[0, 4, 1]);
+        assert_eq!(decoder.next_packet(), None);
+        decoder.push(&[2, 3, 4]);
+        assert_eq!(decoder.next_packet().unwrap(), [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,254 @@
+//! Selection of the local interfaces host candidates are gathered from
+
+use std::net::IpAddr;
+
+/// Name prefixes of VPN, tunnel and virtual bridge devices
+const VIRTUAL_PREFIXES: &[&str] = &[
+    "tun", "tap", "utun", "wg", "ppp", "ipsec", "zt", "docker", "veth", "br-", "virbr", "vmnet",
+    "vboxnet",
+];
+
+/// Address of a local network interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+    pub name: String,
+    pub ip: IpAddr,
+    /// The interface is a point-to-point link, which is usually the case for VPNs
+    pub point_to_point: bool,
+}
+
+/// Matches interfaces by name or address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceFilter {
+    /// Name of the interface, a trailing `*` matches any suffix (e.g. `eth*`)
+    Name(String),
+    /// Addresses inside the network `ip/prefix_len`
+    Network { ip: IpAddr, prefix_len: u8 },
+}
+
+impl InterfaceFilter {
+    fn matches_name(&self, name: &str) -> bool {
+        match self {
+            InterfaceFilter::Name(pattern) => match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            },
+            InterfaceFilter::Network { .. } => false,
+        }
+    }
+
+    fn matches_ip(&self, ip: IpAddr) -> bool {
+        let InterfaceFilter::Network {
+            ip: network,
+            prefix_len,
+        } = *self
+        else {
+            return false;
+        };
+
+        match (network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(prefix_len.min(32)));
+                let mask = mask.unwrap_or(0);
+
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(prefix_len.min(128)));
+                let mask = mask.unwrap_or(0);
+
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Controls which local addresses are used to gather host candidates, and thus revealed to the
+/// peer.
+///
+/// By default loopback, link-local and VPN/virtual devices are skipped.
+#[derive(Debug, Clone)]
+pub struct InterfacePolicy {
+    /// If not empty, only interfaces matching one of the filters are used
+    pub allow: Vec<InterfaceFilter>,
+    /// Interfaces matching one of the filters are never used
+    pub deny: Vec<InterfaceFilter>,
+    pub skip_loopback: bool,
+    pub skip_link_local: bool,
+    /// Skip point-to-point interfaces and devices with a typical VPN, tunnel or virtual bridge
+    /// name (`tun*`, `wg*`, `docker*`, ...)
+    pub skip_virtual: bool,
+}
+
+impl Default for InterfacePolicy {
+    fn default() -> Self {
+        Self {
+            allow: vec![],
+            deny: vec![],
+            skip_loopback: true,
+            skip_link_local: true,
+            skip_virtual: true,
+        }
+    }
+}
+
+impl InterfacePolicy {
+    /// Policy which accepts every address
+    pub fn allow_all() -> Self {
+        Self {
+            allow: vec![],
+            deny: vec![],
+            skip_loopback: false,
+            skip_link_local: false,
+            skip_virtual: false,
+        }
+    }
+
+    /// Returns if the interface may be used
+    pub fn accepts(&self, interface: &Interface) -> bool {
+        if self.skip_virtual
+            && (interface.point_to_point
+                || VIRTUAL_PREFIXES
+                    .iter()
+                    .any(|prefix| interface.name.starts_with(prefix)))
+        {
+            return false;
+        }
+
+        if self.deny.iter().any(|f| f.matches_name(&interface.name)) {
+            return false;
+        }
+
+        let allowed_by_name = self.allow.iter().any(|f| f.matches_name(&interface.name));
+
+        self.accepts_ip_inner(interface.ip, self.allow.is_empty() || allowed_by_name)
+    }
+
+    /// Returns if the address may be used when the name of its interface is unknown. Name filters
+    /// of the allowlist are assumed to match, those of the denylist to not match.
+    pub fn accepts_ip(&self, ip: IpAddr) -> bool {
+        let allowed_by_name = self
+            .allow
+            .iter()
+            .any(|f| matches!(f, InterfaceFilter::Name(_)));
+
+        self.accepts_ip_inner(ip, self.allow.is_empty() || allowed_by_name)
+    }
+
+    fn accepts_ip_inner(&self, ip: IpAddr, allowed: bool) -> bool {
+        if ip.is_unspecified() || ip.is_multicast() {
+            return false;
+        }
+
+        if self.skip_loopback && ip.is_loopback() {
+            return false;
+        }
+
+        if self.skip_link_local && is_link_local(ip) {
+            return false;
+        }
+
+        if self.deny.iter().any(|f| f.matches_ip(ip)) {
+            return false;
+        }
+
+        allowed || self.allow.iter().any(|f| f.matches_ip(ip))
+    }
+
+    /// Enumerate the addresses of the local interfaces which are accepted by the policy
+    #[cfg(feature = "interfaces")]
+    pub fn enumerate(&self) -> std::io::Result<Vec<Interface>> {
+        let interfaces = if_addrs::get_if_addrs()?
+            .into_iter()
+            .filter(|interface| interface.is_oper_up())
+            .map(|interface| Interface {
+                ip: interface.ip(),
+                point_to_point: interface.is_p2p(),
+                name: interface.name,
+            })
+            .filter(|interface| self.accepts(interface))
+            .collect();
+
+        Ok(interfaces)
+    }
+}
+
+fn is_link_local(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_link_local(),
+        IpAddr::V6(ip) => ip.segments()[0] & 0xFFC0 == 0xFE80,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn interface(name: &str, ip: &str) -> Interface {
+        Interface {
+            name: name.into(),
+            ip: ip.parse().unwrap(),
+            point_to_point: false,
+        }
+    }
+
+    #[test]
+    fn default_policy() {
+        let policy = InterfacePolicy::default();
+
+        assert!(policy.accepts(&interface("eth0", "192.168.0.10")));
+        assert!(policy.accepts(&interface("eth0", "2001:db8::1")));
+        assert!(!policy.accepts(&interface("lo", "127.0.0.1")));
+        assert!(!policy.accepts(&interface("eth0", "169.254.10.1")));
+        assert!(!policy.accepts(&interface("eth0", "fe80::1")));
+        assert!(!policy.accepts(&interface("tun0", "10.8.0.2")));
+        assert!(!policy.accepts(&interface("wg0", "10.9.0.2")));
+        assert!(!policy.accepts(&Interface {
+            point_to_point: true,
+            ..interface("vpn", "10.10.0.2")
+        }));
+    }
+
+    #[test]
+    fn allow_and_deny() {
+        let policy = InterfacePolicy {
+            allow: vec![
+                InterfaceFilter::Name("eth*".into()),
+                InterfaceFilter::Network {
+                    ip: "10.0.0.0".parse().unwrap(),
+                    prefix_len: 8,
+                },
+            ],
+            deny: vec![
+                InterfaceFilter::Name("eth1".into()),
+                InterfaceFilter::Network {
+                    ip: "10.1.0.0".parse().unwrap(),
+                    prefix_len: 16,
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert!(policy.accepts(&interface("eth0", "192.168.0.10")));
+        assert!(!policy.accepts(&interface("eth1", "192.168.1.10")));
+        assert!(policy.accepts(&interface("wlan0", "10.0.0.5")));
+        assert!(!policy.accepts(&interface("wlan0", "10.1.0.5")));
+        assert!(!policy.accepts(&interface("wlan0", "192.168.2.10")));
+
+        assert!(policy.accepts_ip("10.0.0.5".parse().unwrap()));
+        assert!(!policy.accepts_ip("10.1.0.5".parse().unwrap()));
+
+        let policy = InterfacePolicy {
+            allow: vec![InterfaceFilter::Network {
+                ip: "10.0.0.0".parse().unwrap(),
+                prefix_len: 8,
+            }],
+            ..Default::default()
+        };
+
+        assert!(policy.accepts_ip("10.0.0.5".parse().unwrap()));
+        assert!(!policy.accepts_ip("192.168.2.10".parse().unwrap()));
+        assert!(!policy.accepts_ip("127.0.0.1".parse().unwrap()));
+    }
+}
@@ -0,0 +1,3751 @@
+//! Sans-IO ICE agent ([RFC8445](https://www.rfc-editor.org/rfc/rfc8445.html))
+//!
+//! The [`IceAgent`] does not own any sockets or timers. Received STUN messages are passed to it using
+//! [`IceAgent::handle_packet`], packets it wants to send are taken using [`IceAgent::poll_transmit`]
+//! and time is driven using [`IceAgent::poll_timeout`] and [`IceAgent::handle_timeout`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use ezk_ice::{IceAgent, IceCredentials, IceEvent, Protocol, ReceivedPacket};
+//! use std::net::UdpSocket;
+//! use std::time::{Duration, Instant};
+//!
+//! let socket = UdpSocket::bind("0.0.0.0:5000").unwrap();
+//!
+//! let mut agent = IceAgent::new(IceCredentials::random(), true);
+//! agent.add_host_addr(socket.local_addr().unwrap());
+//! agent.add_stun_server("142.250.82.127:19302".parse().unwrap());
+//!
+//! // exchange local credentials & candidates with the peer using some kind of signaling
+//! // agent.set_remote_credentials(..);
+//! // agent.add_remote_candidate(..);
+//!
+//! let mut buf = vec![0u8; 65535];
+//!
+//! loop {
+//!     let now = Instant::now();
+//!
+//!     agent.handle_timeout(now);
+//!
+//!     while let Some(transmit) = agent.poll_transmit() {
+//!         socket.send_to(&transmit.data, transmit.destination).unwrap();
+//!     }
+//!
+//!     while let Some(event) = agent.poll_event() {
+//!         if let IceEvent::SelectedPairChanged { local, remote, .. } = event {
+//!             println!("send media from {local} to {remote}");
+//!         }
+//!     }
+//!
+//!     let timeout = agent
+//!         .poll_timeout(now)
+//!         .map(|at| at.saturating_duration_since(now))
+//!         .unwrap_or(Duration::from_millis(100));
+//!     socket.set_read_timeout(Some(timeout.max(Duration::from_millis(1)))).unwrap();
+//!
+//!     if let Ok((len, source)) = socket.recv_from(&mut buf) {
+//!         agent.handle_packet(Instant::now(), ReceivedPacket {
+//!             protocol: Protocol::Udp,
+//!             data: buf[..len].to_vec(),
+//!             source,
+//!             destination: socket.local_addr().unwrap(),
+//!         });
+//!     }
+//! }
+//! ```
+
+use events::EventQueue;
+use mdns::MdnsMessage;
+use priority::{Foundations, TypePreferences};
+use rand::Rng;
+use slotmap::{new_key_type, SlotMap};
+use stats::PairCounters;
+use std::cmp::{min, Reverse};
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use stun::StunTransaction;
+use stun_types::attributes::{ErrorCode, MappedAddress, XorMappedAddress};
+use stun_types::header::{Class, Method};
+use stun_types::parse::ParsedMessage;
+use stun_types::transaction_id;
+
+mod events;
+pub mod framing;
+mod interfaces;
+mod mdns;
+mod nomination;
+pub mod priority;
+#[cfg(feature = "sdp")]
+pub mod sdp;
+mod stats;
+mod stun;
+
+pub use interfaces::{Interface, InterfaceFilter, InterfacePolicy};
+pub use mdns::{MDNS_V4, MDNS_V6};
+pub use nomination::{
+    NominateFirstValid, Nomination, NominationPolicy, ValidPair, WaitForBetterPair,
+};
+pub use stats::{CandidatePairStats, IceAgentStats};
+pub use stun::{StunTimeouts, Transmit};
+
+/// Default pacing interval of outgoing STUN requests
+const DEFAULT_TA: Duration = Duration::from_millis(50);
+
+/// Default time to wait for connectivity before giving up
+/// ([RFC8863](https://datatracker.ietf.org/doc/html/rfc8863#section-4))
+const DEFAULT_FAILURE_TIMEOUT: Duration = Duration::from_millis(39500);
+
+/// Maximum amount of candidate pairs in the check list
+const MAX_PAIRS: usize = 100;
+
+/// Interval of consent checks on the selected pair
+const CONSENT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Consent is lost when no consent check succeeded during this duration
+const CONSENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default interval of keepalives on the selected pairs ([RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-11))
+const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Component ID of candidates added without specifying a component (RTP)
+const DEFAULT_COMPONENT: u8 = 1;
+
+new_key_type! {
+    struct LocalCandidateId;
+    struct RemoteCandidateId;
+}
+
+/// ICE username fragment and password
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IceCredentials {
+    pub ufrag: String,
+    pub pwd: String,
+}
+
+impl IceCredentials {
+    /// Generate new random credentials
+    pub fn random() -> Self {
+        Self {
+            ufrag: random_ice_string(8),
+            pwd: random_ice_string(32),
+        }
+    }
+}
+
+fn random_ice_string(len: usize) -> String {
+    const ICE_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut rng = rand::thread_rng();
+
+    (0..len)
+        .map(|_| ICE_CHARS[rng.gen_range(0..ICE_CHARS.len())] as char)
+        .collect()
+}
+
+/// Type of a candidate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CandidateKind {
+    Host,
+    ServerReflexive,
+    PeerReflexive,
+    Relayed,
+}
+
+/// Preference between IPv4 and IPv6 candidates
+///
+/// When preferring a family, the local preferences of candidates of both families are interleaved
+/// ([RFC8421](https://datatracker.ietf.org/doc/html/rfc8421#section-4)). Pairs of the preferred
+/// family are checked first and selected if they work, while pairs of the other family are checked
+/// right after, so a broken network of the preferred family does not delay connectivity.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AddressFamilyPolicy {
+    #[default]
+    PreferIpv6,
+    PreferIpv4,
+    /// Ignore all IPv6 local candidates
+    Ipv4Only,
+    /// Ignore all IPv4 local candidates
+    Ipv6Only,
+}
+
+impl AddressFamilyPolicy {
+    fn allows(self, ip: IpAddr) -> bool {
+        match self {
+            AddressFamilyPolicy::PreferIpv6 | AddressFamilyPolicy::PreferIpv4 => true,
+            AddressFamilyPolicy::Ipv4Only => ip.is_ipv4(),
+            AddressFamilyPolicy::Ipv6Only => ip.is_ipv6(),
+        }
+    }
+
+    /// Local preference of the `index`th candidate of the address' family
+    fn local_preference(self, max: u16, addr: SocketAddr, index: u16) -> u16 {
+        let preferred = match self {
+            AddressFamilyPolicy::PreferIpv4 => addr.is_ipv4(),
+            _ => addr.is_ipv6(),
+        };
+
+        max.saturating_sub(index.saturating_mul(2) + u16::from(!preferred))
+    }
+}
+
+/// Transport protocol of a candidate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Udp,
+    Tcp,
+}
+
+/// Type of TCP candidates ([RFC6544](https://datatracker.ietf.org/doc/html/rfc6544#section-4.5))
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TcpType {
+    /// Opens outgoing connections, the port is always 9
+    Active,
+    /// Accepts incoming connections
+    Passive,
+    /// Simultaneous open
+    So,
+}
+
+impl TcpType {
+    /// Direction preference for host candidates from [RFC6544](https://datatracker.ietf.org/doc/html/rfc6544#section-4.2)
+    fn direction_preference(self) -> u16 {
+        match self {
+            TcpType::Active => 6,
+            TcpType::Passive => 4,
+            TcpType::So => 2,
+        }
+    }
+
+    /// Returns if a local candidate of this type can be paired with a remote candidate of `remote` type
+    fn is_compatible(self, remote: TcpType) -> bool {
+        matches!(
+            (self, remote),
+            (TcpType::Active, TcpType::Passive)
+                | (TcpType::Passive, TcpType::Active)
+                | (TcpType::So, TcpType::So)
+        )
+    }
+}
+
+/// Candidate as it is exchanged with the peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub foundation: String,
+    /// Component ID, 1 for RTP and 2 for RTCP when not multiplexed
+    pub component: u8,
+    pub priority: u32,
+    pub kind: CandidateKind,
+    pub addr: SocketAddr,
+
+    /// Type of TCP candidates, `None` for UDP candidates
+    pub tcp_type: Option<TcpType>,
+
+    /// Base address of server reflexive candidates
+    pub related_addr: Option<SocketAddr>,
+
+    /// mDNS name (`<uuid>.local`) which is signaled instead of the IP address of `addr`.
+    ///
+    /// For remote candidates the IP address of `addr` is ignored and resolved using mDNS.
+    pub mdns_name: Option<String>,
+}
+
+impl Candidate {
+    pub fn protocol(&self) -> Protocol {
+        if self.tcp_type.is_some() {
+            Protocol::Tcp
+        } else {
+            Protocol::Udp
+        }
+    }
+}
+
+struct LocalCandidate {
+    candidate: Candidate,
+
+    /// Address of the socket the candidate sends from
+    base: SocketAddr,
+}
+
+/// State of the candidate gathering
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IceGatheringState {
+    New,
+    Gathering,
+    /// All local candidates have been gathered, the peer should be sent an end-of-candidates
+    /// indication (`a=end-of-candidates`)
+    Complete,
+}
+
+/// State of the connectivity of the agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IceConnectionState {
+    /// Checks have not been started yet
+    New,
+    /// Connectivity checks are performed
+    Checking,
+    /// A candidate pair has been selected for every component
+    Connected,
+    /// All candidate pairs failed
+    Failed,
+}
+
+/// Reason why no candidate could be gathered from a STUN server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StunServerError {
+    /// The server did not respond
+    Timeout,
+    /// The server responded with the given error code
+    ErrorResponse(Option<u32>),
+    /// The response did not contain a mapped address
+    NoMappedAddress,
+}
+
+/// Events emitted by the agent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IceEvent {
+    GatheringStateChanged {
+        old: IceGatheringState,
+        new: IceGatheringState,
+    },
+    ConnectionStateChanged {
+        old: IceConnectionState,
+        new: IceConnectionState,
+    },
+    /// A new local candidate has been gathered and should be signaled to the peer (trickle ICE)
+    NewLocalCandidate { candidate: Candidate },
+    /// All local candidates have been gathered, the peer should be signaled the end of candidates
+    EndOfCandidates,
+    /// The state of a candidate pair changed. Intermediate states between two calls to the agent
+    /// may be skipped.
+    PairStateChanged {
+        component: u8,
+        local: SocketAddr,
+        remote: SocketAddr,
+        old: PairState,
+        new: PairState,
+    },
+    /// A candidate pair has been selected for the component. Data of the component must be sent
+    /// from the `local` address to `remote`.
+    SelectedPairChanged {
+        component: u8,
+        local: SocketAddr,
+        remote: SocketAddr,
+    },
+    /// A pair has been selected for every component
+    NominationCompleted,
+    /// The relayed candidate is not used by the selected pair and its allocation can be released
+    RelayedCandidateUnused { relayed: SocketAddr },
+    /// Gathering from a STUN server using the `local` host candidate failed. This does not fail
+    /// the gathering, which continues with the remaining servers.
+    StunServerFailed {
+        server: SocketAddr,
+        local: SocketAddr,
+        error: StunServerError,
+    },
+    /// The peer did not respond to consent checks on the selected pair, which has been removed.
+    /// Data must no longer be sent from `local` to `remote`.
+    ///
+    /// All pairs of the component are failed, other components keep their selected pairs. The
+    /// connection state becomes [`IceConnectionState::Failed`] and ICE must be restarted.
+    ConsentExpired {
+        component: u8,
+        local: SocketAddr,
+        remote: SocketAddr,
+    },
+}
+
+/// Packet received on one of the agent's local addresses
+#[derive(Debug, Clone)]
+pub struct ReceivedPacket {
+    /// Transport protocol the packet was received with, TCP packets must already be unframed
+    pub protocol: Protocol,
+    pub data: Vec<u8>,
+    /// Address the packet was received from
+    pub source: SocketAddr,
+    /// Local address the packet was received on
+    pub destination: SocketAddr,
+}
+
+struct StunServerBinding {
+    server: SocketAddr,
+    local: LocalCandidateId,
+    state: StunServerBindingState,
+}
+
+enum StunServerBindingState {
+    Waiting,
+    InProgress(Box<StunTransaction>),
+    Done,
+}
+
+/// State of a candidate pair ([RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-6.1.2.6))
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairState {
+    /// Waiting for a check of a pair with the same foundation to succeed
+    Frozen,
+    /// Check will be sent when the pair is next in line
+    Waiting,
+    /// Check has been sent, waiting for a response
+    InProgress,
+    /// Check produced a successful response
+    Succeeded,
+    /// Check failed or the pair was pruned
+    Failed,
+}
+
+struct CandidatePair {
+    local: LocalCandidateId,
+    remote: RemoteCandidateId,
+    priority: u64,
+    state: PairState,
+    /// State last reported using [`IceEvent::PairStateChanged`]
+    reported_state: PairState,
+    transaction: Option<StunTransaction>,
+
+    /// Checks on this pair carry the USE-CANDIDATE attribute (controlling)
+    nominate: bool,
+    /// Received a check with USE-CANDIDATE for this pair (controlled)
+    received_use_candidate: bool,
+    nominated: bool,
+
+    counters: PairCounters,
+}
+
+/// Consent freshness of a selected pair ([RFC7675](https://datatracker.ietf.org/doc/html/rfc7675))
+struct Consent {
+    local: LocalCandidateId,
+    remote: RemoteCandidateId,
+    last_response: Instant,
+    next_check: Instant,
+    transaction: Option<StunTransaction>,
+}
+
+/// Keepalive timer of a selected pair
+struct Keepalive {
+    local: LocalCandidateId,
+    remote: RemoteCandidateId,
+    next: Instant,
+    /// Data was sent on the pair since the last keepalive was due
+    data_sent: bool,
+}
+
+/// Pending mDNS resolution of remote candidates
+struct MdnsQuery {
+    name: String,
+    candidates: Vec<Candidate>,
+    transmissions: u32,
+    timeout_at: Option<Instant>,
+}
+
+/// Binding request received before the check list was formed
+struct EarlyCheck {
+    local: LocalCandidateId,
+    remote: RemoteCandidateId,
+    use_candidate: bool,
+}
+
+/// Sans-IO ICE agent handling a single media stream with one or more components
+pub struct IceAgent {
+    local_credentials: IceCredentials,
+    remote_credentials: Option<IceCredentials>,
+
+    is_controlling: bool,
+    tie_breaker: u64,
+
+    local_candidates: SlotMap<LocalCandidateId, LocalCandidate>,
+    remote_candidates: SlotMap<RemoteCandidateId, Candidate>,
+    foundations: Foundations,
+    type_preferences: TypePreferences,
+    local_preferences: Vec<(IpAddr, u16)>,
+
+    stun_server: Vec<SocketAddr>,
+    stun_server_bindings: Vec<StunServerBinding>,
+
+    interface_policy: Option<InterfacePolicy>,
+    address_family: AddressFamilyPolicy,
+
+    mdns: bool,
+    mdns_queries: Vec<MdnsQuery>,
+
+    pairs: Vec<CandidatePair>,
+    triggered_check_queue: VecDeque<(LocalCandidateId, RemoteCandidateId)>,
+    early_checks: Vec<EarlyCheck>,
+    /// Time the check list was formed
+    checks_started_at: Option<Instant>,
+
+    ta: Duration,
+    stun_timeouts: StunTimeouts,
+    failure_timeout: Duration,
+    /// Selected pair of each component
+    selected_pairs: Vec<(LocalCandidateId, RemoteCandidateId)>,
+
+    nomination: Nomination,
+    nomination_policy: Box<dyn NominationPolicy>,
+    /// Time the first pair became valid, while waiting for the nomination policy
+    first_valid_at: Option<Instant>,
+
+    /// Selected pairs (component, local, remote) before an ICE restart, which are used until the
+    /// new checks select a pair for the component
+    previous_selected_pairs: Vec<(u8, SocketAddr, SocketAddr)>,
+
+    /// Relayed candidates already reported using [`IceEvent::RelayedCandidateUnused`]
+    released_relayed: Vec<SocketAddr>,
+    /// Relayed candidates removed by an ICE restart which are still used by a previously
+    /// selected pair (component, relayed), released once the component selected a new pair
+    pending_release: Vec<(u8, SocketAddr)>,
+
+    consents: Vec<Consent>,
+    consent_expired: bool,
+
+    keepalive_interval: Option<Duration>,
+    keepalives: Vec<Keepalive>,
+
+    /// The peer will not trickle any more candidates
+    remote_end_of_candidates: bool,
+
+    gathering_state: IceGatheringState,
+    connection_state: IceConnectionState,
+
+    last_ta_trigger: Option<Instant>,
+    transmits: VecDeque<Transmit>,
+    events: EventQueue,
+}
+
+impl IceAgent {
+    /// Create a new agent with the given local credentials.
+    ///
+    /// `is_controlling` should be true for the agent which generated the offer.
+    pub fn new(local_credentials: IceCredentials, is_controlling: bool) -> Self {
+        let events = EventQueue::new(&local_credentials.ufrag, is_controlling);
+
+        Self {
+            local_credentials,
+            remote_credentials: None,
+            is_controlling,
+            tie_breaker: rand::random(),
+            local_candidates: SlotMap::with_key(),
+            remote_candidates: SlotMap::with_key(),
+            foundations: Foundations::new(),
+            type_preferences: TypePreferences::default(),
+            local_preferences: vec![],
+            stun_server: vec![],
+            stun_server_bindings: vec![],
+            interface_policy: None,
+            address_family: AddressFamilyPolicy::default(),
+            mdns: false,
+            mdns_queries: vec![],
+            pairs: vec![],
+            triggered_check_queue: VecDeque::new(),
+            early_checks: vec![],
+            checks_started_at: None,
+            ta: DEFAULT_TA,
+            stun_timeouts: StunTimeouts::default(),
+            failure_timeout: DEFAULT_FAILURE_TIMEOUT,
+            selected_pairs: vec![],
+            nomination: Nomination::Regular,
+            nomination_policy: Box::new(NominateFirstValid),
+            first_valid_at: None,
+            previous_selected_pairs: vec![],
+            released_relayed: vec![],
+            pending_release: vec![],
+            consents: vec![],
+            consent_expired: false,
+            keepalive_interval: Some(DEFAULT_KEEPALIVE_INTERVAL),
+            keepalives: vec![],
+            remote_end_of_candidates: false,
+            gathering_state: IceGatheringState::New,
+            connection_state: IceConnectionState::New,
+            last_ta_trigger: None,
+            transmits: VecDeque::new(),
+            events,
+        }
+    }
+
+    pub fn local_credentials(&self) -> &IceCredentials {
+        &self.local_credentials
+    }
+
+    pub fn remote_credentials(&self) -> Option<&IceCredentials> {
+        self.remote_credentials.as_ref()
+    }
+
+    pub fn is_controlling(&self) -> bool {
+        self.is_controlling
+    }
+
+    pub fn gathering_state(&self) -> IceGatheringState {
+        self.gathering_state
+    }
+
+    pub fn connection_state(&self) -> IceConnectionState {
+        self.connection_state
+    }
+
+    /// Returns the local and remote address of the selected candidate pair of the first component
+    ///
+    /// During an ICE restart this is the pair selected before the restart, until the new checks
+    /// selected a pair.
+    pub fn selected_pair(&self) -> Option<(SocketAddr, SocketAddr)> {
+        self.selected_pair_for(DEFAULT_COMPONENT)
+    }
+
+    /// Returns the local and remote address of the selected candidate pair of the given component
+    pub fn selected_pair_for(&self, component: u8) -> Option<(SocketAddr, SocketAddr)> {
+        if let Some((local, remote)) = self.selected_pair_ids(component) {
+            return Some((
+                self.local_candidates[local].base,
+                self.remote_candidates[remote].addr,
+            ));
+        }
+
+        self.previous_selected_pairs
+            .iter()
+            .find_map(|&(c, local, remote)| (c == component).then_some((local, remote)))
+    }
+
+    fn selected_pair_ids(&self, component: u8) -> Option<(LocalCandidateId, RemoteCandidateId)> {
+        self.selected_pairs
+            .iter()
+            .find(|(local, _)| self.local_candidates[*local].candidate.component == component)
+            .copied()
+    }
+
+    /// Amount of components, determined by the highest component ID of the local candidates
+    fn components(&self) -> u8 {
+        self.local_candidates
+            .values()
+            .map(|c| c.candidate.component)
+            .max()
+            .unwrap_or(DEFAULT_COMPONENT)
+    }
+
+    fn all_components_selected(&self) -> bool {
+        (1..=self.components()).all(|component| self.selected_pair_ids(component).is_some())
+    }
+
+    /// Returns all gathered local candidates
+    ///
+    /// Peer reflexive candidates learned from connectivity checks are not included.
+    pub fn local_candidates(&self) -> impl Iterator<Item = &Candidate> + '_ {
+        self.local_candidates
+            .values()
+            .map(|c| &c.candidate)
+            .filter(|c| c.kind != CandidateKind::PeerReflexive)
+    }
+
+    /// Set the type preferences used to compute the priority of local candidates gathered afterwards
+    pub fn set_type_preferences(&mut self, type_preferences: TypePreferences) {
+        self.type_preferences = type_preferences;
+    }
+
+    /// Set the local preference of host candidates with the given IP which are added afterwards and
+    /// of candidates derived from them. Defaults to preferring candidates in the order they are added.
+    pub fn set_local_preference(&mut self, ip: IpAddr, preference: u16) {
+        self.local_preferences.retain(|(i, _)| *i != ip);
+        self.local_preferences.push((ip, preference));
+    }
+
+    /// Set how pairs are nominated when the agent is controlling, defaults to [`Nomination::Regular`]
+    pub fn set_nomination(&mut self, nomination: Nomination) {
+        self.nomination = nomination;
+    }
+
+    /// Set the policy which decides which pair is nominated when using [`Nomination::Regular`],
+    /// defaults to [`NominateFirstValid`]
+    pub fn set_nomination_policy(&mut self, policy: impl NominationPolicy + 'static) {
+        self.nomination_policy = Box::new(policy);
+    }
+
+    /// Obfuscate the IP addresses of host candidates using mDNS names. Must be called before any
+    /// host addresses are added.
+    ///
+    /// The agent answers mDNS queries for these names and resolves remote candidates with mDNS
+    /// names. mDNS packets are passed to [`IceAgent::handle_mdns_packet`], transmits to
+    /// [`MDNS_V4`] or [`MDNS_V6`] must be sent using the mDNS socket.
+    pub fn enable_mdns(&mut self) {
+        self.mdns = true;
+    }
+
+    /// Set the pacing interval of outgoing STUN requests (Ta), defaults to 50ms.
+    ///
+    /// Agents of servers handling many sessions can increase it to reduce the load of
+    /// connectivity checks.
+    pub fn set_ta(&mut self, ta: Duration) {
+        self.ta = ta;
+    }
+
+    /// Set the retransmission timers of STUN requests sent afterwards
+    pub fn set_stun_timeouts(&mut self, timeouts: StunTimeouts) {
+        self.stun_timeouts = timeouts;
+    }
+
+    /// Set how long the agent waits for connectivity after checks started before it fails, even
+    /// if there are no pairs to check or all checks failed, since new candidates may still be
+    /// trickled ([RFC8863](https://datatracker.ietf.org/doc/html/rfc8863)). Defaults to 39.5s.
+    pub fn set_failure_timeout(&mut self, timeout: Duration) {
+        self.failure_timeout = timeout;
+    }
+
+    /// Set the interval of STUN binding indications sent on the selected pairs to keep NAT bindings
+    /// alive, `None` disables keepalives. Defaults to 15s.
+    ///
+    /// A keepalive is skipped if data has been reported using [`IceAgent::add_bytes_sent`] on the
+    /// pair since the last keepalive was due. Keepalives are sent regardless of consent checks.
+    pub fn set_keepalive_interval(&mut self, interval: Option<Duration>) {
+        self.keepalive_interval = interval;
+        self.keepalives.clear();
+    }
+
+    /// Set which address family is preferred or used exclusively by local candidates added
+    /// afterwards, defaults to [`AddressFamilyPolicy::PreferIpv6`]
+    pub fn set_address_family_policy(&mut self, policy: AddressFamilyPolicy) {
+        self.address_family = policy;
+    }
+
+    /// Only gather host candidates from addresses accepted by the given policy.
+    ///
+    /// Addresses added afterwards using [`IceAgent::add_host_addr`] & [`IceAgent::add_host_tcp_addr`]
+    /// are checked using [`InterfacePolicy::accepts_ip`]. Use [`InterfacePolicy::enumerate`] to
+    /// find the addresses to bind sockets to.
+    pub fn set_interface_policy(&mut self, policy: InterfacePolicy) {
+        self.interface_policy = Some(policy);
+    }
+
+    /// Add a local address to gather candidates from. Must be the address of a socket which is
+    /// used to send and receive packets of this agent.
+    pub fn add_host_addr(&mut self, addr: SocketAddr) {
+        self.add_host(DEFAULT_COMPONENT, addr, None);
+    }
+
+    /// Add a local address of the given component, e.g. 2 for the socket used for RTCP when it is
+    /// not multiplexed with RTP. Pairs are only formed between candidates of the same component.
+    pub fn add_component_host_addr(&mut self, component: u8, addr: SocketAddr) {
+        if component == 0 {
+            log::warn!("ignoring host address {addr} with invalid component 0");
+            return;
+        }
+
+        self.add_host(component, addr, None);
+    }
+
+    /// Add a local TCP host candidate ([RFC6544](https://datatracker.ietf.org/doc/html/rfc6544)).
+    ///
+    /// - [`TcpType::Passive`]: `addr` is the address of a listening socket. Packets to send from
+    ///   it must be sent over the accepted connection from the transmit's destination.
+    /// - [`TcpType::Active`]: the port of `addr` is ignored. Packets to send from it must be sent
+    ///   over a connection from the address' IP to the transmit's destination, which is
+    ///   opened if necessary. Packets received on such connections are passed with the candidate's
+    ///   address (port 9) as destination.
+    /// - [`TcpType::So`]: `addr` is the address of the socket used for simultaneous open.
+    pub fn add_host_tcp_addr(&mut self, mut addr: SocketAddr, tcp_type: TcpType) {
+        if tcp_type == TcpType::Active {
+            addr.set_port(9);
+        }
+
+        self.add_host(DEFAULT_COMPONENT, addr, Some(tcp_type));
+    }
+
+    fn add_host(&mut self, component: u8, addr: SocketAddr, tcp_type: Option<TcpType>) {
+        if addr.ip().is_unspecified() {
+            return;
+        }
+
+        if let Some(policy) = &self.interface_policy {
+            if !policy.accepts_ip(addr.ip()) {
+                log::debug!("not using {addr}, it is rejected by the interface policy");
+                return;
+            }
+        }
+
+        if !self.address_family.allows(addr.ip()) {
+            log::debug!("not using {addr}, its address family is disabled");
+            return;
+        }
+
+        if self.local_candidates.values().any(|c| {
+            c.candidate.kind == CandidateKind::Host
+                && c.base == addr
+                && c.candidate.tcp_type == tcp_type
+        }) {
+            return;
+        }
+
+        let host_count = self
+            .local_candidates
+            .values()
+            .filter(|c| {
+                c.candidate.kind == CandidateKind::Host
+                    && c.candidate.component == component
+                    && c.candidate.tcp_type.is_some() == tcp_type.is_some()
+                    && c.base.is_ipv4() == addr.is_ipv4()
+            })
+            .count() as u16;
+
+        let configured = self
+            .local_preferences
+            .iter()
+            .find_map(|(ip, preference)| (*ip == addr.ip()).then_some(*preference));
+
+        let local_preference = match tcp_type {
+            None => configured.unwrap_or_else(|| {
+                self.address_family
+                    .local_preference(65535, addr, host_count)
+            }),
+            // https://datatracker.ietf.org/doc/html/rfc6544#section-4.2
+            Some(tcp_type) => {
+                let other_preference = configured
+                    .map(|preference| preference.min(8191))
+                    .unwrap_or_else(|| {
+                        self.address_family.local_preference(8191, addr, host_count)
+                    });
+
+                (tcp_type.direction_preference() << 13) + other_preference
+            }
+        };
+
+        let id = self.add_local_candidate(
+            component,
+            CandidateKind::Host,
+            tcp_type,
+            addr,
+            addr,
+            None,
+            None,
+            local_preference,
+        );
+
+        if self.checks_started_at.is_some() {
+            let remote_ids: Vec<RemoteCandidateId> = self.remote_candidates.keys().collect();
+
+            for remote in remote_ids {
+                self.add_trickled_pair(id, remote);
+            }
+        }
+
+        // STUN servers are only used over UDP
+        if tcp_type.is_some() {
+            return;
+        }
+
+        for &server in &self.stun_server {
+            if server.is_ipv4() == addr.is_ipv4() {
+                self.stun_server_bindings.push(StunServerBinding {
+                    server,
+                    local: id,
+                    state: StunServerBindingState::Waiting,
+                });
+            }
+        }
+    }
+
+    /// Add a relayed candidate allocated on a TURN server.
+    ///
+    /// `relayed` is the relayed transport address of the allocation and `mapped` the server
+    /// reflexive address reported by the TURN server. The allocation is managed by the caller:
+    /// transmits with `relayed` as source must be sent through the allocation to their
+    /// destination, creating permissions or channels as required. Packets received through the
+    /// allocation are passed with `relayed` as destination.
+    ///
+    /// Once a pair has been selected, [`IceEvent::RelayedCandidateUnused`] is emitted for all
+    /// relayed candidates not used by the selected pair, so their allocations can be released.
+    pub fn add_relayed_candidate(&mut self, relayed: SocketAddr, mapped: SocketAddr) {
+        self.add_component_relayed_candidate(DEFAULT_COMPONENT, relayed, mapped);
+    }
+
+    /// Add a relayed candidate of the given component, see [`IceAgent::add_relayed_candidate`]
+    pub fn add_component_relayed_candidate(
+        &mut self,
+        component: u8,
+        relayed: SocketAddr,
+        mapped: SocketAddr,
+    ) {
+        if component == 0 {
+            log::warn!("ignoring relayed candidate {relayed} with invalid component 0");
+            return;
+        }
+
+        if !self.address_family.allows(relayed.ip())
+            || self
+                .local_candidates
+                .values()
+                .any(|c| c.candidate.kind == CandidateKind::Relayed && c.base == relayed)
+        {
+            return;
+        }
+
+        // A previously released address may be reused by a new allocation
+        self.released_relayed.retain(|addr| *addr != relayed);
+
+        let relayed_count = self
+            .local_candidates
+            .values()
+            .filter(|c| {
+                c.candidate.kind == CandidateKind::Relayed
+                    && c.candidate.component == component
+                    && c.base.is_ipv4() == relayed.is_ipv4()
+            })
+            .count() as u16;
+
+        let id = self.add_local_candidate(
+            component,
+            CandidateKind::Relayed,
+            None,
+            relayed,
+            relayed,
+            Some(mapped),
+            Some(mapped.ip()),
+            self.address_family
+                .local_preference(65535, relayed, relayed_count),
+        );
+
+        if self.checks_started_at.is_some() {
+            let remote_ids: Vec<RemoteCandidateId> = self.remote_candidates.keys().collect();
+
+            for remote in remote_ids {
+                self.add_trickled_pair(id, remote);
+            }
+        }
+    }
+
+    /// Add a STUN server which is used to gather server reflexive candidates
+    pub fn add_stun_server(&mut self, server: SocketAddr) {
+        if self.stun_server.contains(&server) {
+            return;
+        }
+
+        self.stun_server.push(server);
+
+        for (id, candidate) in &self.local_candidates {
+            if candidate.candidate.kind == CandidateKind::Host
+                && candidate.candidate.tcp_type.is_none()
+                && candidate.base.is_ipv4() == server.is_ipv4()
+            {
+                self.stun_server_bindings.push(StunServerBinding {
+                    server,
+                    local: id,
+                    state: StunServerBindingState::Waiting,
+                });
+            }
+        }
+    }
+
+    pub fn set_remote_credentials(&mut self, credentials: IceCredentials) {
+        self.remote_credentials = Some(credentials);
+    }
+
+    /// Add a candidate received from the peer.
+    ///
+    /// Candidates can be added at any time, candidates added after the connectivity checks have
+    /// started are paired immediately ([RFC8838](https://datatracker.ietf.org/doc/html/rfc8838)).
+    ///
+    /// Candidates with an mDNS name are added once the name has been resolved.
+    ///
+    /// Candidates received after [`IceAgent::set_remote_end_of_candidates`] are ignored.
+    pub fn add_remote_candidate(&mut self, candidate: Candidate) {
+        if self.remote_end_of_candidates {
+            log::debug!(
+                "ignoring remote candidate {} after end-of-candidates",
+                candidate.addr
+            );
+            return;
+        }
+
+        if let Some(name) = candidate
+            .mdns_name
+            .as_deref()
+            .filter(|n| mdns::is_mdns_name(n))
+        {
+            match self.mdns_queries.iter_mut().find(|q| q.name == name) {
+                Some(query) => query.candidates.push(candidate),
+                None => self.mdns_queries.push(MdnsQuery {
+                    name: name.to_owned(),
+                    candidates: vec![candidate],
+                    transmissions: 0,
+                    timeout_at: None,
+                }),
+            }
+
+            return;
+        }
+
+        self.insert_remote_candidate(candidate);
+    }
+
+    /// The peer signaled that it will not send any more candidates (`a=end-of-candidates`,
+    /// [RFC8838](https://datatracker.ietf.org/doc/html/rfc8838#section-8.2)).
+    ///
+    /// The agent no longer waits for the failure timeout, but fails as soon as all candidate pairs
+    /// have failed and local gathering is complete. Reset by [`IceAgent::restart`].
+    pub fn set_remote_end_of_candidates(&mut self) {
+        self.remote_end_of_candidates = true;
+    }
+
+    fn insert_remote_candidate(&mut self, candidate: Candidate) {
+        if let Some(existing) = self
+            .remote_candidates
+            .values_mut()
+            .find(|c| c.addr == candidate.addr && c.protocol() == candidate.protocol())
+        {
+            // A peer reflexive candidate learned from a check is replaced by the signaled one
+            if existing.kind == CandidateKind::PeerReflexive {
+                *existing = candidate;
+                self.update_pair_priorities();
+            }
+
+            return;
+        }
+
+        let remote = self.remote_candidates.insert(candidate);
+
+        if self.checks_started_at.is_some() {
+            for local in self.pairable_local_candidates() {
+                self.add_trickled_pair(local, remote);
+            }
+        }
+    }
+
+    /// Restart ICE ([RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-9)).
+    ///
+    /// Generates new local credentials and discards all remote candidates and candidate pairs.
+    /// Server reflexive candidates are gathered again and all remaining local candidates are
+    /// emitted again as [`IceEvent::NewLocalCandidate`].
+    ///
+    /// The new local credentials must be signaled to the peer and the new remote credentials and
+    /// candidates must be set again. Until the new checks selected a pair, the previously selected
+    /// pair stays in use.
+    ///
+    /// Must also be called when the peer restarted ICE, which is detected by a change of the
+    /// remote credentials.
+    pub fn restart(&mut self) {
+        if !self.selected_pairs.is_empty() {
+            self.previous_selected_pairs = self
+                .selected_pairs
+                .iter()
+                .map(|&(local, remote)| {
+                    let local = &self.local_candidates[local];
+
+                    (
+                        local.candidate.component,
+                        local.base,
+                        self.remote_candidates[remote].addr,
+                    )
+                })
+                .collect();
+        }
+
+        self.local_credentials = IceCredentials::random();
+        self.events
+            .set_agent(&self.local_credentials.ufrag, self.is_controlling);
+        self.remote_credentials = None;
+
+        // Relayed candidates are removed, their allocations can be released unless they are
+        // still used by a previously selected pair
+        let relayed: Vec<(u8, SocketAddr)> = self
+            .local_candidates
+            .values()
+            .filter(|c| c.candidate.kind == CandidateKind::Relayed)
+            .map(|c| (c.candidate.component, c.base))
+            .collect();
+
+        for (component, relayed) in relayed {
+            if self
+                .previous_selected_pairs
+                .iter()
+                .any(|&(c, local, _)| c == component && local == relayed)
+            {
+                self.pending_release.push((component, relayed));
+            } else {
+                self.release_relayed(relayed);
+            }
+        }
+
+        self.local_candidates
+            .retain(|_, c| c.candidate.kind == CandidateKind::Host);
+        self.remote_candidates.clear();
+        self.mdns_queries.clear();
+
+        self.stun_server_bindings.clear();
+
+        for (id, candidate) in &self.local_candidates {
+            self.events.push(IceEvent::NewLocalCandidate {
+                candidate: candidate.candidate.clone(),
+            });
+
+            if candidate.candidate.tcp_type.is_some() {
+                continue;
+            }
+
+            for &server in &self.stun_server {
+                if server.is_ipv4() == candidate.base.is_ipv4() {
+                    self.stun_server_bindings.push(StunServerBinding {
+                        server,
+                        local: id,
+                        state: StunServerBindingState::Waiting,
+                    });
+                }
+            }
+        }
+
+        self.pairs.clear();
+        self.triggered_check_queue.clear();
+        self.early_checks.clear();
+        self.checks_started_at = None;
+        self.selected_pairs.clear();
+        self.first_valid_at = None;
+        self.consents.clear();
+        self.consent_expired = false;
+        self.keepalives.clear();
+        self.remote_end_of_candidates = false;
+
+        if self.gathering_state != IceGatheringState::New {
+            self.events.push(IceEvent::GatheringStateChanged {
+                old: self.gathering_state,
+                new: IceGatheringState::New,
+            });
+            self.gathering_state = IceGatheringState::New;
+        }
+    }
+
+    /// Take the next packet that must be sent
+    pub fn poll_transmit(&mut self) -> Option<Transmit> {
+        let transmit = self.transmits.pop_front()?;
+
+        if let Some(pair) = self.find_pair_mut(transmit.source, transmit.destination) {
+            pair.counters.bytes_sent += transmit.data.len() as u64;
+        }
+
+        Some(transmit)
+    }
+
+    /// Report bytes of a packet (e.g. media) sent from `local` to `remote` outside of the agent
+    pub fn add_bytes_sent(&mut self, local: SocketAddr, remote: SocketAddr, bytes: usize) {
+        let Some(pair) = self.find_pair_mut(local, remote) else {
+            return;
+        };
+
+        pair.counters.bytes_sent += bytes as u64;
+
+        let (local, remote) = (pair.local, pair.remote);
+
+        if let Some(keepalive) = self
+            .keepalives
+            .iter_mut()
+            .find(|k| k.local == local && k.remote == remote)
+        {
+            keepalive.data_sent = true;
+        }
+    }
+
+    /// Report bytes of a packet (e.g. media) received on `local` from `remote` outside of the agent.
+    ///
+    /// Packets passed to [`IceAgent::handle_packet`] are counted automatically.
+    pub fn add_bytes_received(&mut self, local: SocketAddr, remote: SocketAddr, bytes: usize) {
+        if let Some(pair) = self.find_pair_mut(local, remote) {
+            pair.counters.bytes_received += bytes as u64;
+        }
+    }
+
+    /// Returns a snapshot of the state and statistics of all candidate pairs
+    pub fn stats(&self) -> IceAgentStats {
+        let pairs = self
+            .pairs
+            .iter()
+            .map(|pair| {
+                let local = &self.local_candidates[pair.local];
+                let remote = &self.remote_candidates[pair.remote];
+
+                CandidatePairStats {
+                    component: local.candidate.component,
+                    protocol: local.candidate.protocol(),
+                    local: local.base,
+                    local_kind: local.candidate.kind,
+                    remote: remote.addr,
+                    remote_kind: remote.kind,
+                    priority: pair.priority,
+                    state: pair.state,
+                    nominated: pair.nominated,
+                    selected: self.selected_pairs.contains(&(pair.local, pair.remote)),
+                    rtt: pair.counters.rtt,
+                    last_request_sent: pair.counters.last_request_sent,
+                    last_response_received: pair.counters.last_response_received,
+                    requests_sent: pair.counters.requests_sent,
+                    responses_received: pair.counters.responses_received,
+                    keepalives_sent: pair.counters.keepalives_sent,
+                    keepalives_suppressed: pair.counters.keepalives_suppressed,
+                    bytes_sent: pair.counters.bytes_sent,
+                    bytes_received: pair.counters.bytes_received,
+                }
+            })
+            .collect();
+
+        IceAgentStats {
+            pairs,
+            selected_pair: self.selected_pair(),
+        }
+    }
+
+    /// Take the next event emitted by the agent
+    pub fn poll_event(&mut self) -> Option<IceEvent> {
+        self.events.pop()
+    }
+
+    /// Returns the point in time [`IceAgent::handle_timeout`] must be called next
+    ///
+    /// Returns `now` if there is work which is already due.
+    pub fn poll_timeout(&self, now: Instant) -> Option<Instant> {
+        let mut timeout: Option<Instant> = None;
+        let mut set = |at: Instant| {
+            timeout = Some(timeout.map_or(at, |timeout| min(timeout, at)));
+        };
+
+        for binding in &self.stun_server_bindings {
+            if let StunServerBindingState::InProgress(transaction) = &binding.state {
+                set(transaction.timeout_at());
+            }
+        }
+
+        for query in &self.mdns_queries {
+            set(query.timeout_at.unwrap_or(now));
+        }
+
+        if self.connection_state == IceConnectionState::Checking {
+            if let Some(deadline) = self.failure_deadline() {
+                set(deadline);
+            }
+        }
+
+        for consent in &self.consents {
+            set(consent.next_check);
+            set(consent.last_response + CONSENT_TIMEOUT);
+
+            if let Some(transaction) = &consent.transaction {
+                set(transaction.timeout_at());
+            }
+        }
+
+        for keepalive in &self.keepalives {
+            set(keepalive.next);
+        }
+
+        // consent checks and keepalives of newly selected pairs are scheduled on the next call to
+        // handle_timeout
+        if self.consents.len() != self.selected_pairs.len()
+            || (self.keepalive_interval.is_some()
+                && self.keepalives.len() != self.selected_pairs.len())
+        {
+            set(now);
+        }
+
+        for pair in &self.pairs {
+            if let Some(transaction) = &pair.transaction {
+                set(transaction.timeout_at());
+            }
+        }
+
+        // Ask the nomination policy again in the next Ta interval
+        if self.is_waiting_for_nomination() || self.has_paced_work() {
+            match self.last_ta_trigger {
+                Some(last_ta_trigger) => set(last_ta_trigger + self.ta),
+                None => set(now),
+            }
+        }
+
+        timeout
+    }
+
+    /// Drive timers of the agent, must be called at the time returned by [`IceAgent::poll_timeout`]
+    pub fn handle_timeout(&mut self, now: Instant) {
+        self.poll_transactions(now);
+        self.poll_mdns_queries(now);
+        self.poll_consent(now);
+        self.poll_keepalives(now);
+        self.poll_nomination(now);
+        self.poll_gathering_state();
+
+        if self.checks_started_at.is_none() && self.remote_credentials.is_some() {
+            self.start_checks(now);
+        }
+
+        if self
+            .last_ta_trigger
+            .is_none_or(|last_ta_trigger| now >= last_ta_trigger + self.ta)
+            && self.poll_paced_work(now)
+        {
+            self.last_ta_trigger = Some(now);
+        }
+
+        self.poll_pair_states();
+        self.poll_connection_state(now);
+    }
+
+    /// Pass a STUN message received on one of the agent's local addresses
+    pub fn handle_packet(&mut self, now: Instant, pkt: ReceivedPacket) {
+        self.add_bytes_received(pkt.destination, pkt.source, pkt.data.len());
+
+        let msg = match ParsedMessage::parse(pkt.data) {
+            Ok(msg) => msg,
+            Err(e) => {
+                log::debug!("failed to parse STUN message, {e}");
+                return;
+            }
+        };
+
+        if msg.method != Method::Binding {
+            log::debug!(
+                "ignoring STUN message with unexpected method {:?}",
+                msg.method
+            );
+            return;
+        }
+
+        match msg.class {
+            Class::Request => self.handle_request(msg, pkt.protocol, pkt.source, pkt.destination),
+            Class::Success | Class::Error => {
+                self.handle_response(now, msg, pkt.protocol, pkt.source, pkt.destination)
+            }
+            Class::Indication => {}
+        }
+
+        self.poll_pair_states();
+    }
+
+    /// Pass a packet received on the mDNS socket
+    pub fn handle_mdns_packet(&mut self, pkt: ReceivedPacket) {
+        let Some(msg) = MdnsMessage::parse(&pkt.data) else {
+            log::debug!("failed to parse mDNS message");
+            return;
+        };
+
+        let mut answered = vec![];
+
+        for candidate in self.local_candidates.values() {
+            let Some(name) = &candidate.candidate.mdns_name else {
+                continue;
+            };
+
+            // UDP and TCP candidates of the same IP share a name
+            if !msg.queries(name) || answered.contains(&name) {
+                continue;
+            }
+
+            answered.push(name);
+
+            let destination = if pkt.source.is_ipv4() {
+                MDNS_V4
+            } else {
+                MDNS_V6
+            };
+
+            if let Some(data) = mdns::make_response(name, candidate.base.ip()) {
+                self.transmits.push_back(Transmit {
+                    protocol: Protocol::Udp,
+                    source: pkt.destination,
+                    destination,
+                    data,
+                });
+            }
+        }
+
+        let mut resolved = vec![];
+
+        self.mdns_queries
+            .retain_mut(|query| match msg.answer(&query.name) {
+                Some(ip) => {
+                    for mut candidate in query.candidates.drain(..) {
+                        candidate.addr.set_ip(ip);
+                        resolved.push(candidate);
+                    }
+
+                    false
+                }
+                None => true,
+            });
+
+        for candidate in resolved {
+            self.insert_remote_candidate(candidate);
+        }
+    }
+
+    fn poll_mdns_queries(&mut self, now: Instant) {
+        // The address family of a name is unknown until it is resolved, query it using every
+        // family a local candidate could be paired with
+        let mut sources: Vec<SocketAddr> = [
+            IpAddr::from(Ipv4Addr::UNSPECIFIED),
+            IpAddr::from(Ipv6Addr::UNSPECIFIED),
+        ]
+        .into_iter()
+        .filter(|ip| {
+            self.local_candidates
+                .values()
+                .any(|c| c.base.is_ipv4() == ip.is_ipv4())
+        })
+        .map(|ip| SocketAddr::new(ip, 5353))
+        .collect();
+
+        if sources.is_empty() {
+            sources.push(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 5353));
+        }
+
+        self.mdns_queries.retain_mut(|query| {
+            if query.timeout_at.is_some_and(|timeout_at| now < timeout_at) {
+                return true;
+            }
+
+            if query.transmissions >= mdns::MAX_QUERIES {
+                log::debug!("failed to resolve mDNS name {}", query.name);
+                return false;
+            }
+
+            let Some(data) = mdns::make_query(&query.name) else {
+                log::debug!("invalid mDNS name {}", query.name);
+                return false;
+            };
+
+            for &source in &sources {
+                let destination = if source.is_ipv4() { MDNS_V4 } else { MDNS_V6 };
+
+                self.transmits.push_back(Transmit {
+                    protocol: Protocol::Udp,
+                    source,
+                    destination,
+                    data: data.clone(),
+                });
+            }
+
+            query.transmissions += 1;
+            query.timeout_at = Some(now + mdns::QUERY_INTERVAL);
+
+            true
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_local_candidate(
+        &mut self,
+        component: u8,
+        kind: CandidateKind,
+        tcp_type: Option<TcpType>,
+        addr: SocketAddr,
+        base: SocketAddr,
+        related_addr: Option<SocketAddr>,
+        server: Option<IpAddr>,
+        local_preference: u16,
+    ) -> LocalCandidateId {
+        let protocol = if tcp_type.is_some() {
+            Protocol::Tcp
+        } else {
+            Protocol::Udp
+        };
+
+        let foundation = self.foundations.get(kind, protocol, base.ip(), server);
+
+        // The related address is not revealed when host candidates are obfuscated
+        let related_addr = related_addr.filter(|_| !self.mdns);
+
+        let mdns_name = if kind == CandidateKind::Host && self.mdns {
+            let existing = self.local_candidates.values().find_map(|c| {
+                (c.base.ip() == base.ip())
+                    .then(|| c.candidate.mdns_name.clone())
+                    .flatten()
+            });
+
+            Some(existing.unwrap_or_else(mdns::random_name))
+        } else {
+            None
+        };
+
+        let candidate = Candidate {
+            foundation,
+            component,
+            priority: self
+                .type_preferences
+                .candidate_priority(kind, local_preference, component),
+            kind,
+            addr,
+            tcp_type,
+            related_addr,
+            mdns_name,
+        };
+
+        // Peer reflexive candidates are learned from checks and never signaled
+        if kind != CandidateKind::PeerReflexive {
+            self.events.push(IceEvent::NewLocalCandidate {
+                candidate: candidate.clone(),
+            });
+        }
+
+        self.local_candidates
+            .insert(LocalCandidate { candidate, base })
+    }
+
+    fn poll_transactions(&mut self, now: Instant) {
+        for binding in &mut self.stun_server_bindings {
+            if let StunServerBindingState::InProgress(transaction) = &mut binding.state {
+                if !transaction.poll(now, &mut self.transmits) {
+                    log::debug!("STUN server {} did not respond", binding.server);
+                    binding.state = StunServerBindingState::Done;
+
+                    self.events.push(IceEvent::StunServerFailed {
+                        server: binding.server,
+                        local: self.local_candidates[binding.local].base,
+                        error: StunServerError::Timeout,
+                    });
+                }
+            }
+        }
+
+        for pair in &mut self.pairs {
+            if let Some(transaction) = &mut pair.transaction {
+                if !transaction.poll(now, &mut self.transmits) {
+                    pair.transaction = None;
+                    pair.state = PairState::Failed;
+                }
+            }
+        }
+    }
+
+    fn poll_gathering_state(&mut self) {
+        let new = if self
+            .stun_server_bindings
+            .iter()
+            .all(|b| matches!(b.state, StunServerBindingState::Done))
+        {
+            IceGatheringState::Complete
+        } else {
+            IceGatheringState::Gathering
+        };
+
+        if self.gathering_state != new {
+            self.events.push(IceEvent::GatheringStateChanged {
+                old: self.gathering_state,
+                new,
+            });
+            self.gathering_state = new;
+
+            if new == IceGatheringState::Complete {
+                self.events.push(IceEvent::EndOfCandidates);
+            }
+        }
+    }
+
+    fn poll_connection_state(&mut self, now: Instant) {
+        // Patiently wait for connectivity, candidates may still be trickled
+        let all_failed = self
+            .failure_deadline()
+            .is_some_and(|deadline| now >= deadline)
+            && self.gathering_state == IceGatheringState::Complete
+            && self.pairs.iter().all(|p| p.state == PairState::Failed);
+
+        let new = if self.all_components_selected() {
+            IceConnectionState::Connected
+        } else if self.consent_expired {
+            IceConnectionState::Failed
+        } else if all_failed {
+            // The pairs used before a restart are given up once the new checks failed
+            self.previous_selected_pairs.clear();
+
+            IceConnectionState::Failed
+        } else if !self.previous_selected_pairs.is_empty() {
+            IceConnectionState::Connected
+        } else if self.checks_started_at.is_none() {
+            IceConnectionState::New
+        } else {
+            IceConnectionState::Checking
+        };
+
+        if self.connection_state != new {
+            self.events.push(IceEvent::ConnectionStateChanged {
+                old: self.connection_state,
+                new,
+            });
+            self.connection_state = new;
+        }
+    }
+
+    fn failure_deadline(&self) -> Option<Instant> {
+        let checks_started_at = self.checks_started_at?;
+
+        // Without the peer trickling more candidates there is nothing to wait for
+        if self.remote_end_of_candidates {
+            return Some(checks_started_at);
+        }
+
+        Some(checks_started_at + self.failure_timeout)
+    }
+
+    fn has_paced_work(&self) -> bool {
+        let waiting_binding = self
+            .stun_server_bindings
+            .iter()
+            .any(|b| matches!(b.state, StunServerBindingState::Waiting));
+
+        let waiting_pair = !self.all_components_selected()
+            && self
+                .pairs
+                .iter()
+                .any(|p| matches!(p.state, PairState::Waiting | PairState::Frozen));
+
+        // checks will be started on the next call to handle_timeout
+        let can_start_checks =
+            self.checks_started_at.is_none() && self.remote_credentials.is_some();
+
+        waiting_binding
+            || waiting_pair
+            || can_start_checks
+            || !self.triggered_check_queue.is_empty()
+    }
+
+    /// Send the next paced STUN request. Returns true if a request was sent.
+    fn poll_paced_work(&mut self, now: Instant) -> bool {
+        if let Some(binding) = self
+            .stun_server_bindings
+            .iter_mut()
+            .find(|b| matches!(b.state, StunServerBindingState::Waiting))
+        {
+            let tsx_id = transaction_id();
+
+            let transaction = StunTransaction::start(
+                now,
+                tsx_id,
+                Transmit {
+                    protocol: Protocol::Udp,
+                    source: self.local_candidates[binding.local].base,
+                    destination: binding.server,
+                    data: stun::make_stun_server_binding_request(tsx_id),
+                },
+                self.stun_timeouts,
+                &mut self.transmits,
+            );
+
+            binding.state = StunServerBindingState::InProgress(Box::new(transaction));
+
+            return true;
+        }
+
+        if self.checks_started_at.is_none() {
+            return false;
+        }
+
+        while let Some((local, remote)) = self.triggered_check_queue.pop_front() {
+            if let Some(index) = self.pair_index(local, remote) {
+                if self.pairs[index].transaction.is_none() {
+                    self.send_check(now, index);
+                    return true;
+                }
+            }
+        }
+
+        if self.all_components_selected() {
+            return false;
+        }
+
+        let next = self
+            .pairs
+            .iter()
+            .position(|p| p.state == PairState::Waiting)
+            .or_else(|| self.pairs.iter().position(|p| p.state == PairState::Frozen));
+
+        if let Some(index) = next {
+            self.send_check(now, index);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Local candidates which are used in candidate pairs
+    fn pairable_local_candidates(&self) -> Vec<LocalCandidateId> {
+        self.local_candidates
+            .iter()
+            // server reflexive candidates are replaced by their base, which is a host candidate.
+            // Passive TCP candidates do not send checks, their pairs are formed by triggered checks.
+            .filter(|(_, c)| {
+                matches!(
+                    c.candidate.kind,
+                    CandidateKind::Host | CandidateKind::Relayed
+                ) && c.candidate.tcp_type != Some(TcpType::Passive)
+            })
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    fn start_checks(&mut self, now: Instant) {
+        self.checks_started_at = Some(now);
+
+        let local_ids = self.pairable_local_candidates();
+
+        let remote_ids: Vec<RemoteCandidateId> = self.remote_candidates.keys().collect();
+
+        for &local in &local_ids {
+            for &remote in &remote_ids {
+                self.add_pair(local, remote);
+            }
+        }
+
+        self.sort_pairs();
+        self.pairs.truncate(MAX_PAIRS);
+
+        // Set the pair with the lowest component ID and highest priority of each foundation to
+        // waiting https://datatracker.ietf.org/doc/html/rfc8445#section-6.1.2.6
+        let mut first_of_foundation: Vec<((&str, &str), usize)> = vec![];
+
+        for index in 0..self.pairs.len() {
+            let foundation = self.pair_foundation(index);
+            let component = self.pair_component(index);
+
+            match first_of_foundation
+                .iter_mut()
+                .find(|(f, _)| *f == foundation)
+            {
+                Some((_, first)) => {
+                    if component < self.pair_component(*first) {
+                        *first = index;
+                    }
+                }
+                None => first_of_foundation.push((foundation, index)),
+            }
+        }
+
+        let waiting: Vec<usize> = first_of_foundation.into_iter().map(|(_, i)| i).collect();
+
+        for index in waiting {
+            self.pairs[index].state = PairState::Waiting;
+        }
+
+        for early_check in std::mem::take(&mut self.early_checks) {
+            self.triggered_check(
+                early_check.local,
+                early_check.remote,
+                early_check.use_candidate,
+            );
+        }
+    }
+
+    /// Add a pair after the checks have started, as described in
+    /// [RFC8838](https://datatracker.ietf.org/doc/html/rfc8838#section-11)
+    fn add_trickled_pair(&mut self, local: LocalCandidateId, remote: RemoteCandidateId) {
+        if self.pair_index(local, remote).is_some() {
+            return;
+        }
+
+        if self.pairs.len() >= MAX_PAIRS {
+            log::debug!("check list is full, ignoring new candidate pair");
+            return;
+        }
+
+        let Some(index) = self.add_pair(local, remote) else {
+            return;
+        };
+
+        let foundation = self.pair_foundation(index);
+
+        let same_foundation: Vec<PairState> = (0..self.pairs.len())
+            .filter(|&i| i != index && self.pair_foundation(i) == foundation)
+            .map(|i| self.pairs[i].state)
+            .collect();
+
+        // Unfreeze the pair if another pair with the same foundation succeeded, or if it is
+        // the first pair with this foundation
+        if same_foundation.is_empty() || same_foundation.contains(&PairState::Succeeded) {
+            self.pairs[index].state = PairState::Waiting;
+        }
+
+        self.sort_pairs();
+    }
+
+    fn pair_component(&self, index: usize) -> u8 {
+        self.local_candidates[self.pairs[index].local]
+            .candidate
+            .component
+    }
+
+    fn pair_foundation(&self, index: usize) -> (&str, &str) {
+        let pair = &self.pairs[index];
+
+        (
+            &self.local_candidates[pair.local].candidate.foundation,
+            &self.remote_candidates[pair.remote].foundation,
+        )
+    }
+
+    /// Add a new pair in the frozen state, returns the index of the pair
+    fn add_pair(&mut self, local: LocalCandidateId, remote: RemoteCandidateId) -> Option<usize> {
+        if let Some(index) = self.pair_index(local, remote) {
+            return Some(index);
+        }
+
+        let local_candidate = &self.local_candidates[local];
+        let remote_candidate = &self.remote_candidates[remote];
+
+        if local_candidate.candidate.component != remote_candidate.component
+            || local_candidate.base.is_ipv4() != remote_candidate.addr.is_ipv4()
+        {
+            return None;
+        }
+
+        match (
+            local_candidate.candidate.tcp_type,
+            remote_candidate.tcp_type,
+        ) {
+            (None, None) => {}
+            (Some(local), Some(remote)) if local.is_compatible(remote) => {}
+            _ => return None,
+        }
+
+        let priority = pair_priority(
+            &local_candidate.candidate,
+            remote_candidate,
+            self.is_controlling,
+        );
+
+        self.pairs.push(CandidatePair {
+            local,
+            remote,
+            priority,
+            state: PairState::Frozen,
+            reported_state: PairState::Frozen,
+            transaction: None,
+            nominate: false,
+            received_use_candidate: false,
+            nominated: false,
+            counters: PairCounters::default(),
+        });
+
+        Some(self.pairs.len() - 1)
+    }
+
+    fn pair_index(&self, local: LocalCandidateId, remote: RemoteCandidateId) -> Option<usize> {
+        self.pairs
+            .iter()
+            .position(|p| p.local == local && p.remote == remote)
+    }
+
+    fn sort_pairs(&mut self) {
+        self.pairs.sort_by_key(|p| Reverse(p.priority));
+    }
+
+    fn send_check(&mut self, now: Instant, index: usize) {
+        let Some(remote_credentials) = &self.remote_credentials else {
+            return;
+        };
+
+        let pair = &mut self.pairs[index];
+        let local = &self.local_candidates[pair.local];
+        let remote = &self.remote_candidates[pair.remote];
+
+        if self.is_controlling && self.nomination == Nomination::Aggressive {
+            pair.nominate = true;
+        }
+
+        // Priority of a peer reflexive candidate learned from this check
+        let priority = (local.candidate.priority & 0x00FF_FFFF)
+            | (self.type_preferences.get(CandidateKind::PeerReflexive) << 24);
+
+        let tsx_id = transaction_id();
+
+        let request = stun::make_binding_request(
+            tsx_id,
+            &self.local_credentials,
+            remote_credentials,
+            self.is_controlling,
+            self.tie_breaker,
+            priority,
+            pair.nominate,
+        );
+
+        pair.state = PairState::InProgress;
+        pair.counters.request_sent(now);
+        pair.transaction = Some(StunTransaction::start(
+            now,
+            tsx_id,
+            Transmit {
+                protocol: local.candidate.protocol(),
+                source: local.base,
+                destination: remote.addr,
+                data: request,
+            },
+            self.stun_timeouts,
+            &mut self.transmits,
+        ));
+    }
+
+    fn triggered_check(
+        &mut self,
+        local: LocalCandidateId,
+        remote: RemoteCandidateId,
+        use_candidate: bool,
+    ) {
+        let Some(index) = self.add_pair(local, remote) else {
+            return;
+        };
+
+        let pair = &mut self.pairs[index];
+        pair.received_use_candidate |= use_candidate;
+
+        match pair.state {
+            PairState::Succeeded => {
+                if pair.received_use_candidate && !self.is_controlling {
+                    pair.nominated = true;
+                    self.update_selected_pair();
+                }
+            }
+            PairState::InProgress => {
+                // the response to the check in progress will handle the nomination
+            }
+            PairState::Frozen | PairState::Waiting | PairState::Failed => {
+                pair.state = PairState::Waiting;
+                self.triggered_check_queue.push_back((local, remote));
+            }
+        }
+
+        self.sort_pairs();
+    }
+
+    fn handle_request(
+        &mut self,
+        mut msg: ParsedMessage,
+        protocol: Protocol,
+        source: SocketAddr,
+        destination: SocketAddr,
+    ) {
+        let Some(request) = stun::parse_binding_request(&mut msg, &self.local_credentials) else {
+            log::debug!("ignoring invalid binding request from {source}");
+            return;
+        };
+
+        // Detect role conflicts https://datatracker.ietf.org/doc/html/rfc8445#section-7.3.1.1
+        if self.is_controlling {
+            if let Some(tie_breaker) = request.controlling {
+                if self.tie_breaker >= tie_breaker {
+                    self.transmits.push_back(Transmit {
+                        protocol,
+                        source: destination,
+                        destination: source,
+                        data: stun::make_role_conflict_response(
+                            msg.tsx_id,
+                            &self.local_credentials,
+                        ),
+                    });
+                    return;
+                }
+
+                self.switch_role();
+            }
+        } else if let Some(tie_breaker) = request.controlled {
+            if self.tie_breaker >= tie_breaker {
+                self.switch_role();
+            } else {
+                self.transmits.push_back(Transmit {
+                    protocol,
+                    source: destination,
+                    destination: source,
+                    data: stun::make_role_conflict_response(msg.tsx_id, &self.local_credentials),
+                });
+                return;
+            }
+        }
+
+        let Some(local) = self.local_candidates.iter().find_map(|(id, c)| {
+            (matches!(
+                c.candidate.kind,
+                CandidateKind::Host | CandidateKind::Relayed
+            ) && c.candidate.protocol() == protocol
+                && c.base == destination)
+                .then_some(id)
+        }) else {
+            log::debug!("received binding request on unknown local address {destination}");
+            return;
+        };
+
+        self.transmits.push_back(Transmit {
+            protocol,
+            source: destination,
+            destination: source,
+            data: stun::make_success_response(msg.tsx_id, &self.local_credentials, source),
+        });
+
+        let remote = match self
+            .remote_candidates
+            .iter()
+            .find_map(|(id, c)| (c.addr == source && c.protocol() == protocol).then_some(id))
+        {
+            Some(remote) => remote,
+            None => {
+                // Learned a new peer reflexive candidate
+                let foundation = random_ice_string(8);
+
+                // The remote TCP type is the counterpart of the local one
+                let tcp_type = self.local_candidates[local]
+                    .candidate
+                    .tcp_type
+                    .map(|tcp_type| match tcp_type {
+                        TcpType::Active => TcpType::Passive,
+                        TcpType::Passive => TcpType::Active,
+                        TcpType::So => TcpType::So,
+                    });
+
+                self.remote_candidates.insert(Candidate {
+                    foundation,
+                    component: self.local_candidates[local].candidate.component,
+                    priority: request.priority,
+                    kind: CandidateKind::PeerReflexive,
+                    addr: source,
+                    tcp_type,
+                    related_addr: None,
+                    mdns_name: None,
+                })
+            }
+        };
+
+        if self.checks_started_at.is_some() {
+            self.triggered_check(local, remote, request.use_candidate);
+        } else {
+            self.early_checks.push(EarlyCheck {
+                local,
+                remote,
+                use_candidate: request.use_candidate,
+            });
+        }
+    }
+
+    fn handle_response(
+        &mut self,
+        now: Instant,
+        mut msg: ParsedMessage,
+        protocol: Protocol,
+        source: SocketAddr,
+        destination: SocketAddr,
+    ) {
+        if let Some(binding) = self.stun_server_bindings.iter_mut().find(
+            |b| matches!(&b.state, StunServerBindingState::InProgress(t) if t.tsx_id == msg.tsx_id),
+        ) {
+            binding.state = StunServerBindingState::Done;
+
+            let server = binding.server;
+            let local = binding.local;
+
+            self.handle_stun_server_response(msg, server, local);
+            return;
+        }
+
+        if let Some(index) = self.consents.iter().position(|c| {
+            c.transaction
+                .as_ref()
+                .is_some_and(|t| t.tsx_id == msg.tsx_id)
+        }) {
+            self.handle_consent_response(now, index, msg, source, destination);
+            return;
+        }
+
+        let Some(index) = self.pairs.iter().position(|p| {
+            p.transaction
+                .as_ref()
+                .is_some_and(|t| t.tsx_id == msg.tsx_id)
+        }) else {
+            log::debug!("received response with unknown transaction id");
+            return;
+        };
+
+        let Some(remote_credentials) = &self.remote_credentials else {
+            return;
+        };
+
+        if !stun::verify_integrity(&mut msg, &remote_credentials.pwd) {
+            log::debug!("ignoring response with invalid message integrity");
+            return;
+        }
+
+        let mapped = msg
+            .get_attr::<XorMappedAddress>()
+            .and_then(Result::ok)
+            .map(|addr| addr.0);
+
+        let pair = &mut self.pairs[index];
+        let transaction = pair.transaction.take();
+
+        let local = &self.local_candidates[pair.local];
+        let remote = &self.remote_candidates[pair.remote];
+
+        // Responses must be symmetric https://datatracker.ietf.org/doc/html/rfc8445#section-7.2.5.2.1
+        if protocol != local.candidate.protocol()
+            || source != remote.addr
+            || destination != local.base
+        {
+            pair.state = PairState::Failed;
+            return;
+        }
+
+        if msg.class == Class::Error {
+            let number = msg
+                .get_attr::<ErrorCode>()
+                .and_then(Result::ok)
+                .map(|e| e.number);
+
+            if number == Some(487) {
+                let (local, remote) = (pair.local, pair.remote);
+                pair.state = PairState::Waiting;
+
+                self.switch_role();
+                self.triggered_check_queue.push_back((local, remote));
+            } else {
+                pair.state = PairState::Failed;
+            }
+
+            return;
+        }
+
+        pair.state = PairState::Succeeded;
+
+        if let Some(transaction) = transaction {
+            pair.counters.response_received(now, transaction.sent_at());
+        }
+
+        if pair.nominate || (pair.received_use_candidate && !self.is_controlling) {
+            pair.nominated = true;
+        }
+
+        // Unfreeze all pairs with the same foundation
+        let foundation = (
+            local.candidate.foundation.clone(),
+            remote.foundation.clone(),
+        );
+
+        for pair in &mut self.pairs {
+            if pair.state == PairState::Frozen
+                && self.local_candidates[pair.local].candidate.foundation == foundation.0
+                && self.remote_candidates[pair.remote].foundation == foundation.1
+            {
+                pair.state = PairState::Waiting;
+            }
+        }
+
+        if let Some(mapped) = mapped {
+            self.add_valid_pair(index, mapped);
+        }
+
+        self.first_valid_at.get_or_insert(now);
+        self.poll_nomination(now);
+
+        self.update_selected_pair();
+    }
+
+    /// Construct the valid pair of the succeeded check at `index` from the mapped address of the
+    /// response, learning a local peer reflexive candidate if the address is unknown
+    /// ([RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-7.2.5.3.1))
+    fn add_valid_pair(&mut self, index: usize, mapped: SocketAddr) {
+        let pair = &self.pairs[index];
+        let checked = &self.local_candidates[pair.local];
+
+        let component = checked.candidate.component;
+        let protocol = checked.candidate.protocol();
+
+        // TCP candidates are not discovered, the mapped address is the ephemeral port of the
+        // connection
+        if protocol == Protocol::Tcp {
+            return;
+        }
+
+        let known = self.local_candidates.iter().find_map(|(id, c)| {
+            (c.candidate.component == component
+                && c.candidate.protocol() == protocol
+                && c.candidate.addr == mapped)
+                .then_some((id, c.candidate.kind))
+        });
+
+        let remote = pair.remote;
+
+        let local = match known {
+            // Peer reflexive candidate learned from a previous check
+            Some((id, CandidateKind::PeerReflexive)) if id != pair.local => id,
+            Some(_) => return,
+            None => {
+                let base = checked.base;
+                let local_preference = ((checked.candidate.priority >> 8) & 0xFFFF) as u16;
+
+                self.add_local_candidate(
+                    component,
+                    CandidateKind::PeerReflexive,
+                    None,
+                    mapped,
+                    base,
+                    Some(base),
+                    None,
+                    local_preference,
+                )
+            }
+        };
+
+        let Some(valid) = self.add_pair(local, remote) else {
+            return;
+        };
+
+        // The valid pair takes over the nomination of the checked pair
+        let checked = &mut self.pairs[index];
+        let (nominate, received_use_candidate, nominated) = (
+            checked.nominate,
+            checked.received_use_candidate,
+            checked.nominated,
+        );
+        checked.nominated = false;
+
+        let valid = &mut self.pairs[valid];
+        valid.state = PairState::Succeeded;
+        valid.nominate = nominate;
+        valid.received_use_candidate = received_use_candidate;
+        valid.nominated = nominated;
+
+        self.sort_pairs();
+    }
+
+    fn handle_consent_response(
+        &mut self,
+        now: Instant,
+        index: usize,
+        mut msg: ParsedMessage,
+        source: SocketAddr,
+        destination: SocketAddr,
+    ) {
+        let Some(remote_credentials) = &self.remote_credentials else {
+            return;
+        };
+
+        if !stun::verify_integrity(&mut msg, &remote_credentials.pwd) {
+            log::debug!("ignoring consent response with invalid message integrity");
+            return;
+        }
+
+        let consent = &mut self.consents[index];
+        let (local, remote) = (consent.local, consent.remote);
+        let transaction = consent.transaction.take();
+
+        if msg.class == Class::Success
+            && source == self.remote_candidates[remote].addr
+            && destination == self.local_candidates[local].base
+        {
+            consent.last_response = now;
+
+            if let (Some(pair), Some(transaction)) = (
+                self.pairs
+                    .iter_mut()
+                    .find(|p| p.local == local && p.remote == remote),
+                transaction,
+            ) {
+                pair.counters.response_received(now, transaction.sent_at());
+            }
+        }
+    }
+
+    /// Send consent checks on the selected pairs and remove them once consent expired
+    fn poll_consent(&mut self, now: Instant) {
+        let selected_pairs = &self.selected_pairs;
+
+        self.consents
+            .retain(|c| selected_pairs.contains(&(c.local, c.remote)));
+
+        for &(local, remote) in &self.selected_pairs {
+            if !self
+                .consents
+                .iter()
+                .any(|c| c.local == local && c.remote == remote)
+            {
+                self.consents.push(Consent {
+                    local,
+                    remote,
+                    last_response: now,
+                    next_check: now + consent_interval(),
+                    transaction: None,
+                });
+            }
+        }
+
+        let expired: Vec<(LocalCandidateId, RemoteCandidateId)> = self
+            .consents
+            .iter()
+            .filter(|c| now >= c.last_response + CONSENT_TIMEOUT)
+            .map(|c| (c.local, c.remote))
+            .collect();
+
+        for (local, remote) in expired {
+            let local_candidate = &self.local_candidates[local];
+            let component = local_candidate.candidate.component;
+            let local_addr = local_candidate.base;
+            let remote_addr = self.remote_candidates[remote].addr;
+
+            log::debug!(
+                "consent expired for pair {local_addr} -> {remote_addr} of component {component}"
+            );
+
+            // Only the component of the expired pair is torn down
+            let local_candidates = &self.local_candidates;
+
+            for pair in &mut self.pairs {
+                if local_candidates[pair.local].candidate.component == component {
+                    pair.state = PairState::Failed;
+                    pair.transaction = None;
+                }
+            }
+
+            self.consents
+                .retain(|c| !(c.local == local && c.remote == remote));
+            self.selected_pairs
+                .retain(|selected| *selected != (local, remote));
+            self.consent_expired = true;
+            self.events.push(IceEvent::ConsentExpired {
+                component,
+                local: local_addr,
+                remote: remote_addr,
+            });
+        }
+
+        let Some(remote_credentials) = &self.remote_credentials else {
+            return;
+        };
+
+        for consent in &mut self.consents {
+            if let Some(transaction) = &mut consent.transaction {
+                if !transaction.poll(now, &mut self.transmits) {
+                    consent.transaction = None;
+                }
+            }
+
+            if now < consent.next_check || consent.transaction.is_some() {
+                continue;
+            }
+
+            if let Some(pair) = self
+                .pairs
+                .iter_mut()
+                .find(|p| p.local == consent.local && p.remote == consent.remote)
+            {
+                pair.counters.request_sent(now);
+            }
+
+            let local = &self.local_candidates[consent.local];
+            let remote = &self.remote_candidates[consent.remote];
+
+            let tsx_id = transaction_id();
+
+            let request = stun::make_binding_request(
+                tsx_id,
+                &self.local_credentials,
+                remote_credentials,
+                self.is_controlling,
+                self.tie_breaker,
+                local.candidate.priority,
+                false,
+            );
+
+            consent.next_check = now + consent_interval();
+            consent.transaction = Some(StunTransaction::start(
+                now,
+                tsx_id,
+                Transmit {
+                    protocol: local.candidate.protocol(),
+                    source: local.base,
+                    destination: remote.addr,
+                    data: request,
+                },
+                self.stun_timeouts,
+                &mut self.transmits,
+            ));
+        }
+    }
+
+    /// Send binding indications on selected pairs which have not been used to send data
+    fn poll_keepalives(&mut self, now: Instant) {
+        let Some(interval) = self.keepalive_interval else {
+            self.keepalives.clear();
+            return;
+        };
+
+        let selected_pairs = &self.selected_pairs;
+
+        self.keepalives
+            .retain(|k| selected_pairs.contains(&(k.local, k.remote)));
+
+        for &(local, remote) in &self.selected_pairs {
+            if !self
+                .keepalives
+                .iter()
+                .any(|k| k.local == local && k.remote == remote)
+            {
+                self.keepalives.push(Keepalive {
+                    local,
+                    remote,
+                    next: now + interval,
+                    data_sent: false,
+                });
+            }
+        }
+
+        for keepalive in &mut self.keepalives {
+            if now < keepalive.next {
+                continue;
+            }
+
+            keepalive.next = now + interval;
+
+            let Some(pair) = self
+                .pairs
+                .iter_mut()
+                .find(|p| p.local == keepalive.local && p.remote == keepalive.remote)
+            else {
+                continue;
+            };
+
+            if std::mem::take(&mut keepalive.data_sent) {
+                pair.counters.keepalives_suppressed += 1;
+                continue;
+            }
+
+            pair.counters.keepalives_sent += 1;
+
+            let local = &self.local_candidates[keepalive.local];
+
+            self.transmits.push_back(Transmit {
+                protocol: local.candidate.protocol(),
+                source: local.base,
+                destination: self.remote_candidates[keepalive.remote].addr,
+                data: stun::make_binding_indication(transaction_id()),
+            });
+        }
+    }
+
+    fn handle_stun_server_response(
+        &mut self,
+        mut msg: ParsedMessage,
+        server: SocketAddr,
+        local: LocalCandidateId,
+    ) {
+        let base = self.local_candidates[local].base;
+
+        if msg.class == Class::Error {
+            log::debug!("STUN server {server} responded with an error");
+
+            let number = msg
+                .get_attr::<ErrorCode>()
+                .and_then(Result::ok)
+                .map(|e| e.number);
+
+            self.events.push(IceEvent::StunServerFailed {
+                server,
+                local: base,
+                error: StunServerError::ErrorResponse(number),
+            });
+            return;
+        }
+
+        let addr = if let Some(Ok(addr)) = msg.get_attr::<XorMappedAddress>() {
+            addr.0
+        } else if let Some(Ok(addr)) = msg.get_attr::<MappedAddress>() {
+            addr.0
+        } else {
+            log::debug!("STUN server {server} responded without a mapped address");
+
+            self.events.push(IceEvent::StunServerFailed {
+                server,
+                local: base,
+                error: StunServerError::NoMappedAddress,
+            });
+            return;
+        };
+
+        // Multiple servers report the same address for the same base if the NAT uses an
+        // endpoint-independent mapping, only the first one is used
+
+        let redundant = self
+            .local_candidates
+            .values()
+            .any(|c| c.candidate.addr == addr && c.base == base);
+
+        if redundant {
+            return;
+        }
+
+        let component = self.local_candidates[local].candidate.component;
+        let local_preference = (self.local_candidates[local].candidate.priority >> 8) as u16;
+
+        self.add_local_candidate(
+            component,
+            CandidateKind::ServerReflexive,
+            None,
+            addr,
+            base,
+            Some(base),
+            Some(server.ip()),
+            local_preference,
+        );
+    }
+
+    fn find_pair_mut(
+        &mut self,
+        local: SocketAddr,
+        remote: SocketAddr,
+    ) -> Option<&mut CandidatePair> {
+        self.pairs.iter_mut().find(|p| {
+            self.local_candidates[p.local].base == local
+                && self.remote_candidates[p.remote].addr == remote
+        })
+    }
+
+    fn is_waiting_for_nomination(&self) -> bool {
+        self.is_controlling
+            && self.nomination == Nomination::Regular
+            && self.first_valid_at.is_some()
+            && (1..=self.components()).any(|component| !self.has_nominate_pair(component))
+    }
+
+    fn has_nominate_pair(&self, component: u8) -> bool {
+        (0..self.pairs.len()).any(|i| self.pairs[i].nominate && self.pair_component(i) == component)
+    }
+
+    /// Nominate the valid pairs chosen by the nomination policy (regular nomination)
+    fn poll_nomination(&mut self, now: Instant) {
+        if !self.is_waiting_for_nomination() {
+            return;
+        }
+
+        let Some(first_valid_at) = self.first_valid_at else {
+            return;
+        };
+
+        for component in 1..=self.components() {
+            if !self.has_nominate_pair(component) {
+                self.poll_component_nomination(now - first_valid_at, component);
+            }
+        }
+    }
+
+    fn poll_component_nomination(&mut self, since_first_valid: Duration, component: u8) {
+        let of_component: Vec<usize> = (0..self.pairs.len())
+            .filter(|&i| self.pair_component(i) == component)
+            .collect();
+
+        let valid: Vec<usize> = of_component
+            .iter()
+            .copied()
+            .filter(|&i| self.pairs[i].state == PairState::Succeeded)
+            .collect();
+
+        if valid.is_empty() {
+            return;
+        }
+
+        let valid_pairs: Vec<ValidPair> = valid
+            .iter()
+            .map(|&i| {
+                let pair = &self.pairs[i];
+                let local = &self.local_candidates[pair.local];
+                let remote = &self.remote_candidates[pair.remote];
+
+                ValidPair {
+                    component,
+                    local: local.base,
+                    local_kind: local.candidate.kind,
+                    remote: remote.addr,
+                    remote_kind: remote.kind,
+                    priority: pair.priority,
+                }
+            })
+            .collect();
+
+        let checks_pending = of_component.iter().any(|&i| {
+            matches!(
+                self.pairs[i].state,
+                PairState::Frozen | PairState::Waiting | PairState::InProgress
+            )
+        });
+
+        let Some(index) =
+            self.nomination_policy
+                .nominate(&valid_pairs, since_first_valid, checks_pending)
+        else {
+            return;
+        };
+
+        let Some(&index) = valid.get(index) else {
+            log::warn!("nomination policy returned invalid index {index}");
+            return;
+        };
+
+        let pair = &mut self.pairs[index];
+
+        pair.nominate = true;
+        pair.state = PairState::Waiting;
+        self.triggered_check_queue
+            .push_front((pair.local, pair.remote));
+    }
+
+    fn update_selected_pair(&mut self) {
+        // Report the state changes which lead to the selection first
+        self.poll_pair_states();
+
+        let was_completed = self.all_components_selected();
+
+        for component in 1..=self.components() {
+            self.update_component_selected_pair(component);
+        }
+
+        if !was_completed && self.all_components_selected() {
+            self.events.push(IceEvent::NominationCompleted);
+        }
+    }
+
+    fn poll_pair_states(&mut self) {
+        for pair in &mut self.pairs {
+            if pair.state == pair.reported_state {
+                continue;
+            }
+
+            let local = &self.local_candidates[pair.local];
+
+            self.events.push(IceEvent::PairStateChanged {
+                component: local.candidate.component,
+                local: local.base,
+                remote: self.remote_candidates[pair.remote].addr,
+                old: pair.reported_state,
+                new: pair.state,
+            });
+
+            pair.reported_state = pair.state;
+        }
+    }
+
+    fn update_component_selected_pair(&mut self, component: u8) {
+        let Some(index) = (0..self.pairs.len())
+            .find(|&i| self.pairs[i].nominated && self.pair_component(i) == component)
+        else {
+            return;
+        };
+
+        let selected = (self.pairs[index].local, self.pairs[index].remote);
+
+        if self.selected_pair_ids(component) == Some(selected) {
+            return;
+        }
+
+        let local_candidates = &self.local_candidates;
+
+        self.selected_pairs
+            .retain(|(local, _)| local_candidates[*local].candidate.component != component);
+        self.selected_pairs.push(selected);
+        self.previous_selected_pairs
+            .retain(|(c, _, _)| *c != component);
+
+        let selected_base = self.local_candidates[selected.0].base;
+
+        // Stop pending checks of lower priority pairs
+        for pair in &mut self.pairs {
+            if local_candidates[pair.local].candidate.component == component
+                && matches!(pair.state, PairState::Frozen | PairState::Waiting)
+            {
+                pair.state = PairState::Failed;
+            }
+        }
+
+        self.events.push(IceEvent::SelectedPairChanged {
+            component,
+            local: self.local_candidates[selected.0].base,
+            remote: self.remote_candidates[selected.1].addr,
+        });
+
+        let mut unused: Vec<SocketAddr> = self
+            .local_candidates
+            .iter()
+            .filter(|&(id, candidate)| {
+                candidate.candidate.kind == CandidateKind::Relayed
+                    && candidate.candidate.component == component
+                    && id != selected.0
+            })
+            .map(|(_, candidate)| candidate.base)
+            .collect();
+
+        self.pending_release.retain(|&(c, relayed)| {
+            if c == component {
+                if relayed != selected_base {
+                    unused.push(relayed);
+                }
+
+                false
+            } else {
+                true
+            }
+        });
+
+        for relayed in unused {
+            self.release_relayed(relayed);
+        }
+    }
+
+    /// Emit [`IceEvent::RelayedCandidateUnused`] once for the relayed candidate
+    fn release_relayed(&mut self, relayed: SocketAddr) {
+        if !self.released_relayed.contains(&relayed) {
+            self.released_relayed.push(relayed);
+            self.events
+                .push(IceEvent::RelayedCandidateUnused { relayed });
+        }
+    }
+
+    fn switch_role(&mut self) {
+        self.is_controlling = !self.is_controlling;
+        self.events
+            .set_agent(&self.local_credentials.ufrag, self.is_controlling);
+        self.update_pair_priorities();
+    }
+
+    fn update_pair_priorities(&mut self) {
+        for pair in &mut self.pairs {
+            pair.priority = pair_priority(
+                &self.local_candidates[pair.local].candidate,
+                &self.remote_candidates[pair.remote],
+                self.is_controlling,
+            );
+        }
+
+        self.sort_pairs();
+    }
+}
+
+/// Consent check interval randomized by +-20% https://datatracker.ietf.org/doc/html/rfc7675#section-5.1
+fn consent_interval() -> Duration {
+    CONSENT_INTERVAL.mul_f64(rand::thread_rng().gen_range(0.8..1.2))
+}
+
+fn pair_priority(local: &Candidate, remote: &Candidate, is_controlling: bool) -> u64 {
+    if is_controlling {
+        priority::pair_priority(local.priority, remote.priority)
+    } else {
+        priority::pair_priority(remote.priority, local.priority)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    pub(crate) struct Peer {
+        pub(crate) agent: IceAgent,
+        pub(crate) addr: SocketAddr,
+        /// Address of the second component, if any
+        rtcp_addr: Option<SocketAddr>,
+        /// Public address `addr` is mapped to by a NAT
+        nat: Option<SocketAddr>,
+    }
+
+    pub(crate) fn peer(addr: &str, is_controlling: bool) -> Peer {
+        let addr: SocketAddr = addr.parse().unwrap();
+
+        let mut agent = IceAgent::new(IceCredentials::random(), is_controlling);
+        agent.add_host_addr(addr);
+
+        Peer {
+            agent,
+            addr,
+            rtcp_addr: None,
+            nat: None,
+        }
+    }
+
+    pub(crate) fn exchange(a: &mut Peer, b: &mut Peer) {
+        a.agent
+            .set_remote_credentials(b.agent.local_credentials().clone());
+        b.agent
+            .set_remote_credentials(a.agent.local_credentials().clone());
+
+        for candidate in a.agent.local_candidates().cloned().collect::<Vec<_>>() {
+            b.agent.add_remote_candidate(candidate);
+        }
+
+        for candidate in b.agent.local_candidates().cloned().collect::<Vec<_>>() {
+            a.agent.add_remote_candidate(candidate);
+        }
+    }
+
+    /// Deliver all pending packets from `from` to `to`, returns if any packets were sent
+    fn deliver(now: Instant, from: &mut Peer, to: &mut Peer) -> bool {
+        let mut sent = false;
+
+        // Connections of active TCP candidates (port 9) are emulated using this port
+        const EPHEMERAL_PORT: u16 = 50000;
+
+        while let Some(transmit) = from.agent.poll_transmit() {
+            sent = true;
+
+            let mut source = transmit.source;
+            let mut destination = transmit.destination;
+
+            if transmit.protocol == Protocol::Tcp {
+                if source.port() == 9 {
+                    source.set_port(EPHEMERAL_PORT);
+                }
+
+                if destination.port() == EPHEMERAL_PORT {
+                    destination.set_port(9);
+                }
+            }
+
+            if let Some(nat) = from.nat.filter(|_| source == from.addr) {
+                source = nat;
+            }
+
+            if Some(destination) == to.nat {
+                destination = to.addr;
+            }
+
+            if destination == to.addr || Some(destination) == to.rtcp_addr {
+                to.agent.handle_packet(
+                    now,
+                    ReceivedPacket {
+                        protocol: transmit.protocol,
+                        data: transmit.data,
+                        source,
+                        destination,
+                    },
+                );
+            } else if destination == MDNS_V4 {
+                to.agent.handle_mdns_packet(ReceivedPacket {
+                    protocol: Protocol::Udp,
+                    data: transmit.data,
+                    source: from.addr,
+                    destination: MDNS_V4,
+                });
+            }
+        }
+
+        sent
+    }
+
+    /// Run both agents, delivering packets between them until `until` returns true
+    pub(crate) fn run(
+        now: &mut Instant,
+        a: &mut Peer,
+        b: &mut Peer,
+        mut until: impl FnMut(&Peer, &Peer) -> bool,
+    ) {
+        for _ in 0..10_000 {
+            a.agent.handle_timeout(*now);
+            b.agent.handle_timeout(*now);
+
+            while deliver(*now, a, b) | deliver(*now, b, a) {}
+
+            if until(a, b) {
+                return;
+            }
+
+            *now += Duration::from_millis(10);
+        }
+
+        panic!("condition not reached");
+    }
+
+    #[test]
+    fn connect_host_candidates() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        assert_eq!(a.agent.selected_pair(), Some((a.addr, b.addr)));
+        assert_eq!(b.agent.selected_pair(), Some((b.addr, a.addr)));
+
+        let events: Vec<_> = std::iter::from_fn(|| a.agent.poll_event()).collect();
+
+        assert!(events.contains(&IceEvent::SelectedPairChanged {
+            component: 1,
+            local: a.addr,
+            remote: b.addr
+        }));
+        assert!(events.contains(&IceEvent::NominationCompleted));
+
+        let pair_states: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                IceEvent::PairStateChanged { old, new, .. } => Some((*old, *new)),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(pair_states[0].0, PairState::Frozen);
+        assert!(pair_states.contains(&(PairState::InProgress, PairState::Succeeded)));
+    }
+
+    #[test]
+    fn learn_local_peer_reflexive() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        let nat: SocketAddr = "203.0.113.1:40000".parse().unwrap();
+        a.nat = Some(nat);
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        assert_eq!(a.agent.selected_pair(), Some((a.addr, b.addr)));
+        assert_eq!(b.agent.selected_pair(), Some((b.addr, nat)));
+
+        let stats = a.agent.stats();
+        let selected = stats.pairs.iter().find(|p| p.selected).unwrap();
+        assert_eq!(selected.local_kind, CandidateKind::PeerReflexive);
+        assert_eq!(selected.state, PairState::Succeeded);
+
+        // Peer reflexive candidates are never signaled
+        assert!(a
+            .agent
+            .local_candidates()
+            .all(|c| c.kind != CandidateKind::PeerReflexive));
+
+        let events: Vec<_> = std::iter::from_fn(|| a.agent.poll_event()).collect();
+        assert!(!events.iter().any(|e| matches!(
+            e,
+            IceEvent::NewLocalCandidate { candidate } if candidate.addr == nat
+        )));
+    }
+
+    #[test]
+    fn connect_rtp_and_rtcp() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        a.rtcp_addr = Some("10.0.0.1:1001".parse().unwrap());
+        b.rtcp_addr = Some("10.0.0.2:2001".parse().unwrap());
+        a.agent.add_component_host_addr(2, a.rtcp_addr.unwrap());
+        b.agent.add_component_host_addr(2, b.rtcp_addr.unwrap());
+
+        exchange(&mut a, &mut b);
+
+        // pairs are only formed within a component
+        assert!(a
+            .agent
+            .stats()
+            .pairs
+            .iter()
+            .all(|p| (p.local == a.addr) == (p.component == 1)
+                && (p.remote == b.addr) == (p.component == 1)));
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        assert_eq!(a.agent.selected_pair_for(1), Some((a.addr, b.addr)));
+        assert_eq!(
+            a.agent.selected_pair_for(2),
+            Some((a.rtcp_addr.unwrap(), b.rtcp_addr.unwrap()))
+        );
+        assert_eq!(
+            b.agent.selected_pair_for(2),
+            Some((b.rtcp_addr.unwrap(), a.rtcp_addr.unwrap()))
+        );
+
+        let events: Vec<_> = std::iter::from_fn(|| b.agent.poll_event()).collect();
+
+        let completed = events
+            .iter()
+            .position(|e| *e == IceEvent::NominationCompleted);
+        let selected = events
+            .iter()
+            .filter(|e| matches!(e, IceEvent::SelectedPairChanged { .. }))
+            .count();
+
+        // nomination completes once, after both components selected a pair
+        assert_eq!(selected, 2);
+        assert!(events[completed.unwrap()..]
+            .iter()
+            .all(|e| !matches!(e, IceEvent::SelectedPairChanged { .. })));
+
+        assert!(events.contains(&IceEvent::SelectedPairChanged {
+            component: 2,
+            local: b.rtcp_addr.unwrap(),
+            remote: a.rtcp_addr.unwrap(),
+        }));
+    }
+
+    #[test]
+    fn aggressive_nomination() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        a.agent.set_nomination(Nomination::Aggressive);
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        assert_eq!(a.agent.selected_pair(), Some((a.addr, b.addr)));
+        assert_eq!(b.agent.selected_pair(), Some((b.addr, a.addr)));
+
+        // The first check already carried USE-CANDIDATE, no additional check was required
+        assert!(a.agent.pairs.iter().all(|p| p.nominate));
+    }
+
+    #[test]
+    fn pair_stats() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        a.agent.add_bytes_sent(a.addr, b.addr, 100);
+
+        let stats = a.agent.stats();
+        assert_eq!(stats.selected_pair, Some((a.addr, b.addr)));
+
+        let pair = &stats.pairs[0];
+        assert_eq!(pair.state, PairState::Succeeded);
+        assert!(pair.nominated && pair.selected);
+        assert!(pair.rtt.is_some());
+        assert!(pair.last_request_sent.is_some() && pair.last_response_received.is_some());
+        assert!(pair.requests_sent >= 2 && pair.responses_received >= 2);
+        assert!(pair.bytes_sent > 100 && pair.bytes_received > 0);
+    }
+
+    #[test]
+    fn trickle_candidates() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        a.agent
+            .set_remote_credentials(b.agent.local_credentials().clone());
+        b.agent
+            .set_remote_credentials(a.agent.local_credentials().clone());
+
+        let mut a_candidates = vec![];
+        let mut a_end_of_candidates = false;
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Checking
+                && b.agent.connection_state() == IceConnectionState::Checking
+        });
+
+        while let Some(event) = a.agent.poll_event() {
+            match event {
+                IceEvent::NewLocalCandidate { candidate } => a_candidates.push(candidate),
+                IceEvent::EndOfCandidates => a_end_of_candidates = true,
+                _ => {}
+            }
+        }
+
+        assert_eq!(a_candidates.len(), 1);
+        assert!(a_end_of_candidates);
+
+        // Candidates arrive after the checks have started
+        for candidate in a_candidates {
+            b.agent.add_remote_candidate(candidate);
+        }
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        assert_eq!(a.agent.selected_pair(), Some((a.addr, b.addr)));
+        assert_eq!(b.agent.selected_pair(), Some((b.addr, a.addr)));
+    }
+
+    #[test]
+    fn restart() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        let old_credentials = a.agent.local_credentials().clone();
+
+        a.agent.restart();
+        b.agent.restart();
+
+        assert_ne!(a.agent.local_credentials(), &old_credentials);
+
+        // The previous pair keeps being used until the new checks succeed
+        assert_eq!(a.agent.selected_pair(), Some((a.addr, b.addr)));
+        assert_eq!(b.agent.selected_pair(), Some((b.addr, a.addr)));
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            assert_eq!(a.agent.connection_state(), IceConnectionState::Connected);
+            assert_eq!(b.agent.connection_state(), IceConnectionState::Connected);
+
+            !a.agent.selected_pairs.is_empty() && !b.agent.selected_pairs.is_empty()
+        });
+
+        assert_eq!(a.agent.selected_pair(), Some((a.addr, b.addr)));
+        assert_eq!(b.agent.selected_pair(), Some((b.addr, a.addr)));
+    }
+
+    #[test]
+    fn mdns_candidates() {
+        let mut now = Instant::now();
+        let mut a = Peer {
+            agent: IceAgent::new(IceCredentials::random(), true),
+            addr: "10.0.0.1:1000".parse().unwrap(),
+            rtcp_addr: None,
+            nat: None,
+        };
+        a.agent.enable_mdns();
+        a.agent.add_host_addr(a.addr);
+
+        let mut b = peer("10.0.0.2:2000", false);
+
+        let candidate = a.agent.local_candidates().next().unwrap();
+        assert!(candidate.mdns_name.as_ref().unwrap().ends_with(".local"));
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        assert_eq!(b.agent.selected_pair(), Some((b.addr, a.addr)));
+        assert!(b
+            .agent
+            .remote_candidates
+            .values()
+            .any(|c| c.kind == CandidateKind::Host && c.addr == a.addr));
+    }
+
+    #[test]
+    fn mdns_query_address_families() {
+        let mut agent = IceAgent::new(IceCredentials::random(), true);
+        agent.add_host_addr("[2001:db8::1]:1000".parse().unwrap());
+        agent.add_host_addr("10.0.0.1:1000".parse().unwrap());
+
+        agent.add_remote_candidate(Candidate {
+            foundation: "1".into(),
+            component: 1,
+            priority: 1,
+            kind: CandidateKind::Host,
+            addr: "0.0.0.0:2000".parse().unwrap(),
+            tcp_type: None,
+            related_addr: None,
+            mdns_name: Some(mdns::random_name()),
+        });
+
+        agent.handle_timeout(Instant::now());
+
+        let queries: Vec<(SocketAddr, SocketAddr)> = std::iter::from_fn(|| agent.poll_transmit())
+            .filter(|t| t.destination.port() == 5353)
+            .map(|t| (t.source, t.destination))
+            .collect();
+
+        assert_eq!(
+            queries,
+            [
+                ("0.0.0.0:5353".parse().unwrap(), MDNS_V4),
+                ("[::]:5353".parse().unwrap(), MDNS_V6),
+            ]
+        );
+    }
+
+    #[test]
+    fn tcp_candidates() {
+        let mut now = Instant::now();
+
+        let mut a = Peer {
+            agent: IceAgent::new(IceCredentials::random(), true),
+            addr: "10.0.0.1:9".parse().unwrap(),
+            rtcp_addr: None,
+            nat: None,
+        };
+        a.agent
+            .add_host_tcp_addr("10.0.0.1:1000".parse().unwrap(), TcpType::Active);
+
+        let mut b = Peer {
+            agent: IceAgent::new(IceCredentials::random(), false),
+            addr: "10.0.0.2:2000".parse().unwrap(),
+            rtcp_addr: None,
+            nat: None,
+        };
+        b.agent.add_host_tcp_addr(b.addr, TcpType::Passive);
+
+        let candidate = a.agent.local_candidates().next().unwrap();
+        assert_eq!(candidate.addr, a.addr);
+        assert_eq!(candidate.tcp_type, Some(TcpType::Active));
+        assert_eq!(candidate.protocol(), Protocol::Tcp);
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        assert_eq!(a.agent.selected_pair(), Some((a.addr, b.addr)));
+        assert_eq!(
+            b.agent.selected_pair(),
+            Some((b.addr, "10.0.0.1:50000".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn relayed_candidates() {
+        let mut now = Instant::now();
+
+        // a is only reachable through its relayed candidate
+        let relayed: SocketAddr = "3.3.3.3:5000".parse().unwrap();
+
+        let mut a = Peer {
+            agent: IceAgent::new(IceCredentials::random(), true),
+            addr: relayed,
+            rtcp_addr: None,
+            nat: None,
+        };
+        a.agent.add_host_addr("10.0.0.1:1000".parse().unwrap());
+        a.agent
+            .add_relayed_candidate(relayed, "2.2.2.2:4000".parse().unwrap());
+
+        let mut b = peer("10.0.0.2:2000", false);
+
+        let candidate = a
+            .agent
+            .local_candidates()
+            .find(|c| c.kind == CandidateKind::Relayed)
+            .unwrap();
+        assert_eq!(candidate.addr, relayed);
+        assert_eq!(
+            candidate.related_addr,
+            Some("2.2.2.2:4000".parse().unwrap())
+        );
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        assert_eq!(a.agent.selected_pair(), Some((relayed, b.addr)));
+
+        let events: Vec<_> = std::iter::from_fn(|| a.agent.poll_event()).collect();
+        assert!(!events.contains(&IceEvent::RelayedCandidateUnused { relayed }));
+    }
+
+    #[test]
+    fn release_unused_relayed_candidates() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        let relayed: SocketAddr = "3.3.3.3:5000".parse().unwrap();
+        a.agent
+            .add_relayed_candidate(relayed, "2.2.2.2:4000".parse().unwrap());
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        assert_eq!(a.agent.selected_pair(), Some((a.addr, b.addr)));
+
+        let events: Vec<_> = std::iter::from_fn(|| a.agent.poll_event()).collect();
+        assert!(events.contains(&IceEvent::RelayedCandidateUnused { relayed }));
+    }
+
+    #[test]
+    fn release_relayed_candidates_once() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        let relayed: SocketAddr = "3.3.3.3:5000".parse().unwrap();
+        a.agent
+            .add_relayed_candidate(relayed, "2.2.2.2:4000".parse().unwrap());
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        let unused = |agent: &mut IceAgent| {
+            std::iter::from_fn(|| agent.poll_event())
+                .filter_map(|e| match e {
+                    IceEvent::RelayedCandidateUnused { relayed } => Some(relayed),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(unused(&mut a.agent), [relayed]);
+
+        // Selecting the pair again must not report the candidate again
+        a.agent.selected_pairs.clear();
+        a.agent.update_selected_pair();
+        assert!(a.agent.selected_pair().is_some());
+        assert_eq!(unused(&mut a.agent), []);
+
+        // Relayed candidates added after the selection are released by a restart
+        let late_relayed: SocketAddr = "3.3.3.3:5001".parse().unwrap();
+        a.agent
+            .add_relayed_candidate(late_relayed, "2.2.2.2:4001".parse().unwrap());
+
+        a.agent.restart();
+        assert_eq!(unused(&mut a.agent), [late_relayed]);
+    }
+
+    #[test]
+    fn resolve_role_conflict() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", true);
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        assert_ne!(a.agent.is_controlling(), b.agent.is_controlling());
+    }
+
+    #[test]
+    fn consent_expires() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        // Consent checks keep the pair alive
+        let mut steps = 0;
+        run(&mut now, &mut a, &mut b, |_, _| {
+            steps += 1;
+            steps == 6000
+        });
+        assert_eq!(a.agent.connection_state(), IceConnectionState::Connected);
+
+        // b's address becomes unreachable for a
+        b.addr = "10.0.0.3:3000".parse().unwrap();
+        let disconnected_at = now;
+
+        run(&mut now, &mut a, &mut b, |a, _| {
+            a.agent.connection_state() == IceConnectionState::Failed
+        });
+
+        assert!(now - disconnected_at >= CONSENT_TIMEOUT - CONSENT_INTERVAL.mul_f64(1.2));
+        assert_eq!(a.agent.selected_pair(), None);
+
+        let events: Vec<_> = std::iter::from_fn(|| a.agent.poll_event()).collect();
+
+        assert!(events.contains(&IceEvent::ConsentExpired {
+            component: 1,
+            local: a.addr,
+            remote: "10.0.0.2:2000".parse().unwrap(),
+        }));
+    }
+
+    #[test]
+    fn consent_expires_per_component() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        a.rtcp_addr = Some("10.0.0.1:1001".parse().unwrap());
+        b.rtcp_addr = Some("10.0.0.2:2001".parse().unwrap());
+        a.agent.add_component_host_addr(2, a.rtcp_addr.unwrap());
+        b.agent.add_component_host_addr(2, b.rtcp_addr.unwrap());
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        // Only the RTCP address of b becomes unreachable for a
+        let rtcp_remote = b.rtcp_addr.take().unwrap();
+
+        run(&mut now, &mut a, &mut b, |a, _| {
+            a.agent.connection_state() == IceConnectionState::Failed
+        });
+
+        let events: Vec<_> = std::iter::from_fn(|| a.agent.poll_event()).collect();
+        let expired: Vec<_> = events
+            .iter()
+            .filter(|e| matches!(e, IceEvent::ConsentExpired { .. }))
+            .collect();
+
+        assert_eq!(
+            expired,
+            [&IceEvent::ConsentExpired {
+                component: 2,
+                local: a.rtcp_addr.unwrap(),
+                remote: rtcp_remote,
+            }]
+        );
+
+        // The first component is not torn down
+        assert_eq!(a.agent.selected_pair_for(1), Some((a.addr, b.addr)));
+        assert_eq!(a.agent.selected_pair_for(2), None);
+        assert!(a
+            .agent
+            .stats()
+            .pairs
+            .iter()
+            .any(|p| p.component == 1 && p.state == PairState::Succeeded));
+    }
+
+    #[test]
+    fn fail_without_connectivity() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        exchange(&mut a, &mut b);
+
+        // b's address is unreachable for a
+        b.addr = "10.0.0.3:3000".parse().unwrap();
+
+        run(&mut now, &mut a, &mut b, |a, _| {
+            a.agent.connection_state() == IceConnectionState::Failed
+        });
+    }
+
+    #[test]
+    fn patiently_wait_for_candidates() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        a.agent.set_ta(Duration::from_millis(20));
+        a.agent.set_failure_timeout(Duration::from_secs(10));
+
+        // Only credentials are exchanged, candidates are never trickled
+        a.agent
+            .set_remote_credentials(b.agent.local_credentials().clone());
+        b.agent
+            .set_remote_credentials(a.agent.local_credentials().clone());
+
+        let start = now;
+
+        run(&mut now, &mut a, &mut b, |a, _| {
+            a.agent.connection_state() == IceConnectionState::Failed
+        });
+
+        assert!(now - start >= Duration::from_secs(10));
+        assert!(now - start < Duration::from_secs(11));
+    }
+
+    #[test]
+    fn keepalives() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        a.agent.set_keepalive_interval(Some(Duration::from_secs(1)));
+        b.agent.set_keepalive_interval(None);
+
+        exchange(&mut a, &mut b);
+
+        run(&mut now, &mut a, &mut b, |a, b| {
+            a.agent.connection_state() == IceConnectionState::Connected
+                && b.agent.connection_state() == IceConnectionState::Connected
+        });
+
+        let mut step = |a: &mut Peer, b: &mut Peer, steps: u32| {
+            for _ in 0..steps {
+                now += Duration::from_millis(10);
+
+                a.agent.handle_timeout(now);
+                b.agent.handle_timeout(now);
+
+                while deliver(now, a, b) | deliver(now, b, a) {}
+            }
+        };
+
+        let keepalives = |peer: &Peer| {
+            let stats = peer.agent.stats();
+            let selected = stats.pairs.into_iter().find(|p| p.selected).unwrap();
+
+            (selected.keepalives_sent, selected.keepalives_suppressed)
+        };
+
+        step(&mut a, &mut b, 350);
+        assert_eq!(keepalives(&a), (3, 0));
+        assert_eq!(keepalives(&b), (0, 0));
+
+        // keepalives are suppressed while media is sent
+        for _ in 0..30 {
+            a.agent.add_bytes_sent(a.addr, b.addr, 100);
+            step(&mut a, &mut b, 10);
+        }
+
+        assert_eq!(keepalives(&a), (3, 3));
+    }
+
+    #[test]
+    fn fail_after_remote_end_of_candidates() {
+        let mut now = Instant::now();
+        let mut a = peer("10.0.0.1:1000", true);
+        let mut b = peer("10.0.0.2:2000", false);
+
+        a.agent.set_failure_timeout(Duration::from_secs(10));
+
+        a.agent
+            .set_remote_credentials(b.agent.local_credentials().clone());
+        b.agent
+            .set_remote_credentials(a.agent.local_credentials().clone());
+        a.agent.set_remote_end_of_candidates();
+
+        // candidates arriving after end-of-candidates are ignored
+        a.agent
+            .add_remote_candidate(b.agent.local_candidates().next().unwrap().clone());
+        assert!(a.agent.stats().pairs.is_empty());
+
+        let start = now;
+
+        run(&mut now, &mut a, &mut b, |a, _| {
+            a.agent.connection_state() == IceConnectionState::Failed
+        });
+
+        assert!(now - start < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn gather_server_reflexive() {
+        let host: SocketAddr = "10.0.0.1:1000".parse().unwrap();
+        let server: SocketAddr = "1.1.1.1:3478".parse().unwrap();
+        let mapped: SocketAddr = "2.2.2.2:4000".parse().unwrap();
+
+        let mut agent = IceAgent::new(IceCredentials::random(), true);
+        agent.add_host_addr(host);
+        agent.add_stun_server(server);
+
+        agent.handle_timeout(Instant::now());
+        assert_eq!(agent.gathering_state(), IceGatheringState::Gathering);
+
+        let transmit = agent.poll_transmit().unwrap();
+        assert_eq!(transmit.source, host);
+        assert_eq!(transmit.destination, server);
+
+        let request = ParsedMessage::parse(transmit.data).unwrap();
+
+        let mut response = stun_types::builder::MessageBuilder::new(
+            Class::Success,
+            Method::Binding,
+            request.tsx_id,
+        );
+        response.add_attr(&XorMappedAddress(mapped)).unwrap();
+
+        agent.handle_packet(
+            Instant::now(),
+            ReceivedPacket {
+                protocol: Protocol::Udp,
+                data: response.finish(),
+                source: server,
+                destination: host,
+            },
+        );
+        agent.handle_timeout(Instant::now());
+
+        assert_eq!(agent.gathering_state(), IceGatheringState::Complete);
+
+        let srflx = agent
+            .local_candidates()
+            .find(|c| c.kind == CandidateKind::ServerReflexive)
+            .unwrap();
+
+        assert_eq!(srflx.addr, mapped);
+        assert_eq!(srflx.related_addr, Some(host));
+    }
+
+    #[test]
+    fn gather_from_multiple_servers() {
+        let host: SocketAddr = "10.0.0.1:1000".parse().unwrap();
+        let servers: [SocketAddr; 3] = [
+            "1.1.1.1:3478".parse().unwrap(),
+            "1.1.1.2:3478".parse().unwrap(),
+            "1.1.1.3:3478".parse().unwrap(),
+        ];
+        let mapped: SocketAddr = "2.2.2.2:4000".parse().unwrap();
+
+        let mut agent = IceAgent::new(IceCredentials::random(), true);
+        agent.add_host_addr(host);
+
+        for server in servers {
+            agent.add_stun_server(server);
+        }
+
+        let mut now = Instant::now();
+        let mut requests = vec![];
+
+        // All requests are sent paced, without waiting for responses
+        while requests.len() < servers.len() {
+            agent.handle_timeout(now);
+
+            while let Some(transmit) = agent.poll_transmit() {
+                requests.push(transmit);
+            }
+
+            now += DEFAULT_TA;
+        }
+
+        for (i, transmit) in requests.into_iter().enumerate() {
+            assert_eq!(transmit.destination, servers[i]);
+
+            // the last server does not respond
+            if i == 2 {
+                continue;
+            }
+
+            let request = ParsedMessage::parse(transmit.data).unwrap();
+
+            let mut response = stun_types::builder::MessageBuilder::new(
+                Class::Success,
+                Method::Binding,
+                request.tsx_id,
+            );
+            response.add_attr(&XorMappedAddress(mapped)).unwrap();
+
+            agent.handle_packet(
+                now,
+                ReceivedPacket {
+                    protocol: Protocol::Udp,
+                    data: response.finish(),
+                    source: transmit.destination,
+                    destination: host,
+                },
+            );
+        }
+
+        agent.handle_timeout(now);
+        assert_eq!(agent.gathering_state(), IceGatheringState::Gathering);
+
+        while agent.gathering_state() != IceGatheringState::Complete {
+            now += Duration::from_millis(100);
+            agent.handle_timeout(now);
+        }
+
+        // Both servers returned the same mapped address
+        assert_eq!(
+            agent
+                .local_candidates()
+                .filter(|c| c.kind == CandidateKind::ServerReflexive)
+                .count(),
+            1
+        );
+
+        let events: Vec<_> = std::iter::from_fn(|| agent.poll_event()).collect();
+
+        assert!(events.contains(&IceEvent::StunServerFailed {
+            server: servers[2],
+            local: host,
+            error: StunServerError::Timeout,
+        }));
+        assert!(events.contains(&IceEvent::EndOfCandidates));
+    }
+
+    #[test]
+    fn interface_policy() {
+        let mut agent = IceAgent::new(IceCredentials::random(), true);
+        agent.set_interface_policy(InterfacePolicy {
+            deny: vec![InterfaceFilter::Network {
+                ip: "10.1.0.0".parse().unwrap(),
+                prefix_len: 16,
+            }],
+            ..Default::default()
+        });
+
+        agent.add_host_addr("127.0.0.1:1000".parse().unwrap());
+        agent.add_host_addr("169.254.0.1:1000".parse().unwrap());
+        agent.add_host_addr("10.1.0.1:1000".parse().unwrap());
+        agent.add_host_addr("10.0.0.1:1000".parse().unwrap());
+
+        let addrs: Vec<SocketAddr> = agent.local_candidates().map(|c| c.addr).collect();
+        assert_eq!(addrs, ["10.0.0.1:1000".parse().unwrap()]);
+    }
+
+    #[test]
+    fn candidate_priority() {
+        let mut agent = IceAgent::new(IceCredentials::random(), true);
+        agent.set_type_preferences(TypePreferences {
+            relayed: 127,
+            ..Default::default()
+        });
+        agent.set_local_preference("10.0.0.2".parse().unwrap(), 100);
+
+        agent.add_host_addr("10.0.0.1:1000".parse().unwrap());
+        agent.add_host_addr("10.0.0.2:1000".parse().unwrap());
+        agent.add_relayed_candidate(
+            "1.1.1.1:3000".parse().unwrap(),
+            "2.2.2.2:4000".parse().unwrap(),
+        );
+
+        let priorities: Vec<u32> = agent.local_candidates().map(|c| c.priority).collect();
+        assert_eq!(
+            priorities,
+            [
+                priority::candidate_priority(126, 65534, 1),
+                priority::candidate_priority(126, 100, 1),
+                priority::candidate_priority(126, 65534, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn dual_stack_priority() {
+        let mut agent = IceAgent::new(IceCredentials::random(), true);
+
+        agent.add_host_addr("10.0.0.1:1000".parse().unwrap());
+        agent.add_host_addr("10.0.0.2:1000".parse().unwrap());
+        agent.add_host_addr("[2001:db8::1]:1000".parse().unwrap());
+        agent.add_host_addr("[2001:db8::2]:1000".parse().unwrap());
+
+        let local_preferences: Vec<u32> = agent
+            .local_candidates()
+            .map(|c| (c.priority >> 8) & 0xFFFF)
+            .collect();
+
+        // Families are interleaved, starting with IPv6
+        assert_eq!(local_preferences, [65534, 65532, 65535, 65533]);
+
+        let mut agent = IceAgent::new(IceCredentials::random(), true);
+        agent.set_address_family_policy(AddressFamilyPolicy::Ipv4Only);
+
+        agent.add_host_addr("10.0.0.1:1000".parse().unwrap());
+        agent.add_host_addr("[2001:db8::1]:1000".parse().unwrap());
+
+        assert_eq!(agent.local_candidates().count(), 1);
+    }
+}
@@ -0,0 +1,168 @@
+//! Obfuscation of host candidates using mDNS names
+//! ([draft-ietf-mmusic-mdns-ice-candidates](https://datatracker.ietf.org/doc/html/draft-ietf-mmusic-mdns-ice-candidates))
+
+use rand::Rng;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+use trust_dns_proto::op::{Message, MessageType, OpCode, Query};
+use trust_dns_proto::rr::rdata::{A, AAAA};
+use trust_dns_proto::rr::{Name, RData, Record, RecordType};
+use trust_dns_proto::serialize::binary::BinDecodable;
+
+/// IPv4 multicast address of mDNS
+pub const MDNS_V4: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+
+/// IPv6 multicast address of mDNS
+pub const MDNS_V6: SocketAddr = SocketAddr::new(
+    IpAddr::V6(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb)),
+    5353,
+);
+
+/// Interval in which unanswered queries are repeated
+pub(crate) const QUERY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Amount of times a query is sent before the name is considered unresolvable
+pub(crate) const MAX_QUERIES: u32 = 3;
+
+/// TTL of the records sent in responses
+const TTL: u32 = 120;
+
+/// Generate a random name in the form of `<uuid-v4>.local`
+pub(crate) fn random_name() -> String {
+    let mut bytes: [u8; 16] = rand::thread_rng().gen();
+
+    // version 4, variant 1
+    bytes[6] = (bytes[6] & 0x0F) | 0x40;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+
+    format!(
+        "{}-{}-{}-{}-{}.local",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Returns if the given name is an mDNS name
+pub(crate) fn is_mdns_name(name: &str) -> bool {
+    normalize(name).ends_with(".local")
+}
+
+/// Names are compared case insensitive and without the trailing dot
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+pub(crate) fn make_query(name: &str) -> Option<Vec<u8>> {
+    let name = Name::from_str(name).ok()?;
+
+    let mut msg = Message::new();
+    msg.set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .add_query(Query::query(name.clone(), RecordType::A))
+        .add_query(Query::query(name, RecordType::AAAA));
+
+    msg.to_vec().ok()
+}
+
+pub(crate) fn make_response(name: &str, ip: IpAddr) -> Option<Vec<u8>> {
+    let name = Name::from_str(name).ok()?;
+
+    let rdata = match ip {
+        IpAddr::V4(ip) => RData::A(A(ip)),
+        IpAddr::V6(ip) => RData::AAAA(AAAA(ip)),
+    };
+
+    let mut record = Record::from_rdata(name, TTL, rdata);
+    record.set_mdns_cache_flush(true);
+
+    let mut msg = Message::new();
+    msg.set_message_type(MessageType::Response)
+        .set_op_code(OpCode::Query)
+        .set_authoritative(true)
+        .add_answer(record);
+
+    msg.to_vec().ok()
+}
+
+/// Content of a received mDNS message relevant to the agent
+#[derive(Default)]
+pub(crate) struct MdnsMessage {
+    /// Names queried for A or AAAA records
+    pub(crate) queries: Vec<String>,
+    /// Addresses of names from A & AAAA records
+    pub(crate) answers: Vec<(String, IpAddr)>,
+}
+
+impl MdnsMessage {
+    pub(crate) fn parse(data: &[u8]) -> Option<Self> {
+        let msg = Message::from_bytes(data).ok()?;
+
+        let mut parsed = Self::default();
+
+        match msg.message_type() {
+            MessageType::Query => {
+                for query in msg.queries() {
+                    if matches!(query.query_type(), RecordType::A | RecordType::AAAA) {
+                        parsed.queries.push(normalize(&query.name().to_utf8()));
+                    }
+                }
+            }
+            MessageType::Response => {
+                for record in msg.answers().iter().chain(msg.additionals()) {
+                    let ip = match record.data() {
+                        Some(RData::A(a)) => IpAddr::V4(a.0),
+                        Some(RData::AAAA(aaaa)) => IpAddr::V6(aaaa.0),
+                        _ => continue,
+                    };
+
+                    parsed
+                        .answers
+                        .push((normalize(&record.name().to_utf8()), ip));
+                }
+            }
+        }
+
+        Some(parsed)
+    }
+
+    pub(crate) fn queries(&self, name: &str) -> bool {
+        self.queries.contains(&normalize(name))
+    }
+
+    pub(crate) fn answer(&self, name: &str) -> Option<IpAddr> {
+        let name = normalize(name);
+
+        self.answers
+            .iter()
+            .find_map(|(n, ip)| (*n == name).then_some(*ip))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn query_response() {
+        let name = random_name();
+
+        assert!(is_mdns_name(&name));
+        assert_eq!(name.len(), 36 + ".local".len());
+
+        let query = MdnsMessage::parse(&make_query(&name).unwrap()).unwrap();
+        assert!(query.queries(&name.to_uppercase()));
+        assert!(query.answers.is_empty());
+
+        let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 10));
+
+        let response = MdnsMessage::parse(&make_response(&name, ip).unwrap()).unwrap();
+        assert!(response.queries.is_empty());
+        assert_eq!(response.answer(&name), Some(ip));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::CandidateKind;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// How the controlling agent nominates candidate pairs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nomination {
+    /// A valid pair chosen by the [`NominationPolicy`] is nominated using an additional check
+    /// ([RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-8.1.1))
+    Regular,
+    /// Every check carries the USE-CANDIDATE attribute and the first valid pair is selected
+    /// ([RFC5245](https://datatracker.ietf.org/doc/html/rfc5245#section-8.1.1.2)). Reduces the
+    /// setup time, but the selected pair might change when a higher priority pair succeeds.
+    Aggressive,
+}
+
+/// A candidate pair whose connectivity check succeeded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidPair {
+    pub component: u8,
+    pub local: SocketAddr,
+    pub local_kind: CandidateKind,
+    pub remote: SocketAddr,
+    pub remote_kind: CandidateKind,
+    pub priority: u64,
+}
+
+impl ValidPair {
+    /// Returns if one of the candidates is relayed
+    pub fn is_relayed(&self) -> bool {
+        self.local_kind == CandidateKind::Relayed || self.remote_kind == CandidateKind::Relayed
+    }
+}
+
+/// Decides which valid pair is nominated when using [`Nomination::Regular`]
+pub trait NominationPolicy: Send {
+    /// Called for each component when pairs became valid and periodically afterwards until a pair
+    /// of the component is nominated.
+    ///
+    /// `valid_pairs` are sorted by priority, highest first. `since_first_valid` is the time since
+    /// the first pair became valid and `checks_pending` is true if other pairs may still become
+    /// valid.
+    ///
+    /// Returns the index of the pair to nominate, or `None` to wait for more pairs.
+    fn nominate(
+        &mut self,
+        valid_pairs: &[ValidPair],
+        since_first_valid: Duration,
+        checks_pending: bool,
+    ) -> Option<usize>;
+}
+
+/// Nominate the first valid pair, this is the default policy
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NominateFirstValid;
+
+impl NominationPolicy for NominateFirstValid {
+    fn nominate(&mut self, _: &[ValidPair], _: Duration, _: bool) -> Option<usize> {
+        Some(0)
+    }
+}
+
+/// Wait up to `timeout` for a better pair to become valid
+#[derive(Debug, Clone, Copy)]
+pub struct WaitForBetterPair {
+    pub timeout: Duration,
+    /// Nominate pairs without relayed candidates over higher priority relayed pairs
+    pub prefer_non_relayed: bool,
+}
+
+impl NominationPolicy for WaitForBetterPair {
+    fn nominate(
+        &mut self,
+        valid_pairs: &[ValidPair],
+        since_first_valid: Duration,
+        checks_pending: bool,
+    ) -> Option<usize> {
+        let best = if self.prefer_non_relayed {
+            valid_pairs.iter().position(|p| !p.is_relayed())
+        } else {
+            Some(0)
+        };
+
+        match best {
+            // Nothing better can be expected after the timeout or if all checks are done
+            _ if since_first_valid >= self.timeout || !checks_pending => Some(best.unwrap_or(0)),
+            // A non-relayed pair is good enough, when relayed pairs are not preferred
+            Some(index) if self.prefer_non_relayed => Some(index),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pair(priority: u64, relayed: bool) -> ValidPair {
+        ValidPair {
+            component: 1,
+            local: "10.0.0.1:1000".parse().unwrap(),
+            local_kind: if relayed {
+                CandidateKind::Relayed
+            } else {
+                CandidateKind::Host
+            },
+            remote: "10.0.0.2:2000".parse().unwrap(),
+            remote_kind: CandidateKind::Host,
+            priority,
+        }
+    }
+
+    #[test]
+    fn wait_for_better_pair() {
+        let mut policy = WaitForBetterPair {
+            timeout: Duration::from_millis(500),
+            prefer_non_relayed: true,
+        };
+
+        let relayed = [pair(100, true)];
+        assert_eq!(policy.nominate(&relayed, Duration::ZERO, true), None);
+        assert_eq!(
+            policy.nominate(&relayed, Duration::from_millis(500), true),
+            Some(0)
+        );
+        assert_eq!(policy.nominate(&relayed, Duration::ZERO, false), Some(0));
+
+        let mixed = [pair(100, true), pair(50, false)];
+        assert_eq!(policy.nominate(&mixed, Duration::ZERO, true), Some(1));
+
+        policy.prefer_non_relayed = false;
+        assert_eq!(policy.nominate(&mixed, Duration::ZERO, true), None);
+        assert_eq!(
+            policy.nominate(&mixed, Duration::from_secs(1), true),
+            Some(0)
+        );
+    }
+}
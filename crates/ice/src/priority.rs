@@ -0,0 +1,164 @@
+//! Priority and foundation computation ([RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-5.1.1.3)),
+//! usable without an [`IceAgent`](crate::IceAgent) e.g. to generate candidates for SDP
+
+use crate::{CandidateKind, Protocol};
+use std::cmp::{max, min};
+use std::net::IpAddr;
+
+/// Preference of each candidate type, from 0 (lowest) to 126 (highest)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypePreferences {
+    pub host: u8,
+    pub peer_reflexive: u8,
+    pub server_reflexive: u8,
+    pub relayed: u8,
+}
+
+impl Default for TypePreferences {
+    /// Recommended values from [RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-5.1.2.2)
+    fn default() -> Self {
+        Self {
+            host: 126,
+            peer_reflexive: 110,
+            server_reflexive: 100,
+            relayed: 0,
+        }
+    }
+}
+
+impl TypePreferences {
+    pub fn get(&self, kind: CandidateKind) -> u32 {
+        let preference = match kind {
+            CandidateKind::Host => self.host,
+            CandidateKind::PeerReflexive => self.peer_reflexive,
+            CandidateKind::ServerReflexive => self.server_reflexive,
+            CandidateKind::Relayed => self.relayed,
+        };
+
+        u32::from(preference.min(126))
+    }
+
+    /// Compute the priority of a candidate of the given type
+    pub fn candidate_priority(
+        &self,
+        kind: CandidateKind,
+        local_preference: u16,
+        component: u8,
+    ) -> u32 {
+        candidate_priority(self.get(kind), local_preference, component)
+    }
+}
+
+/// Candidate priority as defined in [RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-5.1.2.1)
+///
+/// `component` must not be 0.
+pub fn candidate_priority(type_preference: u32, local_preference: u16, component: u8) -> u32 {
+    (type_preference << 24) + (u32::from(local_preference) << 8) + (256 - u32::from(component))
+}
+
+/// Pair priority as defined in [RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-6.1.2.3)
+pub fn pair_priority(controlling_priority: u32, controlled_priority: u32) -> u64 {
+    let g = u64::from(controlling_priority);
+    let d = u64::from(controlled_priority);
+
+    (1 << 32) * min(g, d) + 2 * max(g, d) + u64::from(g > d)
+}
+
+/// Assigns foundations to candidates
+///
+/// Candidates share a foundation if they have the same type, protocol, base IP and STUN/TURN server.
+#[derive(Debug, Default, Clone)]
+pub struct Foundations {
+    assigned: Vec<(CandidateKind, Protocol, IpAddr, Option<IpAddr>)>,
+}
+
+impl Foundations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the foundation for a candidate, `server` is the IP of the server the candidate was
+    /// obtained from
+    pub fn get(
+        &mut self,
+        kind: CandidateKind,
+        protocol: Protocol,
+        base: IpAddr,
+        server: Option<IpAddr>,
+    ) -> String {
+        let key = (kind, protocol, base, server);
+
+        let index = match self.assigned.iter().position(|f| *f == key) {
+            Some(index) => index,
+            None => {
+                self.assigned.push(key);
+                self.assigned.len() - 1
+            }
+        };
+
+        (index + 1).to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn priority() {
+        let preferences = TypePreferences::default();
+
+        assert_eq!(
+            preferences.candidate_priority(CandidateKind::Host, 65535, 1),
+            2130706431
+        );
+        assert_eq!(
+            preferences.candidate_priority(CandidateKind::ServerReflexive, 65535, 1),
+            1694498815
+        );
+
+        let custom = TypePreferences {
+            relayed: 127,
+            ..preferences
+        };
+        assert_eq!(custom.get(CandidateKind::Relayed), 126);
+
+        assert_eq!(pair_priority(1, 2), (1 << 32) + 4);
+        assert_eq!(pair_priority(2, 1), (1 << 32) + 5);
+    }
+
+    #[test]
+    fn foundations() {
+        let mut foundations = Foundations::new();
+
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let server: IpAddr = "1.1.1.1".parse().unwrap();
+
+        let host = foundations.get(CandidateKind::Host, Protocol::Udp, ip, None);
+        let srflx = foundations.get(
+            CandidateKind::ServerReflexive,
+            Protocol::Udp,
+            ip,
+            Some(server),
+        );
+
+        assert_ne!(host, srflx);
+        assert_ne!(
+            host,
+            foundations.get(CandidateKind::Host, Protocol::Tcp, ip, None)
+        );
+        assert_eq!(
+            host,
+            foundations.get(CandidateKind::Host, Protocol::Udp, ip, None)
+        );
+        assert_eq!(
+            srflx,
+            foundations.get(
+                CandidateKind::ServerReflexive,
+                Protocol::Udp,
+                ip,
+                Some(server)
+            )
+        );
+    }
+}
@@ -0,0 +1,339 @@
+//! Exchange of ICE parameters using SDP ([RFC8839](https://datatracker.ietf.org/doc/html/rfc8839))
+//!
+//! `a=ice-ufrag` and `a=ice-pwd` may appear at session and media level, the media level takes
+//! precedence. `a=ice-options` may also appear at both levels, options of the media level apply
+//! in addition to the session level ones. Candidates are always placed at media level,
+//! `a=ice-lite` at session level.
+
+use crate::{
+    mdns, Candidate, CandidateKind, IceAgent, IceCredentials, IceGatheringState, TcpType,
+    DEFAULT_TA,
+};
+use sdp_types::attributes::candidate::{Candidate as SdpCandidate, UntaggedAddress};
+use sdp_types::attributes::ice::{Password, RemoteCandidate, RemoteCandidates, UsernameFragment};
+use sdp_types::msg::{MediaScope, Message};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+/// ICE option signaling support for trickle ICE ([RFC8840](https://datatracker.ietf.org/doc/html/rfc8840))
+pub const TRICKLE_OPTION: &str = "trickle";
+
+/// Returns the credentials of the media section, falling back to the session level attributes
+pub fn remote_credentials(session: &Message, media: &MediaScope) -> Option<IceCredentials> {
+    let ufrag = media.ice_ufrag.as_ref().or(session.ice_ufrag.as_ref())?;
+    let pwd = media.ice_pwd.as_ref().or(session.ice_pwd.as_ref())?;
+
+    Some(IceCredentials {
+        ufrag: ufrag.ufrag.to_string(),
+        pwd: pwd.pwd.to_string(),
+    })
+}
+
+/// Returns if the given `a=ice-options` option applies to the media section, either set at
+/// session or media level
+pub fn has_ice_option(session: &Message, media: &MediaScope, option: &str) -> bool {
+    session.ice_options.contains(option) || media.ice_options.contains(option)
+}
+
+/// Convert an SDP candidate attribute, returns `None` for unsupported candidates (e.g. unknown
+/// transport or type, or FQDNs which are not mDNS names)
+pub fn candidate_from_sdp(candidate: &SdpCandidate) -> Option<Candidate> {
+    let component = u8::try_from(candidate.component).ok().filter(|c| *c != 0)?;
+
+    let tcp_type = if candidate.transport.eq_ignore_ascii_case("udp") {
+        None
+    } else if candidate.transport.eq_ignore_ascii_case("tcp") {
+        Some(match candidate.tcp_type()?.to_ascii_lowercase().as_str() {
+            "active" => TcpType::Active,
+            "passive" => TcpType::Passive,
+            "so" => TcpType::So,
+            _ => return None,
+        })
+    } else {
+        return None;
+    };
+
+    let kind = match &*candidate.typ {
+        "host" => CandidateKind::Host,
+        "srflx" => CandidateKind::ServerReflexive,
+        "prflx" => CandidateKind::PeerReflexive,
+        "relay" => CandidateKind::Relayed,
+        _ => return None,
+    };
+
+    let (addr, mdns_name) = match &candidate.address {
+        UntaggedAddress::IpAddress(ip) => (SocketAddr::new(*ip, candidate.port), None),
+        // The address is replaced once the name has been resolved
+        UntaggedAddress::Fqdn(name) if mdns::is_mdns_name(name) => (
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), candidate.port),
+            Some(name.to_string()),
+        ),
+        UntaggedAddress::Fqdn(_) => return None,
+    };
+
+    let related_addr = match (&candidate.rel_addr, candidate.rel_port) {
+        (Some(UntaggedAddress::IpAddress(ip)), Some(port)) => Some(SocketAddr::new(*ip, port)),
+        _ => None,
+    };
+
+    Some(Candidate {
+        foundation: candidate.foundation.to_string(),
+        component,
+        priority: u32::try_from(candidate.priority).ok()?,
+        kind,
+        addr,
+        tcp_type,
+        related_addr,
+        mdns_name,
+    })
+}
+
+/// Convert a candidate to an SDP candidate attribute
+pub fn candidate_to_sdp(candidate: &Candidate) -> SdpCandidate {
+    let address = match &candidate.mdns_name {
+        Some(name) => UntaggedAddress::Fqdn(name.clone().into()),
+        None => UntaggedAddress::IpAddress(candidate.addr.ip()),
+    };
+
+    let (transport, unknown) = match candidate.tcp_type {
+        None => ("UDP", vec![]),
+        Some(tcp_type) => {
+            let tcp_type = match tcp_type {
+                TcpType::Active => "active",
+                TcpType::Passive => "passive",
+                TcpType::So => "so",
+            };
+
+            ("TCP", vec![("tcptype".into(), tcp_type.into())])
+        }
+    };
+
+    let typ = match candidate.kind {
+        CandidateKind::Host => "host",
+        CandidateKind::ServerReflexive => "srflx",
+        CandidateKind::PeerReflexive => "prflx",
+        CandidateKind::Relayed => "relay",
+    };
+
+    SdpCandidate {
+        foundation: candidate.foundation.clone().into(),
+        component: candidate.component.into(),
+        transport: transport.into(),
+        priority: candidate.priority.into(),
+        address,
+        port: candidate.addr.port(),
+        typ: typ.into(),
+        rel_addr: candidate
+            .related_addr
+            .map(|addr| UntaggedAddress::IpAddress(addr.ip())),
+        rel_port: candidate.related_addr.map(|addr| addr.port()),
+        unknown,
+    }
+}
+
+impl IceAgent {
+    /// Apply the credentials, candidates and end-of-candidates indication of the peer's media
+    /// section
+    ///
+    /// Unsupported candidates are ignored. If the peer requests a larger pacing interval
+    /// (`a=ice-pacing`) it is used instead of the local one.
+    pub fn set_remote_sdp(&mut self, session: &Message, media: &MediaScope) {
+        if let Some(credentials) = remote_credentials(session, media) {
+            self.set_remote_credentials(credentials);
+        }
+
+        if let Some(pacing) = session.ice_pacing {
+            self.ta = self.ta.max(Duration::from_millis(pacing.into()));
+        }
+
+        for candidate in &media.ice_candidates {
+            match candidate_from_sdp(candidate) {
+                Some(candidate) => self.add_remote_candidate(candidate),
+                None => log::debug!("ignoring unsupported candidate {candidate}"),
+            }
+        }
+
+        if media.ice_end_of_candidates {
+            self.set_remote_end_of_candidates();
+        }
+    }
+
+    /// Write the local credentials and candidates into the media section of an SDP offer or
+    /// answer. End-of-candidates is indicated once gathering is complete.
+    ///
+    /// Once a pair has been selected for every component, the controlling agent also lists the
+    /// remote candidates of the selected pairs (`a=remote-candidates`).
+    ///
+    /// The credentials are written at media level, so every media section can be handled by its
+    /// own agent. Support for trickle ICE and a non-default pacing interval are signaled at
+    /// session level.
+    pub fn write_sdp(&self, session: &mut Message, media: &mut MediaScope) {
+        let credentials = self.local_credentials();
+
+        media.ice_ufrag = Some(UsernameFragment {
+            ufrag: credentials.ufrag.clone().into(),
+        });
+        media.ice_pwd = Some(Password {
+            pwd: credentials.pwd.clone().into(),
+        });
+        media.ice_candidates = self.local_candidates().map(candidate_to_sdp).collect();
+        media.ice_end_of_candidates = self.gathering_state() == IceGatheringState::Complete;
+        media.ice_remote_candidates = self.remote_candidates_for_sdp();
+
+        session.ice_options.add(TRICKLE_OPTION);
+
+        if self.ta != DEFAULT_TA {
+            session.ice_pacing = u32::try_from(self.ta.as_millis()).ok();
+        }
+    }
+
+    fn remote_candidates_for_sdp(&self) -> Option<RemoteCandidates> {
+        if !self.is_controlling || !self.all_components_selected() {
+            return None;
+        }
+
+        let candidates = (1..=self.components())
+            .filter_map(|component| {
+                let (_, remote) = self.selected_pair_for(component)?;
+
+                Some(RemoteCandidate {
+                    component: component.into(),
+                    address: UntaggedAddress::IpAddress(remote.ip()),
+                    port: remote.port(),
+                })
+            })
+            .collect();
+
+        Some(RemoteCandidates { candidates })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytesstr::BytesStr;
+    use sdp_types::msg::{parse, Builder};
+
+    const SDP: &str = "v=0\r
+o=- 1 1 IN IP4 10.0.0.2\r
+s=-\r
+t=0 0\r
+a=ice-options:trickle\r
+a=ice-ufrag:sessionufrag\r
+a=ice-pwd:sessionpasswordsessionpassword\r
+m=audio 2000 RTP/AVP 0\r
+c=IN IP4 10.0.0.2\r
+a=ice-ufrag:mediaufrag\r
+a=candidate:1 1 UDP 2130706431 10.0.0.2 2000 typ host\r
+a=candidate:2 1 TCP 2105458942 10.0.0.2 9 typ host tcptype active\r
+a=candidate:3 1 UDP 2130706431 abc.local 2000 typ host\r
+a=candidate:4 1 UDP 1694498815 1.2.3.4 3000 typ srflx raddr 10.0.0.2 rport 2000\r
+a=candidate:5 1 SCTP 1 10.0.0.2 4000 typ host\r
+a=end-of-candidates\r
+";
+
+    fn sdp() -> Message {
+        parse::<Builder>(&BytesStr::from_static(SDP)).unwrap()
+    }
+
+    #[test]
+    fn read_remote_parameters() {
+        let session = sdp();
+        let media = &session.media_scopes[0];
+
+        let credentials = remote_credentials(&session, media).unwrap();
+        assert_eq!(credentials.ufrag, "mediaufrag");
+        assert_eq!(credentials.pwd, "sessionpasswordsessionpassword");
+        assert!(has_ice_option(&session, media, TRICKLE_OPTION));
+        assert!(media.ice_end_of_candidates);
+
+        let candidates: Vec<_> = media
+            .ice_candidates
+            .iter()
+            .filter_map(candidate_from_sdp)
+            .collect();
+
+        assert_eq!(candidates.len(), 4);
+        assert_eq!(candidates[1].tcp_type, Some(TcpType::Active));
+        assert_eq!(candidates[2].mdns_name.as_deref(), Some("abc.local"));
+        assert_eq!(candidates[3].kind, CandidateKind::ServerReflexive);
+        assert_eq!(
+            candidates[3].related_addr,
+            Some("10.0.0.2:2000".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn media_level_options() {
+        let mut session = sdp();
+        session.ice_options.options.clear();
+        assert!(!has_ice_option(
+            &session,
+            &session.media_scopes[0],
+            TRICKLE_OPTION
+        ));
+
+        session.media_scopes[0].ice_options.add(TRICKLE_OPTION);
+        assert!(has_ice_option(
+            &session,
+            &session.media_scopes[0],
+            TRICKLE_OPTION
+        ));
+    }
+
+    #[test]
+    fn write_local_parameters() {
+        let mut session = sdp();
+        let mut media = session.media_scopes.remove(0);
+        session.ice_options.options.clear();
+
+        let mut agent = IceAgent::new(IceCredentials::random(), false);
+        agent.add_host_addr("10.0.0.1:1000".parse().unwrap());
+        agent.write_sdp(&mut session, &mut media);
+        assert!(!media.ice_end_of_candidates);
+
+        agent.handle_timeout(std::time::Instant::now());
+        agent.write_sdp(&mut session, &mut media);
+        assert!(media.ice_end_of_candidates);
+
+        assert_eq!(
+            media.ice_ufrag.as_ref().unwrap().ufrag,
+            agent.local_credentials().ufrag.as_str()
+        );
+        assert_eq!(
+            media.ice_candidates[0].to_string(),
+            format!(
+                "a=candidate:1 1 UDP {} 10.0.0.1 1000 typ host",
+                agent.local_candidates().next().unwrap().priority
+            )
+        );
+        assert!(has_ice_option(&session, &media, TRICKLE_OPTION));
+
+        let candidate = agent.local_candidates().next().unwrap();
+        assert_eq!(
+            candidate_from_sdp(&candidate_to_sdp(candidate)).as_ref(),
+            Some(candidate)
+        );
+    }
+
+    #[test]
+    fn pacing() {
+        let mut session = sdp();
+        let mut media = session.media_scopes.remove(0);
+
+        let mut agent = IceAgent::new(IceCredentials::random(), false);
+        agent.write_sdp(&mut session, &mut media);
+        assert!(session.ice_pacing.is_none());
+
+        session.ice_pacing = Some(100);
+        agent.set_remote_sdp(&session, &media);
+        assert_eq!(agent.ta, Duration::from_millis(100));
+
+        session.ice_pacing = Some(20);
+        agent.set_remote_sdp(&session, &media);
+        assert_eq!(agent.ta, Duration::from_millis(100));
+
+        agent.write_sdp(&mut session, &mut media);
+        assert_eq!(session.ice_pacing, Some(100));
+    }
+}
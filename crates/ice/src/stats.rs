@@ -0,0 +1,74 @@
+use crate::{CandidateKind, PairState, Protocol};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// Snapshot of the agent's candidate pairs, returned by [`IceAgent::stats`](crate::IceAgent::stats)
+#[derive(Debug, Clone)]
+pub struct IceAgentStats {
+    /// All candidate pairs, sorted by priority
+    pub pairs: Vec<CandidatePairStats>,
+    /// Local and remote address of the selected pair of the first component
+    pub selected_pair: Option<(SocketAddr, SocketAddr)>,
+}
+
+/// Statistics of a single candidate pair
+#[derive(Debug, Clone)]
+pub struct CandidatePairStats {
+    pub component: u8,
+    pub protocol: Protocol,
+    pub local: SocketAddr,
+    pub local_kind: CandidateKind,
+    pub remote: SocketAddr,
+    pub remote_kind: CandidateKind,
+    pub priority: u64,
+    pub state: PairState,
+    pub nominated: bool,
+    pub selected: bool,
+
+    /// Round trip time of the last successful check or consent request
+    pub rtt: Option<Duration>,
+    /// Time the last check or consent request was sent
+    pub last_request_sent: Option<Instant>,
+    /// Time the last successful response was received
+    pub last_response_received: Option<Instant>,
+
+    pub requests_sent: u64,
+    pub responses_received: u64,
+
+    /// Binding indications sent to keep NAT bindings of the selected pair alive
+    pub keepalives_sent: u64,
+    /// Keepalives skipped because data was sent on the selected pair
+    pub keepalives_suppressed: u64,
+
+    /// Bytes sent using this pair, including STUN messages and reported media
+    pub bytes_sent: u64,
+    /// Bytes received using this pair, including STUN messages and reported media
+    pub bytes_received: u64,
+}
+
+/// Counters of a candidate pair, updated by the agent
+#[derive(Default)]
+pub(crate) struct PairCounters {
+    pub(crate) rtt: Option<Duration>,
+    pub(crate) last_request_sent: Option<Instant>,
+    pub(crate) last_response_received: Option<Instant>,
+    pub(crate) requests_sent: u64,
+    pub(crate) responses_received: u64,
+    pub(crate) keepalives_sent: u64,
+    pub(crate) keepalives_suppressed: u64,
+    pub(crate) bytes_sent: u64,
+    pub(crate) bytes_received: u64,
+}
+
+impl PairCounters {
+    pub(crate) fn request_sent(&mut self, now: Instant) {
+        self.last_request_sent = Some(now);
+        self.requests_sent += 1;
+    }
+
+    pub(crate) fn response_received(&mut self, now: Instant, request_sent_at: Instant) {
+        self.rtt = Some(now - request_sent_at);
+        self.last_response_received = Some(now);
+        self.responses_received += 1;
+    }
+}
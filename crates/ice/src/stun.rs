@@ -0,0 +1,252 @@
+use crate::{IceCredentials, Protocol};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use stun_types::attributes::{
+    ErrorCode, Fingerprint, IceControlled, IceControlling, MessageIntegrity, MessageIntegrityKey,
+    Priority, UseCandidate, Username, XorMappedAddress,
+};
+use stun_types::builder::MessageBuilder;
+use stun_types::header::{Class, Method};
+use stun_types::parse::ParsedMessage;
+
+/// Retransmission timers of STUN requests ([RFC8489](https://datatracker.ietf.org/doc/html/rfc8489#section-6.2.1))
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StunTimeouts {
+    /// Initial retransmission timeout, doubled after each retransmission
+    pub initial_rto: Duration,
+    /// Upper bound for the retransmission timeout
+    pub max_rto: Duration,
+    /// Amount of times a request is sent before the transaction is considered failed
+    pub max_transmissions: u32,
+    /// Timeout of requests over TCP, which are never retransmitted
+    pub tcp_timeout: Duration,
+}
+
+impl Default for StunTimeouts {
+    fn default() -> Self {
+        Self {
+            initial_rto: Duration::from_millis(250),
+            max_rto: Duration::from_secs(3),
+            max_transmissions: 7,
+            tcp_timeout: Duration::from_millis(39500),
+        }
+    }
+}
+
+/// Packet which has to be sent by the user of the agent
+#[derive(Debug, Clone)]
+pub struct Transmit {
+    /// Transport protocol to use. TCP packets must be framed using [`crate::framing::frame`].
+    pub protocol: Protocol,
+    /// Local address the packet must be sent from
+    pub source: SocketAddr,
+    /// Address the packet must be sent to
+    pub destination: SocketAddr,
+    /// Packet content
+    pub data: Vec<u8>,
+}
+
+/// Client STUN transaction which handles retransmissions of a request
+pub(crate) struct StunTransaction {
+    pub(crate) tsx_id: u128,
+    request: Transmit,
+    timeouts: StunTimeouts,
+
+    sent_at: Instant,
+    timeout_at: Instant,
+    rto: Duration,
+    transmissions: u32,
+}
+
+impl StunTransaction {
+    /// Create the transaction and queue the first transmission of the request
+    pub(crate) fn start(
+        now: Instant,
+        tsx_id: u128,
+        request: Transmit,
+        timeouts: StunTimeouts,
+        transmits: &mut VecDeque<Transmit>,
+    ) -> Self {
+        transmits.push_back(request.clone());
+
+        // Requests over reliable transports are not retransmitted
+        let (rto, transmissions) = match request.protocol {
+            Protocol::Udp => (timeouts.initial_rto, 1),
+            Protocol::Tcp => (timeouts.tcp_timeout, timeouts.max_transmissions),
+        };
+
+        Self {
+            tsx_id,
+            request,
+            timeouts,
+            sent_at: now,
+            timeout_at: now + rto,
+            rto,
+            transmissions,
+        }
+    }
+
+    /// Time the request was first sent
+    pub(crate) fn sent_at(&self) -> Instant {
+        self.sent_at
+    }
+
+    pub(crate) fn timeout_at(&self) -> Instant {
+        self.timeout_at
+    }
+
+    /// Retransmit the request if its timeout was reached.
+    ///
+    /// Returns false if the transaction has timed out.
+    pub(crate) fn poll(&mut self, now: Instant, transmits: &mut VecDeque<Transmit>) -> bool {
+        if now < self.timeout_at {
+            return true;
+        }
+
+        if self.transmissions >= self.timeouts.max_transmissions {
+            return false;
+        }
+
+        transmits.push_back(self.request.clone());
+
+        self.transmissions += 1;
+        self.rto = (self.rto * 2).min(self.timeouts.max_rto);
+        self.timeout_at = now + self.rto;
+
+        true
+    }
+}
+
+/// Attributes of a binding request which are relevant to ICE
+pub(crate) struct BindingRequest {
+    pub(crate) priority: u32,
+    pub(crate) use_candidate: bool,
+    pub(crate) controlling: Option<u64>,
+    pub(crate) controlled: Option<u64>,
+}
+
+pub(crate) fn make_binding_request(
+    tsx_id: u128,
+    local_credentials: &IceCredentials,
+    remote_credentials: &IceCredentials,
+    is_controlling: bool,
+    tie_breaker: u64,
+    priority: u32,
+    use_candidate: bool,
+) -> Vec<u8> {
+    let mut msg = MessageBuilder::new(Class::Request, Method::Binding, tsx_id);
+
+    let username = format!("{}:{}", remote_credentials.ufrag, local_credentials.ufrag);
+    msg.add_attr(&Username::new(&username)).unwrap();
+    msg.add_attr(&Priority(priority)).unwrap();
+
+    if is_controlling {
+        msg.add_attr(&IceControlling(tie_breaker)).unwrap();
+    } else {
+        msg.add_attr(&IceControlled(tie_breaker)).unwrap();
+    }
+
+    if use_candidate {
+        msg.add_attr(&UseCandidate).unwrap();
+    }
+
+    msg.add_attr_with(
+        &MessageIntegrity::default(),
+        &MessageIntegrityKey::new_short_term(&remote_credentials.pwd),
+    )
+    .unwrap();
+    msg.add_attr(&Fingerprint).unwrap();
+
+    msg.finish()
+}
+
+/// Binding indication used as keepalive, which is not authenticated and not responded to
+pub(crate) fn make_binding_indication(tsx_id: u128) -> Vec<u8> {
+    let mut msg = MessageBuilder::new(Class::Indication, Method::Binding, tsx_id);
+    msg.add_attr(&Fingerprint).unwrap();
+    msg.finish()
+}
+
+pub(crate) fn make_stun_server_binding_request(tsx_id: u128) -> Vec<u8> {
+    let mut msg = MessageBuilder::new(Class::Request, Method::Binding, tsx_id);
+    msg.add_attr(&Fingerprint).unwrap();
+    msg.finish()
+}
+
+pub(crate) fn make_success_response(
+    tsx_id: u128,
+    local_credentials: &IceCredentials,
+    source: SocketAddr,
+) -> Vec<u8> {
+    let mut msg = MessageBuilder::new(Class::Success, Method::Binding, tsx_id);
+    msg.add_attr(&XorMappedAddress(source)).unwrap();
+    msg.add_attr_with(
+        &MessageIntegrity::default(),
+        &MessageIntegrityKey::new_short_term(&local_credentials.pwd),
+    )
+    .unwrap();
+    msg.add_attr(&Fingerprint).unwrap();
+    msg.finish()
+}
+
+pub(crate) fn make_role_conflict_response(
+    tsx_id: u128,
+    local_credentials: &IceCredentials,
+) -> Vec<u8> {
+    let mut msg = MessageBuilder::new(Class::Error, Method::Binding, tsx_id);
+    msg.add_attr(&ErrorCode {
+        number: 487,
+        reason: "Role Conflict",
+    })
+    .unwrap();
+    msg.add_attr_with(
+        &MessageIntegrity::default(),
+        &MessageIntegrityKey::new_short_term(&local_credentials.pwd),
+    )
+    .unwrap();
+    msg.add_attr(&Fingerprint).unwrap();
+    msg.finish()
+}
+
+/// Verify the fingerprint if present and the message integrity using the given password
+pub(crate) fn verify_integrity(msg: &mut ParsedMessage, pwd: &str) -> bool {
+    if let Some(Err(_)) = msg.get_attr::<Fingerprint>() {
+        return false;
+    }
+
+    let key = MessageIntegrityKey::new_short_term(pwd);
+
+    matches!(msg.get_attr_with::<MessageIntegrity>(&key), Some(Ok(_)))
+}
+
+/// Verify an incoming binding request and extract its attributes
+pub(crate) fn parse_binding_request(
+    msg: &mut ParsedMessage,
+    local_credentials: &IceCredentials,
+) -> Option<BindingRequest> {
+    match msg.get_attr::<Username>() {
+        Some(Ok(username)) => {
+            if username.0.split(':').next() != Some(local_credentials.ufrag.as_str()) {
+                return None;
+            }
+        }
+        _ => return None,
+    }
+
+    if !verify_integrity(msg, &local_credentials.pwd) {
+        return None;
+    }
+
+    let priority = msg.get_attr::<Priority>()?.ok()?.0;
+    let use_candidate = msg.get_attr::<UseCandidate>().is_some();
+    let controlling = msg.get_attr::<IceControlling>().and_then(Result::ok);
+    let controlled = msg.get_attr::<IceControlled>().and_then(Result::ok);
+
+    Some(BindingRequest {
+        priority,
+        use_candidate,
+        controlling: controlling.map(|attr| attr.0),
+        controlled: controlled.map(|attr| attr.0),
+    })
+}
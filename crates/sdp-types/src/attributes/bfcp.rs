@@ -0,0 +1,154 @@
+//! BFCP attributes (`a=floorctrl:...`, `a=floorid:...`)
+//!
+//! The conference and user ids (`a=confid`, `a=userid`) have no further structure and are stored
+//! as strings in [`MediaScope`](crate::msg::MediaScope).
+
+use crate::not_whitespace;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::IResult;
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::{multispace0, multispace1};
+use nom::combinator::map;
+use nom::multi::{many0, separated_list1};
+use nom::sequence::{preceded, tuple};
+use std::fmt;
+
+/// Role of an endpoint in floor control
+///
+/// Media Level attribute, contains a list of roles the endpoint is able to perform
+///
+/// [RFC8856](https://www.rfc-editor.org/rfc/rfc8856.html#section-5)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloorControl {
+    /// Floor control client (`c-only`)
+    ClientOnly,
+
+    /// Floor control server (`s-only`)
+    ServerOnly,
+
+    /// Either client or server (`c-s`)
+    ClientServer,
+}
+
+impl FloorControl {
+    pub fn parse(i: &str) -> IResult<&str, Self> {
+        alt((
+            map(tag("c-only"), |_| FloorControl::ClientOnly),
+            map(tag("s-only"), |_| FloorControl::ServerOnly),
+            map(tag("c-s"), |_| FloorControl::ClientServer),
+        ))(i)
+    }
+
+    /// Parse the value of a `a=floorctrl` attribute
+    pub fn parse_list(i: &str) -> IResult<&str, Vec<Self>> {
+        preceded(multispace0, separated_list1(multispace1, Self::parse))(i)
+    }
+}
+
+impl fmt::Display for FloorControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FloorControl::ClientOnly => f.write_str("c-only"),
+            FloorControl::ServerOnly => f.write_str("s-only"),
+            FloorControl::ClientServer => f.write_str("c-s"),
+        }
+    }
+}
+
+/// Floor and the media streams it controls
+///
+/// Media Level attribute, may appear multiple times
+///
+/// [RFC8856](https://www.rfc-editor.org/rfc/rfc8856.html#section-8)
+#[derive(Debug, Clone)]
+pub struct FloorId {
+    pub id: BytesStr,
+
+    /// Labels (`a=label`) of the media streams associated with the floor
+    pub media_streams: Vec<BytesStr>,
+}
+
+impl FloorId {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("floorid:"),
+            map(
+                tuple((
+                    take_while1(not_whitespace),
+                    many0(preceded(
+                        multispace1,
+                        // m-stream is used by RFC4583 which was obsoleted by RFC8856
+                        preceded(
+                            alt((tag("mstrm:"), tag("m-stream:"))),
+                            separated_list1(multispace1, take_while1(not_whitespace)),
+                        ),
+                    )),
+                )),
+                |(id, media_streams)| FloorId {
+                    id: BytesStr::from_parse(src, id),
+                    media_streams: media_streams
+                        .into_iter()
+                        .flatten()
+                        .map(|label| BytesStr::from_parse(src, label))
+                        .collect(),
+                },
+            ),
+        )(i)
+    }
+}
+
+impl fmt::Display for FloorId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=floorid:{}", self.id)?;
+
+        if let Some((first, rest)) = self.media_streams.split_first() {
+            write!(f, " mstrm:{}", first)?;
+
+            for label in rest {
+                write!(f, " {}", label)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn floorctrl() {
+        let (rem, roles) = FloorControl::parse_list("c-only s-only").unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(roles, [FloorControl::ClientOnly, FloorControl::ServerOnly]);
+    }
+
+    #[test]
+    fn floorid() {
+        let input = BytesStr::from_static("floorid:1 mstrm:10 11");
+
+        let (rem, floorid) = FloorId::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(floorid.id, "1");
+        assert_eq!(floorid.media_streams, ["10", "11"]);
+        assert_eq!(floorid.to_string(), "a=floorid:1 mstrm:10 11");
+    }
+
+    #[test]
+    fn floorid_legacy() {
+        let input = BytesStr::from_static("floorid:2 m-stream:12");
+
+        let (rem, floorid) = FloorId::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(floorid.media_streams, ["12"]);
+        assert_eq!(floorid.to_string(), "a=floorid:2 mstrm:12");
+    }
+}
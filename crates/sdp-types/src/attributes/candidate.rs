@@ -24,7 +24,7 @@ pub enum UntaggedAddress {
 }
 
 impl UntaggedAddress {
-    fn parse(src: &Bytes) -> impl FnMut(&str) -> IResult<&str, Self> + '_ {
+    pub(crate) fn parse(src: &Bytes) -> impl FnMut(&str) -> IResult<&str, Self> + '_ {
         move |i| {
             map(take_while(probe_host6), |address| {
                 if let Ok(address) = IpAddr::from_str(address) {
@@ -98,6 +98,26 @@ pub struct Candidate {
 }
 
 impl Candidate {
+    /// Returns the value of an extension attribute (e.g. `tcptype`) stored in `unknown`
+    pub fn extension(&self, key: &str) -> Option<&BytesStr> {
+        self.unknown
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    /// Type of TCP candidates (`active`, `passive` or `so`)
+    ///
+    /// [RFC6544](https://www.rfc-editor.org/rfc/rfc6544.html#section-4.5)
+    pub fn tcp_type(&self) -> Option<&BytesStr> {
+        self.extension("tcptype")
+    }
+
+    /// ICE generation the candidate belongs to, used by some WebRTC implementations
+    pub fn generation(&self) -> Option<u32> {
+        self.extension("generation")?.parse().ok()
+    }
+
     pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
         map_res(
                 preceded(
@@ -229,10 +249,28 @@ mod test {
                 BytesStr::from_static("active")
             )
         );
+        assert_eq!(candidate.tcp_type().unwrap(), "active");
+        assert_eq!(candidate.generation(), None);
 
         assert!(rem.is_empty());
     }
 
+    #[test]
+    fn candidate_generation() {
+        let input = BytesStr::from_static(
+            "candidate:1 1 udp 2122260223 10.0.0.1 54321 typ host generation 0",
+        );
+
+        let (rem, candidate) = Candidate::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(candidate.generation(), Some(0));
+        assert_eq!(
+            candidate.to_string(),
+            "a=candidate:1 1 udp 2122260223 10.0.0.1 54321 typ host generation 0"
+        );
+    }
+
     #[test]
     fn candidate_print() {
         let candidate = Candidate {
@@ -0,0 +1,558 @@
+//! Capability negotiation attributes (`a=acap`, `a=tcap`, `a=pcfg`, `a=acfg`)
+//!
+//! [RFC5939](https://www.rfc-editor.org/rfc/rfc5939.html)
+
+use super::UnknownAttribute;
+use crate::media::TransportProtocol;
+use crate::not_whitespace;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::IResult;
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::{char, digit1, multispace1};
+use nom::combinator::{map, map_res, opt, rest, value};
+use nom::multi::{many0, separated_list1};
+use nom::sequence::{delimited, preceded, separated_pair, terminated, tuple};
+use std::fmt;
+use std::str::FromStr;
+
+/// Attribute which may be used in a potential configuration
+///
+/// Session and Media Level attribute, may appear multiple times
+///
+/// [RFC5939](https://www.rfc-editor.org/rfc/rfc5939.html#section-3.4.1)
+#[derive(Debug, Clone)]
+pub struct AttributeCapability {
+    /// Capability number referenced by configurations
+    pub number: u32,
+
+    /// The attribute, without the `a=` prefix
+    pub attribute: UnknownAttribute,
+}
+
+impl AttributeCapability {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("acap:"),
+            map(
+                separated_pair(number, multispace1, rest),
+                |(number, attribute)| AttributeCapability {
+                    number,
+                    attribute: UnknownAttribute::parse(src, attribute.trim()),
+                },
+            ),
+        )(i)
+    }
+}
+
+impl fmt::Display for AttributeCapability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=acap:{} {}", self.number, self.attribute.name)?;
+
+        if let Some(value) = &self.attribute.value {
+            write!(f, ":{}", value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Transport protocols which may be used in a potential configuration
+///
+/// The protocols are numbered consecutively starting at `number`.
+///
+/// Session and Media Level attribute, may appear multiple times
+///
+/// [RFC5939](https://www.rfc-editor.org/rfc/rfc5939.html#section-3.4.2)
+#[derive(Debug, Clone)]
+pub struct TransportCapability {
+    /// Capability number of the first protocol
+    pub number: u32,
+
+    pub protos: Vec<TransportProtocol>,
+}
+
+impl TransportCapability {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("tcap:"),
+            map(
+                separated_pair(
+                    number,
+                    multispace1,
+                    separated_list1(multispace1, TransportProtocol::parse(src)),
+                ),
+                |(number, protos)| TransportCapability { number, protos },
+            ),
+        )(i)
+    }
+
+    /// Returns the protocol with the given capability number
+    pub fn get(&self, number: u32) -> Option<&TransportProtocol> {
+        let index = number.checked_sub(self.number)?;
+
+        self.protos.get(usize::try_from(index).ok()?)
+    }
+}
+
+impl fmt::Display for TransportCapability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=tcap:{}", self.number)?;
+
+        for proto in &self.protos {
+            write!(f, " {}", proto)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Existing attributes replaced by a configuration (`-m`, `-s`, `-ms`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteAttributes {
+    Media,
+    Session,
+    MediaAndSession,
+}
+
+impl DeleteAttributes {
+    fn parse(i: &str) -> IResult<&str, Self> {
+        preceded(
+            char('-'),
+            alt((
+                value(DeleteAttributes::MediaAndSession, tag("ms")),
+                value(DeleteAttributes::Media, char('m')),
+                value(DeleteAttributes::Session, char('s')),
+            )),
+        )(i)
+    }
+}
+
+impl fmt::Display for DeleteAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeleteAttributes::Media => f.write_str("-m"),
+            DeleteAttributes::Session => f.write_str("-s"),
+            DeleteAttributes::MediaAndSession => f.write_str("-ms"),
+        }
+    }
+}
+
+/// Set of attribute capabilities used together (e.g. `1,2,[3]`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttributeCapabilitySet {
+    /// Capabilities which must be used
+    pub mandatory: Vec<u32>,
+
+    /// Groups of capabilities which may be used, each group is used as a whole
+    pub optional: Vec<Vec<u32>>,
+}
+
+enum CapabilityItem {
+    Mandatory(u32),
+    Optional(Vec<u32>),
+}
+
+impl AttributeCapabilitySet {
+    fn parse(i: &str) -> IResult<&str, Self> {
+        map(
+            separated_list1(
+                char(','),
+                alt((
+                    map(number, CapabilityItem::Mandatory),
+                    map(
+                        delimited(char('['), separated_list1(char(','), number), char(']')),
+                        CapabilityItem::Optional,
+                    ),
+                )),
+            ),
+            |items| {
+                let mut set = AttributeCapabilitySet::default();
+
+                for item in items {
+                    match item {
+                        CapabilityItem::Mandatory(number) => set.mandatory.push(number),
+                        CapabilityItem::Optional(group) => set.optional.push(group),
+                    }
+                }
+
+                set
+            },
+        )(i)
+    }
+}
+
+impl fmt::Display for AttributeCapabilitySet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mandatory = self.mandatory.iter().map(|n| n.to_string());
+        let optional = self.optional.iter().map(|group| {
+            let group: Vec<_> = group.iter().map(|n| n.to_string()).collect();
+            format!("[{}]", group.join(","))
+        });
+
+        let items: Vec<_> = mandatory.chain(optional).collect();
+
+        f.write_str(&items.join(","))
+    }
+}
+
+/// Attribute part of a potential configuration (`a=-m:1,2|3`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeConfig {
+    pub delete: Option<DeleteAttributes>,
+
+    /// Alternative sets, ordered by preference
+    pub alternatives: Vec<AttributeCapabilitySet>,
+}
+
+impl AttributeConfig {
+    fn parse(i: &str) -> IResult<&str, Self> {
+        map(
+            preceded(
+                tag("a="),
+                tuple((
+                    opt(terminated(DeleteAttributes::parse, char(':'))),
+                    separated_list1(char('|'), AttributeCapabilitySet::parse),
+                )),
+            ),
+            |(delete, alternatives)| AttributeConfig {
+                delete,
+                alternatives,
+            },
+        )(i)
+    }
+}
+
+impl fmt::Display for AttributeConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a=")?;
+
+        if let Some(delete) = self.delete {
+            write!(f, "{}:", delete)?;
+        }
+
+        for (i, set) in self.alternatives.iter().enumerate() {
+            if i > 0 {
+                f.write_str("|")?;
+            }
+
+            write!(f, "{}", set)?;
+        }
+
+        Ok(())
+    }
+}
+
+enum ConfigPart<'i> {
+    Attributes(AttributeConfig),
+    Transports(Vec<u32>),
+    Extension(&'i str),
+}
+
+fn config_part(i: &str) -> IResult<&str, ConfigPart<'_>> {
+    alt((
+        map(AttributeConfig::parse, ConfigPart::Attributes),
+        map(
+            preceded(tag("t="), separated_list1(char('|'), number)),
+            ConfigPart::Transports,
+        ),
+        map(take_while1(not_whitespace), ConfigPart::Extension),
+    ))(i)
+}
+
+/// Potential configuration offered for a media description
+///
+/// Media Level attribute, may appear multiple times
+///
+/// [RFC5939](https://www.rfc-editor.org/rfc/rfc5939.html#section-3.5.1)
+#[derive(Debug, Clone)]
+pub struct PotentialConfig {
+    /// Configuration number, lower numbers are preferred
+    pub number: u32,
+
+    pub attributes: Option<AttributeConfig>,
+
+    /// Alternative transport capabilities, ordered by preference
+    pub transports: Vec<u32>,
+
+    /// Extension configurations (e.g. `+x=1`), kept as is
+    pub extensions: Vec<BytesStr>,
+}
+
+impl PotentialConfig {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("pcfg:"),
+            map(
+                tuple((number, many0(preceded(multispace1, config_part)))),
+                |(number, parts)| {
+                    let mut config = PotentialConfig {
+                        number,
+                        attributes: None,
+                        transports: vec![],
+                        extensions: vec![],
+                    };
+
+                    for part in parts {
+                        match part {
+                            ConfigPart::Attributes(attributes) => {
+                                config.attributes = Some(attributes)
+                            }
+                            ConfigPart::Transports(transports) => config.transports = transports,
+                            ConfigPart::Extension(extension) => {
+                                config.extensions.push(BytesStr::from_parse(src, extension))
+                            }
+                        }
+                    }
+
+                    config
+                },
+            ),
+        )(i)
+    }
+}
+
+impl fmt::Display for PotentialConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=pcfg:{}", self.number)?;
+
+        if !self.transports.is_empty() {
+            let transports: Vec<_> = self.transports.iter().map(|n| n.to_string()).collect();
+            write!(f, " t={}", transports.join("|"))?;
+        }
+
+        if let Some(attributes) = &self.attributes {
+            write!(f, " {}", attributes)?;
+        }
+
+        for extension in &self.extensions {
+            write!(f, " {}", extension)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Potential configuration selected by the answerer
+///
+/// Media Level attribute
+///
+/// [RFC5939](https://www.rfc-editor.org/rfc/rfc5939.html#section-3.5.2)
+#[derive(Debug, Clone)]
+pub struct SelectedConfig {
+    /// Number of the selected potential configuration
+    pub number: u32,
+
+    /// Selected attribute capabilities, `delete` is repeated from the potential configuration
+    pub attributes: Option<AttributeConfig>,
+
+    /// Selected transport capability
+    pub transport: Option<u32>,
+
+    /// Selected extension configurations, kept as is
+    pub extensions: Vec<BytesStr>,
+}
+
+impl SelectedConfig {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("acfg:"),
+            map(
+                tuple((number, many0(preceded(multispace1, config_part)))),
+                |(number, parts)| {
+                    let mut config = SelectedConfig {
+                        number,
+                        attributes: None,
+                        transport: None,
+                        extensions: vec![],
+                    };
+
+                    for part in parts {
+                        match part {
+                            ConfigPart::Attributes(attributes) => {
+                                config.attributes = Some(attributes)
+                            }
+                            ConfigPart::Transports(transports) => {
+                                config.transport = transports.first().copied()
+                            }
+                            ConfigPart::Extension(extension) => {
+                                config.extensions.push(BytesStr::from_parse(src, extension))
+                            }
+                        }
+                    }
+
+                    config
+                },
+            ),
+        )(i)
+    }
+
+    /// Create the selection of a potential configuration, choosing its most preferred
+    /// transport and attribute alternative including all optional capabilities
+    pub fn select(config: &PotentialConfig) -> Self {
+        let attributes = config.attributes.as_ref().and_then(|attributes| {
+            let set = attributes.alternatives.first()?;
+
+            Some(AttributeConfig {
+                delete: attributes.delete,
+                alternatives: vec![AttributeCapabilitySet {
+                    mandatory: set
+                        .mandatory
+                        .iter()
+                        .chain(set.optional.iter().flatten())
+                        .copied()
+                        .collect(),
+                    optional: vec![],
+                }],
+            })
+        });
+
+        SelectedConfig {
+            number: config.number,
+            attributes,
+            transport: config.transports.first().copied(),
+            extensions: vec![],
+        }
+    }
+}
+
+impl fmt::Display for SelectedConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=acfg:{}", self.number)?;
+
+        if let Some(transport) = self.transport {
+            write!(f, " t={}", transport)?;
+        }
+
+        if let Some(attributes) = &self.attributes {
+            write!(f, " {}", attributes)?;
+        }
+
+        for extension in &self.extensions {
+            write!(f, " {}", extension)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn number(i: &str) -> IResult<&str, u32> {
+    map_res(digit1, FromStr::from_str)(i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn acap() {
+        let input = BytesStr::from_static(
+            "acap:1 crypto:1 AES_CM_128_HMAC_SHA1_80 inline:WVNfX19zZW1jdGwgKCkgewkyMjA7fQp9CnVubGVz|2^20|1:4",
+        );
+
+        let (rem, acap) = AttributeCapability::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(acap.number, 1);
+        assert_eq!(acap.attribute.name, "crypto");
+        assert!(acap
+            .attribute
+            .value
+            .as_ref()
+            .unwrap()
+            .starts_with("1 AES_CM_128_HMAC_SHA1_80"));
+        assert_eq!(acap.to_string(), format!("a={}", input));
+    }
+
+    #[test]
+    fn tcap() {
+        let input = BytesStr::from_static("tcap:1 RTP/SAVPF RTP/SAVP");
+
+        let (rem, tcap) = TransportCapability::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(
+            tcap.protos,
+            [TransportProtocol::RtpSavpf, TransportProtocol::RtpSavp]
+        );
+        assert_eq!(tcap.get(0), None);
+        assert_eq!(tcap.get(2), Some(&TransportProtocol::RtpSavp));
+        assert_eq!(tcap.get(3), None);
+        assert_eq!(tcap.to_string(), "a=tcap:1 RTP/SAVPF RTP/SAVP");
+    }
+
+    #[test]
+    fn pcfg() {
+        let input = BytesStr::from_static("pcfg:1 t=1|2 a=-m:1,[2,3]|4 +x=5");
+
+        let (rem, pcfg) = PotentialConfig::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(pcfg.number, 1);
+        assert_eq!(pcfg.transports, [1, 2]);
+        assert_eq!(pcfg.extensions, ["+x=5"]);
+
+        let attributes = pcfg.attributes.as_ref().unwrap();
+        assert_eq!(attributes.delete, Some(DeleteAttributes::Media));
+        assert_eq!(
+            attributes.alternatives,
+            [
+                AttributeCapabilitySet {
+                    mandatory: vec![1],
+                    optional: vec![vec![2, 3]],
+                },
+                AttributeCapabilitySet {
+                    mandatory: vec![4],
+                    optional: vec![],
+                }
+            ]
+        );
+
+        assert_eq!(pcfg.to_string(), format!("a={}", input));
+    }
+
+    #[test]
+    fn pcfg_transport_only() {
+        let input = BytesStr::from_static("pcfg:2 t=2");
+
+        let (rem, pcfg) = PotentialConfig::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(pcfg.transports, [2]);
+        assert!(pcfg.attributes.is_none());
+    }
+
+    #[test]
+    fn acfg() {
+        let input = BytesStr::from_static("acfg:1 t=1 a=1,3");
+
+        let (rem, acfg) = SelectedConfig::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(acfg.number, 1);
+        assert_eq!(acfg.transport, Some(1));
+        assert_eq!(
+            acfg.attributes.as_ref().unwrap().alternatives[0].mandatory,
+            [1, 3]
+        );
+        assert_eq!(acfg.to_string(), format!("a={}", input));
+    }
+
+    #[test]
+    fn acfg_select() {
+        let input = BytesStr::from_static("pcfg:1 t=1|2 a=1,[2]|3");
+
+        let (_, pcfg) = PotentialConfig::parse(input.as_ref(), &input).unwrap();
+
+        assert_eq!(
+            SelectedConfig::select(&pcfg).to_string(),
+            "a=acfg:1 t=1 a=1,2"
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Content attribute (`a=content:...`)
+
+use crate::token;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::IResult;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::char;
+use nom::combinator::map;
+use nom::multi::separated_list1;
+use nom::sequence::preceded;
+use std::fmt;
+
+/// Usage of the media stream's content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentValue {
+    /// Presentation slides
+    Slides,
+    /// Image of the current speaker
+    Speaker,
+    /// Sign language
+    Sl,
+    /// Main media stream, e.g. the camera video
+    Main,
+    /// Alternative media stream
+    Alt,
+    Other(BytesStr),
+}
+
+impl ContentValue {
+    fn from_str(src: &Bytes, value: &str) -> Self {
+        match value {
+            "slides" => ContentValue::Slides,
+            "speaker" => ContentValue::Speaker,
+            "sl" => ContentValue::Sl,
+            "main" => ContentValue::Main,
+            "alt" => ContentValue::Alt,
+            _ => ContentValue::Other(BytesStr::from_parse(src, value)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ContentValue::Slides => "slides",
+            ContentValue::Speaker => "speaker",
+            ContentValue::Sl => "sl",
+            ContentValue::Main => "main",
+            ContentValue::Alt => "alt",
+            ContentValue::Other(other) => other,
+        }
+    }
+}
+
+/// Describes the content of a media stream, to distinguish e.g. presentation from camera video
+///
+/// Media Level attribute
+///
+/// [RFC4796](https://www.rfc-editor.org/rfc/rfc4796.html#section-5)
+#[derive(Debug, Clone)]
+pub struct Content {
+    pub values: Vec<ContentValue>,
+}
+
+impl Content {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("content:"),
+            map(
+                separated_list1(char(','), take_while1(token)),
+                |values: Vec<&str>| Content {
+                    values: values
+                        .into_iter()
+                        .map(|value| ContentValue::from_str(src, value))
+                        .collect(),
+                },
+            ),
+        )(i)
+    }
+
+    pub fn contains(&self, value: &ContentValue) -> bool {
+        self.values.contains(value)
+    }
+}
+
+impl fmt::Display for Content {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a=content:")?;
+
+        for (i, value) in self.values.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+
+            f.write_str(value.as_str())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn content() {
+        let input = BytesStr::from_static("content:slides,speaker,g.foo");
+
+        let (rem, content) = Content::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(
+            content.values,
+            [
+                ContentValue::Slides,
+                ContentValue::Speaker,
+                ContentValue::Other("g.foo".into())
+            ]
+        );
+        assert!(content.contains(&ContentValue::Slides));
+        assert!(!content.contains(&ContentValue::Main));
+    }
+
+    #[test]
+    fn content_print() {
+        let content = Content {
+            values: vec![ContentValue::Main, ContentValue::Alt],
+        };
+
+        assert_eq!(content.to_string(), "a=content:main,alt");
+    }
+}
@@ -0,0 +1,251 @@
+//! SDES crypto attribute (`a=crypto:...`)
+
+use crate::not_whitespace;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::{ws, IResult};
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::digit1;
+use nom::combinator::{map_res, rest};
+use nom::sequence::{preceded, tuple};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, thiserror::Error)]
+#[error("failed to parse crypto key params")]
+pub struct InvalidCryptoParam;
+
+/// Master key of a [`Crypto`] attribute (`inline:<key||salt>[|lifetime][|MKI:length]`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CryptoKey {
+    /// Base64 encoded concatenated master key and salt
+    pub key_salt: BytesStr,
+
+    /// Maximum number of packets protected with this key
+    pub lifetime: Option<u64>,
+
+    /// Master key identifier value and its length in bytes
+    pub mki: Option<(u32, u8)>,
+}
+
+impl CryptoKey {
+    /// Create a key from the concatenated master key and salt
+    pub fn new(key_salt: &[u8]) -> Self {
+        Self {
+            key_salt: STANDARD.encode(key_salt).into(),
+            lifetime: None,
+            mki: None,
+        }
+    }
+
+    /// Decode the concatenated master key and salt
+    pub fn decode_key_salt(&self) -> Result<Vec<u8>, base64::DecodeError> {
+        STANDARD.decode(self.key_salt.as_bytes())
+    }
+
+    fn parse(src: &Bytes, key_param: &str) -> Result<Self, InvalidCryptoParam> {
+        let key_info = key_param
+            .strip_prefix("inline:")
+            .ok_or(InvalidCryptoParam)?;
+
+        let mut parts = key_info.split('|');
+
+        let key_salt = parts
+            .next()
+            .filter(|k| !k.is_empty())
+            .ok_or(InvalidCryptoParam)?;
+
+        let mut lifetime = None;
+        let mut mki = None;
+
+        for part in parts {
+            if let Some((value, length)) = part.split_once(':') {
+                mki = Some((
+                    value.parse().map_err(|_| InvalidCryptoParam)?,
+                    length.parse().map_err(|_| InvalidCryptoParam)?,
+                ));
+            } else if let Some(exponent) = part.strip_prefix("2^") {
+                let exponent: u32 = exponent.parse().map_err(|_| InvalidCryptoParam)?;
+                lifetime = Some(1u64.checked_shl(exponent).ok_or(InvalidCryptoParam)?);
+            } else {
+                lifetime = Some(part.parse().map_err(|_| InvalidCryptoParam)?);
+            }
+        }
+
+        Ok(Self {
+            key_salt: BytesStr::from_parse(src, key_salt),
+            lifetime,
+            mki,
+        })
+    }
+}
+
+impl fmt::Display for CryptoKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "inline:{}", self.key_salt)?;
+
+        match self.lifetime {
+            Some(lifetime) if lifetime.is_power_of_two() => {
+                write!(f, "|2^{}", lifetime.trailing_zeros())?
+            }
+            Some(lifetime) => write!(f, "|{}", lifetime)?,
+            None => {}
+        }
+
+        if let Some((value, length)) = self.mki {
+            write!(f, "|{}:{}", value, length)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// SDES cryptographic parameters for SRTP
+///
+/// Media Level attribute, may appear multiple times with different tags
+///
+/// [RFC4568](https://www.rfc-editor.org/rfc/rfc4568.html#section-9.1)
+#[derive(Debug, Clone)]
+pub struct Crypto {
+    /// Identifies the attribute when used in the answer
+    pub tag: u32,
+
+    /// Crypto suite e.g. `AES_CM_128_HMAC_SHA1_80`
+    pub suite: BytesStr,
+
+    /// One or more master keys
+    pub keys: Vec<CryptoKey>,
+
+    /// Session parameters, e.g. `KDR=23` or `UNENCRYPTED_SRTCP`
+    pub session_params: Vec<BytesStr>,
+}
+
+impl Crypto {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        map_res(
+            preceded(
+                tag("crypto:"),
+                tuple((
+                    // tag
+                    map_res(digit1, FromStr::from_str),
+                    ws((
+                        // crypto suite
+                        take_while1(not_whitespace),
+                        // key params
+                        take_while1(not_whitespace),
+                        // session params
+                        rest,
+                    )),
+                )),
+            ),
+            |(tag, (suite, key_params, session_params))| -> Result<Self, InvalidCryptoParam> {
+                let keys = key_params
+                    .split(';')
+                    .map(|key_param| CryptoKey::parse(src, key_param))
+                    .collect::<Result<_, _>>()?;
+
+                Ok(Self {
+                    tag,
+                    suite: BytesStr::from_parse(src, suite),
+                    keys,
+                    session_params: session_params
+                        .split_ascii_whitespace()
+                        .map(|param| BytesStr::from_parse(src, param))
+                        .collect(),
+                })
+            },
+        )(i)
+    }
+}
+
+impl fmt::Display for Crypto {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=crypto:{} {} ", self.tag, self.suite)?;
+
+        for (i, key) in self.keys.iter().enumerate() {
+            if i > 0 {
+                f.write_str(";")?;
+            }
+
+            write!(f, "{}", key)?;
+        }
+
+        for param in &self.session_params {
+            write!(f, " {}", param)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crypto() {
+        let input = BytesStr::from_static(
+            "crypto:1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^20|1:4 KDR=1 UNENCRYPTED_SRTCP",
+        );
+
+        let (rem, crypto) = Crypto::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(crypto.tag, 1);
+        assert_eq!(crypto.suite, "AES_CM_128_HMAC_SHA1_80");
+        assert_eq!(
+            crypto.keys,
+            [CryptoKey {
+                key_salt: "PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR".into(),
+                lifetime: Some(1 << 20),
+                mki: Some((1, 4)),
+            }]
+        );
+        assert_eq!(crypto.keys[0].decode_key_salt().unwrap().len(), 30);
+        assert_eq!(crypto.session_params, ["KDR=1", "UNENCRYPTED_SRTCP"]);
+    }
+
+    #[test]
+    fn crypto_multiple_keys() {
+        let input = BytesStr::from_static(
+            "crypto:2 AES_CM_128_HMAC_SHA1_32 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|1048575;inline:e4ZDklLIyVmidDRDW/JsMoX4GJ6zSLqPyVhy7AsL",
+        );
+
+        let (rem, crypto) = Crypto::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(crypto.keys.len(), 2);
+        assert_eq!(crypto.keys[0].lifetime, Some(1048575));
+        assert_eq!(crypto.keys[1].lifetime, None);
+        assert!(crypto.session_params.is_empty());
+    }
+
+    #[test]
+    fn crypto_invalid() {
+        let input = BytesStr::from_static("crypto:1 AES_CM_128_HMAC_SHA1_80 uri:https://x");
+
+        assert!(Crypto::parse(input.as_ref(), &input).is_err());
+    }
+
+    #[test]
+    fn crypto_print() {
+        let mut key = CryptoKey::new(&[0; 30]);
+        key.lifetime = Some(1 << 31);
+        key.mki = Some((1, 4));
+
+        let crypto = Crypto {
+            tag: 1,
+            suite: "AES_CM_128_HMAC_SHA1_80".into(),
+            keys: vec![key],
+            session_params: vec!["UNENCRYPTED_SRTP".into()],
+        };
+
+        assert_eq!(
+            crypto.to_string(),
+            "a=crypto:1 AES_CM_128_HMAC_SHA1_80 inline:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA|2^31|1:4 UNENCRYPTED_SRTP"
+        );
+    }
+}
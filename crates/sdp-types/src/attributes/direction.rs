@@ -11,7 +11,7 @@ use std::fmt;
 /// > If not specified at all `sendrecv` is assumed by default
 ///
 /// [RFC8866](https://www.rfc-editor.org/rfc/rfc8866.html#section-6.7)
-#[derive(Default, Debug, Copy, Clone)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Direction {
     /// Send and receive media data
     #[default]
@@ -28,6 +28,26 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// Create the direction from the ability to send and receive
+    pub fn from_flags(send: bool, recv: bool) -> Self {
+        match (send, recv) {
+            (true, true) => Direction::SendRecv,
+            (false, true) => Direction::RecvOnly,
+            (true, false) => Direction::SendOnly,
+            (false, false) => Direction::Inactive,
+        }
+    }
+
+    /// Returns if media is sent
+    pub fn sends(self) -> bool {
+        matches!(self, Direction::SendRecv | Direction::SendOnly)
+    }
+
+    /// Returns if media is received
+    pub fn receives(self) -> bool {
+        matches!(self, Direction::SendRecv | Direction::RecvOnly)
+    }
+
     pub fn flipped(self) -> Self {
         match self {
             Direction::SendRecv => self,
@@ -0,0 +1,292 @@
+//! RTP header extension attribute (`a=extmap:...`)
+
+use crate::attributes::direction::Direction;
+use crate::not_whitespace;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::IResult;
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::{char, digit1, multispace1};
+use nom::combinator::{map, map_res, opt, rest};
+use nom::sequence::{preceded, tuple};
+use std::fmt;
+use std::str::FromStr;
+
+/// URI which marks the wrapped header extension as encrypted
+/// ([RFC6904](https://www.rfc-editor.org/rfc/rfc6904.html#section-4))
+pub const ENCRYPT_URI: &str = "urn:ietf:params:rtp-hdrext:encrypt";
+
+/// [RFC6464](https://www.rfc-editor.org/rfc/rfc6464.html)
+pub const SSRC_AUDIO_LEVEL_URI: &str = "urn:ietf:params:rtp-hdrext:ssrc-audio-level";
+
+/// [RFC6465](https://www.rfc-editor.org/rfc/rfc6465.html)
+pub const CSRC_AUDIO_LEVEL_URI: &str = "urn:ietf:params:rtp-hdrext:csrc-audio-level";
+
+/// [RFC5450](https://www.rfc-editor.org/rfc/rfc5450.html)
+pub const TOFFSET_URI: &str = "urn:ietf:params:rtp-hdrext:toffset";
+
+/// [abs-send-time](https://webrtc.googlesource.com/src/+/refs/heads/main/docs/native-code/rtp-hdrext/abs-send-time)
+pub const ABS_SEND_TIME_URI: &str = "http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time";
+
+/// [draft-holmer-rmcat-transport-wide-cc-extensions](https://datatracker.ietf.org/doc/html/draft-holmer-rmcat-transport-wide-cc-extensions-01)
+pub const TRANSPORT_WIDE_CC_URI: &str =
+    "http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01";
+
+/// [RFC9143](https://www.rfc-editor.org/rfc/rfc9143.html#section-15.1)
+pub const SDES_MID_URI: &str = "urn:ietf:params:rtp-hdrext:sdes:mid";
+
+/// [RFC8852](https://www.rfc-editor.org/rfc/rfc8852.html#section-4.3)
+pub const SDES_RTP_STREAM_ID_URI: &str = "urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id";
+
+/// [RFC8852](https://www.rfc-editor.org/rfc/rfc8852.html#section-4.4)
+pub const SDES_REPAIRED_RTP_STREAM_ID_URI: &str =
+    "urn:ietf:params:rtp-hdrext:sdes:repaired-rtp-stream-id";
+
+/// [3GPP TS 26.114](https://www.3gpp.org/dynareport/26114.htm)
+pub const VIDEO_ORIENTATION_URI: &str = "urn:3gpp:video-orientation";
+
+/// Well known RTP header extensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtpHeaderExtension {
+    SsrcAudioLevel,
+    CsrcAudioLevel,
+    TransmissionTimeOffset,
+    AbsSendTime,
+    TransportWideCc,
+    SdesMid,
+    SdesRtpStreamId,
+    SdesRepairedRtpStreamId,
+    VideoOrientation,
+}
+
+impl RtpHeaderExtension {
+    const ALL: [Self; 9] = [
+        Self::SsrcAudioLevel,
+        Self::CsrcAudioLevel,
+        Self::TransmissionTimeOffset,
+        Self::AbsSendTime,
+        Self::TransportWideCc,
+        Self::SdesMid,
+        Self::SdesRtpStreamId,
+        Self::SdesRepairedRtpStreamId,
+        Self::VideoOrientation,
+    ];
+
+    /// Look up the extension identified by the URI, compared case-insensitively
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|extension| extension.uri().eq_ignore_ascii_case(uri))
+    }
+
+    pub fn uri(&self) -> &'static str {
+        match self {
+            Self::SsrcAudioLevel => SSRC_AUDIO_LEVEL_URI,
+            Self::CsrcAudioLevel => CSRC_AUDIO_LEVEL_URI,
+            Self::TransmissionTimeOffset => TOFFSET_URI,
+            Self::AbsSendTime => ABS_SEND_TIME_URI,
+            Self::TransportWideCc => TRANSPORT_WIDE_CC_URI,
+            Self::SdesMid => SDES_MID_URI,
+            Self::SdesRtpStreamId => SDES_RTP_STREAM_ID_URI,
+            Self::SdesRepairedRtpStreamId => SDES_REPAIRED_RTP_STREAM_ID_URI,
+            Self::VideoOrientation => VIDEO_ORIENTATION_URI,
+        }
+    }
+}
+
+/// Maps an RTP header extension to a local identifier
+///
+/// Session and Media Level attribute, may appear multiple times.
+/// Session level mappings apply to all media descriptions.
+///
+/// [RFC8285](https://www.rfc-editor.org/rfc/rfc8285.html#section-8)
+#[derive(Debug, Clone)]
+pub struct ExtMap {
+    /// Identifier used in the RTP packets, 1-14 for one-byte and 1-255 for two-byte headers
+    pub id: u8,
+
+    /// Direction the extension is used in, if not present the direction of the media applies
+    pub direction: Option<Direction>,
+
+    /// The extension is encrypted using SRTP, written as `urn:ietf:params:rtp-hdrext:encrypt`
+    /// in front of the actual URI
+    pub encrypted: bool,
+
+    /// URI identifying the extension
+    pub uri: BytesStr,
+
+    /// Extension specific attributes
+    pub attributes: Option<BytesStr>,
+}
+
+impl ExtMap {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("extmap:"),
+            map(
+                tuple((
+                    map_res(digit1, FromStr::from_str),
+                    opt(preceded(char('/'), direction)),
+                    multispace1,
+                    take_while1(not_whitespace),
+                    opt(preceded(multispace1, rest)),
+                )),
+                |(id, direction, _, uri, attributes): (_, _, _, &str, Option<&str>)| {
+                    let (encrypted, uri, attributes) = match attributes {
+                        Some(attributes) if uri == ENCRYPT_URI => {
+                            match attributes.trim().split_once(char::is_whitespace) {
+                                Some((uri, attributes)) => (true, uri, Some(attributes.trim())),
+                                None => (true, attributes.trim(), None),
+                            }
+                        }
+                        _ => (false, uri, attributes.map(str::trim)),
+                    };
+
+                    ExtMap {
+                        id,
+                        direction,
+                        encrypted,
+                        uri: BytesStr::from_parse(src, uri),
+                        attributes: attributes
+                            .filter(|attributes| !attributes.is_empty())
+                            .map(|attributes| BytesStr::from_parse(src, attributes)),
+                    }
+                },
+            ),
+        )(i)
+    }
+
+    /// Create a mapping for a well known extension
+    pub fn new(id: u8, extension: RtpHeaderExtension) -> Self {
+        ExtMap {
+            id,
+            direction: None,
+            encrypted: false,
+            uri: BytesStr::from_static(extension.uri()),
+            attributes: None,
+        }
+    }
+
+    /// Returns the well known extension, `None` if the URI is unknown
+    pub fn extension(&self) -> Option<RtpHeaderExtension> {
+        RtpHeaderExtension::from_uri(&self.uri)
+    }
+
+    /// Returns if both map the same extension, ignoring id, direction and attributes
+    ///
+    /// An encrypted extension doesn't match its unencrypted variant.
+    pub fn is_same_extension(&self, other: &ExtMap) -> bool {
+        self.encrypted == other.encrypted && self.uri == other.uri
+    }
+}
+
+fn direction(i: &str) -> IResult<&str, Direction> {
+    alt((
+        map(tag("sendrecv"), |_| Direction::SendRecv),
+        map(tag("recvonly"), |_| Direction::RecvOnly),
+        map(tag("sendonly"), |_| Direction::SendOnly),
+        map(tag("inactive"), |_| Direction::Inactive),
+    ))(i)
+}
+
+impl fmt::Display for ExtMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=extmap:{}", self.id)?;
+
+        if let Some(direction) = self.direction {
+            write!(f, "/{}", direction.as_str())?;
+        }
+
+        if self.encrypted {
+            write!(f, " {}", ENCRYPT_URI)?;
+        }
+
+        write!(f, " {}", self.uri)?;
+
+        if let Some(attributes) = &self.attributes {
+            write!(f, " {}", attributes)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn extmap() {
+        let input = BytesStr::from_static("extmap:1 urn:ietf:params:rtp-hdrext:ssrc-audio-level");
+
+        let (rem, extmap) = ExtMap::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(extmap.id, 1);
+        assert!(extmap.direction.is_none());
+        assert!(!extmap.encrypted);
+        assert_eq!(extmap.uri, "urn:ietf:params:rtp-hdrext:ssrc-audio-level");
+        assert!(extmap.attributes.is_none());
+    }
+
+    #[test]
+    fn extmap_direction() {
+        let input = BytesStr::from_static("extmap:2/sendonly urn:example:ext attr1 attr2");
+
+        let (rem, extmap) = ExtMap::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(extmap.direction, Some(Direction::SendOnly));
+        assert_eq!(extmap.attributes.as_deref(), Some("attr1 attr2"));
+        assert_eq!(extmap.to_string(), format!("a={}", &*input));
+    }
+
+    #[test]
+    fn extmap_encrypted() {
+        let input = BytesStr::from_static(
+            "extmap:3 urn:ietf:params:rtp-hdrext:encrypt urn:ietf:params:rtp-hdrext:ssrc-audio-level vad=on",
+        );
+
+        let (rem, extmap) = ExtMap::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert!(extmap.encrypted);
+        assert_eq!(extmap.uri, "urn:ietf:params:rtp-hdrext:ssrc-audio-level");
+        assert_eq!(extmap.attributes.as_deref(), Some("vad=on"));
+        assert_eq!(extmap.to_string(), format!("a={}", &*input));
+
+        let input = BytesStr::from_static("extmap:4 urn:ietf:params:rtp-hdrext:ssrc-audio-level");
+        let (_, plain) = ExtMap::parse(input.as_ref(), &input).unwrap();
+
+        assert!(!extmap.is_same_extension(&plain));
+    }
+
+    #[test]
+    fn extension_lookup() {
+        let input = BytesStr::from_static(
+            "extmap:5 http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01",
+        );
+
+        let (_, extmap) = ExtMap::parse(input.as_ref(), &input).unwrap();
+
+        assert_eq!(
+            extmap.extension(),
+            Some(RtpHeaderExtension::TransportWideCc)
+        );
+        assert_eq!(
+            RtpHeaderExtension::from_uri("URN:IETF:PARAMS:RTP-HDREXT:SDES:MID"),
+            Some(RtpHeaderExtension::SdesMid)
+        );
+        assert_eq!(RtpHeaderExtension::from_uri("urn:example:ext"), None);
+
+        let mid = ExtMap::new(1, RtpHeaderExtension::SdesMid);
+        assert_eq!(
+            mid.to_string(),
+            "a=extmap:1 urn:ietf:params:rtp-hdrext:sdes:mid"
+        );
+    }
+}
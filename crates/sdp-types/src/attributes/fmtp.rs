@@ -25,6 +25,45 @@ pub struct Fmtp {
 }
 
 impl Fmtp {
+    /// Iterate over the `;` separated `key=value` parameters, parameters without `=` have no value
+    pub fn iter_params(&self) -> impl Iterator<Item = (&str, Option<&str>)> + '_ {
+        self.params
+            .split(';')
+            .map(str::trim)
+            .filter(|param| !param.is_empty())
+            .map(|param| match param.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim())),
+                None => (param, None),
+            })
+    }
+
+    /// Returns the value of the parameter, keys are compared case insensitive
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.iter_params()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .and_then(|(_, value)| value)
+    }
+
+    /// Interpret the parameters as H.264 parameters
+    pub fn h264(&self) -> H264Params {
+        H264Params::from_fmtp(self)
+    }
+
+    /// Interpret the parameters as Opus parameters
+    pub fn opus(&self) -> OpusParams {
+        OpusParams::from_fmtp(self)
+    }
+
+    /// Interpret the parameters as VP9 parameters
+    pub fn vp9(&self) -> Vp9Params {
+        Vp9Params::from_fmtp(self)
+    }
+
+    /// Interpret the parameters as the events of a `telephone-event` format
+    pub fn telephone_events(&self) -> TelephoneEvents {
+        TelephoneEvents::from_fmtp(self)
+    }
+
     pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
         map(
             preceded(
@@ -49,6 +88,246 @@ impl fmt::Display for Fmtp {
     }
 }
 
+fn parse_param<T: FromStr>(fmtp: &Fmtp, key: &str) -> Option<T> {
+    fmtp.get(key)?.parse().ok()
+}
+
+fn parse_flag(fmtp: &Fmtp, key: &str) -> bool {
+    fmtp.get(key) == Some("1")
+}
+
+/// H.264 `profile-level-id`, the profile and level of the stream
+///
+/// [RFC6184](https://www.rfc-editor.org/rfc/rfc6184.html#section-8.1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileLevelId {
+    pub profile_idc: u8,
+    pub profile_iop: u8,
+    pub level_idc: u8,
+}
+
+impl Default for ProfileLevelId {
+    /// Constrained baseline profile, level 1.0
+    fn default() -> Self {
+        Self {
+            profile_idc: 0x42,
+            profile_iop: 0x00,
+            level_idc: 0x0A,
+        }
+    }
+}
+
+impl FromStr for ProfileLevelId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [_, profile_idc, profile_iop, level_idc] = u32::from_str_radix(s, 16)?.to_be_bytes();
+
+        Ok(Self {
+            profile_idc,
+            profile_iop,
+            level_idc,
+        })
+    }
+}
+
+impl fmt::Display for ProfileLevelId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:02x}{:02x}{:02x}",
+            self.profile_idc, self.profile_iop, self.level_idc
+        )
+    }
+}
+
+/// H.264 format parameters
+///
+/// [RFC6184](https://www.rfc-editor.org/rfc/rfc6184.html#section-8.1)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct H264Params {
+    pub profile_level_id: ProfileLevelId,
+    /// 0 = single NAL unit, 1 = non-interleaved, 2 = interleaved
+    pub packetization_mode: u8,
+    pub level_asymmetry_allowed: bool,
+}
+
+impl H264Params {
+    pub fn from_fmtp(fmtp: &Fmtp) -> Self {
+        Self {
+            profile_level_id: parse_param(fmtp, "profile-level-id").unwrap_or_default(),
+            packetization_mode: parse_param(fmtp, "packetization-mode").unwrap_or(0),
+            level_asymmetry_allowed: parse_flag(fmtp, "level-asymmetry-allowed"),
+        }
+    }
+}
+
+impl fmt::Display for H264Params {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "profile-level-id={};packetization-mode={}",
+            self.profile_level_id, self.packetization_mode
+        )?;
+
+        if self.level_asymmetry_allowed {
+            f.write_str(";level-asymmetry-allowed=1")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Opus format parameters
+///
+/// [RFC7587](https://www.rfc-editor.org/rfc/rfc7587.html#section-6.1)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OpusParams {
+    /// Maximum average bitrate in bits per second the receiver is able to handle
+    pub maxaveragebitrate: Option<u32>,
+    /// Maximum audio bandwidth in Hz the receiver is able to render
+    pub maxplaybackrate: Option<u32>,
+    pub stereo: bool,
+    pub useinbandfec: bool,
+    pub usedtx: bool,
+}
+
+impl OpusParams {
+    pub fn from_fmtp(fmtp: &Fmtp) -> Self {
+        Self {
+            maxaveragebitrate: parse_param(fmtp, "maxaveragebitrate"),
+            maxplaybackrate: parse_param(fmtp, "maxplaybackrate"),
+            stereo: parse_flag(fmtp, "stereo"),
+            useinbandfec: parse_flag(fmtp, "useinbandfec"),
+            usedtx: parse_flag(fmtp, "usedtx"),
+        }
+    }
+}
+
+impl fmt::Display for OpusParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut params = vec![];
+
+        if let Some(maxaveragebitrate) = self.maxaveragebitrate {
+            params.push(format!("maxaveragebitrate={maxaveragebitrate}"));
+        }
+
+        if let Some(maxplaybackrate) = self.maxplaybackrate {
+            params.push(format!("maxplaybackrate={maxplaybackrate}"));
+        }
+
+        params.push(format!("stereo={}", u8::from(self.stereo)));
+        params.push(format!("useinbandfec={}", u8::from(self.useinbandfec)));
+
+        if self.usedtx {
+            params.push("usedtx=1".into());
+        }
+
+        f.write_str(&params.join(";"))
+    }
+}
+
+/// VP9 format parameters
+///
+/// [RFC9628](https://www.rfc-editor.org/rfc/rfc9628.html#section-6.1)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Vp9Params {
+    pub profile_id: u8,
+    /// Maximum frame rate in frames per second
+    pub max_fr: Option<u32>,
+    /// Maximum frame size in macroblocks
+    pub max_fs: Option<u32>,
+}
+
+impl Vp9Params {
+    pub fn from_fmtp(fmtp: &Fmtp) -> Self {
+        Self {
+            profile_id: parse_param(fmtp, "profile-id").unwrap_or(0),
+            max_fr: parse_param(fmtp, "max-fr"),
+            max_fs: parse_param(fmtp, "max-fs"),
+        }
+    }
+}
+
+impl fmt::Display for Vp9Params {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "profile-id={}", self.profile_id)?;
+
+        if let Some(max_fr) = self.max_fr {
+            write!(f, ";max-fr={max_fr}")?;
+        }
+
+        if let Some(max_fs) = self.max_fs {
+            write!(f, ";max-fs={max_fs}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Events supported by a `telephone-event` (DTMF) format, e.g. `0-15,66`
+///
+/// Invalid entries are ignored. The default, used if no fmtp is present, is `0-15`.
+///
+/// [RFC4733](https://www.rfc-editor.org/rfc/rfc4733.html#section-2.4.1)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelephoneEvents {
+    /// Inclusive ranges of event codes, single events have the same start and end
+    pub ranges: Vec<(u8, u8)>,
+}
+
+impl Default for TelephoneEvents {
+    /// DTMF digits 0-9, *, # and A-D
+    fn default() -> Self {
+        Self {
+            ranges: vec![(0, 15)],
+        }
+    }
+}
+
+impl TelephoneEvents {
+    pub fn from_fmtp(fmtp: &Fmtp) -> Self {
+        let ranges = fmtp
+            .params
+            .split(',')
+            .filter_map(|entry| {
+                let (start, end) = entry.split_once('-').unwrap_or((entry, entry));
+
+                let start = start.trim().parse().ok()?;
+                let end = end.trim().parse().ok()?;
+
+                (start <= end).then_some((start, end))
+            })
+            .collect();
+
+        Self { ranges }
+    }
+
+    pub fn contains(&self, event: u8) -> bool {
+        self.ranges
+            .iter()
+            .any(|&(start, end)| (start..=end).contains(&event))
+    }
+}
+
+impl fmt::Display for TelephoneEvents {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, &(start, end)) in self.ranges.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+
+            if start == end {
+                write!(f, "{start}")?;
+            } else {
+                write!(f, "{start}-{end}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -74,4 +353,74 @@ mod test {
 
         assert_eq!(fmtp.to_string(), "a=fmtp:111 some=param");
     }
+
+    #[test]
+    fn fmtp_key_values() {
+        let fmtp = Fmtp {
+            format: 96,
+            params: "profile-level-id=42e01f; packetization-mode=1;level-asymmetry-allowed=1;flag"
+                .into(),
+        };
+
+        assert_eq!(fmtp.get("Packetization-Mode"), Some("1"));
+        assert_eq!(fmtp.get("flag"), None);
+        assert_eq!(fmtp.iter_params().count(), 4);
+
+        let h264 = fmtp.h264();
+        assert_eq!(
+            h264.profile_level_id,
+            ProfileLevelId {
+                profile_idc: 0x42,
+                profile_iop: 0xe0,
+                level_idc: 0x1f
+            }
+        );
+        assert_eq!(h264.packetization_mode, 1);
+        assert!(h264.level_asymmetry_allowed);
+        assert_eq!(
+            h264.to_string(),
+            "profile-level-id=42e01f;packetization-mode=1;level-asymmetry-allowed=1"
+        );
+    }
+
+    #[test]
+    fn fmtp_opus_vp9() {
+        let fmtp = Fmtp {
+            format: 111,
+            params: "minptime=10;useinbandfec=1;maxaveragebitrate=64000".into(),
+        };
+
+        let opus = fmtp.opus();
+        assert_eq!(opus.maxaveragebitrate, Some(64000));
+        assert!(opus.useinbandfec);
+        assert!(!opus.stereo);
+        assert_eq!(
+            opus.to_string(),
+            "maxaveragebitrate=64000;stereo=0;useinbandfec=1"
+        );
+
+        let fmtp = Fmtp {
+            format: 98,
+            params: "profile-id=2".into(),
+        };
+
+        assert_eq!(fmtp.vp9().profile_id, 2);
+        assert_eq!(Vp9Params::default().to_string(), "profile-id=0");
+    }
+
+    #[test]
+    fn fmtp_telephone_events() {
+        let fmtp = Fmtp {
+            format: 101,
+            params: "0-16, 32,x,66-64".into(),
+        };
+
+        let events = fmtp.telephone_events();
+        assert_eq!(events.ranges, [(0, 16), (32, 32)]);
+        assert!(events.contains(16));
+        assert!(!events.contains(17));
+        assert_eq!(events.to_string(), "0-16,32");
+
+        assert!(TelephoneEvents::default().contains(15));
+    }
 }
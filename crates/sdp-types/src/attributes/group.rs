@@ -0,0 +1,232 @@
+//! Group attribute (`a=group:...`)
+
+use crate::msg::Message;
+use crate::token;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::IResult;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::multispace1;
+use nom::combinator::map;
+use nom::multi::many0;
+use nom::sequence::{preceded, tuple};
+use std::fmt;
+
+/// Typed group semantics
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupSemantics {
+    /// `BUNDLE`, media descriptions share a single transport
+    /// ([RFC9143](https://www.rfc-editor.org/rfc/rfc9143.html))
+    Bundle,
+
+    /// `LS`, lip synchronization, media must be played out synchronized
+    /// ([RFC5888](https://www.rfc-editor.org/rfc/rfc5888.html#section-7.1))
+    LipSync,
+
+    /// `FID`, flow identification, media descriptions carry a single flow over different transports
+    /// ([RFC5888](https://www.rfc-editor.org/rfc/rfc5888.html#section-7.2))
+    Fid,
+
+    /// `FEC-FR`, forward error correction repair and source flows
+    /// ([RFC5956](https://www.rfc-editor.org/rfc/rfc5956.html#section-4.1))
+    FecFr,
+
+    Other(BytesStr),
+}
+
+impl GroupSemantics {
+    pub fn as_str(&self) -> &str {
+        match self {
+            GroupSemantics::Bundle => "BUNDLE",
+            GroupSemantics::LipSync => "LS",
+            GroupSemantics::Fid => "FID",
+            GroupSemantics::FecFr => "FEC-FR",
+            GroupSemantics::Other(other) => other,
+        }
+    }
+}
+
+impl From<&BytesStr> for GroupSemantics {
+    fn from(semantics: &BytesStr) -> Self {
+        [
+            GroupSemantics::Bundle,
+            GroupSemantics::LipSync,
+            GroupSemantics::Fid,
+            GroupSemantics::FecFr,
+        ]
+        .into_iter()
+        .find(|known| known.as_str().eq_ignore_ascii_case(semantics))
+        .unwrap_or_else(|| GroupSemantics::Other(semantics.clone()))
+    }
+}
+
+/// Groups media descriptions by their identification tag (`a=mid`)
+///
+/// Session Level attribute, may appear multiple times
+///
+/// [RFC5888](https://www.rfc-editor.org/rfc/rfc5888.html#section-5)
+#[derive(Debug, Clone)]
+pub struct Group {
+    /// Semantics of the group e.g. `BUNDLE` or `LS`
+    pub semantics: BytesStr,
+
+    /// Identification tags of the media descriptions in the group
+    pub mids: Vec<BytesStr>,
+}
+
+impl Group {
+    pub fn new(semantics: GroupSemantics, mids: Vec<BytesStr>) -> Self {
+        Self {
+            semantics: match semantics {
+                GroupSemantics::Other(other) => other,
+                semantics => semantics.as_str().into(),
+            },
+            mids,
+        }
+    }
+
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("group:"),
+            map(
+                tuple((
+                    take_while1(token),
+                    many0(preceded(multispace1, take_while1(token))),
+                )),
+                |(semantics, mids)| Group {
+                    semantics: BytesStr::from_parse(src, semantics),
+                    mids: mids
+                        .into_iter()
+                        .map(|mid| BytesStr::from_parse(src, mid))
+                        .collect(),
+                },
+            ),
+        )(i)
+    }
+
+    /// Returns the typed semantics of the group
+    pub fn kind(&self) -> GroupSemantics {
+        GroupSemantics::from(&self.semantics)
+    }
+
+    /// Returns if the group contains the given identification tag
+    pub fn contains(&self, mid: &str) -> bool {
+        self.mids.iter().any(|m| m == mid)
+    }
+}
+
+impl Message {
+    /// Returns all groups with the given semantics
+    pub fn groups_with(&self, semantics: GroupSemantics) -> impl Iterator<Item = &Group> {
+        self.groups
+            .iter()
+            .filter(move |group| group.kind() == semantics)
+    }
+
+    /// Resolve the members of the group to indices into `media_scopes`, in the order of the group
+    ///
+    /// Returns `None` if any mid doesn't belong to a media description.
+    pub fn group_members(&self, group: &Group) -> Option<Vec<usize>> {
+        group
+            .mids
+            .iter()
+            .map(|mid| {
+                self.media_scopes
+                    .iter()
+                    .position(|media| media.mid.as_ref() == Some(mid))
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=group:{}", self.semantics)?;
+
+        for mid in &self.mids {
+            write!(f, " {}", mid)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn group() {
+        let input = BytesStr::from_static("group:BUNDLE audio video");
+
+        let (rem, group) = Group::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(group.semantics, "BUNDLE");
+        assert_eq!(group.mids, ["audio", "video"]);
+        assert!(group.contains("video"));
+        assert!(!group.contains("data"));
+    }
+
+    #[test]
+    fn group_empty() {
+        let input = BytesStr::from_static("group:BUNDLE");
+
+        let (rem, group) = Group::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+        assert!(group.mids.is_empty());
+    }
+
+    #[test]
+    fn group_print() {
+        let group = Group {
+            semantics: "LS".into(),
+            mids: vec!["1".into(), "2".into()],
+        };
+
+        assert_eq!(group.to_string(), "a=group:LS 1 2");
+    }
+
+    #[test]
+    fn group_semantics() {
+        let group = Group::new(GroupSemantics::FecFr, vec![]);
+        assert_eq!(group.semantics, "FEC-FR");
+        assert_eq!(group.kind(), GroupSemantics::FecFr);
+
+        let input = BytesStr::from_static("group:ls 1 2");
+        let (_, group) = Group::parse(input.as_ref(), &input).unwrap();
+        assert_eq!(group.kind(), GroupSemantics::LipSync);
+
+        let group = Group::new(GroupSemantics::Other("DDP".into()), vec![]);
+        assert_eq!(group.kind(), GroupSemantics::Other("DDP".into()));
+    }
+
+    #[test]
+    fn group_members() {
+        let msg = crate::msg::parse::<crate::msg::Builder>(&BytesStr::from_static(
+            "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+a=group:LS video audio\r
+a=group:LS audio data\r
+m=audio 1000 RTP/AVP 0\r
+a=mid:audio\r
+m=video 1002 RTP/AVP 96\r
+a=mid:video\r
+a=rtpmap:96 VP8/90000\r
+",
+        ))
+        .unwrap();
+
+        let mut groups = msg.groups_with(GroupSemantics::LipSync);
+
+        assert_eq!(msg.group_members(groups.next().unwrap()), Some(vec![1, 0]));
+        assert_eq!(msg.group_members(groups.next().unwrap()), None);
+        assert!(groups.next().is_none());
+        assert!(msg.groups_with(GroupSemantics::Bundle).next().is_none());
+    }
+}
@@ -1,17 +1,23 @@
-//! Some ICE related SDP attributes (`a=ice-options:...`, `a=ice-ufrag:...`, `a=ice-pwd:...`)
+//! Some ICE related SDP attributes (`a=ice-options:...`, `a=ice-ufrag:...`, `a=ice-pwd:...`,
+//! `a=remote-candidates:...`)
 
+use crate::attributes::candidate::UntaggedAddress;
 use crate::ice_char;
 use bytes::Bytes;
 use bytesstr::BytesStr;
-use internal::IResult;
+use internal::{ws, IResult};
 use nom::bytes::complete::{take_while1, take_while_m_n};
-use nom::combinator::map;
-use nom::multi::many1;
+use nom::character::complete::{digit1, multispace0, multispace1};
+use nom::combinator::{map, map_res};
+use nom::multi::{many1, separated_list1};
+use nom::sequence::preceded;
 use std::fmt;
+use std::str::FromStr;
 
 /// ice-options
 ///
-/// Session Level attribute
+/// Session and Media Level attribute  
+/// Options at media level apply in addition to the ones at session level.
 ///
 /// [RFC5245](https://datatracker.ietf.org/doc/html/rfc5245#section-15.5)
 #[derive(Default, Debug, Clone)]
@@ -21,14 +27,48 @@ pub struct Options {
 }
 
 impl Options {
+    /// Support for trickle ICE ([RFC8840](https://www.rfc-editor.org/rfc/rfc8840.html#section-4.1))
+    pub const TRICKLE: &'static str = "trickle";
+
+    /// Support for renomination of the selected pair ([draft-thatcher-ice-renomination](https://datatracker.ietf.org/doc/html/draft-thatcher-ice-renomination-01))
+    pub const RENOMINATION: &'static str = "renomination";
+
     pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
         map(
-            many1(map(take_while1(ice_char), |option| {
-                BytesStr::from_parse(src, option)
-            })),
+            preceded(
+                multispace0,
+                separated_list1(
+                    multispace1,
+                    map(take_while1(ice_char), |option| {
+                        BytesStr::from_parse(src, option)
+                    }),
+                ),
+            ),
             |options| Self { options },
         )(i)
     }
+
+    /// Returns if the option is present, options are compared case-insensitive
+    pub fn contains(&self, option: &str) -> bool {
+        self.options
+            .iter()
+            .any(|present| present.eq_ignore_ascii_case(option))
+    }
+
+    /// Add the option if it isn't already present
+    pub fn add(&mut self, option: &str) {
+        if !self.contains(option) {
+            self.options.push(BytesStr::from(option));
+        }
+    }
+
+    pub fn supports_trickle(&self) -> bool {
+        self.contains(Self::TRICKLE)
+    }
+
+    pub fn supports_renomination(&self) -> bool {
+        self.contains(Self::RENOMINATION)
+    }
 }
 
 impl fmt::Display for Options {
@@ -37,9 +77,9 @@ impl fmt::Display for Options {
             return Ok(());
         }
 
-        write!(f, "a=ice-options:")?;
+        write!(f, "a=ice-options:{}", self.options[0])?;
 
-        for option in &self.options {
+        for option in &self.options[1..] {
             write!(f, " {}", option)?;
         }
 
@@ -102,3 +142,135 @@ impl fmt::Display for Password {
         write!(f, "a=ice-pwd:{}", self.pwd)
     }
 }
+
+/// Entry of the remote-candidates attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteCandidate {
+    /// Component ID of the candidate
+    pub component: u32,
+
+    /// Address of the candidate
+    pub address: UntaggedAddress,
+
+    /// Port of the candidate
+    pub port: u16,
+}
+
+/// remote-candidates attribute
+///
+/// Media Level attribute  
+/// Sent by the controlling agent once ICE completed, contains the remote candidate of the
+/// selected pair of each component.
+///
+/// [RFC8839](https://datatracker.ietf.org/doc/html/rfc8839#section-5.2)
+#[derive(Debug, Clone)]
+pub struct RemoteCandidates {
+    /// Non empty list of candidates
+    pub candidates: Vec<RemoteCandidate>,
+}
+
+impl RemoteCandidates {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        map(
+            many1(map(
+                ws((
+                    map_res(digit1, FromStr::from_str),
+                    UntaggedAddress::parse(src),
+                    map_res(digit1, FromStr::from_str),
+                )),
+                |(component, address, port)| RemoteCandidate {
+                    component,
+                    address,
+                    port,
+                },
+            )),
+            |candidates| Self { candidates },
+        )(i)
+    }
+}
+
+impl fmt::Display for RemoteCandidates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a=remote-candidates:")?;
+
+        for (i, candidate) in self.candidates.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+
+            write!(
+                f,
+                "{} {} {}",
+                candidate.component, candidate.address, candidate.port
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn options() {
+        let input = BytesStr::from_static("trickle  Renomination");
+
+        let (rem, mut options) = Options::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert!(options.supports_trickle());
+        assert!(options.supports_renomination());
+        assert!(!options.contains("ice2"));
+
+        options.add("ice2");
+        options.add("trickle");
+        assert_eq!(
+            options.to_string(),
+            "a=ice-options:trickle Renomination ice2\r\n"
+        );
+    }
+
+    #[test]
+    fn remote_candidates() {
+        let input = BytesStr::from_static("1 192.0.2.3 45664 2 192.0.2.3 45665");
+
+        let (rem, remote_candidates) = RemoteCandidates::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(
+            remote_candidates.candidates,
+            [
+                RemoteCandidate {
+                    component: 1,
+                    address: UntaggedAddress::IpAddress(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 3))),
+                    port: 45664,
+                },
+                RemoteCandidate {
+                    component: 2,
+                    address: UntaggedAddress::IpAddress(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 3))),
+                    port: 45665,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn remote_candidates_print() {
+        let remote_candidates = RemoteCandidates {
+            candidates: vec![RemoteCandidate {
+                component: 1,
+                address: UntaggedAddress::Fqdn("abc.local".into()),
+                port: 1000,
+            }],
+        };
+
+        assert_eq!(
+            remote_candidates.to_string(),
+            "a=remote-candidates:1 abc.local 1000"
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Identity attribute (`a=identity:...`)
+
+use crate::not_whitespace;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::IResult;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::multispace1;
+use nom::combinator::map;
+use nom::multi::many0;
+use nom::sequence::{preceded, tuple};
+use std::fmt;
+
+/// WebRTC identity assertion
+///
+/// Session Level attribute
+///
+/// [RFC8827](https://www.rfc-editor.org/rfc/rfc8827.html#section-7.4)
+#[derive(Debug, Clone)]
+pub struct Identity {
+    /// Base64 encoded identity assertion
+    pub assertion: BytesStr,
+
+    /// Extensions with their optional value (`name[=value]`)
+    pub extensions: Vec<(BytesStr, Option<BytesStr>)>,
+}
+
+impl Identity {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("identity:"),
+            map(
+                tuple((
+                    take_while1(not_whitespace),
+                    many0(preceded(multispace1, take_while1(not_whitespace))),
+                )),
+                |(assertion, extensions)| Identity {
+                    assertion: BytesStr::from_parse(src, assertion),
+                    extensions: extensions
+                        .into_iter()
+                        .map(|extension: &str| match extension.split_once('=') {
+                            Some((name, value)) => (
+                                BytesStr::from_parse(src, name),
+                                Some(BytesStr::from_parse(src, value)),
+                            ),
+                            None => (BytesStr::from_parse(src, extension), None),
+                        })
+                        .collect(),
+                },
+            ),
+        )(i)
+    }
+
+    /// Decode the identity assertion, which is a JSON object
+    pub fn decode_assertion(&self) -> Result<Vec<u8>, base64::DecodeError> {
+        STANDARD.decode(self.assertion.as_bytes())
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=identity:{}", self.assertion)?;
+
+        for (name, value) in &self.extensions {
+            write!(f, " {}", name)?;
+
+            if let Some(value) = value {
+                write!(f, "={}", value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn identity() {
+        let input = BytesStr::from_static("identity:eyJpZHAiOnt9fQ== ext=1 flag");
+
+        let (rem, identity) = Identity::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(identity.assertion, "eyJpZHAiOnt9fQ==");
+        assert_eq!(identity.decode_assertion().unwrap(), b"{\"idp\":{}}");
+        assert_eq!(identity.extensions.len(), 2);
+        assert_eq!(identity.extensions[0].0, "ext");
+        assert_eq!(identity.extensions[0].1.as_deref(), Some("1"));
+        assert_eq!(identity.extensions[1].0, "flag");
+        assert!(identity.extensions[1].1.is_none());
+
+        assert_eq!(
+            identity.to_string(),
+            "a=identity:eyJpZHAiOnt9fQ== ext=1 flag"
+        );
+    }
+}
@@ -0,0 +1,463 @@
+//! Image attribute (`a=imageattr:...`)
+
+use internal::IResult;
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{char, digit1, multispace1};
+use nom::combinator::{map, map_res, opt, value};
+use nom::multi::{many0, many_m_n, separated_list1};
+use nom::number::complete::float;
+use nom::sequence::{delimited, preceded, separated_pair, terminated, tuple};
+use std::fmt;
+use std::str::FromStr;
+
+/// Image resolutions a video payload type can be sent and received with
+///
+/// Media Level attribute, may appear multiple times
+///
+/// [RFC6236](https://www.rfc-editor.org/rfc/rfc6236.html#section-3.1)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageAttr {
+    /// Payload type the attribute applies to, `None` for all (`*`)
+    pub payload: Option<u32>,
+
+    /// Resolutions the sender of the SDP is able to send
+    pub send: Option<ImageAttrSets>,
+
+    /// Resolutions the sender of the SDP is able to receive
+    pub recv: Option<ImageAttrSets>,
+}
+
+/// Accepted image sets of a direction
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageAttrSets {
+    /// Any image size (`*`)
+    Any,
+
+    /// Image sets, ordered by preference
+    Sets(Vec<ImageAttrSet>),
+}
+
+/// Single image set (`[x=...,y=...]`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageAttrSet {
+    pub x: XyRange,
+    pub y: XyRange,
+
+    /// Sample aspect ratio
+    pub sar: Option<SarRange>,
+
+    /// Picture aspect ratio range
+    pub par: Option<(f32, f32)>,
+
+    /// Preference of the set between 0.0 and 1.0, 0.5 if absent
+    pub q: Option<f32>,
+}
+
+impl ImageAttrSet {
+    /// Returns if the resolution is allowed by the set
+    pub fn allows(&self, width: u32, height: u32) -> bool {
+        self.x.contains(width) && self.y.contains(height)
+    }
+}
+
+/// Allowed pixel width or height
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XyRange {
+    Value(u32),
+    Range {
+        min: u32,
+        /// Step between allowed values, 1 if absent
+        step: Option<u32>,
+        max: u32,
+    },
+    List(Vec<u32>),
+}
+
+impl XyRange {
+    pub fn contains(&self, v: u32) -> bool {
+        match self {
+            XyRange::Value(value) => *value == v,
+            XyRange::Range { min, step, max } => {
+                (*min..=*max).contains(&v) && (v - min) % step.unwrap_or(1).max(1) == 0
+            }
+            XyRange::List(list) => list.contains(&v),
+        }
+    }
+}
+
+/// Allowed sample aspect ratios
+#[derive(Debug, Clone, PartialEq)]
+pub enum SarRange {
+    Value(f32),
+    Range(f32, f32),
+    List(Vec<f32>),
+}
+
+enum KeyValue {
+    Sar(SarRange),
+    Par((f32, f32)),
+    Q(f32),
+}
+
+impl ImageAttr {
+    pub fn parse(i: &str) -> IResult<&str, Self> {
+        map(
+            preceded(
+                tag("imageattr:"),
+                tuple((
+                    alt((value(None, char('*')), map(number, Some))),
+                    many_m_n(
+                        1,
+                        2,
+                        preceded(
+                            multispace1,
+                            separated_pair(
+                                alt((value(true, tag("send")), value(false, tag("recv")))),
+                                multispace1,
+                                sets,
+                            ),
+                        ),
+                    ),
+                )),
+            ),
+            |(payload, directions)| {
+                let mut send = None;
+                let mut recv = None;
+
+                for (is_send, sets) in directions {
+                    if is_send {
+                        send = Some(sets);
+                    } else {
+                        recv = Some(sets);
+                    }
+                }
+
+                ImageAttr {
+                    payload,
+                    send,
+                    recv,
+                }
+            },
+        )(i)
+    }
+}
+
+fn number(i: &str) -> IResult<&str, u32> {
+    map_res(digit1, FromStr::from_str)(i)
+}
+
+fn sets(i: &str) -> IResult<&str, ImageAttrSets> {
+    alt((
+        value(ImageAttrSets::Any, char('*')),
+        map(separated_list1(multispace1, set), ImageAttrSets::Sets),
+    ))(i)
+}
+
+fn set(i: &str) -> IResult<&str, ImageAttrSet> {
+    map(
+        delimited(
+            char('['),
+            tuple((
+                preceded(tag("x="), xy_range),
+                preceded(tag(",y="), xy_range),
+                many0(preceded(char(','), key_value)),
+            )),
+            char(']'),
+        ),
+        |(x, y, key_values)| {
+            let mut set = ImageAttrSet {
+                x,
+                y,
+                sar: None,
+                par: None,
+                q: None,
+            };
+
+            for key_value in key_values {
+                match key_value {
+                    KeyValue::Sar(sar) => set.sar = Some(sar),
+                    KeyValue::Par(par) => set.par = Some(par),
+                    KeyValue::Q(q) => set.q = Some(q),
+                }
+            }
+
+            set
+        },
+    )(i)
+}
+
+fn xy_range(i: &str) -> IResult<&str, XyRange> {
+    alt((
+        map(
+            delimited(
+                char('['),
+                tuple((
+                    terminated(number, char(':')),
+                    number,
+                    opt(preceded(char(':'), number)),
+                )),
+                char(']'),
+            ),
+            |(min, second, third)| match third {
+                Some(max) => XyRange::Range {
+                    min,
+                    step: Some(second),
+                    max,
+                },
+                None => XyRange::Range {
+                    min,
+                    step: None,
+                    max: second,
+                },
+            },
+        ),
+        map(
+            delimited(char('['), separated_list1(char(','), number), char(']')),
+            XyRange::List,
+        ),
+        map(number, XyRange::Value),
+    ))(i)
+}
+
+fn key_value(i: &str) -> IResult<&str, KeyValue> {
+    alt((
+        map(preceded(tag("sar="), sar_range), KeyValue::Sar),
+        map(
+            preceded(
+                tag("par="),
+                delimited(
+                    char('['),
+                    separated_pair(float, char('-'), float),
+                    char(']'),
+                ),
+            ),
+            KeyValue::Par,
+        ),
+        map(preceded(tag("q="), float), KeyValue::Q),
+    ))(i)
+}
+
+fn sar_range(i: &str) -> IResult<&str, SarRange> {
+    alt((
+        map(
+            delimited(
+                char('['),
+                separated_pair(float, char('-'), float),
+                char(']'),
+            ),
+            |(min, max)| SarRange::Range(min, max),
+        ),
+        map(
+            delimited(char('['), separated_list1(char(','), float), char(']')),
+            SarRange::List,
+        ),
+        map(float, SarRange::Value),
+    ))(i)
+}
+
+impl fmt::Display for ImageAttr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a=imageattr:")?;
+
+        match self.payload {
+            Some(payload) => write!(f, "{}", payload)?,
+            None => f.write_str("*")?,
+        }
+
+        if let Some(send) = &self.send {
+            write!(f, " send {}", send)?;
+        }
+
+        if let Some(recv) = &self.recv {
+            write!(f, " recv {}", recv)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for ImageAttrSets {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageAttrSets::Any => f.write_str("*"),
+            ImageAttrSets::Sets(sets) => {
+                for (i, set) in sets.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+
+                    write!(f, "{}", set)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for ImageAttrSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[x={},y={}", self.x, self.y)?;
+
+        if let Some(sar) = &self.sar {
+            write!(f, ",sar={}", sar)?;
+        }
+
+        if let Some((min, max)) = self.par {
+            write!(f, ",par=[{}-{}]", min, max)?;
+        }
+
+        if let Some(q) = self.q {
+            write!(f, ",q={}", q)?;
+        }
+
+        f.write_str("]")
+    }
+}
+
+impl fmt::Display for XyRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XyRange::Value(value) => write!(f, "{}", value),
+            XyRange::Range {
+                min,
+                step: Some(step),
+                max,
+            } => write!(f, "[{}:{}:{}]", min, step, max),
+            XyRange::Range {
+                min,
+                step: None,
+                max,
+            } => write!(f, "[{}:{}]", min, max),
+            XyRange::List(list) => write_list(f, list),
+        }
+    }
+}
+
+impl fmt::Display for SarRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SarRange::Value(value) => write!(f, "{}", value),
+            SarRange::Range(min, max) => write!(f, "[{}-{}]", min, max),
+            SarRange::List(list) => write_list(f, list),
+        }
+    }
+}
+
+fn write_list<T: fmt::Display>(f: &mut fmt::Formatter, list: &[T]) -> fmt::Result {
+    f.write_str("[")?;
+
+    for (i, value) in list.iter().enumerate() {
+        if i > 0 {
+            f.write_str(",")?;
+        }
+
+        write!(f, "{}", value)?;
+    }
+
+    f.write_str("]")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn imageattr() {
+        let (rem, attr) = ImageAttr::parse(
+            "imageattr:97 send [x=800,y=640,sar=1.1,q=0.6] [x=480,y=320] recv [x=330,y=250]",
+        )
+        .unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(attr.payload, Some(97));
+
+        let Some(ImageAttrSets::Sets(send)) = &attr.send else {
+            panic!("expected send sets")
+        };
+        assert_eq!(send.len(), 2);
+        assert_eq!(send[0].x, XyRange::Value(800));
+        assert_eq!(send[0].sar, Some(SarRange::Value(1.1)));
+        assert_eq!(send[0].q, Some(0.6));
+        assert!(send[1].allows(480, 320));
+
+        let Some(ImageAttrSets::Sets(recv)) = &attr.recv else {
+            panic!("expected recv sets")
+        };
+        assert_eq!(recv[0].y, XyRange::Value(250));
+    }
+
+    #[test]
+    fn imageattr_ranges() {
+        let (rem, attr) = ImageAttr::parse(
+            "imageattr:* recv [x=[480:16:800],y=[320:16:640],par=[1.2-1.3]] [x=[176,352],y=[144:288],sar=[0.9-1.1]] send *",
+        )
+        .unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(attr.payload, None);
+        assert_eq!(attr.send, Some(ImageAttrSets::Any));
+
+        let Some(ImageAttrSets::Sets(recv)) = &attr.recv else {
+            panic!("expected recv sets")
+        };
+        assert_eq!(
+            recv[0].x,
+            XyRange::Range {
+                min: 480,
+                step: Some(16),
+                max: 800
+            }
+        );
+        assert!(recv[0].allows(496, 336));
+        assert!(!recv[0].allows(490, 336));
+        assert_eq!(recv[0].par, Some((1.2, 1.3)));
+        assert_eq!(recv[1].x, XyRange::List(vec![176, 352]));
+        assert!(recv[1].allows(352, 200));
+        assert_eq!(recv[1].sar, Some(SarRange::Range(0.9, 1.1)));
+    }
+
+    #[test]
+    fn q_round_trip() {
+        for input in [
+            "imageattr:97 send [x=800,y=640,q=0.6] recv *",
+            "imageattr:97 send [x=800,y=640,q=0.555] recv *",
+        ] {
+            let (_, attr) = ImageAttr::parse(input).unwrap();
+            assert_eq!(attr.to_string(), format!("a={input}"));
+        }
+    }
+
+    #[test]
+    fn imageattr_print() {
+        let attr = ImageAttr {
+            payload: Some(97),
+            send: Some(ImageAttrSets::Sets(vec![ImageAttrSet {
+                x: XyRange::Range {
+                    min: 320,
+                    step: None,
+                    max: 640,
+                },
+                y: XyRange::List(vec![240, 480]),
+                sar: Some(SarRange::Value(1.1)),
+                par: None,
+                q: Some(0.5),
+            }])),
+            recv: Some(ImageAttrSets::Any),
+        };
+
+        let printed = attr.to_string();
+
+        assert_eq!(
+            printed,
+            "a=imageattr:97 send [x=[320:640],y=[240,480],sar=1.1,q=0.5] recv *"
+        );
+
+        let (rem, parsed) = ImageAttr::parse(&printed[2..]).unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(parsed, attr);
+    }
+}
@@ -2,12 +2,28 @@ use bytes::Bytes;
 use bytesstr::BytesStr;
 use std::fmt;
 
+pub mod bfcp;
 pub mod candidate;
+pub mod capneg;
+pub mod content;
+pub mod crypto;
 pub mod direction;
+pub mod extmap;
 pub mod fmtp;
+pub mod group;
 pub mod ice;
+pub mod identity;
+pub mod imageattr;
+pub mod msid;
 pub mod rtcp;
+pub mod rtcp_fb;
 pub mod rtpmap;
+pub mod sctp;
+pub mod ssrc;
+pub mod t38;
+pub mod tcp;
+pub mod tls_id;
+pub mod zrtp;
 
 /// `name:[value]` pair which contains an unparsed/unknown attribute
 #[derive(Debug, Clone)]
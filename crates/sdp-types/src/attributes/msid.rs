@@ -0,0 +1,93 @@
+//! Media stream identification attribute (`a=msid:...`)
+
+use crate::token;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::{ws, IResult};
+use nom::bytes::complete::{tag, take_while_m_n};
+use nom::combinator::{map, opt};
+use nom::sequence::{preceded, tuple};
+use std::fmt;
+
+/// Associates the media description with a media stream and track
+///
+/// Media Level attribute, may appear multiple times
+///
+/// [RFC8830](https://www.rfc-editor.org/rfc/rfc8830.html#section-2)
+#[derive(Debug, Clone)]
+pub struct Msid {
+    /// Identifies the media stream (msid-id), `-` if the media is not part of a stream
+    pub stream_id: BytesStr,
+
+    /// Identifies the track inside the stream (msid-appdata)
+    pub track_id: Option<BytesStr>,
+}
+
+impl Msid {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("msid:"),
+            map(
+                tuple((
+                    take_while_m_n(1, 64, token),
+                    opt(ws((take_while_m_n(1, 64, token),))),
+                )),
+                |(stream_id, track_id)| Msid {
+                    stream_id: BytesStr::from_parse(src, stream_id),
+                    track_id: track_id.map(|(track_id,)| BytesStr::from_parse(src, track_id)),
+                },
+            ),
+        )(i)
+    }
+}
+
+impl fmt::Display for Msid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=msid:{}", self.stream_id)?;
+
+        if let Some(track_id) = &self.track_id {
+            write!(f, " {}", track_id)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn msid() {
+        let input = BytesStr::from_static("msid:stream-1 track-1");
+
+        let (rem, msid) = Msid::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(msid.stream_id, "stream-1");
+        assert_eq!(msid.track_id.unwrap(), "track-1");
+    }
+
+    #[test]
+    fn msid_without_track() {
+        let input = BytesStr::from_static("msid:-");
+
+        let (rem, msid) = Msid::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(msid.stream_id, "-");
+        assert!(msid.track_id.is_none());
+    }
+
+    #[test]
+    fn msid_print() {
+        let msid = Msid {
+            stream_id: "stream-1".into(),
+            track_id: Some("track-1".into()),
+        };
+
+        assert_eq!(msid.to_string(), "a=msid:stream-1 track-1");
+    }
+}
@@ -0,0 +1,180 @@
+//! RTCP feedback capability attribute (`a=rtcp-fb:...`)
+
+use crate::not_whitespace;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::{ws, IResult};
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::digit1;
+use nom::combinator::{map, map_res, rest};
+use nom::sequence::{preceded, tuple};
+use std::fmt;
+use std::str::FromStr;
+
+/// Type of RTCP feedback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RtcpFeedbackKind {
+    /// Generic NACK ([RFC4585](https://www.rfc-editor.org/rfc/rfc4585.html#section-6.2.1))
+    Nack,
+    /// Picture loss indication ([RFC4585](https://www.rfc-editor.org/rfc/rfc4585.html#section-6.3.1))
+    NackPli,
+    /// Full intra request ([RFC5104](https://www.rfc-editor.org/rfc/rfc5104.html#section-7.1))
+    CcmFir,
+    /// Transport wide congestion control ([draft-holmer-rmcat-transport-wide-cc-extensions](https://datatracker.ietf.org/doc/html/draft-holmer-rmcat-transport-wide-cc-extensions-01))
+    TransportCc,
+    /// Receiver estimated maximum bitrate ([draft-alvestrand-rmcat-remb](https://datatracker.ietf.org/doc/html/draft-alvestrand-rmcat-remb-03))
+    GoogRemb,
+    /// Minimal interval between regular RTCP reports in milliseconds
+    TrrInt(u32),
+    /// Any other feedback type with optional parameters
+    Other {
+        typ: BytesStr,
+        params: Option<BytesStr>,
+    },
+}
+
+/// RTCP feedback capability of a format
+///
+/// Media Level attribute, may appear multiple times
+///
+/// [RFC4585](https://www.rfc-editor.org/rfc/rfc4585.html#section-4.2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtcpFeedback {
+    /// The format the feedback applies to, `None` for all formats (`*`)
+    pub payload: Option<u32>,
+
+    /// Type of the feedback
+    pub kind: RtcpFeedbackKind,
+}
+
+impl RtcpFeedback {
+    /// Returns if the feedback applies to the given payload type
+    pub fn applies_to(&self, payload: u32) -> bool {
+        self.payload.is_none_or(|p| p == payload)
+    }
+
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("rtcp-fb:"),
+            map(
+                tuple((
+                    alt((
+                        map(tag("*"), |_| None),
+                        map(map_res(digit1, FromStr::from_str), Some),
+                    )),
+                    // type & remaining into params
+                    ws((take_while1(not_whitespace), rest)),
+                )),
+                |(payload, (typ, params))| {
+                    let params = Some(params.trim()).filter(|p| !p.is_empty());
+
+                    let kind = match (typ, params) {
+                        ("nack", None) => RtcpFeedbackKind::Nack,
+                        ("nack", Some("pli")) => RtcpFeedbackKind::NackPli,
+                        ("ccm", Some("fir")) => RtcpFeedbackKind::CcmFir,
+                        ("transport-cc", None) => RtcpFeedbackKind::TransportCc,
+                        ("goog-remb", None) => RtcpFeedbackKind::GoogRemb,
+                        ("trr-int", Some(interval)) if interval.parse::<u32>().is_ok() => {
+                            RtcpFeedbackKind::TrrInt(interval.parse().unwrap_or_default())
+                        }
+                        (typ, params) => RtcpFeedbackKind::Other {
+                            typ: BytesStr::from_parse(src, typ),
+                            params: params.map(|params| BytesStr::from_parse(src, params)),
+                        },
+                    };
+
+                    RtcpFeedback { payload, kind }
+                },
+            ),
+        )(i)
+    }
+}
+
+impl fmt::Display for RtcpFeedback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.payload {
+            Some(payload) => write!(f, "a=rtcp-fb:{} ", payload)?,
+            None => f.write_str("a=rtcp-fb:* ")?,
+        }
+
+        match &self.kind {
+            RtcpFeedbackKind::Nack => f.write_str("nack"),
+            RtcpFeedbackKind::NackPli => f.write_str("nack pli"),
+            RtcpFeedbackKind::CcmFir => f.write_str("ccm fir"),
+            RtcpFeedbackKind::TransportCc => f.write_str("transport-cc"),
+            RtcpFeedbackKind::GoogRemb => f.write_str("goog-remb"),
+            RtcpFeedbackKind::TrrInt(interval) => write!(f, "trr-int {}", interval),
+            RtcpFeedbackKind::Other { typ, params } => {
+                f.write_str(typ)?;
+
+                if let Some(params) = params {
+                    write!(f, " {}", params)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(input: &'static str) -> RtcpFeedback {
+        let input = BytesStr::from_static(input);
+
+        let (rem, rtcp_fb) = RtcpFeedback::parse(input.as_ref(), &input).unwrap();
+        assert!(rem.is_empty());
+
+        rtcp_fb
+    }
+
+    #[test]
+    fn rtcp_fb() {
+        let rtcp_fb = parse("rtcp-fb:96 nack pli");
+        assert_eq!(rtcp_fb.payload, Some(96));
+        assert_eq!(rtcp_fb.kind, RtcpFeedbackKind::NackPli);
+        assert!(rtcp_fb.applies_to(96));
+        assert!(!rtcp_fb.applies_to(97));
+
+        let rtcp_fb = parse("rtcp-fb:* transport-cc");
+        assert_eq!(rtcp_fb.payload, None);
+        assert_eq!(rtcp_fb.kind, RtcpFeedbackKind::TransportCc);
+        assert!(rtcp_fb.applies_to(97));
+
+        assert_eq!(parse("rtcp-fb:96 nack").kind, RtcpFeedbackKind::Nack);
+        assert_eq!(parse("rtcp-fb:96 ccm fir").kind, RtcpFeedbackKind::CcmFir);
+        assert_eq!(
+            parse("rtcp-fb:96 goog-remb").kind,
+            RtcpFeedbackKind::GoogRemb
+        );
+        assert_eq!(
+            parse("rtcp-fb:96 trr-int 100").kind,
+            RtcpFeedbackKind::TrrInt(100)
+        );
+        assert_eq!(
+            parse("rtcp-fb:96 ccm tmmbr smaxpr=120").kind,
+            RtcpFeedbackKind::Other {
+                typ: "ccm".into(),
+                params: Some("tmmbr smaxpr=120".into())
+            }
+        );
+    }
+
+    #[test]
+    fn rtcp_fb_print() {
+        for input in [
+            "rtcp-fb:96 nack",
+            "rtcp-fb:96 nack pli",
+            "rtcp-fb:* ccm fir",
+            "rtcp-fb:96 transport-cc",
+            "rtcp-fb:96 goog-remb",
+            "rtcp-fb:96 trr-int 100",
+            "rtcp-fb:96 ccm tmmbr smaxpr=120",
+        ] {
+            assert_eq!(parse(input).to_string(), format!("a={input}"));
+        }
+    }
+}
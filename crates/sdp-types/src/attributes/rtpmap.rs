@@ -31,6 +31,54 @@ pub struct RtpMap {
 }
 
 impl RtpMap {
+    /// Number of channels of an audio encoding, taken from the encoding parameters.
+    ///
+    /// Returns `None` if the parameters are absent, which means a single channel for audio
+    /// encodings, or not a channel count.
+    pub fn channels(&self) -> Option<u32> {
+        self.params.as_ref()?.parse().ok()
+    }
+
+    /// Returns the mapping of a static payload type, which may be used without `a=rtpmap`
+    ///
+    /// [RFC3551](https://www.rfc-editor.org/rfc/rfc3551.html#section-6)
+    pub fn from_static(payload: u32) -> Option<Self> {
+        let (encoding, clock_rate, channels) = match payload {
+            0 => ("PCMU", 8000, None),
+            3 => ("GSM", 8000, None),
+            4 => ("G723", 8000, None),
+            5 => ("DVI4", 8000, None),
+            6 => ("DVI4", 16000, None),
+            7 => ("LPC", 8000, None),
+            8 => ("PCMA", 8000, None),
+            9 => ("G722", 8000, None),
+            10 => ("L16", 44100, Some("2")),
+            11 => ("L16", 44100, None),
+            12 => ("QCELP", 8000, None),
+            13 => ("CN", 8000, None),
+            14 => ("MPA", 90000, None),
+            15 => ("G728", 8000, None),
+            16 => ("DVI4", 11025, None),
+            17 => ("DVI4", 22050, None),
+            18 => ("G729", 8000, None),
+            25 => ("CelB", 90000, None),
+            26 => ("JPEG", 90000, None),
+            28 => ("nv", 90000, None),
+            31 => ("H261", 90000, None),
+            32 => ("MPV", 90000, None),
+            33 => ("MP2T", 90000, None),
+            34 => ("H263", 90000, None),
+            _ => return None,
+        };
+
+        Some(Self {
+            payload,
+            encoding: BytesStr::from_static(encoding),
+            clock_rate,
+            params: channels.map(BytesStr::from_static),
+        })
+    }
+
     pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
         preceded(
             tag("rtpmap:"),
@@ -83,6 +131,15 @@ impl fmt::Display for RtpMap {
 mod test {
     use super::*;
 
+    #[test]
+    fn rtpmap_static() {
+        let pcma = RtpMap::from_static(8).unwrap();
+        assert_eq!(pcma.to_string(), "a=rtpmap:8 PCMA/8000");
+
+        assert_eq!(RtpMap::from_static(10).unwrap().channels(), Some(2));
+        assert!(RtpMap::from_static(96).is_none());
+    }
+
     #[test]
     fn rtpmap() {
         let input = BytesStr::from_static("rtpmap:0 PCMU/8000");
@@ -95,6 +152,7 @@ mod test {
         assert_eq!(rtpmap.encoding, "PCMU");
         assert_eq!(rtpmap.clock_rate, 8000);
         assert_eq!(rtpmap.params, None);
+        assert_eq!(rtpmap.channels(), None);
     }
 
     #[test]
@@ -108,6 +166,7 @@ mod test {
         assert_eq!(rtpmap.payload, 0);
         assert_eq!(rtpmap.encoding, "PCMU");
         assert_eq!(rtpmap.clock_rate, 8000);
+        assert_eq!(rtpmap.channels(), Some(1));
         assert_eq!(rtpmap.params.unwrap(), "1");
     }
 
@@ -0,0 +1,91 @@
+//! Legacy SCTP map attribute (`a=sctpmap:...`)
+
+use crate::token;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::{ws, IResult};
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::digit1;
+use nom::combinator::{map, map_res, opt};
+use nom::sequence::{preceded, tuple};
+use std::fmt;
+use std::str::FromStr;
+
+/// Describes the SCTP association of a `DTLS/SCTP` media description
+///
+/// Media Level attribute, replaced by `a=sctp-port` in current specifications but still sent by
+/// some endpoints
+///
+/// [draft-ietf-mmusic-sctp-sdp-05](https://datatracker.ietf.org/doc/html/draft-ietf-mmusic-sctp-sdp-05#section-5)
+#[derive(Debug, Clone)]
+pub struct SctpMap {
+    /// SCTP port, same as the format of the media description
+    pub port: u16,
+
+    /// Application protocol e.g. `webrtc-datachannel`
+    pub app: BytesStr,
+
+    /// Maximum number of streams
+    pub streams: Option<u32>,
+}
+
+impl SctpMap {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("sctpmap:"),
+            map(
+                tuple((
+                    map_res(digit1, FromStr::from_str),
+                    ws((take_while1(token),)),
+                    opt(ws((map_res(digit1, FromStr::from_str),))),
+                )),
+                |(port, (app,), streams)| SctpMap {
+                    port,
+                    app: BytesStr::from_parse(src, app),
+                    streams: streams.map(|(streams,)| streams),
+                },
+            ),
+        )(i)
+    }
+}
+
+impl fmt::Display for SctpMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=sctpmap:{} {}", self.port, self.app)?;
+
+        if let Some(streams) = self.streams {
+            write!(f, " {}", streams)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sctpmap() {
+        let input = BytesStr::from_static("sctpmap:5000 webrtc-datachannel 1024");
+
+        let (rem, sctpmap) = SctpMap::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(sctpmap.port, 5000);
+        assert_eq!(sctpmap.app, "webrtc-datachannel");
+        assert_eq!(sctpmap.streams, Some(1024));
+    }
+
+    #[test]
+    fn sctpmap_print() {
+        let sctpmap = SctpMap {
+            port: 5000,
+            app: "webrtc-datachannel".into(),
+            streams: None,
+        };
+
+        assert_eq!(sctpmap.to_string(), "a=sctpmap:5000 webrtc-datachannel");
+    }
+}
@@ -0,0 +1,223 @@
+//! Source specific attributes (`a=ssrc:...`, `a=ssrc-group:...`)
+
+use crate::attributes::msid::Msid;
+use crate::{not_whitespace, token};
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::IResult;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::{char, digit1, multispace1};
+use nom::combinator::{map, map_res, opt, rest};
+use nom::multi::many0;
+use nom::sequence::{preceded, tuple};
+use std::fmt;
+use std::str::FromStr;
+
+/// Attribute of a single RTP source
+///
+/// Media Level attribute, may appear multiple times
+///
+/// [RFC5576](https://www.rfc-editor.org/rfc/rfc5576.html#section-4.1)
+#[derive(Debug, Clone)]
+pub struct Ssrc {
+    /// Synchronization source the attribute applies to
+    pub ssrc: u32,
+
+    /// Name of the source attribute e.g. `cname`
+    pub attribute: BytesStr,
+
+    pub value: Option<BytesStr>,
+}
+
+impl Ssrc {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("ssrc:"),
+            map(
+                tuple((
+                    map_res(digit1, FromStr::from_str),
+                    multispace1,
+                    take_while1(token),
+                    opt(preceded(char(':'), rest)),
+                )),
+                |(ssrc, _, attribute, value)| Ssrc {
+                    ssrc,
+                    attribute: BytesStr::from_parse(src, attribute),
+                    value: value.map(|value| BytesStr::from_parse(src, value)),
+                },
+            ),
+        )(i)
+    }
+
+    /// Create an `msid` source attribute
+    pub fn from_msid(ssrc: u32, msid: &Msid) -> Self {
+        let value = match &msid.track_id {
+            Some(track_id) => format!("{} {}", msid.stream_id, track_id).into(),
+            None => msid.stream_id.clone(),
+        };
+
+        Ssrc {
+            ssrc,
+            attribute: BytesStr::from_static("msid"),
+            value: Some(value),
+        }
+    }
+
+    /// Returns the canonical name if this is a `cname` attribute
+    pub fn cname(&self) -> Option<&BytesStr> {
+        self.value_of("cname")
+    }
+
+    /// Returns the media stream and track if this is a `msid` attribute
+    ///
+    /// [RFC8830](https://www.rfc-editor.org/rfc/rfc8830.html#appendix-A)
+    pub fn msid(&self) -> Option<Msid> {
+        let value = self.value_of("msid")?;
+
+        let msid = match value.split_once(char::is_whitespace) {
+            Some((stream_id, track_id)) => Msid {
+                stream_id: value.slice_ref(stream_id),
+                track_id: Some(value.slice_ref(track_id.trim())),
+            },
+            None => Msid {
+                stream_id: value.clone(),
+                track_id: None,
+            },
+        };
+
+        Some(msid)
+    }
+
+    /// Returns the media stream label if this is a legacy `mslabel` attribute
+    pub fn mslabel(&self) -> Option<&BytesStr> {
+        self.value_of("mslabel")
+    }
+
+    /// Returns the track label if this is a legacy `label` attribute
+    pub fn label(&self) -> Option<&BytesStr> {
+        self.value_of("label")
+    }
+
+    fn value_of(&self, attribute: &str) -> Option<&BytesStr> {
+        if self.attribute == attribute {
+            self.value.as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Ssrc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=ssrc:{} {}", self.ssrc, self.attribute)?;
+
+        if let Some(value) = &self.value {
+            write!(f, ":{}", value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Relationship between RTP sources, e.g. retransmission (`FID`) or simulcast (`SIM`)
+///
+/// Media Level attribute, may appear multiple times
+///
+/// [RFC5576](https://www.rfc-editor.org/rfc/rfc5576.html#section-4.2)
+#[derive(Debug, Clone)]
+pub struct SsrcGroup {
+    pub semantics: BytesStr,
+    pub ssrcs: Vec<u32>,
+}
+
+impl SsrcGroup {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("ssrc-group:"),
+            map(
+                tuple((
+                    take_while1(not_whitespace),
+                    many0(preceded(multispace1, map_res(digit1, FromStr::from_str))),
+                )),
+                |(semantics, ssrcs)| SsrcGroup {
+                    semantics: BytesStr::from_parse(src, semantics),
+                    ssrcs,
+                },
+            ),
+        )(i)
+    }
+}
+
+impl fmt::Display for SsrcGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=ssrc-group:{}", self.semantics)?;
+
+        for ssrc in &self.ssrcs {
+            write!(f, " {}", ssrc)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ssrc() {
+        let input = BytesStr::from_static("ssrc:3735928559 msid:stream track");
+
+        let (rem, ssrc) = Ssrc::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(ssrc.ssrc, 3735928559);
+        assert_eq!(ssrc.attribute, "msid");
+        assert_eq!(ssrc.value.as_deref(), Some("stream track"));
+        assert_eq!(ssrc.to_string(), "a=ssrc:3735928559 msid:stream track");
+
+        let msid = ssrc.msid().unwrap();
+        assert_eq!(msid.stream_id, "stream");
+        assert_eq!(msid.track_id.as_deref(), Some("track"));
+        assert!(ssrc.cname().is_none());
+
+        assert_eq!(
+            Ssrc::from_msid(3735928559, &msid).to_string(),
+            ssrc.to_string()
+        );
+    }
+
+    #[test]
+    fn ssrc_legacy() {
+        let input = BytesStr::from_static("ssrc:1 mslabel:stream");
+
+        let (_, ssrc) = Ssrc::parse(input.as_ref(), &input).unwrap();
+
+        assert_eq!(ssrc.mslabel().unwrap(), "stream");
+        assert!(ssrc.label().is_none() && ssrc.msid().is_none());
+    }
+
+    #[test]
+    fn ssrc_without_value() {
+        let input = BytesStr::from_static("ssrc:1 x-flag");
+
+        let (rem, ssrc) = Ssrc::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+        assert!(ssrc.value.is_none());
+    }
+
+    #[test]
+    fn ssrc_group() {
+        let input = BytesStr::from_static("ssrc-group:FID 1 2");
+
+        let (rem, group) = SsrcGroup::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(group.semantics, "FID");
+        assert_eq!(group.ssrcs, [1, 2]);
+        assert_eq!(group.to_string(), "a=ssrc-group:FID 1 2");
+    }
+}
@@ -0,0 +1,300 @@
+//! T.38 fax attributes (`a=T38FaxVersion:...`, `a=T38FaxUdpEC:...`, ...)
+//!
+//! Attribute names are case-insensitive, as many gateways send them in all lowercase.
+
+use internal::IResult;
+use nom::branch::alt;
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::{char, digit1, multispace0};
+use nom::combinator::{map, map_res, opt};
+use nom::sequence::preceded;
+use std::fmt;
+use std::str::FromStr;
+
+/// Method used to train the modem connection (`a=T38FaxRateManagement`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateManagement {
+    /// Training check frame is generated locally by the receiving gateway (`localTCF`)
+    LocalTcf,
+
+    /// Training check frame is sent over the network (`transferredTCF`)
+    TransferredTcf,
+}
+
+impl fmt::Display for RateManagement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RateManagement::LocalTcf => f.write_str("localTCF"),
+            RateManagement::TransferredTcf => f.write_str("transferredTCF"),
+        }
+    }
+}
+
+/// Error correction scheme for UDPTL (`a=T38FaxUdpEC`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UdpErrorCorrection {
+    /// Forward error correction (`t38UDPFEC`)
+    Fec,
+
+    /// Redundant packets (`t38UDPRedundancy`)
+    Redundancy,
+}
+
+impl fmt::Display for UdpErrorCorrection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UdpErrorCorrection::Fec => f.write_str("t38UDPFEC"),
+            UdpErrorCorrection::Redundancy => f.write_str("t38UDPRedundancy"),
+        }
+    }
+}
+
+/// A single T.38 attribute
+///
+/// Media Level attribute
+///
+/// [ITU-T T.38 Annex D](https://www.itu.int/rec/T-REC-T.38), [RFC4612](https://www.rfc-editor.org/rfc/rfc4612.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum T38Param {
+    Version(u32),
+    MaxBitRate(u32),
+    FillBitRemoval(bool),
+    TranscodingMmr(bool),
+    TranscodingJbig(bool),
+    RateManagement(RateManagement),
+    MaxBuffer(u32),
+    MaxDatagram(u32),
+    UdpEc(UdpErrorCorrection),
+}
+
+impl T38Param {
+    pub fn parse(i: &str) -> IResult<&str, Self> {
+        alt((
+            map(value("T38FaxVersion", number), T38Param::Version),
+            map(value("T38MaxBitRate", number), T38Param::MaxBitRate),
+            map(flag("T38FaxFillBitRemoval"), T38Param::FillBitRemoval),
+            map(flag("T38FaxTranscodingMMR"), T38Param::TranscodingMmr),
+            map(flag("T38FaxTranscodingJBIG"), T38Param::TranscodingJbig),
+            map(
+                value(
+                    "T38FaxRateManagement",
+                    alt((
+                        map(tag_no_case("localTCF"), |_| RateManagement::LocalTcf),
+                        map(tag_no_case("transferredTCF"), |_| {
+                            RateManagement::TransferredTcf
+                        }),
+                    )),
+                ),
+                T38Param::RateManagement,
+            ),
+            map(value("T38FaxMaxBuffer", number), T38Param::MaxBuffer),
+            map(value("T38FaxMaxDatagram", number), T38Param::MaxDatagram),
+            map(
+                value(
+                    "T38FaxUdpEC",
+                    alt((
+                        map(tag_no_case("t38UDPFEC"), |_| UdpErrorCorrection::Fec),
+                        map(tag_no_case("t38UDPRedundancy"), |_| {
+                            UdpErrorCorrection::Redundancy
+                        }),
+                    )),
+                ),
+                T38Param::UdpEc,
+            ),
+        ))(i)
+    }
+}
+
+fn number(i: &str) -> IResult<&str, u32> {
+    map_res(digit1, FromStr::from_str)(i)
+}
+
+/// Attribute with a value, `name:value`
+fn value<'i, O>(
+    name: &'static str,
+    value: impl FnMut(&'i str) -> IResult<&'i str, O>,
+) -> impl FnMut(&'i str) -> IResult<&'i str, O> {
+    preceded(
+        preceded(tag_no_case(name), preceded(char(':'), multispace0)),
+        value,
+    )
+}
+
+/// Boolean attribute, which is true if present without value. Some implementations
+/// send an explicit `:0` or `:1`.
+fn flag<'i>(name: &'static str) -> impl FnMut(&'i str) -> IResult<&'i str, bool> {
+    map(
+        preceded(
+            tag_no_case(name),
+            opt(preceded(
+                preceded(char(':'), multispace0),
+                alt((map(char('0'), |_| false), map(char('1'), |_| true))),
+            )),
+        ),
+        |value| value.unwrap_or(true),
+    )
+}
+
+/// All T.38 attributes of a media description
+///
+/// Displays as one attribute line per set value, each terminated by CRLF.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct T38Options {
+    pub version: Option<u32>,
+
+    /// Maximum bit rate in bits per second
+    pub max_bit_rate: Option<u32>,
+
+    pub fill_bit_removal: bool,
+    pub transcoding_mmr: bool,
+    pub transcoding_jbig: bool,
+
+    pub rate_management: Option<RateManagement>,
+
+    /// Maximum number of octets the receiver can buffer
+    pub max_buffer: Option<u32>,
+
+    /// Maximum size of a UDPTL packet the receiver accepts
+    pub max_datagram: Option<u32>,
+
+    pub udp_ec: Option<UdpErrorCorrection>,
+}
+
+impl T38Options {
+    /// Set the value of the parameter, replacing any previous value
+    pub fn set(&mut self, param: T38Param) {
+        match param {
+            T38Param::Version(version) => self.version = Some(version),
+            T38Param::MaxBitRate(max_bit_rate) => self.max_bit_rate = Some(max_bit_rate),
+            T38Param::FillBitRemoval(enabled) => self.fill_bit_removal = enabled,
+            T38Param::TranscodingMmr(enabled) => self.transcoding_mmr = enabled,
+            T38Param::TranscodingJbig(enabled) => self.transcoding_jbig = enabled,
+            T38Param::RateManagement(rate_management) => {
+                self.rate_management = Some(rate_management)
+            }
+            T38Param::MaxBuffer(max_buffer) => self.max_buffer = Some(max_buffer),
+            T38Param::MaxDatagram(max_datagram) => self.max_datagram = Some(max_datagram),
+            T38Param::UdpEc(udp_ec) => self.udp_ec = Some(udp_ec),
+        }
+    }
+}
+
+impl fmt::Display for T38Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            T38Param::Version(version) => write!(f, "a=T38FaxVersion:{}", version),
+            T38Param::MaxBitRate(max_bit_rate) => write!(f, "a=T38MaxBitRate:{}", max_bit_rate),
+            T38Param::FillBitRemoval(enabled) => print_flag(f, "T38FaxFillBitRemoval", *enabled),
+            T38Param::TranscodingMmr(enabled) => print_flag(f, "T38FaxTranscodingMMR", *enabled),
+            T38Param::TranscodingJbig(enabled) => print_flag(f, "T38FaxTranscodingJBIG", *enabled),
+            T38Param::RateManagement(rate_management) => {
+                write!(f, "a=T38FaxRateManagement:{}", rate_management)
+            }
+            T38Param::MaxBuffer(max_buffer) => write!(f, "a=T38FaxMaxBuffer:{}", max_buffer),
+            T38Param::MaxDatagram(max_datagram) => {
+                write!(f, "a=T38FaxMaxDatagram:{}", max_datagram)
+            }
+            T38Param::UdpEc(udp_ec) => write!(f, "a=T38FaxUdpEC:{}", udp_ec),
+        }
+    }
+}
+
+fn print_flag(f: &mut fmt::Formatter, name: &str, enabled: bool) -> fmt::Result {
+    if enabled {
+        write!(f, "a={}", name)
+    } else {
+        write!(f, "a={}:0", name)
+    }
+}
+
+impl fmt::Display for T38Options {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(version) = self.version {
+            write!(f, "a=T38FaxVersion:{}\r\n", version)?;
+        }
+
+        if let Some(max_bit_rate) = self.max_bit_rate {
+            write!(f, "a=T38MaxBitRate:{}\r\n", max_bit_rate)?;
+        }
+
+        if self.fill_bit_removal {
+            f.write_str("a=T38FaxFillBitRemoval\r\n")?;
+        }
+
+        if self.transcoding_mmr {
+            f.write_str("a=T38FaxTranscodingMMR\r\n")?;
+        }
+
+        if self.transcoding_jbig {
+            f.write_str("a=T38FaxTranscodingJBIG\r\n")?;
+        }
+
+        if let Some(rate_management) = self.rate_management {
+            write!(f, "a=T38FaxRateManagement:{}\r\n", rate_management)?;
+        }
+
+        if let Some(max_buffer) = self.max_buffer {
+            write!(f, "a=T38FaxMaxBuffer:{}\r\n", max_buffer)?;
+        }
+
+        if let Some(max_datagram) = self.max_datagram {
+            write!(f, "a=T38FaxMaxDatagram:{}\r\n", max_datagram)?;
+        }
+
+        if let Some(udp_ec) = self.udp_ec {
+            write!(f, "a=T38FaxUdpEC:{}\r\n", udp_ec)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn t38_params() {
+        let mut options = T38Options::default();
+
+        for line in [
+            "T38FaxVersion:0",
+            "T38MaxBitRate:14400",
+            "t38faxfillbitremoval:0",
+            "T38FaxTranscodingMMR",
+            "T38FaxRateManagement:transferredTCF",
+            "T38FaxMaxDatagram:400",
+            "T38FaxUdpEC:t38UDPRedundancy",
+        ] {
+            let (rem, param) = T38Param::parse(line).unwrap();
+            assert!(rem.is_empty());
+            options.set(param);
+        }
+
+        assert_eq!(options.max_bit_rate, Some(14400));
+        assert!(!options.fill_bit_removal);
+        assert!(options.transcoding_mmr);
+        assert_eq!(
+            options.rate_management,
+            Some(RateManagement::TransferredTcf)
+        );
+        assert_eq!(options.max_datagram, Some(400));
+        assert_eq!(options.udp_ec, Some(UdpErrorCorrection::Redundancy));
+
+        assert_eq!(
+            options.to_string(),
+            "a=T38FaxVersion:0\r\n\
+a=T38MaxBitRate:14400\r\n\
+a=T38FaxTranscodingMMR\r\n\
+a=T38FaxRateManagement:transferredTCF\r\n\
+a=T38FaxMaxDatagram:400\r\n\
+a=T38FaxUdpEC:t38UDPRedundancy\r\n"
+        );
+    }
+
+    #[test]
+    fn t38_unknown_param() {
+        assert!(T38Param::parse("T38FaxUdpECDepth:1").is_err());
+        assert!(T38Param::parse("T38FaxRateManagement:other").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Connection-oriented media attributes (`a=setup:...`, `a=connection:...`)
+
+use internal::IResult;
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::combinator::map;
+use std::fmt;
+
+/// Which endpoint initiates the connection of the media transport
+///
+/// Session and Media Level attribute. Used by TCP based media (e.g. MSRP, BFCP) and to choose
+/// the DTLS roles.
+///
+/// > If not specified `active` is assumed in offers and `passive` in answers
+///
+/// [RFC4145](https://www.rfc-editor.org/rfc/rfc4145.html#section-4)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setup {
+    /// The endpoint initiates the connection
+    Active,
+
+    /// The endpoint accepts the connection
+    Passive,
+
+    /// The endpoint is willing to do either, only valid in offers
+    ActPass,
+
+    /// No connection is established for now
+    HoldConn,
+}
+
+impl Setup {
+    pub fn parse(i: &str) -> IResult<&str, Self> {
+        alt((
+            map(tag("active"), |_| Setup::Active),
+            map(tag("passive"), |_| Setup::Passive),
+            map(tag("actpass"), |_| Setup::ActPass),
+            map(tag("holdconn"), |_| Setup::HoldConn),
+        ))(i)
+    }
+
+    /// Returns the setup to answer an offer with
+    ///
+    /// For `actpass` the answerer becomes active, as recommended by
+    /// [RFC5763](https://www.rfc-editor.org/rfc/rfc5763.html#section-5).
+    pub fn answer(self) -> Self {
+        match self {
+            Setup::Active => Setup::Passive,
+            Setup::Passive | Setup::ActPass => Setup::Active,
+            Setup::HoldConn => Setup::HoldConn,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Setup::Active => "active",
+            Setup::Passive => "passive",
+            Setup::ActPass => "actpass",
+            Setup::HoldConn => "holdconn",
+        }
+    }
+}
+
+impl fmt::Display for Setup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=setup:{}", self.as_str())
+    }
+}
+
+/// Whether a new connection must be established or an existing one is reused
+///
+/// Session and Media Level attribute.
+///
+/// > If not specified `new` is assumed
+///
+/// [RFC4145](https://www.rfc-editor.org/rfc/rfc4145.html#section-5)
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpConnection {
+    #[default]
+    New,
+    Existing,
+}
+
+impl TcpConnection {
+    pub fn parse(i: &str) -> IResult<&str, Self> {
+        alt((
+            map(tag("new"), |_| TcpConnection::New),
+            map(tag("existing"), |_| TcpConnection::Existing),
+        ))(i)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TcpConnection::New => "new",
+            TcpConnection::Existing => "existing",
+        }
+    }
+}
+
+impl fmt::Display for TcpConnection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=connection:{}", self.as_str())
+    }
+}
+
+/// What the local endpoint has to do for the connection of a negotiated media description
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpRole {
+    /// Connect to the address of the peer
+    Connect,
+
+    /// Wait for the peer to connect
+    Listen,
+
+    /// Keep using the existing connection
+    Reuse,
+
+    /// Don't connect for now
+    Hold,
+}
+
+impl TcpRole {
+    /// Returns the role of the local endpoint from the setup and connection of the answer
+    ///
+    /// `None` if the answer contains `actpass`, which is invalid.
+    pub fn from_answer(
+        setup: Setup,
+        connection: TcpConnection,
+        local_is_answerer: bool,
+    ) -> Option<Self> {
+        let active = match setup {
+            Setup::HoldConn => return Some(TcpRole::Hold),
+            Setup::ActPass => return None,
+            Setup::Active => local_is_answerer,
+            Setup::Passive => !local_is_answerer,
+        };
+
+        if connection == TcpConnection::Existing {
+            Some(TcpRole::Reuse)
+        } else if active {
+            Some(TcpRole::Connect)
+        } else {
+            Some(TcpRole::Listen)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn setup() {
+        let (rem, setup) = Setup::parse("actpass").unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(setup, Setup::ActPass);
+        assert_eq!(setup.answer(), Setup::Active);
+        assert_eq!(setup.to_string(), "a=setup:actpass");
+    }
+
+    #[test]
+    fn role() {
+        let role = TcpRole::from_answer;
+
+        assert_eq!(
+            role(Setup::Active, TcpConnection::New, true),
+            Some(TcpRole::Connect)
+        );
+        assert_eq!(
+            role(Setup::Active, TcpConnection::New, false),
+            Some(TcpRole::Listen)
+        );
+        assert_eq!(
+            role(Setup::Passive, TcpConnection::Existing, false),
+            Some(TcpRole::Reuse)
+        );
+        assert_eq!(
+            role(Setup::HoldConn, TcpConnection::New, true),
+            Some(TcpRole::Hold)
+        );
+        assert_eq!(role(Setup::ActPass, TcpConnection::New, true), None);
+    }
+}
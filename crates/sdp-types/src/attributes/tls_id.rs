@@ -0,0 +1,90 @@
+//! TLS ID attribute (`a=tls-id:...`)
+
+use crate::ice_char;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::IResult;
+use nom::bytes::complete::{tag, take_while_m_n};
+use nom::combinator::map;
+use nom::sequence::preceded;
+use rand::Rng;
+use std::fmt;
+
+/// Identifies the DTLS association of a media description
+///
+/// Media Level attribute. In a subsequent offer or answer an unchanged id signals that the
+/// existing association is reused, a new id that it is replaced by a new one.
+///
+/// [RFC8842](https://www.rfc-editor.org/rfc/rfc8842.html#section-4)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsId {
+    pub id: BytesStr,
+}
+
+impl TlsId {
+    /// Generate a random id with 144 bits of randomness, the minimum is 120 bits
+    pub fn random() -> Self {
+        const TLS_ID_CHARS: &[u8] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+        let mut rng = rand::thread_rng();
+
+        let id: String = (0..24)
+            .map(|_| TLS_ID_CHARS[rng.gen_range(0..TLS_ID_CHARS.len())] as char)
+            .collect();
+
+        Self { id: id.into() }
+    }
+
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("tls-id:"),
+            map(take_while_m_n(20, 255, ice_char), |id| TlsId {
+                id: BytesStr::from_parse(src, id),
+            }),
+        )(i)
+    }
+}
+
+impl fmt::Display for TlsId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=tls-id:{}", self.id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tls_id() {
+        let input = BytesStr::from_static("tls-id:abc3de65cddef001be82abc3de65cddef001be82");
+
+        let (rem, tls_id) = TlsId::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(tls_id.id, "abc3de65cddef001be82abc3de65cddef001be82");
+    }
+
+    #[test]
+    fn tls_id_too_short() {
+        let input = BytesStr::from_static("tls-id:abc");
+
+        assert!(TlsId::parse(input.as_ref(), &input).is_err());
+    }
+
+    #[test]
+    fn tls_id_random() {
+        let first = TlsId::random();
+        let second = TlsId::random();
+
+        assert_ne!(first, second);
+
+        let printed = BytesStr::from(first.to_string());
+        let (rem, parsed) = TlsId::parse(printed.as_ref(), &printed[2..]).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(parsed, first);
+    }
+}
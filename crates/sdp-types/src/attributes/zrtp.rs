@@ -0,0 +1,92 @@
+//! ZRTP hash attribute (`a=zrtp-hash:...`)
+
+use crate::not_whitespace;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use internal::IResult;
+use nom::bytes::complete::{tag, take_while1, take_while_m_n};
+use nom::character::complete::multispace1;
+use nom::combinator::map;
+use nom::sequence::{preceded, separated_pair};
+use std::fmt;
+
+/// Hash of the ZRTP Hello message the endpoint is going to send
+///
+/// Media Level attribute. Binds the ZRTP exchange to the signalling, the media layer compares it
+/// with the hash of the received Hello message.
+///
+/// [RFC6189](https://www.rfc-editor.org/rfc/rfc6189.html#section-8.1)
+#[derive(Debug, Clone)]
+pub struct ZrtpHash {
+    /// ZRTP protocol version, e.g. `1.10`
+    pub version: BytesStr,
+
+    /// SHA-256 hash as 64 hex digits
+    pub hash: BytesStr,
+}
+
+impl ZrtpHash {
+    pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
+        preceded(
+            tag("zrtp-hash:"),
+            map(
+                separated_pair(
+                    take_while1(not_whitespace),
+                    multispace1,
+                    take_while_m_n(64, 64, |c: char| c.is_ascii_hexdigit()),
+                ),
+                |(version, hash)| ZrtpHash {
+                    version: BytesStr::from_parse(src, version),
+                    hash: BytesStr::from_parse(src, hash),
+                },
+            ),
+        )(i)
+    }
+
+    /// Returns the decoded hash
+    pub fn hash_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+
+        for (byte, hex) in bytes.iter_mut().zip(self.hash.as_bytes().chunks(2)) {
+            // The hash is validated when parsing, but the field is public
+            let hex = std::str::from_utf8(hex).unwrap_or_default();
+            *byte = u8::from_str_radix(hex, 16).unwrap_or_default();
+        }
+
+        bytes
+    }
+}
+
+impl fmt::Display for ZrtpHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a=zrtp-hash:{} {}", self.version, self.hash)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zrtp_hash() {
+        let input = BytesStr::from_static(
+            "zrtp-hash:1.10 fe30efd02423cb054e50efd0248742ac7a52c8f91bc2df881ae642c371ba46df",
+        );
+
+        let (rem, zrtp_hash) = ZrtpHash::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(zrtp_hash.version, "1.10");
+        assert_eq!(zrtp_hash.hash_bytes()[..3], [0xfe, 0x30, 0xef]);
+        assert_eq!(zrtp_hash.hash_bytes()[31], 0xdf);
+        assert_eq!(zrtp_hash.to_string(), format!("a={}", &*input));
+    }
+
+    #[test]
+    fn zrtp_hash_too_short() {
+        let input = BytesStr::from_static("zrtp-hash:1.10 fe30efd0");
+
+        assert!(ZrtpHash::parse(input.as_ref(), &input).is_err());
+    }
+}
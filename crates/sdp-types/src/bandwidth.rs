@@ -9,6 +9,63 @@ use nom::sequence::tuple;
 use std::fmt;
 use std::str::FromStr;
 
+/// Overhead of IPv4, UDP and RTP headers in bytes, used to convert between `AS` and `TIAS`
+pub const IPV4_RTP_OVERHEAD: u32 = 20 + 8 + 12;
+
+/// Overhead of IPv6, UDP and RTP headers in bytes, used to convert between `AS` and `TIAS`
+pub const IPV6_RTP_OVERHEAD: u32 = 40 + 8 + 12;
+
+/// Typed bandwidth modifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BandwidthType {
+    /// `AS`, maximum bandwidth of the application in kilobits per second including IP, UDP
+    /// and RTP overhead
+    ApplicationSpecific,
+
+    /// `CT`, total bandwidth of the conference in kilobits per second
+    ConferenceTotal,
+
+    /// `TIAS`, transport independent application specific maximum in bits per second without
+    /// any overhead ([RFC3890](https://www.rfc-editor.org/rfc/rfc3890.html))
+    TransportIndependent,
+
+    /// `RS`, RTCP bandwidth allocated to active senders in bits per second
+    /// ([RFC3556](https://www.rfc-editor.org/rfc/rfc3556.html))
+    RtcpSenders,
+
+    /// `RR`, RTCP bandwidth allocated to receivers in bits per second
+    /// ([RFC3556](https://www.rfc-editor.org/rfc/rfc3556.html))
+    RtcpReceivers,
+
+    Other(BytesStr),
+}
+
+impl BandwidthType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            BandwidthType::ApplicationSpecific => "AS",
+            BandwidthType::ConferenceTotal => "CT",
+            BandwidthType::TransportIndependent => "TIAS",
+            BandwidthType::RtcpSenders => "RS",
+            BandwidthType::RtcpReceivers => "RR",
+            BandwidthType::Other(other) => other,
+        }
+    }
+}
+
+impl From<&BytesStr> for BandwidthType {
+    fn from(modifier: &BytesStr) -> Self {
+        match modifier.as_str() {
+            "AS" => BandwidthType::ApplicationSpecific,
+            "CT" => BandwidthType::ConferenceTotal,
+            "TIAS" => BandwidthType::TransportIndependent,
+            "RS" => BandwidthType::RtcpSenders,
+            "RR" => BandwidthType::RtcpReceivers,
+            _ => BandwidthType::Other(modifier.clone()),
+        }
+    }
+}
+
 /// Bandwidth Information
 ///
 /// [RFC8866](https://www.rfc-editor.org/rfc/rfc8866.html#section-5.8)
@@ -26,6 +83,55 @@ pub struct Bandwidth {
 }
 
 impl Bandwidth {
+    pub fn new(type_: BandwidthType, bandwidth: u32) -> Self {
+        Self {
+            type_: match type_ {
+                BandwidthType::Other(other) => other,
+                type_ => type_.as_str().into(),
+            },
+            bandwidth,
+        }
+    }
+
+    /// Returns the typed bandwidth modifier
+    pub fn kind(&self) -> BandwidthType {
+        BandwidthType::from(&self.type_)
+    }
+
+    /// Returns the bandwidth in bits per second, `None` for unknown modifiers
+    pub fn bits_per_second(&self) -> Option<u64> {
+        let bandwidth = u64::from(self.bandwidth);
+
+        match self.kind() {
+            BandwidthType::ApplicationSpecific | BandwidthType::ConferenceTotal => {
+                Some(bandwidth * 1000)
+            }
+            BandwidthType::TransportIndependent
+            | BandwidthType::RtcpSenders
+            | BandwidthType::RtcpReceivers => Some(bandwidth),
+            BandwidthType::Other(_) => None,
+        }
+    }
+
+    /// Convert `TIAS` (bits per second) into `AS` (kilobits per second, rounded up) by adding
+    /// `overhead` bytes for each of the `packet_rate` packets per second
+    ///
+    /// [RFC3890](https://www.rfc-editor.org/rfc/rfc3890.html#section-6.3)
+    pub fn tias_to_as(tias: u32, packet_rate: u32, overhead: u32) -> u32 {
+        let bps = u64::from(tias) + u64::from(packet_rate) * u64::from(overhead) * 8;
+
+        u32::try_from(bps.div_ceil(1000)).unwrap_or(u32::MAX)
+    }
+
+    /// Convert `AS` (kilobits per second) into `TIAS` (bits per second) by removing `overhead`
+    /// bytes for each of the `packet_rate` packets per second
+    pub fn as_to_tias(as_: u32, packet_rate: u32, overhead: u32) -> u32 {
+        let bps = u64::from(as_) * 1000;
+        let overhead = u64::from(packet_rate) * u64::from(overhead) * 8;
+
+        u32::try_from(bps.saturating_sub(overhead)).unwrap_or(u32::MAX)
+    }
+
     pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
         map(
             tuple((
@@ -72,4 +178,29 @@ mod test {
 
         assert_eq!(origin.to_string(), "b=AS:96000");
     }
+
+    #[test]
+    fn bandwidth_kind() {
+        let tias = Bandwidth::new(BandwidthType::TransportIndependent, 64000);
+        assert_eq!(tias.to_string(), "b=TIAS:64000");
+        assert_eq!(tias.kind(), BandwidthType::TransportIndependent);
+        assert_eq!(tias.bits_per_second(), Some(64000));
+
+        let as_ = Bandwidth::new(BandwidthType::ApplicationSpecific, 80);
+        assert_eq!(as_.bits_per_second(), Some(80000));
+
+        let other = Bandwidth::new(BandwidthType::Other("X-YZ".into()), 1);
+        assert_eq!(other.kind(), BandwidthType::Other("X-YZ".into()));
+        assert_eq!(other.bits_per_second(), None);
+    }
+
+    #[test]
+    fn bandwidth_conversion() {
+        // 64 kbit/s G.711 with 20ms packets
+        assert_eq!(Bandwidth::tias_to_as(64000, 50, IPV4_RTP_OVERHEAD), 80);
+        assert_eq!(Bandwidth::tias_to_as(64001, 50, IPV4_RTP_OVERHEAD), 81);
+        assert_eq!(Bandwidth::as_to_tias(80, 50, IPV4_RTP_OVERHEAD), 64000);
+        assert_eq!(Bandwidth::as_to_tias(80, 50, IPV6_RTP_OVERHEAD), 56000);
+        assert_eq!(Bandwidth::as_to_tias(1, 50, IPV4_RTP_OVERHEAD), 0);
+    }
 }
@@ -0,0 +1,366 @@
+//! Fluent construction of SDP messages
+//!
+//! ```
+//! use ezk_sdp_types::builder::{MediaBuilder, MessageBuilder};
+//! use ezk_sdp_types::offer_answer::Codec;
+//! use ezk_sdp_types::attributes::direction::Direction;
+//!
+//! let opus = Codec {
+//!     payload: 111,
+//!     encoding: "opus".into(),
+//!     clock_rate: 48000,
+//!     channels: Some(2),
+//!     fmtp: Some("useinbandfec=1".into()),
+//! };
+//!
+//! let message = MessageBuilder::new("10.0.0.1".parse().unwrap())
+//!     .media(
+//!         MediaBuilder::audio(5000)
+//!             .codec(opus)
+//!             .direction(Direction::SendRecv),
+//!     )
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(message.media_scopes[0].desc.fmts, [111]);
+//! ```
+
+use crate::attributes::direction::Direction;
+use crate::attributes::group::Group;
+use crate::attributes::UnknownAttribute;
+use crate::connection::Connection;
+use crate::media::{MediaDescription, MediaType, TransportProtocol};
+use crate::msg::{MediaScope, Message};
+use crate::offer_answer::{add_codec, new_session_id, Codec};
+use crate::origin::Origin;
+use crate::validate::{Diagnostic, Severity};
+use bytesstr::BytesStr;
+use std::net::IpAddr;
+
+/// Errors [`Message::validate`] found in the built message
+#[derive(Debug, thiserror::Error)]
+#[error("invalid message: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+pub struct BuildError(pub Vec<Diagnostic>);
+
+/// Builder for a [`Message`]
+///
+/// Origin and connection use the given address, the session id and version are derived from
+/// the current time and the session is unbounded (`t=0 0`).
+#[derive(Debug)]
+pub struct MessageBuilder {
+    address: IpAddr,
+    name: BytesStr,
+    username: BytesStr,
+    session_id: u64,
+    session_version: u64,
+    direction: Direction,
+    groups: Vec<Group>,
+    attributes: Vec<UnknownAttribute>,
+    media_scopes: Vec<MediaScope>,
+}
+
+impl MessageBuilder {
+    pub fn new(address: IpAddr) -> Self {
+        let session_id = new_session_id();
+
+        Self {
+            address,
+            name: "-".into(),
+            username: "-".into(),
+            session_id,
+            session_version: session_id,
+            direction: Direction::default(),
+            groups: vec![],
+            attributes: vec![],
+            media_scopes: vec![],
+        }
+    }
+
+    /// Session name (s field), defaults to `-`
+    pub fn name(mut self, name: impl Into<BytesStr>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Username of the origin, defaults to `-`
+    pub fn username(mut self, username: impl Into<BytesStr>) -> Self {
+        self.username = username.into();
+        self
+    }
+
+    pub fn session_id(mut self, session_id: u64) -> Self {
+        self.session_id = session_id;
+        self
+    }
+
+    pub fn session_version(mut self, session_version: u64) -> Self {
+        self.session_version = session_version;
+        self
+    }
+
+    /// Session level direction, inherited by media which doesn't set its own
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn group(mut self, group: Group) -> Self {
+        self.groups.push(group);
+        self
+    }
+
+    /// Add a session level attribute which has no typed representation
+    pub fn attribute(mut self, name: impl Into<BytesStr>, value: Option<BytesStr>) -> Self {
+        self.attributes.push(UnknownAttribute {
+            name: name.into(),
+            value,
+        });
+        self
+    }
+
+    pub fn media(mut self, media: MediaBuilder) -> Self {
+        let mut scope = media.scope;
+        scope.direction = media.direction.unwrap_or(self.direction);
+
+        self.media_scopes.push(scope);
+        self
+    }
+
+    /// Validate and build the message
+    ///
+    /// Fails if [`Message::validate`] reports any errors, warnings are ignored.
+    pub fn build(self) -> Result<Message, BuildError> {
+        let origin = Origin {
+            username: self.username,
+            session_id: self.session_id.to_string().into(),
+            session_version: self.session_version.to_string().into(),
+            address: self.address.into(),
+        };
+
+        let mut message = Message::new(origin, self.media_scopes);
+        message.name = self.name;
+        message.direction = self.direction;
+        message.groups = self.groups;
+        message.attributes = self.attributes;
+
+        let errors: Vec<Diagnostic> = message
+            .validate()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .collect();
+
+        if errors.is_empty() {
+            Ok(message)
+        } else {
+            Err(BuildError(errors))
+        }
+    }
+}
+
+/// Builder for a [`MediaScope`], added to a message with [`MessageBuilder::media`]
+#[derive(Debug)]
+pub struct MediaBuilder {
+    scope: MediaScope,
+    direction: Option<Direction>,
+}
+
+impl MediaBuilder {
+    pub fn new(media_type: MediaType, port: u16, proto: TransportProtocol) -> Self {
+        Self {
+            scope: MediaScope::new(MediaDescription {
+                media_type,
+                port,
+                ports_num: None,
+                proto,
+                fmts: vec![],
+                named_fmts: vec![],
+            }),
+            direction: None,
+        }
+    }
+
+    /// Audio using RTP/AVP
+    pub fn audio(port: u16) -> Self {
+        Self::new(MediaType::Audio, port, TransportProtocol::RtpAvp)
+    }
+
+    /// Video using RTP/AVP
+    pub fn video(port: u16) -> Self {
+        Self::new(MediaType::Video, port, TransportProtocol::RtpAvp)
+    }
+
+    /// Add a codec with its rtpmap and fmtp, codecs are offered in the order they're added
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.scope.desc.fmts.push(codec.payload);
+        add_codec(&mut self.scope, &codec, codec.payload);
+        self
+    }
+
+    /// Media direction, defaults to the direction of the session
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    /// Connection address of the media, if it differs from the session's
+    pub fn address(mut self, address: IpAddr) -> Self {
+        self.scope.connection = Some(Connection {
+            address: address.into(),
+            ttl: None,
+            num: None,
+        });
+        self
+    }
+
+    pub fn mid(mut self, mid: impl Into<BytesStr>) -> Self {
+        self.scope.mid = Some(mid.into());
+        self
+    }
+
+    pub fn ptime(mut self, ptime: u32) -> Self {
+        self.scope.ptime = Some(ptime);
+        self
+    }
+
+    pub fn rtcp_mux(mut self) -> Self {
+        self.scope.rtcp_mux = true;
+        self
+    }
+
+    /// Add a media level attribute which has no typed representation
+    pub fn attribute(mut self, name: impl Into<BytesStr>, value: Option<BytesStr>) -> Self {
+        self.scope.attributes.push(UnknownAttribute {
+            name: name.into(),
+            value,
+        });
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attributes::rtpmap::RtpMap;
+    use crate::msg::{parse, Builder};
+    use crate::validate::DiagnosticKind;
+
+    fn pcmu() -> Codec {
+        Codec {
+            payload: 0,
+            encoding: "PCMU".into(),
+            clock_rate: 8000,
+            channels: None,
+            fmtp: None,
+        }
+    }
+
+    #[test]
+    fn build() {
+        let message = MessageBuilder::new("10.0.0.1".parse().unwrap())
+            .session_id(1)
+            .session_version(2)
+            .direction(Direction::SendOnly)
+            .media(MediaBuilder::audio(5000).codec(pcmu()).mid("0").ptime(20))
+            .media(
+                MediaBuilder::video(6000)
+                    .codec(Codec {
+                        payload: 96,
+                        encoding: "VP8".into(),
+                        clock_rate: 90000,
+                        channels: None,
+                        fmtp: None,
+                    })
+                    .direction(Direction::Inactive)
+                    .rtcp_mux(),
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            message.to_string(),
+            "v=0\r
+o=- 1 2 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+m=audio 5000 RTP/AVP 0\r
+a=mid:0\r
+a=sendonly\r
+a=rtpmap:0 PCMU/8000\r
+a=ptime:20\r
+m=video 6000 RTP/AVP 96\r
+a=inactive\r
+a=rtcp-mux\r
+a=rtpmap:96 VP8/90000\r
+"
+        );
+
+        let parsed = parse::<Builder>(&BytesStr::from(message.to_string())).unwrap();
+        assert_eq!(parsed.media_scopes.len(), 2);
+    }
+
+    fn errors(result: Result<Message, BuildError>) -> Vec<(Option<usize>, DiagnosticKind)> {
+        result
+            .unwrap_err()
+            .0
+            .into_iter()
+            .map(|diagnostic| (diagnostic.media, diagnostic.kind))
+            .collect()
+    }
+
+    #[test]
+    fn validate() {
+        let builder = || MessageBuilder::new("10.0.0.1".parse().unwrap());
+
+        assert_eq!(
+            errors(builder().media(MediaBuilder::audio(5000)).build()),
+            [(Some(0), DiagnosticKind::NoFormats)]
+        );
+
+        assert_eq!(
+            errors(
+                builder()
+                    .media(MediaBuilder::audio(5000).codec(pcmu()).codec(pcmu()))
+                    .build()
+            ),
+            [(Some(0), DiagnosticKind::DuplicatePayload(0))]
+        );
+
+        let mut media = MediaBuilder::audio(5000);
+        media.scope.desc.fmts.push(100);
+        assert_eq!(
+            errors(builder().media(media).build()),
+            [(Some(0), DiagnosticKind::MissingRtpMap(100))]
+        );
+
+        // Dynamic payload types of RTP profiles not known by name need an rtpmap as well
+        let mut media = MediaBuilder::new(
+            MediaType::Audio,
+            5000,
+            TransportProtocol::Other("UDP/TLS/RTP/SAVPF".into()),
+        );
+        media.scope.desc.fmts.push(100);
+        assert!(errors(builder().media(media).build())
+            .contains(&(Some(0), DiagnosticKind::MissingRtpMap(100))));
+
+        let mut media = MediaBuilder::audio(5000);
+        media.scope.desc.fmts.push(100);
+        media.scope.rtpmaps.push(RtpMap {
+            payload: 100,
+            encoding: "telephone-event".into(),
+            clock_rate: 8000,
+            params: None,
+        });
+        assert!(builder().media(media).build().is_ok());
+
+        assert_eq!(
+            errors(
+                builder()
+                    .media(MediaBuilder::audio(5000).codec(pcmu()).mid("a"))
+                    .media(MediaBuilder::audio(5002).codec(pcmu()).mid("a"))
+                    .build()
+            ),
+            [(Some(1), DiagnosticKind::DuplicateMid("a".into()))]
+        );
+    }
+}
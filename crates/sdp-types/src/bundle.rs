@@ -0,0 +1,341 @@
+//! Helpers to negotiate BUNDLE groups ([RFC9143](https://www.rfc-editor.org/rfc/rfc9143.html))
+//!
+//! Media descriptions in a BUNDLE group share a single transport, which is described by the
+//! media description of the bundle tag.
+
+use crate::attributes::group::{Group, GroupSemantics};
+use crate::msg::{MediaScope, Message};
+use bytesstr::BytesStr;
+
+/// Semantics of a BUNDLE group
+pub const BUNDLE: &str = "BUNDLE";
+
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    #[error("group does not have BUNDLE semantics")]
+    NotBundle,
+    #[error("mid {0} is not used by any media description")]
+    UnknownMid(BytesStr),
+    #[error("mid {0} is part of multiple BUNDLE groups")]
+    DuplicateMid(BytesStr),
+    #[error("no media description can be used as bundle tag")]
+    NoTag,
+    #[error("media description {0} does not share the transport of the bundle tag")]
+    TransportMismatch(BytesStr),
+}
+
+impl Group {
+    /// Returns if the group has BUNDLE semantics
+    pub fn is_bundle(&self) -> bool {
+        self.kind() == GroupSemantics::Bundle
+    }
+}
+
+impl Message {
+    /// Returns all groups with BUNDLE semantics
+    pub fn bundle_groups(&self) -> impl Iterator<Item = &Group> {
+        self.groups.iter().filter(|group| group.is_bundle())
+    }
+
+    /// Returns the BUNDLE group the mid is part of
+    pub fn bundle_group_of(&self, mid: &str) -> Option<&Group> {
+        self.bundle_groups().find(|group| group.contains(mid))
+    }
+
+    /// Returns the media description with the given mid
+    pub fn media_by_mid(&self, mid: &str) -> Option<&MediaScope> {
+        self.media_scopes
+            .iter()
+            .find(|media| media.mid.as_deref() == Some(mid))
+    }
+
+    /// Returns the mid of the bundle tag, the first member of the group which isn't
+    /// bundle-only (port 0)
+    pub fn bundle_tag<'g>(&self, group: &'g Group) -> Option<&'g BytesStr> {
+        group.mids.iter().find(|mid| {
+            self.media_by_mid(mid)
+                .is_some_and(|media| media.desc.port != 0 && !media.bundle_only)
+        })
+    }
+
+    /// Mark every member of the group but the bundle tag as bundle-only in this offer
+    ///
+    /// The members get port 0 and `a=bundle-only`, so they are only accepted by an answerer
+    /// supporting BUNDLE ([RFC9143](https://www.rfc-editor.org/rfc/rfc9143.html#section-7.2.1)).
+    /// This is what browsers expect when using the max-bundle policy.
+    ///
+    /// Returns the mid of the bundle tag or `None` if the group has none.
+    pub fn offer_bundle_only(&mut self, group: &Group) -> Option<BytesStr> {
+        let tag = self.bundle_tag(group)?.clone();
+
+        for media in &mut self.media_scopes {
+            let Some(mid) = &media.mid else {
+                continue;
+            };
+
+            if *mid != tag && group.contains(mid) {
+                media.desc.port = 0;
+                media.bundle_only = true;
+            }
+        }
+
+        Some(tag)
+    }
+
+    /// Validate a BUNDLE group against the media descriptions of the message
+    ///
+    /// Every mid must belong to exactly one media description and BUNDLE group, and a bundle tag
+    /// must be available.
+    ///
+    /// With `shared_transport` set, which is required for answers and all offers but the initial
+    /// one, members must also use the address, port and ICE credentials of the bundle tag.
+    /// Bundle-only members (port 0) are exempt.
+    pub fn validate_bundle(
+        &self,
+        group: &Group,
+        shared_transport: bool,
+    ) -> Result<(), BundleError> {
+        if !group.is_bundle() {
+            return Err(BundleError::NotBundle);
+        }
+
+        for mid in &group.mids {
+            if self.media_by_mid(mid).is_none() {
+                return Err(BundleError::UnknownMid(mid.clone()));
+            }
+
+            let groups = self.bundle_groups().filter(|g| g.contains(mid)).count();
+
+            if groups > 1 {
+                return Err(BundleError::DuplicateMid(mid.clone()));
+            }
+        }
+
+        let tag = self.bundle_tag(group).ok_or(BundleError::NoTag)?;
+
+        if !shared_transport {
+            return Ok(());
+        }
+
+        let tag = self.media_by_mid(tag).ok_or(BundleError::NoTag)?;
+
+        for mid in &group.mids {
+            let media = self
+                .media_by_mid(mid)
+                .ok_or_else(|| BundleError::UnknownMid(mid.clone()))?;
+
+            if media.desc.port != 0 && !self.shares_transport(media, tag) {
+                return Err(BundleError::TransportMismatch(mid.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Accept the BUNDLE group of an offer, rewriting this answer
+    ///
+    /// Members rejected in the answer (port 0) or missing a mid are left out of the group. The
+    /// first remaining member becomes the bundle tag. Its address and port are copied to the other
+    /// members and their ICE credentials and candidates are removed, since they're taken from the
+    /// bundle tag. All members are marked to use rtcp-mux.
+    ///
+    /// Replaces existing BUNDLE groups containing the members, returns the mid of the bundle tag
+    /// or `None` if no member remains.
+    pub fn accept_bundle(&mut self, offer: &Group) -> Option<BytesStr> {
+        let mids: Vec<BytesStr> = offer
+            .mids
+            .iter()
+            .filter(|mid| {
+                self.media_by_mid(mid)
+                    .is_some_and(|media| media.desc.port != 0)
+            })
+            .cloned()
+            .collect();
+
+        let tag = mids.first()?.clone();
+        let tag_media = self.media_by_mid(&tag)?;
+        let port = tag_media.desc.port;
+        let connection = tag_media.connection.clone();
+
+        for media in &mut self.media_scopes {
+            let Some(mid) = &media.mid else {
+                continue;
+            };
+
+            if !mids.contains(mid) {
+                continue;
+            }
+
+            media.rtcp_mux = true;
+
+            // Only allowed in offers
+            media.bundle_only = false;
+
+            if *mid != tag {
+                media.desc.port = port;
+                media.connection.clone_from(&connection);
+                media.ice_ufrag = None;
+                media.ice_pwd = None;
+                media.ice_candidates.clear();
+                media.ice_end_of_candidates = false;
+                media.ice_remote_candidates = None;
+            }
+        }
+
+        self.groups
+            .retain(|group| !(group.is_bundle() && group.mids.iter().any(|m| mids.contains(m))));
+        self.groups.push(Group {
+            semantics: BUNDLE.into(),
+            mids,
+        });
+
+        Some(tag)
+    }
+
+    /// Reject bundling by removing every BUNDLE group from this answer
+    ///
+    /// Each media description keeps its own transport. Media descriptions which were offered as
+    /// bundle-only must be rejected by the caller.
+    pub fn reject_bundle(&mut self) {
+        self.groups.retain(|group| !group.is_bundle());
+    }
+
+    /// Returns if the media description uses the address, port and ICE credentials of the tag
+    fn shares_transport(&self, media: &MediaScope, tag: &MediaScope) -> bool {
+        let address = |m: &MediaScope| {
+            m.connection
+                .as_ref()
+                .or(self.connection.as_ref())
+                .map(|c| c.address.to_string())
+        };
+
+        // Credentials missing at media level are taken from the bundle tag
+        let ufrag = |m: &MediaScope| {
+            m.ice_ufrag
+                .as_ref()
+                .or(self.ice_ufrag.as_ref())
+                .map(|u| u.ufrag.clone())
+        };
+        let pwd = |m: &MediaScope| {
+            m.ice_pwd
+                .as_ref()
+                .or(self.ice_pwd.as_ref())
+                .map(|p| p.pwd.clone())
+        };
+
+        media.desc.port == tag.desc.port
+            && address(media) == address(tag)
+            && (media.ice_ufrag.is_none() || ufrag(media) == ufrag(tag))
+            && (media.ice_pwd.is_none() || pwd(media) == pwd(tag))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::msg::{parse, Builder};
+
+    fn offer() -> Message {
+        parse::<Builder>(&BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+a=group:BUNDLE a v d\r\n\
+m=audio 1000 RTP/AVP 0\r\n\
+a=mid:a\r\n\
+a=ice-ufrag:ufrag1\r\n\
+a=ice-pwd:password1password1password1\r\n\
+m=video 2000 RTP/AVP 96\r\n\
+a=mid:v\r\n\
+a=ice-ufrag:ufrag2\r\n\
+a=ice-pwd:password2password2password2\r\n\
+m=application 0 UDP/DTLS/SCTP 5000\r\n\
+a=mid:d\r\n",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn validate() {
+        let offer = offer();
+        let group = &offer.groups[0];
+
+        assert!(group.is_bundle());
+        assert_eq!(offer.bundle_tag(group).unwrap(), "a");
+        assert!(offer.validate_bundle(group, false).is_ok());
+        assert!(matches!(
+            offer.validate_bundle(group, true),
+            Err(BundleError::TransportMismatch(mid)) if mid == "v"
+        ));
+
+        let unknown = Group {
+            semantics: BUNDLE.into(),
+            mids: vec!["a".into(), "x".into()],
+        };
+        assert!(matches!(
+            offer.validate_bundle(&unknown, false),
+            Err(BundleError::UnknownMid(mid)) if mid == "x"
+        ));
+
+        let bundle_only = Group {
+            semantics: BUNDLE.into(),
+            mids: vec!["d".into()],
+        };
+        assert!(matches!(
+            offer.validate_bundle(&bundle_only, false),
+            Err(BundleError::NoTag)
+        ));
+    }
+
+    #[test]
+    fn accept() {
+        let mut answer = offer();
+        let offered = answer.groups[0].clone();
+
+        // Move the audio transport into the video section to make it the tag
+        answer.media_scopes[0].desc.port = 0;
+        answer.media_scopes[2].desc.port = 3000;
+
+        let tag = answer.accept_bundle(&offered).unwrap();
+        assert_eq!(tag, "v");
+
+        assert_eq!(answer.groups.len(), 1);
+        assert_eq!(answer.groups[0].mids, ["v", "d"]);
+
+        let data = answer.media_by_mid("d").unwrap();
+        assert_eq!(data.desc.port, 2000);
+        assert!(data.rtcp_mux);
+        assert!(answer.validate_bundle(&answer.groups[0], true).is_ok());
+
+        assert!(answer.to_string().contains("a=group:BUNDLE v d\r\n"));
+
+        answer.reject_bundle();
+        assert!(answer.bundle_groups().next().is_none());
+    }
+
+    #[test]
+    fn bundle_only() {
+        let mut offer = offer();
+        let group = offer.groups[0].clone();
+
+        let tag = offer.offer_bundle_only(&group).unwrap();
+        assert_eq!(tag, "a");
+
+        assert!(!offer.media_scopes[0].bundle_only);
+        assert_eq!(offer.media_scopes[0].desc.port, 1000);
+
+        let video = offer.media_by_mid("v").unwrap();
+        assert!(video.bundle_only);
+        assert_eq!(video.desc.port, 0);
+        assert!(offer.validate_bundle(&group, true).is_ok());
+
+        let printed = offer.to_string();
+        assert!(printed.contains("m=video 0 RTP/AVP 96\r\na=mid:v\r\na=bundle-only\r\n"));
+
+        let parsed = parse::<Builder>(&BytesStr::from(printed)).unwrap();
+        assert!(parsed.media_scopes[1].bundle_only && parsed.media_scopes[2].bundle_only);
+        assert_eq!(parsed.bundle_tag(&parsed.groups[0]).unwrap(), "a");
+    }
+}
@@ -0,0 +1,546 @@
+//! Semantic comparison and targeted modification of messages
+//!
+//! Media descriptions are compared by their position, as required by the offer/answer model
+//! ([RFC3264](https://www.rfc-editor.org/rfc/rfc3264.html#section-8)).
+
+use crate::attributes::direction::Direction;
+use crate::attributes::fmtp::Fmtp;
+use crate::attributes::UnknownAttribute;
+use crate::connection::Connection;
+use crate::msg::{MediaScope, Message};
+use bytesstr::BytesStr;
+use std::net::IpAddr;
+
+/// Difference between two messages, returned by [`Message::diff`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Session level connection address changed
+    SessionConnection,
+    MediaAdded(usize),
+    MediaRemoved(usize),
+    Direction {
+        media: usize,
+        old: Direction,
+        new: Direction,
+    },
+    CodecAdded {
+        media: usize,
+        payload: u32,
+    },
+    CodecRemoved {
+        media: usize,
+        payload: u32,
+    },
+    /// rtpmap or fmtp of the payload type changed
+    CodecChanged {
+        media: usize,
+        payload: u32,
+    },
+    /// Formats which aren't payload types (e.g. `webrtc-datachannel`) changed
+    Formats {
+        media: usize,
+    },
+    /// Transport protocol or port changed
+    Transport {
+        media: usize,
+    },
+    /// Connection address used by the media changed, either at media or session level
+    Connection {
+        media: usize,
+    },
+    /// ICE username fragment or password used by the media changed, either at media or session
+    /// level, which signals an ICE restart
+    IceCredentials {
+        media: usize,
+    },
+}
+
+impl Message {
+    /// Compute the changes required to get from `self` to `other`
+    ///
+    /// Only changes relevant to media handling are reported, e.g. a changed session name is not.
+    pub fn diff(&self, other: &Message) -> Vec<Change> {
+        let mut changes = vec![];
+
+        if connection_str(self.connection.as_ref()) != connection_str(other.connection.as_ref()) {
+            changes.push(Change::SessionConnection);
+        }
+
+        for (media, (old, new)) in self
+            .media_scopes
+            .iter()
+            .zip(&other.media_scopes)
+            .enumerate()
+        {
+            if old.direction != new.direction {
+                changes.push(Change::Direction {
+                    media,
+                    old: old.direction,
+                    new: new.direction,
+                });
+            }
+
+            for &payload in &new.desc.fmts {
+                if !old.desc.fmts.contains(&payload) {
+                    changes.push(Change::CodecAdded { media, payload });
+                } else if codec_str(old, payload) != codec_str(new, payload) {
+                    changes.push(Change::CodecChanged { media, payload });
+                }
+            }
+
+            for &payload in &old.desc.fmts {
+                if !new.desc.fmts.contains(&payload) {
+                    changes.push(Change::CodecRemoved { media, payload });
+                }
+            }
+
+            if old.desc.named_fmts != new.desc.named_fmts {
+                changes.push(Change::Formats { media });
+            }
+
+            if old.desc.proto != new.desc.proto || old.desc.port != new.desc.port {
+                changes.push(Change::Transport { media });
+            }
+
+            let old_connection = old.connection.as_ref().or(self.connection.as_ref());
+            let new_connection = new.connection.as_ref().or(other.connection.as_ref());
+
+            if connection_str(old_connection) != connection_str(new_connection) {
+                changes.push(Change::Connection { media });
+            }
+
+            if ice_credentials(self, old) != ice_credentials(other, new) {
+                changes.push(Change::IceCredentials { media });
+            }
+        }
+
+        for media in other.media_scopes.len()..self.media_scopes.len() {
+            changes.push(Change::MediaRemoved(media));
+        }
+
+        for media in self.media_scopes.len()..other.media_scopes.len() {
+            changes.push(Change::MediaAdded(media));
+        }
+
+        changes
+    }
+
+    /// Returns if both messages describe the same session
+    ///
+    /// Differences without meaning are ignored: the origin's session version, the order of
+    /// attributes whose order is irrelevant (rtpmaps, fmtps, candidates, unknown attributes, ...),
+    /// the case of encoding and attribute names and the order of format parameters.
+    /// The order of formats in the media descriptions is significant, as it expresses a
+    /// preference.
+    ///
+    /// Use this to check if a re-INVITE actually changes the session before restarting media.
+    pub fn canonical_eq(&self, other: &Message) -> bool {
+        self.normalized().to_string() == other.normalized().to_string()
+    }
+
+    /// Returns a copy of the message in the form used by [`Message::canonical_eq`]
+    pub fn normalized(&self) -> Message {
+        let mut message = self.clone();
+
+        message.origin.session_version = "0".into();
+        normalize_attributes(&mut message.attributes);
+        sort_by_string(&mut message.extmaps);
+
+        for media in &mut message.media_scopes {
+            for rtpmap in &mut media.rtpmaps {
+                rtpmap.encoding = rtpmap.encoding.to_ascii_lowercase().into();
+            }
+
+            for fmtp in &mut media.fmtps {
+                let mut params: Vec<String> = fmtp
+                    .iter_params()
+                    .map(|(key, value)| match value {
+                        Some(value) => format!("{}={}", key.to_ascii_lowercase(), value),
+                        None => key.to_ascii_lowercase(),
+                    })
+                    .collect();
+
+                params.sort();
+                fmtp.params = params.join(";").into();
+            }
+
+            media.rtpmaps.sort_by_key(|rtpmap| rtpmap.payload);
+            media.fmtps.sort_by_key(|fmtp| fmtp.format);
+            sort_by_string(&mut media.rtcp_fb);
+            sort_by_string(&mut media.extmaps);
+            sort_by_string(&mut media.ice_candidates);
+            sort_by_string(&mut media.ssrcs);
+            normalize_attributes(&mut media.attributes);
+        }
+
+        message
+    }
+
+    /// Set the direction of the session and all media descriptions
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+
+        for media in &mut self.media_scopes {
+            media.direction = direction;
+        }
+    }
+
+    /// Replace the connection address at session level and of every media description which
+    /// has its own, including addresses of `a=rtcp` attributes
+    pub fn set_connection_address(&mut self, address: IpAddr) {
+        match &mut self.connection {
+            Some(connection) => connection.address = address.into(),
+            None => {
+                self.connection = Some(Connection {
+                    address: address.into(),
+                    ttl: None,
+                    num: None,
+                })
+            }
+        }
+
+        for media in &mut self.media_scopes {
+            if let Some(connection) = &mut media.connection {
+                connection.address = address.into();
+            }
+
+            if let Some(rtcp_address) = media
+                .rtcp_attr
+                .as_mut()
+                .and_then(|rtcp| rtcp.address.as_mut())
+            {
+                *rtcp_address = address.into();
+            }
+        }
+    }
+}
+
+impl MediaScope {
+    /// Remove the payload type and all attributes referring to it
+    ///
+    /// Payload types which depend on it are removed as well: RTX formats whose fmtp references
+    /// it with `apt=<payload>` and RED formats listing it as redundant encoding (e.g. `111/111`).
+    ///
+    /// The last format of a media description can't be removed, as it must contain at least one.
+    /// Use [`MediaScope::rejected`] to disable the media instead.
+    ///
+    /// Returns if the payload type was removed.
+    pub fn remove_codec(&mut self, payload: u32) -> bool {
+        if !self.desc.fmts.contains(&payload) {
+            return false;
+        }
+
+        let mut removed = vec![payload];
+        let mut i = 0;
+
+        while let Some(&payload) = removed.get(i) {
+            for fmtp in &self.fmtps {
+                if !removed.contains(&fmtp.format) && depends_on(fmtp, payload) {
+                    removed.push(fmtp.format);
+                }
+            }
+
+            i += 1;
+        }
+
+        if self.desc.named_fmts.is_empty() && self.desc.fmts.iter().all(|f| removed.contains(f)) {
+            return false;
+        }
+
+        self.desc.fmts.retain(|fmt| !removed.contains(fmt));
+        self.rtpmaps
+            .retain(|rtpmap| !removed.contains(&rtpmap.payload));
+        self.fmtps.retain(|fmtp| !removed.contains(&fmtp.format));
+        self.rtcp_fb
+            .retain(|rtcp_fb| rtcp_fb.payload.is_none_or(|p| !removed.contains(&p)));
+        self.imageattrs
+            .retain(|imageattr| imageattr.payload.is_none_or(|p| !removed.contains(&p)));
+
+        true
+    }
+
+    /// Remove all payload types with the given encoding name (e.g. `telephone-event`), returns
+    /// the removed payload types
+    ///
+    /// Like with [`MediaScope::remove_codec`] the last format is kept.
+    pub fn remove_codecs_by_name(&mut self, encoding: &str) -> Vec<u32> {
+        let payloads: Vec<u32> = self
+            .rtpmaps
+            .iter()
+            .filter(|rtpmap| rtpmap.encoding.eq_ignore_ascii_case(encoding))
+            .map(|rtpmap| rtpmap.payload)
+            .collect();
+
+        payloads
+            .into_iter()
+            .filter(|&payload| self.remove_codec(payload))
+            .collect()
+    }
+}
+
+/// Returns if the format of the fmtp references `payload`, as RTX (`apt=<payload>`,
+/// [RFC4588](https://www.rfc-editor.org/rfc/rfc4588.html#section-8.1)) or RED (`111/111`,
+/// [RFC2198](https://www.rfc-editor.org/rfc/rfc2198.html#section-5)) does
+fn depends_on(fmtp: &Fmtp, payload: u32) -> bool {
+    let payload = payload.to_string();
+
+    fmtp.iter_params().any(|(key, value)| match value {
+        Some(value) => key.eq_ignore_ascii_case("apt") && value == payload,
+        None => key.split('/').any(|p| p.trim() == payload),
+    })
+}
+
+fn normalize_attributes(attributes: &mut [UnknownAttribute]) {
+    for attr in attributes.iter_mut() {
+        attr.name = attr.name.to_ascii_lowercase().into();
+    }
+
+    sort_by_string(attributes);
+}
+
+fn sort_by_string<T: ToString>(items: &mut [T]) {
+    items.sort_by_cached_key(ToString::to_string);
+}
+
+fn connection_str(connection: Option<&Connection>) -> Option<String> {
+    connection.map(ToString::to_string)
+}
+
+fn ice_credentials<'a>(
+    message: &'a Message,
+    media: &'a MediaScope,
+) -> (Option<&'a BytesStr>, Option<&'a BytesStr>) {
+    let ufrag = media.ice_ufrag.as_ref().or(message.ice_ufrag.as_ref());
+    let pwd = media.ice_pwd.as_ref().or(message.ice_pwd.as_ref());
+
+    (ufrag.map(|ufrag| &ufrag.ufrag), pwd.map(|pwd| &pwd.pwd))
+}
+
+fn codec_str(media: &MediaScope, payload: u32) -> (Option<String>, Option<BytesStr>) {
+    let rtpmap = media
+        .rtpmaps
+        .iter()
+        .find(|rtpmap| rtpmap.payload == payload)
+        .map(ToString::to_string);
+
+    let fmtp = media
+        .fmtps
+        .iter()
+        .find(|fmtp| fmtp.format == payload)
+        .map(|fmtp| fmtp.params.clone());
+
+    (rtpmap, fmtp)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::msg::{parse, Builder};
+
+    fn sdp(sdp: &'static str) -> Message {
+        parse::<Builder>(&BytesStr::from_static(sdp)).unwrap()
+    }
+
+    const OLD: &str = "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+m=audio 1000 RTP/AVP 0 8 101\r
+a=rtpmap:101 telephone-event/8000\r
+a=fmtp:101 0-15\r
+m=video 2000 RTP/AVP 96\r
+a=rtpmap:96 VP8/90000\r
+";
+
+    #[test]
+    fn diff() {
+        let old = sdp(OLD);
+        let new = sdp("v=0\r
+o=- 1 2 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+m=audio 1000 RTP/AVP 8 101 9\r
+a=sendonly\r
+a=rtpmap:101 telephone-event/8000\r
+a=fmtp:101 0-16\r
+m=video 3000 RTP/SAVP 96\r
+c=IN IP4 10.0.0.2\r
+a=rtpmap:96 VP8/90000\r
+m=audio 0 RTP/AVP 0\r
+");
+
+        assert_eq!(
+            old.diff(&new),
+            [
+                Change::Direction {
+                    media: 0,
+                    old: Direction::SendRecv,
+                    new: Direction::SendOnly
+                },
+                Change::CodecChanged {
+                    media: 0,
+                    payload: 101
+                },
+                Change::CodecAdded {
+                    media: 0,
+                    payload: 9
+                },
+                Change::CodecRemoved {
+                    media: 0,
+                    payload: 0
+                },
+                Change::Transport { media: 1 },
+                Change::Connection { media: 1 },
+                Change::MediaAdded(2),
+            ]
+        );
+
+        assert_eq!(new.diff(&old).last(), Some(&Change::MediaRemoved(2)));
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn diff_formats_and_ice() {
+        let old = sdp("v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+a=ice-ufrag:abcd\r
+a=ice-pwd:aaaaaaaaaaaaaaaaaaaaaa\r
+m=application 1000 UDP/DTLS/SCTP webrtc-datachannel\r
+m=audio 2000 RTP/AVP 0\r
+a=ice-ufrag:efgh\r
+a=ice-pwd:bbbbbbbbbbbbbbbbbbbbbb\r
+");
+        let new = sdp("v=0\r
+o=- 1 2 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+a=ice-ufrag:ijkl\r
+a=ice-pwd:cccccccccccccccccccccc\r
+m=application 1000 UDP/DTLS/SCTP x-other\r
+m=audio 2000 RTP/AVP 0\r
+a=ice-ufrag:efgh\r
+a=ice-pwd:bbbbbbbbbbbbbbbbbbbbbb\r
+");
+
+        // The audio media keeps its own credentials
+        assert_eq!(
+            old.diff(&new),
+            [
+                Change::Formats { media: 0 },
+                Change::IceCredentials { media: 0 }
+            ]
+        );
+    }
+
+    #[test]
+    fn canonical_eq() {
+        let old = sdp(OLD);
+        let reordered = sdp("v=0\r
+o=- 1 2 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+m=audio 1000 RTP/AVP 0 8 101\r
+a=fmtp:101 0-15\r
+a=rtpmap:101 Telephone-Event/8000\r
+m=video 2000 RTP/AVP 96\r
+a=X-Custom:1\r
+a=rtpmap:96 vp8/90000\r
+");
+
+        assert!(!old.canonical_eq(&reordered));
+
+        let mut old = old;
+        old.media_scopes[1].attributes.push(UnknownAttribute {
+            name: "x-custom".into(),
+            value: Some("1".into()),
+        });
+        assert!(old.canonical_eq(&reordered));
+
+        let mut changed = reordered.clone();
+        changed.media_scopes[0].desc.fmts.swap(0, 1);
+        assert!(!old.canonical_eq(&changed));
+
+        let mut fmtp = sdp(OLD);
+        fmtp.media_scopes[0].fmtps[0].params = "a=1; B=2".into();
+        let mut reordered = fmtp.clone();
+        reordered.media_scopes[0].fmtps[0].params = "b=2;a=1".into();
+        assert!(fmtp.canonical_eq(&reordered));
+    }
+
+    #[test]
+    fn mutate() {
+        let mut msg = sdp(OLD);
+
+        // The last format is kept
+        assert!(!msg.media_scopes[1].remove_codec(96));
+        assert!(!msg.media_scopes[1].remove_codec(97));
+        assert_eq!(msg.media_scopes[1].rtpmaps.len(), 1);
+        assert!(msg.media_scopes[0].remove_codec(8));
+
+        assert_eq!(
+            msg.media_scopes[0].remove_codecs_by_name("telephone-event"),
+            [101]
+        );
+        assert!(msg.media_scopes[0].fmtps.is_empty());
+
+        msg.set_direction(Direction::Inactive);
+        msg.set_connection_address("192.168.0.1".parse().unwrap());
+
+        assert_eq!(
+            msg.to_string(),
+            "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 192.168.0.1\r
+t=0 0\r
+m=audio 1000 RTP/AVP 0\r
+a=inactive\r
+m=video 2000 RTP/AVP 96\r
+a=inactive\r
+a=rtpmap:96 VP8/90000\r
+"
+        );
+    }
+
+    #[test]
+    fn remove_dependent_codecs() {
+        let mut msg = sdp("v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+m=audio 1000 RTP/AVP 111 63 0\r
+a=rtpmap:111 opus/48000/2\r
+a=rtpmap:63 red/48000/2\r
+a=fmtp:63 111/111\r
+m=video 2000 RTP/AVP 96 97 98 99\r
+a=rtpmap:96 VP8/90000\r
+a=rtcp-fb:96 nack\r
+a=rtpmap:97 rtx/90000\r
+a=fmtp:97 apt=96\r
+a=rtpmap:98 VP9/90000\r
+a=rtpmap:99 rtx/90000\r
+a=fmtp:99 apt=98\r
+");
+
+        assert!(msg.media_scopes[0].remove_codec(111));
+        assert_eq!(msg.media_scopes[0].desc.fmts, [0]);
+        assert!(msg.media_scopes[0].fmtps.is_empty());
+
+        assert!(msg.media_scopes[1].remove_codec(96));
+        assert_eq!(msg.media_scopes[1].desc.fmts, [98, 99]);
+        assert!(msg.media_scopes[1].rtcp_fb.is_empty());
+        assert_eq!(msg.media_scopes[1].fmtps[0].format, 99);
+
+        // Removing VP9 would also remove its RTX format, leaving no formats
+        assert!(!msg.media_scopes[1].remove_codec(98));
+        assert_eq!(msg.media_scopes[1].desc.fmts, [98, 99]);
+    }
+}
@@ -12,11 +12,20 @@ use std::str::FromStr;
 
 pub mod attributes;
 pub mod bandwidth;
+pub mod builder;
+pub mod bundle;
 pub mod connection;
+pub mod diff;
 pub mod media;
 pub mod msg;
+pub mod offer_answer;
 pub mod origin;
+pub mod plan;
+pub mod preserve;
+pub mod rtp;
+pub mod sdpfrag;
 pub mod time;
+pub mod validate;
 
 #[derive(Debug, Clone)]
 pub enum TaggedAddress {
@@ -37,6 +46,15 @@ impl From<IpAddr> for TaggedAddress {
 }
 
 impl TaggedAddress {
+    /// Returns the IP address, `None` if the address is a FQDN
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self {
+            Self::IP4(ip) => Some(IpAddr::V4(*ip)),
+            Self::IP6(ip) => Some(IpAddr::V6(*ip)),
+            Self::IP4FQDN(_) | Self::IP6FQDN(_) => None,
+        }
+    }
+
     pub fn parse(src: &Bytes) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
         move |i| {
             alt((
@@ -16,6 +16,12 @@ pub enum MediaType {
     Video,
     Text,
     App,
+
+    /// Still images or fax, e.g. T.38 over UDPTL
+    Image,
+
+    /// Instant messaging, e.g. MSRP ([RFC4975](https://www.rfc-editor.org/rfc/rfc4975.html#section-8.1))
+    Message,
 }
 
 impl MediaType {
@@ -25,6 +31,8 @@ impl MediaType {
             map(tag("video"), |_| MediaType::Video),
             map(tag("text"), |_| MediaType::Text),
             map(tag("application"), |_| MediaType::App),
+            map(tag("image"), |_| MediaType::Image),
+            map(tag("message"), |_| MediaType::Message),
         ))(i)
     }
 }
@@ -36,6 +44,8 @@ impl fmt::Display for MediaType {
             MediaType::Video => f.write_str("video"),
             MediaType::Text => f.write_str("text"),
             MediaType::App => f.write_str("application"),
+            MediaType::Image => f.write_str("image"),
+            MediaType::Message => f.write_str("message"),
         }
     }
 }
@@ -53,22 +63,73 @@ pub enum TransportProtocol {
     /// SRTP with [RFC5124](https://www.rfc-editor.org/rfc/rfc5124.html)
     RtpSavpf,
 
+    /// SCTP over DTLS over UDP ([RFC8841](https://www.rfc-editor.org/rfc/rfc8841.html#section-4.1))
+    UdpDtlsSctp,
+
+    /// SCTP over DTLS over TCP ([RFC8841](https://www.rfc-editor.org/rfc/rfc8841.html#section-4.1))
+    TcpDtlsSctp,
+
+    /// Legacy SCTP over DTLS, used together with `a=sctpmap`
+    DtlsSctp,
+
+    /// BFCP over TCP ([RFC8856](https://www.rfc-editor.org/rfc/rfc8856.html#section-4))
+    TcpBfcp,
+
+    /// BFCP over TLS over TCP ([RFC8856](https://www.rfc-editor.org/rfc/rfc8856.html#section-4))
+    TcpTlsBfcp,
+
+    /// BFCP over UDP ([RFC8856](https://www.rfc-editor.org/rfc/rfc8856.html#section-4))
+    UdpBfcp,
+
+    /// BFCP over DTLS over UDP ([RFC8856](https://www.rfc-editor.org/rfc/rfc8856.html#section-4))
+    UdpTlsBfcp,
+
+    /// MSRP over TCP ([RFC4975](https://www.rfc-editor.org/rfc/rfc4975.html#section-8.1))
+    TcpMsrp,
+
+    /// MSRP over TLS ([RFC4975](https://www.rfc-editor.org/rfc/rfc4975.html#section-8.1))
+    TcpTlsMsrp,
+
+    /// UDPTL, used by T.38 fax ([ITU-T T.38 Annex D](https://www.itu.int/rec/T-REC-T.38))
+    ///
+    /// Other spellings (e.g. `UDPTL`) are kept as [`TransportProtocol::Other`], see
+    /// [`TransportProtocol::is_udptl`].
+    Udptl,
+
     /// Other unknown
     Other(BytesStr),
 }
 
 impl TransportProtocol {
     pub fn parse(src: &Bytes) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        // Match the whole token, protocols may be prefixes of each other (RTP/SAVP, RTP/SAVPF)
         move |i| {
-            alt((
-                map(tag("udp"), |_| TransportProtocol::Unspecified),
-                map(tag("RTP/AVP"), |_| TransportProtocol::RtpAvp),
-                map(tag("RTP/SAVP"), |_| TransportProtocol::RtpSavp),
-                map(tag("RTP/SAVPF"), |_| TransportProtocol::RtpSavpf),
-                map(take_while1(not_whitespace), |tp| {
-                    TransportProtocol::Other(BytesStr::from_parse(src, tp))
-                }),
-            ))(i)
+            map(take_while1(not_whitespace), |tp| match tp {
+                "udp" => TransportProtocol::Unspecified,
+                "RTP/AVP" => TransportProtocol::RtpAvp,
+                "RTP/SAVP" => TransportProtocol::RtpSavp,
+                "RTP/SAVPF" => TransportProtocol::RtpSavpf,
+                "UDP/DTLS/SCTP" => TransportProtocol::UdpDtlsSctp,
+                "TCP/DTLS/SCTP" => TransportProtocol::TcpDtlsSctp,
+                "DTLS/SCTP" => TransportProtocol::DtlsSctp,
+                "TCP/BFCP" => TransportProtocol::TcpBfcp,
+                "TCP/TLS/BFCP" => TransportProtocol::TcpTlsBfcp,
+                "UDP/BFCP" => TransportProtocol::UdpBfcp,
+                "UDP/TLS/BFCP" => TransportProtocol::UdpTlsBfcp,
+                "TCP/MSRP" => TransportProtocol::TcpMsrp,
+                "TCP/TLS/MSRP" => TransportProtocol::TcpTlsMsrp,
+                "udptl" => TransportProtocol::Udptl,
+                _ => TransportProtocol::Other(BytesStr::from_parse(src, tp)),
+            })(i)
+        }
+    }
+
+    /// Returns if the protocol is UDPTL, in any spelling
+    pub fn is_udptl(&self) -> bool {
+        match self {
+            TransportProtocol::Udptl => true,
+            TransportProtocol::Other(proto) => proto.eq_ignore_ascii_case("udptl"),
+            _ => false,
         }
     }
 }
@@ -80,6 +141,16 @@ impl fmt::Display for TransportProtocol {
             TransportProtocol::RtpAvp => f.write_str("RTP/AVP"),
             TransportProtocol::RtpSavp => f.write_str("RTP/SAVP"),
             TransportProtocol::RtpSavpf => f.write_str("RTP/SAVPF"),
+            TransportProtocol::UdpDtlsSctp => f.write_str("UDP/DTLS/SCTP"),
+            TransportProtocol::TcpDtlsSctp => f.write_str("TCP/DTLS/SCTP"),
+            TransportProtocol::DtlsSctp => f.write_str("DTLS/SCTP"),
+            TransportProtocol::TcpBfcp => f.write_str("TCP/BFCP"),
+            TransportProtocol::TcpTlsBfcp => f.write_str("TCP/TLS/BFCP"),
+            TransportProtocol::UdpBfcp => f.write_str("UDP/BFCP"),
+            TransportProtocol::UdpTlsBfcp => f.write_str("UDP/TLS/BFCP"),
+            TransportProtocol::TcpMsrp => f.write_str("TCP/MSRP"),
+            TransportProtocol::TcpTlsMsrp => f.write_str("TCP/TLS/MSRP"),
+            TransportProtocol::Udptl => f.write_str("udptl"),
             TransportProtocol::Other(str) => f.write_str(str),
         }
     }
@@ -94,7 +165,12 @@ pub struct MediaDescription {
     pub port: u16,
     pub ports_num: Option<u32>,
     pub proto: TransportProtocol,
+
+    /// Payload type numbers
     pub fmts: Vec<u32>,
+
+    /// Formats which are not numbers, e.g. `webrtc-datachannel`
+    pub named_fmts: Vec<BytesStr>,
 }
 
 impl MediaDescription {
@@ -105,17 +181,74 @@ impl MediaDescription {
                 map_res(digit1, FromStr::from_str),
                 opt(slash_num),
                 TransportProtocol::parse(src),
-                many0(map(ws((map_res(digit1, FromStr::from_str),)), |t| t.0)),
+                many0(map(ws((take_while1(not_whitespace),)), |t| t.0)),
             )),
-            |(media, port, ports_num, proto, fmts)| MediaDescription {
-                media_type: media,
-                port,
-                ports_num,
-                proto,
-                fmts,
+            |(media, port, ports_num, proto, all_fmts)| {
+                let mut fmts = vec![];
+                let mut named_fmts = vec![];
+
+                for fmt in all_fmts {
+                    match fmt.parse() {
+                        Ok(fmt) => fmts.push(fmt),
+                        Err(_) => named_fmts.push(BytesStr::from_parse(src, fmt)),
+                    }
+                }
+
+                MediaDescription {
+                    media_type: media,
+                    port,
+                    ports_num,
+                    proto,
+                    fmts,
+                    named_fmts,
+                }
             },
         )(i)
     }
+
+    /// Returns if the media describes WebRTC data channels
+    /// ([RFC8841](https://www.rfc-editor.org/rfc/rfc8841.html#section-4.1))
+    pub fn is_datachannel(&self) -> bool {
+        self.media_type == MediaType::App
+            && matches!(
+                self.proto,
+                TransportProtocol::UdpDtlsSctp | TransportProtocol::TcpDtlsSctp
+            )
+            && self
+                .named_fmts
+                .iter()
+                .any(|fmt| fmt == "webrtc-datachannel")
+    }
+
+    /// Returns if the media describes a BFCP floor control stream
+    /// ([RFC8856](https://www.rfc-editor.org/rfc/rfc8856.html#section-4))
+    pub fn is_bfcp(&self) -> bool {
+        self.media_type == MediaType::App
+            && matches!(
+                self.proto,
+                TransportProtocol::TcpBfcp
+                    | TransportProtocol::TcpTlsBfcp
+                    | TransportProtocol::UdpBfcp
+                    | TransportProtocol::UdpTlsBfcp
+            )
+    }
+
+    /// Returns if the media describes an MSRP session
+    /// ([RFC4975](https://www.rfc-editor.org/rfc/rfc4975.html#section-8.1))
+    pub fn is_msrp(&self) -> bool {
+        self.media_type == MediaType::Message
+            && matches!(
+                self.proto,
+                TransportProtocol::TcpMsrp | TransportProtocol::TcpTlsMsrp
+            )
+    }
+
+    /// Returns if the media describes a T.38 fax session over UDPTL
+    pub fn is_t38(&self) -> bool {
+        self.media_type == MediaType::Image
+            && self.proto.is_udptl()
+            && self.named_fmts.iter().any(|fmt| fmt == "t38")
+    }
 }
 
 impl fmt::Display for MediaDescription {
@@ -134,6 +267,10 @@ impl fmt::Display for MediaDescription {
             write!(f, " {}", fmt)?;
         }
 
+        for fmt in &self.named_fmts {
+            write!(f, " {}", fmt)?;
+        }
+
         Ok(())
     }
 }
@@ -157,4 +294,72 @@ mod test {
 
         assert!(rem.is_empty());
     }
+
+    #[test]
+    fn media_savpf() {
+        let input = BytesStr::from_static("video 49170 RTP/SAVPF 96 97");
+
+        let (rem, media) = MediaDescription::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(media.proto, TransportProtocol::RtpSavpf);
+        assert_eq!(media.fmts, [96, 97]);
+        assert!(media.named_fmts.is_empty());
+    }
+
+    #[test]
+    fn media_datachannel() {
+        let input = BytesStr::from_static("application 9 UDP/DTLS/SCTP webrtc-datachannel");
+
+        let (rem, media) = MediaDescription::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(media.proto, TransportProtocol::UdpDtlsSctp);
+        assert!(media.fmts.is_empty());
+        assert_eq!(media.named_fmts, ["webrtc-datachannel"]);
+        assert!(media.is_datachannel());
+        assert_eq!(
+            media.to_string(),
+            "m=application 9 UDP/DTLS/SCTP webrtc-datachannel"
+        );
+    }
+
+    #[test]
+    fn media_image() {
+        let input = BytesStr::from_static("image 5000 udptl t38");
+
+        let (rem, media) = MediaDescription::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(media.media_type, MediaType::Image);
+        assert_eq!(media.proto, TransportProtocol::Udptl);
+        assert!(media.is_t38());
+        assert_eq!(media.to_string(), "m=image 5000 udptl t38");
+
+        for input in ["image 5000 UDPTL t38", "image 5000 Udptl t38"] {
+            let input = BytesStr::from_static(input);
+
+            let (_, media) = MediaDescription::parse(input.as_ref(), &input).unwrap();
+
+            assert!(media.is_t38());
+            assert_eq!(media.to_string(), format!("m={input}"));
+        }
+    }
+
+    #[test]
+    fn media_bfcp() {
+        let input = BytesStr::from_static("application 50000 TCP/TLS/BFCP *");
+
+        let (rem, media) = MediaDescription::parse(input.as_ref(), &input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(media.proto, TransportProtocol::TcpTlsBfcp);
+        assert_eq!(media.named_fmts, ["*"]);
+        assert!(media.is_bfcp());
+        assert_eq!(media.to_string(), "m=application 50000 TCP/TLS/BFCP *");
+    }
 }
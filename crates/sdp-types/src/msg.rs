@@ -1,19 +1,44 @@
+use crate::attributes::bfcp::{FloorControl, FloorId};
 use crate::attributes::candidate::Candidate;
+use crate::attributes::capneg::{
+    AttributeCapability, PotentialConfig, SelectedConfig, TransportCapability,
+};
+use crate::attributes::content::Content;
+use crate::attributes::crypto::Crypto;
 use crate::attributes::direction::Direction;
-use crate::attributes::fmtp::Fmtp;
+use crate::attributes::extmap::ExtMap;
+use crate::attributes::fmtp::{Fmtp, TelephoneEvents};
+use crate::attributes::group::Group;
 use crate::attributes::ice::{Options, Password, UsernameFragment};
+use crate::attributes::identity::Identity;
+use crate::attributes::imageattr::ImageAttr;
+use crate::attributes::msid::Msid;
 use crate::attributes::rtcp::RtcpAttr;
+use crate::attributes::rtcp_fb::RtcpFeedback;
 use crate::attributes::rtpmap::RtpMap;
+use crate::attributes::sctp::SctpMap;
+use crate::attributes::ssrc::{Ssrc, SsrcGroup};
+use crate::attributes::t38::{T38Options, T38Param};
+use crate::attributes::tcp::{Setup, TcpConnection, TcpRole};
+use crate::attributes::tls_id::TlsId;
+use crate::attributes::zrtp::ZrtpHash;
 use crate::attributes::{ice, UnknownAttribute};
 use crate::bandwidth::Bandwidth;
 use crate::connection::Connection;
-use crate::media::MediaDescription;
+use crate::media::{MediaDescription, MediaType, TransportProtocol};
 use crate::origin::Origin;
-use crate::time::Time;
+use crate::time::{RepeatTime, Time, ZoneAdjustment};
 use anyhow::Context;
+use bytes::{Buf, Bytes, BytesMut};
 use bytesstr::BytesStr;
-use internal::{Finish, ParseError};
+use internal::{Finish, IResult, ParseError};
+use nom::character::complete::{digit1, multispace0};
+use nom::combinator::map_res;
+use nom::number::complete::float;
+use nom::sequence::preceded;
 use std::fmt::{self, Debug, Display};
+use std::net::SocketAddr;
+use std::str::FromStr;
 
 pub trait ParseBuilder: Default {
     type Message;
@@ -38,17 +63,244 @@ pub trait ParseBuilder: Default {
     fn add_ice_candidate(&mut self, candidate: Candidate) -> Result<(), Self::Error>;
     fn set_ice_end_of_candidates(&mut self, end: bool) -> Result<(), Self::Error>;
     fn add_unknown_attr(&mut self, attr: UnknownAttribute) -> Result<(), Self::Error>;
+
+    // Setters of fields and attributes not every builder is interested in.
+    //
+    // Attributes default to being added using `add_unknown_attr`, other fields are ignored.
+
+    fn set_info(&mut self, info: BytesStr) -> Result<(), Self::Error> {
+        let _ = info;
+        Ok(())
+    }
+
+    fn set_uri(&mut self, uri: BytesStr) -> Result<(), Self::Error> {
+        let _ = uri;
+        Ok(())
+    }
+
+    fn add_email(&mut self, email: BytesStr) -> Result<(), Self::Error> {
+        let _ = email;
+        Ok(())
+    }
+
+    fn add_phone(&mut self, phone: BytesStr) -> Result<(), Self::Error> {
+        let _ = phone;
+        Ok(())
+    }
+
+    fn add_repeat_time(&mut self, repeat: RepeatTime) -> Result<(), Self::Error> {
+        let _ = repeat;
+        Ok(())
+    }
+
+    fn set_zone_adjustments(
+        &mut self,
+        adjustments: Vec<ZoneAdjustment>,
+    ) -> Result<(), Self::Error> {
+        let _ = adjustments;
+        Ok(())
+    }
+
+    fn set_key(&mut self, key: BytesStr) -> Result<(), Self::Error> {
+        let _ = key;
+        Ok(())
+    }
+
+    fn add_group(&mut self, group: Group) -> Result<(), Self::Error> {
+        add_unknown(self, group)
+    }
+
+    fn set_identity(&mut self, identity: Identity) -> Result<(), Self::Error> {
+        add_unknown(self, identity)
+    }
+
+    fn set_mid(&mut self, mid: BytesStr) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("mid", mid))
+    }
+
+    fn set_label(&mut self, label: BytesStr) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("label", label))
+    }
+
+    fn set_content(&mut self, content: Content) -> Result<(), Self::Error> {
+        add_unknown(self, content)
+    }
+
+    fn add_imageattr(&mut self, imageattr: ImageAttr) -> Result<(), Self::Error> {
+        add_unknown(self, imageattr)
+    }
+
+    fn set_ptime(&mut self, ptime: u32) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("ptime", ptime))
+    }
+
+    fn set_maxptime(&mut self, maxptime: u32) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("maxptime", maxptime))
+    }
+
+    fn set_framerate(&mut self, framerate: f32) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("framerate", framerate))
+    }
+
+    fn set_quality(&mut self, quality: u8) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("quality", quality))
+    }
+
+    fn set_sctp_port(&mut self, port: u16) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("sctp-port", port))
+    }
+
+    fn set_max_message_size(&mut self, size: u64) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("max-message-size", size))
+    }
+
+    fn set_sctpmap(&mut self, sctpmap: SctpMap) -> Result<(), Self::Error> {
+        add_unknown(self, sctpmap)
+    }
+
+    fn set_t38_param(&mut self, param: T38Param) -> Result<(), Self::Error> {
+        add_unknown(self, param)
+    }
+
+    fn set_floorctrl(&mut self, roles: Vec<FloorControl>) -> Result<(), Self::Error> {
+        add_unknown(self, ListAttr("floorctrl", roles))
+    }
+
+    fn set_confid(&mut self, confid: BytesStr) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("confid", confid))
+    }
+
+    fn set_userid(&mut self, userid: BytesStr) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("userid", userid))
+    }
+
+    fn add_floorid(&mut self, floorid: FloorId) -> Result<(), Self::Error> {
+        add_unknown(self, floorid)
+    }
+
+    fn set_msrp_path(&mut self, path: Vec<BytesStr>) -> Result<(), Self::Error> {
+        add_unknown(self, ListAttr("path", path))
+    }
+
+    fn set_accept_types(&mut self, types: Vec<BytesStr>) -> Result<(), Self::Error> {
+        add_unknown(self, ListAttr("accept-types", types))
+    }
+
+    fn set_accept_wrapped_types(&mut self, types: Vec<BytesStr>) -> Result<(), Self::Error> {
+        add_unknown(self, ListAttr("accept-wrapped-types", types))
+    }
+
+    fn set_max_size(&mut self, size: u64) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("max-size", size))
+    }
+
+    fn set_rtcp_mux(&mut self, mux: bool) -> Result<(), Self::Error> {
+        add_unknown(self, FlagAttr("rtcp-mux", mux))
+    }
+
+    fn set_rtcp_mux_only(&mut self, mux_only: bool) -> Result<(), Self::Error> {
+        add_unknown(self, FlagAttr("rtcp-mux-only", mux_only))
+    }
+
+    fn set_bundle_only(&mut self, bundle_only: bool) -> Result<(), Self::Error> {
+        add_unknown(self, FlagAttr("bundle-only", bundle_only))
+    }
+
+    fn set_rtcp_rsize(&mut self, rsize: bool) -> Result<(), Self::Error> {
+        add_unknown(self, FlagAttr("rtcp-rsize", rsize))
+    }
+
+    fn add_msid(&mut self, msid: Msid) -> Result<(), Self::Error> {
+        add_unknown(self, msid)
+    }
+
+    fn add_ssrc(&mut self, ssrc: Ssrc) -> Result<(), Self::Error> {
+        add_unknown(self, ssrc)
+    }
+
+    fn add_ssrc_group(&mut self, group: SsrcGroup) -> Result<(), Self::Error> {
+        add_unknown(self, group)
+    }
+
+    fn add_crypto(&mut self, crypto: Crypto) -> Result<(), Self::Error> {
+        add_unknown(self, crypto)
+    }
+
+    fn set_tls_id(&mut self, tls_id: TlsId) -> Result<(), Self::Error> {
+        add_unknown(self, tls_id)
+    }
+
+    fn set_zrtp_hash(&mut self, zrtp_hash: ZrtpHash) -> Result<(), Self::Error> {
+        add_unknown(self, zrtp_hash)
+    }
+
+    fn set_setup(&mut self, setup: Setup) -> Result<(), Self::Error> {
+        add_unknown(self, setup)
+    }
+
+    fn set_tcp_connection(&mut self, connection: TcpConnection) -> Result<(), Self::Error> {
+        add_unknown(self, connection)
+    }
+
+    fn add_acap(&mut self, acap: AttributeCapability) -> Result<(), Self::Error> {
+        add_unknown(self, acap)
+    }
+
+    fn add_tcap(&mut self, tcap: TransportCapability) -> Result<(), Self::Error> {
+        add_unknown(self, tcap)
+    }
+
+    fn add_pcfg(&mut self, pcfg: PotentialConfig) -> Result<(), Self::Error> {
+        add_unknown(self, pcfg)
+    }
+
+    fn set_acfg(&mut self, acfg: SelectedConfig) -> Result<(), Self::Error> {
+        add_unknown(self, acfg)
+    }
+
+    fn add_rtcp_fb(&mut self, rtcp_fb: RtcpFeedback) -> Result<(), Self::Error> {
+        add_unknown(self, rtcp_fb)
+    }
+
+    fn add_extmap(&mut self, extmap: ExtMap) -> Result<(), Self::Error> {
+        add_unknown(self, extmap)
+    }
+
+    fn set_ice_pacing(&mut self, pacing: u32) -> Result<(), Self::Error> {
+        add_unknown(self, ValueAttr("ice-pacing", pacing))
+    }
+
+    fn set_ice_remote_candidates(
+        &mut self,
+        remote_candidates: ice::RemoteCandidates,
+    ) -> Result<(), Self::Error> {
+        add_unknown(self, remote_candidates)
+    }
 }
 
 #[derive(Default)]
 pub struct Builder {
     name: Option<BytesStr>,
+    info: Option<BytesStr>,
+    uri: Option<BytesStr>,
+    emails: Vec<BytesStr>,
+    phones: Vec<BytesStr>,
     origin: Option<Origin>,
     time: Option<Time>,
+    zone_adjustments: Vec<ZoneAdjustment>,
     direction: Direction,
     connection: Option<Connection>,
     bandwidth: Vec<Bandwidth>,
+    key: Option<BytesStr>,
+    groups: Vec<Group>,
+    identity: Option<Identity>,
+    acaps: Vec<AttributeCapability>,
+    tcaps: Vec<TransportCapability>,
+    extmaps: Vec<ExtMap>,
+    setup: Option<Setup>,
+    tcp_connection: Option<TcpConnection>,
     ice_options: ice::Options,
+    ice_pacing: Option<u32>,
     ice_lite: bool,
     ice_ufrag: Option<ice::UsernameFragment>,
     ice_pwd: Option<ice::Password>,
@@ -64,11 +316,25 @@ impl ParseBuilder for Builder {
         Ok(Message {
             origin: self.origin.context("missing origin")?,
             name: self.name.context("missing name")?,
+            info: self.info,
+            uri: self.uri,
+            emails: self.emails,
+            phones: self.phones,
             time: self.time.context("missing time")?,
+            zone_adjustments: self.zone_adjustments,
             direction: self.direction,
             connection: self.connection,
             bandwidth: self.bandwidth,
+            key: self.key,
+            groups: self.groups,
+            identity: self.identity,
+            acaps: self.acaps,
+            tcaps: self.tcaps,
+            extmaps: self.extmaps,
+            setup: self.setup,
+            tcp_connection: self.tcp_connection,
             ice_options: self.ice_options,
+            ice_pacing: self.ice_pacing,
             ice_lite: self.ice_lite,
             ice_ufrag: self.ice_ufrag,
             ice_pwd: self.ice_pwd,
@@ -82,6 +348,31 @@ impl ParseBuilder for Builder {
         Ok(())
     }
 
+    fn set_info(&mut self, info: BytesStr) -> Result<(), Self::Error> {
+        if let Some(media_scope) = self.media_scopes.last_mut() {
+            media_scope.info = Some(info);
+        } else {
+            self.info = Some(info);
+        }
+
+        Ok(())
+    }
+
+    fn set_uri(&mut self, uri: BytesStr) -> Result<(), Self::Error> {
+        self.uri = Some(uri);
+        Ok(())
+    }
+
+    fn add_email(&mut self, email: BytesStr) -> Result<(), Self::Error> {
+        self.emails.push(email);
+        Ok(())
+    }
+
+    fn add_phone(&mut self, phone: BytesStr) -> Result<(), Self::Error> {
+        self.phones.push(phone);
+        Ok(())
+    }
+
     fn set_origin(&mut self, origin: Origin) -> Result<(), Self::Error> {
         self.origin = Some(origin);
         Ok(())
@@ -92,6 +383,24 @@ impl ParseBuilder for Builder {
         Ok(())
     }
 
+    fn add_repeat_time(&mut self, repeat: RepeatTime) -> Result<(), Self::Error> {
+        self.time
+            .as_mut()
+            .context("repeat time without time")?
+            .repeats
+            .push(repeat);
+
+        Ok(())
+    }
+
+    fn set_zone_adjustments(
+        &mut self,
+        adjustments: Vec<ZoneAdjustment>,
+    ) -> Result<(), Self::Error> {
+        self.zone_adjustments = adjustments;
+        Ok(())
+    }
+
     fn set_direction(&mut self, direction: Direction) -> Result<(), Self::Error> {
         if let Some(media_scope) = self.media_scopes.last_mut() {
             media_scope.direction = direction;
@@ -122,134 +431,498 @@ impl ParseBuilder for Builder {
         Ok(())
     }
 
+    fn set_key(&mut self, key: BytesStr) -> Result<(), Self::Error> {
+        if let Some(media_scope) = self.media_scopes.last_mut() {
+            media_scope.key = Some(key);
+        } else {
+            self.key = Some(key);
+        }
+
+        Ok(())
+    }
+
     fn begin_media(&mut self, desc: MediaDescription) -> Result<(), Self::Error> {
         self.media_scopes.push(MediaScope {
-            desc,
             // inherit session direction
             direction: self.direction,
-            connection: None,
-            bandwidth: vec![],
-            rtcp_attr: None,
-            rtpmaps: vec![],
-            fmtps: vec![],
-            ice_ufrag: None,
-            ice_pwd: None,
-            ice_candidates: vec![],
-            ice_end_of_candidates: false,
-            attributes: vec![],
+            ..MediaScope::new(desc)
         });
 
         Ok(())
     }
 
     fn add_rtpmap(&mut self, rtpmap: RtpMap) -> Result<(), Self::Error> {
-        if let Some(media_scope) = self.media_scopes.last_mut() {
-            media_scope.rtpmaps.push(rtpmap);
-        }
+        self.media_attr(rtpmap, |media_scope, rtpmap| {
+            media_scope.rtpmaps.push(rtpmap)
+        })
+    }
+
+    fn add_fmtp(&mut self, fmtp: Fmtp) -> Result<(), Self::Error> {
+        self.media_attr(fmtp, |media_scope, fmtp| media_scope.fmtps.push(fmtp))
+    }
 
-        // TODO error here ?
+    fn add_rtcp(&mut self, rtcp: RtcpAttr) -> Result<(), Self::Error> {
+        self.media_attr(rtcp, |media_scope, rtcp| media_scope.rtcp_attr = Some(rtcp))
+    }
 
-        Ok(())
+    fn set_rtcp_mux(&mut self, mux: bool) -> Result<(), Self::Error> {
+        self.media_attr(
+            FlagAttr("rtcp-mux", mux),
+            |media_scope, FlagAttr(_, mux)| media_scope.rtcp_mux = mux,
+        )
     }
 
-    fn add_fmtp(&mut self, fmtp: Fmtp) -> Result<(), Self::Error> {
-        if let Some(media_scope) = self.media_scopes.last_mut() {
-            media_scope.fmtps.push(fmtp);
-        }
+    fn set_rtcp_mux_only(&mut self, mux_only: bool) -> Result<(), Self::Error> {
+        self.media_attr(
+            FlagAttr("rtcp-mux-only", mux_only),
+            |media_scope, FlagAttr(_, mux_only)| media_scope.rtcp_mux_only = mux_only,
+        )
+    }
 
-        // TODO error here ?
+    fn set_bundle_only(&mut self, bundle_only: bool) -> Result<(), Self::Error> {
+        self.media_attr(
+            FlagAttr("bundle-only", bundle_only),
+            |media_scope, FlagAttr(_, bundle_only)| media_scope.bundle_only = bundle_only,
+        )
+    }
 
-        Ok(())
+    fn add_imageattr(&mut self, imageattr: ImageAttr) -> Result<(), Self::Error> {
+        self.media_attr(imageattr, |media_scope, imageattr| {
+            media_scope.imageattrs.push(imageattr)
+        })
     }
 
-    fn add_rtcp(&mut self, rtcp: RtcpAttr) -> Result<(), Self::Error> {
-        if let Some(media_scope) = self.media_scopes.last_mut() {
-            media_scope.rtcp_attr = Some(rtcp)
-        }
+    fn set_ptime(&mut self, ptime: u32) -> Result<(), Self::Error> {
+        self.media_attr(
+            ValueAttr("ptime", ptime),
+            |media_scope, ValueAttr(_, ptime)| media_scope.ptime = Some(ptime),
+        )
+    }
 
-        // TODO error here?
+    fn set_maxptime(&mut self, maxptime: u32) -> Result<(), Self::Error> {
+        self.media_attr(
+            ValueAttr("maxptime", maxptime),
+            |media_scope, ValueAttr(_, maxptime)| media_scope.maxptime = Some(maxptime),
+        )
+    }
 
-        Ok(())
+    fn set_framerate(&mut self, framerate: f32) -> Result<(), Self::Error> {
+        self.media_attr(
+            ValueAttr("framerate", framerate),
+            |media_scope, ValueAttr(_, framerate)| media_scope.framerate = Some(framerate),
+        )
     }
 
-    fn set_ice_lite(&mut self, lite: bool) -> Result<(), Self::Error> {
-        self.ice_lite = lite;
-        Ok(())
+    fn set_quality(&mut self, quality: u8) -> Result<(), Self::Error> {
+        self.media_attr(
+            ValueAttr("quality", quality),
+            |media_scope, ValueAttr(_, quality)| media_scope.quality = Some(quality),
+        )
     }
 
-    fn set_ice_options(&mut self, options: Options) -> Result<(), Self::Error> {
-        self.ice_options = options;
+    fn set_sctp_port(&mut self, port: u16) -> Result<(), Self::Error> {
+        self.media_attr(
+            ValueAttr("sctp-port", port),
+            |media_scope, ValueAttr(_, port)| media_scope.sctp_port = Some(port),
+        )
+    }
 
-        Ok(())
+    fn set_max_message_size(&mut self, size: u64) -> Result<(), Self::Error> {
+        self.media_attr(
+            ValueAttr("max-message-size", size),
+            |media_scope, ValueAttr(_, size)| media_scope.max_message_size = Some(size),
+        )
     }
 
-    fn set_ice_ufrag(&mut self, ufrag: UsernameFragment) -> Result<(), Self::Error> {
+    fn set_sctpmap(&mut self, sctpmap: SctpMap) -> Result<(), Self::Error> {
+        self.media_attr(sctpmap, |media_scope, sctpmap| {
+            media_scope.sctpmap = Some(sctpmap)
+        })
+    }
+
+    fn set_t38_param(&mut self, param: T38Param) -> Result<(), Self::Error> {
+        self.media_attr(param, |media_scope, param| media_scope.t38.set(param))
+    }
+
+    fn set_floorctrl(&mut self, roles: Vec<FloorControl>) -> Result<(), Self::Error> {
+        self.media_attr(
+            ListAttr("floorctrl", roles),
+            |media_scope, ListAttr(_, roles)| media_scope.floorctrl = roles,
+        )
+    }
+
+    fn set_confid(&mut self, confid: BytesStr) -> Result<(), Self::Error> {
+        self.media_attr(
+            ValueAttr("confid", confid),
+            |media_scope, ValueAttr(_, confid)| media_scope.confid = Some(confid),
+        )
+    }
+
+    fn set_userid(&mut self, userid: BytesStr) -> Result<(), Self::Error> {
+        self.media_attr(
+            ValueAttr("userid", userid),
+            |media_scope, ValueAttr(_, userid)| media_scope.userid = Some(userid),
+        )
+    }
+
+    fn add_floorid(&mut self, floorid: FloorId) -> Result<(), Self::Error> {
+        self.media_attr(floorid, |media_scope, floorid| {
+            media_scope.floorids.push(floorid)
+        })
+    }
+
+    fn set_msrp_path(&mut self, path: Vec<BytesStr>) -> Result<(), Self::Error> {
+        self.media_attr(ListAttr("path", path), |media_scope, ListAttr(_, path)| {
+            media_scope.msrp_path = path
+        })
+    }
+
+    fn set_accept_types(&mut self, types: Vec<BytesStr>) -> Result<(), Self::Error> {
+        self.media_attr(
+            ListAttr("accept-types", types),
+            |media_scope, ListAttr(_, types)| media_scope.accept_types = types,
+        )
+    }
+
+    fn set_accept_wrapped_types(&mut self, types: Vec<BytesStr>) -> Result<(), Self::Error> {
+        self.media_attr(
+            ListAttr("accept-wrapped-types", types),
+            |media_scope, ListAttr(_, types)| media_scope.accept_wrapped_types = types,
+        )
+    }
+
+    fn set_max_size(&mut self, size: u64) -> Result<(), Self::Error> {
+        self.media_attr(
+            ValueAttr("max-size", size),
+            |media_scope, ValueAttr(_, size)| media_scope.max_size = Some(size),
+        )
+    }
+
+    fn set_rtcp_rsize(&mut self, rsize: bool) -> Result<(), Self::Error> {
+        self.media_attr(
+            FlagAttr("rtcp-rsize", rsize),
+            |media_scope, FlagAttr(_, rsize)| media_scope.rtcp_rsize = rsize,
+        )
+    }
+
+    fn add_msid(&mut self, msid: Msid) -> Result<(), Self::Error> {
+        self.media_attr(msid, |media_scope, msid| media_scope.msids.push(msid))
+    }
+
+    fn add_ssrc(&mut self, ssrc: Ssrc) -> Result<(), Self::Error> {
+        self.media_attr(ssrc, |media_scope, ssrc| media_scope.ssrcs.push(ssrc))
+    }
+
+    fn add_ssrc_group(&mut self, group: SsrcGroup) -> Result<(), Self::Error> {
+        self.media_attr(group, |media_scope, group| {
+            media_scope.ssrc_groups.push(group)
+        })
+    }
+
+    fn add_rtcp_fb(&mut self, rtcp_fb: RtcpFeedback) -> Result<(), Self::Error> {
+        self.media_attr(rtcp_fb, |media_scope, rtcp_fb| {
+            media_scope.rtcp_fb.push(rtcp_fb)
+        })
+    }
+
+    fn add_extmap(&mut self, extmap: ExtMap) -> Result<(), Self::Error> {
         if let Some(media_scope) = self.media_scopes.last_mut() {
-            media_scope.ice_ufrag = Some(ufrag)
+            media_scope.extmaps.push(extmap);
         } else {
-            self.ice_ufrag = Some(ufrag);
+            self.extmaps.push(extmap);
         }
 
         Ok(())
     }
 
-    fn set_ice_pwd(&mut self, pwd: Password) -> Result<(), Self::Error> {
+    fn set_setup(&mut self, setup: Setup) -> Result<(), Self::Error> {
         if let Some(media_scope) = self.media_scopes.last_mut() {
-            media_scope.ice_pwd = Some(pwd)
+            media_scope.setup = Some(setup);
         } else {
-            self.ice_pwd = Some(pwd);
+            self.setup = Some(setup);
         }
 
         Ok(())
     }
 
-    fn add_ice_candidate(&mut self, candidate: Candidate) -> Result<(), Self::Error> {
+    fn set_tcp_connection(&mut self, connection: TcpConnection) -> Result<(), Self::Error> {
         if let Some(media_scope) = self.media_scopes.last_mut() {
-            media_scope.ice_candidates.push(candidate);
+            media_scope.tcp_connection = Some(connection);
+        } else {
+            self.tcp_connection = Some(connection);
         }
 
-        // TODO error here?
-
         Ok(())
     }
 
-    fn set_ice_end_of_candidates(&mut self, end: bool) -> Result<(), Self::Error> {
+    fn add_crypto(&mut self, crypto: Crypto) -> Result<(), Self::Error> {
+        self.media_attr(crypto, |media_scope, crypto| {
+            media_scope.crypto.push(crypto)
+        })
+    }
+
+    fn set_tls_id(&mut self, tls_id: TlsId) -> Result<(), Self::Error> {
+        self.media_attr(tls_id, |media_scope, tls_id| {
+            media_scope.tls_id = Some(tls_id)
+        })
+    }
+
+    fn set_zrtp_hash(&mut self, zrtp_hash: ZrtpHash) -> Result<(), Self::Error> {
+        self.media_attr(zrtp_hash, |media_scope, zrtp_hash| {
+            media_scope.zrtp_hash = Some(zrtp_hash)
+        })
+    }
+
+    fn add_acap(&mut self, acap: AttributeCapability) -> Result<(), Self::Error> {
         if let Some(media_scope) = self.media_scopes.last_mut() {
-            media_scope.ice_end_of_candidates = end;
+            media_scope.acaps.push(acap);
+        } else {
+            self.acaps.push(acap);
         }
 
-        // TODO error here?
-
         Ok(())
     }
 
-    fn add_unknown_attr(&mut self, attr: UnknownAttribute) -> Result<(), Self::Error> {
+    fn add_tcap(&mut self, tcap: TransportCapability) -> Result<(), Self::Error> {
         if let Some(media_scope) = self.media_scopes.last_mut() {
-            media_scope.attributes.push(attr);
+            media_scope.tcaps.push(tcap);
         } else {
-            self.attributes.push(attr);
+            self.tcaps.push(tcap);
         }
 
         Ok(())
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct MediaScope {
-    /// Scope's media description line (m field)
-    pub desc: MediaDescription,
+    fn add_pcfg(&mut self, pcfg: PotentialConfig) -> Result<(), Self::Error> {
+        self.media_attr(pcfg, |media_scope, pcfg| media_scope.pcfgs.push(pcfg))
+    }
 
-    /// Media direction
-    pub direction: Direction,
+    fn set_acfg(&mut self, acfg: SelectedConfig) -> Result<(), Self::Error> {
+        self.media_attr(acfg, |media_scope, acfg| media_scope.acfg = Some(acfg))
+    }
 
-    /// Optional connection (c field)
-    pub connection: Option<Connection>,
+    fn set_ice_lite(&mut self, lite: bool) -> Result<(), Self::Error> {
+        self.ice_lite = lite;
+        Ok(())
+    }
 
-    /// Optional bandwidths (b fields)
-    pub bandwidth: Vec<Bandwidth>,
+    fn add_group(&mut self, group: Group) -> Result<(), Self::Error> {
+        self.groups.push(group);
 
-    /// rtcp attribute
-    pub rtcp_attr: Option<RtcpAttr>,
+        Ok(())
+    }
+
+    fn set_label(&mut self, label: BytesStr) -> Result<(), Self::Error> {
+        self.media_attr(
+            ValueAttr("label", label),
+            |media_scope, ValueAttr(_, label)| media_scope.label = Some(label),
+        )
+    }
+
+    fn set_content(&mut self, content: Content) -> Result<(), Self::Error> {
+        self.media_attr(content, |media_scope, content| {
+            media_scope.content = Some(content)
+        })
+    }
+
+    fn set_identity(&mut self, identity: Identity) -> Result<(), Self::Error> {
+        self.identity = Some(identity);
+
+        Ok(())
+    }
+
+    fn set_mid(&mut self, mid: BytesStr) -> Result<(), Self::Error> {
+        self.media_attr(ValueAttr("mid", mid), |media_scope, ValueAttr(_, mid)| {
+            media_scope.mid = Some(mid)
+        })
+    }
+
+    fn set_ice_options(&mut self, options: Options) -> Result<(), Self::Error> {
+        if let Some(media_scope) = self.media_scopes.last_mut() {
+            media_scope.ice_options = options;
+        } else {
+            self.ice_options = options;
+        }
+
+        Ok(())
+    }
+
+    fn set_ice_pacing(&mut self, pacing: u32) -> Result<(), Self::Error> {
+        self.ice_pacing = Some(pacing);
+
+        Ok(())
+    }
+
+    fn set_ice_ufrag(&mut self, ufrag: UsernameFragment) -> Result<(), Self::Error> {
+        if let Some(media_scope) = self.media_scopes.last_mut() {
+            media_scope.ice_ufrag = Some(ufrag)
+        } else {
+            self.ice_ufrag = Some(ufrag);
+        }
+
+        Ok(())
+    }
+
+    fn set_ice_pwd(&mut self, pwd: Password) -> Result<(), Self::Error> {
+        if let Some(media_scope) = self.media_scopes.last_mut() {
+            media_scope.ice_pwd = Some(pwd)
+        } else {
+            self.ice_pwd = Some(pwd);
+        }
+
+        Ok(())
+    }
+
+    fn add_ice_candidate(&mut self, candidate: Candidate) -> Result<(), Self::Error> {
+        self.media_attr(candidate, |media_scope, candidate| {
+            media_scope.ice_candidates.push(candidate)
+        })
+    }
+
+    fn set_ice_end_of_candidates(&mut self, end: bool) -> Result<(), Self::Error> {
+        self.media_attr(
+            FlagAttr("end-of-candidates", end),
+            |media_scope, FlagAttr(_, end)| media_scope.ice_end_of_candidates = end,
+        )
+    }
+
+    fn set_ice_remote_candidates(
+        &mut self,
+        remote_candidates: ice::RemoteCandidates,
+    ) -> Result<(), Self::Error> {
+        self.media_attr(remote_candidates, |media_scope, remote_candidates| {
+            media_scope.ice_remote_candidates = Some(remote_candidates)
+        })
+    }
+
+    fn add_unknown_attr(&mut self, attr: UnknownAttribute) -> Result<(), Self::Error> {
+        if let Some(media_scope) = self.media_scopes.last_mut() {
+            media_scope.attributes.push(attr);
+        } else {
+            self.attributes.push(attr);
+        }
+
+        Ok(())
+    }
+}
+
+impl Builder {
+    /// Apply an attribute which is only valid inside a media description to the current one
+    ///
+    /// Before the first media description the attribute is kept as unknown session attribute.
+    fn media_attr<A: Display>(
+        &mut self,
+        attr: A,
+        apply: impl FnOnce(&mut MediaScope, A),
+    ) -> Result<(), anyhow::Error> {
+        match self.media_scopes.last_mut() {
+            Some(media_scope) => apply(media_scope, attr),
+            None => self.attributes.extend(unknown_attr(attr)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Attribute with a single value, e.g. `a=ptime:20`
+struct ValueAttr<T>(&'static str, T);
+
+impl<T: Display> fmt::Display for ValueAttr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a={}:{}", self.0, self.1)
+    }
+}
+
+/// Attribute with a space separated list of values, e.g. `a=accept-types:text/plain`,
+/// printed only if the list is not empty
+struct ListAttr<T>(&'static str, Vec<T>);
+
+impl<T: Display> fmt::Display for ListAttr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((first, rest)) = self.1.split_first() {
+            write!(f, "a={}:{}", self.0, first)?;
+
+            for item in rest {
+                write!(f, " {}", item)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Property attribute, e.g. `a=rtcp-mux`, printed only if set
+struct FlagAttr(&'static str, bool);
+
+impl fmt::Display for FlagAttr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.1 {
+            write!(f, "a={}", self.0)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Print a typed attribute back into an [`UnknownAttribute`], `None` if nothing is printed
+fn unknown_attr(attr: impl Display) -> Option<UnknownAttribute> {
+    let printed = BytesStr::from(attr.to_string());
+    let line = printed.as_str().trim_end().strip_prefix("a=")?;
+
+    Some(UnknownAttribute::parse(printed.as_ref(), line))
+}
+
+/// Default implementation of the typed attribute setters of [`ParseBuilder`]
+fn add_unknown<B: ParseBuilder>(builder: &mut B, attr: impl Display) -> Result<(), B::Error> {
+    match unknown_attr(attr) {
+        Some(attr) => builder.add_unknown_attr(attr),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MediaScope {
+    /// Scope's media description line (m field)
+    pub desc: MediaDescription,
+
+    /// Media title (i field)
+    pub info: Option<BytesStr>,
+
+    /// Media direction
+    pub direction: Direction,
+
+    /// Optional connection (c field)
+    pub connection: Option<Connection>,
+
+    /// Optional bandwidths (b fields)
+    pub bandwidth: Vec<Bandwidth>,
+
+    /// Obsolete encryption key (k field), kept as is to pass it through
+    pub key: Option<BytesStr>,
+
+    /// Media identification tag (`a=mid`, [RFC5888](https://www.rfc-editor.org/rfc/rfc5888.html#section-4))
+    pub mid: Option<BytesStr>,
+
+    /// Label referenced by external documents e.g. in BFCP or conferencing (`a=label`, [RFC4574](https://www.rfc-editor.org/rfc/rfc4574.html#section-4))
+    pub label: Option<BytesStr>,
+
+    /// Content of the media stream
+    pub content: Option<Content>,
+
+    /// Media description may only be used inside a BUNDLE group, the port is set to 0 (`a=bundle-only`, [RFC9143](https://www.rfc-editor.org/rfc/rfc9143.html#section-6))
+    pub bundle_only: bool,
+
+    /// rtcp attribute
+    pub rtcp_attr: Option<RtcpAttr>,
+
+    /// RTP and RTCP are multiplexed on a single port (`a=rtcp-mux`, [RFC5761](https://www.rfc-editor.org/rfc/rfc5761.html#section-5.1.1))
+    pub rtcp_mux: bool,
+
+    /// Multiplexing RTP and RTCP is required (`a=rtcp-mux-only`, [RFC8858](https://www.rfc-editor.org/rfc/rfc8858.html#section-3))
+    pub rtcp_mux_only: bool,
+
+    /// Reduced-size RTCP is supported (`a=rtcp-rsize`, [RFC5506](https://www.rfc-editor.org/rfc/rfc5506.html#section-5))
+    pub rtcp_rsize: bool,
 
     /// RTP mappings
     pub rtpmaps: Vec<RtpMap>,
@@ -257,6 +930,102 @@ pub struct MediaScope {
     /// Format parameters
     pub fmtps: Vec<Fmtp>,
 
+    /// Image resolution constraints of video formats
+    pub imageattrs: Vec<ImageAttr>,
+
+    /// Length of media represented by a packet in milliseconds (`a=ptime`)
+    pub ptime: Option<u32>,
+
+    /// Maximum length of media in a packet in milliseconds (`a=maxptime`)
+    pub maxptime: Option<u32>,
+
+    /// Maximum video frame rate in frames per second (`a=framerate`)
+    pub framerate: Option<f32>,
+
+    /// Suggested encoding quality from 0 (worst) to 10 (best) (`a=quality`)
+    pub quality: Option<u8>,
+
+    /// SCTP port of a data channel media description (`a=sctp-port`, [RFC8841](https://www.rfc-editor.org/rfc/rfc8841.html#section-5))
+    pub sctp_port: Option<u16>,
+
+    /// Largest message the endpoint is willing to receive, 0 if unlimited (`a=max-message-size`, [RFC8841](https://www.rfc-editor.org/rfc/rfc8841.html#section-6))
+    pub max_message_size: Option<u64>,
+
+    /// Legacy SCTP map
+    pub sctpmap: Option<SctpMap>,
+
+    /// T.38 fax parameters of an `image` media description
+    pub t38: T38Options,
+
+    /// BFCP floor control roles (`a=floorctrl`, [RFC8856](https://www.rfc-editor.org/rfc/rfc8856.html#section-5))
+    pub floorctrl: Vec<FloorControl>,
+
+    /// BFCP conference id (`a=confid`, [RFC8856](https://www.rfc-editor.org/rfc/rfc8856.html#section-6))
+    pub confid: Option<BytesStr>,
+
+    /// BFCP user id (`a=userid`, [RFC8856](https://www.rfc-editor.org/rfc/rfc8856.html#section-7))
+    pub userid: Option<BytesStr>,
+
+    /// BFCP floors (`a=floorid`)
+    pub floorids: Vec<FloorId>,
+
+    /// MSRP URIs of the endpoint and all relays, in the order they must be traversed (`a=path`, [RFC4975](https://www.rfc-editor.org/rfc/rfc4975.html#section-8.2))
+    pub msrp_path: Vec<BytesStr>,
+
+    /// Media types accepted in MSRP messages (`a=accept-types`)
+    pub accept_types: Vec<BytesStr>,
+
+    /// Media types only accepted inside a wrapper type like `message/cpim` (`a=accept-wrapped-types`)
+    pub accept_wrapped_types: Vec<BytesStr>,
+
+    /// Largest MSRP message the endpoint is willing to receive in octets (`a=max-size`)
+    pub max_size: Option<u64>,
+
+    /// RTCP feedback capabilities
+    pub rtcp_fb: Vec<RtcpFeedback>,
+
+    /// RTP header extensions (`a=extmap`)
+    pub extmaps: Vec<ExtMap>,
+
+    /// Media stream identification
+    pub msids: Vec<Msid>,
+
+    /// Source specific attributes (`a=ssrc`)
+    pub ssrcs: Vec<Ssrc>,
+
+    /// Relationships between sources (`a=ssrc-group`)
+    pub ssrc_groups: Vec<SsrcGroup>,
+
+    /// SDES crypto attributes
+    pub crypto: Vec<Crypto>,
+
+    /// Identifies the DTLS association
+    pub tls_id: Option<TlsId>,
+
+    /// Hash of the ZRTP Hello message (`a=zrtp-hash`)
+    pub zrtp_hash: Option<ZrtpHash>,
+
+    /// Which endpoint initiates the connection (`a=setup`)
+    pub setup: Option<Setup>,
+
+    /// Whether a new connection is required (`a=connection`)
+    pub tcp_connection: Option<TcpConnection>,
+
+    /// Attribute capabilities (`a=acap`)
+    pub acaps: Vec<AttributeCapability>,
+
+    /// Transport protocol capabilities (`a=tcap`)
+    pub tcaps: Vec<TransportCapability>,
+
+    /// Potential configurations offered (`a=pcfg`)
+    pub pcfgs: Vec<PotentialConfig>,
+
+    /// Potential configuration selected by the answerer (`a=acfg`)
+    pub acfg: Option<SelectedConfig>,
+
+    /// ICE options which only apply to this media description, in addition to the session's
+    pub ice_options: ice::Options,
+
     /// ICE username fragment
     pub ice_ufrag: Option<ice::UsernameFragment>,
 
@@ -269,14 +1038,192 @@ pub struct MediaScope {
     /// ICE a=end-of-candidates attribute
     pub ice_end_of_candidates: bool,
 
+    /// ICE a=remote-candidates attribute
+    pub ice_remote_candidates: Option<ice::RemoteCandidates>,
+
     /// Additional attributes
     pub attributes: Vec<UnknownAttribute>,
 }
 
+impl MediaScope {
+    /// Create a media scope from its description with all attributes unset
+    pub fn new(desc: MediaDescription) -> Self {
+        Self {
+            desc,
+            info: None,
+            direction: Direction::default(),
+            connection: None,
+            bandwidth: vec![],
+            key: None,
+            mid: None,
+            label: None,
+            content: None,
+            bundle_only: false,
+            rtcp_attr: None,
+            rtcp_mux: false,
+            rtcp_mux_only: false,
+            rtcp_rsize: false,
+            rtpmaps: vec![],
+            fmtps: vec![],
+            imageattrs: vec![],
+            ptime: None,
+            maxptime: None,
+            framerate: None,
+            quality: None,
+            sctp_port: None,
+            max_message_size: None,
+            sctpmap: None,
+            t38: T38Options::default(),
+            floorctrl: vec![],
+            confid: None,
+            userid: None,
+            floorids: vec![],
+            msrp_path: vec![],
+            accept_types: vec![],
+            accept_wrapped_types: vec![],
+            max_size: None,
+            rtcp_fb: vec![],
+            extmaps: vec![],
+            msids: vec![],
+            ssrcs: vec![],
+            ssrc_groups: vec![],
+            crypto: vec![],
+            tls_id: None,
+            zrtp_hash: None,
+            setup: None,
+            tcp_connection: None,
+            acaps: vec![],
+            tcaps: vec![],
+            pcfgs: vec![],
+            acfg: None,
+            ice_options: Default::default(),
+            ice_ufrag: None,
+            ice_pwd: None,
+            ice_candidates: vec![],
+            ice_end_of_candidates: false,
+            ice_remote_candidates: None,
+            attributes: vec![],
+        }
+    }
+
+    /// Returns the address RTCP is expected to be received on
+    ///
+    /// Resolves to the RTP address when RTCP is multiplexed, else the port (and address) of the
+    /// `a=rtcp` attribute ([RFC3605](https://datatracker.ietf.org/doc/html/rfc3605)) or the next
+    /// higher port. The connection address of the media scope is preferred over the session's.
+    ///
+    /// Returns `None` if no IP connection address is available.
+    pub fn rtcp_addr(&self, session: &Message) -> Option<SocketAddr> {
+        let connection = self.connection.as_ref().or(session.connection.as_ref())?;
+        let rtp_ip = connection.address.ip()?;
+
+        if self.rtcp_mux || self.rtcp_mux_only {
+            return Some(SocketAddr::new(rtp_ip, self.desc.port));
+        }
+
+        match &self.rtcp_attr {
+            Some(rtcp) => {
+                let ip = match &rtcp.address {
+                    Some(address) => address.ip()?,
+                    None => rtp_ip,
+                };
+
+                Some(SocketAddr::new(ip, rtcp.port))
+            }
+            None => Some(SocketAddr::new(rtp_ip, self.desc.port.checked_add(1)?)),
+        }
+    }
+
+    /// Returns the attribute capability with the given number, media level capabilities take
+    /// precedence over the session's
+    pub fn attribute_capability<'a>(
+        &'a self,
+        session: &'a Message,
+        number: u32,
+    ) -> Option<&'a UnknownAttribute> {
+        self.acaps
+            .iter()
+            .chain(&session.acaps)
+            .find(|acap| acap.number == number)
+            .map(|acap| &acap.attribute)
+    }
+
+    /// Returns the transport protocol capability with the given number, media level capabilities
+    /// take precedence over the session's
+    pub fn transport_capability<'a>(
+        &'a self,
+        session: &'a Message,
+        number: u32,
+    ) -> Option<&'a TransportProtocol> {
+        self.tcaps
+            .iter()
+            .chain(&session.tcaps)
+            .find_map(|tcap| tcap.get(number))
+    }
+
+    /// Returns the rtpmap of the payload type, falling back to the mapping of static payload types
+    pub fn rtpmap(&self, payload: u32) -> Option<RtpMap> {
+        self.rtpmaps
+            .iter()
+            .find(|rtpmap| rtpmap.payload == payload)
+            .cloned()
+            .or_else(|| RtpMap::from_static(payload))
+    }
+
+    /// Returns the `telephone-event` (DTMF) payload type with the given clock rate
+    pub fn telephone_event(&self, clock_rate: u32) -> Option<u32> {
+        self.desc.fmts.iter().copied().find(|&fmt| {
+            self.rtpmaps.iter().any(|rtpmap| {
+                rtpmap.payload == fmt
+                    && rtpmap.clock_rate == clock_rate
+                    && rtpmap.encoding.eq_ignore_ascii_case("telephone-event")
+            })
+        })
+    }
+
+    /// Returns the `telephone-event` payload type to use together with the given codec, which is
+    /// the one with the same clock rate
+    pub fn telephone_event_for(&self, payload: u32) -> Option<u32> {
+        self.telephone_event(self.rtpmap(payload)?.clock_rate)
+    }
+
+    /// Returns the events supported by the `telephone-event` payload type
+    pub fn telephone_events(&self, payload: u32) -> TelephoneEvents {
+        self.fmtps
+            .iter()
+            .find(|fmtp| fmtp.format == payload)
+            .map(Fmtp::telephone_events)
+            .unwrap_or_default()
+    }
+
+    /// Returns the role of the local endpoint in establishing the connection
+    ///
+    /// Must be called on the media description of the answer, session level attributes and the
+    /// defaults of [RFC4145](https://www.rfc-editor.org/rfc/rfc4145.html) are applied.
+    pub fn tcp_role(&self, session: &Message, local_is_answerer: bool) -> Option<TcpRole> {
+        let setup = self.setup.or(session.setup).unwrap_or(Setup::Passive);
+        let connection = self
+            .tcp_connection
+            .or(session.tcp_connection)
+            .unwrap_or_default();
+
+        TcpRole::from_answer(setup, connection, local_is_answerer)
+    }
+
+    /// Returns the header extensions of the media, including the ones declared at session level
+    pub fn all_extmaps<'a>(&'a self, session: &'a Message) -> impl Iterator<Item = &'a ExtMap> {
+        self.extmaps.iter().chain(&session.extmaps)
+    }
+}
+
 impl fmt::Display for MediaScope {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}\r\n", self.desc)?;
 
+        if let Some(info) = &self.info {
+            write!(f, "i={}\r\n", info)?;
+        }
+
         if let Some(conn) = &self.connection {
             write!(f, "{}\r\n", conn)?;
         }
@@ -285,12 +1232,44 @@ impl fmt::Display for MediaScope {
             write!(f, "{}\r\n", bw)?;
         }
 
+        if let Some(key) = &self.key {
+            write!(f, "k={}\r\n", key)?;
+        }
+
+        if let Some(mid) = &self.mid {
+            write!(f, "a=mid:{}\r\n", mid)?;
+        }
+
+        if let Some(label) = &self.label {
+            write!(f, "a=label:{}\r\n", label)?;
+        }
+
+        if let Some(content) = &self.content {
+            write!(f, "{}\r\n", content)?;
+        }
+
+        if self.bundle_only {
+            f.write_str("a=bundle-only\r\n")?;
+        }
+
         write!(f, "{}\r\n", self.direction)?;
 
         if let Some(rtcp) = &self.rtcp_attr {
             write!(f, "{}\r\n", rtcp)?;
         }
 
+        if self.rtcp_mux {
+            f.write_str("a=rtcp-mux\r\n")?;
+        }
+
+        if self.rtcp_mux_only {
+            f.write_str("a=rtcp-mux-only\r\n")?;
+        }
+
+        if self.rtcp_rsize {
+            f.write_str("a=rtcp-rsize\r\n")?;
+        }
+
         for rtpmap in &self.rtpmaps {
             write!(f, "{}\r\n", rtpmap)?;
         }
@@ -299,6 +1278,128 @@ impl fmt::Display for MediaScope {
             write!(f, "{}\r\n", fmtp)?;
         }
 
+        for imageattr in &self.imageattrs {
+            write!(f, "{}\r\n", imageattr)?;
+        }
+
+        if let Some(ptime) = self.ptime {
+            write!(f, "a=ptime:{}\r\n", ptime)?;
+        }
+
+        if let Some(maxptime) = self.maxptime {
+            write!(f, "a=maxptime:{}\r\n", maxptime)?;
+        }
+
+        if let Some(framerate) = self.framerate {
+            write!(f, "a=framerate:{}\r\n", framerate)?;
+        }
+
+        if let Some(quality) = self.quality {
+            write!(f, "a=quality:{}\r\n", quality)?;
+        }
+
+        if let Some(sctp_port) = self.sctp_port {
+            write!(f, "a=sctp-port:{}\r\n", sctp_port)?;
+        }
+
+        if let Some(sctpmap) = &self.sctpmap {
+            write!(f, "{}\r\n", sctpmap)?;
+        }
+
+        if let Some(max_message_size) = self.max_message_size {
+            write!(f, "a=max-message-size:{}\r\n", max_message_size)?;
+        }
+
+        write!(f, "{}", self.t38)?;
+
+        if let Some((first, rest)) = self.floorctrl.split_first() {
+            write!(f, "a=floorctrl:{}", first)?;
+
+            for role in rest {
+                write!(f, " {}", role)?;
+            }
+
+            f.write_str("\r\n")?;
+        }
+
+        if let Some(confid) = &self.confid {
+            write!(f, "a=confid:{}\r\n", confid)?;
+        }
+
+        if let Some(userid) = &self.userid {
+            write!(f, "a=userid:{}\r\n", userid)?;
+        }
+
+        for floorid in &self.floorids {
+            write!(f, "{}\r\n", floorid)?;
+        }
+
+        write_list(f, "accept-types", &self.accept_types)?;
+        write_list(f, "accept-wrapped-types", &self.accept_wrapped_types)?;
+        write_list(f, "path", &self.msrp_path)?;
+
+        if let Some(max_size) = self.max_size {
+            write!(f, "a=max-size:{}\r\n", max_size)?;
+        }
+
+        for rtcp_fb in &self.rtcp_fb {
+            write!(f, "{}\r\n", rtcp_fb)?;
+        }
+
+        for extmap in &self.extmaps {
+            write!(f, "{}\r\n", extmap)?;
+        }
+
+        for msid in &self.msids {
+            write!(f, "{}\r\n", msid)?;
+        }
+
+        for group in &self.ssrc_groups {
+            write!(f, "{}\r\n", group)?;
+        }
+
+        for ssrc in &self.ssrcs {
+            write!(f, "{}\r\n", ssrc)?;
+        }
+
+        for crypto in &self.crypto {
+            write!(f, "{}\r\n", crypto)?;
+        }
+
+        if let Some(tls_id) = &self.tls_id {
+            write!(f, "{}\r\n", tls_id)?;
+        }
+
+        if let Some(zrtp_hash) = &self.zrtp_hash {
+            write!(f, "{}\r\n", zrtp_hash)?;
+        }
+
+        if let Some(setup) = &self.setup {
+            write!(f, "{}\r\n", setup)?;
+        }
+
+        if let Some(connection) = &self.tcp_connection {
+            write!(f, "{}\r\n", connection)?;
+        }
+
+        for acap in &self.acaps {
+            write!(f, "{}\r\n", acap)?;
+        }
+
+        for tcap in &self.tcaps {
+            write!(f, "{}\r\n", tcap)?;
+        }
+
+        for pcfg in &self.pcfgs {
+            write!(f, "{}\r\n", pcfg)?;
+        }
+
+        if let Some(acfg) = &self.acfg {
+            write!(f, "{}\r\n", acfg)?;
+        }
+
+        write!(f, "{}", self.ice_options)?;
+
         if let Some(ufrag) = &self.ice_ufrag {
             write!(f, "{}\r\n", ufrag)?;
         }
@@ -307,6 +1408,18 @@ impl fmt::Display for MediaScope {
             write!(f, "{}\r\n", pwd)?;
         }
 
+        for candidate in &self.ice_candidates {
+            write!(f, "{}\r\n", candidate)?;
+        }
+
+        if self.ice_end_of_candidates {
+            f.write_str("a=end-of-candidates\r\n")?;
+        }
+
+        if let Some(remote_candidates) = &self.ice_remote_candidates {
+            write!(f, "{}\r\n", remote_candidates)?;
+        }
+
         for attr in &self.attributes {
             write!(f, "{}\r\n", attr)?;
         }
@@ -320,180 +1433,870 @@ pub struct Message {
     /// The name of the sdp session (s field)
     pub name: BytesStr,
 
-    /// Origin (o field)
-    pub origin: Origin,
+    /// Session information (i field)
+    pub info: Option<BytesStr>,
+
+    /// URI of additional information about the session (u field)
+    pub uri: Option<BytesStr>,
+
+    /// Email addresses (e fields)
+    pub emails: Vec<BytesStr>,
+
+    /// Phone numbers (p fields)
+    pub phones: Vec<BytesStr>,
+
+    /// Origin (o field)
+    pub origin: Origin,
+
+    /// Session start/stop time (t field)
+    pub time: Time,
+
+    /// Time zone adjustments for repeat times (z field)
+    pub zone_adjustments: Vec<ZoneAdjustment>,
+
+    /// Global session media direction
+    pub direction: Direction,
+
+    /// Optional connection (c field)
+    pub connection: Option<Connection>,
+
+    /// Bandwidth (b field)
+    pub bandwidth: Vec<Bandwidth>,
+
+    /// Obsolete encryption key (k field), kept as is to pass it through
+    ///
+    /// [RFC8866](https://www.rfc-editor.org/rfc/rfc8866.html#section-5.12) deprecates the field,
+    /// it is neither interpreted nor validated.
+    pub key: Option<BytesStr>,
+
+    /// Media groups (`a=group`), e.g. BUNDLE groups
+    pub groups: Vec<Group>,
+
+    /// Identity assertion (`a=identity`)
+    pub identity: Option<Identity>,
+
+    /// Attribute capabilities shared by all media (`a=acap`)
+    pub acaps: Vec<AttributeCapability>,
+
+    /// Transport protocol capabilities shared by all media (`a=tcap`)
+    pub tcaps: Vec<TransportCapability>,
+
+    /// RTP header extensions used by all media (`a=extmap`)
+    pub extmaps: Vec<ExtMap>,
+
+    /// Which endpoint initiates the connections, if not specified by the media (`a=setup`)
+    pub setup: Option<Setup>,
+
+    /// Whether new connections are required, if not specified by the media (`a=connection`)
+    pub tcp_connection: Option<TcpConnection>,
+
+    /// ICE options, omitted if empty
+    pub ice_options: ice::Options,
+
+    /// Pacing interval of connectivity checks in milliseconds, the default is 50 (`a=ice-pacing`, [RFC8839](https://www.rfc-editor.org/rfc/rfc8839.html#section-5.5))
+    pub ice_pacing: Option<u32>,
+
+    /// If not present: false
+    ///
+    /// If specified an ice-lite implementation is used
+    pub ice_lite: bool,
+
+    /// ICE username fragment
+    pub ice_ufrag: Option<ice::UsernameFragment>,
+
+    /// ICE password
+    pub ice_pwd: Option<ice::Password>,
+
+    /// All attributes not parsed directly
+    pub attributes: Vec<UnknownAttribute>,
+
+    /// Media scopes
+    pub media_scopes: Vec<MediaScope>,
+}
+
+impl Message {
+    /// Create a message with the given origin and media scopes
+    ///
+    /// The session is named `-`, unbounded (`t=0 0`) and uses the origin's address as session
+    /// level connection, all attributes are unset.
+    pub fn new(origin: Origin, media_scopes: Vec<MediaScope>) -> Self {
+        Self {
+            name: "-".into(),
+            info: None,
+            uri: None,
+            emails: vec![],
+            phones: vec![],
+            connection: Some(Connection {
+                address: origin.address.clone(),
+                ttl: None,
+                num: None,
+            }),
+            origin,
+            time: Time {
+                start: 0,
+                stop: 0,
+                repeats: vec![],
+            },
+            zone_adjustments: vec![],
+            direction: Direction::default(),
+            bandwidth: vec![],
+            key: None,
+            groups: vec![],
+            ice_options: Default::default(),
+            ice_pacing: None,
+            identity: None,
+            acaps: vec![],
+            tcaps: vec![],
+            extmaps: vec![],
+            setup: None,
+            tcp_connection: None,
+            ice_lite: false,
+            ice_ufrag: None,
+            ice_pwd: None,
+            attributes: vec![],
+            media_scopes,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error<E: Debug + Display> {
+    #[error(transparent)]
+    ParseError(#[from] ParseError),
+    #[error("message is incomplete")]
+    Incomplete,
+    #[error("line exceeds the maximum length of {0}")]
+    LineTooLong(usize),
+    #[error("message exceeds the maximum of {0} attributes")]
+    TooManyAttributes(usize),
+    #[error("line is not valid UTF-8")]
+    InvalidUtf8,
+    #[error("{0}")]
+    Builder(E),
+}
+
+/// Error returned when parsing a message
+#[derive(Debug)]
+pub struct ParseMessageError<E: Debug + Display> {
+    /// The line which caused the error, `None` if it isn't caused by a single line, e.g. when
+    /// a required field is missing
+    pub line: Option<ErrorLine>,
+
+    pub error: Error<E>,
+}
+
+/// Position and content of a line which failed to parse
+#[derive(Debug, Clone)]
+pub struct ErrorLine {
+    /// Line number starting at 1, empty lines are not counted
+    pub number: usize,
+    pub content: BytesStr,
+}
+
+impl<E: Debug + Display> From<Error<E>> for ParseMessageError<E> {
+    fn from(error: Error<E>) -> Self {
+        Self { line: None, error }
+    }
+}
+
+impl<E: Debug + Display> fmt::Display for ParseMessageError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(line) = &self.line {
+            write!(f, "line {} `{}`: ", line.number, line.content)?;
+        }
+
+        write!(f, "{}", self.error)
+    }
+}
+
+impl<E: Debug + Display> std::error::Error for ParseMessageError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.error {
+            Error::ParseError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// How media descriptions with an unknown media type are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownMedia {
+    /// Fail to parse the message
+    #[default]
+    Reject,
+
+    /// Skip the media description and all its lines
+    Ignore,
+}
+
+/// Options to relax or restrict the parser
+///
+/// The default options are as strict as [`parse`]
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Assume an unbounded session (`t=0 0`) if the message has no time field
+    pub allow_missing_time: bool,
+
+    /// Accept field types in upper case (e.g. `M=` instead of `m=`)
+    pub ignore_field_case: bool,
+
+    /// Maximum length of a single line
+    pub max_line_length: Option<usize>,
+
+    /// Maximum number of attributes at session and media level combined
+    pub max_attributes: Option<usize>,
+
+    pub unknown_media: UnknownMedia,
+}
+
+impl ParseOptions {
+    /// Options for gateways which must accept anything that can be understood
+    pub fn tolerant() -> Self {
+        Self {
+            allow_missing_time: true,
+            ignore_field_case: true,
+            max_line_length: None,
+            max_attributes: None,
+            unknown_media: UnknownMedia::Ignore,
+        }
+    }
+}
+
+pub fn parse<B: ParseBuilder>(src: &BytesStr) -> Result<B::Message, ParseMessageError<B::Error>> {
+    parse_with::<B>(src, &ParseOptions::default())
+}
+
+pub fn parse_with<B: ParseBuilder>(
+    src: &BytesStr,
+    options: &ParseOptions,
+) -> Result<B::Message, ParseMessageError<B::Error>> {
+    let mut parser = Parser::<B>::new(options.clone());
+
+    for line in src.split(['\n', '\r']) {
+        parser.parse_line(&src.slice_ref(line))?;
+    }
+
+    parser.finish()
+}
+
+/// Parse the message, skipping lines which fail to parse
+///
+/// Returns the partially parsed message and the errors of the skipped lines. Fails only if
+/// the message cannot be built at all, e.g. because the origin is missing.
+#[allow(clippy::type_complexity)]
+pub fn parse_partial<B: ParseBuilder>(
+    src: &BytesStr,
+    options: &ParseOptions,
+) -> Result<(B::Message, Vec<ParseMessageError<B::Error>>), ParseMessageError<B::Error>> {
+    let mut parser = Parser::<B>::new(options.clone());
+    let mut errors = vec![];
+
+    for line in src.split(['\n', '\r']) {
+        if let Err(e) = parser.parse_line(&src.slice_ref(line)) {
+            errors.push(e);
+        }
+    }
+
+    Ok((parser.finish()?, errors))
+}
+
+/// Parser which is fed the message line by line or in chunks as it is received
+///
+/// [`parse_with`] is preferable if the complete message is available, since the values can
+/// reference the message instead of the individual lines.
+///
+/// ```
+/// # use ezk_sdp_types::msg::{Builder, ParseOptions, Parser};
+/// let mut parser = Parser::<Builder>::new(ParseOptions::default());
+///
+/// parser.feed(b"v=0\r\no=- 1 1 IN IP4 10.0.0.1\r\ns=-\r").unwrap();
+/// parser.feed(b"\nt=0 0\r\n").unwrap();
+///
+/// let msg = parser.finish().unwrap();
+/// assert_eq!(msg.name, "-");
+/// ```
+///
+/// Lines which fail to parse are skipped, so the parser can be used further after an error to
+/// recover from invalid lines.
+#[derive(Debug)]
+pub struct Parser<B> {
+    builder: B,
+    options: ParseOptions,
+
+    /// Incomplete line of the last chunk
+    buffer: BytesMut,
+
+    /// Number of non-empty lines received
+    line_number: usize,
+
+    has_time: bool,
+    attribute_count: usize,
+    skip_media: bool,
+}
+
+impl<B: ParseBuilder> Parser<B> {
+    pub fn new(options: ParseOptions) -> Self {
+        Self {
+            builder: B::default(),
+            options,
+            buffer: BytesMut::new(),
+            line_number: 0,
+            has_time: false,
+            attribute_count: 0,
+            skip_media: false,
+        }
+    }
+
+    /// Parse the complete lines of the chunk, the rest is kept until the next chunk arrives
+    ///
+    /// On error the remaining lines of the chunk are kept as well and parsed when feeding the
+    /// next chunk.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), ParseMessageError<B::Error>> {
+        self.buffer.extend_from_slice(chunk);
+
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n' || b == b'\r') {
+            let line = self.buffer.split_to(end).freeze();
+            self.buffer.advance(1);
+
+            self.parse_bytes(line)?;
+        }
+
+        if let Some(max) = self.options.max_line_length {
+            if self.buffer.len() > max {
+                return Err(ParseMessageError {
+                    line: Some(ErrorLine {
+                        number: self.line_number + 1,
+                        content: String::from_utf8_lossy(&self.buffer[..max])
+                            .into_owned()
+                            .into(),
+                    }),
+                    error: Error::LineTooLong(max),
+                });
+            }
+        }
+
+        Ok(())
+    }
 
-    /// Session start/stop time (t field)
-    pub time: Time,
+    fn parse_bytes(&mut self, line: Bytes) -> Result<(), ParseMessageError<B::Error>> {
+        match BytesStr::from_utf8_bytes(line.clone()) {
+            Ok(line) => self.parse_line(&line),
+            Err(_) => {
+                self.line_number += 1;
+
+                Err(ParseMessageError {
+                    line: Some(ErrorLine {
+                        number: self.line_number,
+                        content: String::from_utf8_lossy(&line).into_owned().into(),
+                    }),
+                    error: Error::InvalidUtf8,
+                })
+            }
+        }
+    }
 
-    /// Global session media direction
-    pub direction: Direction,
+    /// Parse a single line without the line terminator, empty lines are ignored
+    pub fn parse_line(&mut self, src: &BytesStr) -> Result<(), ParseMessageError<B::Error>> {
+        if src.is_empty() {
+            return Ok(());
+        }
 
-    /// Optional connection (c field)
-    pub connection: Option<Connection>,
+        self.line_number += 1;
 
-    /// Bandwidth (b field)
-    pub bandwidth: Vec<Bandwidth>,
+        self.parse_line_inner(src)
+            .map_err(|error| ParseMessageError {
+                line: Some(ErrorLine {
+                    number: self.line_number,
+                    content: src.clone(),
+                }),
+                error,
+            })
+    }
 
-    /// ICE options, omitted if empty
-    pub ice_options: ice::Options,
+    fn parse_line_inner(&mut self, src: &BytesStr) -> Result<(), Error<B::Error>> {
+        let complete_line = src.as_str();
 
-    /// If not present: false
-    ///
-    /// If specified an ice-lite implementation is used
-    pub ice_lite: bool,
+        if let Some(max) = self.options.max_line_length {
+            if complete_line.len() > max {
+                return Err(Error::LineTooLong(max));
+            }
+        }
 
-    /// ICE username fragment
-    pub ice_ufrag: Option<ice::UsernameFragment>,
+        let line = complete_line.get(2..).ok_or(Error::Incomplete)?;
 
-    /// ICE password
-    pub ice_pwd: Option<ice::Password>,
+        // Only the first 3 bytes are matched on, the field type and `=` (or `v=0`)
+        let mut prefix = [0; 3];
+        let prefix_len = complete_line.len().min(3);
+        prefix[..prefix_len].copy_from_slice(&complete_line.as_bytes()[..prefix_len]);
 
-    /// All attributes not parsed directly
-    pub attributes: Vec<UnknownAttribute>,
+        if self.options.ignore_field_case {
+            prefix[0].make_ascii_lowercase();
+        }
 
-    /// Media scopes
-    pub media_scopes: Vec<MediaScope>,
-}
+        let prefix = &prefix[..prefix_len];
 
-#[derive(Debug, thiserror::Error)]
-pub enum Error<E: Debug + Display> {
-    #[error(transparent)]
-    ParseError(#[from] ParseError),
-    #[error("message is incomplete")]
-    Incomplete,
-    #[error("{0}")]
-    Builder(E),
-}
+        if prefix.starts_with(b"m=") {
+            self.skip_media = self.options.unknown_media == UnknownMedia::Ignore
+                && MediaType::parse(line).is_err();
+        }
 
-pub fn parse<B: ParseBuilder>(src: &BytesStr) -> Result<B::Message, Error<B::Error>> {
-    let lines = src
-        .split(|c| matches!(c, '\n' | '\r'))
-        .filter(|line| !line.is_empty());
+        if self.skip_media {
+            return Ok(());
+        }
 
-    let mut builder = B::default();
+        if prefix.starts_with(b"a=") {
+            self.attribute_count += 1;
 
-    for complete_line in lines {
-        let line = complete_line.get(2..).ok_or(Error::Incomplete)?;
+            if let Some(max) = self.options.max_attributes {
+                if self.attribute_count > max {
+                    return Err(Error::TooManyAttributes(max));
+                }
+            }
+        }
 
-        match complete_line.as_bytes() {
+        match prefix {
             [b'v', b'=', b'0'] => {
                 // parsed the version yay!
             }
             [b's', b'=', ..] => {
                 let name = BytesStr::from_parse(src.as_ref(), line);
-                builder.set_name(name).map_err(Error::Builder)?;
+                self.builder.set_name(name).map_err(Error::Builder)?;
+            }
+            [b'i', b'=', ..] => {
+                let info = BytesStr::from_parse(src.as_ref(), line);
+                self.builder.set_info(info).map_err(Error::Builder)?;
+            }
+            [b'u', b'=', ..] => {
+                let uri = BytesStr::from_parse(src.as_ref(), line);
+                self.builder.set_uri(uri).map_err(Error::Builder)?;
+            }
+            [b'e', b'=', ..] => {
+                let email = BytesStr::from_parse(src.as_ref(), line);
+                self.builder.add_email(email).map_err(Error::Builder)?;
+            }
+            [b'p', b'=', ..] => {
+                let phone = BytesStr::from_parse(src.as_ref(), line);
+                self.builder.add_phone(phone).map_err(Error::Builder)?;
             }
             [b'o', b'=', ..] => {
                 let (_, origin) = Origin::parse(src.as_ref(), line).finish()?;
-                builder.set_origin(origin).map_err(Error::Builder)?;
+                self.builder.set_origin(origin).map_err(Error::Builder)?;
             }
             [b't', b'=', ..] => {
                 let (_, time) = Time::parse(line).finish()?;
-                builder.set_time(time).map_err(Error::Builder)?;
+                self.builder.set_time(time).map_err(Error::Builder)?;
+                self.has_time = true;
+            }
+            [b'r', b'=', ..] => {
+                let (_, repeat) = RepeatTime::parse(line).finish()?;
+                self.builder
+                    .add_repeat_time(repeat)
+                    .map_err(Error::Builder)?;
+            }
+            [b'z', b'=', ..] => {
+                let (_, adjustments) = ZoneAdjustment::parse_list(line).finish()?;
+                self.builder
+                    .set_zone_adjustments(adjustments)
+                    .map_err(Error::Builder)?;
             }
             [b'c', b'=', ..] => {
                 let (_, connection) = Connection::parse(src.as_ref(), line).finish()?;
-                builder.set_connection(connection).map_err(Error::Builder)?;
+                self.builder
+                    .set_connection(connection)
+                    .map_err(Error::Builder)?;
             }
             [b'b', b'=', ..] => {
                 let (_, bandwidth) = Bandwidth::parse(src.as_ref(), line).finish()?;
-                builder.add_bandwidth(bandwidth).map_err(Error::Builder)?;
+                self.builder
+                    .add_bandwidth(bandwidth)
+                    .map_err(Error::Builder)?;
+            }
+            [b'k', b'=', ..] => {
+                let key = BytesStr::from_parse(src.as_ref(), line);
+                self.builder.set_key(key).map_err(Error::Builder)?;
             }
             [b'm', b'=', ..] => {
                 let (_, desc) = MediaDescription::parse(src.as_ref(), line).finish()?;
-                builder.begin_media(desc).map_err(Error::Builder)?;
+                self.builder.begin_media(desc).map_err(Error::Builder)?;
             }
             [b'a', b'=', ..] => {
                 if let Some((attr, attr_v)) = line.split_once(':') {
                     match attr {
                         "rtpmap" => {
                             let (_, rtpmap) = RtpMap::parse(src.as_ref(), line).finish()?;
-                            builder.add_rtpmap(rtpmap).map_err(Error::Builder)?;
+                            self.builder.add_rtpmap(rtpmap).map_err(Error::Builder)?;
                         }
                         "fmtp" => {
                             let (_, fmtp) = Fmtp::parse(src.as_ref(), line).finish()?;
-                            builder.add_fmtp(fmtp).map_err(Error::Builder)?;
+                            self.builder.add_fmtp(fmtp).map_err(Error::Builder)?;
+                        }
+                        "group" => {
+                            let (_, group) = Group::parse(src.as_ref(), line).finish()?;
+                            self.builder.add_group(group).map_err(Error::Builder)?;
+                        }
+                        "label" => {
+                            let label = BytesStr::from_parse(src.as_ref(), attr_v.trim());
+                            self.builder.set_label(label).map_err(Error::Builder)?;
+                        }
+                        "content" => {
+                            let (_, content) = Content::parse(src.as_ref(), line).finish()?;
+                            self.builder.set_content(content).map_err(Error::Builder)?;
+                        }
+                        "identity" => {
+                            let (_, identity) = Identity::parse(src.as_ref(), line).finish()?;
+                            self.builder
+                                .set_identity(identity)
+                                .map_err(Error::Builder)?;
+                        }
+                        "floorctrl" => {
+                            let (_, roles) = FloorControl::parse_list(attr_v).finish()?;
+                            self.builder.set_floorctrl(roles).map_err(Error::Builder)?;
+                        }
+                        "confid" => {
+                            let confid = BytesStr::from_parse(src.as_ref(), attr_v.trim());
+                            self.builder.set_confid(confid).map_err(Error::Builder)?;
+                        }
+                        "userid" => {
+                            let userid = BytesStr::from_parse(src.as_ref(), attr_v.trim());
+                            self.builder.set_userid(userid).map_err(Error::Builder)?;
+                        }
+                        "floorid" => {
+                            let (_, floorid) = FloorId::parse(src.as_ref(), line).finish()?;
+                            self.builder.add_floorid(floorid).map_err(Error::Builder)?;
+                        }
+                        "path" => self
+                            .builder
+                            .set_msrp_path(words(src.as_ref(), attr_v))
+                            .map_err(Error::Builder)?,
+                        "accept-types" => self
+                            .builder
+                            .set_accept_types(words(src.as_ref(), attr_v))
+                            .map_err(Error::Builder)?,
+                        "accept-wrapped-types" => self
+                            .builder
+                            .set_accept_wrapped_types(words(src.as_ref(), attr_v))
+                            .map_err(Error::Builder)?,
+                        "max-size" => match number(attr_v.trim()) {
+                            Ok(("", size)) => {
+                                self.builder.set_max_size(size).map_err(Error::Builder)?
+                            }
+                            _ => self.add_invalid_attr(src, line)?,
+                        },
+                        "mid" => {
+                            let mid = BytesStr::from_parse(src.as_ref(), attr_v.trim());
+                            self.builder.set_mid(mid).map_err(Error::Builder)?;
+                        }
+                        "imageattr" => match ImageAttr::parse(line.trim_end()) {
+                            Ok(("", imageattr)) => self
+                                .builder
+                                .add_imageattr(imageattr)
+                                .map_err(Error::Builder)?,
+                            _ => self.add_invalid_attr(src, line)?,
+                        },
+                        "ptime" => match number(attr_v.trim()) {
+                            Ok(("", ptime)) => {
+                                self.builder.set_ptime(ptime).map_err(Error::Builder)?
+                            }
+                            _ => self.add_invalid_attr(src, line)?,
+                        },
+                        "maxptime" => match number(attr_v.trim()) {
+                            Ok(("", maxptime)) => self
+                                .builder
+                                .set_maxptime(maxptime)
+                                .map_err(Error::Builder)?,
+                            _ => self.add_invalid_attr(src, line)?,
+                        },
+                        "framerate" => match frame_rate(attr_v.trim()) {
+                            Ok(("", framerate)) => self
+                                .builder
+                                .set_framerate(framerate)
+                                .map_err(Error::Builder)?,
+                            _ => self.add_invalid_attr(src, line)?,
+                        },
+                        "quality" => match number(attr_v.trim()) {
+                            Ok(("", quality)) => {
+                                self.builder.set_quality(quality).map_err(Error::Builder)?
+                            }
+                            _ => self.add_invalid_attr(src, line)?,
+                        },
+                        "sctp-port" => match number(attr_v.trim()) {
+                            Ok(("", port)) => {
+                                self.builder.set_sctp_port(port).map_err(Error::Builder)?
+                            }
+                            _ => self.add_invalid_attr(src, line)?,
+                        },
+                        "max-message-size" => match number(attr_v.trim()) {
+                            Ok(("", size)) => self
+                                .builder
+                                .set_max_message_size(size)
+                                .map_err(Error::Builder)?,
+                            _ => self.add_invalid_attr(src, line)?,
+                        },
+                        "sctpmap" => {
+                            let (_, sctpmap) = SctpMap::parse(src.as_ref(), line).finish()?;
+                            self.builder.set_sctpmap(sctpmap).map_err(Error::Builder)?;
                         }
                         "rtcp" => {
                             let (_, rtcp_attr) = RtcpAttr::parse(src.as_ref(), line).finish()?;
-                            builder.add_rtcp(rtcp_attr).map_err(Error::Builder)?;
+                            self.builder.add_rtcp(rtcp_attr).map_err(Error::Builder)?;
+                        }
+                        "rtcp-fb" => {
+                            let (_, rtcp_fb) = RtcpFeedback::parse(src.as_ref(), line).finish()?;
+                            self.builder.add_rtcp_fb(rtcp_fb).map_err(Error::Builder)?;
+                        }
+                        "extmap" => {
+                            let (_, extmap) = ExtMap::parse(src.as_ref(), line).finish()?;
+                            self.builder.add_extmap(extmap).map_err(Error::Builder)?;
+                        }
+                        "crypto" => {
+                            let (_, crypto) = Crypto::parse(src.as_ref(), line).finish()?;
+                            self.builder.add_crypto(crypto).map_err(Error::Builder)?;
+                        }
+                        "tls-id" => {
+                            let (_, tls_id) = TlsId::parse(src.as_ref(), line).finish()?;
+                            self.builder.set_tls_id(tls_id).map_err(Error::Builder)?;
+                        }
+                        "zrtp-hash" => {
+                            let (_, zrtp_hash) = ZrtpHash::parse(src.as_ref(), line).finish()?;
+                            self.builder
+                                .set_zrtp_hash(zrtp_hash)
+                                .map_err(Error::Builder)?;
+                        }
+                        "setup" => match Setup::parse(attr_v.trim()) {
+                            Ok(("", setup)) => {
+                                self.builder.set_setup(setup).map_err(Error::Builder)?
+                            }
+                            _ => self.add_invalid_attr(src, line)?,
+                        },
+                        "connection" => match TcpConnection::parse(attr_v.trim()) {
+                            Ok(("", connection)) => self
+                                .builder
+                                .set_tcp_connection(connection)
+                                .map_err(Error::Builder)?,
+                            _ => self.add_invalid_attr(src, line)?,
+                        },
+                        "acap" => {
+                            let (_, acap) =
+                                AttributeCapability::parse(src.as_ref(), line).finish()?;
+                            self.builder.add_acap(acap).map_err(Error::Builder)?;
+                        }
+                        "tcap" => {
+                            let (_, tcap) =
+                                TransportCapability::parse(src.as_ref(), line).finish()?;
+                            self.builder.add_tcap(tcap).map_err(Error::Builder)?;
+                        }
+                        "pcfg" => {
+                            let (_, pcfg) = PotentialConfig::parse(src.as_ref(), line).finish()?;
+                            self.builder.add_pcfg(pcfg).map_err(Error::Builder)?;
+                        }
+                        "acfg" => {
+                            let (_, acfg) = SelectedConfig::parse(src.as_ref(), line).finish()?;
+                            self.builder.set_acfg(acfg).map_err(Error::Builder)?;
+                        }
+                        "msid" => {
+                            let (_, msid) = Msid::parse(src.as_ref(), line).finish()?;
+                            self.builder.add_msid(msid).map_err(Error::Builder)?;
+                        }
+                        "ssrc" => {
+                            let (_, ssrc) = Ssrc::parse(src.as_ref(), line).finish()?;
+                            self.builder.add_ssrc(ssrc).map_err(Error::Builder)?;
+                        }
+                        "ssrc-group" => {
+                            let (_, group) = SsrcGroup::parse(src.as_ref(), line).finish()?;
+                            self.builder.add_ssrc_group(group).map_err(Error::Builder)?;
                         }
                         "ice-lite" => {
-                            builder.set_ice_lite(true).map_err(Error::Builder)?;
+                            self.builder.set_ice_lite(true).map_err(Error::Builder)?;
                         }
                         "ice-options" => {
                             let (_, options) =
                                 ice::Options::parse(src.as_ref(), attr_v).finish()?;
-                            builder.set_ice_options(options).map_err(Error::Builder)?;
+                            self.builder
+                                .set_ice_options(options)
+                                .map_err(Error::Builder)?;
                         }
+                        "ice-pacing" => match number(attr_v.trim()) {
+                            Ok(("", pacing)) => self
+                                .builder
+                                .set_ice_pacing(pacing)
+                                .map_err(Error::Builder)?,
+                            _ => self.add_invalid_attr(src, line)?,
+                        },
                         "ice-ufrag" => {
                             let (_, ice_ufrag) =
                                 ice::UsernameFragment::parse(src.as_ref(), attr_v).finish()?;
-                            builder.set_ice_ufrag(ice_ufrag).map_err(Error::Builder)?;
+                            self.builder
+                                .set_ice_ufrag(ice_ufrag)
+                                .map_err(Error::Builder)?;
                         }
                         "ice-pwd" => {
                             let (_, ice_pwd) =
                                 ice::Password::parse(src.as_ref(), attr_v).finish()?;
-                            builder.set_ice_pwd(ice_pwd).map_err(Error::Builder)?;
+                            self.builder.set_ice_pwd(ice_pwd).map_err(Error::Builder)?;
+                        }
+                        "remote-candidates" => {
+                            let (_, remote_candidates) =
+                                ice::RemoteCandidates::parse(src.as_ref(), attr_v).finish()?;
+                            self.builder
+                                .set_ice_remote_candidates(remote_candidates)
+                                .map_err(Error::Builder)?;
                         }
                         "candidate" => {
                             let (_, ice_candidate) =
                                 Candidate::parse(src.as_ref(), line).finish()?;
-                            builder
+                            self.builder
                                 .add_ice_candidate(ice_candidate)
                                 .map_err(Error::Builder)?;
                         }
-                        _ => {
-                            let attr = UnknownAttribute {
-                                name: src.slice_ref(attr),
-                                value: Some(src.slice_ref(attr_v)),
-                            };
-
-                            builder.add_unknown_attr(attr).map_err(Error::Builder)?;
-                        }
+                        _ => match T38Param::parse(line) {
+                            Ok(("", param)) => {
+                                self.builder.set_t38_param(param).map_err(Error::Builder)?
+                            }
+                            _ => {
+                                let attr = UnknownAttribute {
+                                    name: src.slice_ref(attr),
+                                    value: Some(src.slice_ref(attr_v)),
+                                };
+
+                                self.builder
+                                    .add_unknown_attr(attr)
+                                    .map_err(Error::Builder)?;
+                            }
+                        },
                     }
                 } else {
                     match line {
                         "sendrecv" => {
-                            builder
+                            self.builder
                                 .set_direction(Direction::SendRecv)
                                 .map_err(Error::Builder)?;
                         }
                         "recvonly" => {
-                            builder
+                            self.builder
                                 .set_direction(Direction::RecvOnly)
                                 .map_err(Error::Builder)?;
                         }
                         "sendonly" => {
-                            builder
+                            self.builder
                                 .set_direction(Direction::SendOnly)
                                 .map_err(Error::Builder)?;
                         }
                         "inactive" => {
-                            builder
+                            self.builder
                                 .set_direction(Direction::Inactive)
                                 .map_err(Error::Builder)?;
                         }
-                        "end-of-candidates" => builder
-                            .set_ice_end_of_candidates(true)
+                        "rtcp-mux" => self.builder.set_rtcp_mux(true).map_err(Error::Builder)?,
+                        "rtcp-mux-only" => self
+                            .builder
+                            .set_rtcp_mux_only(true)
                             .map_err(Error::Builder)?,
-                        _ => {
-                            let attr = UnknownAttribute {
-                                name: src.slice_ref(line),
-                                value: None,
-                            };
-
-                            builder.add_unknown_attr(attr).map_err(Error::Builder)?;
+                        "rtcp-rsize" => {
+                            self.builder.set_rtcp_rsize(true).map_err(Error::Builder)?
+                        }
+                        "bundle-only" => {
+                            self.builder.set_bundle_only(true).map_err(Error::Builder)?
                         }
+                        "end-of-candidates" => self
+                            .builder
+                            .set_ice_end_of_candidates(true)
+                            .map_err(Error::Builder)?,
+                        _ => match T38Param::parse(line) {
+                            Ok(("", param)) => {
+                                self.builder.set_t38_param(param).map_err(Error::Builder)?
+                            }
+                            _ => {
+                                let attr = UnknownAttribute {
+                                    name: src.slice_ref(line),
+                                    value: None,
+                                };
+
+                                self.builder
+                                    .add_unknown_attr(attr)
+                                    .map_err(Error::Builder)?;
+                            }
+                        },
                     }
                 }
             }
             _ => {}
         }
+
+        Ok(())
+    }
+
+    /// Keep an attribute with an invalid value as unknown attribute instead of rejecting the
+    /// message, e.g. to report it when validating
+    fn add_invalid_attr(&mut self, src: &BytesStr, line: &str) -> Result<(), Error<B::Error>> {
+        self.builder
+            .add_unknown_attr(UnknownAttribute::parse(src.as_ref(), line))
+            .map_err(Error::Builder)
+    }
+
+    /// Parse the remaining input and build the message
+    pub fn finish(mut self) -> Result<B::Message, ParseMessageError<B::Error>> {
+        if !self.buffer.is_empty() {
+            let line = self.buffer.split().freeze();
+            self.parse_bytes(line)?;
+        }
+
+        if !self.has_time && self.options.allow_missing_time {
+            let time = Time {
+                start: 0,
+                stop: 0,
+                repeats: vec![],
+            };
+
+            self.builder.set_time(time).map_err(Error::Builder)?;
+        }
+
+        Ok(self.builder.finish().map_err(Error::Builder)?)
+    }
+}
+
+impl Message {
+    /// Returns the length of the serialized message
+    ///
+    /// The message is formatted without storing the output, so this doesn't allocate.
+    pub fn encoded_len(&self) -> usize {
+        let mut counter = LenCounter(0);
+        let _ = self.write_to(&mut counter);
+        counter.0
+    }
+
+    /// Serialize the message into `w`, same as `to_string` without allocating a new `String`
+    pub fn write_to(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        write!(w, "{}", self)
+    }
+
+    /// Append the serialized message to `buf`
+    ///
+    /// The required capacity is reserved up front using [`Message::encoded_len`], so `buf`
+    /// grows at most once. Reusing `buf` for multiple messages avoids allocations entirely.
+    pub fn to_bytes_in(&self, buf: &mut Vec<u8>) {
+        buf.reserve(self.encoded_len());
+
+        // Writing into a Vec cannot fail
+        let _ = self.write_to(&mut VecWriter(buf));
+    }
+}
+
+struct LenCounter(usize);
+
+impl fmt::Write for LenCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
     }
+}
+
+struct VecWriter<'a>(&'a mut Vec<u8>);
 
-    builder.finish().map_err(Error::Builder)
+impl fmt::Write for VecWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
 }
 
 impl fmt::Display for Message {
@@ -508,6 +2311,22 @@ s={}\r\n\
             self.origin, self.name
         )?;
 
+        if let Some(info) = &self.info {
+            write!(f, "i={}\r\n", info)?;
+        }
+
+        if let Some(uri) = &self.uri {
+            write!(f, "u={}\r\n", uri)?;
+        }
+
+        for email in &self.emails {
+            write!(f, "e={}\r\n", email)?;
+        }
+
+        for phone in &self.phones {
+            write!(f, "p={}\r\n", phone)?;
+        }
+
         if let Some(conn) = &self.connection {
             write!(f, "{}\r\n", conn)?;
         }
@@ -516,7 +2335,22 @@ s={}\r\n\
             write!(f, "{}\r\n", bw)?;
         }
 
-        write!(f, "{}\r\n{}", self.time, self.ice_options)?;
+        write!(f, "{}\r\n", self.time)?;
+
+        if !self.zone_adjustments.is_empty() {
+            ZoneAdjustment::fmt_list(&self.zone_adjustments, f)?;
+            f.write_str("\r\n")?;
+        }
+
+        if let Some(key) = &self.key {
+            write!(f, "k={}\r\n", key)?;
+        }
+
+        write!(f, "{}", self.ice_options)?;
+
+        if let Some(ice_pacing) = self.ice_pacing {
+            write!(f, "a=ice-pacing:{}\r\n", ice_pacing)?;
+        }
 
         if self.ice_lite {
             f.write_str("a=ice-lite\r\n")?;
@@ -530,6 +2364,34 @@ s={}\r\n\
             write!(f, "{}\r\n", pwd)?;
         }
 
+        for group in &self.groups {
+            write!(f, "{}\r\n", group)?;
+        }
+
+        if let Some(identity) = &self.identity {
+            write!(f, "{}\r\n", identity)?;
+        }
+
+        for acap in &self.acaps {
+            write!(f, "{}\r\n", acap)?;
+        }
+
+        for tcap in &self.tcaps {
+            write!(f, "{}\r\n", tcap)?;
+        }
+
+        for extmap in &self.extmaps {
+            write!(f, "{}\r\n", extmap)?;
+        }
+
+        if let Some(setup) = &self.setup {
+            write!(f, "{}\r\n", setup)?;
+        }
+
+        if let Some(connection) = &self.tcp_connection {
+            write!(f, "{}\r\n", connection)?;
+        }
+
         for attr in &self.attributes {
             write!(f, "{}\r\n", attr)?;
         }
@@ -541,3 +2403,964 @@ s={}\r\n\
         Ok(())
     }
 }
+
+fn frame_rate(i: &str) -> IResult<&str, f32> {
+    preceded(multispace0, float)(i)
+}
+
+fn number<T>(i: &str) -> IResult<&str, T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    preceded(multispace0, map_res(digit1, FromStr::from_str))(i)
+}
+
+/// Split a whitespace separated attribute value
+fn words(src: &Bytes, i: &str) -> Vec<BytesStr> {
+    i.split_whitespace()
+        .map(|word| BytesStr::from_parse(src, word))
+        .collect()
+}
+
+/// Write a whitespace separated list attribute, omitted if empty
+fn write_list(f: &mut fmt::Formatter, name: &str, list: &[BytesStr]) -> fmt::Result {
+    if let Some((first, rest)) = list.split_first() {
+        write!(f, "a={}:{}", name, first)?;
+
+        for item in rest {
+            write!(f, " {}", item)?;
+        }
+
+        f.write_str("\r\n")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attributes::content::ContentValue;
+    use crate::attributes::t38::UdpErrorCorrection;
+
+    #[test]
+    fn rtcp_flags() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=audio 1000 RTP/AVP 0\r\n\
+a=rtcp-mux\r\n\
+a=rtcp-rsize\r\n\
+m=video 2000 RTP/AVP 96\r\n\
+a=rtcp-mux-only\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        let audio = &msg.media_scopes[0];
+        assert!(audio.rtcp_mux && audio.rtcp_rsize && !audio.rtcp_mux_only);
+
+        let video = &msg.media_scopes[1];
+        assert!(!video.rtcp_mux && !video.rtcp_rsize && video.rtcp_mux_only);
+
+        let printed = audio.to_string();
+        assert!(printed.contains("a=rtcp-mux\r\n"));
+        assert!(printed.contains("a=rtcp-rsize\r\n"));
+        assert!(!printed.contains("a=rtcp-mux-only"));
+    }
+
+    #[test]
+    fn rtcp_addr() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+m=audio 1000 RTP/AVP 0\r\n\
+m=audio 2000 RTP/AVP 0\r\n\
+a=rtcp:2005\r\n\
+m=audio 3000 RTP/AVP 0\r\n\
+c=IN IP4 10.0.0.3\r\n\
+a=rtcp:3005 IN IP4 10.0.0.4\r\n\
+m=audio 4000 RTP/AVP 0\r\n\
+a=rtcp:4005\r\n\
+a=rtcp-mux\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        let addrs: Vec<_> = msg
+            .media_scopes
+            .iter()
+            .map(|media| media.rtcp_addr(&msg).unwrap().to_string())
+            .collect();
+
+        assert_eq!(
+            addrs,
+            [
+                "10.0.0.1:1001",
+                "10.0.0.1:2005",
+                "10.0.0.4:3005",
+                "10.0.0.1:4000"
+            ]
+        );
+
+        assert!(msg.media_scopes[2]
+            .to_string()
+            .contains("a=rtcp:3005 IN IP4 10.0.0.4\r\n"));
+    }
+
+    #[test]
+    fn datachannel() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+a=sctp-port:5000\r\n\
+a=max-message-size:262144\r\n\
+m=application 9 DTLS/SCTP 5000\r\n\
+a=sctpmap:5000 webrtc-datachannel 1024\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        let current = &msg.media_scopes[0];
+        assert!(current.desc.is_datachannel());
+        assert_eq!(current.sctp_port, Some(5000));
+        assert_eq!(current.max_message_size, Some(262144));
+
+        let legacy = &msg.media_scopes[1];
+        assert!(!legacy.desc.is_datachannel());
+        assert_eq!(legacy.desc.fmts, [5000]);
+        assert_eq!(legacy.sctpmap.as_ref().unwrap().streams, Some(1024));
+
+        assert!(current
+            .to_string()
+            .contains("a=sctp-port:5000\r\na=max-message-size:262144\r\n"));
+        assert!(legacy
+            .to_string()
+            .contains("a=sctpmap:5000 webrtc-datachannel 1024\r\n"));
+    }
+
+    #[test]
+    fn content_and_label() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+m=video 1000 RTP/AVP 96\r\n\
+a=label:11\r\n\
+a=content:main\r\n\
+m=video 2000 RTP/AVP 96\r\n\
+a=label:12\r\n\
+a=content:slides\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        let presentation = msg
+            .media_scopes
+            .iter()
+            .find(|media| {
+                media
+                    .content
+                    .as_ref()
+                    .is_some_and(|c| c.contains(&ContentValue::Slides))
+            })
+            .unwrap();
+
+        assert_eq!(presentation.label.as_deref(), Some("12"));
+        assert!(presentation
+            .to_string()
+            .contains("a=label:12\r\na=content:slides\r\n"));
+    }
+
+    #[test]
+    fn repeat_times() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+t=3724394400 3724398000\r\n\
+r=7d 1h 0 25h\r\n\
+z=3730922900 -1h 3749680500 0\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        assert_eq!(msg.time.repeats.len(), 1);
+        assert_eq!(msg.time.repeats[0].interval, 604800);
+        assert_eq!(msg.zone_adjustments.len(), 2);
+        assert_eq!(msg.zone_adjustments[0].offset, -3600);
+
+        assert!(msg.to_string().contains(
+            "t=3724394400 3724398000\r\nr=7d 1h 0 25h\r\nz=3730922900 -1h 3749680500 0\r\n"
+        ));
+    }
+
+    #[test]
+    fn descriptive_fields() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=Call\r\n\
+i=A call with info\r\n\
+u=http://example.com/call\r\n\
+e=alice@example.com (Alice)\r\n\
+e=bob@example.com\r\n\
+p=+1 617 555-6011\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+m=audio 1000 RTP/AVP 0\r\n\
+i=Main audio\r\n\
+a=sendrecv\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        assert_eq!(msg.info.as_deref(), Some("A call with info"));
+        assert_eq!(msg.uri.as_deref(), Some("http://example.com/call"));
+        assert_eq!(msg.emails, ["alice@example.com (Alice)", "bob@example.com"]);
+        assert_eq!(msg.phones, ["+1 617 555-6011"]);
+        assert_eq!(msg.media_scopes[0].info.as_deref(), Some("Main audio"));
+
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn capability_negotiation() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+a=tcap:1 RTP/SAVP\r\n\
+m=audio 1000 RTP/AVP 0\r\n\
+a=sendrecv\r\n\
+a=acap:1 crypto:1 AES_CM_128_HMAC_SHA1_80 inline:WVNfX19zZW1jdGwgKCkgewkyMjA7fQp9CnVubGVz\r\n\
+a=pcfg:1 t=1 a=1\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+        let media = &msg.media_scopes[0];
+
+        assert_eq!(msg.tcaps.len(), 1);
+        assert_eq!(media.acaps.len(), 1);
+        assert_eq!(media.pcfgs[0].transports, [1]);
+
+        assert_eq!(
+            media.transport_capability(&msg, 1),
+            Some(&TransportProtocol::RtpSavp)
+        );
+        assert_eq!(media.transport_capability(&msg, 2), None);
+        assert_eq!(
+            media.attribute_capability(&msg, 1).map(|attr| &*attr.name),
+            Some("crypto")
+        );
+
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn key() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+k=clear:secret\r\n\
+m=audio 1000 RTP/AVP 0\r\n\
+b=AS:64\r\n\
+k=prompt\r\n\
+a=sendrecv\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        assert_eq!(msg.key.as_deref(), Some("clear:secret"));
+        assert_eq!(msg.media_scopes[0].key.as_deref(), Some("prompt"));
+
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn parse_options() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+O=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+m=x-custom 1000 TCP *\r\n\
+a=x-attribute:value\r\n\
+M=audio 2000 RTP/AVP 0\r\n\
+a=sendrecv\r\n",
+        );
+
+        assert!(parse::<Builder>(&input).is_err());
+
+        let msg = parse_with::<Builder>(&input, &ParseOptions::tolerant()).unwrap();
+
+        assert_eq!(msg.time.start, 0);
+        assert_eq!(msg.media_scopes.len(), 1);
+        assert_eq!(msg.media_scopes[0].desc.port, 2000);
+        assert!(msg.media_scopes[0].attributes.is_empty());
+
+        let options = ParseOptions {
+            max_line_length: Some(20),
+            ..ParseOptions::tolerant()
+        };
+        assert!(matches!(
+            parse_with::<Builder>(&input, &options),
+            Err(ParseMessageError {
+                error: Error::LineTooLong(20),
+                ..
+            })
+        ));
+
+        let options = ParseOptions {
+            max_attributes: Some(1),
+            ..ParseOptions::default()
+        };
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=tool:x\r\n\
+m=audio 2000 RTP/AVP 0\r\n\
+a=sendrecv\r\n",
+        );
+        assert!(matches!(
+            parse_with::<Builder>(&input, &options),
+            Err(ParseMessageError {
+                error: Error::TooManyAttributes(1),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn write_to_buffer() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+m=audio 1000 RTP/AVP 0 8\r\n\
+a=sendrecv\r\n\
+a=rtpmap:8 PCMA/8000\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        assert_eq!(msg.encoded_len(), input.len());
+
+        let mut buf = b"prefix".to_vec();
+        msg.to_bytes_in(&mut buf);
+        assert_eq!(&buf[6..], input.as_bytes());
+
+        let mut string = String::new();
+        msg.write_to(&mut string).unwrap();
+        assert_eq!(string, *input);
+    }
+
+    #[test]
+    fn partial() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=audio 1000 RTP/AVP 0 8\r\n\
+a=rtpmap:8 PCMA\r\n\
+a=rtcp-mux\r\n\
+c=invalid\r\n",
+        );
+
+        let error = parse::<Builder>(&input).unwrap_err();
+        let line = error.line.as_ref().unwrap();
+        assert_eq!(line.number, 6);
+        assert_eq!(line.content, "a=rtpmap:8 PCMA");
+        assert!(error.to_string().starts_with("line 6 `a=rtpmap:8 PCMA`: "));
+
+        let (msg, errors) = parse_partial::<Builder>(&input, &ParseOptions::default()).unwrap();
+
+        let lines: Vec<usize> = errors
+            .iter()
+            .map(|error| error.line.as_ref().unwrap().number)
+            .collect();
+        assert_eq!(lines, [6, 8]);
+        assert!(msg.media_scopes[0].rtcp_mux);
+        assert!(msg.media_scopes[0].rtpmaps.is_empty());
+
+        let error = parse::<Builder>(&BytesStr::from_static("v=0\r\ns=-\r\n")).unwrap_err();
+        assert!(error.line.is_none());
+    }
+
+    #[test]
+    fn incremental() {
+        let input = "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+m=audio 1000 RTP/AVP 0 8\r\n\
+a=rtcp-mux\r\n\
+a=rtpmap:8 PCMA/8000";
+
+        let mut parser = Parser::<Builder>::new(ParseOptions::default());
+
+        for chunk in input.as_bytes().chunks(7) {
+            parser.feed(chunk).unwrap();
+        }
+
+        let msg = parser.finish().unwrap();
+
+        assert_eq!(msg.media_scopes[0].rtpmaps[0].encoding, "PCMA");
+        assert!(msg.media_scopes[0].rtcp_mux);
+        assert_eq!(
+            msg.to_string(),
+            parse::<Builder>(&BytesStr::from_static(input))
+                .unwrap()
+                .to_string()
+        );
+
+        let mut parser = Parser::<Builder>::new(ParseOptions::default());
+        assert!(matches!(
+            parser.feed(b"s=\xff\r\n"),
+            Err(ParseMessageError {
+                error: Error::InvalidUtf8,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn ptime() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=audio 1000 RTP/AVP 0\r\n\
+a=ptime:20\r\n\
+a=maxptime:40\r\n\
+m=audio 2000 RTP/AVP 0\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        let first = &msg.media_scopes[0];
+        assert_eq!(first.ptime, Some(20));
+        assert_eq!(first.maxptime, Some(40));
+
+        let second = &msg.media_scopes[1];
+        assert_eq!(second.ptime, None);
+        assert_eq!(second.maxptime, None);
+
+        let printed = first.to_string();
+        assert!(printed.contains("a=ptime:20\r\na=maxptime:40\r\n"));
+        assert!(!second.to_string().contains("ptime"));
+    }
+
+    #[test]
+    fn framerate_quality() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 1000 RTP/AVP 34\r\n\
+a=sendrecv\r\n\
+a=framerate:29.97\r\n\
+a=quality:8\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        assert_eq!(msg.media_scopes[0].framerate, Some(29.97));
+        assert_eq!(msg.media_scopes[0].quality, Some(8));
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn imageattr_invalid() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 1000 RTP/AVP 97\r\n\
+a=sendrecv\r\n\
+a=imageattr:97 send [x=800,y=640]\r\n\
+a=imageattr:97 send [x=800]\r\n\
+a=imageattr:97 send [x=800,y=640] garbage\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+        let media = &msg.media_scopes[0];
+
+        assert_eq!(media.imageattrs.len(), 1);
+        assert_eq!(media.attributes.len(), 2);
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn framerate_invalid() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 1000 RTP/AVP 34\r\n\
+a=sendrecv\r\n\
+a=framerate:fast\r\n\
+m=video 2000 RTP/AVP 34\r\n\
+a=sendrecv\r\n\
+a=framerate:30fps\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        for media in &msg.media_scopes {
+            assert!(media.framerate.is_none());
+            assert_eq!(media.attributes[0].name, "framerate");
+        }
+
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn t38() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+m=image 5000 udptl t38\r\n\
+a=sendrecv\r\n\
+a=T38FaxVersion:0\r\n\
+a=T38MaxBitRate:14400\r\n\
+a=T38FaxRateManagement:transferredTCF\r\n\
+a=T38FaxMaxBuffer:262\r\n\
+a=T38FaxMaxDatagram:176\r\n\
+a=T38FaxUdpEC:t38UDPRedundancy\r\n\
+a=T38FaxUdpECDepth:1\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+        let media = &msg.media_scopes[0];
+
+        assert!(media.desc.is_t38());
+        assert_eq!(media.t38.max_datagram, Some(176));
+        assert_eq!(media.t38.udp_ec, Some(UdpErrorCorrection::Redundancy));
+        assert_eq!(media.attributes[0].name, "T38FaxUdpECDepth");
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn bfcp() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+m=application 50000 TCP/TLS/BFCP *\r\n\
+a=sendrecv\r\n\
+a=floorctrl:c-only s-only\r\n\
+a=confid:4321\r\n\
+a=userid:1234\r\n\
+a=floorid:1 mstrm:10\r\n\
+a=floorid:2 mstrm:11\r\n\
+m=video 50002 RTP/AVP 31\r\n\
+a=label:10\r\n\
+a=sendrecv\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+        let bfcp = &msg.media_scopes[0];
+
+        assert!(bfcp.desc.is_bfcp());
+        assert_eq!(
+            bfcp.floorctrl,
+            [FloorControl::ClientOnly, FloorControl::ServerOnly]
+        );
+        assert_eq!(bfcp.confid.as_deref(), Some("4321"));
+        assert_eq!(bfcp.userid.as_deref(), Some("1234"));
+        assert_eq!(bfcp.floorids[1].media_streams, ["11"]);
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn msrp() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+m=message 7394 TCP/MSRP *\r\n\
+a=sendrecv\r\n\
+a=accept-types:message/cpim text/plain\r\n\
+a=accept-wrapped-types:*\r\n\
+a=path:msrp://relay.example.com:2855/9di4ea;tcp msrp://10.0.0.1:7394/jshA7we;tcp\r\n\
+a=max-size:131072\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+        let media = &msg.media_scopes[0];
+
+        assert!(media.desc.is_msrp());
+        assert_eq!(media.accept_types, ["message/cpim", "text/plain"]);
+        assert_eq!(media.accept_wrapped_types, ["*"]);
+        assert_eq!(media.msrp_path.len(), 2);
+        assert_eq!(media.max_size, Some(131072));
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn tcp_setup() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+a=setup:active\r\n\
+m=message 7394 TCP/MSRP *\r\n\
+a=sendrecv\r\n\
+a=setup:passive\r\n\
+a=connection:existing\r\n\
+m=application 5000 TCP/BFCP *\r\n\
+a=sendrecv\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        assert_eq!(msg.setup, Some(Setup::Active));
+        assert_eq!(msg.media_scopes[0].setup, Some(Setup::Passive));
+        assert_eq!(
+            msg.media_scopes[0].tcp_connection,
+            Some(TcpConnection::Existing)
+        );
+        assert_eq!(
+            msg.media_scopes[0].tcp_role(&msg, false),
+            Some(TcpRole::Reuse)
+        );
+        assert_eq!(
+            msg.media_scopes[1].tcp_role(&msg, true),
+            Some(TcpRole::Connect)
+        );
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn ice_pacing() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+a=ice-options:trickle renomination\r\n\
+a=ice-pacing:100\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        assert!(msg.ice_options.supports_renomination());
+        assert_eq!(msg.ice_pacing, Some(100));
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn media_level_ice_options() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+a=sendrecv\r\n\
+a=rtpmap:111 opus/48000/2\r\n\
+a=ice-options:trickle\r\n\
+a=ice-ufrag:abcd\r\n\
+a=ice-pwd:aaaaaaaaaaaaaaaaaaaaaa\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        assert!(msg.ice_options.options.is_empty());
+        assert!(msg.media_scopes[0].ice_options.supports_trickle());
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn extmap() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+a=extmap:1 urn:ietf:params:rtp-hdrext:sdes:mid\r\n\
+m=audio 1000 RTP/SAVP 0\r\n\
+a=sendrecv\r\n\
+a=extmap:2/recvonly urn:ietf:params:rtp-hdrext:encrypt urn:ietf:params:rtp-hdrext:ssrc-audio-level\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+        let media = &msg.media_scopes[0];
+
+        assert_eq!(msg.extmaps.len(), 1);
+        assert!(media.extmaps[0].encrypted);
+        assert_eq!(media.all_extmaps(&msg).count(), 2);
+        assert_eq!(msg.to_string(), *input);
+    }
+
+    #[test]
+    fn telephone_event() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+c=IN IP4 10.0.0.1\r\n\
+t=0 0\r\n\
+m=audio 1000 RTP/AVP 111 0 100 101\r\n\
+a=rtpmap:111 opus/48000/2\r\n\
+a=rtpmap:100 telephone-event/48000\r\n\
+a=rtpmap:101 telephone-event/8000\r\n\
+a=fmtp:101 0-16\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+        let media = &msg.media_scopes[0];
+
+        assert_eq!(media.telephone_event(8000), Some(101));
+        assert_eq!(media.telephone_event_for(111), Some(100));
+        assert_eq!(media.telephone_event_for(0), Some(101));
+        assert_eq!(media.telephone_event(16000), None);
+
+        assert!(media.telephone_events(101).contains(16));
+        assert!(!media.telephone_events(100).contains(16));
+    }
+
+    #[test]
+    fn ptime_invalid() {
+        for ptime in ["abc", "20.5", "20xyz"] {
+            let input = BytesStr::from(format!(
+                "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=audio 1000 RTP/AVP 0\r\n\
+a=ptime:{ptime}\r\n"
+            ));
+
+            let msg = parse::<Builder>(&input).unwrap();
+            let media = &msg.media_scopes[0];
+
+            assert!(media.ptime.is_none());
+            assert_eq!(media.attributes[0].to_string(), format!("a=ptime:{ptime}"));
+        }
+    }
+
+    #[test]
+    fn session_level_media_attributes() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=rtpmap:0 PCMU/8000\r\n\
+a=ptime:20\r\n\
+a=rtcp-mux\r\n\
+a=accept-types:text/plain message/cpim\r\n\
+a=T38FaxVersion:0\r\n\
+m=audio 1000 RTP/AVP 0\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        let attributes: Vec<_> = msg
+            .attributes
+            .iter()
+            .map(|attr| (attr.name.as_str(), attr.value.as_deref()))
+            .collect();
+
+        assert_eq!(
+            attributes,
+            [
+                ("rtpmap", Some("0 PCMU/8000")),
+                ("ptime", Some("20")),
+                ("rtcp-mux", None),
+                ("accept-types", Some("text/plain message/cpim")),
+                ("T38FaxVersion", Some("0"))
+            ]
+        );
+
+        let audio = &msg.media_scopes[0];
+        assert!(audio.rtpmaps.is_empty() && audio.ptime.is_none() && !audio.rtcp_mux);
+
+        let printed = msg.to_string();
+        assert!(printed.contains("t=0 0\r\na=rtpmap:0 PCMU/8000\r\na=ptime:20\r\na=rtcp-mux\r\n"));
+    }
+
+    #[test]
+    fn default_builder_methods() {
+        /// Builder which only implements the required methods
+        #[derive(Default)]
+        struct Attributes(Vec<String>);
+
+        impl ParseBuilder for Attributes {
+            type Message = Vec<String>;
+            type Error = anyhow::Error;
+
+            fn finish(self) -> Result<Self::Message, Self::Error> {
+                Ok(self.0)
+            }
+
+            fn set_name(&mut self, _: BytesStr) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn set_origin(&mut self, _: Origin) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn set_time(&mut self, _: Time) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn set_direction(&mut self, _: Direction) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn set_connection(&mut self, _: Connection) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn add_bandwidth(&mut self, _: Bandwidth) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn begin_media(&mut self, _: MediaDescription) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn add_rtpmap(&mut self, _: RtpMap) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn add_fmtp(&mut self, _: Fmtp) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn add_rtcp(&mut self, _: RtcpAttr) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn set_ice_lite(&mut self, _: bool) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn set_ice_options(&mut self, _: ice::Options) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn set_ice_ufrag(&mut self, _: ice::UsernameFragment) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn set_ice_pwd(&mut self, _: ice::Password) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn add_ice_candidate(&mut self, _: Candidate) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn set_ice_end_of_candidates(&mut self, _: bool) -> Result<(), Self::Error> {
+                Ok(())
+            }
+
+            fn add_unknown_attr(&mut self, attr: UnknownAttribute) -> Result<(), Self::Error> {
+                self.0.push(attr.to_string());
+                Ok(())
+            }
+        }
+
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+i=ignored\r\n\
+t=0 0\r\n\
+a=group:BUNDLE 0\r\n\
+m=audio 1000 RTP/AVP 0\r\n\
+a=mid:0\r\n\
+a=ptime:20\r\n\
+a=rtcp-mux\r\n\
+a=setup:actpass\r\n\
+a=ssrc:1 cname:a\r\n\
+a=x-custom:1\r\n",
+        );
+
+        assert_eq!(
+            parse::<Attributes>(&input).unwrap(),
+            [
+                "a=group:BUNDLE 0",
+                "a=mid:0",
+                "a=ptime:20",
+                "a=rtcp-mux",
+                "a=setup:actpass",
+                "a=ssrc:1 cname:a",
+                "a=x-custom:1"
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_values_kept() {
+        let input = BytesStr::from_static(
+            "v=0\r\n\
+o=- 1 1 IN IP4 10.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=video 1000 RTP/AVP 96\r\n\
+a=quality:high\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+a=sctp-port:70000\r\n\
+a=max-message-size:1k\r\n\
+m=message 7394 TCP/MSRP *\r\n\
+a=max-size:-1\r\n\
+a=connection:reused\r\n",
+        );
+
+        let msg = parse::<Builder>(&input).unwrap();
+
+        let unknown: Vec<Vec<_>> = msg
+            .media_scopes
+            .iter()
+            .map(|media| {
+                media
+                    .attributes
+                    .iter()
+                    .map(|attr| attr.to_string())
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(
+            unknown,
+            [
+                vec!["a=quality:high"],
+                vec!["a=sctp-port:70000", "a=max-message-size:1k"],
+                vec!["a=max-size:-1", "a=connection:reused"],
+            ]
+        );
+
+        assert!(msg.media_scopes[0].quality.is_none());
+        assert!(msg.media_scopes[1].sctp_port.is_none());
+        assert!(msg.media_scopes[2].tcp_connection.is_none());
+    }
+}
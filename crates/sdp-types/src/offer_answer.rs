@@ -0,0 +1,754 @@
+//! Offer/answer negotiation ([RFC3264](https://www.rfc-editor.org/rfc/rfc3264.html))
+//!
+//! [`OfferAnswer`] creates offers and answers from the local capabilities and tracks the state of
+//! the negotiation, including re-offers which must keep the order of existing media descriptions.
+
+use crate::attributes::direction::Direction;
+use crate::attributes::fmtp::Fmtp;
+use crate::attributes::rtpmap::RtpMap;
+use crate::media::{MediaDescription, MediaType, TransportProtocol};
+use crate::msg::{MediaScope, Message};
+use crate::origin::Origin;
+use bytesstr::BytesStr;
+use std::net::IpAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Codec supported by the local endpoint
+#[derive(Debug, Clone)]
+pub struct Codec {
+    /// Payload type used in local offers, answers use the payload type of the offer
+    pub payload: u32,
+
+    /// Name of the encoding e.g. `opus`
+    pub encoding: BytesStr,
+
+    pub clock_rate: u32,
+
+    /// Number of audio channels, `None` for a single channel
+    pub channels: Option<u32>,
+
+    /// Format parameters the local endpoint wants to receive with
+    pub fmtp: Option<BytesStr>,
+}
+
+impl Codec {
+    /// Returns if the codec matches the format of the media description
+    ///
+    /// Encoding name, clock rate and channels must be equal, static payload types without rtpmap
+    /// use their well known mapping. For codecs where format parameters describe entirely
+    /// different bitstreams these have to match as well:
+    ///
+    /// - H.264: `packetization-mode` and the profile of `profile-level-id`
+    /// - VP9: `profile-id`
+    pub fn matches(&self, media: &MediaScope, fmt: u32) -> bool {
+        let Some(rtpmap) = media.rtpmap(fmt) else {
+            return false;
+        };
+
+        if !rtpmap.encoding.eq_ignore_ascii_case(&self.encoding)
+            || rtpmap.clock_rate != self.clock_rate
+            || rtpmap.channels().unwrap_or(1) != self.channels.unwrap_or(1)
+        {
+            return false;
+        }
+
+        let local = Fmtp {
+            format: self.payload,
+            params: self.fmtp.clone().unwrap_or_default(),
+        };
+
+        let remote = media
+            .fmtps
+            .iter()
+            .find(|fmtp| fmtp.format == fmt)
+            .cloned()
+            .unwrap_or(Fmtp {
+                format: fmt,
+                params: BytesStr::default(),
+            });
+
+        if self.encoding.eq_ignore_ascii_case("H264") {
+            let (local, remote) = (local.h264(), remote.h264());
+
+            local.packetization_mode == remote.packetization_mode
+                && local.profile_level_id.profile_idc == remote.profile_level_id.profile_idc
+        } else if self.encoding.eq_ignore_ascii_case("VP9") {
+            local.vp9().profile_id == remote.vp9().profile_id
+        } else {
+            true
+        }
+    }
+
+    fn rtpmap(&self, payload: u32) -> RtpMap {
+        RtpMap {
+            payload,
+            encoding: self.encoding.clone(),
+            clock_rate: self.clock_rate,
+            params: self.channels.map(|channels| channels.to_string().into()),
+        }
+    }
+}
+
+/// Codec supported by both sides, returned by [`intersect_codecs`]
+#[derive(Debug, Clone, Copy)]
+pub struct CodecMatch<'a> {
+    /// Payload type used by the remote media description, which must also be used in the answer
+    pub payload: u32,
+
+    pub local: &'a Codec,
+}
+
+/// Intersect the local codecs with the formats of a remote media description
+///
+/// The result is ordered by the preference of the remote side. Every format is matched with the
+/// first local codec it [matches](Codec::matches).
+pub fn intersect_codecs<'a>(local: &'a [Codec], remote: &MediaScope) -> Vec<CodecMatch<'a>> {
+    remote
+        .desc
+        .fmts
+        .iter()
+        .filter_map(|&payload| {
+            let local = local.iter().find(|codec| codec.matches(remote, payload))?;
+
+            Some(CodecMatch { payload, local })
+        })
+        .collect()
+}
+
+/// Media the local endpoint is able to handle
+#[derive(Debug, Clone)]
+pub struct LocalMedia {
+    pub media_type: MediaType,
+    pub proto: TransportProtocol,
+
+    /// Supported codecs, ordered by preference
+    pub codecs: Vec<Codec>,
+
+    /// Directions the local endpoint supports
+    pub direction: Direction,
+
+    /// Port media is received on
+    pub port: u16,
+}
+
+/// Capabilities used to create offers and answers
+#[derive(Debug, Clone)]
+pub struct LocalCapabilities {
+    /// Address written into the origin and connection fields
+    pub address: IpAddr,
+
+    /// Media sections, a local offer contains one media description per entry
+    pub media: Vec<LocalMedia>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NegotiationState {
+    /// No offer is outstanding
+    Stable,
+
+    /// A local offer has been created and awaits an answer
+    HaveLocalOffer,
+
+    /// A remote offer has been received and awaits an answer
+    HaveRemoteOffer,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OfferAnswerError {
+    #[error("operation is invalid in state {0:?}")]
+    InvalidState(NegotiationState),
+    #[error("expected {expected} media descriptions, got {got}")]
+    MediaCount { expected: usize, got: usize },
+    #[error("media description {0} changed its media type")]
+    MediaTypeChanged(usize),
+}
+
+/// Offer/answer state machine
+#[derive(Debug)]
+pub struct OfferAnswer {
+    capabilities: LocalCapabilities,
+    state: NegotiationState,
+
+    session_id: u64,
+    session_version: u64,
+
+    /// Outstanding offer, local or remote depending on the state
+    pending: Option<Message>,
+
+    /// Descriptions of the last completed negotiation
+    local: Option<Message>,
+    remote: Option<Message>,
+}
+
+impl OfferAnswer {
+    pub fn new(capabilities: LocalCapabilities) -> Self {
+        let session_id = new_session_id();
+
+        Self {
+            capabilities,
+            state: NegotiationState::Stable,
+            session_id,
+            session_version: session_id,
+            pending: None,
+            local: None,
+            remote: None,
+        }
+    }
+
+    pub fn state(&self) -> NegotiationState {
+        self.state
+    }
+
+    /// Local description of the last completed negotiation
+    pub fn local_description(&self) -> Option<&Message> {
+        self.local.as_ref()
+    }
+
+    /// Remote description of the last completed negotiation
+    pub fn remote_description(&self) -> Option<&Message> {
+        self.remote.as_ref()
+    }
+
+    /// Update the capabilities used for the next offer or answer
+    pub fn set_capabilities(&mut self, capabilities: LocalCapabilities) {
+        self.capabilities = capabilities;
+    }
+
+    /// Create an offer containing all local media
+    ///
+    /// In re-offers the media descriptions of the current session keep their position, media
+    /// which is no longer supported is offered with port 0.
+    pub fn create_offer(&mut self) -> Result<Message, OfferAnswerError> {
+        if self.state != NegotiationState::Stable {
+            return Err(OfferAnswerError::InvalidState(self.state));
+        }
+
+        let mut used = vec![false; self.capabilities.media.len()];
+        let mut media_scopes = vec![];
+
+        for existing in self.local.iter().flat_map(|local| &local.media_scopes) {
+            let scope = match self.find_local_media(&mut used, &existing.desc) {
+                Some(local) => offer_media(local),
+                None => existing.rejected(),
+            };
+
+            media_scopes.push(MediaScope {
+                mid: existing.mid.clone(),
+                ..scope
+            });
+        }
+
+        for (local, _) in self
+            .capabilities
+            .media
+            .iter()
+            .zip(used)
+            .filter(|(_, used)| !used)
+        {
+            media_scopes.push(offer_media(local));
+        }
+
+        let offer = self.message(media_scopes);
+
+        self.pending = Some(offer.clone());
+        self.state = NegotiationState::HaveLocalOffer;
+
+        Ok(offer)
+    }
+
+    /// Apply the answer to the outstanding local offer
+    pub fn receive_answer(&mut self, answer: Message) -> Result<(), OfferAnswerError> {
+        if self.state != NegotiationState::HaveLocalOffer {
+            return Err(OfferAnswerError::InvalidState(self.state));
+        }
+
+        let offer = self.pending.as_ref().expect("state has a pending offer");
+
+        compare_media(&offer.media_scopes, &answer.media_scopes, true)?;
+
+        self.local = self.pending.take();
+        self.remote = Some(answer);
+        self.state = NegotiationState::Stable;
+
+        Ok(())
+    }
+
+    /// Receive an offer, which is answered using [`create_answer`](Self::create_answer)
+    ///
+    /// A re-offer must contain all media descriptions of the current session in their original
+    /// position, their media types may change.
+    pub fn receive_offer(&mut self, offer: Message) -> Result<(), OfferAnswerError> {
+        if self.state != NegotiationState::Stable {
+            return Err(OfferAnswerError::InvalidState(self.state));
+        }
+
+        if let Some(local) = &self.local {
+            compare_media(&local.media_scopes, &offer.media_scopes, false)?;
+        }
+
+        self.pending = Some(offer);
+        self.state = NegotiationState::HaveRemoteOffer;
+
+        Ok(())
+    }
+
+    /// Create the answer to the received offer
+    ///
+    /// The answer contains a media description for every offered one. Media without local
+    /// support or common codecs is rejected with port 0. Accepted media uses the offered payload
+    /// types of all supported codecs and the offered direction from the local point of view,
+    /// restricted by the local capabilities.
+    pub fn create_answer(&mut self) -> Result<Message, OfferAnswerError> {
+        if self.state != NegotiationState::HaveRemoteOffer {
+            return Err(OfferAnswerError::InvalidState(self.state));
+        }
+
+        let offer = self.pending.take().expect("state has a pending offer");
+
+        let mut used = vec![false; self.capabilities.media.len()];
+
+        let media_scopes = offer
+            .media_scopes
+            .iter()
+            .map(|offered| {
+                let local = if offered.desc.port == 0 {
+                    None
+                } else {
+                    self.find_local_media(&mut used, &offered.desc)
+                };
+
+                let scope = local
+                    .and_then(|local| answer_media(local, offered))
+                    .unwrap_or_else(|| offered.rejected());
+
+                MediaScope {
+                    mid: offered.mid.clone(),
+                    ..scope
+                }
+            })
+            .collect();
+
+        let answer = self.message(media_scopes);
+
+        self.local = Some(answer.clone());
+        self.remote = Some(offer);
+        self.state = NegotiationState::Stable;
+
+        Ok(answer)
+    }
+
+    /// Discard the outstanding offer, returning to the last completed negotiation
+    pub fn rollback(&mut self) {
+        self.pending = None;
+        self.state = NegotiationState::Stable;
+    }
+
+    fn find_local_media(&self, used: &mut [bool], desc: &MediaDescription) -> Option<&LocalMedia> {
+        let (local, used) = self
+            .capabilities
+            .media
+            .iter()
+            .zip(used)
+            .find(|(local, used)| {
+                !**used && local.media_type == desc.media_type && local.proto == desc.proto
+            })?;
+
+        *used = true;
+
+        Some(local)
+    }
+
+    fn message(&mut self, media_scopes: Vec<MediaScope>) -> Message {
+        self.session_version += 1;
+
+        let origin = Origin {
+            username: "-".into(),
+            session_id: self.session_id.to_string().into(),
+            session_version: self.session_version.to_string().into(),
+            address: self.capabilities.address.into(),
+        };
+
+        Message::new(origin, media_scopes)
+    }
+}
+
+/// Session id derived from the current time, also used as initial session version
+pub(crate) fn new_session_id() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn offer_media(local: &LocalMedia) -> MediaScope {
+    let mut scope = MediaScope::new(MediaDescription {
+        media_type: local.media_type,
+        port: local.port,
+        ports_num: None,
+        proto: local.proto.clone(),
+        fmts: local.codecs.iter().map(|codec| codec.payload).collect(),
+        named_fmts: vec![],
+    });
+
+    scope.direction = local.direction;
+
+    for codec in &local.codecs {
+        add_codec(&mut scope, codec, codec.payload);
+    }
+
+    scope
+}
+
+/// Returns `None` if no offered codec is supported
+fn answer_media(local: &LocalMedia, offered: &MediaScope) -> Option<MediaScope> {
+    let mut scope = MediaScope::new(MediaDescription {
+        media_type: local.media_type,
+        port: local.port,
+        ports_num: None,
+        proto: local.proto.clone(),
+        fmts: vec![],
+        named_fmts: vec![],
+    });
+
+    for codec in intersect_codecs(&local.codecs, offered) {
+        scope.desc.fmts.push(codec.payload);
+        add_codec(&mut scope, codec.local, codec.payload);
+    }
+
+    if scope.desc.fmts.is_empty() {
+        return None;
+    }
+
+    let offered_direction = offered.direction.flipped();
+
+    scope.direction = Direction::from_flags(
+        offered_direction.sends() && local.direction.sends(),
+        offered_direction.receives() && local.direction.receives(),
+    );
+    scope.rtcp_mux = offered.rtcp_mux;
+
+    Some(scope)
+}
+
+pub(crate) fn add_codec(scope: &mut MediaScope, codec: &Codec, payload: u32) {
+    scope.rtpmaps.push(codec.rtpmap(payload));
+
+    if let Some(params) = &codec.fmtp {
+        scope.fmtps.push(Fmtp {
+            format: payload,
+            params: params.clone(),
+        });
+    }
+}
+
+impl MediaScope {
+    /// Returns the media description to reject this one with
+    ///
+    /// The rejected media description has port 0, only the first format and keeps the mid, all
+    /// other attributes are removed.
+    pub fn rejected(&self) -> MediaScope {
+        let mut scope = MediaScope::new(MediaDescription {
+            port: 0,
+            ports_num: None,
+            fmts: self.desc.fmts.first().copied().into_iter().collect(),
+            named_fmts: if self.desc.fmts.is_empty() {
+                self.desc.named_fmts.first().cloned().into_iter().collect()
+            } else {
+                vec![]
+            },
+            ..self.desc.clone()
+        });
+
+        scope.mid.clone_from(&self.mid);
+        scope.direction = Direction::Inactive;
+        scope
+    }
+}
+
+impl Message {
+    /// Reject the media description at `index`, keeping its position
+    ///
+    /// The media description is replaced by its [rejected](MediaScope::rejected) form and its mid
+    /// is removed from all BUNDLE groups, groups left empty are removed. Rejecting the bundle tag
+    /// doesn't move the transport to another member, which is up to the caller.
+    ///
+    /// Returns `false` if there is no media description at `index`.
+    pub fn reject_media(&mut self, index: usize) -> bool {
+        let Some(media) = self.media_scopes.get_mut(index) else {
+            return false;
+        };
+
+        *media = media.rejected();
+
+        if let Some(mid) = &media.mid {
+            for group in self.groups.iter_mut().filter(|group| group.is_bundle()) {
+                group.mids.retain(|m| m != mid);
+            }
+
+            self.groups
+                .retain(|group| !(group.is_bundle() && group.mids.is_empty()));
+        }
+
+        true
+    }
+}
+
+/// Check that `new` contains the media descriptions of `old` in the same position
+///
+/// If `exact` is set (an answer to the offer `old`) both must contain the same number of them,
+/// with the same media types. A re-offer may change the media type of a stream or reuse the
+/// position of a rejected one for another media type (RFC 3264 Section 8).
+fn compare_media(
+    old: &[MediaScope],
+    new: &[MediaScope],
+    exact: bool,
+) -> Result<(), OfferAnswerError> {
+    if new.len() < old.len() || (exact && new.len() != old.len()) {
+        return Err(OfferAnswerError::MediaCount {
+            expected: old.len(),
+            got: new.len(),
+        });
+    }
+
+    if !exact {
+        return Ok(());
+    }
+
+    if let Some(index) = old
+        .iter()
+        .zip(new)
+        .position(|(old, new)| old.desc.media_type != new.desc.media_type)
+    {
+        return Err(OfferAnswerError::MediaTypeChanged(index));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::msg::{parse, Builder};
+
+    fn capabilities() -> LocalCapabilities {
+        LocalCapabilities {
+            address: "10.0.0.1".parse().unwrap(),
+            media: vec![LocalMedia {
+                media_type: MediaType::Audio,
+                proto: TransportProtocol::RtpAvp,
+                codecs: vec![
+                    Codec {
+                        payload: 111,
+                        encoding: "opus".into(),
+                        clock_rate: 48000,
+                        channels: Some(2),
+                        fmtp: Some("useinbandfec=1".into()),
+                    },
+                    Codec {
+                        payload: 0,
+                        encoding: "PCMU".into(),
+                        clock_rate: 8000,
+                        channels: None,
+                        fmtp: None,
+                    },
+                ],
+                direction: Direction::SendRecv,
+                port: 1000,
+            }],
+        }
+    }
+
+    fn sdp(sdp: &'static str) -> Message {
+        parse::<Builder>(&BytesStr::from_static(sdp)).unwrap()
+    }
+
+    const OFFER: &str = "v=0\r
+o=- 1 1 IN IP4 10.0.0.2\r
+s=-\r
+c=IN IP4 10.0.0.2\r
+t=0 0\r
+m=video 2000 RTP/AVP 96\r
+a=rtpmap:96 VP8/90000\r
+m=audio 3000 RTP/AVP 0 100\r
+a=mid:audio\r
+a=sendonly\r
+a=rtpmap:100 opus/48000/2\r
+";
+
+    #[test]
+    fn answer() {
+        let mut negotiation = OfferAnswer::new(capabilities());
+
+        negotiation.receive_offer(sdp(OFFER)).unwrap();
+        assert_eq!(negotiation.state(), NegotiationState::HaveRemoteOffer);
+
+        let answer = negotiation.create_answer().unwrap();
+        assert_eq!(negotiation.state(), NegotiationState::Stable);
+
+        assert_eq!(answer.media_scopes.len(), 2);
+
+        let video = &answer.media_scopes[0];
+        assert_eq!(video.desc.media_type, MediaType::Video);
+        assert_eq!(video.desc.port, 0);
+        assert_eq!(video.desc.fmts, [96]);
+
+        let audio = &answer.media_scopes[1];
+        assert_eq!(audio.desc.port, 1000);
+        assert_eq!(audio.desc.fmts, [0, 100]);
+        assert_eq!(audio.direction, Direction::RecvOnly);
+        assert_eq!(audio.mid.as_deref(), Some("audio"));
+        assert_eq!(audio.fmtps[0].format, 100);
+
+        assert!(answer.to_string().contains("a=rtpmap:100 opus/48000/2\r\n"));
+    }
+
+    #[test]
+    fn reject_media() {
+        let mut answer = sdp("v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+a=group:BUNDLE a v\r
+a=group:LS a v\r
+m=audio 1000 RTP/AVP 0 8\r
+a=mid:a\r
+m=video 1000 RTP/AVP 96 97\r
+a=mid:v\r
+a=rtcp-mux\r
+a=rtpmap:96 VP8/90000\r
+a=rtpmap:97 VP9/90000\r
+");
+
+        assert!(answer.reject_media(1));
+        assert!(!answer.reject_media(2));
+
+        let video = &answer.media_scopes[1];
+        assert_eq!(video.desc.port, 0);
+        assert_eq!(video.desc.fmts, [96]);
+        assert_eq!(video.mid.as_deref(), Some("v"));
+        assert!(!video.rtcp_mux && video.rtpmaps.is_empty());
+
+        assert_eq!(answer.groups[0].mids, ["a"]);
+        assert_eq!(answer.groups[1].mids, ["a", "v"]);
+
+        assert!(answer.reject_media(0));
+        assert_eq!(answer.groups.len(), 1);
+        assert!(!answer.groups[0].is_bundle());
+    }
+
+    #[test]
+    fn codec_intersection() {
+        let h264 = |payload, fmtp: &'static str| Codec {
+            payload,
+            encoding: "H264".into(),
+            clock_rate: 90000,
+            channels: None,
+            fmtp: Some(fmtp.into()),
+        };
+
+        let local = [
+            h264(96, "profile-level-id=42e01f;packetization-mode=1"),
+            h264(97, "profile-level-id=42e01f"),
+        ];
+
+        let remote = sdp("v=0\r
+o=- 1 1 IN IP4 10.0.0.2\r
+s=-\r
+c=IN IP4 10.0.0.2\r
+t=0 0\r
+m=video 2000 RTP/AVP 100 101 102 34\r
+a=rtpmap:100 H264/90000\r
+a=fmtp:100 profile-level-id=640c1f;packetization-mode=1\r
+a=rtpmap:101 h264/90000\r
+a=fmtp:101 profile-level-id=42e00a\r
+a=rtpmap:102 H264/90000\r
+a=fmtp:102 packetization-mode=1\r
+");
+
+        let matches = intersect_codecs(&local, &remote.media_scopes[0]);
+        let matches: Vec<(u32, u32)> = matches
+            .iter()
+            .map(|codec| (codec.payload, codec.local.payload))
+            .collect();
+
+        assert_eq!(matches, [(101, 97), (102, 96)]);
+
+        let pcmu = capabilities().media[0].codecs[1].clone();
+        assert!(!pcmu.matches(&remote.media_scopes[0], 34));
+    }
+
+    #[test]
+    fn offer_and_reoffer() {
+        let mut negotiation = OfferAnswer::new(capabilities());
+
+        let offer = negotiation.create_offer().unwrap();
+        assert_eq!(offer.media_scopes[0].desc.fmts, [111, 0]);
+        assert!(matches!(
+            negotiation.create_offer(),
+            Err(OfferAnswerError::InvalidState(
+                NegotiationState::HaveLocalOffer
+            ))
+        ));
+
+        assert!(matches!(
+            negotiation.receive_answer(sdp(OFFER)),
+            Err(OfferAnswerError::MediaCount { .. })
+        ));
+
+        // The answer must keep the media types of the offer
+        assert!(matches!(
+            negotiation.receive_answer(sdp("v=0\r
+o=- 1 1 IN IP4 10.0.0.2\r
+s=-\r
+c=IN IP4 10.0.0.2\r
+t=0 0\r
+m=video 3000 RTP/AVP 96\r
+a=rtpmap:96 VP8/90000\r
+")),
+            Err(OfferAnswerError::MediaTypeChanged(0))
+        ));
+
+        negotiation
+            .receive_answer(sdp("v=0\r
+o=- 1 1 IN IP4 10.0.0.2\r
+s=-\r
+c=IN IP4 10.0.0.2\r
+t=0 0\r
+m=audio 3000 RTP/AVP 111\r
+a=rtpmap:111 opus/48000/2\r
+"))
+            .unwrap();
+        assert_eq!(negotiation.state(), NegotiationState::Stable);
+
+        // A re-offer may change the media type of a stream
+        negotiation.receive_offer(sdp(OFFER)).unwrap();
+        assert_eq!(negotiation.state(), NegotiationState::HaveRemoteOffer);
+        negotiation.rollback();
+
+        // But must keep all media descriptions
+        assert!(matches!(
+            negotiation.receive_offer(sdp("v=0\r
+o=- 1 2 IN IP4 10.0.0.2\r
+s=-\r
+t=0 0\r
+")),
+            Err(OfferAnswerError::MediaCount { .. })
+        ));
+
+        let mut capabilities = capabilities();
+        capabilities.media[0].media_type = MediaType::Video;
+        negotiation.set_capabilities(capabilities);
+
+        // The existing audio media keeps its position but is rejected
+        let reoffer = negotiation.create_offer().unwrap();
+        assert_eq!(reoffer.media_scopes.len(), 2);
+        assert_eq!(reoffer.media_scopes[0].desc.media_type, MediaType::Audio);
+        assert_eq!(reoffer.media_scopes[0].desc.port, 0);
+        assert_eq!(reoffer.media_scopes[1].desc.media_type, MediaType::Video);
+        assert_ne!(offer.origin.session_version, reoffer.origin.session_version);
+
+        negotiation.rollback();
+        assert_eq!(negotiation.state(), NegotiationState::Stable);
+    }
+}
@@ -0,0 +1,324 @@
+//! Conversion between Unified Plan and Plan B
+//!
+//! Unified Plan ([RFC8829](https://www.rfc-editor.org/rfc/rfc8829.html)) uses one media
+//! description per track, which is identified by `a=msid`. Legacy Plan B endpoints use a single
+//! media description per media type and identify tracks by their sources, using the `msid`
+//! (or `mslabel` and `label`) source attribute, e.g. `a=ssrc:1234 msid:stream track`.
+
+use crate::attributes::direction::Direction;
+use crate::attributes::msid::Msid;
+use crate::attributes::ssrc::Ssrc;
+use crate::media::MediaType;
+use crate::msg::{MediaScope, Message};
+use bytesstr::BytesStr;
+
+impl Message {
+    /// Fold all active audio and video media descriptions into the first one of their type
+    ///
+    /// `a=msid` attributes are converted into `msid` source attributes and the mids of the
+    /// removed media descriptions are removed from all groups.
+    pub fn to_plan_b(&self) -> Message {
+        let mut message = self.clone();
+        let mut media_scopes: Vec<MediaScope> = vec![];
+        let mut removed_mids = vec![];
+
+        for mut media in message.media_scopes.drain(..) {
+            msid_to_ssrc_attributes(&mut media);
+
+            let base = media_scopes
+                .iter_mut()
+                .find(|base| is_foldable(base) && base.desc.media_type == media.desc.media_type);
+
+            match base {
+                Some(base) if is_foldable(&media) => {
+                    base.ssrcs.append(&mut media.ssrcs);
+                    base.ssrc_groups.append(&mut media.ssrc_groups);
+                    base.direction = Direction::from_flags(
+                        base.direction.sends() || media.direction.sends(),
+                        base.direction.receives() || media.direction.receives(),
+                    );
+
+                    removed_mids.extend(media.mid);
+                }
+                _ => media_scopes.push(media),
+            }
+        }
+
+        for group in &mut message.groups {
+            group.mids.retain(|mid| !removed_mids.contains(mid));
+        }
+
+        message.media_scopes = media_scopes;
+        message
+    }
+
+    /// Split media descriptions which contain multiple tracks into one media description per
+    /// track
+    ///
+    /// Sources belong to the same track if they share the `msid` source attribute or an
+    /// `a=ssrc-group` (e.g. for retransmission). The first track keeps the mid of the original
+    /// media description, all others get new mids which are added to the groups of the original.
+    pub fn to_unified_plan(&self) -> Message {
+        let mut message = self.clone();
+        let mut media_scopes = vec![];
+
+        let mut used_mids: Vec<BytesStr> = self
+            .media_scopes
+            .iter()
+            .filter_map(|media| media.mid.clone())
+            .collect();
+
+        for media in message.media_scopes.drain(..) {
+            let tracks = tracks(&media);
+
+            let mut new_mids = vec![];
+
+            for (i, track) in tracks.iter().enumerate() {
+                let mut split = media.clone();
+
+                split.ssrcs.retain(|ssrc| track.contains(&ssrc.ssrc));
+                split
+                    .ssrc_groups
+                    .retain(|group| group.ssrcs.iter().all(|ssrc| track.contains(ssrc)));
+
+                if split.msids.is_empty() {
+                    split
+                        .msids
+                        .extend(ssrc_msid(&split, track[0]).map(parse_msid));
+                }
+
+                if i > 0 {
+                    let mid = unused_mid(&used_mids);
+                    used_mids.push(mid.clone());
+                    new_mids.push(mid.clone());
+                    split.mid = Some(mid);
+                }
+
+                media_scopes.push(split);
+            }
+
+            if tracks.is_empty() {
+                media_scopes.push(media);
+            } else if let Some(mid) = &media.mid {
+                for group in &mut message.groups {
+                    if let Some(pos) = group.mids.iter().position(|m| m == mid) {
+                        group
+                            .mids
+                            .splice(pos + 1..pos + 1, new_mids.iter().cloned());
+                    }
+                }
+            }
+        }
+
+        message.media_scopes = media_scopes;
+        message
+    }
+}
+
+fn is_foldable(media: &MediaScope) -> bool {
+    matches!(media.desc.media_type, MediaType::Audio | MediaType::Video) && media.desc.port != 0
+}
+
+/// Add an `msid` source attribute to every source of the media which has none
+fn msid_to_ssrc_attributes(media: &mut MediaScope) {
+    let msids = std::mem::take(&mut media.msids);
+
+    let mut ssrcs: Vec<u32> = vec![];
+
+    for ssrc in &media.ssrcs {
+        if !ssrcs.contains(&ssrc.ssrc) {
+            ssrcs.push(ssrc.ssrc);
+        }
+    }
+
+    for ssrc in ssrcs {
+        let has_msid = media
+            .ssrcs
+            .iter()
+            .any(|attr| attr.ssrc == ssrc && attr.attribute == "msid");
+
+        if has_msid {
+            continue;
+        }
+
+        for msid in &msids {
+            media.ssrcs.push(Ssrc::from_msid(ssrc, msid));
+        }
+    }
+}
+
+/// Group the sources of the media description into tracks
+fn tracks(media: &MediaScope) -> Vec<Vec<u32>> {
+    let mut tracks: Vec<(Option<BytesStr>, Vec<u32>)> = vec![];
+
+    for ssrc in &media.ssrcs {
+        let ssrc = ssrc.ssrc;
+
+        if tracks.iter().any(|(_, ssrcs)| ssrcs.contains(&ssrc)) {
+            continue;
+        }
+
+        let msid = ssrc_msid(media, ssrc);
+
+        let grouped: Vec<u32> = media
+            .ssrc_groups
+            .iter()
+            .filter(|group| group.ssrcs.contains(&ssrc))
+            .flat_map(|group| group.ssrcs.iter().copied())
+            .collect();
+
+        let track = tracks.iter_mut().find(|(track_msid, ssrcs)| {
+            (msid.is_some() && *track_msid == msid) || grouped.iter().any(|s| ssrcs.contains(s))
+        });
+
+        match track {
+            Some((_, ssrcs)) => ssrcs.push(ssrc),
+            None => tracks.push((msid, vec![ssrc])),
+        }
+    }
+
+    tracks.into_iter().map(|(_, ssrcs)| ssrcs).collect()
+}
+
+/// Returns the value of the `msid` source attribute, or the `mslabel` and `label` attributes
+/// used by older implementations
+fn ssrc_msid(media: &MediaScope, ssrc: u32) -> Option<BytesStr> {
+    let attribute = |name: &str| {
+        media
+            .ssrcs
+            .iter()
+            .find(|attr| attr.ssrc == ssrc && attr.attribute == name)
+            .and_then(|attr| attr.value.clone())
+    };
+
+    attribute("msid").or_else(|| {
+        let stream = attribute("mslabel")?;
+
+        match attribute("label") {
+            Some(track) => Some(format!("{} {}", stream, track).into()),
+            None => Some(stream),
+        }
+    })
+}
+
+fn parse_msid(value: BytesStr) -> Msid {
+    match value.split_once(' ') {
+        Some((stream_id, track_id)) => Msid {
+            stream_id: value.slice_ref(stream_id),
+            track_id: Some(value.slice_ref(track_id.trim())),
+        },
+        None => Msid {
+            stream_id: value,
+            track_id: None,
+        },
+    }
+}
+
+fn unused_mid(used: &[BytesStr]) -> BytesStr {
+    (0..)
+        .map(|i: u32| i.to_string())
+        .find(|mid| !used.iter().any(|used| used == mid.as_str()))
+        .expect("ran out of mids")
+        .into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::msg::{parse, Builder};
+
+    const UNIFIED: &str = "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+a=group:BUNDLE 0 1 2\r
+m=audio 9 UDP/TLS/RTP/SAVPF 111\r
+a=mid:0\r
+a=sendrecv\r
+a=rtpmap:111 opus/48000/2\r
+a=msid:stream audio\r
+a=ssrc:1 cname:abc\r
+m=video 9 UDP/TLS/RTP/SAVPF 96 97\r
+a=mid:1\r
+a=sendonly\r
+a=rtpmap:96 VP8/90000\r
+a=rtpmap:97 rtx/90000\r
+a=msid:stream camera\r
+a=ssrc-group:FID 2 3\r
+a=ssrc:2 cname:abc\r
+a=ssrc:3 cname:abc\r
+m=video 9 UDP/TLS/RTP/SAVPF 96 97\r
+a=mid:2\r
+a=recvonly\r
+a=rtpmap:96 VP8/90000\r
+a=rtpmap:97 rtx/90000\r
+a=msid:stream screen\r
+a=ssrc:4 cname:abc\r
+";
+
+    #[test]
+    fn plan_b() {
+        let unified = parse::<Builder>(&BytesStr::from_static(UNIFIED)).unwrap();
+        let plan_b = unified.to_plan_b();
+
+        assert_eq!(plan_b.media_scopes.len(), 2);
+        assert_eq!(plan_b.groups[0].mids, ["0", "1"]);
+
+        let video = &plan_b.media_scopes[1];
+        assert_eq!(video.direction, Direction::SendRecv);
+        assert!(video.msids.is_empty());
+        assert_eq!(video.ssrc_groups.len(), 1);
+
+        let printed = video.to_string();
+        assert!(printed.contains("a=ssrc:2 msid:stream camera\r\na=ssrc:3 msid:stream camera\r\n"));
+        assert!(printed.contains("a=ssrc:4 msid:stream screen\r\n"));
+
+        let unified = plan_b.to_unified_plan();
+
+        assert_eq!(unified.media_scopes.len(), 3);
+        assert_eq!(unified.groups[0].mids, ["0", "1", "2"]);
+
+        let camera = &unified.media_scopes[1];
+        assert_eq!(camera.mid.as_deref(), Some("1"));
+        assert_eq!(camera.msids[0].track_id.as_deref(), Some("camera"));
+        assert_eq!(camera.ssrc_groups[0].ssrcs, [2, 3]);
+
+        let screen = &unified.media_scopes[2];
+        assert_eq!(screen.mid.as_deref(), Some("2"));
+        assert_eq!(screen.msids[0].track_id.as_deref(), Some("screen"));
+        assert!(screen.ssrcs.iter().all(|ssrc| ssrc.ssrc == 4));
+    }
+
+    #[test]
+    fn unified_plan_labels() {
+        let plan_b = parse::<Builder>(&BytesStr::from_static(
+            "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+m=audio 1000 RTP/AVP 0\r
+a=mid:audio\r
+a=ssrc:1 mslabel:stream\r
+a=ssrc:1 label:first\r
+a=ssrc:2 mslabel:stream\r
+a=ssrc:2 label:second\r
+m=video 1002 RTP/AVP 96\r
+a=mid:video\r
+a=rtpmap:96 VP8/90000\r
+",
+        ))
+        .unwrap();
+
+        let unified = plan_b.to_unified_plan();
+
+        assert_eq!(unified.media_scopes.len(), 3);
+        assert_eq!(unified.media_scopes[1].mid.as_deref(), Some("0"));
+        assert_eq!(
+            unified.media_scopes[1].msids[0].to_string(),
+            "a=msid:stream second"
+        );
+        assert!(unified.media_scopes[2].ssrcs.is_empty());
+    }
+}
@@ -0,0 +1,313 @@
+//! Round-trip preserving serialization
+//!
+//! [`Message`]'s `Display` implementation writes fields and attributes in a fixed order and drops
+//! lines it doesn't understand. A [`PreservedMessage`] remembers the text it was parsed from and
+//! writes the original text of the session part and every media description which wasn't
+//! changed, so messages are forwarded byte-for-byte if nothing was modified. Lines of unknown
+//! types (e.g. `y=`) are kept when a section is rewritten.
+
+use crate::msg::{parse, Builder, Message, ParseMessageError};
+use bytesstr::BytesStr;
+use std::fmt;
+
+/// Message which remembers the text it was parsed from
+#[derive(Debug, Clone)]
+pub struct PreservedMessage {
+    /// The parsed message, changes are written in the canonical format of [`Message`]
+    pub message: Message,
+
+    session: Section,
+    media: Vec<Section>,
+}
+
+/// Original text of a section and how it was rendered right after parsing
+#[derive(Debug, Clone)]
+struct Section {
+    original: BytesStr,
+    rendered: String,
+    unknown: Vec<UnknownLine>,
+}
+
+/// Line which the parser ignores and [`Message`] can't render
+#[derive(Debug, Clone)]
+struct UnknownLine {
+    /// The known line preceding it in the original text
+    after: Option<BytesStr>,
+    line: BytesStr,
+}
+
+impl Section {
+    fn new(original: BytesStr, rendered: String) -> Self {
+        let mut unknown = vec![];
+        let mut after = None;
+
+        for line in original.split(['\r', '\n']).filter(|line| !line.is_empty()) {
+            let line = original.slice_ref(line);
+
+            if is_known(&line) {
+                after = Some(line);
+            } else {
+                unknown.push(UnknownLine {
+                    after: after.clone(),
+                    line,
+                });
+            }
+        }
+
+        Self {
+            original,
+            rendered,
+            unknown,
+        }
+    }
+
+    /// Write the section in its canonical format, re-inserting the unknown lines after the
+    /// known lines they followed in the original text
+    fn write_modified(&self, f: &mut fmt::Formatter, rendered: &str) -> fmt::Result {
+        let mut written = vec![false; self.unknown.len()];
+
+        for line in rendered.split_inclusive('\n') {
+            f.write_str(line)?;
+
+            let line = line.trim_end();
+
+            for (unknown, written) in self.unknown.iter().zip(&mut written) {
+                if !*written && unknown.after.as_deref() == Some(line) {
+                    write!(f, "{}\r\n", unknown.line)?;
+                    *written = true;
+                }
+            }
+        }
+
+        // The preceding lines were removed or changed
+        for (unknown, written) in self.unknown.iter().zip(written) {
+            if !written {
+                write!(f, "{}\r\n", unknown.line)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns if the line has a type the parser handles (e.g. `a=`)
+fn is_known(line: &str) -> bool {
+    match line.as_bytes() {
+        [field, b'=', ..] => b"vosiuepcbtrzkma".contains(field),
+        _ => false,
+    }
+}
+
+impl PreservedMessage {
+    pub fn parse(src: &BytesStr) -> Result<Self, ParseMessageError<anyhow::Error>> {
+        let message = parse::<Builder>(src)?;
+
+        let mut originals = split_sections(src).into_iter();
+        let session_original = originals.next().unwrap_or_default();
+
+        let session = Section::new(session_original, render_session(&message));
+
+        let media = message
+            .media_scopes
+            .iter()
+            .zip(originals)
+            .map(|(media, original)| Section::new(original, media.to_string()))
+            .collect();
+
+        Ok(Self {
+            message,
+            session,
+            media,
+        })
+    }
+
+    /// Returns if the message was changed since it was parsed
+    pub fn is_modified(&self) -> bool {
+        self.session.rendered != render_session(&self.message)
+            || self.media.len() != self.message.media_scopes.len()
+            || self
+                .media
+                .iter()
+                .zip(&self.message.media_scopes)
+                .any(|(section, media)| section.rendered != media.to_string())
+    }
+
+    pub fn into_message(self) -> Message {
+        self.message
+    }
+}
+
+impl From<PreservedMessage> for Message {
+    fn from(preserved: PreservedMessage) -> Self {
+        preserved.message
+    }
+}
+
+impl fmt::Display for PreservedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let session = render_session(&self.message);
+
+        if session == self.session.rendered {
+            f.write_str(&self.session.original)?;
+        } else {
+            self.session.write_modified(f, &session)?;
+        }
+
+        // Media descriptions are matched by position, so removing or inserting a media
+        // description causes all following ones to be written in the canonical format
+        for (index, media) in self.message.media_scopes.iter().enumerate() {
+            let rendered = media.to_string();
+
+            match self.media.get(index) {
+                Some(section) if section.rendered == rendered => f.write_str(&section.original)?,
+                Some(section) => section.write_modified(f, &rendered)?,
+                None => f.write_str(&rendered)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Render only the session part of the message, without media descriptions
+fn render_session(message: &Message) -> String {
+    let mut rendered = message.to_string();
+
+    let media_len: usize = message
+        .media_scopes
+        .iter()
+        .map(|media| media.to_string().len())
+        .sum();
+
+    rendered.truncate(rendered.len() - media_len);
+    rendered
+}
+
+/// Split the source into the session part and one part per media description, including
+/// their original line endings
+fn split_sections(src: &BytesStr) -> Vec<BytesStr> {
+    let mut sections = vec![];
+    let mut start = 0;
+
+    let line_starts = std::iter::once(0).chain(src.match_indices(['\r', '\n']).map(|(i, _)| i + 1));
+
+    for line_start in line_starts {
+        if line_start > start && src[line_start..].starts_with("m=") {
+            sections.push(src.slice_ref(&src[start..line_start]));
+            start = line_start;
+        }
+    }
+
+    sections.push(src.slice_ref(&src[start..]));
+    sections
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attributes::direction::Direction;
+
+    const SDP: &str = "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+t=0 0\r
+c=IN IP4 10.0.0.1\r
+a=x-unknown:foo\r
+m=audio 1000 RTP/AVP 0 8\r
+a=rtpmap:8 PCMA/8000\r
+a=sendrecv\r
+a=rtpmap:0 PCMU/8000\r
+m=video 2000 RTP/AVP 96\n\
+a=rtpmap:96 VP8/90000\n\
+y=unknown line\n";
+
+    #[test]
+    fn unmodified() {
+        let preserved = PreservedMessage::parse(&BytesStr::from_static(SDP)).unwrap();
+
+        assert!(!preserved.is_modified());
+        assert_eq!(preserved.to_string(), SDP);
+        assert_ne!(preserved.message.to_string(), SDP);
+    }
+
+    #[test]
+    fn modified_media() {
+        let mut preserved = PreservedMessage::parse(&BytesStr::from_static(SDP)).unwrap();
+
+        preserved.message.media_scopes[1].direction = Direction::Inactive;
+        assert!(preserved.is_modified());
+
+        let printed = preserved.to_string();
+        let (unchanged, changed) = printed.split_at(printed.find("m=video").unwrap());
+
+        assert_eq!(unchanged, &SDP[..SDP.find("m=video").unwrap()]);
+        assert_eq!(
+            changed,
+            "m=video 2000 RTP/AVP 96\r\na=inactive\r\na=rtpmap:96 VP8/90000\r\ny=unknown line\r\n"
+        );
+    }
+
+    #[test]
+    fn modified_session() {
+        let mut preserved = PreservedMessage::parse(&BytesStr::from_static(SDP)).unwrap();
+
+        preserved.message.name = "Talk".into();
+
+        let printed = preserved.to_string();
+        assert!(printed.starts_with("v=0\r\no=- 1 1 IN IP4 10.0.0.1\r\ns=Talk\r\n"));
+        assert!(printed.ends_with(&SDP[SDP.find("m=audio").unwrap()..]));
+    }
+
+    #[test]
+    fn media_level_ice_options() {
+        let sdp = "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+t=0 0\r
+m=audio 1000 RTP/AVP 0\r
+a=ice-options:trickle\r
+";
+        let mut preserved = PreservedMessage::parse(&BytesStr::from_static(sdp)).unwrap();
+
+        preserved.message.name = "Talk".into();
+
+        let printed = preserved.to_string();
+        assert_eq!(printed.matches("a=ice-options:trickle").count(), 1);
+        assert!(printed.ends_with("m=audio 1000 RTP/AVP 0\r\na=ice-options:trickle\r\n"));
+    }
+
+    #[test]
+    fn unknown_lines_kept() {
+        let sdp = "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+y=after name\r
+t=0 0\r
+m=audio 1000 RTP/AVP 0\r
+y=after media\r
+a=ptime:20\r
+y=after ptime\r
+a=rtpmap:0 PCMU/8000\r
+";
+        let mut preserved = PreservedMessage::parse(&BytesStr::from_static(sdp)).unwrap();
+
+        preserved.message.name = "Talk".into();
+        preserved.message.media_scopes[0].ptime = None;
+        preserved.message.media_scopes[0].direction = Direction::SendOnly;
+
+        assert_eq!(
+            preserved.to_string(),
+            "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=Talk\r
+t=0 0\r
+y=after name\r
+m=audio 1000 RTP/AVP 0\r
+y=after media\r
+a=sendonly\r
+a=rtpmap:0 PCMU/8000\r
+y=after ptime\r
+"
+        );
+    }
+}
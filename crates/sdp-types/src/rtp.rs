@@ -0,0 +1,481 @@
+//! Structured view of the RTP parameters of a media description
+//!
+//! Modeled after ORTC's `RTCRtpCapabilities` and `RTCRtpParameters`
+//! ([ORTC](https://draft.ortc.org/)), so media engines don't have to collect
+//! rtpmap, fmtp, rtcp-fb and extmap attributes themselves.
+
+use crate::attributes::extmap::ExtMap;
+use crate::attributes::fmtp::Fmtp;
+use crate::attributes::rtcp_fb::RtcpFeedbackKind;
+use crate::attributes::ssrc::Ssrc;
+use crate::msg::{MediaScope, Message};
+use bytesstr::BytesStr;
+use std::time::Duration;
+
+/// Codec of a media description, combining its rtpmap, fmtp and rtcp-fb attributes
+#[derive(Debug, Clone)]
+pub struct RtpCodec {
+    pub payload: u32,
+    pub encoding: BytesStr,
+    pub clock_rate: u32,
+
+    /// Number of audio channels, `None` for a single channel
+    pub channels: Option<u32>,
+
+    pub fmtp: Option<Fmtp>,
+
+    /// RTCP feedback of the payload type, including the one for all formats (`*`)
+    pub rtcp_fb: Vec<RtcpFeedbackKind>,
+}
+
+impl RtpCodec {
+    /// Returns the value of the format parameter
+    pub fn parameter(&self, key: &str) -> Option<&str> {
+        self.fmtp.as_ref()?.get(key)
+    }
+
+    /// Returns the packetization a sender should use given the `ptime` and `maxptime` of the
+    /// receiver in milliseconds
+    ///
+    /// The longest duration supported by the codec not exceeding `ptime` and `maxptime` is
+    /// chosen, or the shortest one if none fits. Without `ptime` 20ms (30ms for iLBC) is used.
+    ///
+    /// - G.711, G.722 and G.729: multiples of 10ms
+    /// - GSM: multiples of 20ms
+    /// - iLBC: multiples of the frame size (`mode`, 20 or 30ms)
+    /// - Opus: 2.5, 5, 10, 20, 40 or 60ms frames, multiple frames up to 120ms, respecting `minptime`
+    ///
+    /// Other codecs use whole milliseconds.
+    pub fn packetization(&self, ptime: Option<u32>, maxptime: Option<u32>) -> Packetization {
+        let (durations, default) = self.packet_durations();
+
+        let max = maxptime.map_or(u32::MAX, |maxptime| maxptime.saturating_mul(1000));
+        let desired = ptime.map_or(default, |ptime| ptime.saturating_mul(1000));
+        let desired = desired.min(max);
+
+        let duration = durations
+            .iter()
+            .copied()
+            .filter(|&duration| duration <= desired)
+            .max()
+            .or_else(|| durations.iter().copied().min())
+            .unwrap_or(default);
+
+        Packetization {
+            duration: Duration::from_micros(duration.into()),
+            samples: (u64::from(self.clock_rate) * u64::from(duration) / 1_000_000) as u32,
+        }
+    }
+
+    /// Returns the packet durations the codec supports and the default one in microseconds
+    fn packet_durations(&self) -> (Vec<u32>, u32) {
+        let multiples = |frame: u32| (1..=MAX_PACKET_DURATION / frame).map(move |n| n * frame);
+
+        let encoding = self.encoding.to_ascii_lowercase();
+
+        match encoding.as_str() {
+            "pcmu" | "pcma" | "g722" | "g729" => (multiples(10_000).collect(), 20_000),
+            "gsm" => (multiples(20_000).collect(), 20_000),
+            "ilbc" => {
+                let frame = match self.parameter("mode") {
+                    Some("20") => 20_000,
+                    _ => 30_000,
+                };
+
+                (multiples(frame).collect(), frame)
+            }
+            "opus" => {
+                let min = self
+                    .parameter("minptime")
+                    .and_then(|minptime| minptime.parse::<u32>().ok())
+                    .map_or(0, |minptime| minptime.saturating_mul(1000));
+
+                let durations = OPUS_PACKET_DURATIONS
+                    .into_iter()
+                    .filter(|&duration| duration >= min)
+                    .collect();
+
+                (durations, 20_000)
+            }
+            _ => (multiples(1_000).collect(), 20_000),
+        }
+    }
+}
+
+/// Longest packet duration considered in microseconds, RTP audio packets are rarely any longer
+const MAX_PACKET_DURATION: u32 = 200_000;
+
+/// Opus packet durations in microseconds, a packet contains one or more frames of up to 60ms
+/// ([RFC6716](https://www.rfc-editor.org/rfc/rfc6716.html#section-3.2.5))
+const OPUS_PACKET_DURATIONS: [u32; 9] = [
+    2_500, 5_000, 10_000, 20_000, 40_000, 60_000, 80_000, 100_000, 120_000,
+];
+
+/// Duration of media a sender puts into a single packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packetization {
+    pub duration: Duration,
+
+    /// Number of samples per packet in units of the RTP clock rate, which is the RTP timestamp
+    /// increment between packets
+    pub samples: u32,
+}
+
+/// Codecs and header extensions a peer supports
+#[derive(Debug, Clone)]
+pub struct RtpCapabilities {
+    /// Codecs in order of preference
+    pub codecs: Vec<RtpCodec>,
+
+    /// Header extensions of the media and the session
+    pub header_extensions: Vec<ExtMap>,
+}
+
+/// RTCP related parameters
+#[derive(Debug, Clone, Default)]
+pub struct RtcpParameters {
+    /// Canonical name, taken from the first `cname` source attribute
+    pub cname: Option<BytesStr>,
+
+    /// RTP and RTCP share a port (`a=rtcp-mux`)
+    pub mux: bool,
+
+    /// Reduced size RTCP (`a=rtcp-rsize`)
+    pub reduced_size: bool,
+}
+
+/// Parameters of an RTP media description
+#[derive(Debug, Clone)]
+pub struct RtpParameters {
+    pub mid: Option<BytesStr>,
+
+    /// Codecs in order of preference
+    pub codecs: Vec<RtpCodec>,
+
+    /// Header extensions of the media and the session
+    pub header_extensions: Vec<ExtMap>,
+
+    /// Sources announced using `a=ssrc`, in order of appearance
+    pub ssrcs: Vec<u32>,
+
+    pub rtcp: RtcpParameters,
+}
+
+/// Retransmission and forward error correction relationships of a media description
+///
+/// Collected from the `apt` format parameter of `rtx` payload types
+/// ([RFC4588](https://www.rfc-editor.org/rfc/rfc4588.html#section-8.6)), the `red`, `ulpfec` and
+/// `flexfec` payload types and the `FID` and `FEC-FR` source groups
+/// ([RFC5576](https://www.rfc-editor.org/rfc/rfc5576.html#section-4.2)).
+#[derive(Debug, Clone, Default)]
+pub struct PayloadAssociations {
+    /// Retransmission payload types and their associated primary payload type
+    pub rtx: Vec<(u32, u32)>,
+
+    /// Payload types for redundant audio data (`red`)
+    pub red: Vec<u32>,
+
+    /// Payload types for FEC (`ulpfec`, `flexfec`)
+    pub fec: Vec<u32>,
+
+    /// Primary sources and their retransmission source (`FID`)
+    pub rtx_ssrcs: Vec<(u32, u32)>,
+
+    /// Primary sources and their FEC source (`FEC-FR`)
+    pub fec_ssrcs: Vec<(u32, u32)>,
+}
+
+impl PayloadAssociations {
+    /// Returns the retransmission payload type of the primary payload type
+    pub fn rtx_for(&self, payload: u32) -> Option<u32> {
+        self.rtx
+            .iter()
+            .find(|(_, apt)| *apt == payload)
+            .map(|(rtx, _)| *rtx)
+    }
+
+    /// Returns the primary payload type of the retransmission payload type
+    pub fn primary_of(&self, rtx: u32) -> Option<u32> {
+        self.rtx
+            .iter()
+            .find(|(payload, _)| *payload == rtx)
+            .map(|(_, apt)| *apt)
+    }
+
+    /// Returns the retransmission source of the primary source
+    pub fn rtx_ssrc_for(&self, ssrc: u32) -> Option<u32> {
+        self.rtx_ssrcs
+            .iter()
+            .find(|(primary, _)| *primary == ssrc)
+            .map(|(_, rtx)| *rtx)
+    }
+
+    /// Returns the FEC source of the primary source
+    pub fn fec_ssrc_for(&self, ssrc: u32) -> Option<u32> {
+        self.fec_ssrcs
+            .iter()
+            .find(|(primary, _)| *primary == ssrc)
+            .map(|(_, fec)| *fec)
+    }
+}
+
+impl MediaScope {
+    /// Returns the retransmission and FEC relationships of the payload types and sources
+    ///
+    /// `rtx` payload types without a valid `apt` or which reference a payload type not offered are
+    /// left out.
+    pub fn payload_associations(&self) -> PayloadAssociations {
+        let mut associations = PayloadAssociations::default();
+
+        for codec in self.rtp_codecs() {
+            let encoding = codec.encoding.to_ascii_lowercase();
+
+            match encoding.as_str() {
+                "rtx" => {
+                    let apt = codec
+                        .parameter("apt")
+                        .and_then(|apt| apt.parse::<u32>().ok())
+                        .filter(|apt| self.desc.fmts.contains(apt));
+
+                    if let Some(apt) = apt {
+                        associations.rtx.push((codec.payload, apt));
+                    }
+                }
+                "red" => associations.red.push(codec.payload),
+                "ulpfec" | "flexfec" | "flexfec-03" => associations.fec.push(codec.payload),
+                _ => {}
+            }
+        }
+
+        for group in &self.ssrc_groups {
+            let [primary, secondary] = group.ssrcs[..] else {
+                continue;
+            };
+
+            if group.semantics.eq_ignore_ascii_case("FID") {
+                associations.rtx_ssrcs.push((primary, secondary));
+            } else if group.semantics.eq_ignore_ascii_case("FEC-FR") {
+                associations.fec_ssrcs.push((primary, secondary));
+            }
+        }
+
+        associations
+    }
+
+    /// Returns the codecs of the media description in order of preference
+    ///
+    /// Static payload types without rtpmap are included, unknown dynamic payload types are not.
+    pub fn rtp_codecs(&self) -> Vec<RtpCodec> {
+        self.desc
+            .fmts
+            .iter()
+            .filter_map(|&payload| {
+                let rtpmap = self.rtpmap(payload)?;
+
+                Some(RtpCodec {
+                    payload,
+                    channels: rtpmap.channels(),
+                    encoding: rtpmap.encoding,
+                    clock_rate: rtpmap.clock_rate,
+                    fmtp: self
+                        .fmtps
+                        .iter()
+                        .find(|fmtp| fmtp.format == payload)
+                        .cloned(),
+                    rtcp_fb: self
+                        .rtcp_fb
+                        .iter()
+                        .filter(|rtcp_fb| rtcp_fb.applies_to(payload))
+                        .map(|rtcp_fb| rtcp_fb.kind.clone())
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the packetization to use when sending the payload type to the peer which sent
+    /// this media description, see [`RtpCodec::packetization`]
+    pub fn packetization(&self, payload: u32) -> Option<Packetization> {
+        let codec = self
+            .rtp_codecs()
+            .into_iter()
+            .find(|codec| codec.payload == payload)?;
+
+        Some(codec.packetization(self.ptime, self.maxptime))
+    }
+
+    pub fn rtp_capabilities(&self, session: &Message) -> RtpCapabilities {
+        RtpCapabilities {
+            codecs: self.rtp_codecs(),
+            header_extensions: self.all_extmaps(session).cloned().collect(),
+        }
+    }
+
+    pub fn rtp_parameters(&self, session: &Message) -> RtpParameters {
+        let mut ssrcs = vec![];
+
+        for ssrc in &self.ssrcs {
+            if !ssrcs.contains(&ssrc.ssrc) {
+                ssrcs.push(ssrc.ssrc);
+            }
+        }
+
+        let cname = self.ssrcs.iter().find_map(Ssrc::cname).cloned();
+
+        RtpParameters {
+            mid: self.mid.clone(),
+            codecs: self.rtp_codecs(),
+            header_extensions: self.all_extmaps(session).cloned().collect(),
+            ssrcs,
+            rtcp: RtcpParameters {
+                cname,
+                mux: self.rtcp_mux,
+                reduced_size: self.rtcp_rsize,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::attributes::rtcp_fb::RtcpFeedbackKind;
+    use crate::msg::{parse, Builder};
+    use bytesstr::BytesStr;
+    use std::time::Duration;
+
+    #[test]
+    fn rtp_parameters() {
+        let msg = parse::<Builder>(&BytesStr::from_static(
+            "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+a=extmap:1 urn:ietf:params:rtp-hdrext:sdes:mid\r
+m=video 1000 RTP/AVPF 96 34 97 98\r
+a=mid:video\r
+a=rtcp-mux\r
+a=rtcp-rsize\r
+a=rtpmap:96 H264/90000\r
+a=fmtp:96 packetization-mode=1\r
+a=rtpmap:98 VP8/90000\r
+a=rtcp-fb:* nack\r
+a=rtcp-fb:96 nack pli\r
+a=extmap:2 urn:ietf:params:rtp-hdrext:toffset\r
+a=ssrc:1 cname:abc\r
+a=ssrc:1 msid:stream track\r
+a=ssrc:2 cname:abc\r
+",
+        ))
+        .unwrap();
+
+        let media = &msg.media_scopes[0];
+        let params = media.rtp_parameters(&msg);
+
+        assert_eq!(params.mid.as_deref(), Some("video"));
+
+        let payloads: Vec<u32> = params.codecs.iter().map(|codec| codec.payload).collect();
+        assert_eq!(payloads, [96, 34, 98]);
+
+        let h264 = &params.codecs[0];
+        assert_eq!(h264.parameter("packetization-mode"), Some("1"));
+        assert_eq!(
+            h264.rtcp_fb,
+            [RtcpFeedbackKind::Nack, RtcpFeedbackKind::NackPli]
+        );
+        assert_eq!(params.codecs[1].encoding, "H263");
+        assert_eq!(params.codecs[2].rtcp_fb, [RtcpFeedbackKind::Nack]);
+
+        assert_eq!(params.header_extensions.len(), 2);
+        assert_eq!(params.ssrcs, [1, 2]);
+        assert_eq!(params.rtcp.cname.as_deref(), Some("abc"));
+        assert!(params.rtcp.mux && params.rtcp.reduced_size);
+
+        assert_eq!(media.rtp_capabilities(&msg).codecs.len(), 3);
+    }
+
+    #[test]
+    fn payload_associations() {
+        let msg = parse::<Builder>(&BytesStr::from_static(
+            "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+m=video 1000 RTP/AVPF 96 97 98 99 100 101\r
+a=rtpmap:96 VP8/90000\r
+a=rtpmap:97 rtx/90000\r
+a=fmtp:97 apt=96\r
+a=rtpmap:98 red/90000\r
+a=rtpmap:99 ulpfec/90000\r
+a=rtpmap:100 rtx/90000\r
+a=fmtp:100 apt=98\r
+a=rtpmap:101 rtx/90000\r
+a=fmtp:101 apt=120\r
+a=ssrc-group:FID 1 2\r
+a=ssrc-group:FEC-FR 1 3\r
+a=ssrc-group:SIM 1 4 5\r
+",
+        ))
+        .unwrap();
+
+        let associations = msg.media_scopes[0].payload_associations();
+
+        assert_eq!(associations.rtx, [(97, 96), (100, 98)]);
+        assert_eq!(associations.rtx_for(96), Some(97));
+        assert_eq!(associations.primary_of(100), Some(98));
+        assert_eq!(associations.primary_of(101), None);
+        assert_eq!(associations.red, [98]);
+        assert_eq!(associations.fec, [99]);
+        assert_eq!(associations.rtx_ssrc_for(1), Some(2));
+        assert_eq!(associations.fec_ssrc_for(1), Some(3));
+        assert_eq!(associations.rtx_ssrc_for(4), None);
+    }
+
+    #[test]
+    fn packetization() {
+        let msg = parse::<Builder>(&BytesStr::from_static(
+            "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+m=audio 1000 RTP/AVP 0 111 112\r
+a=rtpmap:111 opus/48000/2\r
+a=fmtp:111 minptime=10\r
+a=rtpmap:112 iLBC/8000\r
+a=fmtp:112 mode=20\r
+a=ptime:25\r
+a=maxptime:60\r
+",
+        ))
+        .unwrap();
+
+        let media = &msg.media_scopes[0];
+
+        let pcmu = media.packetization(0).unwrap();
+        assert_eq!(pcmu.duration, Duration::from_millis(20));
+        assert_eq!(pcmu.samples, 160);
+
+        let opus = media.packetization(111).unwrap();
+        assert_eq!(opus.duration, Duration::from_millis(20));
+        assert_eq!(opus.samples, 960);
+
+        let ilbc = media.packetization(112).unwrap();
+        assert_eq!(ilbc.duration, Duration::from_millis(20));
+
+        let opus = &media.rtp_codecs()[1];
+        assert_eq!(
+            opus.packetization(Some(2), None).duration,
+            Duration::from_millis(10)
+        );
+
+        let pcmu = &media.rtp_codecs()[0];
+        assert_eq!(
+            pcmu.packetization(Some(300), Some(90)).duration,
+            Duration::from_millis(90)
+        );
+
+        assert!(media.packetization(8).is_none());
+    }
+}
@@ -0,0 +1,351 @@
+//! Trickle ICE SDP fragments ([RFC8840](https://www.rfc-editor.org/rfc/rfc8840.html#section-9))
+//!
+//! Fragments are the body of `application/trickle-ice-sdpfrag` messages (e.g. SIP INFO) and
+//! carry ICE credentials and newly gathered candidates. Candidates are scoped by the media
+//! description they belong to, which is identified by its `a=mid`.
+
+use crate::attributes::candidate::Candidate;
+use crate::attributes::group::Group;
+use crate::attributes::ice::{Options, Password, UsernameFragment};
+use crate::media::MediaDescription;
+use crate::msg::{MediaScope, Message};
+use bytesstr::BytesStr;
+use internal::{Finish, ParseError};
+use std::fmt;
+
+/// Content type of SDP fragments
+pub const CONTENT_TYPE: &str = "application/trickle-ice-sdpfrag";
+
+#[derive(Debug, thiserror::Error)]
+pub enum FragmentError {
+    #[error(transparent)]
+    ParseError(#[from] ParseError),
+    #[error("fragment is incomplete")]
+    Incomplete,
+    #[error("candidate outside of a media description")]
+    CandidateWithoutMedia,
+    #[error("fragment for unknown media description with mid {0:?}")]
+    UnknownMedia(Option<BytesStr>),
+}
+
+/// Body of a trickle ICE INFO request
+#[derive(Debug, Clone, Default)]
+pub struct SdpFragment {
+    /// ICE options, omitted if empty
+    pub ice_options: Options,
+
+    /// ICE username fragment, applies to all media descriptions which don't specify their own
+    pub ice_ufrag: Option<UsernameFragment>,
+
+    /// ICE password, applies to all media descriptions which don't specify their own
+    pub ice_pwd: Option<Password>,
+
+    /// Media groups, e.g. BUNDLE groups
+    pub groups: Vec<Group>,
+
+    /// All candidates of all media descriptions have been sent
+    pub end_of_candidates: bool,
+
+    pub media: Vec<MediaFragment>,
+}
+
+/// Candidates of a single media description
+#[derive(Debug, Clone)]
+pub struct MediaFragment {
+    /// Media description line, copied from the session description with port 9
+    pub desc: MediaDescription,
+
+    /// Identification tag of the media description the candidates belong to
+    pub mid: Option<BytesStr>,
+
+    pub ice_ufrag: Option<UsernameFragment>,
+    pub ice_pwd: Option<Password>,
+
+    pub candidates: Vec<Candidate>,
+
+    /// All candidates of the media description have been sent
+    pub end_of_candidates: bool,
+}
+
+impl MediaFragment {
+    /// Create an empty fragment for the given media scope
+    pub fn new(media: &MediaScope) -> Self {
+        Self {
+            desc: MediaDescription {
+                port: 9,
+                ports_num: None,
+                ..media.desc.clone()
+            },
+            mid: media.mid.clone(),
+            ice_ufrag: None,
+            ice_pwd: None,
+            candidates: vec![],
+            end_of_candidates: false,
+        }
+    }
+}
+
+impl SdpFragment {
+    pub fn parse(src: &BytesStr) -> Result<Self, FragmentError> {
+        let lines = src.split(['\n', '\r']).filter(|line| !line.is_empty());
+
+        let mut fragment = SdpFragment::default();
+
+        for complete_line in lines {
+            let line = complete_line.get(2..).ok_or(FragmentError::Incomplete)?;
+
+            match complete_line.as_bytes() {
+                [b'm', b'=', ..] => {
+                    let (_, desc) = MediaDescription::parse(src.as_ref(), line).finish()?;
+
+                    fragment.media.push(MediaFragment {
+                        desc,
+                        mid: None,
+                        ice_ufrag: None,
+                        ice_pwd: None,
+                        candidates: vec![],
+                        end_of_candidates: false,
+                    });
+                }
+                [b'a', b'=', ..] => {
+                    let (attr, attr_v) = line.split_once(':').unwrap_or((line, ""));
+                    let media = fragment.media.last_mut();
+
+                    match attr {
+                        "ice-options" => {
+                            let (_, options) = Options::parse(src.as_ref(), attr_v).finish()?;
+                            fragment.ice_options = options;
+                        }
+                        "ice-ufrag" => {
+                            let (_, ufrag) =
+                                UsernameFragment::parse(src.as_ref(), attr_v).finish()?;
+
+                            match media {
+                                Some(media) => media.ice_ufrag = Some(ufrag),
+                                None => fragment.ice_ufrag = Some(ufrag),
+                            }
+                        }
+                        "ice-pwd" => {
+                            let (_, pwd) = Password::parse(src.as_ref(), attr_v).finish()?;
+
+                            match media {
+                                Some(media) => media.ice_pwd = Some(pwd),
+                                None => fragment.ice_pwd = Some(pwd),
+                            }
+                        }
+                        "group" => {
+                            let (_, group) = Group::parse(src.as_ref(), line).finish()?;
+                            fragment.groups.push(group);
+                        }
+                        "mid" => {
+                            if let Some(media) = media {
+                                media.mid = Some(BytesStr::from_parse(src.as_ref(), attr_v.trim()));
+                            }
+                        }
+                        "candidate" => {
+                            let (_, candidate) = Candidate::parse(src.as_ref(), line).finish()?;
+
+                            media
+                                .ok_or(FragmentError::CandidateWithoutMedia)?
+                                .candidates
+                                .push(candidate);
+                        }
+                        "end-of-candidates" => match media {
+                            Some(media) => media.end_of_candidates = true,
+                            None => fragment.end_of_candidates = true,
+                        },
+                        _ => {}
+                    }
+                }
+                _ => {
+                    // other lines are not relevant for trickle ICE
+                }
+            }
+        }
+
+        Ok(fragment)
+    }
+
+    /// Add the credentials and candidates of the fragment to the given session description
+    ///
+    /// Media fragments are matched to media scopes by their mid, or by their position if the
+    /// fragment contains no mid.
+    pub fn apply_to(&self, session: &mut Message) -> Result<(), FragmentError> {
+        if self.ice_ufrag.is_some() {
+            session.ice_ufrag.clone_from(&self.ice_ufrag);
+        }
+
+        if self.ice_pwd.is_some() {
+            session.ice_pwd.clone_from(&self.ice_pwd);
+        }
+
+        for (index, fragment) in self.media.iter().enumerate() {
+            let media = match &fragment.mid {
+                Some(mid) => session
+                    .media_scopes
+                    .iter_mut()
+                    .find(|media| media.mid.as_ref() == Some(mid)),
+                None => session.media_scopes.get_mut(index),
+            };
+
+            let media = media.ok_or_else(|| FragmentError::UnknownMedia(fragment.mid.clone()))?;
+
+            if fragment.ice_ufrag.is_some() {
+                media.ice_ufrag.clone_from(&fragment.ice_ufrag);
+            }
+
+            if fragment.ice_pwd.is_some() {
+                media.ice_pwd.clone_from(&fragment.ice_pwd);
+            }
+
+            media
+                .ice_candidates
+                .extend(fragment.candidates.iter().cloned());
+            media.ice_end_of_candidates |= fragment.end_of_candidates;
+        }
+
+        if self.end_of_candidates {
+            for media in &mut session.media_scopes {
+                media.ice_end_of_candidates = true;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SdpFragment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.ice_options)?;
+
+        if let Some(ufrag) = &self.ice_ufrag {
+            write!(f, "{}\r\n", ufrag)?;
+        }
+
+        if let Some(pwd) = &self.ice_pwd {
+            write!(f, "{}\r\n", pwd)?;
+        }
+
+        for group in &self.groups {
+            write!(f, "{}\r\n", group)?;
+        }
+
+        if self.end_of_candidates {
+            f.write_str("a=end-of-candidates\r\n")?;
+        }
+
+        for media in &self.media {
+            write!(f, "{}", media)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for MediaFragment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\r\n", self.desc)?;
+
+        if let Some(mid) = &self.mid {
+            write!(f, "a=mid:{}\r\n", mid)?;
+        }
+
+        if let Some(ufrag) = &self.ice_ufrag {
+            write!(f, "{}\r\n", ufrag)?;
+        }
+
+        if let Some(pwd) = &self.ice_pwd {
+            write!(f, "{}\r\n", pwd)?;
+        }
+
+        for candidate in &self.candidates {
+            write!(f, "{}\r\n", candidate)?;
+        }
+
+        if self.end_of_candidates {
+            f.write_str("a=end-of-candidates\r\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::msg::{parse, Builder};
+
+    const FRAGMENT: &str = "a=ice-ufrag:8hhY\r
+a=ice-pwd:asd88fgpdd777uzjYhagZg\r
+m=audio 9 RTP/AVP 0\r
+a=mid:1\r
+a=candidate:1 1 UDP 1658497328 198.51.100.33 5000 typ host\r
+a=candidate:2 1 UDP 1658497328 198.51.100.33 5002 typ host\r
+a=end-of-candidates\r
+";
+
+    #[test]
+    fn fragment() {
+        let fragment = SdpFragment::parse(&BytesStr::from_static(FRAGMENT)).unwrap();
+
+        assert_eq!(fragment.ice_ufrag.as_ref().unwrap().ufrag, "8hhY");
+
+        let media = &fragment.media[0];
+        assert_eq!(media.mid.as_deref(), Some("1"));
+        assert_eq!(media.candidates.len(), 2);
+        assert!(media.end_of_candidates);
+
+        assert_eq!(fragment.to_string(), FRAGMENT);
+    }
+
+    #[test]
+    fn fragment_without_media() {
+        let err = SdpFragment::parse(&BytesStr::from_static(
+            "a=candidate:1 1 UDP 1658497328 198.51.100.33 5000 typ host\r\n",
+        ))
+        .unwrap_err();
+
+        assert!(matches!(err, FragmentError::CandidateWithoutMedia));
+    }
+
+    #[test]
+    fn apply() {
+        let mut session = parse::<Builder>(&BytesStr::from_static(
+            "v=0\r
+o=- 1 1 IN IP4 198.51.100.33\r
+s=-\r
+t=0 0\r
+m=audio 5000 RTP/AVP 0\r
+a=mid:1\r
+m=video 5004 RTP/AVP 96\r
+a=mid:2\r
+a=rtpmap:96 VP8/90000\r
+",
+        ))
+        .unwrap();
+
+        let mut fragment = SdpFragment::default();
+        fragment
+            .media
+            .push(MediaFragment::new(&session.media_scopes[1]));
+        assert_eq!(fragment.to_string(), "m=video 9 RTP/AVP 96\r\na=mid:2\r\n");
+
+        let fragment = SdpFragment::parse(&BytesStr::from_static(FRAGMENT)).unwrap();
+        fragment.apply_to(&mut session).unwrap();
+
+        assert_eq!(
+            session.ice_pwd.as_ref().unwrap().pwd,
+            "asd88fgpdd777uzjYhagZg"
+        );
+        assert_eq!(session.media_scopes[0].ice_candidates.len(), 2);
+        assert!(session.media_scopes[0].ice_end_of_candidates);
+        assert!(session.media_scopes[1].ice_candidates.is_empty());
+
+        let mut unknown = fragment.clone();
+        unknown.media[0].mid = Some("3".into());
+        assert!(matches!(
+            unknown.apply_to(&mut session),
+            Err(FragmentError::UnknownMedia(Some(mid))) if mid == "3"
+        ));
+    }
+}
@@ -1,8 +1,10 @@
 use internal::ws;
 use internal::IResult;
-use nom::character::complete::digit1;
-use nom::combinator::map;
-use nom::combinator::map_res;
+use nom::branch::alt;
+use nom::character::complete::{char, digit1, multispace1};
+use nom::combinator::{map, map_res, opt, recognize, value};
+use nom::multi::{many0, many1};
+use nom::sequence::{pair, preceded, tuple};
 use std::fmt;
 use std::str::FromStr;
 
@@ -22,6 +24,9 @@ pub struct Time {
     /// If 0 is specified the session will run forever
     /// or until torn down by the parent signaling protocol.
     pub stop: u64,
+
+    /// Repeat times (r fields) following the time
+    pub repeats: Vec<RepeatTime>,
 }
 
 impl Time {
@@ -31,14 +36,171 @@ impl Time {
                 map_res(digit1, FromStr::from_str),
                 map_res(digit1, FromStr::from_str),
             )),
-            |(start, stop)| Time { start, stop },
+            |(start, stop)| Time {
+                start,
+                stop,
+                repeats: vec![],
+            },
         )(i)
     }
 }
 
 impl fmt::Display for Time {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "t={} {}", self.start, self.stop)
+        write!(f, "t={} {}", self.start, self.stop)?;
+
+        for repeat in &self.repeats {
+            write!(f, "\r\n{}", repeat)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Repetition of the session, all values are in seconds
+///
+/// [RFC8866](https://www.rfc-editor.org/rfc/rfc8866.html#section-5.10)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepeatTime {
+    /// Interval in which the session is repeated
+    pub interval: u64,
+
+    /// Duration of each repetition
+    pub duration: u64,
+
+    /// Offsets of the repetitions from the start time
+    pub offsets: Vec<u64>,
+}
+
+impl RepeatTime {
+    pub fn parse(i: &str) -> IResult<&str, Self> {
+        map(
+            tuple((
+                typed_time,
+                preceded(multispace1, typed_time),
+                many1(preceded(multispace1, typed_time)),
+            )),
+            |(interval, duration, offsets)| RepeatTime {
+                interval,
+                duration,
+                offsets,
+            },
+        )(i)
+    }
+}
+
+impl fmt::Display for RepeatTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "r={} {}",
+            TypedTime(self.interval.into()),
+            TypedTime(self.duration.into())
+        )?;
+
+        for offset in &self.offsets {
+            write!(f, " {}", TypedTime((*offset).into()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Time at which the offset to UTC of the session changes, e.g. due to daylight saving time
+///
+/// [RFC8866](https://www.rfc-editor.org/rfc/rfc8866.html#section-5.11)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZoneAdjustment {
+    /// Time of the adjustment, in seconds since January 1 1900 UTC
+    pub time: u64,
+
+    /// Offset in seconds to apply to the repeat times from then on
+    pub offset: i64,
+}
+
+impl ZoneAdjustment {
+    /// Parse the content of a z field
+    pub fn parse_list(i: &str) -> IResult<&str, Vec<Self>> {
+        map(
+            pair(
+                zone_adjustment,
+                many0(preceded(multispace1, zone_adjustment)),
+            ),
+            |(first, mut rest)| {
+                rest.insert(0, first);
+                rest
+            },
+        )(i)
+    }
+
+    /// Write the adjustments as z field
+    pub fn fmt_list(adjustments: &[Self], f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("z=")?;
+
+        for (i, adjustment) in adjustments.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+
+            write!(
+                f,
+                "{} {}",
+                adjustment.time,
+                TypedTime(adjustment.offset.into())
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn zone_adjustment(i: &str) -> IResult<&str, ZoneAdjustment> {
+    map(
+        tuple((
+            map_res(digit1, FromStr::from_str),
+            multispace1,
+            map_res(recognize(pair(opt(char('-')), digit1)), i64::from_str),
+            unit,
+        )),
+        |(time, _, offset, unit)| ZoneAdjustment {
+            time,
+            offset: offset * unit as i64,
+        },
+    )(i)
+}
+
+/// Time in seconds or with unit suffix (`d`, `h`, `m`, `s`)
+fn typed_time(i: &str) -> IResult<&str, u64> {
+    map(
+        pair(map_res(digit1, u64::from_str), unit),
+        |(value, unit)| value * unit,
+    )(i)
+}
+
+fn unit(i: &str) -> IResult<&str, u64> {
+    map(
+        opt(alt((
+            value(86400, char('d')),
+            value(3600, char('h')),
+            value(60, char('m')),
+            value(1, char('s')),
+        ))),
+        |unit| unit.unwrap_or(1),
+    )(i)
+}
+
+/// Writes seconds using the largest unit without loss
+struct TypedTime(i128);
+
+impl fmt::Display for TypedTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            0 => f.write_str("0"),
+            v if v % 86400 == 0 => write!(f, "{}d", v / 86400),
+            v if v % 3600 == 0 => write!(f, "{}h", v / 3600),
+            v if v % 60 == 0 => write!(f, "{}m", v / 60),
+            v => write!(f, "{}", v),
+        }
     }
 }
 
@@ -58,8 +220,71 @@ mod test {
 
     #[test]
     fn time_print() {
-        let time = Time { start: 0, stop: 0 };
+        let time = Time {
+            start: 0,
+            stop: 0,
+            repeats: vec![],
+        };
 
         assert_eq!(time.to_string(), "t=0 0");
     }
+
+    #[test]
+    fn repeat_time() {
+        let (rem, repeat) = RepeatTime::parse("7d 1h 0 25h").unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(
+            repeat,
+            RepeatTime {
+                interval: 604800,
+                duration: 3600,
+                offsets: vec![0, 90000],
+            }
+        );
+
+        let (rem, repeat) = RepeatTime::parse("604800 3600 0 90000").unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(repeat.offsets, [0, 90000]);
+    }
+
+    #[test]
+    fn repeat_time_print() {
+        let time = Time {
+            start: 3724394400,
+            stop: 3724398000,
+            repeats: vec![RepeatTime {
+                interval: 604800,
+                duration: 5400,
+                offsets: vec![0, 90000],
+            }],
+        };
+
+        assert_eq!(
+            time.to_string(),
+            "t=3724394400 3724398000\r\nr=7d 90m 0 25h"
+        );
+    }
+
+    #[test]
+    fn zone_adjustments() {
+        let (rem, adjustments) = ZoneAdjustment::parse_list("3730922900 -1h 3749680500 0").unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(
+            adjustments,
+            [
+                ZoneAdjustment {
+                    time: 3730922900,
+                    offset: -3600
+                },
+                ZoneAdjustment {
+                    time: 3749680500,
+                    offset: 0
+                }
+            ]
+        );
+    }
 }
@@ -0,0 +1,293 @@
+//! Semantic validation of parsed or constructed messages
+
+use crate::attributes::UnknownAttribute;
+use crate::media::TransportProtocol;
+use crate::msg::{MediaScope, Message};
+use bytesstr::BytesStr;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The message violates the specification and will likely be rejected by peers
+    Error,
+    /// The message is valid but likely to cause interoperability issues
+    Warning,
+}
+
+/// Issue found by [`Message::validate`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DiagnosticKind {
+    #[error("media description has no formats")]
+    NoFormats,
+    #[error("payload type {0} is used multiple times")]
+    DuplicatePayload(u32),
+    #[error("dynamic payload type {0} has no rtpmap")]
+    MissingRtpMap(u32),
+    #[error("rtpmap for payload type {0} which is not used by the media description")]
+    UnusedRtpMap(u32),
+    #[error("mid {0} is used by multiple media descriptions")]
+    DuplicateMid(BytesStr),
+    #[error("{semantics} group references unknown mid {mid}")]
+    UnknownGroupMid { semantics: BytesStr, mid: BytesStr },
+    #[error("no connection address at session or media level")]
+    MissingConnection,
+    #[error("DTLS media without fingerprint")]
+    MissingFingerprint,
+    #[error("DTLS media without setup attribute")]
+    MissingSetup,
+    #[error("invalid setup attribute {0}")]
+    InvalidSetup(BytesStr),
+    #[error("fingerprint without DTLS media")]
+    UnusedFingerprint,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    /// Index of the media description, `None` for issues on session level
+    pub media: Option<usize>,
+
+    pub kind: DiagnosticKind,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.media {
+            Some(index) => write!(f, "media description {index}: {}", self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl Message {
+    /// Check the message for semantic issues which aren't detected during parsing
+    ///
+    /// Returns all issues found, an empty list means the message is valid.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+
+        let mut push = |severity, media, kind| {
+            diagnostics.push(Diagnostic {
+                severity,
+                media,
+                kind,
+            })
+        };
+
+        let session_fingerprint = find_attr(&self.attributes, "fingerprint").is_some();
+        // Invalid setup values are kept as unknown attributes
+        let session_setup = find_attr(&self.attributes, "setup");
+        let mut uses_dtls = false;
+
+        for (index, media) in self.media_scopes.iter().enumerate() {
+            let at = Some(index);
+
+            if media.desc.fmts.is_empty() && media.desc.named_fmts.is_empty() {
+                push(Severity::Error, at, DiagnosticKind::NoFormats);
+            }
+
+            let fmts = &media.desc.fmts;
+
+            for (i, &payload) in fmts.iter().enumerate() {
+                if fmts[..i].contains(&payload) {
+                    push(
+                        Severity::Error,
+                        at,
+                        DiagnosticKind::DuplicatePayload(payload),
+                    );
+                }
+            }
+
+            if is_rtp(&media.desc.proto) {
+                for &payload in &media.desc.fmts {
+                    if payload >= 96 && !media.rtpmaps.iter().any(|r| r.payload == payload) {
+                        push(Severity::Error, at, DiagnosticKind::MissingRtpMap(payload));
+                    }
+                }
+            }
+
+            for rtpmap in &media.rtpmaps {
+                if !media.desc.fmts.contains(&rtpmap.payload) {
+                    push(
+                        Severity::Warning,
+                        at,
+                        DiagnosticKind::UnusedRtpMap(rtpmap.payload),
+                    );
+                }
+            }
+
+            if let Some(mid) = &media.mid {
+                let duplicate = self.media_scopes[..index]
+                    .iter()
+                    .any(|other| other.mid.as_ref() == Some(mid));
+
+                if duplicate {
+                    push(
+                        Severity::Error,
+                        at,
+                        DiagnosticKind::DuplicateMid(mid.clone()),
+                    );
+                }
+            }
+
+            // Rejected media doesn't need a connection
+            if media.desc.port != 0 && media.connection.is_none() && self.connection.is_none() {
+                push(Severity::Error, at, DiagnosticKind::MissingConnection);
+            }
+
+            if is_dtls(media) {
+                uses_dtls = true;
+
+                if !session_fingerprint && find_attr(&media.attributes, "fingerprint").is_none() {
+                    push(Severity::Error, at, DiagnosticKind::MissingFingerprint);
+                }
+
+                match find_attr(&media.attributes, "setup").or(session_setup) {
+                    Some(setup) => {
+                        let value = setup.value.clone().unwrap_or_default();
+                        push(Severity::Error, at, DiagnosticKind::InvalidSetup(value));
+                    }
+                    None if media.setup.is_none() && self.setup.is_none() => {
+                        push(Severity::Warning, at, DiagnosticKind::MissingSetup)
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        if session_fingerprint && !uses_dtls {
+            push(Severity::Warning, None, DiagnosticKind::UnusedFingerprint);
+        }
+
+        for group in &self.groups {
+            for mid in &group.mids {
+                let known = self
+                    .media_scopes
+                    .iter()
+                    .any(|media| media.mid.as_ref() == Some(mid));
+
+                if !known {
+                    push(
+                        Severity::Error,
+                        None,
+                        DiagnosticKind::UnknownGroupMid {
+                            semantics: group.semantics.clone(),
+                            mid: mid.clone(),
+                        },
+                    );
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+fn find_attr<'a>(attributes: &'a [UnknownAttribute], name: &str) -> Option<&'a UnknownAttribute> {
+    attributes.iter().find(|attr| attr.name == name)
+}
+
+fn is_rtp(proto: &TransportProtocol) -> bool {
+    match proto {
+        TransportProtocol::RtpAvp | TransportProtocol::RtpSavp | TransportProtocol::RtpSavpf => {
+            true
+        }
+        TransportProtocol::Other(proto) => proto.contains("RTP/"),
+        TransportProtocol::Unspecified
+        | TransportProtocol::UdpDtlsSctp
+        | TransportProtocol::TcpDtlsSctp
+        | TransportProtocol::DtlsSctp
+        | TransportProtocol::TcpBfcp
+        | TransportProtocol::TcpTlsBfcp
+        | TransportProtocol::UdpBfcp
+        | TransportProtocol::UdpTlsBfcp
+        | TransportProtocol::TcpMsrp
+        | TransportProtocol::TcpTlsMsrp
+        | TransportProtocol::Udptl => false,
+    }
+}
+
+fn is_dtls(media: &MediaScope) -> bool {
+    match &media.desc.proto {
+        TransportProtocol::UdpDtlsSctp
+        | TransportProtocol::TcpDtlsSctp
+        | TransportProtocol::DtlsSctp
+        | TransportProtocol::UdpTlsBfcp => true,
+        TransportProtocol::Other(proto) => proto.contains("DTLS") || proto.contains("TLS/RTP"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::msg::{parse, Builder};
+
+    fn validate(sdp: &'static str) -> Vec<DiagnosticKind> {
+        parse::<Builder>(&BytesStr::from_static(sdp))
+            .unwrap()
+            .validate()
+            .into_iter()
+            .map(|diagnostic| diagnostic.kind)
+            .collect()
+    }
+
+    #[test]
+    fn valid() {
+        let diagnostics = validate(
+            "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+c=IN IP4 10.0.0.1\r
+t=0 0\r
+a=group:BUNDLE 0\r
+a=fingerprint:sha-256 AB:CD\r
+m=audio 1000 UDP/TLS/RTP/SAVPF 0 111\r
+a=mid:0\r
+a=setup:actpass\r
+a=rtpmap:111 opus/48000/2\r
+",
+        );
+
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    }
+
+    #[test]
+    fn invalid() {
+        let diagnostics = validate(
+            "v=0\r
+o=- 1 1 IN IP4 10.0.0.1\r
+s=-\r
+t=0 0\r
+a=group:BUNDLE 0 1 2\r
+m=audio 1000 RTP/AVP 96\r
+a=mid:0\r
+a=rtpmap:97 opus/48000/2\r
+m=video 2000 UDP/TLS/RTP/SAVPF 96\r
+c=IN IP4 10.0.0.1\r
+a=mid:0\r
+a=setup:both\r
+a=rtpmap:96 VP8/90000\r
+m=video 0 RTP/AVP\r
+m=audio 0 RTP/AVP 8 8\r
+",
+        );
+
+        let expected = [
+            "dynamic payload type 96 has no rtpmap",
+            "rtpmap for payload type 97 which is not used by the media description",
+            "no connection address at session or media level",
+            "mid 0 is used by multiple media descriptions",
+            "DTLS media without fingerprint",
+            "invalid setup attribute both",
+            "media description has no formats",
+            "payload type 8 is used multiple times",
+            "BUNDLE group references unknown mid 1",
+            "BUNDLE group references unknown mid 2",
+        ];
+
+        let diagnostics: Vec<_> = diagnostics.iter().map(ToString::to_string).collect();
+        assert_eq!(diagnostics, expected);
+    }
+}
@@ -5,7 +5,10 @@ use sip_types::header::typed::{
     Algorithm, AuthChallenge, AuthResponse, DigestChallenge, DigestResponse, QopOption,
     QopResponse, Username,
 };
+use sip_types::msg::RequestLine;
 use sip_types::print::{AppendCtx, PrintCtx, UriContext};
+use sip_types::uri::sip::SipUri;
+use sip_types::Method;
 
 pub struct DigestCredentials {
     user: String,
@@ -23,6 +26,82 @@ impl DigestCredentials {
             password: password.into(),
         }
     }
+
+    /// Verify a digest response received in a request against these credentials
+    ///
+    /// Only the response itself and its `uri` (which must match the Request-URI) are checked, the
+    /// caller must make sure that the nonce is one it issued, that it is still valid and that the
+    /// nonce-count increases.
+    pub fn verify(
+        &self,
+        response: &DigestResponse,
+        request_parts: RequestParts<'_>,
+    ) -> Result<bool, Error> {
+        let Some((hash, is_session)) = algorithm_hash(&response.algorithm) else {
+            return Err(Error::UnsupportedAlgorithm(
+                response.algorithm.to_string().into(),
+            ));
+        };
+
+        let username_matches = if response.userhash {
+            let username_hash = hash(format!("{}:{}", self.user, response.realm).as_bytes());
+
+            response.username == Username::Username(username_hash.into())
+        } else {
+            response.username == Username::new(self.user.as_str().into())
+        };
+
+        if !username_matches || !uri_matches(&response.uri, request_parts.line) {
+            return Ok(false);
+        }
+
+        let qop_response = response.qop_response.as_ref();
+
+        if let Some(QopOption::Other(_)) = qop_response.map(|qop_response| &qop_response.qop) {
+            return Err(Error::UnsupportedQop);
+        }
+
+        let session = match (is_session, qop_response) {
+            (true, Some(qop_response)) => Some((&response.nonce, &qop_response.cnonce)),
+            // Session variants require a cnonce
+            (true, None) => return Ok(false),
+            (false, _) => None,
+        };
+
+        let ha1 = ha1(hash, self, &response.realm, session);
+        let ha2 = ha2(
+            hash,
+            &request_parts.line.method,
+            &response.uri,
+            qop_response.map(|qop_response| &qop_response.qop),
+            request_parts.body,
+        );
+
+        let expected = response_hash(hash, &ha1, &response.nonce, qop_response, &ha2);
+
+        Ok(expected.eq_ignore_ascii_case(&response.response))
+    }
+}
+
+/// The `uri` of a digest response must reference the Request-URI (RFC7616 Section 3.4.6)
+fn uri_matches(uri: &str, line: &RequestLine) -> bool {
+    if request_uri(line) == uri {
+        return true;
+    }
+
+    match (uri.parse::<SipUri>(), line.uri.downcast_ref::<SipUri>()) {
+        (Ok(uri), Some(request_uri)) => uri.compare(request_uri),
+        _ => false,
+    }
+}
+
+fn request_uri(line: &RequestLine) -> String {
+    let ctx = PrintCtx {
+        method: Some(&line.method),
+        uri: Some(UriContext::ReqUri),
+    };
+
+    line.uri.print_ctx(ctx).to_string()
 }
 
 fn hash_md5(i: &[u8]) -> String {
@@ -43,9 +122,65 @@ fn hash_sha512_trunc256(i: &[u8]) -> String {
 
 type HashFn = fn(&[u8]) -> String;
 
+/// Returns the hash function of the algorithm and if it is a session variant
+fn algorithm_hash(algorithm: &Algorithm) -> Option<(HashFn, bool)> {
+    match algorithm {
+        Algorithm::MD5 => Some((hash_md5, false)),
+        Algorithm::MD5Sess => Some((hash_md5, true)),
+        Algorithm::SHA256 => Some((hash_sha256, false)),
+        Algorithm::SHA256Sess => Some((hash_sha256, true)),
+        Algorithm::SHA512256 => Some((hash_sha512_trunc256, false)),
+        Algorithm::SHA512256Sess => Some((hash_sha512_trunc256, true)),
+        Algorithm::Other(_) => None,
+    }
+}
+
+/// H(A1), session variants include nonce and cnonce (RFC7616 Section 3.4.2)
+fn ha1(
+    hash: HashFn,
+    credentials: &DigestCredentials,
+    realm: &str,
+    session: Option<(&BytesStr, &BytesStr)>,
+) -> String {
+    let ha1 = hash(format!("{}:{}:{}", credentials.user, realm, credentials.password).as_bytes());
+
+    match session {
+        Some((nonce, cnonce)) => hash(format!("{}:{}:{}", ha1, nonce, cnonce).as_bytes()),
+        None => ha1,
+    }
+}
+
+/// H(A2), `auth-int` includes the hash of the body (RFC7616 Section 3.4.3)
+fn ha2(hash: HashFn, method: &Method, uri: &str, qop: Option<&QopOption>, body: &[u8]) -> String {
+    if let Some(QopOption::AuthInt) = qop {
+        hash(format!("{}:{}:{}", method, uri, hash(body)).as_bytes())
+    } else {
+        hash(format!("{}:{}", method, uri).as_bytes())
+    }
+}
+
+/// The digest response (RFC7616 Section 3.4.1)
+fn response_hash(
+    hash: HashFn,
+    ha1: &str,
+    nonce: &str,
+    qop_response: Option<&QopResponse>,
+    ha2: &str,
+) -> String {
+    match qop_response {
+        Some(qop_response) => hash(
+            format!(
+                "{}:{}:{:08x}:{}:{}:{}",
+                ha1, nonce, qop_response.nc, qop_response.cnonce, qop_response.qop, ha2
+            )
+            .as_bytes(),
+        ),
+        None => hash(format!("{}:{}:{}", ha1, nonce, ha2).as_bytes()),
+    }
+}
+
 struct QopEntry {
     ha1: String,
-    ha2: String,
     hash: HashFn,
 }
 
@@ -57,6 +192,9 @@ pub struct DigestAuthenticator {
     pub enforce_qop: bool,
     /// Reject challenges with MD5 algorithm. Is false by default
     pub reject_md5: bool,
+    /// When a realm is challenged with multiple algorithms, respond to the strongest one
+    /// (SHA-512-256, SHA-256, MD5) instead of the topmost. Is false by default
+    pub prefer_strongest_algorithm: bool,
 }
 
 impl UacAuthenticator for DigestAuthenticator {
@@ -109,22 +247,43 @@ impl UacAuthenticator for DigestAuthenticator {
         }
     }
 
-    fn on_authorize_request(&mut self, response: &mut ResponseEntry) {
+    fn challenge_priority(&self, challenge: &AuthChallenge) -> u32 {
+        let AuthChallenge::Digest(digest) = challenge else {
+            return 0;
+        };
+
+        if !self.prefer_strongest_algorithm {
+            return 0;
+        }
+
+        match digest.algorithm {
+            Algorithm::SHA512256 | Algorithm::SHA512256Sess => 3,
+            Algorithm::SHA256 | Algorithm::SHA256Sess => 2,
+            Algorithm::MD5 | Algorithm::MD5Sess => 1,
+            Algorithm::Other(_) => 0,
+        }
+    }
+
+    fn on_authorize_request(
+        &mut self,
+        response: &mut ResponseEntry,
+        line: &RequestLine,
+        body: &[u8],
+    ) {
         let digest = match &mut response.response {
             AuthResponse::Digest(response) => response,
             AuthResponse::Other(_) => return,
         };
 
-        // nc is already correct
-        if response.use_count == 0 {
-            return;
-        }
-
         let digest_realm = &digest.realm;
 
-        // qop response needs its nonce-count incremented and response re-calculated
+        // qop response needs its nonce-count incremented (unless this is its first use) and
+        // response re-calculated for the request it is used in
         let qop_response = if let Some(qop_response) = &mut digest.qop_response {
-            qop_response.nc += 1;
+            if response.use_count > 0 {
+                qop_response.nc += 1;
+            }
+
             qop_response
         } else {
             return;
@@ -136,21 +295,27 @@ impl UacAuthenticator for DigestAuthenticator {
             .find(|(realm, _)| realm == digest_realm)
             .expect("qop_entry must be some");
 
-        let response = match qop_response.qop {
-            QopOption::Auth | QopOption::AuthInt => (qop_entry.hash)(
-                format!(
-                    "{}:{}:{:08X}:{}:auth:{}",
-                    qop_entry.ha1,
-                    digest.nonce,
-                    qop_response.nc,
-                    qop_response.cnonce,
-                    qop_entry.ha2
-                )
-                .as_bytes(),
-            ),
-            QopOption::Other(_) => unreachable!(),
-        };
+        // H(A2) covers method, Request-URI and for `auth-int` the body, all of which may differ
+        // from the request that was challenged
+        let uri = request_uri(line);
+
+        let ha2 = ha2(
+            qop_entry.hash,
+            &line.method,
+            &uri,
+            Some(&qop_response.qop),
+            body,
+        );
 
+        let response = response_hash(
+            qop_entry.hash,
+            &qop_entry.ha1,
+            &digest.nonce,
+            Some(qop_response),
+            &ha2,
+        );
+
+        digest.uri = uri.into();
         digest.response = response.into();
     }
 }
@@ -162,26 +327,14 @@ impl DigestAuthenticator {
         digest: DigestChallenge,
         request_parts: RequestParts<'_>,
     ) -> Result<AuthResponse, Error> {
-        let (hash, is_session): (HashFn, bool) = match digest.algorithm {
-            Algorithm::MD5 => {
-                if self.reject_md5 {
-                    return Err(Error::UnsupportedAlgorithm(BytesStr::from_static("MD5")));
-                } else {
-                    (hash_md5, false)
-                }
-            }
-            Algorithm::MD5Sess => {
-                if self.reject_md5 {
-                    return Err(Error::UnsupportedAlgorithm(BytesStr::from_static("MD5")));
-                } else {
-                    (hash_md5, true)
-                }
-            }
-            Algorithm::SHA256 => (hash_sha256, false),
-            Algorithm::SHA256Sess => (hash_sha256, true),
-            Algorithm::SHA512256 => (hash_sha512_trunc256, false),
-            Algorithm::SHA512256Sess => (hash_sha512_trunc256, true),
-            Algorithm::Other(other) => return Err(Error::UnsupportedAlgorithm(other)),
+        if self.reject_md5 && matches!(digest.algorithm, Algorithm::MD5 | Algorithm::MD5Sess) {
+            return Err(Error::UnsupportedAlgorithm(BytesStr::from_static("MD5")));
+        }
+
+        let Some((hash, is_session)) = algorithm_hash(&digest.algorithm) else {
+            return Err(Error::UnsupportedAlgorithm(
+                digest.algorithm.to_string().into(),
+            ));
         };
 
         let response = self.digest_respond(digest, request_parts, credentials, is_session, hash)?;
@@ -199,96 +352,46 @@ impl DigestAuthenticator {
     ) -> Result<DigestResponse, Error> {
         let cnonce = BytesStr::from(uuid::Uuid::new_v4().simple().to_string());
 
-        let mut ha1 = hash(
-            format!(
-                "{}:{}:{}",
-                credentials.user, challenge.realm, credentials.password
-            )
-            .as_bytes(),
+        let ha1 = ha1(
+            hash,
+            credentials,
+            &challenge.realm,
+            is_session.then_some((&challenge.nonce, &cnonce)),
         );
 
-        if is_session {
-            ha1 = format!("{}:{}:{}", ha1, challenge.nonce, cnonce);
-        }
-
-        let ctx = PrintCtx {
-            method: Some(&request_parts.line.method),
-            uri: Some(UriContext::ReqUri),
-        };
-
-        let uri = request_parts.line.uri.print_ctx(ctx).to_string();
+        let uri = request_uri(request_parts.line);
 
         // enforce qop when enabled (See RFC8760 Section 2.6)
         if challenge.qop.is_empty() && self.enforce_qop {
             challenge.qop.push(QopOption::Auth)
         }
 
-        let (response, qop_response) = if !challenge.qop.is_empty() {
-            if challenge.qop.contains(&QopOption::AuthInt) {
-                let ha2 = hash(
-                    format!(
-                        "{}:{}:{}",
-                        &request_parts.line.method,
-                        uri,
-                        hash(request_parts.body)
-                    )
-                    .as_bytes(),
-                );
-
-                let nc = 1;
-
-                let response = hash(
-                    format!(
-                        "{}:{}:{:08X}:{}:auth-int:{}",
-                        ha1, challenge.nonce, nc, cnonce, ha2
-                    )
-                    .as_bytes(),
-                );
-
-                self.save_qop_response(&challenge.realm, ha1, ha2, hash);
-
-                let qop_response = QopResponse {
-                    qop: QopOption::AuthInt,
-                    cnonce,
-                    nc,
-                };
-
-                (response, Some(qop_response))
-            } else if challenge.qop.contains(&QopOption::Auth) {
-                let a2 = format!("{}:{}", &request_parts.line.method, uri);
-                let ha2 = hash(a2.as_bytes());
-
-                let nc = 1;
-
-                let response = hash(
-                    format!(
-                        "{}:{}:{:08X}:{}:auth:{}",
-                        ha1, challenge.nonce, nc, cnonce, ha2
-                    )
-                    .as_bytes(),
-                );
-
-                self.save_qop_response(&challenge.realm, ha1, ha2, hash);
-
-                let qop_response = QopResponse {
-                    qop: QopOption::Auth,
-                    cnonce,
-                    nc,
-                };
-
-                (response, Some(qop_response))
-            } else {
-                return Err(Error::UnsupportedQop);
-            }
+        let qop = if challenge.qop.is_empty() {
+            None
+        } else if challenge.qop.contains(&QopOption::AuthInt) {
+            Some(QopOption::AuthInt)
+        } else if challenge.qop.contains(&QopOption::Auth) {
+            Some(QopOption::Auth)
         } else {
-            let a2 = format!("{}:{}", &request_parts.line.method, uri);
-
-            (
-                hash(format!("{}:{}:{}", ha1, challenge.nonce, hash(a2.as_bytes())).as_bytes()),
-                None,
-            )
+            return Err(Error::UnsupportedQop);
         };
 
+        let ha2 = ha2(
+            hash,
+            &request_parts.line.method,
+            &uri,
+            qop.as_ref(),
+            request_parts.body,
+        );
+
+        let qop_response = qop.map(|qop| QopResponse { qop, cnonce, nc: 1 });
+
+        let response = response_hash(hash, &ha1, &challenge.nonce, qop_response.as_ref(), &ha2);
+
+        if qop_response.is_some() {
+            self.save_qop_response(&challenge.realm, ha1, hash);
+        }
+
         let username = if challenge.userhash {
             // Hash the username when the challenge sets `userhash` (RFC7616 Section 3.4.4)
             let username_hash =
@@ -315,14 +418,8 @@ impl DigestAuthenticator {
         })
     }
 
-    fn save_qop_response(
-        &mut self,
-        challenge_realm: &BytesStr,
-        ha1: String,
-        ha2: String,
-        hash: HashFn,
-    ) {
-        let qop_entry = QopEntry { ha1, ha2, hash };
+    fn save_qop_response(&mut self, challenge_realm: &BytesStr, ha1: String, hash: HashFn) {
+        let qop_entry = QopEntry { ha1, hash };
 
         if let Some((_, old_qop_entry)) = self
             .qop_responses
@@ -402,7 +499,7 @@ mod test {
             .unwrap();
 
         let mut response_headers = Headers::new();
-        session.authorize_request(&mut response_headers);
+        session.authorize_request(&line, &mut response_headers, &[]);
 
         let authorization = response_headers
             .get::<AuthResponse>(Name::AUTHORIZATION)
@@ -436,6 +533,194 @@ mod test {
         }
     }
 
+    fn challenge(algorithm: Algorithm) -> AuthChallenge {
+        AuthChallenge::Digest(DigestChallenge {
+            realm: "example.org".into(),
+            domain: None,
+            nonce: "YWmh5GFpoLjiTDCA1hTSSygkgdj99aHE".into(),
+            opaque: None,
+            stale: false,
+            algorithm,
+            qop: vec![],
+            userhash: false,
+            other: vec![],
+        })
+    }
+
+    fn respond(authenticator: DigestAuthenticator, headers: &Headers) -> DigestResponse {
+        let uri: SipUri = "sip:example.org".parse().unwrap();
+
+        let line = RequestLine {
+            method: Method::REGISTER,
+            uri: Box::new(uri),
+        };
+
+        let mut session = UacAuthSession::new(authenticator);
+
+        session
+            .handle_authenticate(
+                headers,
+                &test_credentials(),
+                RequestParts {
+                    line: &line,
+                    headers: &Headers::new(),
+                    body: &[],
+                },
+            )
+            .unwrap();
+
+        let mut response_headers = Headers::new();
+        session.authorize_request(&line, &mut response_headers, &[]);
+
+        match response_headers
+            .get::<AuthResponse>(Name::AUTHORIZATION)
+            .unwrap()
+        {
+            AuthResponse::Digest(response) => response,
+            _ => panic!("Expected digest"),
+        }
+    }
+
+    #[test]
+    fn digest_sha256() {
+        let mut headers = Headers::new();
+        headers.insert_type(Name::WWW_AUTHENTICATE, &challenge(Algorithm::SHA256));
+
+        let response = respond(DigestAuthenticator::default(), &headers);
+
+        assert_eq!(response.algorithm, Algorithm::SHA256);
+        assert_eq!(
+            response.response,
+            "7e0884590ebfc039aec04beb098400da5774c810547fd46c05a73e397f3c82d1"
+        );
+    }
+
+    #[test]
+    fn digest_algorithm_negotiation() {
+        let mut headers = Headers::new();
+        headers.insert_type(Name::WWW_AUTHENTICATE, &challenge(Algorithm::MD5));
+        headers.insert_type(Name::WWW_AUTHENTICATE, &challenge(Algorithm::SHA256));
+        headers.insert_type(Name::WWW_AUTHENTICATE, &challenge(Algorithm::SHA512256));
+
+        // The topmost supported challenge is used by default
+        let response = respond(DigestAuthenticator::default(), &headers);
+        assert_eq!(response.algorithm, Algorithm::MD5);
+
+        let authenticator = DigestAuthenticator {
+            prefer_strongest_algorithm: true,
+            ..Default::default()
+        };
+
+        let response = respond(authenticator, &headers);
+        assert_eq!(response.algorithm, Algorithm::SHA512256);
+
+        let authenticator = DigestAuthenticator {
+            reject_md5: true,
+            ..Default::default()
+        };
+
+        let response = respond(authenticator, &headers);
+        assert_eq!(response.algorithm, Algorithm::SHA256);
+    }
+
+    #[test]
+    fn digest_auth_int_verify() {
+        let credentials = DigestCredentials::new("user123", "password123");
+
+        let mut headers = Headers::new();
+        headers.insert_type(
+            Name::WWW_AUTHENTICATE,
+            &AuthChallenge::Digest(DigestChallenge {
+                realm: "example.org".into(),
+                domain: None,
+                nonce: "YWmh5GFpoLjiTDCA1hTSSygkgdj99aHE".into(),
+                opaque: None,
+                stale: false,
+                algorithm: Algorithm::SHA256Sess,
+                qop: vec![QopOption::AuthInt],
+                userhash: false,
+                other: vec![],
+            }),
+        );
+
+        let uri: SipUri = "sip:bob@example.org".parse().unwrap();
+
+        let line = RequestLine {
+            method: Method::INVITE,
+            uri: Box::new(uri),
+        };
+
+        let request_headers = Headers::new();
+
+        let parts = |body: &'static [u8]| RequestParts {
+            line: &line,
+            headers: &request_headers,
+            body,
+        };
+
+        let mut session = UacAuthSession::<DigestAuthenticator>::default();
+        session
+            .handle_authenticate(&headers, &test_credentials(), parts(b"v=0\r\n"))
+            .unwrap();
+
+        // The cached response is reused for requests with a different body
+        for (nc, body, other_body) in [(1, b"v=0\r\n", b"v=1\r\n"), (2, b"v=1\r\n", b"v=0\r\n")] {
+            let mut response_headers = Headers::new();
+            session.authorize_request(&line, &mut response_headers, body);
+
+            let Ok(AuthResponse::Digest(response)) =
+                response_headers.get::<AuthResponse>(Name::AUTHORIZATION)
+            else {
+                panic!("Expected digest");
+            };
+
+            assert_eq!(response.qop_response.as_ref().unwrap().nc, nc);
+            assert!(credentials.verify(&response, parts(body)).unwrap());
+
+            // The body is protected by auth-int
+            assert!(!credentials.verify(&response, parts(other_body)).unwrap());
+
+            let other = DigestCredentials::new("user123", "password456");
+            assert!(!other.verify(&response, parts(body)).unwrap());
+        }
+    }
+
+    #[test]
+    fn digest_verify_uri() {
+        let credentials = DigestCredentials::new("user123", "password123");
+
+        let mut headers = Headers::new();
+        headers.insert_type(Name::WWW_AUTHENTICATE, &challenge(Algorithm::SHA256));
+
+        let line = |uri: &str| RequestLine {
+            method: Method::REGISTER,
+            uri: Box::new(uri.parse::<SipUri>().unwrap()),
+        };
+
+        let request_headers = Headers::new();
+
+        let response = respond(DigestAuthenticator::default(), &headers);
+        assert_eq!(response.uri, "sip:example.org");
+
+        let verify = |line: &RequestLine| {
+            credentials
+                .verify(
+                    &response,
+                    RequestParts {
+                        line,
+                        headers: &request_headers,
+                        body: &[],
+                    },
+                )
+                .unwrap()
+        };
+
+        assert!(verify(&line("sip:example.org")));
+
+        // The response must not be replayed against another Request-URI
+        assert!(!verify(&line("sip:other.example.org")));
+    }
+
     #[test]
     fn digest_challenge_and_response() {
         let credentials = test_credentials();
@@ -479,7 +764,7 @@ mod test {
             .unwrap();
 
         let mut response_headers = Headers::new();
-        session.authorize_request(&mut response_headers);
+        session.authorize_request(&line, &mut response_headers, &[]);
 
         let response = response_headers
             .get::<AuthResponse>(Name::AUTHORIZATION)
@@ -515,7 +800,7 @@ mod test {
         };
 
         let mut response_headers = Headers::new();
-        session.authorize_request(&mut response_headers);
+        session.authorize_request(&line, &mut response_headers, &[]);
 
         let response = response_headers
             .get::<AuthResponse>(Name::AUTHORIZATION)
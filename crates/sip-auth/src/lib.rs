@@ -3,6 +3,7 @@ use digest::{DigestAuthenticator, DigestCredentials};
 use sip_types::header::typed::{AuthChallenge, AuthResponse};
 use sip_types::msg::RequestLine;
 use sip_types::{Headers, Name};
+use std::cmp::Reverse;
 use std::collections::HashMap;
 
 pub mod digest;
@@ -90,7 +91,25 @@ pub trait UacAuthenticator: Default + Send + Sync {
     ) -> Result<AuthResponse, Error>;
 
     /// Gets called when a header gets used/reused for a request.
-    fn on_authorize_request(&mut self, response: &mut ResponseEntry);
+    ///
+    /// `line` and `body` are those of the request the header is added to, which may differ from
+    /// the request that was challenged.
+    fn on_authorize_request(
+        &mut self,
+        response: &mut ResponseEntry,
+        line: &RequestLine,
+        body: &[u8],
+    );
+
+    /// Return the priority of a challenge. When a realm is challenged multiple times
+    /// the challenges are tried in order of descending priority.
+    ///
+    /// All challenges have the same priority by default, so the topmost supported challenge is
+    /// used.
+    fn challenge_priority(&self, challenge: &AuthChallenge) -> u32 {
+        let _ = challenge;
+        0
+    }
 }
 
 /// Contains a list of authentication challenges that want to authenticate the same realm.
@@ -150,7 +169,7 @@ impl<A: UacAuthenticator> UacAuthSession<A> {
 
         let mut failed_realms = vec![];
 
-        'outer: for challenged_realm in challenged_realms {
+        'outer: for mut challenged_realm in challenged_realms {
             let credentials = if let Some(credentials) =
                 credential_store.get_for_realm(&challenged_realm.realm)
             {
@@ -160,6 +179,11 @@ impl<A: UacAuthenticator> UacAuthSession<A> {
                 continue;
             };
 
+            // Stable sort, challenges with the same priority keep their order
+            challenged_realm.challenges.sort_by_key(|(_, challenge)| {
+                Reverse(self.authenticator.challenge_priority(challenge))
+            });
+
             for (is_proxy, challenge) in challenged_realm.challenges {
                 let result = self.authenticator.handle_challenge(
                     &self.responses,
@@ -218,8 +242,9 @@ impl<A: UacAuthenticator> UacAuthSession<A> {
         Ok(())
     }
 
-    /// Apply the generated authentication headers to the provided `headers`
-    pub fn authorize_request(&mut self, headers: &mut Headers) {
+    /// Apply the generated authentication headers to the `headers` of the request with the given
+    /// `line` and `body`
+    pub fn authorize_request(&mut self, line: &RequestLine, headers: &mut Headers, body: &[u8]) {
         for entry in &mut self.responses {
             let name = if entry.is_proxy {
                 Name::PROXY_AUTHORIZATION
@@ -227,7 +252,7 @@ impl<A: UacAuthenticator> UacAuthSession<A> {
                 Name::AUTHORIZATION
             };
 
-            self.authenticator.on_authorize_request(entry);
+            self.authenticator.on_authorize_request(entry, line, body);
 
             entry.use_count += 1;
 
@@ -1,5 +1,6 @@
 use crate::transaction::{ClientInvTsx, ClientTsx, ServerInvTsx, ServerTsx, TsxKey};
 use crate::transaction::{Transactions, TsxMessage};
+use crate::transport::resolver::{DnsResolver, Resolver, ServerEntry};
 use crate::transport::{
     Direction, Factory, OutgoingParts, OutgoingRequest, OutgoingResponse, ReceivedMessage,
     TargetTransportInfo, TpHandle, Transports, TransportsBuilder,
@@ -21,11 +22,15 @@ use std::mem::take;
 use std::net::{IpAddr, SocketAddr};
 use std::ops::Index;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{fmt, io};
 use stun_types::parse::ParsedMessage;
 use tokio::sync::broadcast;
 use tracing::Instrument;
 
+/// Time to wait for the response to a CRLF keep-alive (RFC5626 Section 4.4.1)
+const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// The endpoint is the centerpiece of the sip stack. It contains all information about the
 /// application and a stack of layered modules which build the logic of SIP applications and
 /// its extensions.
@@ -141,6 +146,27 @@ impl Endpoint {
         self.transports().select(self, uri).await
     }
 
+    /// Resolve the target of the uri into a list of servers, in the order they should be tried
+    pub async fn resolve(&self, uri: &dyn Uri) -> Result<Vec<ServerEntry>> {
+        Ok(self.transports().resolve(uri).await?)
+    }
+
+    /// Find or create a suitable transport to contact a single `server` returned by
+    /// [`Endpoint::resolve`], used to fail over between the servers of an uri manually
+    pub async fn select_transport_for_server(
+        &self,
+        uri: &dyn Uri,
+        server: &ServerEntry,
+    ) -> Result<(TpHandle, SocketAddr)> {
+        self.transports()
+            .select_for_server(self, uri, server)
+            .await
+            .map(|transport| (transport, server.address))
+            .ok_or_else(|| {
+                io::Error::other(format!("Failed to connect to {}", server.address)).into()
+            })
+    }
+
     /// Takes a request and converts it into an `Outgoing`.
     /// To do so it calculates the destination and retrieves a suitable transport
     pub async fn create_outgoing(
@@ -189,7 +215,7 @@ impl Endpoint {
             )
             .map_err(|e| {
                 // wrap
-                io::Error::new(io::ErrorKind::Other, e)
+                io::Error::other(e)
             })?;
 
             buffer.extend_from_slice(&message.msg.body);
@@ -233,7 +259,7 @@ impl Endpoint {
             )
             .map_err(|e| {
                 // wrap
-                io::Error::new(io::ErrorKind::Other, e)
+                io::Error::other(e)
             })?;
 
             buffer.extend_from_slice(&message.msg.body);
@@ -469,6 +495,40 @@ impl Endpoint {
             .await
     }
 
+    /// Send a keep-alive to `target` to detect if the flow to it has failed
+    /// ([RFC5626](https://www.rfc-editor.org/rfc/rfc5626.html#section-4.4))
+    ///
+    /// Reliable transports send a double CRLF and wait for the single CRLF response.
+    /// Other transports send a STUN binding request and return the mapped address, which
+    /// changes when the flow has failed (e.g. because of a NAT rebinding).
+    pub async fn keep_alive(
+        &self,
+        transport: &TpHandle,
+        target: SocketAddr,
+    ) -> io::Result<Option<SocketAddr>> {
+        if !transport.reliable() {
+            return match self.discover_public_address(target, transport).await {
+                Ok(address) => Ok(Some(address)),
+                Err(StunError::Io(e)) => Err(e),
+                Err(e) => Err(io::Error::other(e)),
+            };
+        }
+
+        let response = self
+            .transports()
+            .expect_keep_alive_response(&transport.key());
+
+        transport.send(b"\r\n\r\n", target).await?;
+
+        match tokio::time::timeout(KEEP_ALIVE_TIMEOUT, response).await {
+            Ok(Ok(())) => Ok(None),
+            _ => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no keep-alive response received",
+            )),
+        }
+    }
+
     pub(crate) fn transactions(&self) -> &Transactions {
         &self.inner.transactions
     }
@@ -499,6 +559,7 @@ pub struct EndpointBuilder {
     allow: Vec<Allow>,
     supported: Vec<Supported>,
 
+    parser: Parser,
     transports: TransportsBuilder,
     layer: Vec<Box<dyn Layer>>,
 }
@@ -518,6 +579,7 @@ impl EndpointBuilder {
             accept: vec![],
             allow: vec![],
             supported: vec![],
+            parser: Default::default(),
             transports: Default::default(),
             layer: Default::default(),
         }
@@ -560,7 +622,23 @@ impl EndpointBuilder {
     ///
     /// Uses the system config by default.
     pub fn set_dns_resolver(&mut self, dns_resolver: trust_dns_resolver::TokioAsyncResolver) {
-        self.transports.set_dns_resolver(dns_resolver)
+        self.set_resolver(DnsResolver::new(dns_resolver))
+    }
+
+    /// Set the [`Resolver`] used to resolve URIs into servers to contact.
+    ///
+    /// Uses a [`DnsResolver`] with the system config by default.
+    pub fn set_resolver<R: Resolver>(&mut self, resolver: R) {
+        self.transports.set_resolver(Box::new(resolver))
+    }
+
+    /// Set the [`Parser`] used to parse incoming messages.
+    ///
+    /// By default recoverable deviations from the SIP grammar (e.g. malformed header lines) are
+    /// skipped and recorded in [`MessageTpInfo::deviations`](crate::transport::MessageTpInfo::deviations).
+    /// Enable [`Parser::strict`] to reject such messages instead.
+    pub fn set_parser(&mut self, parser: Parser) {
+        self.parser = parser;
     }
 
     /// Add a implementation of [`Layer`] to the endpoint.
@@ -601,7 +679,7 @@ impl EndpointBuilder {
         let inner = Inner {
             allow: take(&mut self.allow),
             supported: take(&mut self.supported),
-            parser: Default::default(),
+            parser: self.parser,
             transports: self.transports.build(),
             transactions: Default::default(),
             layer,
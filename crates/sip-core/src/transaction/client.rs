@@ -58,6 +58,26 @@ impl ClientTsx {
         );
 
         request.msg.headers.insert_named_front(&via);
+
+        Self::send_outgoing(registration, request).await
+    }
+
+    /// Internal: Used by [`ClientInvTsx::cancel`](super::ClientInvTsx::cancel) to send a
+    /// request which already contains the Via header of the transaction
+    pub(crate) async fn send_cancel(
+        endpoint: Endpoint,
+        request: OutgoingRequest,
+        tsx_key: TsxKey,
+    ) -> Result<Self> {
+        let registration = TsxRegistration::create(endpoint, tsx_key);
+
+        Self::send_outgoing(registration, request).await
+    }
+
+    async fn send_outgoing(
+        registration: TsxRegistration,
+        mut request: OutgoingRequest,
+    ) -> Result<Self> {
         registration
             .endpoint
             .send_outgoing_request(&mut request)
@@ -1,12 +1,12 @@
 use super::consts::T1;
 use super::key::TsxKey;
-use super::{TsxRegistration, TsxResponse};
+use super::{ClientTsx, TsxRegistration, TsxResponse};
 use crate::error::Error;
 use crate::transport::{OutgoingParts, OutgoingRequest, TargetTransportInfo};
 use crate::Result;
 use crate::{Endpoint, Request};
 use bytes::Bytes;
-use sip_types::header::typed::CSeq;
+use sip_types::header::typed::{CSeq, Reason, Via};
 use sip_types::header::HeaderError;
 use sip_types::msg::RequestLine;
 use sip_types::{CodeKind, Headers, Method, Name};
@@ -19,7 +19,6 @@ use tokio::time::{timeout, timeout_at};
 /// that the peer has received the request, as the transaction is also responsible
 /// for retransmitting the original request until a response is received or the
 /// timeout is triggered.
-// TODO REMOVE TIMEOUT WHEN a provisional response has been received
 #[must_use]
 #[derive(Debug)]
 pub struct ClientInvTsx {
@@ -88,6 +87,38 @@ impl ClientInvTsx {
         &self.request
     }
 
+    /// Cancel the pending INVITE request ([RFC3261 Section 9.1](https://datatracker.ietf.org/doc/html/rfc3261#section-9.1))
+    ///
+    /// A CANCEL request should only be sent after a provisional response has been received.
+    /// The returned transaction receives the response to the CANCEL request, while this
+    /// transaction will receive the final response to the INVITE (usually 487).
+    ///
+    /// Returns `None` if a final response has already been received.
+    pub async fn cancel(&self) -> Result<Option<ClientTsx>> {
+        self.cancel_with_reason(&[]).await
+    }
+
+    /// Cancel the pending INVITE request, see [`ClientInvTsx::cancel`]
+    ///
+    /// The given [`Reason`]s are added to the CANCEL request ([RFC3326](https://datatracker.ietf.org/doc/html/rfc3326)).
+    pub async fn cancel_with_reason(&self, reason: &[Reason]) -> Result<Option<ClientTsx>> {
+        let registration = match (&self.registration, &self.state) {
+            (Some(registration), State::Init | State::Proceeding) => registration,
+            _ => return Ok(None),
+        };
+
+        let cancel = create_cancel(&self.request, reason)?;
+
+        let transaction = ClientTsx::send_cancel(
+            registration.endpoint.clone(),
+            cancel,
+            registration.tsx_key.with_method(&Method::CANCEL),
+        )
+        .await?;
+
+        Ok(Some(transaction))
+    }
+
     /// Receive one or more responses.
     ///
     /// The return type differs from [`ClientTsx::receive`](super::ClientTsx::receive)
@@ -125,12 +156,17 @@ impl ClientInvTsx {
                     }
                 }
             }
-            State::Init | State::Proceeding => {
+            State::Init => {
                 match timeout_at(self.timeout.into(), registration.receive_response()).await {
                     Ok(msg) => self.handle_msg(msg).await,
                     Err(_) => Err(Error::RequestTimedOut),
                 }
             }
+            State::Proceeding => {
+                // Timer B no longer applies, the TU decides how long to wait for a final response
+                let msg = registration.receive_response().await;
+                self.handle_msg(msg).await
+            }
             State::Accepted => {
                 match timeout_at(self.timeout.into(), registration.receive_response()).await {
                     Ok(msg) => Ok(Some(msg)),
@@ -196,9 +232,45 @@ fn create_ack(
 ) -> Result<OutgoingRequest, HeaderError> {
     let mut headers = Headers::with_capacity(5);
 
-    request.msg.headers.clone_into(&mut headers, Name::VIA)?;
-    request.msg.headers.clone_into(&mut headers, Name::FROM)?;
     response.headers.clone_into(&mut headers, Name::TO)?;
+
+    let _ = request.msg.headers.clone_into(&mut headers, Name::ROUTE);
+
+    create_hop_by_hop(request, Method::ACK, headers)
+}
+
+fn create_cancel(
+    request: &OutgoingRequest,
+    reason: &[Reason],
+) -> Result<OutgoingRequest, HeaderError> {
+    let mut headers = Headers::with_capacity(6);
+
+    request.msg.headers.clone_into(&mut headers, Name::TO)?;
+
+    let _ = request.msg.headers.clone_into(&mut headers, Name::ROUTE);
+    let _ = request
+        .msg
+        .headers
+        .clone_into(&mut headers, Name::MAX_FORWARDS);
+
+    for reason in reason {
+        headers.insert_named(reason);
+    }
+
+    create_hop_by_hop(request, Method::CANCEL, headers)
+}
+
+/// Create an ACK or CANCEL request which belongs to the INVITE transaction,
+/// it must only contain the top Via header of the INVITE request
+fn create_hop_by_hop(
+    request: &OutgoingRequest,
+    method: Method,
+    mut headers: Headers,
+) -> Result<OutgoingRequest, HeaderError> {
+    let via: Vec<Via> = request.msg.headers.get_named()?;
+
+    headers.insert_named_front(&via[0]);
+    request.msg.headers.clone_into(&mut headers, Name::FROM)?;
     request
         .msg
         .headers
@@ -208,13 +280,13 @@ fn create_ack(
 
     headers.insert_named(&CSeq {
         cseq: cseq.cseq,
-        method: Method::ACK,
+        method: method.clone(),
     });
 
     Ok(OutgoingRequest {
         msg: Request {
             line: RequestLine {
-                method: Method::ACK,
+                method,
                 uri: request.msg.line.uri.clone(),
             },
             headers,
@@ -97,6 +97,20 @@ impl TsxKey {
         }))
     }
 
+    /// Returns the key of the client transaction with the same branch but a different method.
+    ///
+    /// Used to match the responses of a CANCEL request to its own transaction.
+    pub(crate) fn with_method(&self, method: &Method) -> Self {
+        let mut key = self.clone();
+
+        match &mut key.0 {
+            Repr::RFC3261(repr) => repr.method = filter_method(method),
+            Repr::RFC2543(repr) => repr.method = filter_method(method),
+        }
+
+        key
+    }
+
     #[inline]
     pub fn branch(&self) -> &BytesStr {
         match &self.0 {
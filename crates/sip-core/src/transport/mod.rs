@@ -1,11 +1,11 @@
 use self::managed::{DropNotifier, ManagedTransportState, MangedTransport, RefOwner, WeakRefOwner};
-use self::resolver::ServerEntry;
+use self::resolver::{DnsResolver, Resolver, ServerEntry};
 use self::stun_user::StunUser;
 use crate::{Endpoint, Request, Response, Result};
 use bytes::Bytes;
 use parking_lot::Mutex;
-use sip_types::host::{Host, HostPort};
-use sip_types::msg::MessageLine;
+use sip_types::host::HostPort;
+use sip_types::msg::{Deviation, MessageLine};
 use sip_types::print::AppendCtx;
 use sip_types::uri::{Uri, UriInfo};
 use sip_types::Headers;
@@ -15,7 +15,7 @@ use std::mem::take;
 use std::net::SocketAddr;
 use std::ops::Deref;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use std::{fmt, io};
 use stun::StunEndpoint;
 use stun_types::parse::ParsedMessage;
@@ -23,7 +23,7 @@ use tokio::sync::oneshot;
 
 mod managed;
 mod parse;
-mod resolver;
+pub mod resolver;
 pub mod streaming;
 mod stun_user;
 
@@ -33,6 +33,8 @@ pub mod native_tls;
 pub mod rustls;
 pub mod tcp;
 pub mod udp;
+#[cfg(feature = "websocket")]
+pub mod ws;
 
 /// Abstraction over a transport factory.
 ///
@@ -203,6 +205,10 @@ pub struct MessageTpInfo {
 
     /// Handle to the transport the messages was received from
     pub transport: TpHandle,
+
+    /// Deviations from the SIP grammar tolerated when parsing the message.
+    /// Always empty if the endpoint's parser is strict.
+    pub deviations: Vec<Deviation>,
 }
 
 /// Message received directly from a transport
@@ -234,6 +240,7 @@ impl ReceivedMessage {
         line: MessageLine,
         headers: Headers,
         body: Bytes,
+        deviations: Vec<Deviation>,
     ) -> Self {
         Self {
             tp_info: MessageTpInfo {
@@ -241,6 +248,7 @@ impl ReceivedMessage {
                 source,
                 buffer,
                 transport,
+                deviations,
             },
             line,
             headers,
@@ -294,6 +302,9 @@ impl TpKey {
     }
 }
 
+/// Time after which connecting to a server is aborted and the next server is tried
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub(crate) struct Transports {
     unmanaged: Box<[TpHandle]>,
     factories: Box<[Arc<dyn Factory>]>,
@@ -302,28 +313,13 @@ pub(crate) struct Transports {
 
     stun: StunEndpoint<StunUser>,
 
-    dns_resolver: trust_dns_resolver::TokioAsyncResolver,
+    resolver: Box<dyn Resolver>,
+
+    /// Senders waiting for a keep-alive response on a transport
+    keep_alive_waiters: Mutex<HashMap<TpKey, Vec<oneshot::Sender<()>>>>,
 }
 
 impl Transports {
-    async fn resolve_host_port(&self, host: &Host, port: u16) -> io::Result<Vec<ServerEntry>> {
-        match host {
-            Host::IP6(ip) => Ok(vec![ServerEntry::from((*ip, port))]),
-            Host::IP4(ip) => Ok(vec![ServerEntry::from((*ip, port))]),
-            Host::Name(name) => resolver::resolve_host(&self.dns_resolver, name, port).await,
-        }
-    }
-
-    async fn resolve_uri(&self, info: &UriInfo<'_>) -> io::Result<Vec<ServerEntry>> {
-        let port = match info.host_port.port {
-            Some(port) => port,
-            None if info.secure => 5061,
-            None => 5060,
-        };
-
-        self.resolve_host_port(&info.host_port.host, port).await
-    }
-
     /// Will try to find or create a suitable transport the given Uri
     #[tracing::instrument(name = "select_transport", level = "trace", skip(self, endpoint))]
     pub(crate) async fn select(
@@ -333,36 +329,45 @@ impl Transports {
     ) -> Result<(TpHandle, SocketAddr)> {
         log::trace!("select transport for {:?}", uri);
 
-        let info = uri.info();
-
         // Resolve host_port to possible remote addresses
-        let servers = self.resolve_uri(&info).await?;
+        let servers = self.resolve(uri).await?;
 
         for server in servers {
-            // Search unmanaged ones (connectionless, e.g. udp)
-            if let Some(transport) = self.find_matching_unmanaged_transport(&info, &server) {
-                log::trace!("selected connectionless: {}", transport);
-
-                return Ok((transport.clone(), server.address));
-            }
-
-            // Search managed idling transports (connections, e.g. tcp / tls)
-            if let Some(found) = self.find_matching_idling_transport(&info, &server) {
+            if let Some(found) = self.select_for_server(endpoint, uri, &server).await {
                 return Ok((found, server.address));
             }
+        }
 
-            // No existing transport found, try and connect a new one
+        Err(io::Error::other(format!("Failed to select transport for {uri:?}")).into())
+    }
 
-            if let Some(found) = self.connect(endpoint, &info, &server).await {
-                return Ok((found, server.address));
-            }
+    pub(crate) async fn resolve(&self, uri: &dyn Uri) -> io::Result<Vec<ServerEntry>> {
+        self.resolver.resolve(&uri.info()).await
+    }
+
+    /// Find or create a suitable transport to contact a single resolved server of the given Uri
+    pub(crate) async fn select_for_server(
+        &self,
+        endpoint: &Endpoint,
+        uri: &dyn Uri,
+        server: &ServerEntry,
+    ) -> Option<TpHandle> {
+        let info = uri.info();
+
+        // Search unmanaged ones (connectionless, e.g. udp)
+        if let Some(transport) = self.find_matching_unmanaged_transport(&info, server) {
+            log::trace!("selected connectionless: {}", transport);
+
+            return Some(transport.clone());
+        }
+
+        // Search managed idling transports (connections, e.g. tcp / tls)
+        if let Some(found) = self.find_matching_idling_transport(&info, server) {
+            return Some(found);
         }
 
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("Failed to select transport for {uri:?}"),
-        )
-        .into())
+        // No existing transport found, try and connect a new one
+        self.connect(endpoint, &info, server).await
     }
 
     fn find_matching_unmanaged_transport(
@@ -378,9 +383,18 @@ impl Transports {
                 .map(|t| t.as_str() == tp.name())
                 .unwrap_or(true);
 
+            let transport_param_matches = uri
+                .transport
+                .as_deref()
+                .map(|t| tp.matches_transport_param(t))
+                .unwrap_or(true);
+
             let security_level_matches = uri.allows_security_level(tp.secure());
 
-            addr_familiy_supported && transport_name_matches && security_level_matches
+            addr_familiy_supported
+                && transport_name_matches
+                && transport_param_matches
+                && security_level_matches
         })
     }
 
@@ -399,6 +413,12 @@ impl Transports {
                 }
             }
 
+            if let Some(transport) = &uri.transport {
+                if !managed.transport.matches_transport_param(transport) {
+                    continue;
+                }
+            }
+
             // Check if the transport is connected to the server's address
             let remote = match managed.transport.direction() {
                 Direction::None => unreachable!(),
@@ -441,11 +461,23 @@ impl Transports {
                 }
             }
 
+            if let Some(transport) = &uri.transport {
+                if !factory.matches_transport_param(transport) {
+                    continue;
+                }
+            }
+
             if !uri.allows_security_level(factory.secure()) {
                 continue;
             }
 
-            match factory.create(endpoint.clone(), uri, server.address).await {
+            let create = factory.create(endpoint.clone(), uri, server.address);
+
+            // Give up on unresponsive servers to fail over to the next one
+            match tokio::time::timeout(CONNECT_TIMEOUT, create)
+                .await
+                .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))
+            {
                 Ok(transport) => {
                     log::debug!("created new transport {}", transport);
 
@@ -545,6 +577,28 @@ impl Transports {
         log::trace!("drop transport {:?}", tp_key);
 
         self.transports.lock().remove(tp_key);
+        self.keep_alive_waiters.lock().remove(tp_key);
+    }
+
+    /// Returns a receiver which completes when a keep-alive response is received on the transport
+    pub(crate) fn expect_keep_alive_response(&self, tp_key: &TpKey) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+
+        self.keep_alive_waiters
+            .lock()
+            .entry(*tp_key)
+            .or_default()
+            .push(tx);
+
+        rx
+    }
+
+    pub(crate) fn receive_keep_alive_response(&self, tp_key: &TpKey) {
+        let waiters = self.keep_alive_waiters.lock().remove(tp_key);
+
+        for waiter in waiters.into_iter().flatten() {
+            let _ = waiter.send(());
+        }
     }
 
     pub async fn receive_stun(
@@ -561,7 +615,7 @@ impl Transports {
 pub(crate) struct TransportsBuilder {
     unmanaged: Vec<TpHandle>,
     factories: Vec<Arc<dyn Factory>>,
-    dns_resolver: Option<trust_dns_resolver::TokioAsyncResolver>,
+    resolver: Option<Box<dyn Resolver>>,
 }
 
 impl TransportsBuilder {
@@ -575,17 +629,16 @@ impl TransportsBuilder {
         self.factories.push(factory);
     }
 
-    pub(crate) fn set_dns_resolver(
-        &mut self,
-        dns_resolver: trust_dns_resolver::TokioAsyncResolver,
-    ) {
-        self.dns_resolver = Some(dns_resolver);
+    pub(crate) fn set_resolver(&mut self, resolver: Box<dyn Resolver>) {
+        self.resolver = Some(resolver);
     }
 
     pub(crate) fn build(&mut self) -> Transports {
-        let dns_resolver = self.dns_resolver.take().unwrap_or_else(|| {
-            trust_dns_resolver::TokioAsyncResolver::tokio_from_system_conf()
-                .expect("Failed to create default system DNS resolver")
+        let resolver = self.resolver.take().unwrap_or_else(|| {
+            let dns_resolver = trust_dns_resolver::TokioAsyncResolver::tokio_from_system_conf()
+                .expect("Failed to create default system DNS resolver");
+
+            Box::new(DnsResolver::new(dns_resolver))
         });
 
         Transports {
@@ -593,7 +646,8 @@ impl TransportsBuilder {
             factories: take(&mut self.factories).into_boxed_slice(),
             stun: StunEndpoint::new(StunUser),
             transports: Default::default(),
-            dns_resolver,
+            resolver,
+            keep_alive_waiters: Default::default(),
         }
     }
 }
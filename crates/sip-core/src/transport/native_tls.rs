@@ -97,5 +97,5 @@ impl StreamingTransport for TlsStream<TcpStream> {
 }
 
 fn native_tls_err_to_io_err(e: native_tls::Error) -> io::Error {
-    io::Error::new(io::ErrorKind::Other, e)
+    io::Error::other(e)
 }
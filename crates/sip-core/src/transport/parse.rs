@@ -1,10 +1,8 @@
 use bytes::Bytes;
-use internal::Finish;
 use sip_types::header::typed::ContentLength;
-use sip_types::msg::{Line, MessageLine, PullParser};
+use sip_types::msg::{Deviation, MessageHead, MessageLine};
 use sip_types::parse::{ParseCtx, Parser};
 use sip_types::Headers;
-use std::str::from_utf8;
 use stun_types::is_stun_message;
 use stun_types::parse::ParsedMessage;
 
@@ -23,6 +21,7 @@ pub enum CompleteItem {
         headers: Headers,
         body: Bytes,
         buffer: Bytes,
+        deviations: Vec<Deviation>,
     },
 }
 
@@ -55,55 +54,28 @@ fn parse_complete_stun(bytes: &[u8]) -> Result<CompleteItem, Error> {
     Ok(CompleteItem::Stun(msg))
 }
 
-fn parse_complete_sip(parser_: Parser, bytes: &[u8]) -> Result<CompleteItem, Error> {
+fn parse_complete_sip(parser: Parser, bytes: &[u8]) -> Result<CompleteItem, Error> {
     let buffer = Bytes::copy_from_slice(bytes);
 
-    let mut parser = PullParser::new(&buffer, 0);
-
-    let mut message_line = None;
-    let mut headers = Headers::new();
-
-    for item in &mut parser {
-        let line = match item {
-            Ok(line) => line,
-            Err(_) => {
-                log::warn!("Incoming SIP messages is incomplete");
-                return Err(Error::FailedToParse);
-            }
-        };
-
-        let line = from_utf8(line).map_err(|_| {
-            log::warn!("Incoming SIP message contained invalid UTF8 in header line");
-            Error::FailedToParse
-        })?;
-
-        if message_line.is_none() {
-            let ctx = ParseCtx::new(&buffer, parser_);
-
-            match MessageLine::parse(ctx)(line) {
-                Ok((_, line)) => {
-                    message_line = Some(line);
-                }
-                Err(_) => {
-                    log::warn!(
-                        "Incoming SIP message contained invalid Request/Status Line: {:?}",
-                        line
-                    );
-                    return Err(Error::FailedToParse);
-                }
-            }
-        } else {
-            match Line::parse(&buffer, line).finish() {
-                Ok((_, line)) => headers.insert(line.name, line.value),
-                Err(e) => {
-                    log::error!("Incoming SIP message has malformed header line, {}", e);
-                    return Err(Error::FailedToParse);
-                }
-            }
+    let MessageHead {
+        line,
+        headers,
+        head_end,
+        deviations,
+    } = match MessageHead::parse(ParseCtx::new(&buffer, parser)) {
+        Ok(head) => head,
+        Err(e) => {
+            log::warn!("Failed to parse incoming SIP message head, {}", e);
+            return Err(Error::FailedToParse);
         }
-    }
+    };
 
-    let head_end = parser.head_end();
+    if !deviations.is_empty() {
+        log::debug!(
+            "Incoming SIP message deviates from the grammar, {:?}",
+            deviations
+        );
+    }
 
     // look for optional content-length header
     let body = match headers.get_named::<ContentLength>() {
@@ -129,9 +101,10 @@ fn parse_complete_sip(parser_: Parser, bytes: &[u8]) -> Result<CompleteItem, Err
     };
 
     Ok(CompleteItem::Sip {
-        line: message_line.ok_or(Error::FailedToParse)?,
+        line,
         headers,
         body,
         buffer,
+        deviations,
     })
 }
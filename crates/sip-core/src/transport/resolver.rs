@@ -1,15 +1,36 @@
+//! Resolution of SIP URIs into a list of servers to contact
+//! ([RFC3263](https://www.rfc-editor.org/rfc/rfc3263.html))
+
 use multimap::MultiMap;
+use parking_lot::Mutex;
+use rand::Rng;
+use sip_types::host::Host;
+use sip_types::uri::UriInfo;
+use std::collections::HashMap;
 use std::io;
 use std::net::{IpAddr, SocketAddr};
+use std::time::Instant;
 use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
 use trust_dns_resolver::proto::rr::rdata::{NAPTR, SRV};
 use trust_dns_resolver::proto::rr::{RData, RecordType};
 use trust_dns_resolver::{Name, TokioAsyncResolver};
 
-#[derive(Debug, Clone, Copy)]
-pub(super) struct ServerEntry {
-    pub(super) address: SocketAddr,
-    pub(super) transport: Option<Transport>,
+/// Resolves the target of a URI into a list of servers
+///
+/// The transport layer tries the servers in the returned order until a transport can be
+/// used or created for one of them. That way a failing server results in a failover to the next.
+#[async_trait::async_trait]
+pub trait Resolver: Send + Sync + 'static {
+    async fn resolve(&self, uri: &UriInfo<'_>) -> io::Result<Vec<ServerEntry>>;
+}
+
+/// A single resolved server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerEntry {
+    pub address: SocketAddr,
+
+    /// The transport the server must be contacted with, `None` if any transport can be used
+    pub transport: Option<Transport>,
 }
 
 impl<S> From<S> for ServerEntry
@@ -24,8 +45,8 @@ where
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub(super) enum Transport {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transport {
     /// SIP+D2U
     Udp,
     /// SIP+D2T
@@ -37,7 +58,7 @@ pub(super) enum Transport {
 }
 
 impl Transport {
-    pub(super) fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             Transport::Udp => "UDP",
             Transport::Tcp => "TCP",
@@ -46,6 +67,23 @@ impl Transport {
         }
     }
 
+    /// Map the `transport` parameter of a URI, `tcp` in a `sips:` URI means TLS
+    fn from_param(param: &str, secure: bool) -> Option<Self> {
+        if param.eq_ignore_ascii_case("udp") {
+            Some(Self::Udp)
+        } else if param.eq_ignore_ascii_case("tcp") && secure {
+            Some(Self::TlsOverTcp)
+        } else if param.eq_ignore_ascii_case("tcp") {
+            Some(Self::Tcp)
+        } else if param.eq_ignore_ascii_case("tls") {
+            Some(Self::TlsOverTcp)
+        } else if param.eq_ignore_ascii_case("sctp") {
+            Some(Self::Sctp)
+        } else {
+            None
+        }
+    }
+
     fn from_services(services: &[u8]) -> Option<Self> {
         match services {
             b"SIP+D2U" => Some(Self::Udp),
@@ -56,6 +94,19 @@ impl Transport {
         }
     }
 
+    fn srv_prefix(&self) -> &'static str {
+        match self {
+            Transport::Udp => "_sip._udp",
+            Transport::Tcp => "_sip._tcp",
+            Transport::TlsOverTcp => "_sips._tcp",
+            Transport::Sctp => "_sip._sctp",
+        }
+    }
+
+    fn secure(&self) -> bool {
+        matches!(self, Transport::TlsOverTcp)
+    }
+
     fn default_port(&self) -> u16 {
         match self {
             Transport::Udp => 5060,
@@ -66,56 +117,189 @@ impl Transport {
     }
 }
 
-#[tracing::instrument(err, skip(dns_resolver, uri_port))]
-pub(super) async fn resolve_host(
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    name: String,
+    port: Option<u16>,
+    transport: Option<Transport>,
+    secure: bool,
+}
+
+/// [`Resolver`] implementation using DNS NAPTR, SRV and A/AAAA records
+///
+/// Results are cached until the TTL of the records used expires.
+pub struct DnsResolver {
+    resolver: TokioAsyncResolver,
+    cache: Mutex<HashMap<CacheKey, Resolved>>,
+}
+
+impl DnsResolver {
+    pub fn new(resolver: TokioAsyncResolver) -> Self {
+        Self {
+            resolver,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Remove all cached results
+    pub fn clear_cache(&self) {
+        self.cache.lock().clear();
+    }
+}
+
+#[async_trait::async_trait]
+impl Resolver for DnsResolver {
+    async fn resolve(&self, uri: &UriInfo<'_>) -> io::Result<Vec<ServerEntry>> {
+        let transport = uri
+            .transport
+            .as_deref()
+            .and_then(|param| Transport::from_param(param, uri.secure));
+
+        let default_port = match transport {
+            Some(transport) => transport.default_port(),
+            None if uri.secure => 5061,
+            None => 5060,
+        };
+
+        let name = match &uri.host_port.host {
+            Host::IP6(ip) => {
+                let port = uri.host_port.port.unwrap_or(default_port);
+                return Ok(vec![ServerEntry {
+                    address: SocketAddr::new((*ip).into(), port),
+                    transport,
+                }]);
+            }
+            Host::IP4(ip) => {
+                let port = uri.host_port.port.unwrap_or(default_port);
+                return Ok(vec![ServerEntry {
+                    address: SocketAddr::new((*ip).into(), port),
+                    transport,
+                }]);
+            }
+            Host::Name(name) => name,
+        };
+
+        let key = CacheKey {
+            name: name.to_ascii_lowercase(),
+            port: uri.host_port.port,
+            transport,
+            secure: uri.secure,
+        };
+
+        if let Some(resolved) = self.cache.lock().get(&key) {
+            if resolved.valid_until > Instant::now() {
+                return Ok(resolved.entries.clone());
+            }
+        }
+
+        let resolved = resolve_host(
+            &self.resolver,
+            name,
+            uri.host_port.port,
+            default_port,
+            transport,
+            uri.secure,
+        )
+        .await?;
+
+        let entries = resolved.entries.clone();
+
+        let mut cache = self.cache.lock();
+        let now = Instant::now();
+        cache.retain(|_, resolved| resolved.valid_until > now);
+        cache.insert(key, resolved);
+
+        Ok(entries)
+    }
+}
+
+/// Resolved server entries and how long they are valid
+#[derive(Debug)]
+struct Resolved {
+    entries: Vec<ServerEntry>,
+    valid_until: Instant,
+}
+
+impl Resolved {
+    fn extend(&mut self, valid_until: Instant, entries: impl IntoIterator<Item = ServerEntry>) {
+        self.valid_until = self.valid_until.min(valid_until);
+        self.entries.extend(entries);
+    }
+}
+
+#[tracing::instrument(err, skip(dns_resolver, default_port))]
+async fn resolve_host(
     dns_resolver: &TokioAsyncResolver,
     name: &str,
-    uri_port: u16,
-) -> io::Result<Vec<ServerEntry>> {
+    port: Option<u16>,
+    default_port: u16,
+    transport: Option<Transport>,
+    secure: bool,
+) -> io::Result<Resolved> {
     log::debug!("Resolving hostname {:?}", name);
 
     let name = Name::from_utf8(name)?;
 
-    let mut entries: Vec<ServerEntry> = vec![];
+    let mut resolved = Resolved {
+        entries: vec![],
+        // Lowered to the smallest TTL of the records used
+        valid_until: Instant::now() + std::time::Duration::from_secs(86400),
+    };
+
+    // An explicit port means the host must be resolved using A/AAAA records
+    if let Some(port) = port {
+        resolve_a_records(dns_resolver, name.clone(), transport, port, &mut resolved).await?;
+    } else {
+        // First find NAPTR DNS records, unless the transport is already known
+        if transport.is_none() {
+            resolve_naptr_records(dns_resolver, name.clone(), secure, &mut resolved).await?;
+        }
 
-    // First find NAPTR DNS records
-    resolve_naptr_records(dns_resolver, name.clone(), &mut entries).await?;
+        // If there are none, look for SRV entries directly
+        if resolved.entries.is_empty() {
+            use Transport::*;
 
-    // If there are none, look for SRV entries directly
-    if entries.is_empty() {
-        use Transport::*;
+            // Try all transports this library should support
+            let transports = match transport {
+                Some(transport) => vec![transport],
+                None if secure => vec![TlsOverTcp],
+                None => vec![TlsOverTcp, Udp, Tcp],
+            };
 
-        // Try all transports this library should support
-        let records = [
-            (Name::from_utf8(format!("_sips._tcp.{name}"))?, TlsOverTcp),
-            (Name::from_utf8(format!("_sip._udp.{name}"))?, Udp),
-            (Name::from_utf8(format!("_sip._tcp.{name}"))?, Tcp),
-        ];
+            for transport in transports {
+                let name = Name::from_utf8(format!("{}.{name}", transport.srv_prefix()))?;
 
-        for (name, transport) in records {
-            resolve_srv_records(dns_resolver, name, Some(transport), &mut entries).await?;
+                resolve_srv_records(dns_resolver, name, Some(transport), &mut resolved).await?;
+            }
         }
-    }
 
-    // Neither NAPTR nor SRV entries exist - just resolve A/AAAA records
-    if entries.is_empty() {
-        resolve_a_records(dns_resolver, name.clone(), None, uri_port, &mut entries).await?;
+        // Neither NAPTR nor SRV entries exist - just resolve A/AAAA records
+        if resolved.entries.is_empty() {
+            resolve_a_records(
+                dns_resolver,
+                name.clone(),
+                transport,
+                default_port,
+                &mut resolved,
+            )
+            .await?;
+        }
     }
 
-    if entries.is_empty() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("No DNS records for host '{name}' found"),
-        ));
+    if resolved.entries.is_empty() {
+        return Err(io::Error::other(format!(
+            "No DNS records for host '{name}' found"
+        )));
     }
 
-    Ok(entries)
+    Ok(resolved)
 }
 
 async fn resolve_naptr_records(
     dns_resolver: &TokioAsyncResolver,
     name: Name,
-    entries: &mut Vec<ServerEntry>,
+    secure: bool,
+    resolved: &mut Resolved,
 ) -> Result<(), ResolveError> {
     log::debug!("Resolving NAPTR records for \"{name}\"");
 
@@ -127,7 +311,9 @@ async fn resolve_naptr_records(
         return Ok(());
     };
 
-    // Order records by 'order' field
+    resolved.valid_until = resolved.valid_until.min(lookup.valid_until());
+
+    // Order records by 'order' and 'preference' field
     let mut naptr_records: Vec<&NAPTR> = lookup
         .record_iter()
         .filter_map(|record| match record.data()? {
@@ -138,7 +324,7 @@ async fn resolve_naptr_records(
             }
         })
         .collect();
-    naptr_records.sort_unstable_by_key(|naptr| naptr.order());
+    naptr_records.sort_unstable_by_key(|naptr| (naptr.order(), naptr.preference()));
 
     log::debug!("Got {} NAPTR records for \"{name}\"", naptr_records.len());
 
@@ -153,13 +339,18 @@ async fn resolve_naptr_records(
             continue;
         };
 
+        // SIPS URIs must only be resolved to secure transports
+        if secure && !transport.secure() {
+            continue;
+        }
+
         match record.flags() {
             b"s" => {
                 resolve_srv_records(
                     dns_resolver,
                     record.replacement().clone(),
                     Some(transport),
-                    entries,
+                    resolved,
                 )
                 .await?
             }
@@ -169,7 +360,7 @@ async fn resolve_naptr_records(
                     record.replacement().clone(),
                     Some(transport),
                     transport.default_port(),
-                    entries,
+                    resolved,
                 )
                 .await?;
             }
@@ -195,7 +386,7 @@ async fn resolve_srv_records(
     dns_resolver: &TokioAsyncResolver,
     name: Name,
     transport: Option<Transport>,
-    entries: &mut Vec<ServerEntry>,
+    resolved: &mut Resolved,
 ) -> Result<(), ResolveError> {
     log::debug!("Resolving SRV records for \"{name}\"");
 
@@ -205,15 +396,19 @@ async fn resolve_srv_records(
         return Ok(());
     };
 
-    // Order SRV records by priority
-    let mut srv_records: Vec<&SRV> = lookup
+    resolved.valid_until = resolved.valid_until.min(lookup.valid_until());
+
+    // Order SRV records by priority and weight
+    let srv_records: Vec<&SRV> = lookup
         .record_iter()
         .filter_map(|record| match record.data()? {
             RData::SRV(srv) => Some(srv),
             _ => None,
         })
         .collect();
-    srv_records.sort_unstable_by_key(|srv| srv.priority());
+    let srv_records = order_by_priority_and_weight(srv_records, &mut rand::thread_rng(), |srv| {
+        (srv.priority(), srv.weight())
+    });
 
     log::debug!("Got {} SRV records for \"{name}\"", srv_records.len());
 
@@ -232,24 +427,66 @@ async fn resolve_srv_records(
         let port = record.port();
 
         if let Some(ips) = ip_records.get_vec(target) {
-            entries.extend(ips.iter().map(|ip| ServerEntry {
+            resolved.entries.extend(ips.iter().map(|ip| ServerEntry {
                 address: SocketAddr::new(*ip, port),
                 transport,
             }));
         } else {
-            resolve_a_records(dns_resolver, target.clone(), transport, port, entries).await?;
+            resolve_a_records(dns_resolver, target.clone(), transport, port, resolved).await?;
         };
     }
 
     Ok(())
 }
 
+/// Order records by ascending priority and randomly by weight within the same priority
+/// ([RFC2782](https://www.rfc-editor.org/rfc/rfc2782.html))
+fn order_by_priority_and_weight<T>(
+    mut records: Vec<T>,
+    rng: &mut impl Rng,
+    priority_and_weight: impl Fn(&T) -> (u16, u16),
+) -> Vec<T> {
+    // Records with a weight of 0 are placed first, giving them a very small chance to be chosen
+    records.sort_by_key(|record| priority_and_weight(record));
+
+    let mut ordered = Vec::with_capacity(records.len());
+
+    while !records.is_empty() {
+        let (priority, _) = priority_and_weight(&records[0]);
+
+        let same_priority = records
+            .iter()
+            .take_while(|record| priority_and_weight(record).0 == priority)
+            .count();
+
+        let total_weight: u32 = records[..same_priority]
+            .iter()
+            .map(|record| u32::from(priority_and_weight(record).1))
+            .sum();
+
+        let chosen = rng.gen_range(0..=total_weight);
+
+        let mut running_weight = 0;
+        let index = records[..same_priority]
+            .iter()
+            .position(|record| {
+                running_weight += u32::from(priority_and_weight(record).1);
+                running_weight >= chosen
+            })
+            .unwrap_or(0);
+
+        ordered.push(records.remove(index));
+    }
+
+    ordered
+}
+
 async fn resolve_a_records(
     dns_resolver: &TokioAsyncResolver,
     name: Name,
     transport: Option<Transport>,
     port: u16,
-    entries: &mut Vec<ServerEntry>,
+    resolved: &mut Resolved,
 ) -> Result<(), ResolveError> {
     log::debug!("Resolving A/AAAA records for \"{name}\"");
 
@@ -263,10 +500,13 @@ async fn resolve_a_records(
         lookup.as_lookup().records().len()
     );
 
-    entries.extend(lookup.iter().map(|ip| ServerEntry {
-        address: SocketAddr::new(ip, port),
-        transport,
-    }));
+    resolved.extend(
+        lookup.valid_until(),
+        lookup.iter().map(|ip| ServerEntry {
+            address: SocketAddr::new(ip, port),
+            transport,
+        }),
+    );
 
     Ok(())
 }
@@ -279,3 +519,45 @@ fn filter_no_records<T>(e: Result<T, ResolveError>) -> Result<Option<T>, Resolve
         Err(e) => Err(e),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn transport_param() {
+        assert_eq!(Transport::from_param("UDP", false), Some(Transport::Udp));
+        assert_eq!(Transport::from_param("tcp", false), Some(Transport::Tcp));
+        assert_eq!(
+            Transport::from_param("tcp", true),
+            Some(Transport::TlsOverTcp)
+        );
+        assert_eq!(Transport::from_param("ws", false), None);
+    }
+
+    #[test]
+    fn srv_ordering() {
+        let records = vec![(20, 0, "d"), (10, 60, "a"), (10, 40, "b"), (10, 0, "c")];
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut first_a = 0;
+
+        for _ in 0..1000 {
+            let ordered = order_by_priority_and_weight(records.clone(), &mut rng, |r| (r.0, r.1));
+            let names: Vec<_> = ordered.iter().map(|r| r.2).collect();
+
+            // Lower priorities are always tried first
+            assert_eq!(names.len(), 4);
+            assert_eq!(names[3], "d");
+
+            if names[0] == "a" {
+                first_a += 1;
+            }
+        }
+
+        // "a" has a weight of 60 out of 100 and should be picked first roughly 60% of the time
+        assert!((500..700).contains(&first_a), "{first_a}");
+    }
+}
@@ -1,16 +1,46 @@
+//! TLS transport backed by rustls
+//!
+//! [`TlsConnector`] and [`TlsAcceptor`] can be used directly with a custom rustls config,
+//! or created from a [`RustlsConnectorBuilder`] and [`RustlsAcceptorBuilder`].
+
 use super::streaming::{
     StreamingFactory, StreamingListener, StreamingListenerBuilder, StreamingTransport,
 };
+use rustls::client::{ServerCertVerifier, WebPkiVerifier};
+use rustls::server::{
+    AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, ClientCertVerifier,
+};
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig};
 use sip_types::{host::Host, uri::UriInfo};
 use std::convert::TryFrom;
 use std::io;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
 use tokio_rustls::rustls::ServerName;
 use tokio_rustls::{TlsAcceptor, TlsConnector, TlsStream};
 
 // ==== Connector
 
+fn server_name(uri_info: &UriInfo) -> io::Result<ServerName> {
+    match uri_info.host_port.host {
+        Host::Name(ref name) => ServerName::try_from(name.as_str()).map_err(io::Error::other),
+        Host::IP4(ip) => Ok(ServerName::IpAddress(ip.into())),
+        Host::IP6(ip) => Ok(ServerName::IpAddress(ip.into())),
+    }
+}
+
+async fn connect<A: ToSocketAddrs>(
+    connector: &TlsConnector,
+    server_name: ServerName,
+    addr: A,
+) -> io::Result<TlsStream<TcpStream>> {
+    let stream = TcpStream::connect(addr).await?;
+    let stream = connector.connect(server_name, stream).await?;
+
+    Ok(TlsStream::Client(stream))
+}
+
 #[async_trait::async_trait]
 impl StreamingFactory for TlsConnector {
     type Transport = TlsStream<TcpStream>;
@@ -20,22 +50,168 @@ impl StreamingFactory for TlsConnector {
         uri_info: &UriInfo,
         addr: A,
     ) -> io::Result<Self::Transport> {
-        let server_name = match uri_info.host_port.host {
-            Host::Name(ref name) => ServerName::try_from(name.as_str())
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?,
-            Host::IP4(ip) => ServerName::IpAddress(ip.into()),
-            Host::IP6(ip) => ServerName::IpAddress(ip.into()),
+        connect(self, server_name(uri_info)?, addr).await
+    }
+}
+
+/// Builder for a [`RustlsConnector`]
+pub struct RustlsConnectorBuilder {
+    verifier: Arc<dyn ServerCertVerifier>,
+    client_cert: Option<(Vec<Certificate>, PrivateKey)>,
+    alpn_protocols: Vec<Vec<u8>>,
+    server_name: Option<ServerName>,
+    enable_sni: bool,
+}
+
+impl RustlsConnectorBuilder {
+    /// Verify the certificate chain of servers against the given trusted roots and the server name
+    pub fn new(roots: RootCertStore) -> Self {
+        Self::with_verifier(Arc::new(WebPkiVerifier::new(roots, None)))
+    }
+
+    /// Verify servers using a custom policy (e.g. certificate pinning) instead of the default
+    /// chain verification
+    pub fn with_verifier(verifier: Arc<dyn ServerCertVerifier>) -> Self {
+        Self {
+            verifier,
+            client_cert: None,
+            alpn_protocols: vec![],
+            server_name: None,
+            enable_sni: true,
+        }
+    }
+
+    /// Present the given certificate chain when the server requests client authentication
+    pub fn client_certificate(mut self, chain: Vec<Certificate>, key: PrivateKey) -> Self {
+        self.client_cert = Some((chain, key));
+        self
+    }
+
+    /// Set the protocols offered using ALPN, in order of preference
+    pub fn alpn_protocols(mut self, protocols: Vec<Vec<u8>>) -> Self {
+        self.alpn_protocols = protocols;
+        self
+    }
+
+    /// Use a fixed server name for SNI and verification instead of the host of the URI
+    ///
+    /// Useful for trunks which are addressed by IP but present a certificate for a domain.
+    pub fn server_name(mut self, server_name: ServerName) -> Self {
+        self.server_name = Some(server_name);
+        self
+    }
+
+    /// Send the server name indication extension, enabled by default
+    pub fn enable_sni(mut self, enable: bool) -> Self {
+        self.enable_sni = enable;
+        self
+    }
+
+    pub fn build(self) -> Result<RustlsConnector, rustls::Error> {
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(self.verifier);
+
+        let mut config = match self.client_cert {
+            Some((chain, key)) => builder.with_client_auth_cert(chain, key)?,
+            None => builder.with_no_client_auth(),
         };
 
-        let stream = TcpStream::connect(addr).await?;
-        let stream = self.connect(server_name, stream).await?;
+        config.alpn_protocols = self.alpn_protocols;
+        config.enable_sni = self.enable_sni;
+
+        Ok(RustlsConnector {
+            connector: TlsConnector::from(Arc::new(config)),
+            server_name: self.server_name,
+        })
+    }
+}
+
+/// TLS connector created by a [`RustlsConnectorBuilder`]
+pub struct RustlsConnector {
+    connector: TlsConnector,
+    server_name: Option<ServerName>,
+}
+
+#[async_trait::async_trait]
+impl StreamingFactory for RustlsConnector {
+    type Transport = TlsStream<TcpStream>;
+
+    async fn connect<A: ToSocketAddrs + Send>(
+        &self,
+        uri_info: &UriInfo,
+        addr: A,
+    ) -> io::Result<Self::Transport> {
+        let server_name = match &self.server_name {
+            Some(server_name) => server_name.clone(),
+            None => server_name(uri_info)?,
+        };
 
-        Ok(TlsStream::Client(stream))
+        connect(&self.connector, server_name, addr).await
     }
 }
 
 // ==== Listener
 
+/// Builder for a [`TlsAcceptor`]
+pub struct RustlsAcceptorBuilder {
+    chain: Vec<Certificate>,
+    key: PrivateKey,
+    client_verifier: Option<Arc<dyn ClientCertVerifier>>,
+    alpn_protocols: Vec<Vec<u8>>,
+}
+
+impl RustlsAcceptorBuilder {
+    /// Accept connections presenting the given certificate chain
+    pub fn new(chain: Vec<Certificate>, key: PrivateKey) -> Self {
+        Self {
+            chain,
+            key,
+            client_verifier: None,
+            alpn_protocols: vec![],
+        }
+    }
+
+    /// Request a client certificate signed by one of the given roots
+    ///
+    /// If `required` is false, clients without a certificate are accepted as well.
+    pub fn client_auth(self, roots: RootCertStore, required: bool) -> Self {
+        let verifier = if required {
+            AllowAnyAuthenticatedClient::new(roots).boxed()
+        } else {
+            AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed()
+        };
+
+        self.client_verifier(verifier)
+    }
+
+    /// Verify client certificates using the given verifier
+    pub fn client_verifier(mut self, verifier: Arc<dyn ClientCertVerifier>) -> Self {
+        self.client_verifier = Some(verifier);
+        self
+    }
+
+    /// Set the protocols accepted using ALPN, in order of preference
+    pub fn alpn_protocols(mut self, protocols: Vec<Vec<u8>>) -> Self {
+        self.alpn_protocols = protocols;
+        self
+    }
+
+    pub fn build(self) -> Result<TlsAcceptor, rustls::Error> {
+        let builder = ServerConfig::builder().with_safe_defaults();
+
+        let builder = match self.client_verifier {
+            Some(verifier) => builder.with_client_cert_verifier(verifier),
+            None => builder.with_no_client_auth(),
+        };
+
+        let mut config = builder.with_single_cert(self.chain, self.key)?;
+        config.alpn_protocols = self.alpn_protocols;
+
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
 #[async_trait::async_trait]
 impl StreamingListenerBuilder for TlsAcceptor {
     type Transport = TlsStream<TcpStream>;
@@ -92,3 +268,139 @@ impl StreamingTransport for TlsStream<TcpStream> {
         self.get_ref().0.peer_addr()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa};
+    use sip_types::uri::sip::SipUri;
+    use sip_types::uri::Uri;
+
+    /// Certificate authority issuing the server and client certificates
+    struct Pki {
+        ca: rcgen::Certificate,
+    }
+
+    impl Pki {
+        fn new() -> Self {
+            let mut params = CertificateParams::new(vec![]);
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+
+            Self {
+                ca: rcgen::Certificate::from_params(params).unwrap(),
+            }
+        }
+
+        fn roots(&self) -> RootCertStore {
+            let mut roots = RootCertStore::empty();
+            roots
+                .add(&Certificate(self.ca.serialize_der().unwrap()))
+                .unwrap();
+            roots
+        }
+
+        fn issue(&self, name: &str) -> (Vec<Certificate>, PrivateKey) {
+            let cert = rcgen::generate_simple_self_signed(vec![name.into()]).unwrap();
+
+            (
+                vec![Certificate(
+                    cert.serialize_der_with_signer(&self.ca).unwrap(),
+                )],
+                PrivateKey(cert.serialize_private_key_der()),
+            )
+        }
+    }
+
+    /// Connect to the server which is addressed by its IP but presents a certificate for
+    /// `sip.example.org`
+    async fn handshake(
+        acceptor: TlsAcceptor,
+        connector: RustlsConnector,
+    ) -> (
+        io::Result<TlsStream<TcpStream>>,
+        io::Result<TlsStream<TcpStream>>,
+    ) {
+        let (mut listener, bound) = acceptor.bind("127.0.0.1:0").await.unwrap();
+
+        let uri: SipUri = format!("sips:{bound}").parse().unwrap();
+        let uri_info = uri.info();
+
+        let (server, client) = tokio::join!(listener.accept(), connector.connect(&uri_info, bound));
+
+        (server.map(|(stream, _)| stream), client)
+    }
+
+    fn server_name() -> ServerName {
+        ServerName::try_from("sip.example.org").unwrap()
+    }
+
+    #[tokio::test]
+    async fn alpn_and_server_name() {
+        let pki = Pki::new();
+        let (chain, key) = pki.issue("sip.example.org");
+
+        let acceptor = || {
+            RustlsAcceptorBuilder::new(chain.clone(), key.clone())
+                .alpn_protocols(vec![b"sip".to_vec()])
+                .build()
+                .unwrap()
+        };
+
+        let connector = RustlsConnectorBuilder::new(pki.roots())
+            .alpn_protocols(vec![b"h2".to_vec(), b"sip".to_vec()])
+            .server_name(server_name())
+            .build()
+            .unwrap();
+
+        let (server, client) = handshake(acceptor(), connector).await;
+        let (server, client) = (server.unwrap(), client.unwrap());
+
+        assert_eq!(client.get_ref().1.alpn_protocol(), Some(&b"sip"[..]));
+        assert_eq!(server.get_ref().1.alpn_protocol(), Some(&b"sip"[..]));
+
+        // Without the override the certificate doesn't match the IP of the URI
+        let connector = RustlsConnectorBuilder::new(pki.roots()).build().unwrap();
+
+        let (_, client) = handshake(acceptor(), connector).await;
+        assert!(client.is_err());
+    }
+
+    #[tokio::test]
+    async fn client_auth_required() {
+        let pki = Pki::new();
+        let (chain, key) = pki.issue("sip.example.org");
+        let (client_chain, client_key) = pki.issue("alice.example.org");
+
+        let acceptor = || {
+            RustlsAcceptorBuilder::new(chain.clone(), key.clone())
+                .client_auth(pki.roots(), true)
+                .build()
+                .unwrap()
+        };
+
+        // Clients without a certificate are rejected. With TLS 1.3 the client completes its
+        // side of the handshake before the server verifies it, so only the server fails.
+        let connector = RustlsConnectorBuilder::new(pki.roots())
+            .server_name(server_name())
+            .build()
+            .unwrap();
+
+        let (server, _) = handshake(acceptor(), connector).await;
+        assert!(server.is_err());
+
+        let connector = RustlsConnectorBuilder::new(pki.roots())
+            .server_name(server_name())
+            .client_certificate(client_chain.clone(), client_key)
+            .build()
+            .unwrap();
+
+        let (server, client) = handshake(acceptor(), connector).await;
+        assert!(client.is_ok());
+
+        let server = server.unwrap();
+        assert_eq!(
+            server.get_ref().1.peer_certificates(),
+            Some(&client_chain[..])
+        );
+    }
+}
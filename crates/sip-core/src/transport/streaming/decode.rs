@@ -1,7 +1,6 @@
 use crate::Result;
-use bytes::{Bytes, BytesMut};
-use internal::Finish;
-use sip_types::msg::{Line, MessageLine, PullParser};
+use bytes::{Buf, Bytes, BytesMut};
+use sip_types::msg::{Deviation, MessageHead, MessageLine, PullParser};
 use sip_types::parse::{ParseCtx, Parser};
 use sip_types::Headers;
 use std::io;
@@ -31,12 +30,24 @@ impl From<io::Error> for Error {
     }
 }
 
+pub enum StreamingItem {
+    /// Double CRLF keep-alive ping ([RFC5626](https://www.rfc-editor.org/rfc/rfc5626.html#section-4.4.1))
+    KeepAliveRequest,
+
+    /// Single CRLF keep-alive pong
+    KeepAliveResponse,
+
+    Message(DecodedMessage),
+}
+
 pub struct DecodedMessage {
     pub line: MessageLine,
     pub headers: Headers,
     pub body: Bytes,
 
     pub buffer: Bytes,
+
+    pub deviations: Vec<Deviation>,
 }
 
 pub struct StreamingDecoder {
@@ -54,13 +65,25 @@ impl StreamingDecoder {
 }
 
 impl Decoder for StreamingDecoder {
-    type Item = DecodedMessage;
+    type Item = StreamingItem;
     type Error = Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        if &src[..] == b"\r\n" {
-            src.clear();
-            return Ok(None);
+        if self.head_progress == 0 {
+            if src.starts_with(b"\r\n\r\n") {
+                src.advance(4);
+                return Ok(Some(StreamingItem::KeepAliveRequest));
+            }
+
+            // A single CRLF might be an incomplete ping if nothing follows it, wait for more bytes
+            if matches!(&src[..], b"\r" | b"\r\n" | b"\r\n\r") {
+                return Ok(None);
+            }
+
+            if src.starts_with(b"\r\n") {
+                src.advance(2);
+                return Ok(Some(StreamingItem::KeepAliveResponse));
+            }
         }
 
         if src.len() > 4096 {
@@ -81,7 +104,11 @@ impl Decoder for StreamingDecoder {
                 let mut split = line.splitn(2, |&c| c == b':');
 
                 if let Some(name) = split.next() {
-                    if name.eq_ignore_ascii_case(b"content-length") || name.starts_with(b"l") {
+                    let name = name.trim_ascii_end();
+
+                    if name.eq_ignore_ascii_case(b"content-length")
+                        || name.eq_ignore_ascii_case(b"l")
+                    {
                         let value = split.next().ok_or(Error::Malformed)?;
                         let value = from_utf8(value)?;
 
@@ -128,46 +155,33 @@ impl Decoder for StreamingDecoder {
         // reset state
         self.head_progress = 0;
 
-        // reset parser
-        parser = PullParser::new(&src_bytes, 0);
-
         // Now properly parse the message
-        let mut message_line = None;
-        let mut headers = Headers::new();
-
-        for item in &mut parser {
-            let item = item.expect("got error when input was already checked");
-
-            let line = from_utf8(item)?;
-
-            if message_line.is_none() {
-                let ctx = ParseCtx::new(&src_bytes, self.parser);
-
-                match MessageLine::parse(ctx)(line) {
-                    Ok((_, line)) => message_line = Some(line),
-                    Err(_) => return Err(Error::Malformed),
-                }
-            } else {
-                match Line::parse(&src_bytes, line).finish() {
-                    Ok((_, line)) => headers.insert(line.name, line.value),
-                    Err(e) => {
-                        log::error!("Incoming SIP message has malformed header line, {}", e);
-                    }
-                }
+        let head = match MessageHead::parse(ParseCtx::new(&src_bytes, self.parser)) {
+            Ok(head) => head,
+            Err(e) => {
+                // The message is already framed, drop it without closing the connection
+                log::warn!("Failed to parse incoming SIP message head, {}", e);
+                return self.decode(src);
             }
-        }
+        };
 
-        let head_end = parser.head_end();
+        if !head.deviations.is_empty() {
+            log::debug!(
+                "Incoming SIP message deviates from the grammar, {:?}",
+                head.deviations
+            );
+        }
 
         // slice remaining bytes
-        let body = src_bytes.slice(head_end..head_end + content_len);
+        let body = src_bytes.slice(head.head_end..head.head_end + content_len);
         assert_eq!(content_len, body.len());
 
-        Ok(Some(DecodedMessage {
-            line: message_line.ok_or(Error::Malformed)?,
-            headers,
+        Ok(Some(StreamingItem::Message(DecodedMessage {
+            line: head.line,
+            headers: head.headers,
             body,
             buffer: src_bytes,
-        }))
+            deviations: head.deviations,
+        })))
     }
 }
@@ -1,20 +1,20 @@
 use crate::transport::managed::DropNotifier;
 use crate::transport::{Direction, Factory, ReceivedMessage, TpHandle, TpKey, Transport};
 use crate::{Endpoint, EndpointBuilder};
-use decode::StreamingDecoder;
+use decode::{StreamingDecoder, StreamingItem};
 use sip_types::uri::UriInfo;
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::time::Duration;
 use std::{fmt, io};
-use tokio::io::{split, AsyncRead, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::io::{split, AsyncRead, AsyncWrite, AsyncWriteExt, WriteHalf};
 use tokio::net::ToSocketAddrs;
 use tokio::sync::{broadcast, oneshot, Mutex};
 use tokio::time::{sleep, Sleep};
-use tokio_stream::StreamExt;
+use tokio_stream::{Stream, StreamExt};
 use tokio_util::codec::FramedRead;
 
-mod decode;
+pub(super) mod decode;
 
 /// Helper trait to implement the transport specific behavior of binding to an address
 #[async_trait::async_trait]
@@ -187,13 +187,18 @@ where
 
         let (transport, notifier) = endpoint.transports().add_managed_used(transport);
 
+        let tp_key = TpKey {
+            name: T::Transport::NAME,
+            bound: local,
+            direction: Direction::Outgoing(remote),
+        };
+
         tokio::spawn(receive_task(
             endpoint.clone(),
             framed,
             ReceiveTaskState::InUse(notifier),
-            local,
+            tp_key,
             remote,
-            false,
         ));
 
         return Ok(transport);
@@ -235,13 +240,18 @@ where
 
                 let framed = FramedRead::new(read, StreamingDecoder::new(endpoint.parser()));
 
+                let tp_key = TpKey {
+                    name: I::Transport::NAME,
+                    bound: local,
+                    direction: Direction::Incoming(remote),
+                };
+
                 tokio::spawn(receive_task(
                     endpoint.clone(),
                     framed,
-                    ReceiveTaskState::Unused(Box::pin(sleep(Duration::from_secs(32))), rx),
-                    local,
+                    ReceiveTaskState::unused(rx),
+                    tp_key,
                     remote,
-                    true,
                 ));
             }
             Err(e) => log::error!("Error accepting connection, {}", e),
@@ -249,31 +259,30 @@ where
     }
 }
 
-enum ReceiveTaskState {
+pub(super) enum ReceiveTaskState {
     InUse(DropNotifier),
     Unused(Pin<Box<Sleep>>, oneshot::Receiver<DropNotifier>),
 }
 
-async fn receive_task<T>(
+impl ReceiveTaskState {
+    /// State of a transport which isn't used yet and destroyed if not used soon
+    pub(super) fn unused(rx: oneshot::Receiver<DropNotifier>) -> Self {
+        Self::Unused(Box::pin(sleep(Duration::from_secs(32))), rx)
+    }
+}
+
+/// Receive messages of a connection oriented transport until the connection is closed or the
+/// transport isn't used anymore
+pub(super) async fn receive_task<S, E>(
     endpoint: Endpoint,
-    mut framed: FramedRead<ReadHalf<T>, StreamingDecoder>,
+    mut framed: S,
     mut state: ReceiveTaskState,
-    local: SocketAddr,
+    tp_key: TpKey,
     remote: SocketAddr,
-    incoming: bool,
 ) where
-    T: StreamingTransport,
+    S: Stream<Item = Result<StreamingItem, E>> + Unpin,
+    E: fmt::Display,
 {
-    let tp_key = TpKey {
-        name: T::NAME,
-        bound: local,
-        direction: if incoming {
-            Direction::Incoming(remote)
-        } else {
-            Direction::Outgoing(remote)
-        },
-    };
-
     let _drop_guard = UnclaimedGuard {
         endpoint: &endpoint,
         tp_key,
@@ -289,7 +298,7 @@ async fn receive_task<T>(
                     _ = notifier => {
                         log::debug!("all refs to transport dropped, destroying soon if not used");
                         let rx = endpoint.transports().set_unused(&tp_key);
-                        state = ReceiveTaskState::Unused(Box::pin(sleep(Duration::from_secs(32))), rx);
+                        state = ReceiveTaskState::unused(rx);
                         continue;
                     }
                 }
@@ -320,9 +329,24 @@ async fn receive_task<T>(
         let transport = endpoint.transports().set_used(&tp_key);
 
         let message = match item {
-            Some(Ok(item)) => item,
+            Some(Ok(StreamingItem::Message(message))) => message,
+            Some(Ok(StreamingItem::KeepAliveRequest)) => {
+                if let Err(e) = transport.send(b"\r\n", remote).await {
+                    log::warn!("Failed to respond to keep-alive, {}", e);
+                }
+
+                continue;
+            }
+            Some(Ok(StreamingItem::KeepAliveResponse)) => {
+                endpoint.transports().receive_keep_alive_response(&tp_key);
+                continue;
+            }
             Some(Err(e)) => {
-                log::warn!("An error occurred when reading {} stream {}", T::NAME, e);
+                log::warn!(
+                    "An error occurred when reading {} stream {}",
+                    tp_key.name,
+                    e
+                );
                 return;
             }
             None => {
@@ -338,6 +362,7 @@ async fn receive_task<T>(
             message.line,
             message.headers,
             message.body,
+            message.deviations,
         );
 
         endpoint.receive(message);
@@ -129,6 +129,7 @@ async fn handle_msg(
             headers,
             body,
             buffer,
+            deviations,
         }) => {
             endpoint.receive(ReceivedMessage::new(
                 remote,
@@ -137,6 +138,7 @@ async fn handle_msg(
                 line,
                 headers,
                 body,
+                deviations,
             ));
         }
         Err(_e) => {
@@ -0,0 +1,459 @@
+//! SIP over WebSocket ([RFC7118](https://www.rfc-editor.org/rfc/rfc7118.html))
+//!
+//! WebSocket connections are established over any [`StreamingTransport`], e.g. TCP for `WS`
+//! or TLS for `WSS`.
+
+use super::parse::{parse_complete, CompleteItem};
+use super::streaming::decode::{DecodedMessage, StreamingItem};
+use super::streaming::{
+    receive_task, ReceiveTaskState, StreamingFactory, StreamingListener, StreamingListenerBuilder,
+    StreamingTransport,
+};
+use super::{Direction, Factory, TpHandle, TpKey, Transport};
+use crate::{Endpoint, EndpointBuilder};
+use futures_util::stream::{SplitSink, StreamExt};
+use futures_util::{future, SinkExt};
+use sip_types::parse::Parser;
+use sip_types::uri::UriInfo;
+use std::net::SocketAddr;
+use std::{fmt, io};
+use tokio::net::ToSocketAddrs;
+use tokio::sync::{broadcast, Mutex};
+use tokio_tungstenite::tungstenite::handshake::client::generate_key;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{self, HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::WebSocketStream;
+
+/// The WebSocket sub-protocol which must be negotiated for SIP
+pub const SIP_SUBPROTOCOL: &str = "sip";
+
+const SEC_WEBSOCKET_PROTOCOL: &str = "Sec-WebSocket-Protocol";
+
+fn name(secure: bool) -> &'static str {
+    if secure {
+        "WSS"
+    } else {
+        "WS"
+    }
+}
+
+fn matches_transport_param(secure: bool, name: &str) -> bool {
+    // RFC7118 only defines `transport=ws`, `wss` is accepted as it is widely used
+    name.eq_ignore_ascii_case("ws") || (secure && name.eq_ignore_ascii_case("wss"))
+}
+
+fn into_io_error(error: WsError) -> io::Error {
+    match error {
+        WsError::Io(error) => error,
+        error => io::Error::other(error),
+    }
+}
+
+// ==== Transport
+
+pub struct WsTransport<T> {
+    bound: SocketAddr,
+    remote: SocketAddr,
+    incoming: bool,
+
+    sink: Mutex<SplitSink<WebSocketStream<T>, Message>>,
+}
+
+impl<T: StreamingTransport> fmt::Debug for WsTransport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WsTransport")
+            .field("bound", &self.bound)
+            .field("remote", &self.remote)
+            .field("incoming", &self.incoming)
+            .finish()
+    }
+}
+
+impl<T: StreamingTransport> fmt::Display for WsTransport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:bound={}:remote={}",
+            name(T::SECURE),
+            self.bound,
+            self.remote
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> Transport for WsTransport<T>
+where
+    T: StreamingTransport + Unpin,
+{
+    fn name(&self) -> &'static str {
+        name(T::SECURE)
+    }
+
+    fn matches_transport_param(&self, name: &str) -> bool {
+        matches_transport_param(T::SECURE, name)
+    }
+
+    fn secure(&self) -> bool {
+        T::SECURE
+    }
+
+    fn reliable(&self) -> bool {
+        true
+    }
+
+    fn bound(&self) -> SocketAddr {
+        self.bound
+    }
+
+    fn sent_by(&self) -> SocketAddr {
+        self.bound
+    }
+
+    fn direction(&self) -> Direction {
+        if self.incoming {
+            Direction::Incoming(self.remote)
+        } else {
+            Direction::Outgoing(self.remote)
+        }
+    }
+
+    async fn send(&self, bytes: &[u8], _target: SocketAddr) -> io::Result<()> {
+        // Each SIP message is sent in its own frame, using a text frame if possible
+        let message = match std::str::from_utf8(bytes) {
+            Ok(text) => Message::Text(text.into()),
+            Err(_) => Message::Binary(bytes.into()),
+        };
+
+        self.sink
+            .lock()
+            .await
+            .send(message)
+            .await
+            .map_err(into_io_error)
+    }
+}
+
+/// Register the websocket connection with the endpoint and start receiving on it
+fn register<T>(
+    endpoint: &Endpoint,
+    stream: WebSocketStream<T>,
+    local: SocketAddr,
+    remote: SocketAddr,
+    incoming: bool,
+) -> Option<TpHandle>
+where
+    T: StreamingTransport + Unpin,
+{
+    let (sink, stream) = stream.split();
+
+    let transport = WsTransport {
+        bound: local,
+        remote,
+        incoming,
+        sink: Mutex::new(sink),
+    };
+
+    let parser = endpoint.parser();
+    let messages = stream.filter_map(move |message| future::ready(decode(parser, message)));
+
+    let (handle, state, direction) = if incoming {
+        let rx = endpoint.transports().add_managed_unused(transport);
+
+        (
+            None,
+            ReceiveTaskState::unused(rx),
+            Direction::Incoming(remote),
+        )
+    } else {
+        let (handle, notifier) = endpoint.transports().add_managed_used(transport);
+
+        (
+            Some(handle),
+            ReceiveTaskState::InUse(notifier),
+            Direction::Outgoing(remote),
+        )
+    };
+
+    let tp_key = TpKey {
+        name: name(T::SECURE),
+        bound: local,
+        direction,
+    };
+
+    tokio::spawn(receive_task(
+        endpoint.clone(),
+        messages,
+        state,
+        tp_key,
+        remote,
+    ));
+
+    handle
+}
+
+/// Every websocket message contains exactly one SIP message
+fn decode(
+    parser: Parser,
+    message: Result<Message, WsError>,
+) -> Option<Result<StreamingItem, WsError>> {
+    let bytes = match message {
+        Ok(Message::Text(text)) => text.into_bytes(),
+        Ok(Message::Binary(bytes)) => bytes,
+        // Control frames are handled by tungstenite
+        Ok(_) => return None,
+        Err(e) => return Some(Err(e)),
+    };
+
+    match parse_complete(parser, &bytes) {
+        Ok(CompleteItem::Sip {
+            line,
+            headers,
+            body,
+            buffer,
+            deviations,
+        }) => Some(Ok(StreamingItem::Message(DecodedMessage {
+            line,
+            headers,
+            body,
+            buffer,
+            deviations,
+        }))),
+        Ok(_) => {
+            log::debug!("ignoring non SIP message received over websocket");
+            None
+        }
+        Err(e) => {
+            log::warn!("failed to parse message received over websocket, {}", e);
+            None
+        }
+    }
+}
+
+// ==== Connector
+
+/// Factory creating WebSocket connections over the transport created by `F`
+///
+/// Use a [`TcpConnector`](super::tcp::TcpConnector) for `WS` or a TLS connector for `WSS`.
+pub struct WsConnector<F> {
+    inner: F,
+    path: String,
+}
+
+impl<F: StreamingFactory> WsConnector<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            path: String::from("/"),
+        }
+    }
+
+    /// Set the resource path requested in the handshake, defaults to `/`
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<F> Factory for WsConnector<F>
+where
+    F: StreamingFactory,
+    F::Transport: Unpin,
+{
+    fn name(&self) -> &'static str {
+        name(F::Transport::SECURE)
+    }
+
+    fn matches_transport_param(&self, name: &str) -> bool {
+        matches_transport_param(F::Transport::SECURE, name)
+    }
+
+    fn secure(&self) -> bool {
+        F::Transport::SECURE
+    }
+
+    async fn create(
+        &self,
+        endpoint: Endpoint,
+        uri_info: &UriInfo,
+        addr: SocketAddr,
+    ) -> io::Result<TpHandle> {
+        log::trace!("{} trying to connect to {}", self.name(), addr);
+
+        let stream = self.inner.connect(uri_info, addr).await?;
+        let local = stream.local_addr()?;
+        let remote = stream.peer_addr()?;
+
+        let scheme = if F::Transport::SECURE { "wss" } else { "ws" };
+        let host = format!("{}:{}", uri_info.host_port.host, addr.port());
+
+        let request = http::Request::builder()
+            .uri(format!("{scheme}://{host}{}", self.path))
+            .header("Host", host)
+            .header("Connection", "Upgrade")
+            .header("Upgrade", "websocket")
+            .header("Sec-WebSocket-Version", "13")
+            .header("Sec-WebSocket-Key", generate_key())
+            .header(SEC_WEBSOCKET_PROTOCOL, SIP_SUBPROTOCOL)
+            .body(())
+            .map_err(io::Error::other)?;
+
+        let (stream, response) = tokio_tungstenite::client_async(request, stream)
+            .await
+            .map_err(into_io_error)?;
+
+        let protocol = response.headers().get(SEC_WEBSOCKET_PROTOCOL);
+
+        if protocol.map(|p| p.as_bytes()) != Some(SIP_SUBPROTOCOL.as_bytes()) {
+            return Err(io::Error::other(
+                "websocket server did not accept the sip subprotocol",
+            ));
+        }
+
+        register(&endpoint, stream, local, remote, false)
+            .ok_or_else(|| io::Error::other("failed to register websocket transport"))
+    }
+}
+
+// ==== Listener
+
+/// Accepts WebSocket connections on the listener built by `B`
+pub struct WsListener<B> {
+    inner: B,
+}
+
+impl<B: StreamingListenerBuilder> WsListener<B> {
+    pub fn new(inner: B) -> Self {
+        Self { inner }
+    }
+
+    pub async fn spawn<A: ToSocketAddrs + Send>(
+        self,
+        endpoint: &mut EndpointBuilder,
+        addr: A,
+    ) -> io::Result<()>
+    where
+        B::Transport: Unpin,
+    {
+        let (listener, bound) = self.inner.bind(addr).await?;
+
+        log::info!(
+            "Accepting {} connections on {}",
+            name(B::Transport::SECURE),
+            bound
+        );
+
+        tokio::spawn(task_accept(endpoint.subscribe(), listener));
+
+        Ok(())
+    }
+}
+
+async fn task_accept<I>(mut endpoint: broadcast::Receiver<Endpoint>, mut incoming: I)
+where
+    I: StreamingListener,
+    I::Transport: Unpin,
+{
+    let endpoint = match endpoint.recv().await.ok() {
+        Some(endpoint) => endpoint,
+        None => return,
+    };
+
+    loop {
+        match incoming.accept().await {
+            Ok((stream, remote)) => {
+                let local = match stream.local_addr() {
+                    Ok(local) => local,
+                    Err(e) => {
+                        log::error!("Could not retrieve local addr for incoming stream {}", e);
+                        continue;
+                    }
+                };
+
+                log::trace!("Connection accepted from {} on {}", remote, local);
+
+                // Do the handshake in a separate task, to not block other incoming connections
+                let endpoint = endpoint.clone();
+
+                tokio::spawn(async move {
+                    match tokio_tungstenite::accept_hdr_async(stream, accept_sip_subprotocol).await
+                    {
+                        Ok(stream) => {
+                            register(&endpoint, stream, local, remote, true);
+                        }
+                        Err(e) => {
+                            log::warn!("websocket handshake with {} failed, {}", remote, e)
+                        }
+                    }
+                });
+            }
+            Err(e) => log::error!("Error accepting connection, {}", e),
+        }
+    }
+}
+
+/// Handshake callback which rejects all clients not offering the `sip` subprotocol
+// The signature is dictated by tungstenite's callback trait
+#[allow(clippy::result_large_err)]
+fn accept_sip_subprotocol(
+    request: &Request,
+    mut response: Response,
+) -> Result<Response, ErrorResponse> {
+    let offers_sip = request
+        .headers()
+        .get_all(SEC_WEBSOCKET_PROTOCOL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|protocol| protocol.trim().eq_ignore_ascii_case(SIP_SUBPROTOCOL));
+
+    if !offers_sip {
+        let mut response = ErrorResponse::new(Some("sip subprotocol required".into()));
+        *response.status_mut() = StatusCode::BAD_REQUEST;
+        return Err(response);
+    }
+
+    response.headers_mut().insert(
+        SEC_WEBSOCKET_PROTOCOL,
+        HeaderValue::from_static(SIP_SUBPROTOCOL),
+    );
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transport_param() {
+        assert!(matches_transport_param(false, "ws"));
+        assert!(!matches_transport_param(false, "wss"));
+        assert!(matches_transport_param(true, "WS"));
+        assert!(matches_transport_param(true, "wss"));
+        assert!(!matches_transport_param(true, "tcp"));
+    }
+
+    #[test]
+    fn subprotocol() {
+        let request = Request::builder()
+            .header(SEC_WEBSOCKET_PROTOCOL, "chat, sip")
+            .body(())
+            .unwrap();
+
+        let response = accept_sip_subprotocol(&request, Response::default()).unwrap();
+        assert_eq!(
+            response.headers().get(SEC_WEBSOCKET_PROTOCOL).unwrap(),
+            SIP_SUBPROTOCOL
+        );
+
+        let request = Request::builder()
+            .header(SEC_WEBSOCKET_PROTOCOL, "chat")
+            .body(())
+            .unwrap();
+
+        assert!(accept_sip_subprotocol(&request, Response::default()).is_err());
+    }
+}
@@ -141,6 +141,10 @@ codes! {
     /// 200 OK
     [200 => OK, "OK"];
 
+    /// [[RFC3515, Section 2.4.2](https://tools.ietf.org/html/rfc3515#section-2.4.2)]
+    /// 202 Accepted
+    [202 => ACCEPTED, "Accepted"];
+
     // ==== REDIRECTION 3XX ====
 
     /// [[RFC3621, Section 21.3.1](https://tools.ietf.org/html/rfc3261#section-21.3.1)]
@@ -237,6 +241,22 @@ codes! {
     /// 423 Interval Too Brief
     [423 => INTERVAL_TOO_BRIEF, "Interval Too Brief"];
 
+    /// [[RFC8224, Section 6.2.2](https://datatracker.ietf.org/doc/html/rfc8224#section-6.2.2)]
+    /// 428 Use Identity Header
+    [428 => USE_IDENTITY_HEADER, "Use Identity Header"];
+
+    /// [[RFC8224, Section 6.2.2](https://datatracker.ietf.org/doc/html/rfc8224#section-6.2.2)]
+    /// 436 Bad Identity Info
+    [436 => BAD_IDENTITY_INFO, "Bad Identity Info"];
+
+    /// [[RFC8224, Section 6.2.2](https://datatracker.ietf.org/doc/html/rfc8224#section-6.2.2)]
+    /// 437 Unsupported Credential
+    [437 => UNSUPPORTED_CREDENTIAL, "Unsupported Credential"];
+
+    /// [[RFC8224, Section 6.2.2](https://datatracker.ietf.org/doc/html/rfc8224#section-6.2.2)]
+    /// 438 Invalid Identity Header
+    [438 => INVALID_IDENTITY_HEADER, "Invalid Identity Header"];
+
     /// [[RFC3621, Section 21.4.18](https://tools.ietf.org/html/rfc3261#section-21.4.18)]
     /// 480 Temporarily Unavailable
     [480 => TEMPORARILY_UNAVAILABLE, "Temporarily Unavailable"];
@@ -273,6 +293,10 @@ codes! {
     /// 488 Not Acceptable Here
     [488 => NOT_ACCEPTABLE_HERE, "Not Acceptable Here"];
 
+    /// [[RFC6665, Section 8.3.1](https://tools.ietf.org/html/rfc6665#section-8.3.1)]
+    /// 489 Bad Event
+    [489 => BAD_EVENT, "Bad Event"];
+
     /// [[RFC3621, Section 21.4.27](https://tools.ietf.org/html/rfc3261#section-21.4.27)]
     /// 491 Request Pending
     [491 => REQUEST_PENDING, "Request Pending"];
@@ -4,7 +4,7 @@ use crate::parse::Parser;
 use crate::print::{AppendCtx, Print, PrintCtx};
 use bytesstr::BytesStr;
 use std::iter::{once, FromIterator};
-use std::mem::take;
+use std::mem::{replace, take};
 use std::{fmt, slice};
 
 /// Headers is simple container for SIP-Message headers.
@@ -62,12 +62,14 @@ impl Headers {
     }
 
     /// Inserts `header` using its [`InsertIntoHeaders`] implementation to the front of the list
+    ///
+    /// If the header already exists, the values are inserted before the existing ones.
     #[inline]
     pub fn insert_type_front<H: ExtendValues>(&mut self, name: Name, header: &H) {
         let ctx = PrintCtx::default();
 
         if let Some(Entry { values, .. }) = self.entry_mut(&name) {
-            header.extend_values(ctx, values);
+            values.prepend(header.create_values(ctx));
         } else {
             self.entries.insert(
                 0,
@@ -80,6 +82,8 @@ impl Headers {
     }
 
     /// Inserts a header value with the given name to the front of the list
+    ///
+    /// If the header already exists, the value is inserted before the existing ones.
     #[inline]
     pub fn insert_front<N, V>(&mut self, name: N, value: V)
     where
@@ -91,7 +95,7 @@ impl Headers {
         let value = value.print_ctx(ctx).to_string();
 
         if let Some(Entry { values, .. }) = self.entry_mut(&name) {
-            values.push(value.into());
+            values.prepend(OneOrMore::One(value.into()));
         } else {
             self.entries.insert(
                 0,
@@ -385,6 +389,16 @@ impl OneOrMore {
         }
     }
 
+    /// Insert `values` before the existing values
+    fn prepend(&mut self, values: OneOrMore) {
+        let existing = replace(self, values);
+
+        match existing {
+            OneOrMore::One(value) => self.push(value),
+            OneOrMore::More(vec) => self.extend(vec),
+        }
+    }
+
     fn decode<H: DecodeValues>(&self, name: Name, parser: Parser) -> Result<H, HeaderError> {
         match &self {
             OneOrMore::One(v) => H::decode(parser, &mut once(v)),
@@ -469,6 +483,25 @@ mod test {
         );
     }
 
+    #[test]
+    fn header_insert_front_existing() {
+        let mut headers = Headers::new();
+
+        headers.insert(Name::VIA, BytesStr::from_static("SIP/2.0/UDP a"));
+        headers.insert(Name::VIA, BytesStr::from_static("SIP/2.0/UDP b"));
+        headers.insert_front(Name::VIA, BytesStr::from_static("SIP/2.0/UDP c"));
+
+        assert_eq!(headers.entries.len(), 1);
+        assert_eq!(
+            headers.entries[0].values,
+            OneOrMore::More(vec![
+                BytesStr::from_static("SIP/2.0/UDP c"),
+                BytesStr::from_static("SIP/2.0/UDP a"),
+                BytesStr::from_static("SIP/2.0/UDP b"),
+            ])
+        );
+    }
+
     #[test]
     fn header_remove() {
         let mut headers = Headers::new();
@@ -172,12 +172,21 @@ header_names! {
     /// [[RFC3621, Section 20.18](https://tools.ietf.org/html/rfc3261#section-20.18)]
     "Error-Info",           ErrorInfo,          ["error-info"],             ERROR_INFO;
 
+    /// [[RFC6665, Section 8.2.1](https://datatracker.ietf.org/doc/html/rfc6665#section-8.2.1)]
+    "Event",                Event,              ["event", "o"],             EVENT;
+
     /// [[RFC3621, Section 20.19](https://tools.ietf.org/html/rfc3261#section-20.19)]
     "Expires",              Expires,            ["expires"],                EXPIRES;
 
+    /// [[RFC5626, Section 11](https://datatracker.ietf.org/doc/html/rfc5626#section-11)]
+    "Flow-Timer",           FlowTimer,          ["flow-timer"],             FLOW_TIMER;
+
     /// [[RFC3621, Section 20.20](https://tools.ietf.org/html/rfc3261#section-20.20)]
     "From",                 From,               ["from", "f"],              FROM;
 
+    /// [[RFC8224, Section 4](https://datatracker.ietf.org/doc/html/rfc8224#section-4)]
+    "Identity",             Identity,           ["identity", "y"],          IDENTITY;
+
     /// [[RFC3621, Section 20.21](https://tools.ietf.org/html/rfc3261#section-20.21)]
     "In-Reply-To",          InReplyTo,          ["in-reply-to"],            IN_REPLY_TO;
 
@@ -196,9 +205,21 @@ header_names! {
     /// [[RFC3621, Section 20.25](https://tools.ietf.org/html/rfc3261#section-20.25)]
     "Organization",         Organization,       ["organization"],           ORGANIZATION;
 
+    /// [[RFC3325, Section 9.1](https://datatracker.ietf.org/doc/html/rfc3325#section-9.1)]
+    "P-Asserted-Identity",  PAssertedIdentity,  ["p-asserted-identity"],    P_ASSERTED_IDENTITY;
+
+    /// [[RFC3325, Section 9.2](https://datatracker.ietf.org/doc/html/rfc3325#section-9.2)]
+    "P-Preferred-Identity", PPreferredIdentity, ["p-preferred-identity"],   P_PREFERRED_IDENTITY;
+
+    /// [[RFC3327, Section 4](https://datatracker.ietf.org/doc/html/rfc3327#section-4)]
+    "Path",                 Path,               ["path"],                   PATH;
+
     /// [[RFC3621, Section 20.26](https://tools.ietf.org/html/rfc3261#section-20.26)]
     "Priority",             Priority,           ["priority"],               PRIORITY;
 
+    /// [[RFC3323, Section 4.2](https://datatracker.ietf.org/doc/html/rfc3323#section-4.2)]
+    "Privacy",              Privacy,            ["privacy"],                PRIVACY;
+
     /// [[RFC3621, Section 20.27](https://tools.ietf.org/html/rfc3261#section-20.27)]
     "Proxy-Authenticate",   ProxyAuthenticate,  ["proxy-authenticate"],     PROXY_AUTHENTICATE;
 
@@ -211,9 +232,18 @@ header_names! {
      /// [[RFC3262, Section 20.34](https://datatracker.ietf.org/doc/html/rfc3262#section-7.2)]
     "RAck",                 RAck,               ["rack"],                   RACK;
 
+    /// [[RFC3326, Section 2](https://datatracker.ietf.org/doc/html/rfc3326#section-2)]
+    "Reason",               Reason,             ["reason"],                 REASON;
+
     /// [[RFC3621, Section 20.30](https://tools.ietf.org/html/rfc3261#section-20.30)]
     "Record-Route",         RecordRoute,        ["record-route"],           RECORD_ROUTE;
 
+    /// [[RFC3515, Section 2.1](https://datatracker.ietf.org/doc/html/rfc3515#section-2.1)]
+    "Refer-To",             ReferTo,            ["refer-to", "r"],          REFER_TO;
+
+    /// [[RFC3892, Section 3](https://datatracker.ietf.org/doc/html/rfc3892#section-3)]
+    "Referred-By",          ReferredBy,         ["referred-by", "b"],       REFERRED_BY;
+
     /// [[RFC3891, Section 6.1](https://datatracker.ietf.org/doc/html/rfc3891#section-6.1)]
     "Replaces",             Replaces,           ["replaces"],               REPLACES;
 
@@ -235,12 +265,18 @@ header_names! {
     /// [[RFC3621, Section 20.35](https://tools.ietf.org/html/rfc3261#section-20.35)]
     "Server",               Server,             ["server"],                 SERVER;
 
+    /// [[RFC3608, Section 5](https://datatracker.ietf.org/doc/html/rfc3608#section-5)]
+    "Service-Route",        ServiceRoute,       ["service-route"],          SERVICE_ROUTE;
+
     /// [[RFC4028, Section 20.35](https://datatracker.ietf.org/doc/html/rfc4028#section-4)]
     "Session-Expires",      SessionExpires,     ["session-expires", "x"],        SESSION_EXPIRES;
 
     /// [[RFC3621, Section 20.36](https://tools.ietf.org/html/rfc3261#section-20.36)]
     "Subject",              Subject,            ["subject", "s"],           SUBJECT;
 
+    /// [[RFC6665, Section 8.2.3](https://datatracker.ietf.org/doc/html/rfc6665#section-8.2.3)]
+    "Subscription-State",   SubscriptionState,  ["subscription-state"],     SUBSCRIPTION_STATE;
+
     /// [[RFC3621, Section 20.37](https://tools.ietf.org/html/rfc3261#section-20.37)]
     "Supported",            Supported,          ["supported", "k"],         SUPPORTED;
 
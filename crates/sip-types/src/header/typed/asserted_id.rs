@@ -0,0 +1,124 @@
+//! [RFC3325](https://datatracker.ietf.org/doc/html/rfc3325)
+
+use crate::header::headers::OneOrMore;
+use crate::header::name::Name;
+use crate::header::{ConstNamed, ExtendValues, HeaderParse};
+use crate::parse::ParseCtx;
+use crate::print::{AppendCtx, Print, PrintCtx, UriContext};
+use crate::uri::NameAddr;
+use anyhow::Result;
+use nom::Finish;
+use std::fmt;
+
+macro_rules! identity_header {
+    ($(#[$meta:meta])* $struct_name:ident, $header_name:expr) => {
+        $(#[$meta])*
+        ///
+        /// The header may contain multiple identities (e.g. one sip and one tel URI), use
+        /// `Vec<Self>` to get or insert all of them.
+        #[derive(Debug, Clone)]
+        pub struct $struct_name(pub NameAddr);
+
+        impl ConstNamed for $struct_name {
+            const NAME: Name = $header_name;
+        }
+
+        impl HeaderParse for $struct_name {
+            fn parse<'i>(ctx: ParseCtx<'_>, i: &'i str) -> Result<(&'i str, Self)> {
+                let (rem, uri) = NameAddr::parse_no_params(ctx)(i.trim_start()).finish()?;
+
+                Ok((rem, Self(uri)))
+            }
+        }
+
+        impl ExtendValues for $struct_name {
+            fn extend_values(&self, ctx: PrintCtx<'_>, values: &mut OneOrMore) {
+                let value = match values {
+                    OneOrMore::One(value) => value,
+                    OneOrMore::More(values) => {
+                        values.last_mut().expect("empty OneOrMore::More variant")
+                    }
+                };
+
+                *value = format!("{}, {}", value, self.print_ctx(ctx)).into();
+            }
+
+            fn create_values(&self, ctx: PrintCtx<'_>) -> OneOrMore {
+                OneOrMore::One(self.print_ctx(ctx).to_string().into())
+            }
+        }
+
+        impl Print for $struct_name {
+            fn print(&self, f: &mut fmt::Formatter<'_>, mut ctx: PrintCtx<'_>) -> fmt::Result {
+                ctx.uri = Some(UriContext::FromTo);
+                write!(f, "{}", self.0.print_ctx(ctx))
+            }
+        }
+    };
+}
+
+identity_header! {
+    /// `P-Asserted-Identity` header, identity of the originator asserted by a trusted entity
+    PAssertedIdentity,
+    Name::P_ASSERTED_IDENTITY
+}
+
+identity_header! {
+    /// `P-Preferred-Identity` header, identity a user agent wishes to be asserted by a trusted proxy
+    PPreferredIdentity,
+    Name::P_PREFERRED_IDENTITY
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::uri::sip::SipUri;
+    use crate::uri::tel::TelUri;
+    use crate::Headers;
+
+    #[test]
+    fn p_asserted_identity_multiple() {
+        let mut headers = Headers::new();
+        headers.insert(
+            Name::P_ASSERTED_IDENTITY,
+            "\"Cullen Jennings\" <sip:fluffy@cisco.com>, <tel:+14085264000>",
+        );
+
+        let identities: Vec<PAssertedIdentity> = headers.get_named().unwrap();
+        assert_eq!(identities.len(), 2);
+
+        assert_eq!(
+            identities[0].0.name.as_ref().map(|name| name.as_str()),
+            Some("Cullen Jennings")
+        );
+        assert!(identities[0].0.uri.downcast_ref::<SipUri>().is_some());
+
+        let tel: &TelUri = identities[1].0.uri.downcast_ref().unwrap();
+        assert_eq!(tel.number, "+14085264000");
+
+        let mut headers = Headers::new();
+        headers.insert_named(&identities);
+
+        assert_eq!(
+            headers.to_string(),
+            "P-Asserted-Identity: \"Cullen Jennings\"<sip:fluffy@cisco.com>, <tel:+14085264000>\r\n"
+        );
+    }
+
+    #[test]
+    fn p_preferred_identity_addr_spec() {
+        let mut headers = Headers::new();
+        headers.insert(Name::P_PREFERRED_IDENTITY, "sip:alice@example.org");
+
+        let identity: PPreferredIdentity = headers.get_named().unwrap();
+        assert!(identity.0.name.is_none());
+
+        let mut headers = Headers::new();
+        headers.insert_named(&identity);
+
+        assert_eq!(
+            headers.to_string(),
+            "P-Preferred-Identity: <sip:alice@example.org>\r\n"
+        );
+    }
+}
@@ -411,7 +411,7 @@ impl Print for DigestResponse {
         if let Some(qop_response) = &self.qop_response {
             write!(
                 f,
-                r#", qop="{}", cnonce="{}", nc={:08X}"#,
+                r#", qop="{}", cnonce="{}", nc={:08x}"#,
                 qop_response.qop, qop_response.cnonce, qop_response.nc
             )?;
         }
@@ -28,8 +28,45 @@ impl Contact {
     }
 
     impl_with_params!(params, with_key_param, with_value_param);
+
+    /// Set the `+sip.instance` parameter which identifies the user agent instance,
+    /// e.g. `urn:uuid:00000000-0000-1000-8000-AABBCCDDEEFF`
+    ///
+    /// [RFC5626](https://www.rfc-editor.org/rfc/rfc5626.html#section-4.1)
+    pub fn with_instance(self, instance: &str) -> Self {
+        self.with_value_param(SIP_INSTANCE, format!("\"<{instance}>\""))
+    }
+
+    /// Returns the value of the `+sip.instance` parameter without quotes and angle brackets
+    pub fn instance(&self) -> Option<&str> {
+        let instance = self.params.get_val(SIP_INSTANCE)?.as_str();
+        let instance = instance.trim_matches('"');
+
+        Some(
+            instance
+                .strip_prefix('<')
+                .and_then(|i| i.strip_suffix('>'))
+                .unwrap_or(instance),
+        )
+    }
+
+    /// Set the `reg-id` parameter which distinguishes multiple flows registered by the same
+    /// instance
+    ///
+    /// [RFC5626](https://www.rfc-editor.org/rfc/rfc5626.html#section-4.2)
+    pub fn with_reg_id(self, reg_id: u32) -> Self {
+        self.with_value_param(REG_ID, reg_id.to_string())
+    }
+
+    /// Returns the value of the `reg-id` parameter
+    pub fn reg_id(&self) -> Option<u32> {
+        self.params.get_val(REG_ID)?.parse().ok()
+    }
 }
 
+const SIP_INSTANCE: &str = "+sip.instance";
+const REG_ID: &str = "reg-id";
+
 impl ConstNamed for Contact {
     const NAME: Name = Name::CONTACT;
 }
@@ -112,6 +149,35 @@ mod test {
         assert_eq!(headers, "Contact: <sip:example.org>, <sip:example.org>\r\n")
     }
 
+    #[test]
+    fn contact_instance() {
+        let contact = test_contact().with_instance("urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6");
+
+        assert_eq!(
+            contact.instance(),
+            Some("urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6")
+        );
+
+        let mut headers = Headers::new();
+        headers.insert_named(&contact);
+
+        let contact: Contact = headers.get_named().unwrap();
+        assert_eq!(
+            contact.instance(),
+            Some("urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6")
+        );
+
+        assert_eq!(contact.reg_id(), None);
+
+        let contact = contact.with_reg_id(2);
+        assert_eq!(contact.reg_id(), Some(2));
+
+        assert_eq!(
+            headers.to_string(),
+            "Contact: <sip:example.org>;+sip.instance=\"<urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6>\"\r\n"
+        );
+    }
+
     #[test]
     fn parse_contact_single() {
         let mut headers = Headers::new();
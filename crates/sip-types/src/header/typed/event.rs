@@ -0,0 +1,239 @@
+//! [RFC6665](https://datatracker.ietf.org/doc/html/rfc6665)
+
+use crate::header::headers::OneOrMore;
+use crate::header::name::Name;
+use crate::header::{ConstNamed, ExtendValues, HeaderParse};
+use crate::parse::{token, ParseCtx};
+use crate::print::PrintCtx;
+use crate::uri::params::{Params, CPS};
+use anyhow::Result;
+use bytesstr::BytesStr;
+use internal::ws;
+use nom::bytes::complete::take_while1;
+use nom::combinator::map;
+use nom::Finish;
+use std::fmt;
+
+/// `Event` header
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// The event package, e.g. `refer` or `presence`
+    pub event: BytesStr,
+    /// The `id` parameter, identifies a subscription when multiple exist inside a dialog
+    pub id: Option<BytesStr>,
+    pub params: Params<CPS>,
+}
+
+impl Event {
+    pub fn new<E: Into<BytesStr>>(event: E) -> Self {
+        Self {
+            event: event.into(),
+            id: None,
+            params: Params::new(),
+        }
+    }
+
+    pub fn with_id<I: Into<BytesStr>>(mut self, id: I) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+}
+
+impl ConstNamed for Event {
+    const NAME: Name = Name::EVENT;
+}
+
+impl HeaderParse for Event {
+    fn parse<'i>(ctx: ParseCtx<'_>, i: &'i str) -> Result<(&'i str, Self)> {
+        let (rem, event) = map(
+            ws((take_while1(token), Params::<CPS>::parse(ctx))),
+            |(event, mut params)| Self {
+                event: BytesStr::from_parse(ctx.src, event),
+                id: params.take("id"),
+                params,
+            },
+        )(i)
+        .finish()?;
+
+        Ok((rem, event))
+    }
+}
+
+impl ExtendValues for Event {
+    fn extend_values(&self, ctx: PrintCtx<'_>, values: &mut OneOrMore) {
+        *values = self.create_values(ctx)
+    }
+
+    fn create_values(&self, _: PrintCtx<'_>) -> OneOrMore {
+        OneOrMore::One(self.to_string().into())
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.event)?;
+
+        if let Some(id) = &self.id {
+            write!(f, ";id={}", id)?;
+        }
+
+        write!(f, "{}", self.params)
+    }
+}
+
+/// Value of the `Subscription-State` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubState {
+    Active,
+    Pending,
+    Terminated,
+    Other(BytesStr),
+}
+
+impl fmt::Display for SubState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubState::Active => f.write_str("active"),
+            SubState::Pending => f.write_str("pending"),
+            SubState::Terminated => f.write_str("terminated"),
+            SubState::Other(other) => f.write_str(other),
+        }
+    }
+}
+
+/// `Subscription-State` header
+#[derive(Debug, Clone)]
+pub struct SubscriptionState {
+    pub state: SubState,
+    /// Remaining duration of the subscription in seconds
+    pub expires: Option<u32>,
+    /// Reason the subscription was terminated, e.g. `noresource` or `timeout`
+    pub reason: Option<BytesStr>,
+    /// Seconds after which the subscriber may retry to subscribe
+    pub retry_after: Option<u32>,
+    pub params: Params<CPS>,
+}
+
+impl SubscriptionState {
+    pub fn new(state: SubState) -> Self {
+        Self {
+            state,
+            expires: None,
+            reason: None,
+            retry_after: None,
+            params: Params::new(),
+        }
+    }
+
+    pub fn active(expires: u32) -> Self {
+        Self {
+            expires: Some(expires),
+            ..Self::new(SubState::Active)
+        }
+    }
+
+    pub fn terminated<R: Into<BytesStr>>(reason: R) -> Self {
+        Self {
+            reason: Some(reason.into()),
+            ..Self::new(SubState::Terminated)
+        }
+    }
+}
+
+impl ConstNamed for SubscriptionState {
+    const NAME: Name = Name::SUBSCRIPTION_STATE;
+}
+
+impl HeaderParse for SubscriptionState {
+    fn parse<'i>(ctx: ParseCtx<'_>, i: &'i str) -> Result<(&'i str, Self)> {
+        let (rem, state) = map(
+            ws((take_while1(token), Params::<CPS>::parse(ctx))),
+            |(state, mut params)| {
+                let state = match state {
+                    _ if state.eq_ignore_ascii_case("active") => SubState::Active,
+                    _ if state.eq_ignore_ascii_case("pending") => SubState::Pending,
+                    _ if state.eq_ignore_ascii_case("terminated") => SubState::Terminated,
+                    _ => SubState::Other(BytesStr::from_parse(ctx.src, state)),
+                };
+
+                Self {
+                    state,
+                    expires: params.take("expires").and_then(|v| v.parse().ok()),
+                    reason: params.take("reason"),
+                    retry_after: params.take("retry-after").and_then(|v| v.parse().ok()),
+                    params,
+                }
+            },
+        )(i)
+        .finish()?;
+
+        Ok((rem, state))
+    }
+}
+
+impl ExtendValues for SubscriptionState {
+    fn extend_values(&self, ctx: PrintCtx<'_>, values: &mut OneOrMore) {
+        *values = self.create_values(ctx)
+    }
+
+    fn create_values(&self, _: PrintCtx<'_>) -> OneOrMore {
+        OneOrMore::One(self.to_string().into())
+    }
+}
+
+impl fmt::Display for SubscriptionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.state)?;
+
+        if let Some(expires) = self.expires {
+            write!(f, ";expires={}", expires)?;
+        }
+
+        if let Some(reason) = &self.reason {
+            write!(f, ";reason={}", reason)?;
+        }
+
+        if let Some(retry_after) = self.retry_after {
+            write!(f, ";retry-after={}", retry_after)?;
+        }
+
+        write!(f, "{}", self.params)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Headers;
+
+    #[test]
+    fn event() {
+        let mut headers = Headers::new();
+        headers.insert(Name::EVENT, "refer;id=93809824");
+
+        let event: Event = headers.get_named().unwrap();
+        assert_eq!(event.event, "refer");
+        assert_eq!(event.id.as_deref(), Some("93809824"));
+
+        let mut headers = Headers::new();
+        headers.insert_named(&event);
+        assert_eq!(headers.to_string(), "Event: refer;id=93809824\r\n");
+    }
+
+    #[test]
+    fn subscription_state() {
+        let mut headers = Headers::new();
+        headers.insert(Name::SUBSCRIPTION_STATE, "terminated;reason=noresource");
+
+        let state: SubscriptionState = headers.get_named().unwrap();
+        assert_eq!(state.state, SubState::Terminated);
+        assert_eq!(state.reason.as_deref(), Some("noresource"));
+
+        let mut headers = Headers::new();
+        headers.insert_named(&SubscriptionState::active(60));
+        assert_eq!(
+            headers.to_string(),
+            "Subscription-State: active;expires=60\r\n"
+        );
+    }
+}
@@ -19,6 +19,14 @@ from_str_header! {
     u32
 }
 
+from_str_header! {
+    /// `Flow-Timer` header, the interval in seconds in which the server expects keep-alives
+    /// ([RFC5626](https://datatracker.ietf.org/doc/html/rfc5626#section-11))
+    FlowTimer,
+    Name::FLOW_TIMER,
+    u32
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,216 @@
+//! [RFC8224](https://datatracker.ietf.org/doc/html/rfc8224)
+
+use crate::header::headers::OneOrMore;
+use crate::header::name::Name;
+use crate::header::{ConstNamed, ExtendValues, HeaderParse};
+use crate::parse::{parse_quoted, token, ParseCtx};
+use crate::print::PrintCtx;
+use crate::uri::params::{Param, Params, CPS};
+use anyhow::{bail, Result};
+use bytesstr::BytesStr;
+use std::fmt;
+
+/// `Identity` header, carries a signed identity assertion (usually a PASSporT)
+///
+/// A request may contain multiple identities, use `Vec<Identity>` to get or insert all of them.
+#[derive(Debug, Clone)]
+pub struct Identity {
+    /// The signed identity digest, e.g. a compact form JWS
+    pub token: BytesStr,
+    /// URI of the credential (e.g. certificate) used to sign the `token`, without the angle brackets
+    pub info: Option<BytesStr>,
+    /// Signing algorithm, `ES256` if absent
+    pub alg: Option<BytesStr>,
+    /// PASSporT extension, e.g. `shaken`
+    pub ppt: Option<BytesStr>,
+    pub params: Params<CPS>,
+}
+
+impl Identity {
+    pub fn new<T, I>(token: T, info: I) -> Self
+    where
+        T: Into<BytesStr>,
+        I: Into<BytesStr>,
+    {
+        Self {
+            token: token.into(),
+            info: Some(info.into()),
+            alg: None,
+            ppt: None,
+            params: Params::new(),
+        }
+    }
+
+    pub fn with_alg<A: Into<BytesStr>>(mut self, alg: A) -> Self {
+        self.alg = Some(alg.into());
+        self
+    }
+
+    pub fn with_ppt<P: Into<BytesStr>>(mut self, ppt: P) -> Self {
+        self.ppt = Some(ppt.into());
+        self
+    }
+
+    /// Returns the signing algorithm, defaulting to `ES256`
+    pub fn alg(&self) -> &str {
+        self.alg.as_deref().unwrap_or("ES256")
+    }
+}
+
+impl ConstNamed for Identity {
+    const NAME: Name = Name::IDENTITY;
+}
+
+fn identity_token(c: char) -> bool {
+    lookup_table!(c => alpha; num; '-', '_', '.', '=', '+', '/')
+}
+
+fn param_value(c: char) -> bool {
+    !matches!(c, ';' | ',' | '"' | '<' | '>') && !c.is_whitespace()
+}
+
+impl HeaderParse for Identity {
+    fn parse<'i>(ctx: ParseCtx<'_>, i: &'i str) -> Result<(&'i str, Self)> {
+        let i = i.trim_start();
+
+        let token_len = i.find(|c| !identity_token(c)).unwrap_or(i.len());
+
+        if token_len == 0 {
+            bail!("missing identity token");
+        }
+
+        let mut identity = Self {
+            token: BytesStr::from_parse(ctx.src, &i[..token_len]),
+            info: None,
+            alg: None,
+            ppt: None,
+            params: Params::new(),
+        };
+
+        let mut i = i[token_len..].trim_start();
+
+        while let Some(rem) = i.strip_prefix(';') {
+            let rem = rem.trim_start();
+
+            let name_len = rem.find(|c| !token(c)).unwrap_or(rem.len());
+            let name = &rem[..name_len];
+            let rem = rem[name_len..].trim_start();
+
+            let (rem, value) = if let Some(rem) = rem.strip_prefix('=') {
+                let rem = rem.trim_start();
+
+                if let Some(rem) = rem.strip_prefix('<') {
+                    let Some(end) = rem.find('>') else {
+                        bail!("unterminated identity info");
+                    };
+
+                    (&rem[end + 1..], Some(&rem[..end]))
+                } else if rem.starts_with('"') {
+                    let Ok((rem, value)) = parse_quoted(rem) else {
+                        bail!("unterminated quoted identity parameter");
+                    };
+
+                    (rem, Some(value))
+                } else {
+                    let value_len = rem.find(|c| !param_value(c)).unwrap_or(rem.len());
+                    (&rem[value_len..], Some(&rem[..value_len]))
+                }
+            } else {
+                (rem, None)
+            };
+
+            let value = value.map(|value| BytesStr::from_parse(ctx.src, value));
+
+            match name {
+                _ if name.eq_ignore_ascii_case("info") => identity.info = value,
+                _ if name.eq_ignore_ascii_case("alg") => identity.alg = value,
+                _ if name.eq_ignore_ascii_case("ppt") => identity.ppt = value,
+                _ => identity.params.push(Param {
+                    name: BytesStr::from_parse(ctx.src, name),
+                    value,
+                }),
+            }
+
+            i = rem.trim_start();
+        }
+
+        Ok((i, identity))
+    }
+}
+
+impl ExtendValues for Identity {
+    fn extend_values(&self, _: PrintCtx<'_>, values: &mut OneOrMore) {
+        let value = match values {
+            OneOrMore::One(value) => value,
+            OneOrMore::More(values) => values.last_mut().expect("empty OneOrMore::More variant"),
+        };
+
+        *value = format!("{}, {}", value, self).into();
+    }
+
+    fn create_values(&self, _: PrintCtx<'_>) -> OneOrMore {
+        OneOrMore::One(self.to_string().into())
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.token)?;
+
+        if let Some(info) = &self.info {
+            write!(f, ";info=<{}>", info)?;
+        }
+
+        if let Some(alg) = &self.alg {
+            write!(f, ";alg={}", alg)?;
+        }
+
+        if let Some(ppt) = &self.ppt {
+            write!(f, ";ppt={}", ppt)?;
+        }
+
+        write!(f, "{}", self.params)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Headers;
+
+    #[test]
+    fn identity() {
+        let mut headers = Headers::new();
+        headers.insert(
+            Name::IDENTITY,
+            "eyJhbGciOiJFUzI1NiJ9.eyJpYXQiOjF9.c2ln;info=<https://cert.example.org/passport.cer;v=1>;alg=ES256;ppt=\"shaken\"",
+        );
+
+        let identity: Identity = headers.get_named().unwrap();
+        assert_eq!(identity.token, "eyJhbGciOiJFUzI1NiJ9.eyJpYXQiOjF9.c2ln");
+        assert_eq!(
+            identity.info.as_deref(),
+            Some("https://cert.example.org/passport.cer;v=1")
+        );
+        assert_eq!(identity.alg(), "ES256");
+        assert_eq!(identity.ppt.as_deref(), Some("shaken"));
+
+        let mut headers = Headers::new();
+        headers.insert_named(&identity);
+        assert_eq!(
+            headers.to_string(),
+            "Identity: eyJhbGciOiJFUzI1NiJ9.eyJpYXQiOjF9.c2ln;info=<https://cert.example.org/passport.cer;v=1>;alg=ES256;ppt=shaken\r\n"
+        );
+    }
+
+    #[test]
+    fn identity_multiple() {
+        let mut headers = Headers::new();
+        headers.insert(Name::IDENTITY, "a.b.c;info=<https://a.example.org>, d.e.f");
+
+        let identities: Vec<Identity> = headers.get_named().unwrap();
+        assert_eq!(identities.len(), 2);
+        assert_eq!(identities[1].token, "d.e.f");
+        assert!(identities[1].info.is_none());
+    }
+}
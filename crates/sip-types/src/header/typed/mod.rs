@@ -2,16 +2,22 @@
 
 mod accept;
 mod allow;
+mod asserted_id;
 mod auth;
 mod call_id;
 mod contact;
 mod content;
 mod cseq;
+mod event;
 mod expires;
 mod extensions;
 mod from_to;
+mod identity;
 mod max_fwd;
 mod prack;
+mod privacy;
+mod reason;
+mod refer;
 mod replaces;
 mod retry_after;
 mod routing;
@@ -20,16 +26,22 @@ mod via;
 
 pub use accept::Accept;
 pub use allow::Allow;
+pub use asserted_id::{PAssertedIdentity, PPreferredIdentity};
 pub use auth::*;
 pub use call_id::CallID;
 pub use contact::Contact;
 pub use content::{ContentLength, ContentType};
 pub use cseq::CSeq;
-pub use expires::{Expires, MinExpires};
-pub use extensions::{Require, Supported};
+pub use event::{Event, SubState, SubscriptionState};
+pub use expires::{Expires, FlowTimer, MinExpires};
+pub use extensions::{Require, Supported, Unsupported};
 pub use from_to::FromTo;
+pub use identity::Identity;
 pub use max_fwd::MaxForwards;
 pub use prack::{RAck, RSeq};
+pub use privacy::{Privacy, PrivacyValue};
+pub use reason::{Reason, ReasonProtocol};
+pub use refer::{ReferTo, ReferredBy};
 pub use replaces::Replaces;
 pub use retry_after::RetryAfter;
 pub use routing::Routing;
@@ -0,0 +1,156 @@
+//! [RFC3323](https://datatracker.ietf.org/doc/html/rfc3323)
+
+use crate::header::headers::OneOrMore;
+use crate::header::name::Name;
+use crate::header::{ConstNamed, ExtendValues, HeaderParse};
+use crate::parse::ParseCtx;
+use crate::print::PrintCtx;
+use anyhow::{bail, Result};
+use bytesstr::BytesStr;
+use std::fmt;
+
+/// Type of privacy requested in the `Privacy` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrivacyValue {
+    /// Hide headers which could reveal information about the user (e.g. `User-Agent`)
+    Header,
+    /// Hide the media session, e.g. by relaying the media
+    Session,
+    /// Hide the user's identity in user-level headers (e.g. `From`)
+    User,
+    /// No privacy must be applied
+    None,
+    /// The request must be rejected if the requested privacy cannot be applied
+    Critical,
+    /// Hide the `P-Asserted-Identity` ([RFC3325](https://datatracker.ietf.org/doc/html/rfc3325#section-9.3))
+    Id,
+    /// Hide the `History-Info` ([RFC7044](https://datatracker.ietf.org/doc/html/rfc7044#section-9))
+    History,
+    Other(BytesStr),
+}
+
+impl fmt::Display for PrivacyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrivacyValue::Header => f.write_str("header"),
+            PrivacyValue::Session => f.write_str("session"),
+            PrivacyValue::User => f.write_str("user"),
+            PrivacyValue::None => f.write_str("none"),
+            PrivacyValue::Critical => f.write_str("critical"),
+            PrivacyValue::Id => f.write_str("id"),
+            PrivacyValue::History => f.write_str("history"),
+            PrivacyValue::Other(other) => f.write_str(other),
+        }
+    }
+}
+
+/// `Privacy` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Privacy(pub Vec<PrivacyValue>);
+
+impl Privacy {
+    pub fn new(values: Vec<PrivacyValue>) -> Self {
+        Self(values)
+    }
+
+    pub fn contains(&self, value: &PrivacyValue) -> bool {
+        self.0.contains(value)
+    }
+
+    /// Returns if any privacy is requested, `none` overrides all other values
+    pub fn is_requested(&self) -> bool {
+        !self.contains(&PrivacyValue::None) && !self.0.is_empty()
+    }
+}
+
+impl ConstNamed for Privacy {
+    const NAME: Name = Name::PRIVACY;
+}
+
+impl HeaderParse for Privacy {
+    fn parse<'i>(ctx: ParseCtx<'_>, i: &'i str) -> Result<(&'i str, Self)> {
+        let values = i
+            .split(';')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| match value {
+                _ if value.eq_ignore_ascii_case("header") => PrivacyValue::Header,
+                _ if value.eq_ignore_ascii_case("session") => PrivacyValue::Session,
+                _ if value.eq_ignore_ascii_case("user") => PrivacyValue::User,
+                _ if value.eq_ignore_ascii_case("none") => PrivacyValue::None,
+                _ if value.eq_ignore_ascii_case("critical") => PrivacyValue::Critical,
+                _ if value.eq_ignore_ascii_case("id") => PrivacyValue::Id,
+                _ if value.eq_ignore_ascii_case("history") => PrivacyValue::History,
+                _ => PrivacyValue::Other(BytesStr::from_parse(ctx.src, value)),
+            })
+            .collect::<Vec<_>>();
+
+        if values.is_empty() {
+            bail!("empty Privacy header");
+        }
+
+        Ok(("", Self(values)))
+    }
+}
+
+impl ExtendValues for Privacy {
+    fn extend_values(&self, ctx: PrintCtx<'_>, values: &mut OneOrMore) {
+        *values = self.create_values(ctx)
+    }
+
+    fn create_values(&self, _: PrintCtx<'_>) -> OneOrMore {
+        OneOrMore::One(self.to_string().into())
+    }
+}
+
+impl fmt::Display for Privacy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut values = self.0.iter();
+
+        if let Some(value) = values.next() {
+            write!(f, "{}", value)?;
+        }
+
+        for value in values {
+            write!(f, ";{}", value)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Headers;
+
+    #[test]
+    fn privacy() {
+        let mut headers = Headers::new();
+        headers.insert(Name::PRIVACY, "id ; header;critical");
+
+        let privacy: Privacy = headers.get_named().unwrap();
+        assert_eq!(
+            privacy.0,
+            [
+                PrivacyValue::Id,
+                PrivacyValue::Header,
+                PrivacyValue::Critical
+            ]
+        );
+        assert!(privacy.is_requested());
+
+        let mut headers = Headers::new();
+        headers.insert_named(&privacy);
+        assert_eq!(headers.to_string(), "Privacy: id;header;critical\r\n");
+    }
+
+    #[test]
+    fn privacy_none() {
+        let mut headers = Headers::new();
+        headers.insert(Name::PRIVACY, "none");
+
+        let privacy: Privacy = headers.get_named().unwrap();
+        assert!(!privacy.is_requested());
+    }
+}
@@ -0,0 +1,229 @@
+//! [RFC3326](https://datatracker.ietf.org/doc/html/rfc3326)
+
+use crate::code::Code;
+use crate::header::headers::OneOrMore;
+use crate::header::name::Name;
+use crate::header::{ConstNamed, ExtendValues, HeaderParse};
+use crate::parse::{token, ParseCtx};
+use crate::print::PrintCtx;
+use crate::uri::params::{Params, CPS};
+use anyhow::Result;
+use bytesstr::BytesStr;
+use internal::ws;
+use nom::bytes::complete::take_while1;
+use nom::combinator::map;
+use nom::Finish;
+use std::fmt;
+
+/// Protocol of the cause contained in a [`Reason`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReasonProtocol {
+    /// The cause is a SIP status code
+    Sip,
+    /// The cause is an ITU-T Q.850 cause value, e.g. 16 (normal call clearing)
+    Q850,
+    Other(BytesStr),
+}
+
+impl fmt::Display for ReasonProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReasonProtocol::Sip => f.write_str("SIP"),
+            ReasonProtocol::Q850 => f.write_str("Q.850"),
+            ReasonProtocol::Other(other) => f.write_str(other),
+        }
+    }
+}
+
+/// `Reason` header, why a request (e.g. BYE or CANCEL) was sent or a response was generated
+///
+/// A message may contain one `Reason` per protocol, use `Vec<Reason>` to get or insert all of them.
+#[derive(Debug, Clone)]
+pub struct Reason {
+    pub protocol: ReasonProtocol,
+    pub cause: Option<u16>,
+    /// Unescaped value of the `text` parameter
+    pub text: Option<BytesStr>,
+    pub params: Params<CPS>,
+}
+
+impl Reason {
+    pub fn new(protocol: ReasonProtocol, cause: u16) -> Self {
+        Self {
+            protocol,
+            cause: Some(cause),
+            text: None,
+            params: Params::new(),
+        }
+    }
+
+    /// Create a `Reason` with a SIP status code as cause
+    pub fn sip(code: Code) -> Self {
+        Self {
+            text: code.text().map(BytesStr::from_static),
+            ..Self::new(ReasonProtocol::Sip, code.into_u16())
+        }
+    }
+
+    /// Create a `Reason` with a Q.850 cause value
+    pub fn q850(cause: u16) -> Self {
+        Self::new(ReasonProtocol::Q850, cause)
+    }
+
+    pub fn with_text<T: Into<BytesStr>>(mut self, text: T) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Returns the cause as SIP status code, if the protocol is SIP
+    pub fn sip_code(&self) -> Option<Code> {
+        match (&self.protocol, self.cause) {
+            (ReasonProtocol::Sip, Some(cause)) => Some(Code::from(cause)),
+            _ => None,
+        }
+    }
+
+    /// Returns the Q.850 cause value, if the protocol is Q.850
+    pub fn q850_cause(&self) -> Option<u16> {
+        match self.protocol {
+            ReasonProtocol::Q850 => self.cause,
+            _ => None,
+        }
+    }
+}
+
+impl ConstNamed for Reason {
+    const NAME: Name = Name::REASON;
+}
+
+impl HeaderParse for Reason {
+    fn parse<'i>(ctx: ParseCtx<'_>, i: &'i str) -> Result<(&'i str, Self)> {
+        let (rem, reason) = map(
+            ws((take_while1(token), Params::<CPS>::parse(ctx))),
+            |(protocol, mut params)| {
+                let protocol = match protocol {
+                    _ if protocol.eq_ignore_ascii_case("SIP") => ReasonProtocol::Sip,
+                    _ if protocol.eq_ignore_ascii_case("Q.850") => ReasonProtocol::Q850,
+                    _ => ReasonProtocol::Other(BytesStr::from_parse(ctx.src, protocol)),
+                };
+
+                Self {
+                    protocol,
+                    cause: params.take("cause").and_then(|v| v.parse().ok()),
+                    text: params.take("text").map(unescape),
+                    params,
+                }
+            },
+        )(i)
+        .finish()?;
+
+        Ok((rem, reason))
+    }
+}
+
+fn unescape(text: BytesStr) -> BytesStr {
+    if !text.contains('\\') {
+        return text;
+    }
+
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            unescaped.extend(chars.next());
+        } else {
+            unescaped.push(c);
+        }
+    }
+
+    unescaped.into()
+}
+
+impl ExtendValues for Reason {
+    fn extend_values(&self, _: PrintCtx<'_>, values: &mut OneOrMore) {
+        let value = match values {
+            OneOrMore::One(value) => value,
+            OneOrMore::More(values) => values.last_mut().expect("empty OneOrMore::More variant"),
+        };
+
+        *value = format!("{}, {}", value, self).into();
+    }
+
+    fn create_values(&self, _: PrintCtx<'_>) -> OneOrMore {
+        OneOrMore::One(self.to_string().into())
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.protocol)?;
+
+        if let Some(cause) = self.cause {
+            write!(f, ";cause={}", cause)?;
+        }
+
+        if let Some(text) = &self.text {
+            f.write_str(";text=\"")?;
+
+            for c in text.chars() {
+                if matches!(c, '"' | '\\') {
+                    f.write_str("\\")?;
+                }
+
+                write!(f, "{}", c)?;
+            }
+
+            f.write_str("\"")?;
+        }
+
+        write!(f, "{}", self.params)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Headers;
+
+    #[test]
+    fn reason_multiple() {
+        let mut headers = Headers::new();
+        headers.insert(
+            Name::REASON,
+            "SIP ;cause=200 ;text=\"Call completed, elsewhere\", Q.850;cause=16",
+        );
+
+        let reasons: Vec<Reason> = headers.get_named().unwrap();
+        assert_eq!(reasons.len(), 2);
+
+        assert_eq!(reasons[0].sip_code(), Some(Code::OK));
+        assert_eq!(
+            reasons[0].text.as_deref(),
+            Some("Call completed, elsewhere")
+        );
+        assert_eq!(reasons[1].q850_cause(), Some(16));
+        assert!(reasons[1].text.is_none());
+
+        let mut headers = Headers::new();
+        headers.insert_named(&reasons);
+        assert_eq!(
+            headers.to_string(),
+            "Reason: SIP;cause=200;text=\"Call completed, elsewhere\", Q.850;cause=16\r\n"
+        );
+    }
+
+    #[test]
+    fn reason_escaped_text() {
+        let mut headers = Headers::new();
+        headers.insert_named(&Reason::q850(31).with_text("say \"bye\""));
+
+        assert_eq!(
+            headers.to_string(),
+            "Reason: Q.850;cause=31;text=\"say \\\"bye\\\"\"\r\n"
+        );
+
+        let reason: Reason = headers.get_named().unwrap();
+        assert_eq!(reason.text.as_deref(), Some("say \"bye\""));
+    }
+}
@@ -0,0 +1,108 @@
+//! [RFC3515](https://datatracker.ietf.org/doc/html/rfc3515) & [RFC3892](https://datatracker.ietf.org/doc/html/rfc3892)
+
+use crate::header::headers::OneOrMore;
+use crate::header::name::Name;
+use crate::header::{ConstNamed, ExtendValues, HeaderParse};
+use crate::parse::ParseCtx;
+use crate::print::{AppendCtx, Print, PrintCtx};
+use crate::uri::params::{Params, CPS};
+use crate::uri::NameAddr;
+use anyhow::Result;
+use nom::combinator::map;
+use nom::sequence::tuple;
+use nom::Finish;
+use std::fmt;
+
+macro_rules! refer_header {
+    ($(#[$meta:meta])* $struct_name:ident, $header_name:expr) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone)]
+        pub struct $struct_name {
+            pub uri: NameAddr,
+            pub params: Params<CPS>,
+        }
+
+        impl $struct_name {
+            pub fn new(uri: NameAddr) -> Self {
+                Self {
+                    uri,
+                    params: Params::new(),
+                }
+            }
+        }
+
+        impl ConstNamed for $struct_name {
+            const NAME: Name = $header_name;
+        }
+
+        impl HeaderParse for $struct_name {
+            fn parse<'i>(ctx: ParseCtx<'_>, i: &'i str) -> Result<(&'i str, Self)> {
+                let (rem, header) = map(
+                    tuple((NameAddr::parse_no_params(ctx), Params::<CPS>::parse(ctx))),
+                    |(uri, params)| Self { uri, params },
+                )(i)
+                .finish()?;
+
+                Ok((rem, header))
+            }
+        }
+
+        impl ExtendValues for $struct_name {
+            fn extend_values(&self, ctx: PrintCtx<'_>, values: &mut OneOrMore) {
+                *values = self.create_values(ctx)
+            }
+
+            fn create_values(&self, ctx: PrintCtx<'_>) -> OneOrMore {
+                OneOrMore::One(self.print_ctx(ctx).to_string().into())
+            }
+        }
+
+        impl Print for $struct_name {
+            fn print(&self, f: &mut fmt::Formatter<'_>, mut ctx: PrintCtx<'_>) -> fmt::Result {
+                // Print the URI without context to keep any embedded headers (e.g. Replaces)
+                ctx.uri = None;
+                write!(f, "{}{}", self.uri.print_ctx(ctx), self.params)
+            }
+        }
+    };
+}
+
+refer_header! {
+    /// `Refer-To` header, contains the URI the recipient of a REFER request should contact
+    ReferTo,
+    Name::REFER_TO
+}
+
+refer_header! {
+    /// `Referred-By` header, identifies the party sending a REFER request
+    ReferredBy,
+    Name::REFERRED_BY
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::uri::sip::SipUri;
+    use crate::Headers;
+
+    #[test]
+    fn refer_to() {
+        let mut headers = Headers::new();
+        headers.insert(
+            Name::REFER_TO,
+            "<sip:bob@example.org?Replaces=abc%40example.org%3Bto-tag%3D1%3Bfrom-tag%3D2>",
+        );
+
+        let refer_to: ReferTo = headers.get_named().unwrap();
+        let uri: &SipUri = refer_to.uri.uri.downcast_ref().unwrap();
+        assert!(uri.header_params.get("Replaces").is_some());
+
+        let mut headers = Headers::new();
+        headers.insert_named(&refer_to);
+
+        assert_eq!(
+            headers.to_string(),
+            "Refer-To: <sip:bob@example.org?Replaces=abc%40example.org%3Bto-tag%3D1%3Bfrom-tag%3D2>\r\n"
+        );
+    }
+}
@@ -122,4 +122,22 @@ mod test {
         assert!(routing[1].params.is_empty());
         assert_eq!(routing[1].uri.name, None)
     }
+
+    #[test]
+    fn parse_path() {
+        let mut headers = Headers::new();
+        headers.insert(Name::PATH, "<sip:example.org;lr>");
+        headers.insert(Name::PATH, "<sip:example.org>");
+
+        let path: Vec<Routing> = headers.get(Name::PATH).unwrap();
+        assert_eq!(path.len(), 2);
+
+        let mut echoed = Headers::new();
+        headers.clone_into(&mut echoed, Name::PATH).unwrap();
+
+        assert_eq!(
+            echoed.to_string(),
+            "Path: <sip:example.org;lr>\r\nPath: <sip:example.org>\r\n"
+        );
+    }
 }
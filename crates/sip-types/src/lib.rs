@@ -16,6 +16,7 @@ pub mod header;
 pub mod host;
 mod method;
 pub mod msg;
+pub mod multipart;
 pub mod parse;
 
 pub use code::Code;
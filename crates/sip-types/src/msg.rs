@@ -5,7 +5,7 @@ use crate::method::Method;
 use crate::parse::{token, whitespace, ParseCtx};
 use crate::print::{AppendCtx, Print, PrintCtx};
 use crate::uri::Uri;
-use crate::Name;
+use crate::{Headers, Name};
 use anyhow::Result;
 use bytes::Bytes;
 use bytesstr::BytesStr;
@@ -13,13 +13,13 @@ use internal::ws;
 use internal::IResult;
 use memchr::memchr2;
 use nom::branch::alt;
-use nom::bytes::complete::{tag, take_while};
+use nom::bytes::complete::{tag, take_while, take_while1};
 use nom::character::complete::char;
 use nom::combinator::{map, map_res, opt};
 use nom::sequence::{preceded, separated_pair, terminated, tuple};
-use nom::AsChar;
+use nom::{AsChar, Finish};
 use std::fmt;
-use std::str::FromStr;
+use std::str::{from_utf8, FromStr};
 
 fn not_newline(c: char) -> bool {
     !matches!(c, '\n' | '\r')
@@ -28,7 +28,8 @@ fn not_newline(c: char) -> bool {
 /// Represents a header `header-name: header-value` line inside a message
 ///
 /// When using [`PullParser`] to extract lines from a SIP message this type should be used to
-/// parse the [`Name`] and remaining value from it.
+/// parse the [`Name`] and remaining value from it. Values folded over multiple lines are
+/// unfolded, replacing each line break and its surrounding whitespace with a single space.
 ///
 /// # Example
 ///
@@ -72,15 +73,160 @@ pub struct Line {
 impl Line {
     pub fn parse<'i>(src: &Bytes, i: &'i str) -> IResult<&'i str, Self> {
         map(
-            ws((take_while(token), char(':'), |i| Ok(("", i)))),
-            |(name, _, value)| Line {
+            ws((take_while1(token), char(':'), |i| Ok(("", i)))),
+            |(name, _, value): (&str, _, &str)| Line {
                 name: BytesStr::from_parse(src, name).into(),
-                value: BytesStr::from_parse(src, value),
+                value: unfold(src, value.trim_end_matches(whitespace)),
             },
         )(i)
     }
 }
 
+fn unfold(src: &Bytes, value: &str) -> BytesStr {
+    if !value.contains(['\r', '\n']) {
+        return BytesStr::from_parse(src, value);
+    }
+
+    let mut unfolded = String::with_capacity(value.len());
+
+    for part in value
+        .split(['\r', '\n'])
+        .map(|part| part.trim_matches([' ', '\t']))
+        .filter(|part| !part.is_empty())
+    {
+        if !unfolded.is_empty() {
+            unfolded.push(' ');
+        }
+
+        unfolded.push_str(part);
+    }
+
+    unfolded.into()
+}
+
+/// Recoverable deviation from the SIP grammar, recorded instead of rejecting the message when
+/// parsing a [`MessageHead`] with a parser which is not [strict](crate::parse::Parser::strict)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Deviation {
+    /// Empty lines preceding the message line were skipped
+    LeadingEmptyLines,
+    /// The header line with the given index contained invalid UTF-8 and was skipped
+    InvalidUtf8 { line: usize },
+    /// The header line with the given index couldn't be parsed and was skipped
+    MalformedHeader { line: usize, content: BytesStr },
+}
+
+/// Error returned by [`MessageHead::parse`]
+#[derive(Debug, thiserror::Error)]
+pub enum HeadError {
+    #[error("message head is incomplete")]
+    Incomplete,
+    #[error("message head is missing the message line")]
+    MissingMessageLine,
+    #[error("message head contains invalid UTF-8")]
+    InvalidUtf8,
+    #[error("invalid message line {0:?}")]
+    InvalidMessageLine(BytesStr),
+    #[error("malformed header line {0:?}")]
+    MalformedHeader(BytesStr),
+}
+
+/// Message line and headers of a SIP message
+#[derive(Debug)]
+pub struct MessageHead {
+    pub line: MessageLine,
+
+    /// All headers found inside the message head, neither parsed nor validated
+    pub headers: Headers,
+
+    /// Index of the first byte after the message head, where the body begins
+    pub head_end: usize,
+
+    /// Deviations tolerated when parsing, always empty if the parser is strict
+    pub deviations: Vec<Deviation>,
+}
+
+impl MessageHead {
+    /// Parse the message head at the beginning of the `ctx.src` buffer
+    ///
+    /// Leading empty lines and header lines which cannot be parsed are skipped and recorded as
+    /// [`Deviation`]s, unless the parser is [strict](crate::parse::Parser::strict) which fails
+    /// the whole message instead. The message line must always be valid.
+    pub fn parse(ctx: ParseCtx<'_>) -> Result<Self, HeadError> {
+        let src = ctx.src;
+        let lenient = !ctx.parser.strict;
+
+        let mut deviations = vec![];
+
+        let mut start = 0;
+
+        if lenient {
+            start = src
+                .iter()
+                .take_while(|&&b| matches!(b, b'\r' | b'\n'))
+                .count();
+
+            if start > 0 {
+                deviations.push(Deviation::LeadingEmptyLines);
+            }
+        }
+
+        let mut parser = PullParser::new(src, start);
+
+        let mut message_line = None;
+        let mut headers = Headers::new();
+
+        for (index, item) in (&mut parser).enumerate() {
+            let item = item.map_err(|_| HeadError::Incomplete)?;
+
+            let line = match from_utf8(item) {
+                Ok(line) => line,
+                Err(_) if lenient && message_line.is_some() => {
+                    deviations.push(Deviation::InvalidUtf8 { line: index });
+                    continue;
+                }
+                Err(_) => return Err(HeadError::InvalidUtf8),
+            };
+
+            if message_line.is_none() {
+                match MessageLine::parse(ctx)(line) {
+                    Ok((_, line)) => message_line = Some(line),
+                    Err(_) => {
+                        return Err(HeadError::InvalidMessageLine(BytesStr::from_parse(
+                            src, line,
+                        )))
+                    }
+                }
+
+                continue;
+            }
+
+            match Line::parse(src, line).finish() {
+                Ok((_, line)) => headers.insert(line.name, line.value),
+                Err(_) => {
+                    let content = BytesStr::from_parse(src, line);
+
+                    if !lenient {
+                        return Err(HeadError::MalformedHeader(content));
+                    }
+
+                    deviations.push(Deviation::MalformedHeader {
+                        line: index,
+                        content,
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            line: message_line.ok_or(HeadError::MissingMessageLine)?,
+            headers,
+            head_end: parser.head_end(),
+            deviations,
+        })
+    }
+}
+
 /// The leading line of any SIP message
 #[derive(Debug)]
 pub enum MessageLine {
@@ -202,7 +348,7 @@ impl StatusLine {
 /// Simple pull parser which returns all lines in a SIP message.
 ///
 /// > __Note:__ Lines are terminated with either `\n` or `\r\n` followed by anything but a whitespace.
-/// This is a SIP message feature allowing multi-line headers.
+/// > This is a SIP message feature allowing multi-line headers.
 ///
 /// # Examples
 ///
@@ -362,3 +508,121 @@ impl<'i> Iterator for PullParser<'i> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::typed::{CSeq, Contact, FromTo, MaxForwards, Via};
+    use crate::parse::Parser;
+
+    // Shortened version of the wsinv message from RFC4475 section 3.1.1.1
+    const WSINV: &str = concat!(
+        "INVITE sip:vivekg@chair-dnrc.example.com;unknownparam SIP/2.0\r\n",
+        "TO :\r\n",
+        " sip:vivekg@chair-dnrc.example.com ;   tag    = 1918181833n\r\n",
+        "from   : \"J Rosenberg \\\\\\\"\"       <sip:jdrosen@example.com>\r\n",
+        "  ;\r\n",
+        "  tag = 98asjd8\r\n",
+        "MaX-fOrWaRdS: 0068\r\n",
+        "Call-ID: wsinv.ndaksdj@192.0.2.1\r\n",
+        "Content-Length   : 0\r\n",
+        "cseq: 0009\r\n",
+        "  INVITE\r\n",
+        "Via  : SIP  /   2.0\r\n",
+        " /UDP\r\n",
+        "    192.0.2.2;branch=390skdjuw\r\n",
+        "s :\r\n",
+        "NewFangledHeader:   newfangled value\r\n",
+        " continued newfangled value\r\n",
+        "UnknownHeaderWithUnusualValue: ;;,,;;,;\r\n",
+        "m:\"Quoted string \\\"\\\"\" <sip:jdrosen@example.com> ; newparam =\r\n",
+        "      newvalue ;\r\n",
+        "  secondparam ; q = 0.33\r\n",
+        "\r\n",
+    );
+
+    fn parse(msg: &'static [u8], strict: bool) -> Result<MessageHead, HeadError> {
+        let src = Bytes::from_static(msg);
+
+        let parser = Parser {
+            strict,
+            ..Parser::default()
+        };
+
+        MessageHead::parse(ParseCtx::new(&src, parser))
+    }
+
+    #[test]
+    fn torture_whitespace() {
+        let head = parse(WSINV.as_bytes(), true).unwrap();
+
+        assert_eq!(head.head_end, WSINV.len());
+        assert!(head.deviations.is_empty());
+
+        let headers = &head.headers;
+
+        let to: FromTo = headers.get(Name::TO).unwrap();
+        assert_eq!(to.tag.as_deref(), Some("1918181833n"));
+
+        let from: FromTo = headers.get(Name::FROM).unwrap();
+        assert_eq!(from.uri.name.as_deref(), Some("J Rosenberg \\\\\\\""));
+        assert_eq!(from.tag.as_deref(), Some("98asjd8"));
+
+        let max_fwd: MaxForwards = headers.get_named().unwrap();
+        assert_eq!(max_fwd.0, 68);
+
+        let cseq: CSeq = headers.get_named().unwrap();
+        assert_eq!(cseq.cseq, 9);
+        assert_eq!(cseq.method, Method::INVITE);
+
+        let via: Via = headers.get_named().unwrap();
+        assert_eq!(via.transport, "UDP");
+        assert_eq!(via.params.get_val("branch").unwrap(), "390skdjuw");
+
+        let contact: Contact = headers.get_named().unwrap();
+        assert_eq!(contact.uri.name.as_deref(), Some("Quoted string \\\"\\\""));
+        assert_eq!(contact.params.get_val("newparam").unwrap(), "newvalue");
+
+        let (_, value) = headers
+            .iter()
+            .find(|(name, _)| **name == Name::custom("NewFangledHeader", &[]))
+            .unwrap();
+        assert_eq!(value, "newfangled value continued newfangled value");
+    }
+
+    #[test]
+    fn lenient_deviations() {
+        let msg = b"\r\nOPTIONS sip:user@example.com SIP/2.0\r\n\
+            To: sip:user@example.com\r\n\
+            This is not a header\r\n\
+            From: caller<sip:caller@example.com>;tag=323\r\n\
+            Subject: \xff\xfe\r\n\
+            Max-Forwards: 70\r\n\
+            \r\n";
+
+        assert!(matches!(
+            parse(&msg[2..], true),
+            Err(HeadError::MalformedHeader(line)) if line == "This is not a header"
+        ));
+
+        let head = parse(msg, false).unwrap();
+
+        assert_eq!(
+            head.deviations,
+            [
+                Deviation::LeadingEmptyLines,
+                Deviation::MalformedHeader {
+                    line: 2,
+                    content: BytesStr::from_static("This is not a header")
+                },
+                Deviation::InvalidUtf8 { line: 4 },
+            ]
+        );
+
+        assert!(head.headers.contains(&Name::FROM));
+        assert!(!head.headers.contains(&Name::SUBJECT));
+
+        let max_fwd: MaxForwards = head.headers.get_named().unwrap();
+        assert_eq!(max_fwd.0, 70);
+    }
+}
@@ -0,0 +1,402 @@
+//! `multipart/*` message bodies ([RFC2046 Section 5.1](https://datatracker.ietf.org/doc/html/rfc2046#section-5.1))
+//!
+//! SIP requests may carry multiple bodies at once, e.g. an SDP offer together with an ISUP
+//! message or an XML document ([RFC5621](https://datatracker.ietf.org/doc/html/rfc5621)).
+
+use crate::header::typed::ContentType;
+use crate::msg::{Line, PullParser};
+use crate::{Headers, Name};
+use bytes::{BufMut, Bytes, BytesMut};
+use bytesstr::BytesStr;
+use nom::Finish;
+use std::str::from_utf8;
+
+pub const MULTIPART_MIXED: &str = "multipart/mixed";
+pub const MULTIPART_ALTERNATIVE: &str = "multipart/alternative";
+
+#[derive(Debug, thiserror::Error)]
+pub enum MultipartError {
+    #[error("content type is not multipart")]
+    NotMultipart,
+    #[error("content type has no boundary parameter")]
+    MissingBoundary,
+    #[error("body contains no delimiter")]
+    MissingDelimiter,
+    #[error("body part has malformed headers")]
+    MalformedPart,
+}
+
+/// Single part of a multipart body
+#[derive(Debug, Default)]
+pub struct Part {
+    pub headers: Headers,
+    pub body: Bytes,
+}
+
+impl Part {
+    pub fn new<C: Into<BytesStr>>(content_type: C, body: Bytes) -> Self {
+        let mut headers = Headers::new();
+        headers.insert_named(&ContentType(content_type.into()));
+
+        Self { headers, body }
+    }
+
+    /// Set the `Content-Disposition` header, e.g. `signal;handling=optional`
+    pub fn with_disposition<D: Into<BytesStr>>(mut self, disposition: D) -> Self {
+        self.headers.remove(&Name::CONTENT_DISPOSITION);
+        self.headers
+            .insert(Name::CONTENT_DISPOSITION, disposition.into());
+        self
+    }
+
+    /// Content type of the part, parts without one default to `text/plain`
+    pub fn content_type(&self) -> BytesStr {
+        self.headers
+            .get_named::<ContentType>()
+            .map(|ContentType(content_type)| content_type)
+            .unwrap_or_else(|_| BytesStr::from_static("text/plain"))
+    }
+
+    pub fn disposition(&self) -> Option<&BytesStr> {
+        self.headers
+            .iter()
+            .find(|(name, _)| **name == Name::CONTENT_DISPOSITION)
+            .map(|(_, value)| value)
+    }
+}
+
+/// A `multipart/*` body
+#[derive(Debug)]
+pub struct Multipart {
+    /// Media type including the subtype, e.g. `multipart/mixed`
+    pub media_type: BytesStr,
+    pub boundary: BytesStr,
+    pub parts: Vec<Part>,
+}
+
+impl Multipart {
+    /// Create an empty multipart body, the boundary must not occur inside any of the parts
+    pub fn new<M, B>(media_type: M, boundary: B) -> Self
+    where
+        M: Into<BytesStr>,
+        B: Into<BytesStr>,
+    {
+        Self {
+            media_type: media_type.into(),
+            boundary: boundary.into(),
+            parts: vec![],
+        }
+    }
+
+    pub fn with_part(mut self, part: Part) -> Self {
+        self.parts.push(part);
+        self
+    }
+
+    /// Returns the first part with the given media type, e.g. `application/sdp`
+    pub fn find(&self, media_type: &str) -> Option<&Part> {
+        self.parts
+            .iter()
+            .find(|part| media_type_eq(&part.content_type(), media_type))
+    }
+
+    /// `Content-Type` header of a message containing this body
+    pub fn content_type(&self) -> ContentType {
+        ContentType(format!("{};boundary={}", self.media_type, self.boundary).into())
+    }
+
+    /// Parse a multipart body using the content type of the message
+    pub fn parse(content_type: &str, body: &Bytes) -> Result<Self, MultipartError> {
+        let mut params = content_type.split(';');
+
+        let media_type = params.next().unwrap_or_default().trim();
+
+        if !media_type
+            .get(..10)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("multipart/"))
+        {
+            return Err(MultipartError::NotMultipart);
+        }
+
+        let boundary = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("boundary"))
+            .map(|(_, value)| value.trim().trim_matches('"'))
+            .filter(|boundary| !boundary.is_empty())
+            .ok_or(MultipartError::MissingBoundary)?;
+
+        let delimiter = format!("--{boundary}");
+        let delimiter = delimiter.as_bytes();
+
+        // The first delimiter may be preceded by a preamble
+        let mut pos = if body.starts_with(delimiter) {
+            0
+        } else {
+            find_delimiter(body, delimiter, 0)
+                .ok_or(MultipartError::MissingDelimiter)?
+                .1
+        };
+
+        let mut parts = vec![];
+
+        loop {
+            let after_delimiter = pos + delimiter.len();
+
+            if body[after_delimiter..].starts_with(b"--") {
+                // Close delimiter, the rest is the epilogue
+                break;
+            }
+
+            // Skip transport padding and the line break after the delimiter
+            let part_start = match memchr::memchr(b'\n', &body[after_delimiter..]) {
+                Some(idx) => after_delimiter + idx + 1,
+                None => return Err(MultipartError::MissingDelimiter),
+            };
+
+            let (part_end, next) = find_delimiter(body, delimiter, part_start)
+                .ok_or(MultipartError::MissingDelimiter)?;
+
+            parts.push(parse_part(body.slice(part_start..part_end))?);
+
+            pos = next;
+        }
+
+        Ok(Self {
+            media_type: media_type.into(),
+            boundary: boundary.into(),
+            parts,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+
+        for part in &self.parts {
+            buf.put_slice(b"--");
+            buf.put_slice(self.boundary.as_bytes());
+            buf.put_slice(b"\r\n");
+            buf.put_slice(part.headers.to_string().as_bytes());
+            buf.put_slice(b"\r\n");
+            buf.put_slice(&part.body);
+            buf.put_slice(b"\r\n");
+        }
+
+        buf.put_slice(b"--");
+        buf.put_slice(self.boundary.as_bytes());
+        buf.put_slice(b"--\r\n");
+
+        buf.freeze()
+    }
+}
+
+/// Returns the body of the given media type, which is either the body itself or one of its parts
+/// if it is a multipart body
+pub fn find_body(content_type: &str, body: &Bytes, media_type: &str) -> Option<Bytes> {
+    if media_type_eq(content_type, media_type) {
+        return Some(body.clone());
+    }
+
+    let multipart = Multipart::parse(content_type, body).ok()?;
+
+    multipart.find(media_type).map(|part| part.body.clone())
+}
+
+/// Compare the media type of a content type (which may contain parameters) to the given one
+fn media_type_eq(content_type: &str, media_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case(media_type))
+}
+
+/// Find the next delimiter starting at `from`, which must be at the beginning of a line
+///
+/// Returns the end of the preceding part (excluding the line break which belongs to the
+/// delimiter) and the position of the delimiter.
+fn find_delimiter(body: &[u8], delimiter: &[u8], from: usize) -> Option<(usize, usize)> {
+    let mut search = from;
+
+    loop {
+        let idx = search + memchr::memmem::find(&body[search..], delimiter)?;
+
+        if idx == from {
+            // Empty part directly followed by the next delimiter
+            return Some((idx, idx));
+        }
+
+        if body[idx - 1] == b'\n' {
+            let end = if idx >= 2 && body[idx - 2] == b'\r' {
+                idx - 2
+            } else {
+                idx - 1
+            };
+
+            return Some((end.max(from), idx));
+        }
+
+        search = idx + 1;
+    }
+}
+
+fn parse_part(part: Bytes) -> Result<Part, MultipartError> {
+    let mut headers = Headers::new();
+
+    // A part without headers starts with an empty line
+    let body_start = if part.starts_with(b"\r\n") {
+        2
+    } else if part.starts_with(b"\n") {
+        1
+    } else {
+        let mut parser = PullParser::new(&part, 0);
+
+        for line in &mut parser {
+            let line = line.map_err(|_| MultipartError::MalformedPart)?;
+            let line = from_utf8(line).map_err(|_| MultipartError::MalformedPart)?;
+
+            let (_, line) = Line::parse(&part, line)
+                .finish()
+                .map_err(|_| MultipartError::MalformedPart)?;
+
+            headers.insert(line.name, line.value);
+        }
+
+        parser.head_end()
+    };
+
+    Ok(Part {
+        headers,
+        body: part.slice(body_start..),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BODY: &[u8] = b"preamble\r\n\
+--unique-boundary-1\r\n\
+Content-Type: application/sdp\r\n\
+\r\n\
+v=0\r\n\
+o=- 1 1 IN IP4 192.0.2.1\r\n\
+\r\n\
+--unique-boundary-1\r\n\
+Content-Type: application/isup;version=itu-t92+\r\n\
+Content-Disposition: signal;handling=optional\r\n\
+\r\n\
+\x01\x00\x49\x00\r\n\
+--unique-boundary-1--\r\n\
+epilogue";
+
+    #[test]
+    fn parse_mixed() {
+        let body = Bytes::from_static(BODY);
+
+        let multipart =
+            Multipart::parse("multipart/mixed; boundary=\"unique-boundary-1\"", &body).unwrap();
+
+        assert_eq!(multipart.media_type, MULTIPART_MIXED);
+        assert_eq!(multipart.boundary, "unique-boundary-1");
+        assert_eq!(multipart.parts.len(), 2);
+
+        let sdp = &multipart.parts[0];
+        assert_eq!(sdp.content_type(), "application/sdp");
+        assert_eq!(sdp.body, "v=0\r\no=- 1 1 IN IP4 192.0.2.1\r\n");
+        assert!(sdp.disposition().is_none());
+
+        let isup = &multipart.parts[1];
+        assert_eq!(isup.content_type(), "application/isup;version=itu-t92+");
+        assert_eq!(isup.body, &b"\x01\x00\x49\x00"[..]);
+        assert_eq!(isup.disposition().unwrap(), "signal;handling=optional");
+
+        assert!(multipart.find("application/isup").is_some());
+        assert!(multipart.find("application/pidf+xml").is_none());
+    }
+
+    #[test]
+    fn parse_lf_and_no_headers() {
+        let body = Bytes::from_static(
+            b"--b\n\nplain text\n--b\nContent-Type: application/xml\n\n<a/>\n--b--",
+        );
+
+        let multipart = Multipart::parse("multipart/alternative;boundary=b", &body).unwrap();
+
+        assert_eq!(multipart.parts.len(), 2);
+        assert_eq!(multipart.parts[0].content_type(), "text/plain");
+        assert_eq!(multipart.parts[0].body, "plain text");
+        assert_eq!(multipart.parts[1].body, "<a/>");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let body = Bytes::from_static(b"--b\r\n\r\ntext\r\n");
+
+        assert!(matches!(
+            Multipart::parse("application/sdp", &body),
+            Err(MultipartError::NotMultipart)
+        ));
+        assert!(matches!(
+            Multipart::parse("multipart/mixed", &body),
+            Err(MultipartError::MissingBoundary)
+        ));
+        assert!(matches!(
+            Multipart::parse("multipart/mixed;boundary=b", &body),
+            Err(MultipartError::MissingDelimiter)
+        ));
+    }
+
+    #[test]
+    fn print_and_parse() {
+        let multipart = Multipart::new(MULTIPART_MIXED, "boundary42")
+            .with_part(Part::new("application/sdp", Bytes::from_static(b"v=0\r\n")))
+            .with_part(
+                Part::new("application/pidf+xml", Bytes::from_static(b"<presence/>"))
+                    .with_disposition("render;handling=optional"),
+            );
+
+        let ContentType(content_type) = multipart.content_type();
+        assert_eq!(content_type, "multipart/mixed;boundary=boundary42");
+
+        let body = multipart.to_bytes();
+
+        assert_eq!(
+            body,
+            "--boundary42\r\n\
+Content-Type: application/sdp\r\n\
+\r\n\
+v=0\r\n\
+\r\n\
+--boundary42\r\n\
+Content-Type: application/pidf+xml\r\n\
+Content-Disposition: render;handling=optional\r\n\
+\r\n\
+<presence/>\r\n\
+--boundary42--\r\n"
+        );
+
+        let parsed = Multipart::parse(&content_type, &body).unwrap();
+
+        assert_eq!(parsed.parts.len(), 2);
+        assert_eq!(parsed.parts[0].body, "v=0\r\n");
+        assert_eq!(parsed.parts[1].body, "<presence/>");
+        assert_eq!(
+            parsed.parts[1].disposition().unwrap(),
+            "render;handling=optional"
+        );
+    }
+
+    #[test]
+    fn find_body_in_multipart() {
+        let body = Bytes::from_static(BODY);
+        let content_type = "multipart/mixed;boundary=unique-boundary-1";
+
+        let sdp = find_body(content_type, &body, "application/sdp").unwrap();
+        assert!(sdp.starts_with(b"v=0"));
+
+        let plain = Bytes::from_static(b"v=0\r\n");
+        assert_eq!(
+            find_body("application/sdp", &plain, "application/sdp").unwrap(),
+            plain
+        );
+    }
+}
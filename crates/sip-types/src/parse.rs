@@ -2,17 +2,18 @@
 //! Parsing utilities for SIP message components
 
 use crate::uri::sip::SipUri;
+use crate::uri::tel::TelUri;
 use crate::uri::Uri;
 use bytes::Bytes;
 use internal::{IResult, ParseError};
 use nom::branch::alt;
 use nom::bytes::complete::{escaped, is_not};
-use nom::character::complete::char;
+use nom::character::complete::{anychar, char};
 use nom::combinator::map;
 use nom::sequence::delimited;
 
 pub(crate) fn parse_quoted(i: &str) -> IResult<&str, &str> {
-    delimited(char('"'), escaped(is_not("\""), '\\', char('"')), char('"'))(i)
+    delimited(char('"'), escaped(is_not("\"\\"), '\\', anychar), char('"'))(i)
 }
 
 pub(crate) fn whitespace(c: char) -> bool {
@@ -30,6 +31,10 @@ pub(crate) fn token(c: char) -> bool {
 pub struct Parser {
     pub parse_other_uri: fn(&str) -> IResult<&str, Box<dyn Uri>>,
     pub parse_other_uri_no_params: fn(&str) -> IResult<&str, Box<dyn Uri>>,
+
+    /// Reject messages with recoverable deviations from the SIP grammar instead of skipping and
+    /// recording them, see [`MessageHead::parse`](crate::msg::MessageHead::parse)
+    pub strict: bool,
 }
 
 fn fail(_: &str) -> IResult<&str, Box<dyn Uri>> {
@@ -43,6 +48,7 @@ impl Default for Parser {
         Self {
             parse_other_uri: fail,
             parse_other_uri_no_params: fail,
+            strict: false,
         }
     }
 }
@@ -73,6 +79,7 @@ impl<'p> ParseCtx<'p> {
         move |i| {
             alt((
                 map(SipUri::parse(self), |uri| -> Box<dyn Uri> { Box::new(uri) }),
+                map(TelUri::parse(self), |uri| -> Box<dyn Uri> { Box::new(uri) }),
                 self.parser.parse_other_uri,
             ))(i)
         }
@@ -84,6 +91,9 @@ impl<'p> ParseCtx<'p> {
                 map(SipUri::parse_no_params(self), |uri| -> Box<dyn Uri> {
                     Box::new(uri)
                 }),
+                map(TelUri::parse_no_params(self), |uri| -> Box<dyn Uri> {
+                    Box::new(uri)
+                }),
                 self.parser.parse_other_uri_no_params,
             ))(i)
         }
@@ -1,4 +1,4 @@
-//! Contains the URI trait, SIP and NameAddr implementation
+//! Contains the URI trait, SIP, tel and NameAddr implementation
 
 use crate::host::HostPort;
 use crate::print::{Print, PrintCtx};
@@ -11,6 +11,7 @@ use std::fmt;
 pub mod params;
 mod name_addr;
 pub mod sip;
+pub mod tel;
 
 pub use name_addr::NameAddr;
 
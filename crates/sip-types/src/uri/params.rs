@@ -215,7 +215,8 @@ encode_set!(header_char, HPS_SET);
 impl ParamsSpec for HPS {
     const FIRST_DELIMITER: &'static str = "?";
     const DELIMITER: &'static str = "&";
-    const CHAR_SPEC: fn(char) -> bool = header_char;
+    // Accept escaped characters when parsing, but keep escaping '%' when printing
+    const CHAR_SPEC: fn(char) -> bool = |c| c == '%' || header_char(c);
     const ENCODE_SET: fn() -> &'static AsciiSet = || &HPS_SET;
 }
 
@@ -269,6 +270,10 @@ impl Param {
     pub(crate) fn write(&self, f: &mut fmt::Formatter<'_>, set: &'static AsciiSet) -> fmt::Result {
         match (&self.name, &self.value) {
             (name, None) => write!(f, "{}", percent_encode(name.as_bytes(), set)),
+            // Quoted values (e.g. `+sip.instance="<urn:...>"`) are printed as is
+            (name, Some(value)) if is_quoted(value) => {
+                write!(f, "{}={}", percent_encode(name.as_bytes(), set), value)
+            }
             (name, Some(value)) => write!(
                 f,
                 "{}={}",
@@ -310,6 +315,10 @@ impl Param {
     }
 }
 
+fn is_quoted(value: &str) -> bool {
+    value.len() >= 2 && value.starts_with('"') && value.ends_with('"')
+}
+
 // helper macro to implement param functions on types that contain one or more Params
 #[doc(hidden)]
 #[macro_export]
@@ -0,0 +1,180 @@
+//! [RFC3966](https://datatracker.ietf.org/doc/html/rfc3966) tel URI
+
+use crate::host::{Host, HostPort};
+use crate::parse::ParseCtx;
+use crate::print::{AppendCtx, Print, PrintCtx};
+use crate::uri::params::{Params, CPS};
+use crate::uri::{Uri, UriInfo};
+use bytesstr::BytesStr;
+use internal::IResult;
+use nom::bytes::complete::{tag_no_case, take_while1};
+use nom::combinator::map;
+use nom::sequence::{preceded, tuple};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// URI describing a telephone number, e.g. `tel:+1-201-555-0123` or `tel:7042;phone-context=example.com`
+#[derive(Clone)]
+pub struct TelUri {
+    /// The number including visual separators
+    pub number: BytesStr,
+    pub params: Params<CPS>,
+}
+
+impl TelUri {
+    pub fn new<N: Into<BytesStr>>(number: N) -> Self {
+        Self {
+            number: number.into(),
+            params: Params::new(),
+        }
+    }
+
+    impl_with_params!(params, param_key, param_value);
+
+    /// Returns if the number is a global number (starts with `+`)
+    pub fn is_global(&self) -> bool {
+        self.number.starts_with('+')
+    }
+
+    /// Returns the number without any visual separators
+    pub fn digits(&self) -> String {
+        self.number
+            .chars()
+            .filter(|c| !visual_separator(*c))
+            .collect()
+    }
+
+    pub fn compare(&self, other: &Self) -> bool {
+        self.digits().eq_ignore_ascii_case(&other.digits())
+            && self.params.get_val("phone-context") == other.params.get_val("phone-context")
+    }
+
+    pub fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| {
+            map(
+                tuple((parse_number, Params::<CPS>::parse(ctx))),
+                |(number, params)| TelUri {
+                    number: BytesStr::from_parse(ctx.src, number),
+                    params,
+                },
+            )(i)
+        }
+    }
+
+    pub fn parse_no_params(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| {
+            map(parse_number, |number| TelUri {
+                number: BytesStr::from_parse(ctx.src, number),
+                params: Params::new(),
+            })(i)
+        }
+    }
+}
+
+fn parse_number(i: &str) -> IResult<&str, &str> {
+    preceded(tag_no_case("tel:"), take_while1(number))(i)
+}
+
+fn number(c: char) -> bool {
+    lookup_table!(c => num; 'A', 'B', 'C', 'D', 'E', 'F', 'a', 'b', 'c', 'd', 'e', 'f', '*', '#', '+', '-', '.', '(', ')')
+}
+
+fn visual_separator(c: char) -> bool {
+    matches!(c, '-' | '.' | '(' | ')')
+}
+
+impl fmt::Debug for TelUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.print_ctx(PrintCtx::default()))
+    }
+}
+
+impl Print for TelUri {
+    fn print(&self, f: &mut fmt::Formatter<'_>, _ctx: PrintCtx<'_>) -> fmt::Result {
+        write!(f, "tel:{}{}", self.number, self.params)
+    }
+}
+
+impl Uri for TelUri {
+    fn info(&self) -> UriInfo<'_> {
+        // tel URIs don't have a host, use the phone-context or the number itself
+        let host = self
+            .params
+            .get_val("phone-context")
+            .cloned()
+            .unwrap_or_else(|| self.number.clone());
+
+        UriInfo {
+            transport: None,
+            secure: false,
+            host_port: HostPort {
+                host: Host::Name(host),
+                port: None,
+            },
+        }
+    }
+
+    fn compare(&self, other: &dyn Uri) -> bool {
+        if let Some(other) = other.downcast_ref::<Self>() {
+            self.compare(other)
+        } else {
+            false
+        }
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Uri> {
+        Box::new(TelUri::clone(self))
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid tel uri")]
+pub struct InvalidTelUri(());
+
+impl FromStr for TelUri {
+    type Err = InvalidTelUri;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = BytesStr::from(s);
+
+        let ctx = ParseCtx::default(&s);
+
+        let res = Self::parse(ctx)(s.as_ref())
+            .map(|(_, uri)| uri)
+            .map_err(|_| InvalidTelUri(()));
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tel_uri() {
+        let uri: TelUri = "tel:+1-201-555-0123".parse().unwrap();
+
+        assert!(uri.is_global());
+        assert_eq!(uri.digits(), "+12015550123");
+        assert_eq!(
+            uri.print_ctx(PrintCtx::default()).to_string(),
+            "tel:+1-201-555-0123"
+        );
+    }
+
+    #[test]
+    fn tel_uri_phone_context() {
+        let uri: TelUri = "tel:7042;phone-context=example.com".parse().unwrap();
+
+        assert!(!uri.is_global());
+        assert_eq!(
+            uri.params.get_val("phone-context").map(BytesStr::as_str),
+            Some("example.com")
+        );
+
+        let other: TelUri = "tel:70-42;phone-context=example.com".parse().unwrap();
+        assert!(uri.compare(&other));
+    }
+}
@@ -4,8 +4,8 @@ use crate::util::{random_sequence_number, random_string};
 use bytes::Bytes;
 use sip_core::transaction::TsxResponse;
 use sip_core::transport::TargetTransportInfo;
-use sip_core::{Endpoint, LayerKey, Request};
-use sip_types::header::typed::{CSeq, CallID, Contact, FromTo, MaxForwards};
+use sip_core::{Endpoint, IncomingRequest, LayerKey, Request};
+use sip_types::header::typed::{CSeq, CallID, Contact, FromTo, MaxForwards, Routing};
 use sip_types::header::HeaderError;
 use sip_types::msg::RequestLine;
 use sip_types::uri::{NameAddr, Uri};
@@ -22,6 +22,9 @@ pub struct ClientDialogBuilder {
     pub local_contact: Contact,
     pub call_id: CallID,
     pub target: Box<dyn Uri>,
+    /// Preloaded route set used for the initial request, e.g. the
+    /// [`Service-Route`](crate::register::Registration::service_route) of a registration
+    pub route_set: Vec<Routing>,
     pub secure: bool,
     pub target_tp_info: TargetTransportInfo,
 }
@@ -44,6 +47,7 @@ impl ClientDialogBuilder {
             call_id: CallID(random_string()),
             secure: target.info().secure,
             target,
+            route_set: vec![],
             target_tp_info: TargetTransportInfo::default(),
         }
     }
@@ -61,6 +65,10 @@ impl ClientDialogBuilder {
         });
         headers.insert_named(&self.local_contact);
 
+        if !self.route_set.is_empty() {
+            headers.insert_type(Name::ROUTE, &self.route_set);
+        }
+
         Request {
             line: RequestLine {
                 method,
@@ -100,4 +108,35 @@ impl ClientDialogBuilder {
 
         Ok(dialog)
     }
+
+    /// Create a dialog from a NOTIFY request received for a SUBSCRIBE request created by this
+    /// builder. Every fork of the subscription creates its own dialog.
+    pub fn create_dialog_from_notify(
+        &mut self,
+        notify: &IncomingRequest,
+    ) -> Result<Dialog, HeaderError> {
+        assert_eq!(notify.line.method, Method::NOTIFY);
+
+        let dialog = Dialog {
+            endpoint: self.endpoint.clone(),
+            dialog_layer: self.dialog_layer,
+            local_cseq: (self.local_cseq + 1).into(),
+            local_fromto: self.local_fromto.clone(),
+            peer_fromto: notify.base_headers.from.clone(),
+            local_contact: self.local_contact.clone(),
+            peer_contact: notify.headers.get_named()?,
+            call_id: self.call_id.clone(),
+            route_set: notify.headers.get(Name::RECORD_ROUTE).unwrap_or_default(),
+            secure: self.secure,
+            target_tp_info: Mutex::new(self.target_tp_info.clone()),
+        };
+
+        let entry = DialogEntry::new(Some(notify.base_headers.cseq.cseq));
+        self.endpoint[self.dialog_layer]
+            .dialogs
+            .lock()
+            .insert(dialog.key(), entry);
+
+        Ok(dialog)
+    }
 }
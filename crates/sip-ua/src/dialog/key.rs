@@ -1,5 +1,6 @@
 use bytesstr::BytesStr;
 use sip_core::IncomingRequest;
+use sip_types::header::typed::Replaces;
 
 #[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct DialogKey {
@@ -17,4 +18,54 @@ impl DialogKey {
             local_tag: base_headers.to.tag.as_ref()?.clone_detach(),
         })
     }
+
+    /// Returns the key of the dialog a `Replaces` header refers to
+    ///
+    /// The `to-tag` of the header is the local tag of the recipient.
+    pub fn from_replaces(replaces: &Replaces) -> Self {
+        Self {
+            call_id: replaces.call_id.clone_detach(),
+            peer_tag: Some(replaces.from_tag.clone_detach()),
+            local_tag: replaces.to_tag.clone_detach(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dialog::test::dialog;
+    use sip_types::{Headers, Name};
+
+    #[tokio::test]
+    async fn replaces_identifies_peer_dialog() {
+        // The same dialog seen from both sides
+        let alice = dialog("1928301774", "a6c85cf");
+        let bob = dialog("a6c85cf", "1928301774");
+
+        let replaces = alice.replaces(false);
+        assert_eq!(DialogKey::from_replaces(&replaces), bob.key());
+        assert_ne!(DialogKey::from_replaces(&replaces), alice.key());
+    }
+
+    #[test]
+    fn from_replaces() {
+        let mut headers = Headers::new();
+        headers.insert(
+            Name::REPLACES,
+            "425928@bobster.example.org;to-tag=7743;from-tag=6472;early-only",
+        );
+
+        let replaces: Replaces = headers.get_named().unwrap();
+        assert!(replaces.early_only);
+
+        assert_eq!(
+            DialogKey::from_replaces(&replaces),
+            DialogKey {
+                call_id: "425928@bobster.example.org".into(),
+                peer_tag: Some("6472".into()),
+                local_tag: "7743".into(),
+            }
+        );
+    }
 }
@@ -3,7 +3,7 @@ use crate::util::{random_sequence_number, random_string};
 use bytesstr::BytesStr;
 use sip_core::transport::{OutgoingResponse, TargetTransportInfo};
 use sip_core::{Endpoint, Error, IncomingRequest, LayerKey, Request, Result};
-use sip_types::header::typed::{CSeq, CallID, Contact, FromTo, MaxForwards, Routing};
+use sip_types::header::typed::{CSeq, CallID, Contact, FromTo, MaxForwards, Replaces, Routing};
 use sip_types::header::HeaderError;
 use sip_types::{Code, Method, Name};
 use std::sync::atomic::{AtomicU32, Ordering};
@@ -115,6 +115,17 @@ impl Dialog {
         }
     }
 
+    /// Create a `Replaces` header which a third party can use to replace this dialog,
+    /// e.g. for an attended transfer
+    pub fn replaces(&self, early_only: bool) -> Replaces {
+        Replaces {
+            call_id: self.call_id.0.clone(),
+            from_tag: self.local_fromto.tag.clone().unwrap_or_default(),
+            to_tag: self.peer_fromto.tag.clone().unwrap_or_default(),
+            early_only,
+        }
+    }
+
     pub fn create_request(&self, method: Method) -> Request {
         let mut request = Request::new(method.clone(), self.peer_contact.uri.uri.clone());
 
@@ -168,6 +179,20 @@ impl Dialog {
 
                 response.msg.headers.insert_named(self.endpoint.supported());
             }
+        } else if matches!(
+            request.line.method,
+            Method::UPDATE | Method::REFER | Method::SUBSCRIBE
+        ) {
+            if let 200..=299 = code.into_u16() {
+                response.msg.headers.insert_named(&self.local_contact);
+
+                if request.base_headers.to.tag.is_none() {
+                    // Add To-tag to success response of dialog creating SUBSCRIBE
+                    response.msg.headers.edit(Name::TO, |to: &mut FromTo| {
+                        to.tag.clone_from(&self.local_fromto.tag);
+                    })?;
+                }
+            }
         }
 
         Ok(response)
@@ -182,3 +207,37 @@ impl Drop for Dialog {
             .remove(&self.key());
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use sip_types::uri::sip::SipUri;
+    use sip_types::uri::NameAddr;
+
+    /// Create a confirmed dialog between `sip:alice@example.org` (local) and
+    /// `sip:bob@example.com` (peer), without any transport
+    pub(crate) fn dialog(local_tag: &'static str, peer_tag: &'static str) -> Dialog {
+        let mut builder = Endpoint::builder();
+        let dialog_layer = builder.add_layer(DialogLayer::default());
+        let endpoint = builder.build();
+
+        let local: SipUri = "sip:alice@example.org".parse().unwrap();
+        let local_contact: SipUri = "sip:alice@192.0.2.1:5060".parse().unwrap();
+        let peer: SipUri = "sip:bob@example.com".parse().unwrap();
+        let peer_contact: SipUri = "sip:bob@192.0.2.2:5060".parse().unwrap();
+
+        Dialog {
+            endpoint,
+            dialog_layer,
+            local_cseq: 1.into(),
+            local_fromto: FromTo::new(NameAddr::uri(local), Some(local_tag.into())),
+            peer_fromto: FromTo::new(NameAddr::uri(peer), Some(peer_tag.into())),
+            local_contact: Contact::new(NameAddr::uri(local_contact)),
+            peer_contact: Contact::new(NameAddr::uri(peer_contact)),
+            call_id: CallID::new("a84b4c76e66710"),
+            route_set: vec![],
+            secure: false,
+            target_tp_info: Default::default(),
+        }
+    }
+}
@@ -0,0 +1,689 @@
+//! STIR/SHAKEN identity assertions ([RFC8224](https://datatracker.ietf.org/doc/html/rfc8224),
+//! [RFC8225](https://datatracker.ietf.org/doc/html/rfc8225), [RFC8588](https://datatracker.ietf.org/doc/html/rfc8588))
+//!
+//! A [`Passport`] is signed by an authentication service using an [`IdentitySigner`] and carried
+//! in the [`Identity`] header. The verification service decodes it and checks the signature using
+//! an [`IdentityVerifier`]. The cryptography and the retrieval of the certificates are left to the
+//! implementations of these traits.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+use sip_types::header::typed::{FromTo, Identity, PAssertedIdentity};
+use sip_types::print::AppendCtx;
+use sip_types::uri::sip::{SipUri, UserPart};
+use sip_types::uri::tel::TelUri;
+use sip_types::uri::Uri;
+use sip_types::{Code, Headers, Name};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Maximum age of a PASSporT accepted by [`verify_headers`] ([RFC8224 Section 6.2.1](https://datatracker.ietf.org/doc/html/rfc8224#section-6.2.1))
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum IdentityError {
+    #[error("no Identity header")]
+    Missing,
+    #[error("malformed PASSporT: {0}")]
+    Malformed(&'static str),
+    #[error("unsupported credential")]
+    UnsupportedCredential,
+    #[error("failed to retrieve the credential: {0}")]
+    BadInfo(String),
+    #[error("PASSporT is older than allowed")]
+    Stale,
+    #[error("PASSporT doesn't match the request's {0}")]
+    Mismatch(&'static str),
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("request has no identity which can be asserted")]
+    NoIdentity,
+    #[error("failed to sign PASSporT: {0}")]
+    Signing(String),
+}
+
+impl IdentityError {
+    /// Returns the status code to reject a request with, when its identity failed to verify
+    pub fn code(&self) -> Code {
+        match self {
+            IdentityError::Missing => Code::USE_IDENTITY_HEADER,
+            IdentityError::Malformed(_)
+            | IdentityError::Mismatch(_)
+            | IdentityError::InvalidSignature => Code::INVALID_IDENTITY_HEADER,
+            IdentityError::UnsupportedCredential => Code::UNSUPPORTED_CREDENTIAL,
+            IdentityError::BadInfo(_) => Code::BAD_IDENTITY_INFO,
+            IdentityError::Stale => Code::FORBIDDEN,
+            IdentityError::NoIdentity | IdentityError::Signing(_) => Code::SERVER_INTERNAL_ERROR,
+        }
+    }
+}
+
+/// Level of trust the authentication service has in the caller's number ([RFC8588 Section 4](https://datatracker.ietf.org/doc/html/rfc8588#section-4))
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attestation {
+    /// `A`: the caller is authenticated and authorized to use the number
+    Full,
+    /// `B`: the caller is authenticated, but not verified to use the number
+    Partial,
+    /// `C`: the call has been received from a gateway
+    Gateway,
+}
+
+impl Attestation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Attestation::Full => "A",
+            Attestation::Partial => "B",
+            Attestation::Gateway => "C",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "A" => Some(Attestation::Full),
+            "B" => Some(Attestation::Partial),
+            "C" => Some(Attestation::Gateway),
+            _ => None,
+        }
+    }
+}
+
+/// Originating or destination identity of a [`Passport`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassportId {
+    /// Canonical telephone number, digits only
+    Tn(String),
+    Uri(String),
+}
+
+impl PassportId {
+    /// Create the identity from a URI, sip URIs with a telephone number user part and tel URIs
+    /// are converted into a canonical telephone number
+    pub fn from_uri(uri: &dyn Uri) -> Self {
+        if let Some(tn) = canonical_tn(uri) {
+            return PassportId::Tn(tn);
+        }
+
+        PassportId::Uri(uri.clone_boxed().default_print_ctx().to_string())
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            PassportId::Tn(tn) => json!({ "tn": tn }),
+            PassportId::Uri(uri) => json!({ "uri": uri }),
+        }
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        if let Some(tn) = value.get("tn").and_then(Value::as_str) {
+            Some(PassportId::Tn(tn.into()))
+        } else {
+            value
+                .get("uri")
+                .and_then(Value::as_str)
+                .map(|uri| PassportId::Uri(uri.into()))
+        }
+    }
+}
+
+/// Returns the telephone number of the URI without any visual separators or leading `+`
+pub fn canonical_tn(uri: &dyn Uri) -> Option<String> {
+    let number = if let Some(tel) = uri.downcast_ref::<TelUri>() {
+        tel.digits()
+    } else if let Some(sip) = uri.downcast_ref::<SipUri>() {
+        match &sip.user_part {
+            UserPart::User(user) => user.to_string(),
+            UserPart::UserPw(user_pw) => user_pw.user.to_string(),
+            UserPart::Empty => return None,
+        }
+    } else {
+        return None;
+    };
+
+    let number = number.strip_prefix('+').unwrap_or(&number);
+
+    let digits: String = number
+        .chars()
+        .filter(|c| !matches!(c, '-' | '.' | '(' | ')'))
+        .collect();
+
+    if !digits.is_empty()
+        && digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '*' || c == '#')
+    {
+        Some(digits)
+    } else {
+        None
+    }
+}
+
+/// Personal Assertion Token ([RFC8225](https://datatracker.ietf.org/doc/html/rfc8225))
+#[derive(Debug, Clone)]
+pub struct Passport {
+    /// Signing algorithm
+    pub alg: String,
+    /// PASSporT extension, `shaken` for SHAKEN PASSporTs
+    pub ppt: Option<String>,
+    /// URL of the certificate used to sign the PASSporT
+    pub x5u: String,
+
+    /// Issued at, seconds since the unix epoch
+    pub iat: u64,
+    pub orig: PassportId,
+    pub dest: Vec<PassportId>,
+
+    /// SHAKEN attestation level
+    pub attest: Option<Attestation>,
+    /// SHAKEN origination identifier
+    pub origid: Option<String>,
+}
+
+impl Passport {
+    /// Create a SHAKEN PASSporT issued now
+    pub fn shaken(
+        attest: Attestation,
+        orig: PassportId,
+        dest: Vec<PassportId>,
+        x5u: String,
+    ) -> Self {
+        Self {
+            alg: "ES256".into(),
+            ppt: Some("shaken".into()),
+            x5u,
+            iat: unix_now(),
+            orig,
+            dest,
+            attest: Some(attest),
+            origid: Some(random_uuid()),
+        }
+    }
+
+    /// Returns the JWS signing input, the base64url encoded header and claims
+    ///
+    /// The JSON objects are serialized with their keys in lexicographic order.
+    pub fn signing_input(&self) -> String {
+        let mut header = Map::new();
+        header.insert("alg".into(), self.alg.clone().into());
+        if let Some(ppt) = &self.ppt {
+            header.insert("ppt".into(), ppt.clone().into());
+        }
+        header.insert("typ".into(), "passport".into());
+        header.insert("x5u".into(), self.x5u.clone().into());
+
+        let mut claims = Map::new();
+        if let Some(attest) = self.attest {
+            claims.insert("attest".into(), attest.as_str().into());
+        }
+        claims.insert(
+            "dest".into(),
+            dest_to_json(&self.dest).unwrap_or_else(|| json!({})),
+        );
+        claims.insert("iat".into(), self.iat.into());
+        claims.insert("orig".into(), self.orig.to_json());
+        if let Some(origid) = &self.origid {
+            claims.insert("origid".into(), origid.clone().into());
+        }
+
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(Value::Object(header).to_string()),
+            URL_SAFE_NO_PAD.encode(Value::Object(claims).to_string())
+        )
+    }
+
+    /// Sign the PASSporT and create the [`Identity`] header carrying it
+    pub async fn sign(&self, signer: &dyn IdentitySigner) -> Result<Identity, IdentityError> {
+        let signing_input = self.signing_input();
+        let signature = signer.sign(signing_input.as_bytes()).await?;
+
+        let token = format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature));
+
+        let mut identity = Identity::new(token, self.x5u.clone()).with_alg(self.alg.clone());
+
+        if let Some(ppt) = &self.ppt {
+            identity = identity.with_ppt(ppt.clone());
+        }
+
+        Ok(identity)
+    }
+
+    /// Decode the PASSporT carried in the [`Identity`] header, without verifying it
+    pub fn decode(identity: &Identity) -> Result<SignedPassport, IdentityError> {
+        let mut parts = identity.token.split('.');
+
+        let (Some(header), Some(claims), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(IdentityError::Malformed("expected compact JWS"));
+        };
+
+        let header = decode_json(header)?;
+        let claims = decode_json(claims)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| IdentityError::Malformed("invalid signature encoding"))?;
+
+        let alg = get_str(&header, "alg").ok_or(IdentityError::Malformed("missing alg"))?;
+
+        if alg != identity.alg() {
+            return Err(IdentityError::Malformed(
+                "alg doesn't match the header parameter",
+            ));
+        }
+
+        let ppt = get_str(&header, "ppt");
+
+        if ppt != identity.ppt.as_deref() {
+            return Err(IdentityError::Malformed(
+                "ppt doesn't match the header parameter",
+            ));
+        }
+
+        let x5u = get_str(&header, "x5u")
+            .or(identity.info.as_deref())
+            .ok_or(IdentityError::Malformed("missing x5u"))?;
+
+        let iat = claims
+            .get("iat")
+            .and_then(Value::as_u64)
+            .ok_or(IdentityError::Malformed("missing iat"))?;
+
+        let orig = claims
+            .get("orig")
+            .and_then(PassportId::from_json)
+            .ok_or(IdentityError::Malformed("missing orig"))?;
+
+        let dest = claims
+            .get("dest")
+            .map(dest_from_json)
+            .ok_or(IdentityError::Malformed("missing dest"))?;
+
+        let attest = get_str(&claims, "attest").and_then(Attestation::from_str);
+
+        if ppt == Some("shaken") && attest.is_none() {
+            return Err(IdentityError::Malformed("missing attest"));
+        }
+
+        let passport = Passport {
+            alg: alg.into(),
+            ppt: ppt.map(Into::into),
+            x5u: x5u.into(),
+            iat,
+            orig,
+            dest,
+            attest,
+            origid: get_str(&claims, "origid").map(Into::into),
+        };
+
+        let signing_input_len = identity.token.len() - signature_len(&identity.token);
+
+        Ok(SignedPassport {
+            passport,
+            signing_input: identity.token[..signing_input_len].to_string(),
+            signature,
+        })
+    }
+}
+
+/// A decoded [`Passport`] with the data required to verify its signature
+#[derive(Debug, Clone)]
+pub struct SignedPassport {
+    pub passport: Passport,
+    pub signing_input: String,
+    pub signature: Vec<u8>,
+}
+
+/// Length of the signature part of a compact JWS including the separating dot
+fn signature_len(token: &str) -> usize {
+    token.rfind('.').map(|idx| token.len() - idx).unwrap_or(0)
+}
+
+fn get_str<'v>(object: &'v Value, key: &str) -> Option<&'v str> {
+    object.get(key).and_then(Value::as_str)
+}
+
+fn decode_json(part: &str) -> Result<Value, IdentityError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(part)
+        .map_err(|_| IdentityError::Malformed("invalid base64url encoding"))?;
+
+    let value: Value =
+        serde_json::from_slice(&bytes).map_err(|_| IdentityError::Malformed("invalid JSON"))?;
+
+    if value.is_object() {
+        Ok(value)
+    } else {
+        Err(IdentityError::Malformed("expected JSON object"))
+    }
+}
+
+/// Destinations are grouped by their type, e.g. `{"tn":["12155551213"]}`
+fn dest_to_json(dest: &[PassportId]) -> Option<Value> {
+    let mut tn = vec![];
+    let mut uri = vec![];
+
+    for id in dest {
+        match id {
+            PassportId::Tn(value) => tn.push(Value::from(value.as_str())),
+            PassportId::Uri(value) => uri.push(Value::from(value.as_str())),
+        }
+    }
+
+    let mut object = Map::new();
+
+    if !tn.is_empty() {
+        object.insert("tn".into(), tn.into());
+    }
+
+    if !uri.is_empty() {
+        object.insert("uri".into(), uri.into());
+    }
+
+    (!object.is_empty()).then_some(Value::Object(object))
+}
+
+fn dest_from_json(value: &Value) -> Vec<PassportId> {
+    let values = |key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+
+    values("tn")
+        .into_iter()
+        .map(PassportId::Tn)
+        .chain(values("uri").into_iter().map(PassportId::Uri))
+        .collect()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn random_uuid() -> String {
+    let bytes = rand::random::<u128>();
+
+    // Set version 4 and the RFC4122 variant
+    let uuid = (bytes & !(0xF << 76) & !(0x3 << 62)) | (0x4 << 76) | (0x2 << 62);
+
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        uuid >> 96,
+        (uuid >> 80) & 0xFFFF,
+        (uuid >> 64) & 0xFFFF,
+        (uuid >> 48) & 0xFFFF,
+        uuid & 0xFFFF_FFFF_FFFF
+    )
+}
+
+/// Signing hook of an authentication service
+#[async_trait::async_trait]
+pub trait IdentitySigner: Send + Sync + 'static {
+    /// URL of the certificate used by [`IdentitySigner::sign`]
+    fn x5u(&self) -> &str;
+
+    /// Sign the JWS signing input, returns the raw signature (for ES256 the 64 byte `R || S`)
+    async fn sign(&self, signing_input: &[u8]) -> Result<Vec<u8>, IdentityError>;
+}
+
+/// Verification hook of a verification service
+#[async_trait::async_trait]
+pub trait IdentityVerifier: Send + Sync + 'static {
+    /// Verify the signature of the PASSporT using the certificate referenced by its `x5u`
+    ///
+    /// Must return [`IdentityError::BadInfo`] if the certificate cannot be retrieved,
+    /// [`IdentityError::UnsupportedCredential`] if it cannot be used and
+    /// [`IdentityError::InvalidSignature`] if the signature doesn't match.
+    async fn verify(&self, passport: &SignedPassport) -> Result<(), IdentityError>;
+}
+
+/// Returns the identity of the originator, the `P-Asserted-Identity` or the `From` header
+fn orig_uri(headers: &Headers) -> Option<Box<dyn Uri>> {
+    let asserted = headers
+        .get_named::<Vec<PAssertedIdentity>>()
+        .unwrap_or_default();
+
+    // Prefer the asserted telephone number
+    if let Some(asserted) = asserted
+        .iter()
+        .find(|identity| canonical_tn(&*identity.0.uri).is_some())
+    {
+        return Some(asserted.0.uri.clone());
+    }
+
+    headers
+        .get::<FromTo>(Name::FROM)
+        .ok()
+        .map(|from| from.uri.uri)
+}
+
+fn dest_uri(headers: &Headers) -> Option<Box<dyn Uri>> {
+    headers.get::<FromTo>(Name::TO).ok().map(|to| to.uri.uri)
+}
+
+/// Create and sign a SHAKEN PASSporT for the request and add it as [`Identity`] header
+///
+/// The originating identity is taken from the `P-Asserted-Identity` or `From` header, the
+/// destination from the `To` header.
+pub async fn sign_headers(
+    signer: &dyn IdentitySigner,
+    attest: Attestation,
+    headers: &mut Headers,
+) -> Result<(), IdentityError> {
+    let orig = orig_uri(headers).ok_or(IdentityError::NoIdentity)?;
+    let dest = dest_uri(headers).ok_or(IdentityError::NoIdentity)?;
+
+    let passport = Passport::shaken(
+        attest,
+        PassportId::from_uri(&*orig),
+        vec![PassportId::from_uri(&*dest)],
+        signer.x5u().into(),
+    );
+
+    let identity = passport.sign(signer).await?;
+    headers.insert_named(&identity);
+
+    Ok(())
+}
+
+/// Verify the [`Identity`] headers of a request, returns the first valid PASSporT
+///
+/// The PASSporT must not be older than `max_age` (see [`DEFAULT_MAX_AGE`]) and its `orig` and
+/// `dest` claims must match the request. If no PASSporT is valid the error of the last one is
+/// returned, which can be used to reject the request with [`IdentityError::code`].
+pub async fn verify_headers(
+    verifier: &dyn IdentityVerifier,
+    headers: &Headers,
+    max_age: Duration,
+) -> Result<Passport, IdentityError> {
+    let identities: Vec<Identity> = headers.get_named().unwrap_or_default();
+
+    let mut result = Err(IdentityError::Missing);
+
+    for identity in &identities {
+        result = verify_identity(verifier, headers, identity, max_age).await;
+
+        if result.is_ok() {
+            break;
+        }
+    }
+
+    result
+}
+
+async fn verify_identity(
+    verifier: &dyn IdentityVerifier,
+    headers: &Headers,
+    identity: &Identity,
+    max_age: Duration,
+) -> Result<Passport, IdentityError> {
+    let signed = Passport::decode(identity)?;
+    let passport = &signed.passport;
+
+    if unix_now().abs_diff(passport.iat) > max_age.as_secs() {
+        return Err(IdentityError::Stale);
+    }
+
+    let orig = orig_uri(headers).ok_or(IdentityError::Mismatch("originator"))?;
+
+    if passport.orig != PassportId::from_uri(&*orig) {
+        return Err(IdentityError::Mismatch("originator"));
+    }
+
+    let dest = dest_uri(headers).ok_or(IdentityError::Mismatch("destination"))?;
+
+    if !passport.dest.contains(&PassportId::from_uri(&*dest)) {
+        return Err(IdentityError::Mismatch("destination"));
+    }
+
+    verifier.verify(&signed).await?;
+
+    Ok(signed.passport)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Signs by appending the key to the signing input, only to test the plumbing
+    struct TestKey;
+
+    const KEY: &[u8] = b"secret";
+
+    #[async_trait::async_trait]
+    impl IdentitySigner for TestKey {
+        fn x5u(&self) -> &str {
+            "https://cert.example.org/passport.cer"
+        }
+
+        async fn sign(&self, signing_input: &[u8]) -> Result<Vec<u8>, IdentityError> {
+            Ok([signing_input, KEY].concat())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl IdentityVerifier for TestKey {
+        async fn verify(&self, passport: &SignedPassport) -> Result<(), IdentityError> {
+            if passport.passport.x5u != self.x5u() {
+                return Err(IdentityError::BadInfo(passport.passport.x5u.clone()));
+            }
+
+            if passport.signature == [passport.signing_input.as_bytes(), KEY].concat() {
+                Ok(())
+            } else {
+                Err(IdentityError::InvalidSignature)
+            }
+        }
+    }
+
+    fn headers() -> Headers {
+        let mut headers = Headers::new();
+        headers.insert(Name::FROM, "<sip:+1-215-555-1212@example.org>;tag=1");
+        headers.insert(Name::TO, "<tel:+12155551213>");
+        headers
+    }
+
+    #[tokio::test]
+    async fn passport_sign_decode() {
+        let mut headers = headers();
+        sign_headers(&TestKey, Attestation::Full, &mut headers)
+            .await
+            .unwrap();
+
+        let identity: Identity = headers.get_named().unwrap();
+        assert_eq!(identity.alg(), "ES256");
+        assert_eq!(identity.ppt.as_deref(), Some("shaken"));
+
+        let signed = Passport::decode(&identity).unwrap();
+        let passport = &signed.passport;
+
+        assert_eq!(passport.orig, PassportId::Tn("12155551212".into()));
+        assert_eq!(passport.dest, [PassportId::Tn("12155551213".into())]);
+        assert_eq!(passport.attest, Some(Attestation::Full));
+        assert_eq!(passport.x5u, TestKey.x5u());
+        assert_eq!(signed.signing_input, passport.signing_input());
+
+        let verified = verify_headers(&TestKey, &headers, DEFAULT_MAX_AGE)
+            .await
+            .unwrap();
+        assert_eq!(verified.origid, passport.origid);
+    }
+
+    #[tokio::test]
+    async fn passport_tampered_claim() {
+        let mut headers = headers();
+        sign_headers(&TestKey, Attestation::Gateway, &mut headers)
+            .await
+            .unwrap();
+
+        let identity: Identity = headers.get_named().unwrap();
+        let mut signed = Passport::decode(&identity).unwrap();
+
+        // Upgrade the attestation, but keep the original signature
+        signed.passport.attest = Some(Attestation::Full);
+        let signing_input = signed.passport.signing_input();
+        let signature = &identity.token[signing_input.len()..];
+        assert!(signature.starts_with('.'));
+
+        let tampered =
+            Identity::new(format!("{signing_input}{signature}"), TestKey.x5u()).with_ppt("shaken");
+
+        // Decoding doesn't verify the signature
+        let decoded = Passport::decode(&tampered).unwrap();
+        assert_eq!(decoded.passport.attest, Some(Attestation::Full));
+
+        headers.remove(&Name::IDENTITY);
+        headers.insert_named(&tampered);
+
+        let err = verify_headers(&TestKey, &headers, DEFAULT_MAX_AGE)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, IdentityError::InvalidSignature));
+        assert_eq!(err.code(), Code::INVALID_IDENTITY_HEADER);
+    }
+
+    #[tokio::test]
+    async fn passport_mismatch() {
+        let mut headers = headers();
+        sign_headers(&TestKey, Attestation::Full, &mut headers)
+            .await
+            .unwrap();
+
+        let identity: Identity = headers.get_named().unwrap();
+
+        let mut other_dest = Headers::new();
+        other_dest.insert(Name::FROM, "<sip:+1-215-555-1212@example.org>;tag=1");
+        other_dest.insert(Name::TO, "<tel:+12155551214>");
+        other_dest.insert_named(&identity);
+
+        let err = verify_headers(&TestKey, &other_dest, DEFAULT_MAX_AGE)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, IdentityError::Mismatch("destination")));
+
+        let mut stale = Passport::decode(&identity).unwrap().passport;
+        stale.iat -= 120;
+
+        let mut stale_headers = self::headers();
+        stale_headers.insert_named(&stale.sign(&TestKey).await.unwrap());
+
+        let err = verify_headers(&TestKey, &stale_headers, DEFAULT_MAX_AGE)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, IdentityError::Stale));
+
+        let err = verify_headers(&TestKey, &self::headers(), DEFAULT_MAX_AGE)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::USE_IDENTITY_HEADER);
+    }
+}
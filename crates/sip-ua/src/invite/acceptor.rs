@@ -1,5 +1,6 @@
 use super::session::Session;
 use super::timer::{AcceptorTimerConfig, SessionTimer};
+use super::update::{create_update, EarlyUpdateUsage};
 use super::{AwaitedAck, AwaitedPrack, Inner, InviteLayer};
 use crate::dialog::{register_usage, Dialog, UsageGuard};
 use crate::invite::session::Role;
@@ -8,9 +9,12 @@ use crate::util::random_sequence_number;
 use bytesstr::BytesStr;
 use parking_lot as pl;
 use sip_core::transaction::consts::T1;
+use sip_core::transaction::{ServerTsx, TsxResponse};
 use sip_core::transport::OutgoingResponse;
-use sip_core::{Endpoint, IncomingRequest, LayerKey, Result};
-use sip_types::header::typed::{RSeq, Require, Supported};
+use sip_core::{Endpoint, IncomingRequest, LayerKey, Request, Result};
+use sip_types::header::typed::{
+    PAssertedIdentity, PPreferredIdentity, RSeq, Reason, Require, Supported,
+};
 use sip_types::{Code, Method};
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot, Mutex};
@@ -37,6 +41,20 @@ pub struct Acceptor {
 
     /// Configuration for `timer` extension
     timer_config: AcceptorTimerConfig,
+
+    /// The INVITE contained `Require: 100rel`
+    peer_requires_100rel: bool,
+
+    /// Identities contained in the INVITE
+    asserted_identity: Vec<PAssertedIdentity>,
+    preferred_identity: Vec<PPreferredIdentity>,
+
+    /// RSeq of the next reliable provisional response
+    next_rseq: u32,
+
+    /// UPDATE requests received inside the early dialog
+    update_rx: mpsc::Receiver<IncomingRequest>,
+    _update_guard: UsageGuard,
 }
 
 impl Drop for Acceptor {
@@ -74,7 +92,18 @@ impl Acceptor {
             .unwrap_or_default();
 
         let peer_supports_timer = supported.iter().any(|ext| ext.0 == "timer");
-        let peer_supports_100rel = supported.iter().any(|ext| ext.0 == "100rel");
+        let peer_requires_100rel = invite
+            .headers
+            .get_named::<Vec<Require>>()
+            .unwrap_or_default()
+            .iter()
+            .any(|ext| ext.0 == "100rel");
+
+        let peer_supports_100rel =
+            peer_requires_100rel || supported.iter().any(|ext| ext.0 == "100rel");
+
+        let asserted_identity = invite.headers.get_named().unwrap_or_default();
+        let preferred_identity = invite.headers.get_named().unwrap_or_default();
 
         // ==== register acceptor usage to dialog
 
@@ -100,13 +129,16 @@ impl Acceptor {
             peer_supports_100rel,
             awaited_ack: pl::Mutex::new(None),
             awaited_prack: pl::Mutex::new(None),
+            cancel_reason: pl::Mutex::new(vec![]),
         });
 
+        endpoint[invite_layer].add_replaceable(dialog_key.clone(), &inner);
+
         // Register the usage to the dialog
         let usage_guard = register_usage(
             endpoint.clone(),
             dialog_layer,
-            dialog_key,
+            dialog_key.clone(),
             InviteUsage {
                 inner: inner.clone(),
             },
@@ -114,6 +146,15 @@ impl Acceptor {
         // Unwrap is safe as we still hold the dialog
         .unwrap();
 
+        let (update_sender, update_rx) = mpsc::channel(4);
+        let update_guard = register_usage(
+            endpoint.clone(),
+            dialog_layer,
+            dialog_key,
+            EarlyUpdateUsage { update_sender },
+        )
+        .unwrap();
+
         // ==== register Inner to the acceptor layer
         endpoint[invite_layer]
             .cancellables
@@ -126,6 +167,12 @@ impl Acceptor {
             usage_guard: Some(usage_guard),
             cancellable_key,
             timer_config: AcceptorTimerConfig::default(),
+            peer_requires_100rel,
+            asserted_identity,
+            preferred_identity,
+            next_rseq: random_sequence_number(),
+            update_rx,
+            _update_guard: update_guard,
         })
     }
 
@@ -133,10 +180,33 @@ impl Acceptor {
         self.inner.peer_supports_100rel
     }
 
+    /// Returns if the peer requires all provisional responses (except 100) to be sent
+    /// using [`Acceptor::respond_provisional_reliable`]
+    pub fn peer_requires_100rel(&self) -> bool {
+        self.peer_requires_100rel
+    }
+
     pub fn peer_supports_timer(&self) -> bool {
         self.inner.peer_supports_timer
     }
 
+    /// Returns the `P-Asserted-Identity` values of the INVITE
+    ///
+    /// These should only be trusted if the INVITE was received from a trusted peer (e.g. a trunk).
+    pub fn asserted_identity(&self) -> &[PAssertedIdentity] {
+        &self.asserted_identity
+    }
+
+    /// Returns the [`Reason`]s of the CANCEL request, if the INVITE was cancelled by the peer
+    pub fn cancel_reason(&self) -> Vec<Reason> {
+        self.inner.cancel_reason.lock().clone()
+    }
+
+    /// Returns the `P-Preferred-Identity` values of the INVITE
+    pub fn preferred_identity(&self) -> &[PPreferredIdentity] {
+        &self.preferred_identity
+    }
+
     pub async fn create_response(
         &self,
         code: Code,
@@ -153,6 +223,70 @@ impl Acceptor {
         }
     }
 
+    /// Receive the next UPDATE request sent by the peer inside the early dialog
+    ///
+    /// The response to it must be created using [`Acceptor::create_update_response`].
+    pub async fn receive_update(&mut self) -> Result<(IncomingRequest, ServerTsx), Error> {
+        let update = self
+            .update_rx
+            .recv()
+            .await
+            .ok_or(Error::RequestTerminated)?;
+
+        let transaction = self.endpoint.create_server_tsx(&update);
+
+        Ok((update, transaction))
+    }
+
+    pub async fn create_update_response(
+        &self,
+        update: &IncomingRequest,
+        code: Code,
+        reason: Option<BytesStr>,
+    ) -> Result<OutgoingResponse, Error> {
+        let state = self.inner.state.lock().await;
+
+        if let InviteSessionState::UasProvisional { dialog, .. } = &*state {
+            dialog
+                .create_response(update, code, reason)
+                .map_err(Error::Core)
+        } else {
+            Err(Error::RequestTerminated)
+        }
+    }
+
+    /// Create an UPDATE request inside the early dialog
+    pub async fn create_update(&self) -> Result<Request, Error> {
+        let state = self.inner.state.lock().await;
+
+        if let InviteSessionState::UasProvisional { dialog, .. } = &*state {
+            Ok(create_update(dialog))
+        } else {
+            Err(Error::RequestTerminated)
+        }
+    }
+
+    /// Send an UPDATE request created using [`Acceptor::create_update`] and return the final
+    /// response to it
+    pub async fn send_update(&self, request: Request) -> Result<TsxResponse, Error> {
+        let mut target_tp_info = {
+            let state = self.inner.state.lock().await;
+
+            if let InviteSessionState::UasProvisional { dialog, .. } = &*state {
+                dialog.target_tp_info.lock().await.clone()
+            } else {
+                return Err(Error::RequestTerminated);
+            }
+        };
+
+        let mut transaction = self
+            .endpoint
+            .send_request(request, &mut target_tp_info)
+            .await?;
+
+        Ok(transaction.receive_final().await?)
+    }
+
     pub async fn respond_provisional(
         &mut self,
         mut response: OutgoingResponse,
@@ -168,6 +302,11 @@ impl Acceptor {
         }
     }
 
+    /// Send a reliable provisional response and wait for the PRACK acknowledging it
+    ///
+    /// The response is retransmitted until the PRACK is received. If none is received after
+    /// `64*T1` the INVITE is rejected with a `504` response and the function returns with an
+    /// error.
     pub async fn respond_provisional_reliable(
         &mut self,
         mut response: OutgoingResponse,
@@ -186,7 +325,8 @@ impl Acceptor {
         let mut state = self.inner.state.lock().await;
 
         if let InviteSessionState::UasProvisional { tsx, invite, .. } = &mut *state {
-            let rack = random_sequence_number();
+            let rack = self.next_rseq;
+            self.next_rseq += 1;
 
             response.msg.headers.insert_named(&Require("100rel".into()));
             response.msg.headers.insert_named(&RSeq(rack));
@@ -201,26 +341,37 @@ impl Acceptor {
 
             tsx.respond_provisional(&mut response).await?;
 
-            let mut prack = None;
             let mut delta = T1;
 
-            for _ in 1..6 {
+            // Retransmit with exponential backoff for 64*T1
+            for _ in 0..5 {
                 match timeout(delta, &mut prack_recv).await {
                     Ok(res) => {
                         // Unwrap is safe as no other function sets `awaiting_prack`
                         // which means the channel will not be dropped
-                        prack = Some(res.unwrap());
-                        break;
+                        return Ok(res.unwrap());
                     }
                     Err(_) => {
                         // retransmit on timeout
                         tsx.respond_provisional(&mut response).await?;
-                        delta = T1 * 2;
+                        delta *= 2;
                     }
                 }
             }
 
-            prack.ok_or(Error::RequestTerminated)
+            if let Ok(prack) = timeout(delta, &mut prack_recv).await {
+                return Ok(prack.unwrap());
+            }
+
+            self.inner.awaited_prack.lock().take();
+
+            // No PRACK received, reject the INVITE
+            if let Some((dialog, tsx, invite)) = state.set_cancelled() {
+                let response = dialog.create_response(&invite, Code::SERVER_TIMEOUT, None)?;
+                tsx.respond_failure(response).await?;
+            }
+
+            Err(Error::Core(sip_core::Error::RequestTimedOut))
         } else {
             Err(Error::RequestTerminated)
         }
@@ -0,0 +1,415 @@
+//! Back-to-back user agent, bridges two [`Session`]s
+//!
+//! A [`B2bua`] relays the re-INVITE, UPDATE and INFO requests received on one leg to the other
+//! leg and relays the responses back. Which headers are copied between the legs is controlled by
+//! a [`RelayFilter`]. Once one leg is terminated, the other leg is terminated as well, using the
+//! `Reason` of the received BYE.
+
+use super::session::{Event, Session};
+use crate::dialog::Dialog;
+use sip_core::transaction::TsxResponse;
+use sip_core::transport::OutgoingResponse;
+use sip_core::{Error, IncomingRequest, Request, Result};
+use sip_types::header::typed::Reason;
+use sip_types::{Code, CodeKind, Headers, Method, Name};
+
+/// One of the two legs of a [`B2bua`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Leg {
+    A,
+    B,
+}
+
+impl Leg {
+    fn other(self) -> Self {
+        match self {
+            Leg::A => Leg::B,
+            Leg::B => Leg::A,
+        }
+    }
+}
+
+/// Hooks to control how messages are relayed between the legs of a [`B2bua`]
+pub trait RelayFilter: Send + Sync + 'static {
+    /// Returns if a header of a message received on one leg is copied to the message sent on
+    /// the `to` leg. Defaults to [`is_end_to_end`].
+    fn copy_header(&self, to: Leg, method: &Method, name: &Name) -> bool {
+        let _ = (to, method);
+        is_end_to_end(name)
+    }
+
+    /// Modify a relayed request before it is sent on the `to` leg
+    fn request(&self, to: Leg, request: &mut Request) {
+        let _ = (to, request);
+    }
+
+    /// Modify a relayed response before it is sent on the `to` leg
+    fn response(&self, to: Leg, response: &mut OutgoingResponse) {
+        let _ = (to, response);
+    }
+}
+
+/// [`RelayFilter`] which only copies the headers describing the body of a message
+pub struct DefaultFilter;
+
+impl RelayFilter for DefaultFilter {}
+
+/// Returns if the header describes the message body and can be copied between the legs
+/// without revealing any information about the other leg
+pub fn is_end_to_end(name: &Name) -> bool {
+    [
+        Name::CONTENT_TYPE,
+        Name::CONTENT_DISPOSITION,
+        Name::CONTENT_ENCODING,
+        Name::CONTENT_LANGUAGE,
+    ]
+    .contains(name)
+}
+
+pub struct B2bua {
+    a: Session,
+    b: Session,
+    filter: Box<dyn RelayFilter>,
+}
+
+impl B2bua {
+    /// Bridge the two established sessions
+    pub fn new(a: Session, b: Session) -> Self {
+        Self {
+            a,
+            b,
+            filter: Box::new(DefaultFilter),
+        }
+    }
+
+    pub fn with_filter<F: RelayFilter>(mut self, filter: F) -> Self {
+        self.filter = Box::new(filter);
+        self
+    }
+
+    pub fn leg(&self, leg: Leg) -> &Session {
+        match leg {
+            Leg::A => &self.a,
+            Leg::B => &self.b,
+        }
+    }
+
+    /// Relay requests between the legs until one of them is terminated
+    ///
+    /// The other leg is then terminated as well. Returns the leg which ended the call.
+    pub async fn run(mut self) -> Result<Leg> {
+        let dialog_a = self.a.dialog.clone();
+        let dialog_b = self.b.dialog.clone();
+
+        loop {
+            let (leg, event) = tokio::select! {
+                event = self.a.drive() => (Leg::A, event?),
+                event = self.b.drive() => (Leg::B, event?),
+            };
+
+            let to = leg.other();
+
+            let other = match to {
+                Leg::A => &dialog_a,
+                Leg::B => &dialog_b,
+            };
+
+            let filter = &*self.filter;
+
+            match event {
+                Event::RefreshNeeded(event) => event.process_default().await?,
+                Event::ReInviteReceived(event) => {
+                    let response = relay_invite(filter, to, other, &event.invite).await;
+                    let response = create_response(
+                        filter,
+                        leg,
+                        &event.session.dialog,
+                        &event.invite,
+                        response,
+                    )?;
+
+                    if response.msg.line.code.kind() == CodeKind::Success {
+                        event.respond_success(response).await?;
+                    } else {
+                        event.transaction.respond_failure(response).await?;
+                    }
+                }
+                Event::UpdateReceived(event) => {
+                    let response = relay_request(filter, to, other, &event.update).await;
+                    let response = create_response(
+                        filter,
+                        leg,
+                        &event.session.dialog,
+                        &event.update,
+                        response,
+                    )?;
+
+                    event.respond(response).await?;
+                }
+                Event::InfoReceived(event) => {
+                    let response = relay_request(filter, to, other, &event.info).await;
+                    let response =
+                        create_response(filter, leg, &event.session.dialog, &event.info, response)?;
+
+                    event.respond(response).await?;
+                }
+                Event::ReferReceived(event) => {
+                    // Transfers must be handled by the application, as they replace a leg
+                    event.decline().await?;
+                }
+                Event::Bye(event) => {
+                    // Forward the reason the call was ended with to the other leg
+                    let reason = event.reason();
+                    event.process_default().await?;
+                    self.terminate_leg(to, &reason).await;
+                    return Ok(leg);
+                }
+                Event::Replaced(session) => {
+                    session.terminate().await?;
+                    self.terminate_leg(to, &[]).await;
+                    return Ok(leg);
+                }
+                Event::Terminated => {
+                    self.terminate_leg(to, &[]).await;
+                    return Ok(leg);
+                }
+            }
+        }
+    }
+
+    /// Terminate both legs
+    pub async fn terminate(mut self) -> Result<()> {
+        let (a, b) = tokio::join!(self.a.terminate(), self.b.terminate());
+
+        a?;
+        b?;
+
+        Ok(())
+    }
+
+    async fn terminate_leg(&mut self, leg: Leg, reason: &[Reason]) {
+        let session = match leg {
+            Leg::A => &mut self.a,
+            Leg::B => &mut self.b,
+        };
+
+        if let Err(e) = session.terminate_with_reason(reason).await {
+            log::warn!("failed to terminate leg {leg:?}, {e}");
+        }
+    }
+}
+
+/// Create the request sent on the `to` leg from a request received on the other leg
+fn create_request(
+    filter: &dyn RelayFilter,
+    to: Leg,
+    dialog: &Dialog,
+    received: &IncomingRequest,
+) -> Request {
+    let method = &received.line.method;
+
+    let mut request = dialog.create_request(method.clone());
+
+    if matches!(method, &Method::INVITE | &Method::UPDATE) {
+        request.headers.insert_named(&dialog.local_contact);
+    }
+
+    copy_headers(filter, to, method, &received.headers, &mut request.headers);
+    request.body = received.body.clone();
+
+    filter.request(to, &mut request);
+
+    request
+}
+
+fn copy_headers(
+    filter: &dyn RelayFilter,
+    to: Leg,
+    method: &Method,
+    src: &Headers,
+    dst: &mut Headers,
+) {
+    for (name, value) in src.iter() {
+        if filter.copy_header(to, method, name) {
+            dst.insert(name.clone(), value.clone());
+        }
+    }
+}
+
+/// Create the response on the leg the request was received on from the relayed response
+fn create_response(
+    filter: &dyn RelayFilter,
+    to: Leg,
+    dialog: &Dialog,
+    request: &IncomingRequest,
+    relayed: Result<TsxResponse>,
+) -> Result<OutgoingResponse> {
+    let relayed = match relayed {
+        Ok(relayed) => relayed,
+        Err(e) => {
+            log::warn!("failed to relay {} request, {e}", request.line.method);
+
+            let code = match e {
+                Error::RequestTimedOut => Code::REQUEST_TIMEOUT,
+                _ => Code::SERVER_INTERNAL_ERROR,
+            };
+
+            return dialog.create_response(request, code, None);
+        }
+    };
+
+    let mut response =
+        dialog.create_response(request, relayed.line.code, relayed.line.reason.clone())?;
+
+    copy_headers(
+        filter,
+        to,
+        &request.line.method,
+        &relayed.headers,
+        &mut response.msg.headers,
+    );
+    response.msg.body = relayed.body;
+
+    filter.response(to, &mut response);
+
+    Ok(response)
+}
+
+async fn relay_request(
+    filter: &dyn RelayFilter,
+    to: Leg,
+    dialog: &Dialog,
+    received: &IncomingRequest,
+) -> Result<TsxResponse> {
+    let request = create_request(filter, to, dialog, received);
+
+    let mut target_tp_info = dialog.target_tp_info.lock().await;
+
+    let mut transaction = dialog
+        .endpoint
+        .send_request(request, &mut target_tp_info)
+        .await?;
+
+    drop(target_tp_info);
+
+    transaction.receive_final().await
+}
+
+async fn relay_invite(
+    filter: &dyn RelayFilter,
+    to: Leg,
+    dialog: &Dialog,
+    received: &IncomingRequest,
+) -> Result<TsxResponse> {
+    let invite = create_request(filter, to, dialog, received);
+
+    let mut target_tp_info = dialog.target_tp_info.lock().await;
+
+    let mut transaction = dialog
+        .endpoint
+        .send_invite(invite, &mut target_tp_info)
+        .await?;
+
+    drop(target_tp_info);
+
+    loop {
+        let response = transaction.receive().await?.ok_or(Error::RequestTimedOut)?;
+
+        match response.line.code.kind() {
+            CodeKind::Provisional => continue,
+            CodeKind::Success => {}
+            // The transaction acknowledges failure responses itself
+            _ => return Ok(response),
+        }
+
+        let mut ack = super::create_ack(dialog, response.base_headers.cseq.cseq).await?;
+        dialog.endpoint.send_outgoing_request(&mut ack).await?;
+
+        // Acknowledge retransmissions of the 2xx response until the transaction terminates
+        let endpoint = dialog.endpoint.clone();
+
+        tokio::spawn(async move {
+            while let Ok(Some(_)) = transaction.receive().await {
+                if let Err(e) = endpoint.send_outgoing_request(&mut ack).await {
+                    log::warn!("failed to retransmit ACK, {e}");
+                }
+            }
+        });
+
+        return Ok(response);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn received() -> Headers {
+        let mut headers = Headers::new();
+        headers.insert(
+            Name::VIA,
+            "SIP/2.0/UDP 192.0.2.1:5060;branch=z9hG4bK776asdhds",
+        );
+        headers.insert(Name::CONTACT, "<sip:alice@192.0.2.1:5060>");
+        headers.insert(Name::CONTENT_TYPE, "application/sdp");
+        headers.insert(Name::CONTENT_DISPOSITION, "session");
+        headers.insert(Name::USER_AGENT, "ezk");
+        headers
+    }
+
+    fn copied(filter: &dyn RelayFilter, to: Leg, method: Method) -> Vec<Name> {
+        let mut dst = Headers::new();
+        copy_headers(filter, to, &method, &received(), &mut dst);
+        dst.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    #[test]
+    fn end_to_end_headers() {
+        assert!(is_end_to_end(&Name::CONTENT_TYPE));
+        assert!(is_end_to_end(&Name::CONTENT_LANGUAGE));
+        assert!(!is_end_to_end(&Name::CONTACT));
+        assert!(!is_end_to_end(&Name::VIA));
+        assert!(!is_end_to_end(&Name::RECORD_ROUTE));
+    }
+
+    #[test]
+    fn default_filter() {
+        assert_eq!(
+            copied(&DefaultFilter, Leg::B, Method::INVITE),
+            [Name::CONTENT_TYPE, Name::CONTENT_DISPOSITION]
+        );
+    }
+
+    #[test]
+    fn custom_filter() {
+        /// Copies the User-Agent to leg A only, and nothing for INFO requests
+        struct Filter;
+
+        impl RelayFilter for Filter {
+            fn copy_header(&self, to: Leg, method: &Method, name: &Name) -> bool {
+                *method != Method::INFO
+                    && (is_end_to_end(name) || (to == Leg::A && *name == Name::USER_AGENT))
+            }
+        }
+
+        assert_eq!(
+            copied(&Filter, Leg::A, Method::UPDATE),
+            [
+                Name::CONTENT_TYPE,
+                Name::CONTENT_DISPOSITION,
+                Name::USER_AGENT
+            ]
+        );
+        assert_eq!(
+            copied(&Filter, Leg::B, Method::UPDATE),
+            [Name::CONTENT_TYPE, Name::CONTENT_DISPOSITION]
+        );
+        assert!(copied(&Filter, Leg::A, Method::INFO).is_empty());
+    }
+
+    #[test]
+    fn other_leg() {
+        assert_eq!(Leg::A.other(), Leg::B);
+        assert_eq!(Leg::B.other(), Leg::A);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::dialog::Dialog;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use sip_core::transaction::TsxResponse;
+use sip_core::{IncomingRequest, Request, Result};
+use sip_types::header::typed::ContentType;
+use sip_types::multipart::Multipart;
+use sip_types::Method;
+
+pub const DTMF_RELAY: &str = "application/dtmf-relay";
+pub const DTMF: &str = "application/dtmf";
+
+/// DTMF tone carried inside the body of an INFO request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dtmf {
+    /// One of `0-9`, `*`, `#` or `A-D`
+    pub signal: char,
+    /// Duration of the tone in milliseconds
+    pub duration: Option<u32>,
+}
+
+impl Dtmf {
+    pub fn new(signal: char, duration: u32) -> Self {
+        Self {
+            signal,
+            duration: Some(duration),
+        }
+    }
+
+    /// Parse an `application/dtmf-relay` or `application/dtmf` body
+    ///
+    /// Returns `None` if the content type is not one of both or the body is malformed
+    pub fn parse(content_type: &str, body: &[u8]) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim();
+        let body = std::str::from_utf8(body).ok()?;
+
+        if mime.eq_ignore_ascii_case(DTMF_RELAY) {
+            let mut signal = None;
+            let mut duration = None;
+
+            for line in body.lines() {
+                let Some((key, value)) = line.split_once('=') else {
+                    continue;
+                };
+
+                let key = key.trim();
+                let value = value.trim();
+
+                if key.eq_ignore_ascii_case("signal") {
+                    signal = parse_signal(value);
+                } else if key.eq_ignore_ascii_case("duration") {
+                    duration = value.parse().ok();
+                }
+            }
+
+            Some(Self {
+                signal: signal?,
+                duration,
+            })
+        } else if mime.eq_ignore_ascii_case(DTMF) {
+            Some(Self {
+                signal: parse_signal(body.trim())?,
+                duration: None,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Create an `application/dtmf-relay` body
+    pub fn to_relay_body(&self) -> Bytes {
+        match self.duration {
+            Some(duration) => format!("Signal={}\r\nDuration={}\r\n", self.signal, duration).into(),
+            None => format!("Signal={}\r\n", self.signal).into(),
+        }
+    }
+}
+
+/// Parse a DTMF signal, also accepts the numeric event codes (e.g. `11` for `#`) some gateways send
+fn parse_signal(value: &str) -> Option<char> {
+    const EVENTS: [char; 16] = [
+        '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '*', '#', 'A', 'B', 'C', 'D',
+    ];
+
+    if let Ok(event) = value.parse::<usize>() {
+        return EVENTS.get(event).copied();
+    }
+
+    let mut chars = value.chars();
+    let signal = chars.next()?.to_ascii_uppercase();
+
+    if chars.next().is_some() || !EVENTS.contains(&signal) {
+        return None;
+    }
+
+    Some(signal)
+}
+
+/// Create an INFO request inside the dialog carrying the DTMF tone as `application/dtmf-relay` body
+pub fn create_dtmf_info(dialog: &Dialog, dtmf: &Dtmf) -> Request {
+    let mut request = dialog.create_request(Method::INFO);
+
+    request
+        .headers
+        .insert_named(&ContentType(BytesStr::from_static(DTMF_RELAY)));
+    request.body = dtmf.to_relay_body();
+
+    request
+}
+
+/// Returns the DTMF tone contained in an INFO request, if any
+///
+/// The tone may also be contained in a part of a multipart body.
+pub fn dtmf_from_info(request: &IncomingRequest) -> Option<Dtmf> {
+    let ContentType(content_type) = request.headers.get_named().ok()?;
+
+    if let Ok(multipart) = Multipart::parse(&content_type, &request.body) {
+        return multipart
+            .parts
+            .iter()
+            .find_map(|part| Dtmf::parse(&part.content_type(), &part.body));
+    }
+
+    Dtmf::parse(&content_type, &request.body)
+}
+
+pub async fn send_info(dialog: &Dialog, request: Request) -> Result<TsxResponse> {
+    let mut target_tp_info = dialog.target_tp_info.lock().await;
+
+    let mut transaction = dialog
+        .endpoint
+        .send_request(request, &mut target_tp_info)
+        .await?;
+
+    drop(target_tp_info);
+
+    transaction.receive_final().await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dtmf_relay_round_trip() {
+        for dtmf in [
+            Dtmf::new('5', 160),
+            Dtmf::new('#', 250),
+            Dtmf {
+                signal: 'A',
+                duration: None,
+            },
+        ] {
+            let body = dtmf.to_relay_body();
+
+            assert_eq!(Dtmf::parse(DTMF_RELAY, &body), Some(dtmf));
+        }
+
+        assert_eq!(
+            &Dtmf::new('*', 100).to_relay_body()[..],
+            b"Signal=*\r\nDuration=100\r\n"
+        );
+    }
+
+    #[test]
+    fn dtmf_relay_parse() {
+        let dtmf = Dtmf::parse(
+            "Application/DTMF-Relay; charset=utf-8",
+            b"signal = b\nDURATION= 90\nunknown=1\n",
+        );
+        assert_eq!(dtmf, Some(Dtmf::new('B', 90)));
+
+        // Numeric event codes
+        assert_eq!(
+            Dtmf::parse(DTMF_RELAY, b"Signal=11\r\n"),
+            Some(Dtmf {
+                signal: '#',
+                duration: None
+            })
+        );
+
+        assert_eq!(Dtmf::parse(DTMF_RELAY, b"Duration=100\r\n"), None);
+        assert_eq!(Dtmf::parse(DTMF_RELAY, b"Signal=E\r\n"), None);
+        assert_eq!(Dtmf::parse(DTMF_RELAY, b"Signal=16\r\n"), None);
+        assert_eq!(
+            Dtmf::parse(DTMF_RELAY, b"Signal=12\r\n").map(|d| d.signal),
+            Some('A')
+        );
+    }
+
+    #[test]
+    fn dtmf_parse() {
+        assert_eq!(
+            Dtmf::parse(DTMF, b"7\r\n"),
+            Some(Dtmf {
+                signal: '7',
+                duration: None
+            })
+        );
+        assert_eq!(Dtmf::parse(DTMF, b"77"), None);
+        assert_eq!(Dtmf::parse("text/plain", b"Signal=7"), None);
+    }
+}
@@ -1,16 +1,19 @@
 // TODO: remove clippy allow
 #![allow(clippy::large_enum_variant)]
 
-use super::prack::get_rseq;
+use super::prack::{create_prack, get_rseq, is_next_rseq, send_prack};
 use super::session::{Role, Session};
 use super::timer::InitiatorTimerConfig;
+use super::update::EarlyUpdateUsage;
 use super::{Inner, InviteLayer, InviteSessionState, InviteUsage};
-use crate::dialog::{ClientDialogBuilder, Dialog, DialogLayer};
+use crate::dialog::{ClientDialogBuilder, Dialog, DialogLayer, UsageGuard};
 use bytesstr::BytesStr;
 use parking_lot as pl;
-use sip_core::transaction::{ClientInvTsx, TsxResponse};
-use sip_core::{Endpoint, Error, LayerKey, Request};
-use sip_types::header::typed::{Contact, RSeq, Refresher, Supported};
+use sip_core::transaction::{ClientInvTsx, ServerTsx, TsxResponse};
+use sip_core::{Endpoint, Error, IncomingRequest, LayerKey, Request};
+use sip_types::header::typed::{
+    Contact, PAssertedIdentity, PPreferredIdentity, Privacy, RSeq, Refresher, Require, Supported,
+};
 use sip_types::header::HeaderError;
 use sip_types::uri::{NameAddr, Uri};
 use sip_types::Method;
@@ -43,8 +46,20 @@ pub struct Initiator {
     pub support_timer: bool,
     pub support_100rel: bool,
 
+    /// Require the peer to send all provisional responses reliably
+    pub require_100rel: bool,
+
     pub timer_config: InitiatorTimerConfig,
 
+    /// Identities asserted to the peer, only set these when sending to a trusted peer (e.g. a trunk)
+    pub asserted_identity: Vec<PAssertedIdentity>,
+
+    /// Identities the next trusted proxy is asked to assert
+    pub preferred_identity: Vec<PPreferredIdentity>,
+
+    /// Privacy requested from the privacy services along the path
+    pub privacy: Option<Privacy>,
+
     invite_layer: LayerKey<InviteLayer>,
 }
 
@@ -66,11 +81,15 @@ impl Initiator {
             early_list: vec![],
             support_timer: true,
             support_100rel: true,
+            require_100rel: false,
             timer_config: InitiatorTimerConfig {
                 expires_secs: None,
                 refresher: Refresher::Unspecified,
                 expires_secs_min: 90,
             },
+            asserted_identity: vec![],
+            preferred_identity: vec![],
+            privacy: None,
             invite_layer,
         }
     }
@@ -78,9 +97,15 @@ impl Initiator {
     pub fn create_invite(&mut self) -> Request {
         let mut request = self.dialog_builder.create_request(Method::INVITE);
 
-        if self.support_100rel {
+        if self.support_100rel || self.require_100rel {
             let prov_rel_str = BytesStr::from_static("100rel");
-            request.headers.insert_named(&Supported(prov_rel_str));
+            request
+                .headers
+                .insert_named(&Supported(prov_rel_str.clone()));
+
+            if self.require_100rel {
+                request.headers.insert_named(&Require(prov_rel_str));
+            }
         }
 
         if self.support_timer {
@@ -90,6 +115,18 @@ impl Initiator {
             self.timer_config.populate_request(&mut request);
         }
 
+        if !self.asserted_identity.is_empty() {
+            request.headers.insert_named(&self.asserted_identity);
+        }
+
+        if !self.preferred_identity.is_empty() {
+            request.headers.insert_named(&self.preferred_identity);
+        }
+
+        if let Some(privacy) = &self.privacy {
+            request.headers.insert_named(privacy);
+        }
+
         request
     }
 
@@ -138,10 +175,11 @@ impl Initiator {
 
                         continue;
                     } else if let 101..=199 = code {
-                        let early = self.create_early_dialog(&response)?;
-
                         let rseq = get_rseq(&response);
 
+                        let mut early = self.create_early_dialog(&response)?;
+                        early.last_rseq = rseq.as_ref().map(|rseq| rseq.0);
+
                         return Ok(Response::Early(early, response, rseq));
                     } else if let 200..=299 = code {
                         let session = self.create_session(&response)?;
@@ -185,12 +223,18 @@ impl Initiator {
 
         self.early_list.push((to_tag, tx));
 
+        let (update_sender, update_rx) = mpsc::channel(4);
+        let update_guard = dialog.register_usage(EarlyUpdateUsage { update_sender });
+
         Ok(Early {
             endpoint: self.dialog_builder.endpoint.clone(),
             dialog: Some(dialog),
             response_rx,
+            update_rx,
+            update_guard: Some(update_guard),
             timer_config: self.timer_config,
             invite_layer: self.invite_layer,
+            last_rseq: None,
         })
     }
 
@@ -214,12 +258,15 @@ impl Initiator {
             peer_supports_100rel,
             awaited_ack: pl::Mutex::new(None),
             awaited_prack: pl::Mutex::new(None),
+            cancel_reason: pl::Mutex::new(vec![]),
         });
 
         let usage_guard = dialog.register_usage(InviteUsage {
             inner: inner.clone(),
         });
 
+        self.dialog_builder.endpoint[self.invite_layer].add_replaceable(dialog.key(), &inner);
+
         let session_timer = self.timer_config.create_timer_from_response(response)?;
 
         Ok(Session::new(
@@ -247,23 +294,83 @@ pub struct Early {
 
     response_rx: mpsc::Receiver<EarlyEvent>,
 
+    /// UPDATE requests received inside the early dialog
+    update_rx: mpsc::Receiver<IncomingRequest>,
+    update_guard: Option<UsageGuard>,
+
     timer_config: InitiatorTimerConfig,
 
     invite_layer: LayerKey<InviteLayer>,
+
+    /// RSeq of the last reliable provisional response received
+    last_rseq: Option<u32>,
 }
 
 #[derive(Debug)]
 pub enum EarlyResponse {
     Provisional(TsxResponse, Option<RSeq>),
+    /// UPDATE request received inside the early dialog, must be responded to using the
+    /// transaction
+    Update(IncomingRequest, ServerTsx),
     Success(Session, TsxResponse),
     Terminated,
 }
 
 impl Early {
+    /// Returns the early dialog
+    pub fn dialog(&self) -> &Dialog {
+        self.dialog.as_ref().unwrap()
+    }
+
+    /// Acknowledge a reliable provisional response received in this early dialog
+    pub async fn prack(
+        &self,
+        response: &mut TsxResponse,
+        rseq: RSeq,
+    ) -> Result<TsxResponse, Error> {
+        let dialog = self.dialog();
+        let request = create_prack(dialog, response, rseq.0);
+
+        send_prack(dialog, request).await
+    }
+
+    /// Receive the next response in this early dialog
+    ///
+    /// Retransmitted and out of order reliable provisional responses are discarded.
     pub async fn receive(&mut self) -> Result<EarlyResponse, Error> {
+        loop {
+            let event = tokio::select! {
+                event = self.response_rx.recv() => event.expect("dropped initiator"),
+                Some(update) = self.update_rx.recv() => {
+                    let transaction = self.endpoint.create_server_tsx(&update);
+
+                    return Ok(EarlyResponse::Update(update, transaction));
+                }
+            };
+
+            if let EarlyEvent::Response(response) = &event {
+                if let 101..=199 = response.line.code.into_u16() {
+                    let rseq = get_rseq(response);
+
+                    if let Some(rseq) = &rseq {
+                        if !is_next_rseq(self.last_rseq, rseq.0) {
+                            log::debug!("discarding reliable provisional response with RSeq {}, last RSeq {:?}", rseq.0, self.last_rseq);
+                            continue;
+                        }
+
+                        self.last_rseq = Some(rseq.0);
+                    }
+                }
+            }
+
+            return self.handle_event(event);
+        }
+    }
+
+    fn handle_event(&mut self, event: EarlyEvent) -> Result<EarlyResponse, Error> {
         let dialog = self.dialog.as_mut().unwrap();
 
-        match self.response_rx.recv().await.expect("dropped initiator") {
+        match event {
             EarlyEvent::Response(response) => match response.line.code.into_u16() {
                 101..=199 => {
                     let rseq = get_rseq(&response);
@@ -271,6 +378,9 @@ impl Early {
                     Ok(EarlyResponse::Provisional(response, rseq))
                 }
                 200..=299 => {
+                    // UPDATE requests are now handled by the session
+                    self.update_guard = None;
+
                     let (evt_sink, usage_events) = mpsc::channel(4);
 
                     let supported = response
@@ -288,12 +398,15 @@ impl Early {
                         peer_supports_100rel,
                         awaited_ack: pl::Mutex::new(None),
                         awaited_prack: pl::Mutex::new(None),
+                        cancel_reason: pl::Mutex::new(vec![]),
                     });
 
                     let usage_guard = dialog.register_usage(InviteUsage {
                         inner: inner.clone(),
                     });
 
+                    self.endpoint[self.invite_layer].add_replaceable(dialog.key(), &inner);
+
                     let session_timer = self.timer_config.create_timer_from_response(&response)?;
 
                     let session = Session::new(
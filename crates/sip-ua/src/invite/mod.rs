@@ -1,4 +1,4 @@
-use crate::dialog::{Dialog, Usage};
+use crate::dialog::{Dialog, DialogKey, Usage};
 use acceptor::CancellableKey;
 use parking_lot as pl;
 use prack::AwaitedPrack;
@@ -9,20 +9,23 @@ use sip_core::transport::OutgoingRequest;
 use sip_core::{
     Endpoint, EndpointBuilder, Error, IncomingRequest, Layer, LayerKey, MayTake, Result,
 };
-use sip_types::header::typed::CSeq;
+use sip_types::header::typed::{CSeq, Reason, Replaces};
 use sip_types::{Code, Method};
 use std::collections::HashMap;
 use std::mem::replace;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio::time::timeout;
 
 pub mod acceptor;
+pub mod b2bua;
+pub mod info;
 pub mod initiator;
 pub mod prack;
 pub mod session;
 mod timer;
+pub mod update;
 
 #[derive(Debug)]
 struct AwaitedAck {
@@ -42,6 +45,9 @@ struct Inner {
 
     awaited_ack: pl::Mutex<Option<AwaitedAck>>,
     awaited_prack: pl::Mutex<Option<AwaitedPrack>>,
+
+    /// Reason headers of the CANCEL request which cancelled the INVITE
+    cancel_reason: pl::Mutex<Vec<Reason>>,
 }
 
 #[derive(Debug)]
@@ -120,6 +126,38 @@ impl InviteSessionState {
 #[derive(Default)]
 pub struct InviteLayer {
     cancellables: pl::Mutex<HashMap<CancellableKey, Arc<Inner>>>,
+
+    /// Sessions which can be replaced by an INVITE with a `Replaces` header
+    replaceables: pl::Mutex<HashMap<DialogKey, Weak<Inner>>>,
+}
+
+/// Session matched by the `Replaces` header of an incoming INVITE, see [`InviteLayer::find_replaced`]
+pub struct ReplacedDialog {
+    inner: Arc<Inner>,
+}
+
+impl ReplacedDialog {
+    /// Terminate the replaced session, must be called after the replacing INVITE has been accepted
+    ///
+    /// A pending incoming INVITE is rejected with a 487 response. An established session
+    /// receives [`session::Event::Replaced`] and must be terminated using it.
+    pub async fn terminate(self) -> Result<()> {
+        let mut state = self.inner.state.lock().await;
+
+        if let Some((dialog, tsx, invite)) = state.set_cancelled() {
+            let response = dialog.create_response(&invite, Code::REQUEST_TERMINATED, None)?;
+
+            return tsx.respond_failure(response).await;
+        }
+
+        if let InviteSessionState::Established { evt_sink } = &*state {
+            if evt_sink.send(UsageEvent::Replaced).await.is_err() {
+                log::warn!("replaced session dropped before it could be terminated");
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -135,9 +173,13 @@ impl Layer for InviteLayer {
         endpoint.add_allow(Method::ACK);
         endpoint.add_allow(Method::CANCEL);
         endpoint.add_allow(Method::PRACK);
+        endpoint.add_allow(Method::REFER);
+        endpoint.add_allow(Method::INFO);
+        endpoint.add_allow(Method::NOTIFY);
 
         endpoint.add_supported("100rel");
         endpoint.add_supported("timer");
+        endpoint.add_supported("replaces");
     }
 
     async fn receive(&self, endpoint: &Endpoint, mut request: MayTake<'_, IncomingRequest>) {
@@ -153,6 +195,52 @@ impl Layer for InviteLayer {
 }
 
 impl InviteLayer {
+    fn add_replaceable(&self, dialog_key: DialogKey, inner: &Arc<Inner>) {
+        let mut replaceables = self.replaceables.lock();
+
+        replaceables.retain(|_, inner| inner.strong_count() > 0);
+        replaceables.insert(dialog_key, Arc::downgrade(inner));
+    }
+
+    /// Find the session an incoming INVITE wants to replace using the `Replaces` header
+    /// ([RFC3891](https://datatracker.ietf.org/doc/html/rfc3891#section-3))
+    ///
+    /// Returns `Ok(None)` if the INVITE contains no `Replaces` header. If the INVITE cannot
+    /// replace any session it must be rejected using the returned code.
+    pub async fn find_replaced(
+        &self,
+        invite: &IncomingRequest,
+    ) -> Result<Option<ReplacedDialog>, Code> {
+        let replaces = match invite.headers.try_get_named::<Replaces>() {
+            None => return Ok(None),
+            Some(Ok(replaces)) => replaces,
+            Some(Err(_)) => return Err(Code::BAD_REQUEST),
+        };
+
+        let dialog_key = DialogKey::from_replaces(&replaces);
+
+        // Early dialogs initiated by this UA are never registered, as they cannot be replaced
+        let inner = self
+            .replaceables
+            .lock()
+            .get(&dialog_key)
+            .and_then(Weak::upgrade)
+            .ok_or(Code::CALL_OR_TRANSACTION_DOES_NOT_EXIST)?;
+
+        match &*inner.state.lock().await {
+            InviteSessionState::UasProvisional { .. } => {}
+            InviteSessionState::Established { .. } if replaces.early_only => {
+                return Err(Code::BUSY_HERE)
+            }
+            InviteSessionState::Established { .. } => {}
+            InviteSessionState::Cancelled | InviteSessionState::Terminated => {
+                return Err(Code::DECLINE)
+            }
+        }
+
+        Ok(Some(ReplacedDialog { inner }))
+    }
+
     async fn handle_cancel(
         &self,
         endpoint: &Endpoint,
@@ -178,6 +266,8 @@ impl InviteLayer {
             let cancel_tsx = endpoint.create_server_tsx(&cancel);
 
             if let Some((dialog, invite_tsx, invite)) = inner.state.lock().await.set_cancelled() {
+                *inner.cancel_reason.lock() = cancel.headers.get_named().unwrap_or_default();
+
                 let invite_response =
                     dialog.create_response(&invite, Code::REQUEST_TERMINATED, None)?;
 
@@ -283,6 +373,45 @@ impl Usage for InviteUsage {
                     }
                 }
             }
+            Method::UPDATE => {
+                let state = self.inner.state.lock().await;
+
+                if let InviteSessionState::Established { evt_sink } = &*state {
+                    let update = request.inner().take().unwrap();
+
+                    if let Err(SendError(UsageEvent::Update(update))) =
+                        evt_sink.send(UsageEvent::Update(update)).await
+                    {
+                        *request.inner() = Some(update);
+                    }
+                }
+            }
+            Method::REFER => {
+                let state = self.inner.state.lock().await;
+
+                if let InviteSessionState::Established { evt_sink } = &*state {
+                    let refer = request.inner().take().unwrap();
+
+                    if let Err(SendError(UsageEvent::Refer(refer))) =
+                        evt_sink.send(UsageEvent::Refer(refer)).await
+                    {
+                        *request.inner() = Some(refer);
+                    }
+                }
+            }
+            Method::INFO => {
+                let state = self.inner.state.lock().await;
+
+                if let InviteSessionState::Established { evt_sink } = &*state {
+                    let info = request.inner().take().unwrap();
+
+                    if let Err(SendError(UsageEvent::Info(info))) =
+                        evt_sink.send(UsageEvent::Info(info)).await
+                    {
+                        *request.inner() = Some(info);
+                    }
+                }
+            }
             Method::PRACK if self.inner.peer_supports_100rel => {
                 if let Err(e) = self
                     .handle_prack(endpoint, MayTake::new(request.inner()))
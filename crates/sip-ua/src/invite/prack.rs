@@ -3,7 +3,7 @@ use crate::dialog::Dialog;
 use sip_core::transaction::TsxResponse;
 use sip_core::{Endpoint, IncomingRequest, MayTake, Request, Result};
 use sip_types::header::typed::{RAck, RSeq, Require};
-use sip_types::{Code, Method};
+use sip_types::{Code, Headers, Method};
 use tokio::sync::oneshot;
 
 #[derive(Debug)]
@@ -18,6 +18,13 @@ pub(super) struct AwaitedPrack {
     pub rack: u32,
 }
 
+impl AwaitedPrack {
+    /// Returns if the RAck header of a PRACK request acknowledges the awaited response
+    fn matches(&self, rack: &RAck) -> bool {
+        self.rack == rack.rack && self.cseq == rack.cseq
+    }
+}
+
 impl InviteUsage {
     pub(super) async fn handle_prack(
         &self,
@@ -29,7 +36,7 @@ impl InviteUsage {
             if let Some(awaited_prack) = awaited_prack_opt.take() {
                 let rack = request.headers.get_named::<RAck>()?;
 
-                if awaited_prack.rack == rack.rack && awaited_prack.cseq == rack.cseq {
+                if awaited_prack.matches(&rack) {
                     (request.take(), awaited_prack)
                 } else {
                     *awaited_prack_opt = Some(awaited_prack);
@@ -53,15 +60,30 @@ impl InviteUsage {
 }
 
 pub fn get_rseq(response: &TsxResponse) -> Option<RSeq> {
-    if let Some(Ok(requires)) = response.headers.try_get_named::<Vec<Require>>() {
+    rseq_from_headers(&response.headers)
+}
+
+fn rseq_from_headers(headers: &Headers) -> Option<RSeq> {
+    if let Some(Ok(requires)) = headers.try_get_named::<Vec<Require>>() {
         if requires.iter().any(|r| r.0 == "100rel") {
-            return response.headers.get_named().ok();
+            return headers.get_named().ok();
         }
     }
 
     None
 }
 
+/// Returns if a reliable provisional response with the given RSeq must be handled.
+///
+/// After the first reliable provisional response, only the one with the next
+/// RSeq is accepted, retransmissions and out of order responses are discarded (RFC3262 Section 4).
+pub(super) fn is_next_rseq(last_rseq: Option<u32>, rseq: u32) -> bool {
+    match last_rseq {
+        Some(last_rseq) => rseq == last_rseq.wrapping_add(1),
+        None => true,
+    }
+}
+
 pub fn create_prack(dialog: &Dialog, response: &mut TsxResponse, rack: u32) -> Request {
     let mut request = dialog.create_request(Method::PRACK);
 
@@ -86,3 +108,57 @@ pub async fn send_prack(dialog: &Dialog, request: Request) -> Result<TsxResponse
 
     transaction.receive_final().await
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sip_types::Name;
+
+    #[test]
+    fn rseq_requires_100rel() {
+        let mut headers = Headers::new();
+        headers.insert(Name::RSEQ, "988789");
+        assert!(rseq_from_headers(&headers).is_none());
+
+        headers.insert(Name::REQUIRE, "timer, 100rel");
+        assert_eq!(rseq_from_headers(&headers).unwrap().0, 988789);
+    }
+
+    #[test]
+    fn rseq_sequence() {
+        assert!(is_next_rseq(None, 10));
+        assert!(is_next_rseq(Some(10), 11));
+
+        // retransmission and gap
+        assert!(!is_next_rseq(Some(11), 11));
+        assert!(!is_next_rseq(Some(11), 13));
+        assert!(!is_next_rseq(Some(11), 10));
+    }
+
+    #[test]
+    fn rack_matches_awaited_prack() {
+        let (prack_sender, _prack_receiver) = oneshot::channel();
+
+        let awaited_prack = AwaitedPrack {
+            prack_sender,
+            cseq: 314159,
+            rack: 776656,
+        };
+
+        assert!(awaited_prack.matches(&RAck {
+            rack: 776656,
+            cseq: 314159,
+            method: Method::INVITE,
+        }));
+        assert!(!awaited_prack.matches(&RAck {
+            rack: 776655,
+            cseq: 314159,
+            method: Method::INVITE,
+        }));
+        assert!(!awaited_prack.matches(&RAck {
+            rack: 776656,
+            cseq: 314160,
+            method: Method::INVITE,
+        }));
+    }
+}
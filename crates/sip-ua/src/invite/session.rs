@@ -1,11 +1,13 @@
 use super::timer::SessionTimer;
 use super::Inner;
 use crate::dialog::{Dialog, UsageGuard};
+use crate::invite::info::Dtmf;
 use crate::invite::AwaitedAck;
+use crate::refer::ReferNotifier;
 use sip_core::transaction::{ServerInvTsx, ServerTsx, TsxResponse};
 use sip_core::transport::OutgoingResponse;
 use sip_core::{Endpoint, IncomingRequest, Result};
-use sip_types::header::typed::Refresher;
+use sip_types::header::typed::{Reason, Refresher};
 use sip_types::{Code, CodeKind, Method};
 use std::sync::Arc;
 use tokio::select;
@@ -79,6 +81,16 @@ impl RefreshNeeded<'_> {
 
         Ok(())
     }
+
+    /// Refresh the session using an UPDATE request without offer, which unlike a re-INVITE
+    /// doesn't require a new offer/answer exchange
+    pub async fn process_update(self) -> Result<()> {
+        let update = super::update::create_update(&self.session.dialog);
+
+        super::update::send_update(&self.session.dialog, update).await?;
+
+        Ok(())
+    }
 }
 
 pub struct ReInviteReceived<'s> {
@@ -103,6 +115,87 @@ impl ReInviteReceived<'_> {
     }
 }
 
+pub struct UpdateReceived<'s> {
+    pub session: &'s mut Session,
+    pub update: IncomingRequest,
+    pub transaction: ServerTsx,
+}
+
+impl UpdateReceived<'_> {
+    /// Respond to the UPDATE, the response must contain the answer if the request contained an
+    /// offer
+    pub async fn respond(self, response: OutgoingResponse) -> Result<()> {
+        self.transaction.respond(response).await
+    }
+
+    /// Accept an UPDATE without offer (e.g. a session refresh) with a 200 OK
+    pub async fn process_default(self) -> Result<()> {
+        let response = self
+            .session
+            .dialog
+            .create_response(&self.update, Code::OK, None)?;
+
+        self.transaction.respond(response).await
+    }
+}
+
+pub struct ReferReceived<'s> {
+    pub session: &'s mut Session,
+    pub refer: IncomingRequest,
+    pub transaction: ServerTsx,
+}
+
+impl ReferReceived<'_> {
+    /// Accept the REFER request, the returned notifier must be used to report the progress of
+    /// the request sent to the [`ReferNotifier::refer_to`] target
+    pub async fn accept(self) -> Result<ReferNotifier> {
+        ReferNotifier::accept(self.session.dialog.clone(), &self.refer, self.transaction).await
+    }
+
+    /// Decline the REFER request with a 603
+    pub async fn decline(self) -> Result<()> {
+        let response = self
+            .session
+            .dialog
+            .create_response(&self.refer, Code::DECLINE, None)?;
+
+        self.transaction.respond(response).await
+    }
+}
+
+pub struct InfoReceived<'s> {
+    pub session: &'s mut Session,
+    pub info: IncomingRequest,
+    pub transaction: ServerTsx,
+}
+
+impl InfoReceived<'_> {
+    /// Returns the DTMF tone contained in the INFO request, if any
+    pub fn dtmf(&self) -> Option<Dtmf> {
+        super::info::dtmf_from_info(&self.info)
+    }
+
+    pub async fn respond(self, response: OutgoingResponse) -> Result<()> {
+        self.transaction.respond(response).await
+    }
+
+    /// Respond with a 200 OK if the INFO contains a DTMF tone, otherwise with 415
+    pub async fn process_default(self) -> Result<()> {
+        let code = if self.dtmf().is_some() {
+            Code::OK
+        } else {
+            Code::UNSUPPORTED_MEDIA_TYPE
+        };
+
+        let response = self
+            .session
+            .dialog
+            .create_response(&self.info, code, None)?;
+
+        self.transaction.respond(response).await
+    }
+}
+
 pub struct ByeEvent<'s> {
     pub session: &'s mut Session,
     pub bye: IncomingRequest,
@@ -110,6 +203,11 @@ pub struct ByeEvent<'s> {
 }
 
 impl ByeEvent<'_> {
+    /// Returns the [`Reason`]s contained in the BYE, explaining why the peer ended the session
+    pub fn reason(&self) -> Vec<Reason> {
+        self.bye.headers.get_named().unwrap_or_default()
+    }
+
     /// Process the BYE as one would expect, respond with a 200 OK
     pub async fn process_default(self) -> Result<()> {
         let response = self
@@ -125,7 +223,13 @@ impl ByeEvent<'_> {
 pub enum Event<'s> {
     RefreshNeeded(RefreshNeeded<'s>),
     ReInviteReceived(ReInviteReceived<'s>),
+    UpdateReceived(UpdateReceived<'s>),
+    ReferReceived(ReferReceived<'s>),
+    InfoReceived(InfoReceived<'s>),
     Bye(ByeEvent<'s>),
+    /// The session was replaced by another session using the `Replaces` header and must be
+    /// terminated using [`Session::terminate`]
+    Replaced(&'s mut Session),
     Terminated,
 }
 
@@ -161,11 +265,27 @@ impl Session {
         }
     }
 
+    /// Send a DTMF tone to the peer using an INFO request
+    pub async fn send_dtmf(&self, dtmf: Dtmf) -> Result<TsxResponse> {
+        let info = super::info::create_dtmf_info(&self.dialog, &dtmf);
+
+        super::info::send_info(&self.dialog, info).await
+    }
+
     pub async fn terminate(&mut self) -> Result<TsxResponse> {
+        self.terminate_with_reason(&[]).await
+    }
+
+    /// Terminate the session with a BYE request containing the given [`Reason`]s
+    pub async fn terminate_with_reason(&mut self, reason: &[Reason]) -> Result<TsxResponse> {
         let mut state = self.inner.state.lock().await;
         state.set_terminated();
 
-        let request = self.dialog.create_request(Method::BYE);
+        let mut request = self.dialog.create_request(Method::BYE);
+
+        for reason in reason {
+            request.headers.insert_named(reason);
+        }
 
         let mut target_tp_info = self.dialog.target_tp_info.lock().await;
 
@@ -209,6 +329,36 @@ impl Session {
                     transaction,
                 }))
             }
+            UsageEvent::Replaced => Ok(Event::Replaced(self)),
+            UsageEvent::Refer(refer) => {
+                let transaction = self.endpoint.create_server_tsx(&refer);
+
+                Ok(Event::ReferReceived(ReferReceived {
+                    session: self,
+                    refer,
+                    transaction,
+                }))
+            }
+            UsageEvent::Info(info) => {
+                let transaction = self.endpoint.create_server_tsx(&info);
+
+                Ok(Event::InfoReceived(InfoReceived {
+                    session: self,
+                    info,
+                    transaction,
+                }))
+            }
+            UsageEvent::Update(update) => {
+                self.session_timer.reset();
+
+                let transaction = self.endpoint.create_server_tsx(&update);
+
+                Ok(Event::UpdateReceived(UpdateReceived {
+                    session: self,
+                    update,
+                    transaction,
+                }))
+            }
         }
     }
 
@@ -234,5 +384,9 @@ impl Session {
 
 pub(super) enum UsageEvent {
     ReInvite(IncomingRequest),
+    Update(IncomingRequest),
+    Refer(IncomingRequest),
+    Info(IncomingRequest),
     Bye(IncomingRequest),
+    Replaced,
 }
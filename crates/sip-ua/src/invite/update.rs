@@ -0,0 +1,80 @@
+use crate::dialog::{Dialog, Usage};
+use sip_core::transaction::TsxResponse;
+use sip_core::{Endpoint, IncomingRequest, MayTake, Request, Result};
+use sip_types::Method;
+use tokio::sync::mpsc;
+
+/// Usage which forwards UPDATE requests received inside an early dialog
+pub(super) struct EarlyUpdateUsage {
+    pub update_sender: mpsc::Sender<IncomingRequest>,
+}
+
+#[async_trait::async_trait]
+impl Usage for EarlyUpdateUsage {
+    fn name(&self) -> &'static str {
+        "early-update-usage"
+    }
+
+    async fn receive(&self, _: &Endpoint, mut request: MayTake<'_, IncomingRequest>) {
+        if request.line.method != Method::UPDATE {
+            return;
+        }
+
+        let update = request.inner().take().unwrap();
+
+        if let Err(mpsc::error::SendError(update)) = self.update_sender.send(update).await {
+            *request.inner() = Some(update);
+        }
+    }
+}
+
+/// Create an UPDATE request inside the given (early or confirmed) dialog
+///
+/// An SDP offer can be added as body of the request, the answer is then contained in the success
+/// response.
+pub fn create_update(dialog: &Dialog) -> Request {
+    let mut request = dialog.create_request(Method::UPDATE);
+
+    request.headers.insert_named(&dialog.local_contact);
+
+    request
+}
+
+pub async fn send_update(dialog: &Dialog, request: Request) -> Result<TsxResponse> {
+    let mut target_tp_info = dialog.target_tp_info.lock().await;
+
+    let mut transaction = dialog
+        .endpoint
+        .send_request(request, &mut target_tp_info)
+        .await?;
+
+    drop(target_tp_info);
+
+    transaction.receive_final().await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dialog::test::dialog;
+    use sip_types::header::typed::{CSeq, Contact};
+
+    #[tokio::test]
+    async fn update_request() {
+        let dialog = dialog("1928301774", "a6c85cf");
+
+        let request = create_update(&dialog);
+
+        assert_eq!(request.line.method, Method::UPDATE);
+
+        // The UPDATE can change the remote target of the dialog and must contain a Contact
+        let contact: Contact = request.headers.get_named().unwrap();
+        assert!(contact.uri.uri.compare(&*dialog.local_contact.uri.uri));
+
+        let cseq: CSeq = request.headers.get_named().unwrap();
+        assert_eq!(cseq.method, Method::UPDATE);
+
+        let next: CSeq = create_update(&dialog).headers.get_named().unwrap();
+        assert_eq!(next.cseq, cseq.cseq + 1);
+    }
+}
@@ -1,4 +1,13 @@
 pub mod dialog;
+pub mod identity;
 pub mod invite;
+pub mod message;
+pub mod options;
+pub mod presence;
+pub mod privacy;
+pub mod proxy;
+pub mod refer;
 pub mod register;
+pub mod registrar;
+pub mod subscription;
 pub mod util;
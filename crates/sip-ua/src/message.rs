@@ -0,0 +1,335 @@
+//! Pager-mode instant messaging using the MESSAGE method ([RFC3428](https://datatracker.ietf.org/doc/html/rfc3428))
+//!
+//! Message bodies are either `text/plain` or wrapped inside a `message/cpim`
+//! ([RFC3862](https://datatracker.ietf.org/doc/html/rfc3862)) envelope, see [`MessageBody`].
+
+use crate::util::{random_sequence_number, random_string};
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use sip_core::transaction::{ServerTsx, TsxResponse};
+use sip_core::transport::TargetTransportInfo;
+use sip_core::{Endpoint, IncomingRequest, Request};
+use sip_types::header::typed::{Accept, CSeq, CallID, ContentType, FromTo, MaxForwards};
+use sip_types::multipart::Multipart;
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::{Code, CodeKind, Method, Name};
+
+/// Maximum size of a MESSAGE body, larger messages must be sent using a session based protocol
+/// (e.g. MSRP) as the MESSAGE request could be fragmented when using an unreliable transport
+pub const MAX_MESSAGE_SIZE: usize = 1300;
+
+const TEXT_PLAIN: &str = "text/plain";
+const MESSAGE_CPIM: &str = "message/cpim";
+
+#[derive(Debug, thiserror::Error)]
+pub enum MessageError {
+    #[error(transparent)]
+    Core(#[from] sip_core::Error),
+    #[error("message body of {0} bytes exceeds the size limit")]
+    TooLarge(usize),
+    #[error("message was rejected with {0:?}")]
+    Rejected(Code),
+}
+
+/// `message/cpim` envelope
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cpim {
+    /// Message headers, e.g. `From`, `To` and `DateTime`
+    pub headers: Vec<(String, String)>,
+    /// MIME headers of the encapsulated content, e.g. `Content-Type`
+    pub content_headers: Vec<(String, String)>,
+    pub content: Bytes,
+}
+
+impl Cpim {
+    /// Create a CPIM envelope containing a `text/plain` message
+    pub fn text<F, T>(from: F, to: T, text: &str) -> Self
+    where
+        F: Into<String>,
+        T: Into<String>,
+    {
+        Self {
+            headers: vec![("From".into(), from.into()), ("To".into(), to.into())],
+            content_headers: vec![("Content-Type".into(), format!("{TEXT_PLAIN};charset=utf-8"))],
+            content: Bytes::copy_from_slice(text.as_bytes()),
+        }
+    }
+
+    /// Parse a CPIM envelope, returns `None` if the envelope is malformed
+    ///
+    /// Only the headers must be valid UTF-8, the content is kept as is.
+    pub fn parse(body: &Bytes) -> Option<Self> {
+        let (headers, rest) = split_headers(body)?;
+        let (content_headers, content) = split_headers(&body[rest..])?;
+
+        Some(Self {
+            headers: parse_headers(&body[..headers])?,
+            content_headers: parse_headers(&body[rest..][..content_headers])?,
+            content: body.slice(rest + content..),
+        })
+    }
+
+    /// Serialize the envelope, the content is appended unchanged
+    pub fn to_bytes(&self) -> Bytes {
+        let mut bytes = Vec::new();
+
+        for headers in [&self.headers, &self.content_headers] {
+            for (name, value) in headers {
+                bytes.extend_from_slice(format!("{name}: {value}\r\n").as_bytes());
+            }
+
+            bytes.extend_from_slice(b"\r\n");
+        }
+
+        bytes.extend_from_slice(&self.content);
+        bytes.into()
+    }
+
+    /// Returns the value of the first message header with the given name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        find_header(&self.content_headers, "Content-Type")
+    }
+}
+
+/// Returns the length of the header block and the offset of what follows the empty line
+fn split_headers(bytes: &[u8]) -> Option<(usize, usize)> {
+    let find = |needle: &[u8]| bytes.windows(needle.len()).position(|w| w == needle);
+
+    if let Some(idx) = find(b"\r\n\r\n") {
+        Some((idx, idx + 4))
+    } else {
+        find(b"\n\n").map(|idx| (idx, idx + 2))
+    }
+}
+
+fn parse_headers(bytes: &[u8]) -> Option<Vec<(String, String)>> {
+    std::str::from_utf8(bytes)
+        .ok()?
+        .lines()
+        .map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().into(), value.trim().into()))
+        })
+        .collect()
+}
+
+fn find_header<'h>(headers: &'h [(String, String)], name: &str) -> Option<&'h str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Body of a MESSAGE request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageBody {
+    Text(String),
+    Cpim(Cpim),
+}
+
+impl MessageBody {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            MessageBody::Text(_) => TEXT_PLAIN,
+            MessageBody::Cpim(_) => MESSAGE_CPIM,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        match self {
+            MessageBody::Text(text) => Bytes::copy_from_slice(text.as_bytes()),
+            MessageBody::Cpim(cpim) => cpim.to_bytes(),
+        }
+    }
+
+    /// Parse the body of a MESSAGE request using its content type
+    ///
+    /// Multipart bodies are parsed using their first supported part. Returns `None` if the
+    /// content type is not supported or the body is malformed
+    pub fn parse(content_type: &str, body: &Bytes) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim();
+
+        if mime.eq_ignore_ascii_case(TEXT_PLAIN) {
+            String::from_utf8(body.to_vec()).ok().map(Self::Text)
+        } else if mime.eq_ignore_ascii_case(MESSAGE_CPIM) {
+            Cpim::parse(body).map(Self::Cpim)
+        } else if let Ok(multipart) = Multipart::parse(content_type, body) {
+            multipart
+                .parts
+                .iter()
+                .find_map(|part| Self::parse(&part.content_type(), &part.body))
+        } else {
+            None
+        }
+    }
+}
+
+/// Create an out-of-dialog MESSAGE request from `local_addr` to `target`
+pub fn create_message(local_addr: NameAddr, target: Box<dyn Uri>, body: &MessageBody) -> Request {
+    let mut request = Request::new(Method::MESSAGE, target.clone());
+
+    request.headers.insert_named(&MaxForwards(70));
+    request
+        .headers
+        .insert_type(Name::FROM, &FromTo::new(local_addr, Some(random_string())));
+    request
+        .headers
+        .insert_type(Name::TO, &FromTo::new(NameAddr::uri(target), None));
+    request.headers.insert_named(&CallID::new(random_string()));
+    request
+        .headers
+        .insert_named(&CSeq::new(random_sequence_number(), Method::MESSAGE));
+    request
+        .headers
+        .insert_named(&ContentType(BytesStr::from_static(body.content_type())));
+    request.body = body.to_bytes();
+
+    request
+}
+
+/// Send a MESSAGE request created using [`create_message`]
+///
+/// Fails without sending the request if the body exceeds [`MAX_MESSAGE_SIZE`]. Any non-2xx final
+/// response is returned as [`MessageError::Rejected`].
+pub async fn send_message(
+    endpoint: &Endpoint,
+    request: Request,
+) -> Result<TsxResponse, MessageError> {
+    if request.body.len() > MAX_MESSAGE_SIZE {
+        return Err(MessageError::TooLarge(request.body.len()));
+    }
+
+    let mut target_tp_info = TargetTransportInfo::default();
+
+    let mut transaction = endpoint.send_request(request, &mut target_tp_info).await?;
+    let response = transaction.receive_final().await?;
+
+    if response.line.code.kind() == CodeKind::Success {
+        Ok(response)
+    } else {
+        Err(MessageError::Rejected(response.line.code))
+    }
+}
+
+/// A received MESSAGE request with a supported body
+pub struct ReceivedMessage {
+    pub request: IncomingRequest,
+    pub transaction: ServerTsx,
+    pub body: MessageBody,
+}
+
+impl ReceivedMessage {
+    /// Validate an incoming MESSAGE request
+    ///
+    /// Requests with bodies larger than `max_size` are rejected with 413, unsupported content
+    /// types with 415. In both cases `None` is returned.
+    pub async fn new(
+        endpoint: &Endpoint,
+        request: IncomingRequest,
+        max_size: usize,
+    ) -> Result<Option<Self>, MessageError> {
+        assert_eq!(request.line.method, Method::MESSAGE);
+
+        let transaction = endpoint.create_server_tsx(&request);
+
+        if request.body.len() > max_size {
+            let response = endpoint.create_response(&request, Code::REQUEST_ENTITY_TOO_LARGE, None);
+            transaction.respond(response).await?;
+            return Ok(None);
+        }
+
+        let body =
+            request.headers.get_named::<ContentType>().ok().and_then(
+                |ContentType(content_type)| MessageBody::parse(&content_type, &request.body),
+            );
+
+        let Some(body) = body else {
+            let mut response =
+                endpoint.create_response(&request, Code::UNSUPPORTED_MEDIA_TYPE, None);
+            response.msg.headers.insert_named(&vec![
+                Accept(BytesStr::from_static(TEXT_PLAIN)),
+                Accept(BytesStr::from_static(MESSAGE_CPIM)),
+            ]);
+            transaction.respond(response).await?;
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            request,
+            transaction,
+            body,
+        }))
+    }
+
+    /// Respond to the MESSAGE request, a 200 response indicates the message was delivered
+    pub async fn respond(self, endpoint: &Endpoint, code: Code) -> Result<(), MessageError> {
+        let response = endpoint.create_response(&self.request, code, None);
+        self.transaction.respond(response).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CPIM: &[u8] = b"From: MR SANDERS <im:piglet@100akerwood.com>\r\n\
+To: Depressed Donkey <im:eeyore@100akerwood.com>\r\n\
+DateTime: 2000-12-13T13:40:00-08:00\r\n\
+\r\n\
+Content-Type: text/plain; charset=utf-8\r\n\
+\r\n\
+Here is the text of my message.";
+
+    #[test]
+    fn cpim_round_trip() {
+        let body = Bytes::from_static(CPIM);
+        let cpim = Cpim::parse(&body).unwrap();
+
+        assert_eq!(
+            cpim.header("from"),
+            Some("MR SANDERS <im:piglet@100akerwood.com>")
+        );
+        assert_eq!(cpim.header("DateTime"), Some("2000-12-13T13:40:00-08:00"));
+        assert_eq!(cpim.content_type(), Some("text/plain; charset=utf-8"));
+        assert_eq!(&cpim.content[..], b"Here is the text of my message.");
+
+        assert_eq!(cpim.to_bytes(), body);
+    }
+
+    #[test]
+    fn cpim_binary_content() {
+        let mut cpim = Cpim::text("im:alice@example.org", "im:bob@example.org", "");
+        cpim.content_headers = vec![("Content-Type".into(), "application/octet-stream".into())];
+        cpim.content = Bytes::from_static(b"\xff\x00\r\n\r\n\xfe");
+
+        let bytes = MessageBody::Cpim(cpim.clone()).to_bytes();
+
+        // The content is not required to be UTF-8 and may contain empty lines
+        let parsed = MessageBody::parse("message/cpim", &bytes).unwrap();
+        assert_eq!(parsed, MessageBody::Cpim(cpim));
+    }
+
+    #[test]
+    fn cpim_lf_only() {
+        let body =
+            Bytes::from_static(b"From: <im:alice@example.org>\n\nContent-Type: text/plain\n\nhi");
+        let cpim = Cpim::parse(&body).unwrap();
+
+        assert_eq!(cpim.header("From"), Some("<im:alice@example.org>"));
+        assert_eq!(cpim.content_type(), Some("text/plain"));
+        assert_eq!(&cpim.content[..], b"hi");
+    }
+
+    #[test]
+    fn cpim_malformed() {
+        assert!(Cpim::parse(&Bytes::from_static(b"From: <im:alice@example.org>\r\n")).is_none());
+        assert!(Cpim::parse(&Bytes::from_static(b"From\r\n\r\n\r\n\r\n")).is_none());
+        assert!(Cpim::parse(&Bytes::from_static(b"From: \xff\r\n\r\n\r\n\r\n")).is_none());
+    }
+}
@@ -0,0 +1,323 @@
+//! Monitor the reachability of a peer or trunk by periodically sending OPTIONS requests
+
+use crate::dialog::Dialog;
+use crate::util::{random_sequence_number, random_string};
+use sip_core::transport::TargetTransportInfo;
+use sip_core::{Endpoint, Error, Request, Result};
+use sip_types::header::typed::{Accept, CSeq, CallID, FromTo, MaxForwards};
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::{Code, Method, Name};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{interval, Instant, MissedTickBehavior};
+
+/// Reachability of the monitored peer, passed to the [`OptionsPing::on_transition`] callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reachability {
+    /// The peer responded, contains the round-trip time of the OPTIONS transaction
+    Up(Duration),
+    /// The peer didn't respond (or responded with 408/503) to consecutive requests
+    Down,
+}
+
+#[allow(clippy::large_enum_variant)]
+enum Target {
+    Dialog(Arc<Dialog>),
+    OutOfDialog {
+        endpoint: Endpoint,
+        from: FromTo,
+        to: FromTo,
+        call_id: CallID,
+        cseq: u32,
+        target: Box<dyn Uri>,
+        target_tp_info: TargetTransportInfo,
+    },
+}
+
+pub struct OptionsPing {
+    target: Target,
+
+    interval: Duration,
+    /// Number of consecutive failures after which the peer is considered down
+    failure_threshold: u32,
+
+    failures: u32,
+    reachable: Option<bool>,
+    rtt: Option<Duration>,
+
+    on_transition: Option<Box<dyn FnMut(Reachability) + Send>>,
+}
+
+impl OptionsPing {
+    /// Send out-of-dialog OPTIONS requests from `local_addr` to `target`
+    pub fn new(endpoint: Endpoint, local_addr: NameAddr, target: Box<dyn Uri>) -> Self {
+        Self::with_target(Target::OutOfDialog {
+            endpoint,
+            from: FromTo::new(local_addr, Some(random_string())),
+            to: FromTo::new(NameAddr::uri(target.clone()), None),
+            call_id: CallID::new(random_string()),
+            cseq: random_sequence_number(),
+            target,
+            target_tp_info: TargetTransportInfo::default(),
+        })
+    }
+
+    /// Send OPTIONS requests inside the dialog
+    pub fn in_dialog(dialog: Arc<Dialog>) -> Self {
+        Self::with_target(Target::Dialog(dialog))
+    }
+
+    fn with_target(target: Target) -> Self {
+        Self {
+            target,
+            interval: Duration::from_secs(30),
+            failure_threshold: 2,
+            failures: 0,
+            reachable: None,
+            rtt: None,
+            on_transition: None,
+        }
+    }
+
+    /// Set the interval in which requests are sent by [`run`](Self::run), defaults to 30 seconds
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the number of consecutive failed requests after which the peer is considered down,
+    /// defaults to 2
+    pub fn with_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    /// Set a callback which is called every time the peer goes up or down
+    pub fn on_transition<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(Reachability) + Send + 'static,
+    {
+        self.on_transition = Some(Box::new(f));
+        self
+    }
+
+    /// Returns if the peer is reachable, `None` until the first ping completed
+    pub fn is_reachable(&self) -> Option<bool> {
+        self.reachable
+    }
+
+    /// Round-trip time of the last successful ping
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt
+    }
+
+    /// Send OPTIONS requests in the configured interval until an unexpected error occurs
+    pub async fn run(&mut self) -> Result<()> {
+        let mut interval = interval(self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            self.ping().await?;
+        }
+    }
+
+    /// Send a single OPTIONS request and update the reachability of the peer
+    ///
+    /// Any final response except 408 and 503 counts as the peer being reachable. Returns the
+    /// round-trip time if it was.
+    pub async fn ping(&mut self) -> Result<Option<Duration>> {
+        let start = Instant::now();
+
+        let code = match self.send_options().await {
+            Ok(code) => Some(code),
+            Err(Error::RequestTimedOut | Error::Io(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(self.update(code, start.elapsed()))
+    }
+
+    /// Update the reachability using the final response code of an OPTIONS request, `None` if
+    /// the request timed out or couldn't be sent
+    fn update(&mut self, code: Option<Code>, elapsed: Duration) -> Option<Duration> {
+        let rtt = match code {
+            Some(Code::REQUEST_TIMEOUT | Code::SERVICE_UNAVAILABLE) | None => None,
+            Some(_) => Some(elapsed),
+        };
+
+        match rtt {
+            Some(rtt) => {
+                self.failures = 0;
+                self.rtt = Some(rtt);
+
+                if self.reachable != Some(true) {
+                    self.reachable = Some(true);
+                    self.transition(Reachability::Up(rtt));
+                }
+            }
+            None => {
+                self.failures += 1;
+
+                if let Target::OutOfDialog { target_tp_info, .. } = &mut self.target {
+                    // Resolve the target again with the next request, the peer may have failed over
+                    target_tp_info.transport = None;
+                }
+
+                if self.failures >= self.failure_threshold && self.reachable != Some(false) {
+                    self.reachable = Some(false);
+                    self.transition(Reachability::Down);
+                }
+            }
+        }
+
+        rtt
+    }
+
+    fn transition(&mut self, reachability: Reachability) {
+        if let Some(on_transition) = &mut self.on_transition {
+            on_transition(reachability);
+        }
+    }
+
+    async fn send_options(&mut self) -> Result<Code> {
+        let response = match &mut self.target {
+            Target::Dialog(dialog) => {
+                let request = create_options(dialog.create_request(Method::OPTIONS));
+
+                let mut target_tp_info = dialog.target_tp_info.lock().await;
+
+                let mut transaction = dialog
+                    .endpoint
+                    .send_request(request, &mut target_tp_info)
+                    .await?;
+
+                drop(target_tp_info);
+
+                transaction.receive_final().await?
+            }
+            Target::OutOfDialog {
+                endpoint,
+                from,
+                to,
+                call_id,
+                cseq,
+                target,
+                target_tp_info,
+            } => {
+                let mut request = Request::new(Method::OPTIONS, target.clone());
+
+                request.headers.insert_named(&MaxForwards(70));
+                request.headers.insert_type(Name::FROM, from);
+                request.headers.insert_type(Name::TO, to);
+                request.headers.insert_named(call_id);
+                request
+                    .headers
+                    .insert_named(&CSeq::new(*cseq, Method::OPTIONS));
+
+                *cseq += 1;
+
+                let mut transaction = endpoint
+                    .send_request(create_options(request), target_tp_info)
+                    .await?;
+
+                transaction.receive_final().await?
+            }
+        };
+
+        Ok(response.line.code)
+    }
+}
+
+fn create_options(mut request: Request) -> Request {
+    request
+        .headers
+        .insert_named(&Accept("application/sdp".into()));
+
+    request
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sip_types::uri::sip::SipUri;
+    use std::sync::Mutex;
+
+    fn ping() -> (OptionsPing, Arc<Mutex<Vec<Reachability>>>) {
+        let local: SipUri = "sip:alice@example.org".parse().unwrap();
+        let target: SipUri = "sip:trunk.example.com".parse().unwrap();
+
+        let transitions = Arc::new(Mutex::new(vec![]));
+
+        let ping = OptionsPing::new(
+            Endpoint::builder().build(),
+            NameAddr::uri(local),
+            Box::new(target),
+        )
+        .on_transition({
+            let transitions = transitions.clone();
+            move |reachability| transitions.lock().unwrap().push(reachability)
+        });
+
+        (ping, transitions)
+    }
+
+    #[tokio::test]
+    async fn reachability_transitions() {
+        let (mut ping, transitions) = ping();
+        let rtt = Duration::from_millis(20);
+
+        assert_eq!(ping.is_reachable(), None);
+
+        // Any response, even an error, means the peer is reachable
+        assert_eq!(ping.update(Some(Code::NOT_FOUND), rtt), Some(rtt));
+        assert_eq!(ping.is_reachable(), Some(true));
+        assert_eq!(ping.update(Some(Code::OK), rtt), Some(rtt));
+
+        // Down only after the failure threshold is reached
+        assert_eq!(ping.update(None, rtt), None);
+        assert_eq!(ping.is_reachable(), Some(true));
+        assert_eq!(ping.update(Some(Code::SERVICE_UNAVAILABLE), rtt), None);
+        assert_eq!(ping.is_reachable(), Some(false));
+        assert_eq!(ping.update(Some(Code::REQUEST_TIMEOUT), rtt), None);
+
+        assert_eq!(ping.update(Some(Code::OK), rtt), Some(rtt));
+        assert_eq!(ping.rtt(), Some(rtt));
+
+        assert_eq!(
+            *transitions.lock().unwrap(),
+            [
+                Reachability::Up(rtt),
+                Reachability::Down,
+                Reachability::Up(rtt)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failures_reset_on_success() {
+        let (ping, transitions) = ping();
+        let mut ping = ping.with_failure_threshold(2);
+        let rtt = Duration::from_millis(20);
+
+        ping.update(Some(Code::OK), rtt);
+        ping.update(None, rtt);
+        ping.update(Some(Code::OK), rtt);
+        ping.update(None, rtt);
+
+        assert_eq!(ping.is_reachable(), Some(true));
+        assert_eq!(*transitions.lock().unwrap(), [Reachability::Up(rtt)]);
+    }
+
+    #[tokio::test]
+    async fn initially_down() {
+        let (ping, transitions) = ping();
+        let mut ping = ping.with_failure_threshold(0);
+
+        ping.update(None, Duration::ZERO);
+
+        // The threshold is at least 1
+        assert_eq!(ping.is_reachable(), Some(false));
+        assert_eq!(*transitions.lock().unwrap(), [Reachability::Down]);
+    }
+}
@@ -0,0 +1,574 @@
+//! Presence event package ([RFC3856](https://datatracker.ietf.org/doc/html/rfc3856))
+//!
+//! Watch the presence of an entity using a [`Subscriber<Presence>`](crate::subscription::Subscriber)
+//! and publish it to watchers using a [`Notifier<Presence>`](crate::subscription::Notifier).
+//! The state is exchanged as PIDF ([RFC3863](https://datatracker.ietf.org/doc/html/rfc3863))
+//! documents, see [`Pidf`], which may contain rich presence
+//! ([RFC4480](https://datatracker.ietf.org/doc/html/rfc4480)) information.
+
+use crate::subscription::EventPackage;
+use bytes::Bytes;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::fmt;
+
+/// The `presence` event package
+pub struct Presence;
+
+impl EventPackage for Presence {
+    const EVENT: &'static str = "presence";
+    const CONTENT_TYPE: &'static str = PIDF_CONTENT_TYPE;
+}
+
+pub const PIDF_CONTENT_TYPE: &str = "application/pidf+xml";
+
+const PIDF_NS: &str = "urn:ietf:params:xml:ns:pidf";
+const DATA_MODEL_NS: &str = "urn:ietf:params:xml:ns:pidf:data-model";
+const RPID_NS: &str = "urn:ietf:params:xml:ns:pidf:rpid";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PidfError {
+    #[error(transparent)]
+    Xml(#[from] quick_xml::Error),
+    #[error("document has no presence element")]
+    MissingPresence,
+}
+
+/// `<basic>` status of a tuple
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BasicStatus {
+    Open,
+    Closed,
+}
+
+/// `<tuple>` element, describes a single communication address of the presentity
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuple {
+    pub id: String,
+    pub basic: Option<BasicStatus>,
+    pub contact: Option<String>,
+    /// `priority` attribute of the contact, between 0 and 1
+    pub priority: Option<f32>,
+    pub timestamp: Option<String>,
+    pub notes: Vec<String>,
+}
+
+impl Tuple {
+    pub fn new<I: Into<String>>(id: I, basic: BasicStatus) -> Self {
+        Self {
+            id: id.into(),
+            basic: Some(basic),
+            contact: None,
+            priority: None,
+            timestamp: None,
+            notes: vec![],
+        }
+    }
+}
+
+/// Rich presence activity ([RFC4480](https://datatracker.ietf.org/doc/html/rfc4480#section-3.2))
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Activity {
+    Appointment,
+    Away,
+    Breakfast,
+    Busy,
+    Dinner,
+    Holiday,
+    InTransit,
+    LookingForWork,
+    Meal,
+    Meeting,
+    OnThePhone,
+    Performance,
+    PermanentAbsence,
+    Playing,
+    Presentation,
+    Shopping,
+    Sleeping,
+    Spectator,
+    Steering,
+    Travel,
+    Tv,
+    Unknown,
+    Vacation,
+    Working,
+    Worship,
+    /// `<rpid:other>` element or an activity defined by an extension
+    Other(String),
+}
+
+macro_rules! activities {
+    ($($variant:ident => $name:literal),* $(,)?) => {
+        impl Activity {
+            fn from_name(name: &str) -> Self {
+                match name {
+                    $($name => Self::$variant,)*
+                    other => Self::Other(other.into()),
+                }
+            }
+
+            fn name(&self) -> Option<&'static str> {
+                match self {
+                    $(Self::$variant => Some($name),)*
+                    Self::Other(_) => None,
+                }
+            }
+        }
+    };
+}
+
+activities! {
+    Appointment => "appointment",
+    Away => "away",
+    Breakfast => "breakfast",
+    Busy => "busy",
+    Dinner => "dinner",
+    Holiday => "holiday",
+    InTransit => "in-transit",
+    LookingForWork => "looking-for-work",
+    Meal => "meal",
+    Meeting => "meeting",
+    OnThePhone => "on-the-phone",
+    Performance => "performance",
+    PermanentAbsence => "permanent-absence",
+    Playing => "playing",
+    Presentation => "presentation",
+    Shopping => "shopping",
+    Sleeping => "sleeping",
+    Spectator => "spectator",
+    Steering => "steering",
+    Travel => "travel",
+    Tv => "tv",
+    Unknown => "unknown",
+    Vacation => "vacation",
+    Working => "working",
+    Worship => "worship",
+}
+
+/// Moods defined by [RFC4480](https://datatracker.ietf.org/doc/html/rfc4480#section-3.2),
+/// all others are written as `<rpid:other>`
+const MOODS: &[&str] = &[
+    "afraid",
+    "amazed",
+    "angry",
+    "annoyed",
+    "anxious",
+    "ashamed",
+    "bored",
+    "brave",
+    "calm",
+    "cold",
+    "confused",
+    "contented",
+    "cranky",
+    "curious",
+    "depressed",
+    "disappointed",
+    "disgusted",
+    "distracted",
+    "embarrassed",
+    "excited",
+    "flirtatious",
+    "frustrated",
+    "grumpy",
+    "guilty",
+    "happy",
+    "hot",
+    "humbled",
+    "humiliated",
+    "hungry",
+    "hurt",
+    "impressed",
+    "in_awe",
+    "in_love",
+    "indignant",
+    "interested",
+    "invincible",
+    "jealous",
+    "lonely",
+    "mean",
+    "moody",
+    "nervous",
+    "neutral",
+    "offended",
+    "playful",
+    "proud",
+    "relieved",
+    "remorseful",
+    "restless",
+    "sad",
+    "sarcastic",
+    "serious",
+    "shocked",
+    "shy",
+    "sick",
+    "sleepy",
+    "stressed",
+    "surprised",
+    "thirsty",
+    "unknown",
+    "worried",
+];
+
+/// `<dm:person>` element ([RFC4479](https://datatracker.ietf.org/doc/html/rfc4479)), carries
+/// the rich presence information of the presentity
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Person {
+    pub id: String,
+    pub activities: Vec<Activity>,
+    /// Names of the `<rpid:mood>` child elements, e.g. `happy`, or the text of `<rpid:other>`
+    ///
+    /// Moods not defined by RFC4480 are written as `<rpid:other>`.
+    pub moods: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+/// PIDF presence document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pidf {
+    /// URI of the presentity
+    pub entity: String,
+    pub tuples: Vec<Tuple>,
+    pub persons: Vec<Person>,
+    pub notes: Vec<String>,
+}
+
+impl Pidf {
+    pub fn new<E: Into<String>>(entity: E) -> Self {
+        Self {
+            entity: entity.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns if any tuple of the document has the basic status `open`
+    pub fn is_open(&self) -> bool {
+        self.tuples
+            .iter()
+            .any(|tuple| tuple.basic == Some(BasicStatus::Open))
+    }
+
+    /// Parse a PIDF document
+    ///
+    /// Elements are matched by their local name, unknown elements are ignored.
+    pub fn parse(body: &[u8]) -> Result<Self, PidfError> {
+        let mut reader = Reader::from_reader(body);
+        reader.trim_text(true);
+
+        let mut pidf = None;
+        let mut path: Vec<Vec<u8>> = vec![];
+        let mut buf = vec![];
+
+        loop {
+            buf.clear();
+
+            let (start, empty) = match reader.read_event_into(&mut buf)? {
+                Event::Start(start) => (start.into_owned(), false),
+                Event::Empty(start) => (start.into_owned(), true),
+                Event::End(_) => {
+                    path.pop();
+                    continue;
+                }
+                Event::Text(text) => {
+                    if let Some(pidf) = &mut pidf {
+                        handle_text(pidf, &path, text.unescape()?.into_owned());
+                    }
+                    continue;
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+
+            let name = start.local_name().as_ref().to_vec();
+
+            match (path_names(&path).as_slice(), name.as_slice()) {
+                ([], b"presence") => {
+                    pidf = Some(Pidf::new(attribute(&start, "entity")?.unwrap_or_default()));
+                }
+                ([b"presence"], b"tuple") => {
+                    if let Some(pidf) = &mut pidf {
+                        pidf.tuples.push(Tuple {
+                            id: attribute(&start, "id")?.unwrap_or_default(),
+                            basic: None,
+                            contact: None,
+                            priority: None,
+                            timestamp: None,
+                            notes: vec![],
+                        });
+                    }
+                }
+                ([b"presence", b"tuple"], b"contact") => {
+                    let priority = attribute(&start, "priority")?.and_then(|p| p.parse().ok());
+
+                    if let Some(tuple) = pidf.as_mut().and_then(|pidf| pidf.tuples.last_mut()) {
+                        tuple.priority = priority;
+                    }
+                }
+                ([b"presence"], b"person") => {
+                    if let Some(pidf) = &mut pidf {
+                        pidf.persons.push(Person {
+                            id: attribute(&start, "id")?.unwrap_or_default(),
+                            ..Person::default()
+                        });
+                    }
+                }
+                ([b"presence", b"person", b"activities"], activity) if activity != b"other" => {
+                    if let Some(person) = pidf.as_mut().and_then(|pidf| pidf.persons.last_mut()) {
+                        let activity = String::from_utf8_lossy(activity);
+                        person.activities.push(Activity::from_name(&activity));
+                    }
+                }
+                ([b"presence", b"person", b"mood"], mood) if mood != b"other" => {
+                    if let Some(person) = pidf.as_mut().and_then(|pidf| pidf.persons.last_mut()) {
+                        person
+                            .moods
+                            .push(String::from_utf8_lossy(mood).into_owned());
+                    }
+                }
+                _ => {}
+            }
+
+            if !empty {
+                path.push(name);
+            }
+        }
+
+        pidf.ok_or(PidfError::MissingPresence)
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        self.to_string().into()
+    }
+}
+
+fn path_names(path: &[Vec<u8>]) -> Vec<&[u8]> {
+    path.iter().map(Vec::as_slice).collect()
+}
+
+fn attribute(start: &BytesStart<'_>, name: &str) -> Result<Option<String>, PidfError> {
+    match start.try_get_attribute(name)? {
+        Some(attribute) => Ok(Some(attribute.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+fn handle_text(pidf: &mut Pidf, path: &[Vec<u8>], text: String) {
+    match path_names(path).as_slice() {
+        [b"presence", b"note"] => pidf.notes.push(text),
+        [b"presence", b"tuple", rest @ ..] => {
+            let Some(tuple) = pidf.tuples.last_mut() else {
+                return;
+            };
+
+            match rest {
+                [b"status", b"basic"] => {
+                    tuple.basic = match text.as_str() {
+                        "open" => Some(BasicStatus::Open),
+                        "closed" => Some(BasicStatus::Closed),
+                        _ => None,
+                    }
+                }
+                [b"contact"] => tuple.contact = Some(text),
+                [b"timestamp"] => tuple.timestamp = Some(text),
+                [b"note"] => tuple.notes.push(text),
+                _ => {}
+            }
+        }
+        [b"presence", b"person", rest @ ..] => {
+            let Some(person) = pidf.persons.last_mut() else {
+                return;
+            };
+
+            match rest {
+                [b"activities", b"other"] => person.activities.push(Activity::Other(text)),
+                [b"mood", b"other"] => person.moods.push(text),
+                [b"note"] => person.notes.push(text),
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+}
+
+impl fmt::Display for Pidf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            f,
+            r#"<presence xmlns="{PIDF_NS}" xmlns:dm="{DATA_MODEL_NS}" xmlns:rpid="{RPID_NS}" entity="{}">"#,
+            escape(&self.entity)
+        )?;
+
+        for tuple in &self.tuples {
+            writeln!(f, r#"<tuple id="{}">"#, escape(&tuple.id))?;
+
+            if let Some(basic) = tuple.basic {
+                let basic = match basic {
+                    BasicStatus::Open => "open",
+                    BasicStatus::Closed => "closed",
+                };
+
+                writeln!(f, "<status><basic>{basic}</basic></status>")?;
+            }
+
+            if let Some(contact) = &tuple.contact {
+                match tuple.priority {
+                    Some(priority) => writeln!(
+                        f,
+                        r#"<contact priority="{priority}">{}</contact>"#,
+                        escape(contact)
+                    )?,
+                    None => writeln!(f, "<contact>{}</contact>", escape(contact))?,
+                }
+            }
+
+            for note in &tuple.notes {
+                writeln!(f, "<note>{}</note>", escape(note))?;
+            }
+
+            if let Some(timestamp) = &tuple.timestamp {
+                writeln!(f, "<timestamp>{}</timestamp>", escape(timestamp))?;
+            }
+
+            writeln!(f, "</tuple>")?;
+        }
+
+        // Notes must precede the elements of other namespaces, like `<dm:person>`
+        for note in &self.notes {
+            writeln!(f, "<note>{}</note>", escape(note))?;
+        }
+
+        for person in &self.persons {
+            writeln!(f, r#"<dm:person id="{}">"#, escape(&person.id))?;
+
+            if !person.activities.is_empty() {
+                write!(f, "<rpid:activities>")?;
+
+                for activity in &person.activities {
+                    match activity.name() {
+                        Some(name) => write!(f, "<rpid:{name}/>")?,
+                        None => {
+                            if let Activity::Other(other) = activity {
+                                write!(f, "<rpid:other>{}</rpid:other>", escape(other))?;
+                            }
+                        }
+                    }
+                }
+
+                writeln!(f, "</rpid:activities>")?;
+            }
+
+            if !person.moods.is_empty() {
+                write!(f, "<rpid:mood>")?;
+
+                for mood in &person.moods {
+                    if MOODS.contains(&mood.as_str()) {
+                        write!(f, "<rpid:{mood}/>")?;
+                    } else {
+                        write!(f, "<rpid:other>{}</rpid:other>", escape(mood))?;
+                    }
+                }
+
+                writeln!(f, "</rpid:mood>")?;
+            }
+
+            for note in &person.notes {
+                writeln!(f, "<dm:note>{}</dm:note>", escape(note))?;
+            }
+
+            writeln!(f, "</dm:person>")?;
+        }
+
+        write!(f, "</presence>")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pidf() -> Pidf {
+        let mut tuple = Tuple::new("t1", BasicStatus::Open);
+        tuple.contact = Some("sip:alice@example.org".into());
+        tuple.priority = Some(0.8);
+        tuple.timestamp = Some("2005-12-09T13:40:00Z".into());
+        tuple.notes = vec!["Don't bother me & my <friends>".into()];
+
+        Pidf {
+            entity: "pres:alice@example.org".into(),
+            tuples: vec![tuple, Tuple::new("t2", BasicStatus::Closed)],
+            persons: vec![Person {
+                id: "p1".into(),
+                activities: vec![Activity::OnThePhone, Activity::Other("juggling".into())],
+                moods: vec!["happy".into(), "overjoyed".into()],
+                notes: vec!["Talking".into()],
+            }],
+            notes: vec!["Available".into()],
+        }
+    }
+
+    #[test]
+    fn pidf_round_trip() {
+        let pidf = pidf();
+
+        let parsed = Pidf::parse(&pidf.to_bytes()).unwrap();
+
+        assert_eq!(parsed, pidf);
+        assert!(parsed.is_open());
+    }
+
+    #[test]
+    fn pidf_print() {
+        let printed = pidf().to_string();
+
+        assert!(printed.contains(r#"<contact priority="0.8">sip:alice@example.org</contact>"#));
+        assert!(printed.contains("<note>Don&apos;t bother me &amp; my &lt;friends&gt;</note>"));
+        assert!(printed.contains(
+            "<rpid:activities><rpid:on-the-phone/><rpid:other>juggling</rpid:other></rpid:activities>"
+        ));
+        assert!(printed
+            .contains("<rpid:mood><rpid:happy/><rpid:other>overjoyed</rpid:other></rpid:mood>"));
+
+        // Document level notes precede the person elements
+        let note = printed.find("<note>Available</note>").unwrap();
+        let person = printed.find("<dm:person").unwrap();
+        assert!(note < person);
+    }
+
+    #[test]
+    fn pidf_parse() {
+        let body = br#"<?xml version="1.0" encoding="UTF-8"?>
+<presence xmlns="urn:ietf:params:xml:ns:pidf" xmlns:p="urn:ietf:params:xml:ns:pidf:data-model"
+    xmlns:r="urn:ietf:params:xml:ns:pidf:rpid" entity="pres:bob@example.org">
+  <tuple id="a">
+    <status><basic>closed</basic></status>
+    <unknown-element>ignored</unknown-element>
+  </tuple>
+  <note>Gone fishing</note>
+  <p:person id="b">
+    <r:activities><r:shopping/><r:other>fishing</r:other></r:activities>
+    <r:mood><r:in_love/></r:mood>
+  </p:person>
+</presence>"#;
+
+        let pidf = Pidf::parse(body).unwrap();
+
+        assert_eq!(pidf.entity, "pres:bob@example.org");
+        assert_eq!(pidf.tuples.len(), 1);
+        assert_eq!(pidf.tuples[0].basic, Some(BasicStatus::Closed));
+        assert!(!pidf.is_open());
+        assert_eq!(pidf.notes, ["Gone fishing"]);
+        assert_eq!(
+            pidf.persons[0].activities,
+            [Activity::Shopping, Activity::Other("fishing".into())]
+        );
+        assert_eq!(pidf.persons[0].moods, ["in_love"]);
+
+        assert!(matches!(
+            Pidf::parse(b"<other/>"),
+            Err(PidfError::MissingPresence)
+        ));
+    }
+}
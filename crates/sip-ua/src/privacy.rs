@@ -0,0 +1,120 @@
+//! Privacy service ([RFC3323](https://datatracker.ietf.org/doc/html/rfc3323))
+//!
+//! A [`PrivacyPolicy`] is applied to requests leaving the trust domain which contain a
+//! [`Privacy`] header, the [`DefaultPrivacyPolicy`] removes all headers which reveal the
+//! identity of the user as requested.
+
+use sip_types::header::typed::{Privacy, PrivacyValue};
+use sip_types::{Headers, Name};
+
+const HISTORY_INFO: Name = Name::custom("History-Info", &["history-info"]);
+
+/// Headers which may reveal information about the user, removed by `Privacy: header`
+///
+/// `Via`, `Record-Route` and `Contact` are needed for routing and must be anonymized by the
+/// application instead.
+pub const IDENTIFYING_HEADERS: [Name; 8] = [
+    Name::CALL_INFO,
+    Name::IN_REPLY_TO,
+    Name::ORGANIZATION,
+    Name::REPLY_TO,
+    Name::SERVER,
+    Name::SUBJECT,
+    Name::USER_AGENT,
+    Name::WARNING,
+];
+
+/// Hook to apply the privacy requested by a message
+pub trait PrivacyPolicy: Send + Sync + 'static {
+    /// Modify the headers of a message according to the requested privacy
+    ///
+    /// Only called if privacy is requested, see [`Privacy::is_requested`].
+    fn apply(&self, privacy: &Privacy, headers: &mut Headers);
+}
+
+/// [`PrivacyPolicy`] which removes identity revealing headers
+///
+/// - `id`: removes `P-Asserted-Identity`
+/// - `header`: removes the [`IDENTIFYING_HEADERS`]
+/// - `history`: removes `History-Info`
+///
+/// `user` and `session` privacy cannot be applied by removing headers, as they require changing
+/// the `From` header or relaying the media.
+pub struct DefaultPrivacyPolicy;
+
+impl PrivacyPolicy for DefaultPrivacyPolicy {
+    fn apply(&self, privacy: &Privacy, headers: &mut Headers) {
+        if privacy.contains(&PrivacyValue::Id) {
+            headers.remove(&Name::P_ASSERTED_IDENTITY);
+        }
+
+        if privacy.contains(&PrivacyValue::Header) {
+            for name in &IDENTIFYING_HEADERS {
+                headers.remove(name);
+            }
+        }
+
+        if privacy.contains(&PrivacyValue::History) {
+            headers.remove(&HISTORY_INFO);
+        }
+    }
+}
+
+/// Apply the `policy` if the headers contain a `Privacy` header requesting privacy
+pub fn apply_privacy(policy: &dyn PrivacyPolicy, headers: &mut Headers) {
+    let Ok(privacy) = headers.get_named::<Privacy>() else {
+        return;
+    };
+
+    if privacy.is_requested() {
+        policy.apply(&privacy, headers);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytesstr::BytesStr;
+
+    fn headers(privacy: &str) -> Headers {
+        let mut headers = Headers::new();
+        headers.insert(Name::PRIVACY, BytesStr::from(privacy));
+        headers.insert(Name::P_ASSERTED_IDENTITY, "<sip:alice@example.org>");
+        headers.insert(Name::USER_AGENT, "ezk");
+        headers.insert(Name::SUBJECT, "Lunch");
+        headers.insert(HISTORY_INFO, "<sip:bob@example.org>;index=1");
+        headers
+    }
+
+    #[test]
+    fn default_policy() {
+        let mut id = headers("id");
+        apply_privacy(&DefaultPrivacyPolicy, &mut id);
+        assert!(!id.contains(&Name::P_ASSERTED_IDENTITY));
+        assert!(id.contains(&Name::USER_AGENT));
+        assert!(id.contains(&HISTORY_INFO));
+
+        let mut header = headers("header;history");
+        apply_privacy(&DefaultPrivacyPolicy, &mut header);
+        assert!(header.contains(&Name::P_ASSERTED_IDENTITY));
+        assert!(!header.contains(&Name::USER_AGENT));
+        assert!(!header.contains(&Name::SUBJECT));
+        assert!(!header.contains(&HISTORY_INFO));
+    }
+
+    #[test]
+    fn privacy_not_requested() {
+        for privacy in ["none", "id;none"] {
+            let mut headers = headers(privacy);
+            apply_privacy(&DefaultPrivacyPolicy, &mut headers);
+
+            assert!(headers.contains(&Name::P_ASSERTED_IDENTITY));
+            assert!(headers.contains(&Name::USER_AGENT));
+        }
+
+        let mut headers = headers("");
+        headers.remove(&Name::PRIVACY);
+        apply_privacy(&DefaultPrivacyPolicy, &mut headers);
+        assert!(headers.contains(&Name::P_ASSERTED_IDENTITY));
+    }
+}
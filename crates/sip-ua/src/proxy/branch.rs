@@ -0,0 +1,348 @@
+use sip_core::transaction::{ClientInvTsx, TsxResponse};
+use sip_core::transport::TargetTransportInfo;
+use sip_core::{Endpoint, Error, Request, Result};
+use sip_types::header::typed::{Reason, Routing};
+use sip_types::{Code, CodeKind, Method, Name};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{sleep_until, Instant};
+
+pub(super) type Events = mpsc::UnboundedSender<(usize, BranchEvent)>;
+
+#[allow(clippy::large_enum_variant)]
+pub(super) enum BranchEvent {
+    Response(TsxResponse),
+    /// The branch failed without receiving a final response
+    Failed(Code),
+    /// The branch will not send any more events
+    Terminated,
+}
+
+/// Handle to a client transaction forwarding the request to a single target
+pub(super) struct Branch {
+    cancel: Option<oneshot::Sender<Option<Reason>>>,
+}
+
+impl Branch {
+    pub(super) fn spawn(
+        idx: usize,
+        endpoint: Endpoint,
+        request: Request,
+        timer_c: Duration,
+        events: Events,
+    ) -> Self {
+        if request.line.method == Method::INVITE {
+            let (cancel_tx, cancel_rx) = oneshot::channel();
+
+            tokio::spawn(async move {
+                run_invite(idx, &endpoint, request, timer_c, cancel_rx, &events).await;
+                let _ = events.send((idx, BranchEvent::Terminated));
+            });
+
+            Self {
+                cancel: Some(cancel_tx),
+            }
+        } else {
+            tokio::spawn(async move {
+                run_non_invite(idx, &endpoint, request, &events).await;
+                let _ = events.send((idx, BranchEvent::Terminated));
+            });
+
+            // Non-INVITE transactions cannot be cancelled
+            Self { cancel: None }
+        }
+    }
+
+    /// Cancel the branch if it hasn't received a final response yet
+    pub(super) fn cancel(&mut self, reason: Option<Reason>) {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(reason);
+        }
+    }
+}
+
+/// Requests are sent to the first Route, or to the Request-URI if there is none
+async fn target_tp_info(endpoint: &Endpoint, request: &Request) -> Result<TargetTransportInfo> {
+    let mut target_tp_info = TargetTransportInfo::default();
+
+    let route: Vec<Routing> = request.headers.get(Name::ROUTE).unwrap_or_default();
+
+    if let Some(route) = route.first() {
+        target_tp_info.transport = Some(endpoint.select_transport(&*route.uri.uri).await?);
+    }
+
+    Ok(target_tp_info)
+}
+
+fn failure_code(e: &Error) -> Code {
+    match e {
+        Error::RequestTimedOut => Code::REQUEST_TIMEOUT,
+        _ => Code::SERVICE_UNAVAILABLE,
+    }
+}
+
+async fn run_invite(
+    idx: usize,
+    endpoint: &Endpoint,
+    request: Request,
+    timer_c: Duration,
+    mut cancel: oneshot::Receiver<Option<Reason>>,
+    events: &Events,
+) {
+    let send = async {
+        let mut target_tp_info = target_tp_info(endpoint, &request).await?;
+        endpoint.send_invite(request, &mut target_tp_info).await
+    };
+
+    let mut transaction = match send.await {
+        Ok(transaction) => transaction,
+        Err(e) => {
+            log::debug!("failed to forward INVITE, {e}");
+            let _ = events.send((idx, BranchEvent::Failed(failure_code(&e))));
+            return;
+        }
+    };
+
+    let mut timer_c_deadline = Instant::now() + timer_c;
+
+    let mut proceeding = false;
+    let mut accepted = false;
+    let mut cancel_requested = false;
+    let mut cancel_sent = false;
+    let mut cancel_reason = None;
+
+    loop {
+        tokio::select! {
+            result = transaction.receive() => {
+                let response = match result {
+                    Ok(Some(response)) => response,
+                    Ok(None) => return,
+                    Err(e) => {
+                        let _ = events.send((idx, BranchEvent::Failed(failure_code(&e))));
+                        return;
+                    }
+                };
+
+                let kind = response.line.code.kind();
+
+                match kind {
+                    CodeKind::Provisional => {
+                        proceeding = true;
+                        timer_c_deadline = Instant::now() + timer_c;
+
+                        // CANCEL must not be sent before a provisional response was received
+                        if cancel_requested && !cancel_sent {
+                            cancel_sent = true;
+                            send_cancel(&transaction, cancel_reason.take()).await;
+                        }
+                    }
+                    CodeKind::Success => accepted = true,
+                    _ => {}
+                }
+
+                let _ = events.send((idx, BranchEvent::Response(response)));
+
+                if !matches!(kind, CodeKind::Provisional | CodeKind::Success) {
+                    return;
+                }
+            }
+            reason = &mut cancel, if !cancel_requested => {
+                cancel_requested = true;
+                cancel_reason = reason.ok().flatten();
+
+                if proceeding && !accepted {
+                    cancel_sent = true;
+                    send_cancel(&transaction, cancel_reason.take()).await;
+                }
+            }
+            _ = sleep_until(timer_c_deadline), if !accepted => {
+                if proceeding && !cancel_sent {
+                    // Timer C fired, cancel the branch and wait for its final response
+                    cancel_sent = true;
+                    send_cancel(&transaction, None).await;
+                    timer_c_deadline = Instant::now() + timer_c;
+                } else {
+                    let _ = events.send((idx, BranchEvent::Failed(Code::REQUEST_TIMEOUT)));
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn send_cancel(transaction: &ClientInvTsx, reason: Option<Reason>) {
+    match transaction.cancel_with_reason(reason.as_slice()).await {
+        Ok(Some(mut cancel)) => {
+            tokio::spawn(async move {
+                if let Err(e) = cancel.receive_final().await {
+                    log::debug!("CANCEL request failed, {e}");
+                }
+            });
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("failed to send CANCEL, {e}"),
+    }
+}
+
+async fn run_non_invite(idx: usize, endpoint: &Endpoint, request: Request, events: &Events) {
+    let send = async {
+        let mut target_tp_info = target_tp_info(endpoint, &request).await?;
+        endpoint.send_request(request, &mut target_tp_info).await
+    };
+
+    let mut transaction = match send.await {
+        Ok(transaction) => transaction,
+        Err(e) => {
+            log::debug!("failed to forward request, {e}");
+            let _ = events.send((idx, BranchEvent::Failed(failure_code(&e))));
+            return;
+        }
+    };
+
+    loop {
+        match transaction.receive().await {
+            Ok(response) => {
+                let is_final = response.line.code.kind() != CodeKind::Provisional;
+
+                let _ = events.send((idx, BranchEvent::Response(response)));
+
+                if is_final {
+                    return;
+                }
+            }
+            Err(e) => {
+                let _ = events.send((idx, BranchEvent::Failed(failure_code(&e))));
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::proxy::test::{Peer, Setup};
+    use bytes::Bytes;
+    use sip_types::header::typed::{CSeq, CallID, FromTo, MaxForwards};
+    use sip_types::msg::RequestLine;
+    use sip_types::uri::sip::SipUri;
+    use sip_types::uri::{NameAddr, Uri};
+    use sip_types::Headers;
+    use tokio::time::timeout;
+
+    /// Never reached in the tests
+    const TIMER_C: Duration = Duration::from_secs(180);
+
+    fn request(method: Method, uri: Box<dyn Uri>) -> Request {
+        let from: SipUri = "sip:alice@example.org".parse().unwrap();
+
+        let mut headers = Headers::new();
+        headers.insert_named(&MaxForwards(70));
+        headers.insert_type(
+            Name::FROM,
+            &FromTo::new(NameAddr::uri(from), Some("1928301774".into())),
+        );
+        headers.insert_type(Name::TO, &FromTo::new(NameAddr::uri(uri.clone()), None));
+        headers.insert_named(&CallID("a84b4c76e66710@example.org".into()));
+        headers.insert_named(&CSeq {
+            cseq: 1,
+            method: method.clone(),
+        });
+
+        Request {
+            line: RequestLine { method, uri },
+            headers,
+            body: Bytes::new(),
+        }
+    }
+
+    async fn event(events: &mut mpsc::UnboundedReceiver<(usize, BranchEvent)>) -> BranchEvent {
+        let (idx, event) = timeout(Duration::from_secs(5), events.recv())
+            .await
+            .expect("no branch event")
+            .unwrap();
+
+        assert_eq!(idx, 3);
+        event
+    }
+
+    fn response_code(event: BranchEvent) -> u16 {
+        match event {
+            BranchEvent::Response(response) => response.line.code.into_u16(),
+            _ => panic!("expected response"),
+        }
+    }
+
+    #[test]
+    fn failure_codes() {
+        assert_eq!(failure_code(&Error::RequestTimedOut), Code::REQUEST_TIMEOUT);
+        assert_eq!(
+            failure_code(&Error::Io(std::io::ErrorKind::ConnectionRefused.into())),
+            Code::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn non_invite() {
+        let setup = Setup::new().await;
+        let peer = Peer::bind().await;
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+
+        let request = request(Method::OPTIONS, Box::new(peer.uri()));
+        let mut branch = Branch::spawn(3, setup.endpoint.clone(), request, TIMER_C, events_tx);
+
+        // Non-INVITE branches ignore cancellation
+        branch.cancel(None);
+
+        let options = peer.recv("OPTIONS").await;
+        peer.respond(&options, 200, "").await;
+
+        assert_eq!(response_code(event(&mut events).await), 200);
+        assert!(matches!(event(&mut events).await, BranchEvent::Terminated));
+    }
+
+    #[tokio::test]
+    async fn cancel_after_provisional() {
+        let setup = Setup::new().await;
+        let peer = Peer::bind().await;
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+
+        let request = request(Method::INVITE, Box::new(peer.uri()));
+        let mut branch = Branch::spawn(3, setup.endpoint.clone(), request, TIMER_C, events_tx);
+
+        let invite = peer.recv("INVITE").await;
+
+        // CANCEL is deferred until a provisional response has been received
+        branch.cancel(Some(Reason::sip(Code::OK)));
+        peer.assert_not_received("CANCEL", Duration::from_millis(200))
+            .await;
+
+        peer.respond(&invite, 180, "").await;
+        assert_eq!(response_code(event(&mut events).await), 180);
+
+        let cancel = peer.recv("CANCEL").await;
+        assert!(cancel.header("Reason").contains("cause=200"));
+        peer.respond(&cancel, 200, "").await;
+        peer.respond(&invite, 487, "").await;
+
+        assert_eq!(response_code(event(&mut events).await), 487);
+        assert!(matches!(event(&mut events).await, BranchEvent::Terminated));
+    }
+
+    #[tokio::test]
+    async fn transport_failure() {
+        let setup = Setup::new().await;
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+
+        // No transport is available for SCTP
+        let uri: SipUri = "sip:bob@127.0.0.1:5060;transport=sctp".parse().unwrap();
+        let request = request(Method::INVITE, Box::new(uri));
+        Branch::spawn(3, setup.endpoint.clone(), request, TIMER_C, events_tx);
+
+        assert!(matches!(
+            event(&mut events).await,
+            BranchEvent::Failed(Code::SERVICE_UNAVAILABLE)
+        ));
+        assert!(matches!(event(&mut events).await, BranchEvent::Terminated));
+    }
+}
@@ -0,0 +1,1348 @@
+//! Transaction stateful proxy ([RFC3261 Section 16](https://datatracker.ietf.org/doc/html/rfc3261#section-16))
+//!
+//! The [`Proxy`] forwards a request to one or more [`Target`]s, either in parallel or one after
+//! another (see [`ForkMode`]), and relays the best final response back to the sender. The targets
+//! are usually the bindings of a [`Registrar`], see [`Proxy::proxy`].
+//!
+//! The [`ProxyLayer`] must be added to the endpoint to propagate CANCEL requests to the
+//! branches of a forwarded INVITE.
+
+use crate::identity::{sign_headers, Attestation, IdentitySigner};
+use crate::privacy::{apply_privacy, PrivacyPolicy};
+use crate::registrar::{address_of_record, Binding, BindingStore, Registrar};
+use branch::{Branch, BranchEvent};
+use bytesstr::BytesStr;
+use parking_lot as pl;
+use response::{best_response, local_response, relay_response, BranchResponse};
+use sip_core::transaction::{Accepted, ServerInvTsx, ServerTsx, TsxKey};
+use sip_core::transport::{OutgoingResponse, TargetTransportInfo};
+use sip_core::{Endpoint, IncomingRequest, Layer, LayerKey, MayTake, Request, Result};
+use sip_types::header::typed::{MaxForwards, Reason, Routing};
+use sip_types::msg::RequestLine;
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::{Code, CodeKind, Headers, Method, Name};
+use std::collections::{HashMap, VecDeque};
+use std::future::pending;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify};
+use tokio::time::{sleep_until, Instant};
+
+mod branch;
+mod response;
+
+/// How a request is forwarded to multiple targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForkMode {
+    /// Forward the request to all targets at once
+    Parallel,
+    /// Forward the request to one target after another, until a 2xx or 6xx response is received
+    Sequential,
+}
+
+/// Destination a request is forwarded to
+#[derive(Debug, Clone)]
+pub struct Target {
+    /// Request-URI of the forwarded request
+    pub uri: Box<dyn Uri>,
+    /// Route headers inserted in front of the existing ones, e.g. the `Path` of a binding
+    pub route: Vec<Routing>,
+}
+
+impl Target {
+    pub fn new(uri: Box<dyn Uri>) -> Self {
+        Self { uri, route: vec![] }
+    }
+}
+
+impl From<Binding> for Target {
+    fn from(binding: Binding) -> Self {
+        Self {
+            uri: binding.contact.uri.uri,
+            route: binding.path,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ProxyLayer {
+    /// INVITE requests currently being forwarded, keyed by branch and CSeq number
+    cancellables: pl::Mutex<HashMap<(BytesStr, u32), Arc<Notify>>>,
+}
+
+#[async_trait::async_trait]
+impl Layer for ProxyLayer {
+    fn name(&self) -> &'static str {
+        "proxy"
+    }
+
+    async fn receive(&self, endpoint: &Endpoint, mut request: MayTake<'_, IncomingRequest>) {
+        if request.line.method != Method::CANCEL {
+            return;
+        }
+
+        let key = (
+            request.tsx_key.branch().clone(),
+            request.base_headers.cseq.cseq,
+        );
+
+        let Some(cancelled) = self.cancellables.lock().remove(&key) else {
+            return;
+        };
+
+        let cancel = request.inner().take().unwrap();
+
+        let transaction = endpoint.create_server_tsx(&cancel);
+        let response = endpoint.create_response(&cancel, Code::OK, None);
+
+        cancelled.notify_one();
+
+        if let Err(e) = transaction.respond(response).await {
+            log::warn!("failed to respond to CANCEL request, {e}");
+        }
+    }
+}
+
+/// Removes the cancellable INVITE from the [`ProxyLayer`] once the forwarding has completed
+struct CancellableGuard {
+    endpoint: Endpoint,
+    layer: LayerKey<ProxyLayer>,
+    key: (BytesStr, u32),
+}
+
+impl Drop for CancellableGuard {
+    fn drop(&mut self) {
+        self.endpoint[self.layer]
+            .cancellables
+            .lock()
+            .remove(&self.key);
+    }
+}
+
+pub struct Proxy {
+    endpoint: Endpoint,
+    layer: LayerKey<ProxyLayer>,
+
+    fork_mode: ForkMode,
+    record_route: Option<Routing>,
+    timer_c: Duration,
+    branch_timeout: Option<Duration>,
+    privacy: Option<Box<dyn PrivacyPolicy>>,
+    identity_signer: Option<(Box<dyn IdentitySigner>, Attestation)>,
+}
+
+impl Proxy {
+    pub fn new(endpoint: Endpoint, layer: LayerKey<ProxyLayer>) -> Self {
+        Self {
+            endpoint,
+            layer,
+            fork_mode: ForkMode::Parallel,
+            record_route: None,
+            timer_c: Duration::from_secs(180),
+            branch_timeout: None,
+            privacy: None,
+            identity_signer: None,
+        }
+    }
+
+    /// Set how requests are forwarded to multiple targets, defaults to [`ForkMode::Parallel`]
+    pub fn with_fork_mode(mut self, fork_mode: ForkMode) -> Self {
+        self.fork_mode = fork_mode;
+        self
+    }
+
+    /// Insert a Record-Route header with the given URI into dialog creating requests, to stay in
+    /// the path of all subsequent requests of the dialog
+    ///
+    /// The URI should contain the `lr` parameter. Route headers containing the URI are removed
+    /// from incoming requests.
+    pub fn with_record_route(mut self, uri: Box<dyn Uri>) -> Self {
+        self.record_route = Some(Routing {
+            uri: NameAddr::uri(uri),
+            params: Default::default(),
+        });
+        self
+    }
+
+    /// Set the duration a forwarded INVITE may stay without a final response before it is
+    /// cancelled, reset with every provisional response. Defaults to 3 minutes.
+    ///
+    /// Values below 3 minutes are raised to 3 minutes, as Timer C must be larger than that
+    /// ([RFC3261 Section 16.6](https://datatracker.ietf.org/doc/html/rfc3261#section-16.6)).
+    /// Use [`Self::with_branch_timeout`] to try the next target earlier.
+    pub fn with_timer_c(mut self, timer_c: Duration) -> Self {
+        self.timer_c = timer_c.max(Duration::from_secs(180));
+        self
+    }
+
+    /// With [`ForkMode::Sequential`], cancel a forwarded INVITE which hasn't received a final
+    /// response within the given duration after it was sent and forward the request to the next
+    /// target. Disabled by default.
+    ///
+    /// Unlike Timer C, the timeout isn't reset by provisional responses.
+    pub fn with_branch_timeout(mut self, timeout: Duration) -> Self {
+        self.branch_timeout = Some(timeout);
+        self
+    }
+
+    /// Act as privacy service, applying the policy to forwarded requests which request privacy
+    ///
+    /// Should only be set if the proxy forwards requests out of the trust domain.
+    pub fn with_privacy<P: PrivacyPolicy>(mut self, policy: P) -> Self {
+        self.privacy = Some(Box::new(policy));
+        self
+    }
+
+    /// Act as authentication service, adding a signed SHAKEN PASSporT to forwarded INVITE
+    /// requests which create a dialog and don't contain an `Identity` header yet
+    pub fn with_identity_signer<S: IdentitySigner>(
+        mut self,
+        signer: S,
+        attest: Attestation,
+    ) -> Self {
+        self.identity_signer = Some((Box::new(signer), attest));
+        self
+    }
+
+    /// Forward the request using the location service of the registrar
+    ///
+    /// Requests which contain Route headers (after removing the proxy's own) or which are sent
+    /// inside a dialog are forwarded to their Request-URI. All other requests are forwarded to
+    /// the bindings of the address of record in the Request-URI, or rejected with 480 if there
+    /// are none.
+    pub async fn proxy<S: BindingStore>(
+        &self,
+        registrar: &Registrar<S>,
+        request: IncomingRequest,
+    ) -> Result<()> {
+        let routes = self.routes(&request);
+
+        if !routes.is_empty() || request.base_headers.to.tag.is_some() {
+            let target = Target::new(request.line.uri.clone());
+            return self.forward_routed(request, routes, vec![target]).await;
+        }
+
+        let aor = address_of_record(&*request.line.uri);
+
+        let targets = match registrar.lookup(&aor).await {
+            Ok(bindings) => bindings.into_iter().map(Target::from).collect(),
+            Err(e) => {
+                log::warn!("failed to look up bindings of {aor}, {e}");
+                return self.respond(&request, Code::SERVER_INTERNAL_ERROR).await;
+            }
+        };
+
+        self.forward_routed(request, routes, targets).await
+    }
+
+    /// Forward the request to the given targets and relay the final response
+    ///
+    /// Returns once all branches have terminated. ACK requests are forwarded statelessly to the
+    /// first target. Requests without any target are rejected with 480.
+    pub async fn forward(&self, request: IncomingRequest, targets: Vec<Target>) -> Result<()> {
+        let routes = self.routes(&request);
+
+        self.forward_routed(request, routes, targets).await
+    }
+
+    /// Returns the Route headers of the request without the proxy's own
+    fn routes(&self, request: &IncomingRequest) -> Vec<Routing> {
+        let mut routes: Vec<Routing> = request.headers.get(Name::ROUTE).unwrap_or_default();
+
+        if let (Some(record_route), Some(route)) = (&self.record_route, routes.first()) {
+            if route.uri.uri.compare(&*record_route.uri.uri) {
+                routes.remove(0);
+            }
+        }
+
+        routes
+    }
+
+    async fn forward_routed(
+        &self,
+        mut request: IncomingRequest,
+        routes: Vec<Routing>,
+        targets: Vec<Target>,
+    ) -> Result<()> {
+        if request.line.method == Method::ACK {
+            return match targets.first() {
+                Some(target) => self.forward_ack(&request, &routes, target).await,
+                None => Ok(()),
+            };
+        }
+
+        if request.line.method == Method::CANCEL {
+            // CANCEL requests matching a forwarded INVITE are handled by the ProxyLayer
+            return self
+                .respond(&request, Code::CALL_OR_TRANSACTION_DOES_NOT_EXIST)
+                .await;
+        }
+
+        if let Ok(MaxForwards(0)) = request.headers.get_named() {
+            return self.respond(&request, Code::TOO_MANY_HOPS).await;
+        }
+
+        if targets.is_empty() {
+            return self.respond(&request, Code::TEMPORARILY_UNAVAILABLE).await;
+        }
+
+        if let Some((signer, attest)) = &self.identity_signer {
+            if request.line.method == Method::INVITE
+                && request.base_headers.to.tag.is_none()
+                && !request.headers.contains(&Name::IDENTITY)
+            {
+                if let Err(e) = sign_headers(&**signer, *attest, &mut request.headers).await {
+                    log::warn!("failed to sign forwarded INVITE, {e}");
+                }
+            }
+        }
+
+        Forwarding::new(self, request, routes, targets).run().await
+    }
+
+    async fn respond(&self, request: &IncomingRequest, code: Code) -> Result<()> {
+        let response = local_response(&self.endpoint, request, code);
+
+        if request.line.method == Method::INVITE {
+            self.endpoint
+                .create_server_inv_tsx(request)
+                .respond_failure(response)
+                .await
+        } else {
+            self.endpoint
+                .create_server_tsx(request)
+                .respond(response)
+                .await
+        }
+    }
+
+    /// Create the request forwarded to the target ([RFC3261 Section 16.6](https://datatracker.ietf.org/doc/html/rfc3261#section-16.6))
+    fn create_forwarded(
+        &self,
+        request: &IncomingRequest,
+        routes: &[Routing],
+        target: &Target,
+    ) -> Request {
+        let mut headers = Headers::with_capacity(request.headers.iter().count());
+
+        // Use the parsed Via headers, as they contain the received and rport parameters
+        headers.insert_named(&request.base_headers.via);
+
+        let max_forwards = request
+            .headers
+            .get_named::<MaxForwards>()
+            .map(|MaxForwards(max_forwards)| max_forwards.saturating_sub(1))
+            .unwrap_or(70);
+
+        headers.insert_named(&MaxForwards(max_forwards));
+
+        if let Some(record_route) = &self.record_route {
+            if request.base_headers.to.tag.is_none() {
+                headers.insert_type(Name::RECORD_ROUTE, record_route);
+            }
+        }
+
+        let routes: Vec<Routing> = target.route.iter().chain(routes).cloned().collect();
+
+        if !routes.is_empty() {
+            headers.insert_type(Name::ROUTE, &routes);
+        }
+
+        for (name, value) in request.headers.iter() {
+            if ![
+                Name::VIA,
+                Name::MAX_FORWARDS,
+                Name::ROUTE,
+                Name::CONTENT_LENGTH,
+            ]
+            .contains(name)
+            {
+                headers.insert(name.clone(), value.clone());
+            }
+        }
+
+        if let Some(policy) = &self.privacy {
+            apply_privacy(&**policy, &mut headers);
+        }
+
+        Request {
+            line: RequestLine {
+                method: request.line.method.clone(),
+                uri: target.uri.clone(),
+            },
+            headers,
+            body: request.body.clone(),
+        }
+    }
+
+    /// ACK requests for 2xx responses are end-to-end and forwarded without a transaction
+    async fn forward_ack(
+        &self,
+        request: &IncomingRequest,
+        routes: &[Routing],
+        target: &Target,
+    ) -> Result<()> {
+        let ack = self.create_forwarded(request, routes, target);
+
+        let mut target_tp_info = TargetTransportInfo::default();
+
+        if let Some(route) = target.route.first().or(routes.first()) {
+            target_tp_info.transport = Some(self.endpoint.select_transport(&*route.uri.uri).await?);
+        }
+
+        let mut ack = self
+            .endpoint
+            .create_outgoing(ack, &mut target_tp_info)
+            .await?;
+
+        // Create temporary transaction key to create Via, but never register it
+        // as ACK requests don't receive responses
+        let tsx_key = TsxKey::client(&Method::ACK);
+        let via = self
+            .endpoint
+            .create_via(&ack.parts.transport, &tsx_key, None);
+
+        ack.msg.headers.insert_named_front(&via);
+
+        self.endpoint.send_outgoing_request(&mut ack).await?;
+
+        Ok(())
+    }
+}
+
+/// Server transaction of the request being forwarded
+enum Upstream {
+    Invite(ServerInvTsx),
+    NonInvite(ServerTsx),
+    /// A 2xx response to the INVITE has been sent, additional 2xx responses of other branches
+    /// are still relayed. The transaction is kept to absorb retransmissions of the INVITE.
+    Accepted {
+        _transaction: Box<Accepted>,
+    },
+    /// A final response has been sent
+    Completed,
+}
+
+impl Upstream {
+    fn is_final(&self) -> bool {
+        matches!(self, Upstream::Accepted { .. } | Upstream::Completed)
+    }
+
+    async fn respond(&mut self, endpoint: &Endpoint, mut response: OutgoingResponse) -> Result<()> {
+        let kind = response.msg.line.code.kind();
+
+        match self {
+            Upstream::Invite(transaction) if kind == CodeKind::Provisional => {
+                transaction.respond_provisional(&mut response).await
+            }
+            Upstream::NonInvite(transaction) if kind == CodeKind::Provisional => {
+                transaction.respond_provisional(&mut response).await
+            }
+            Upstream::Accepted { .. } if kind == CodeKind::Success => {
+                endpoint.send_outgoing_response(&mut response).await?;
+                Ok(())
+            }
+            Upstream::Accepted { .. } | Upstream::Completed => Ok(()),
+            _ if kind == CodeKind::Provisional => Ok(()),
+            _ => match std::mem::replace(self, Upstream::Completed) {
+                Upstream::Invite(transaction) if kind == CodeKind::Success => {
+                    let transaction = transaction.respond_success(response).await?;
+                    *self = Upstream::Accepted {
+                        _transaction: Box::new(transaction),
+                    };
+                    Ok(())
+                }
+                Upstream::Invite(transaction) => transaction.respond_failure(response).await,
+                Upstream::NonInvite(transaction) => transaction.respond(response).await,
+                Upstream::Accepted { .. } | Upstream::Completed => unreachable!(),
+            },
+        }
+    }
+}
+
+/// State of a single forwarded request
+struct Forwarding<'p> {
+    proxy: &'p Proxy,
+
+    request: IncomingRequest,
+    routes: Vec<Routing>,
+    upstream: Upstream,
+
+    /// Targets which haven't been tried yet
+    pending: VecDeque<Target>,
+    branches: Vec<Branch>,
+    active: usize,
+    /// Index of the branch the next target waits for, with [`ForkMode::Sequential`]
+    current: Option<usize>,
+    /// When the current branch is cancelled in favor of the next target
+    branch_deadline: Option<Instant>,
+    /// Final responses of all branches, except 2xx
+    responses: Vec<BranchResponse>,
+
+    /// A 2xx or 6xx response has been received or the request was cancelled,
+    /// no more branches will be created
+    stopped: bool,
+    cancelled: bool,
+
+    events_tx: branch::Events,
+    events_rx: mpsc::UnboundedReceiver<(usize, BranchEvent)>,
+}
+
+impl<'p> Forwarding<'p> {
+    fn new(
+        proxy: &'p Proxy,
+        request: IncomingRequest,
+        routes: Vec<Routing>,
+        targets: Vec<Target>,
+    ) -> Self {
+        let upstream = if request.line.method == Method::INVITE {
+            Upstream::Invite(proxy.endpoint.create_server_inv_tsx(&request))
+        } else {
+            Upstream::NonInvite(proxy.endpoint.create_server_tsx(&request))
+        };
+
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+
+        Self {
+            proxy,
+            request,
+            routes,
+            upstream,
+            pending: targets.into(),
+            branches: vec![],
+            active: 0,
+            current: None,
+            branch_deadline: None,
+            responses: vec![],
+            stopped: false,
+            cancelled: false,
+            events_tx,
+            events_rx,
+        }
+    }
+
+    async fn run(mut self) -> Result<()> {
+        let endpoint = self.proxy.endpoint.clone();
+
+        let cancelled = Arc::new(Notify::new());
+
+        let _guard = if self.request.line.method == Method::INVITE {
+            let key = (
+                self.request.tsx_key.branch().clone(),
+                self.request.base_headers.cseq.cseq,
+            );
+
+            endpoint[self.proxy.layer]
+                .cancellables
+                .lock()
+                .insert(key.clone(), cancelled.clone());
+
+            // Stop the retransmissions of the INVITE, the final response may take a while
+            let trying = local_response(&endpoint, &self.request, Code::TRYING);
+            self.upstream.respond(&endpoint, trying).await?;
+
+            Some(CancellableGuard {
+                endpoint: endpoint.clone(),
+                layer: self.proxy.layer,
+                key,
+            })
+        } else {
+            None
+        };
+
+        self.start_branches();
+
+        while self.active > 0 {
+            let branch_deadline = async {
+                match self.branch_deadline {
+                    Some(deadline) => sleep_until(deadline).await,
+                    None => pending().await,
+                }
+            };
+
+            let (idx, event) = tokio::select! {
+                event = self.events_rx.recv() => event.expect("sender is owned by self"),
+                _ = cancelled.notified(), if !self.cancelled => {
+                    self.cancelled = true;
+                    self.stop();
+                    continue;
+                }
+                _ = branch_deadline => {
+                    self.next_target();
+                    continue;
+                }
+            };
+
+            match event {
+                BranchEvent::Response(response) => {
+                    self.handle_response(&endpoint, idx, response).await?;
+                }
+                BranchEvent::Failed(code) => {
+                    self.responses.push(BranchResponse {
+                        code,
+                        response: None,
+                    });
+                }
+                BranchEvent::Terminated => {
+                    self.active -= 1;
+
+                    if self.current == Some(idx) {
+                        self.current = None;
+                        self.branch_deadline = None;
+                    }
+
+                    self.start_branches();
+                }
+            }
+
+            // Non-INVITE requests don't receive additional final responses
+            if matches!(self.upstream, Upstream::Completed) {
+                return Ok(());
+            }
+        }
+
+        if !self.upstream.is_final() {
+            let fallback = if self.cancelled {
+                Code::REQUEST_TERMINATED
+            } else {
+                Code::REQUEST_TIMEOUT
+            };
+
+            let response = best_response(&endpoint, &self.request, &self.responses, fallback);
+            self.upstream.respond(&endpoint, response).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Create new branches for the pending targets, depending on the fork mode
+    fn start_branches(&mut self) {
+        if self.stopped || self.current.is_some() {
+            return;
+        }
+
+        while let Some(target) = self.pending.pop_front() {
+            let request = self
+                .proxy
+                .create_forwarded(&self.request, &self.routes, &target);
+
+            let branch = Branch::spawn(
+                self.branches.len(),
+                self.proxy.endpoint.clone(),
+                request,
+                self.proxy.timer_c,
+                self.events_tx.clone(),
+            );
+
+            self.branches.push(branch);
+            self.active += 1;
+
+            if self.proxy.fork_mode == ForkMode::Sequential {
+                self.current = Some(self.branches.len() - 1);
+
+                if self.request.line.method == Method::INVITE {
+                    self.branch_deadline = self
+                        .proxy
+                        .branch_timeout
+                        .map(|timeout| Instant::now() + timeout);
+                }
+
+                break;
+            }
+        }
+    }
+
+    /// The current branch timed out, cancel it and move on to the next target without waiting
+    /// for the branch to terminate
+    fn next_target(&mut self) {
+        self.branch_deadline = None;
+
+        if let Some(idx) = self.current.take() {
+            self.branches[idx].cancel(None);
+        }
+
+        self.start_branches();
+    }
+
+    /// Cancel all active branches and discard the pending targets
+    fn stop(&mut self) {
+        self.stopped = true;
+        self.pending.clear();
+
+        for branch in &mut self.branches {
+            branch.cancel(None);
+        }
+    }
+
+    async fn handle_response(
+        &mut self,
+        endpoint: &Endpoint,
+        idx: usize,
+        response: sip_core::transaction::TsxResponse,
+    ) -> Result<()> {
+        let code = response.line.code;
+
+        match code.kind() {
+            CodeKind::Provisional => {
+                // 100 Trying is hop-by-hop and never relayed
+                if code != Code::TRYING {
+                    let relayed = relay_response(endpoint, &self.request, &response);
+                    self.upstream.respond(endpoint, relayed).await?;
+                }
+            }
+            CodeKind::Success => {
+                // All 2xx responses are relayed, even after the first one
+                let relayed = relay_response(endpoint, &self.request, &response);
+                self.upstream.respond(endpoint, relayed).await?;
+
+                let reason = Reason::sip(code).with_text("Call completed elsewhere");
+                self.stop_others(idx, reason);
+            }
+            kind => {
+                if kind == CodeKind::GlobalFailure {
+                    self.stop_others(idx, Reason::sip(code));
+                }
+
+                self.responses.push(BranchResponse {
+                    code,
+                    response: Some(response),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Cancel all other branches, the reason is added to the CANCEL requests
+    fn stop_others(&mut self, idx: usize, reason: Reason) {
+        self.stopped = true;
+        self.pending.clear();
+
+        for (other_idx, branch) in self.branches.iter_mut().enumerate() {
+            if other_idx != idx {
+                branch.cancel(Some(reason.clone()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use super::*;
+    use sip_core::transport::udp::Udp;
+    use sip_types::header::typed::Via;
+    use sip_types::print::{AppendCtx, Print};
+    use sip_types::uri::sip::SipUri;
+    use std::future::Future;
+    use std::net::SocketAddr;
+    use std::pin::pin;
+    use tokio::net::UdpSocket;
+    use tokio::time::timeout;
+
+    /// Passes all requests not taken by the [`ProxyLayer`] to the test
+    struct Collect(mpsc::UnboundedSender<IncomingRequest>);
+
+    #[async_trait::async_trait]
+    impl Layer for Collect {
+        fn name(&self) -> &'static str {
+            "collect"
+        }
+
+        async fn receive(&self, _: &Endpoint, mut request: MayTake<'_, IncomingRequest>) {
+            let _ = self.0.send(request.inner().take().unwrap());
+        }
+    }
+
+    pub(super) struct Setup {
+        pub(super) endpoint: Endpoint,
+        pub(super) layer: LayerKey<ProxyLayer>,
+        pub(super) addr: SocketAddr,
+        requests: mpsc::UnboundedReceiver<IncomingRequest>,
+    }
+
+    impl Setup {
+        pub(super) async fn new() -> Self {
+            let (tx, requests) = mpsc::unbounded_channel();
+
+            let mut builder = Endpoint::builder();
+            let layer = builder.add_layer(ProxyLayer::default());
+            builder.add_layer(Collect(tx));
+
+            let transport = Udp::spawn(&mut builder, "127.0.0.1:0").await.unwrap();
+
+            Self {
+                endpoint: builder.build(),
+                layer,
+                addr: transport.bound(),
+                requests,
+            }
+        }
+
+        fn proxy(&self) -> Proxy {
+            Proxy::new(self.endpoint.clone(), self.layer)
+        }
+
+        /// Send the request from the peer to the proxy and wait for it to arrive
+        async fn incoming(&mut self, from: &Peer, request: &str) -> IncomingRequest {
+            from.send(request, self.addr).await;
+
+            timeout(Duration::from_secs(5), self.requests.recv())
+                .await
+                .expect("request not received")
+                .unwrap()
+        }
+    }
+
+    /// A UA on the other side of the proxy exchanging raw messages
+    pub(super) struct Peer {
+        socket: UdpSocket,
+    }
+
+    pub(super) struct Message {
+        text: String,
+        source: SocketAddr,
+    }
+
+    impl Peer {
+        pub(super) async fn bind() -> Self {
+            Self {
+                socket: UdpSocket::bind("127.0.0.1:0").await.unwrap(),
+            }
+        }
+
+        pub(super) fn addr(&self) -> SocketAddr {
+            self.socket.local_addr().unwrap()
+        }
+
+        pub(super) fn target(&self) -> Target {
+            Target::new(Box::new(self.uri()))
+        }
+
+        pub(super) fn uri(&self) -> SipUri {
+            format!("sip:bob@{}", self.addr()).parse().unwrap()
+        }
+
+        pub(super) async fn send(&self, message: &str, to: SocketAddr) {
+            self.socket.send_to(message.as_bytes(), to).await.unwrap();
+        }
+
+        async fn recv_timeout(&self, duration: Duration) -> Option<Message> {
+            let mut buf = vec![0; 65535];
+
+            let (len, source) = timeout(duration, self.socket.recv_from(&mut buf))
+                .await
+                .ok()?
+                .unwrap();
+
+            Some(Message {
+                text: String::from_utf8(buf[..len].to_vec()).unwrap(),
+                source,
+            })
+        }
+
+        /// Receive the next message whose first line starts with `prefix`, skipping all others
+        /// like retransmissions, provisional responses and ACKs
+        pub(super) async fn recv(&self, prefix: &str) -> Message {
+            loop {
+                let message = self
+                    .recv_timeout(Duration::from_secs(5))
+                    .await
+                    .unwrap_or_else(|| panic!("{prefix} not received"));
+
+                if message.line().starts_with(prefix) {
+                    return message;
+                }
+            }
+        }
+
+        /// Assert that no message whose first line starts with `prefix` is received in the
+        /// given duration
+        pub(super) async fn assert_not_received(&self, prefix: &str, duration: Duration) {
+            let deadline = Instant::now() + duration;
+
+            while let Some(message) = self
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .await
+            {
+                assert!(
+                    !message.line().starts_with(prefix),
+                    "unexpected {}",
+                    message.line()
+                );
+            }
+        }
+
+        /// Respond to the received request
+        pub(super) async fn respond(&self, request: &Message, code: u16, headers: &str) {
+            let response = request.response(code, self.addr().port(), headers);
+            self.send(&response, request.source).await;
+        }
+    }
+
+    impl Message {
+        pub(super) fn line(&self) -> &str {
+            self.text.lines().next().unwrap()
+        }
+
+        pub(super) fn headers(&self, name: &str) -> Vec<&str> {
+            self.text
+                .lines()
+                .skip(1)
+                .take_while(|line| !line.is_empty())
+                .filter_map(|line| {
+                    let (n, value) = line.split_once(':')?;
+                    n.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+                })
+                .collect()
+        }
+
+        pub(super) fn header(&self, name: &str) -> &str {
+            self.headers(name)
+                .first()
+                .unwrap_or_else(|| panic!("missing {name} header"))
+        }
+
+        /// Create a response to this request, adding the To tag if there is none
+        fn response(&self, code: u16, tag: u16, headers: &str) -> String {
+            let mut response = format!("SIP/2.0 {code} Test\r\n");
+
+            for via in self.headers("Via") {
+                response += &format!("Via: {via}\r\n");
+            }
+
+            let to = self.header("To");
+            let to = if to.contains("tag=") || code == 100 {
+                to.to_string()
+            } else {
+                format!("{to};tag={tag}")
+            };
+
+            response += &format!(
+                "From: {}\r\nTo: {to}\r\nCall-ID: {}\r\nCSeq: {}\r\n{headers}Content-Length: 0\r\n\r\n",
+                self.header("From"),
+                self.header("Call-ID"),
+                self.header("CSeq"),
+            );
+
+            response
+        }
+    }
+
+    /// Request sent by `peer` to the proxy
+    fn uac_request(method: &str, peer: &Peer, branch: &str, headers: &str) -> String {
+        let max_forwards = if headers.contains("Max-Forwards") {
+            ""
+        } else {
+            "Max-Forwards: 70\r\n"
+        };
+
+        format!(
+            "{method} sip:bob@example.org SIP/2.0\r\n\
+            Via: SIP/2.0/UDP {addr};branch=z9hG4bK{branch}\r\n\
+            {max_forwards}\
+            From: <sip:alice@example.org>;tag=1928301774\r\n\
+            To: <sip:bob@example.org>\r\n\
+            Call-ID: {branch}@example.org\r\n\
+            CSeq: 1 {method}\r\n\
+            Contact: <sip:alice@{addr}>\r\n\
+            {headers}\
+            Content-Length: 0\r\n\r\n",
+            addr = peer.addr(),
+        )
+    }
+
+    /// Drive the forwarding until the script completes, forwarding an INVITE continues after a
+    /// 2xx response to absorb additional responses
+    async fn run<S: Future>(forward: impl Future<Output = Result<()>>, script: S) -> S::Output {
+        let mut forward = pin!(forward);
+        let mut script = pin!(script);
+        let mut forwarding = true;
+
+        loop {
+            tokio::select! {
+                result = &mut forward, if forwarding => {
+                    result.unwrap();
+                    forwarding = false;
+                }
+                output = &mut script => return output,
+            }
+        }
+    }
+
+    fn print<P: Print>(values: &[P]) -> Vec<String> {
+        values
+            .iter()
+            .map(|value| value.default_print_ctx().to_string())
+            .collect()
+    }
+
+    fn routing(uri: &str) -> Routing {
+        Routing {
+            uri: NameAddr::uri(uri.parse::<SipUri>().unwrap()),
+            params: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn forwarded_request() {
+        let mut setup = Setup::new().await;
+        let uac = Peer::bind().await;
+
+        let own = format!("sip:{};lr", setup.addr);
+        let proxy = setup
+            .proxy()
+            .with_record_route(Box::new(own.parse::<SipUri>().unwrap()));
+
+        let target = Target {
+            uri: Box::new("sip:bob@192.0.2.2".parse::<SipUri>().unwrap()),
+            route: vec![routing("sip:edge.example.org;lr")],
+        };
+
+        let headers = format!(
+            "Max-Forwards: 10\r\n\
+            Route: <{own}>, <sip:next.example.org;lr>\r\n\
+            Subject: forwarded\r\n"
+        );
+        let request = setup
+            .incoming(&uac, &uac_request("INVITE", &uac, "initial", &headers))
+            .await;
+
+        // The proxy's own Route is removed
+        let routes = proxy.routes(&request);
+        assert_eq!(print(&routes), ["<sip:next.example.org;lr>"]);
+
+        let forwarded = proxy.create_forwarded(&request, &routes, &target);
+
+        assert_eq!(
+            forwarded.line.uri.default_print_ctx().to_string(),
+            "sip:bob@192.0.2.2"
+        );
+
+        // The Via of the proxy itself is added when sending the request
+        let via: Vec<Via> = forwarded.headers.get_named().unwrap();
+        assert_eq!(print(&via), print(&request.base_headers.via));
+
+        let MaxForwards(max_forwards) = forwarded.headers.get_named().unwrap();
+        assert_eq!(max_forwards, 9);
+
+        // The target's Route (e.g. Path) is placed in front of the remaining ones
+        let route: Vec<Routing> = forwarded.headers.get(Name::ROUTE).unwrap();
+        assert_eq!(
+            print(&route),
+            ["<sip:edge.example.org;lr>", "<sip:next.example.org;lr>"]
+        );
+
+        let record_route: Vec<Routing> = forwarded.headers.get(Name::RECORD_ROUTE).unwrap();
+        assert_eq!(print(&record_route), [format!("<{own}>")]);
+
+        let subject: Vec<_> = forwarded
+            .headers
+            .iter()
+            .filter(|(name, _)| **name == Name::SUBJECT)
+            .map(|(_, value)| value.as_str())
+            .collect();
+        assert_eq!(subject, ["forwarded"]);
+        assert!(!forwarded.headers.contains(&Name::CONTENT_LENGTH));
+
+        // Requests inside a dialog aren't record-routed, a missing Max-Forwards defaults to 70
+        let in_dialog = uac_request("BYE", &uac, "in-dialog", "")
+            .replace("Max-Forwards: 70\r\n", "")
+            .replace(
+                "To: <sip:bob@example.org>",
+                "To: <sip:bob@example.org>;tag=a6c85cf",
+            );
+        let request = setup.incoming(&uac, &in_dialog).await;
+
+        let forwarded = proxy.create_forwarded(&request, &[], &Target::new(Box::new(uac.uri())));
+
+        let MaxForwards(max_forwards) = forwarded.headers.get_named().unwrap();
+        assert_eq!(max_forwards, 70);
+        assert!(!forwarded.headers.contains(&Name::RECORD_ROUTE));
+        assert!(!forwarded.headers.contains(&Name::ROUTE));
+    }
+
+    #[tokio::test]
+    async fn timer_c_minimum() {
+        let setup = Setup::new().await;
+
+        let proxy = setup.proxy().with_timer_c(Duration::from_secs(30));
+        assert_eq!(proxy.timer_c, Duration::from_secs(180));
+
+        let proxy = setup.proxy().with_timer_c(Duration::from_secs(300));
+        assert_eq!(proxy.timer_c, Duration::from_secs(300));
+    }
+
+    #[tokio::test]
+    async fn too_many_hops() {
+        let mut setup = Setup::new().await;
+        let (uac, target) = (Peer::bind().await, Peer::bind().await);
+        let proxy = setup.proxy();
+
+        let request = setup
+            .incoming(
+                &uac,
+                &uac_request("OPTIONS", &uac, "hops", "Max-Forwards: 0\r\n"),
+            )
+            .await;
+
+        proxy.forward(request, vec![target.target()]).await.unwrap();
+
+        uac.recv("SIP/2.0 483").await;
+        target
+            .assert_not_received("OPTIONS", Duration::from_millis(100))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn parallel_fork() {
+        let mut setup = Setup::new().await;
+        let (uac, a, b) = (Peer::bind().await, Peer::bind().await, Peer::bind().await);
+        let proxy = setup.proxy();
+
+        let request = setup
+            .incoming(&uac, &uac_request("INVITE", &uac, "parallel", ""))
+            .await;
+
+        run(
+            proxy.forward(request, vec![a.target(), b.target()]),
+            async {
+                uac.recv("SIP/2.0 100").await;
+
+                // Both targets receive the request at once
+                let invite_a = a.recv("INVITE").await;
+                let invite_b = b.recv("INVITE").await;
+
+                a.respond(&invite_a, 486, "").await;
+                a.recv("ACK").await;
+                b.respond(&invite_b, 200, "").await;
+
+                uac.recv("SIP/2.0 200").await;
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn every_2xx_relayed() {
+        let mut setup = Setup::new().await;
+        let (uac, a, b) = (Peer::bind().await, Peer::bind().await, Peer::bind().await);
+        let proxy = setup.proxy();
+
+        let request = setup
+            .incoming(&uac, &uac_request("INVITE", &uac, "every2xx", ""))
+            .await;
+
+        run(
+            proxy.forward(request, vec![a.target(), b.target()]),
+            async {
+                let invite_a = a.recv("INVITE").await;
+                let invite_b = b.recv("INVITE").await;
+
+                a.respond(&invite_a, 200, "").await;
+                let first = uac.recv("SIP/2.0 200").await;
+
+                // The other branch hasn't sent a provisional response and cannot be cancelled yet
+                b.respond(&invite_b, 200, "").await;
+                let second = uac.recv("SIP/2.0 200").await;
+
+                assert_ne!(first.header("To"), second.header("To"));
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn global_failure_cancels_branches() {
+        let mut setup = Setup::new().await;
+        let (uac, a, b) = (Peer::bind().await, Peer::bind().await, Peer::bind().await);
+        let proxy = setup.proxy();
+
+        let request = setup
+            .incoming(&uac, &uac_request("INVITE", &uac, "global", ""))
+            .await;
+
+        run(
+            proxy.forward(request, vec![a.target(), b.target()]),
+            async {
+                let invite_a = a.recv("INVITE").await;
+                let invite_b = b.recv("INVITE").await;
+
+                a.respond(&invite_a, 180, "").await;
+                uac.recv("SIP/2.0 180").await;
+
+                b.respond(&invite_b, 603, "").await;
+
+                let cancel = a.recv("CANCEL").await;
+                assert!(cancel.header("Reason").contains("cause=603"));
+                a.respond(&cancel, 200, "").await;
+                a.respond(&invite_a, 487, "").await;
+
+                // The 6xx is preferred over the 487 of the cancelled branch
+                uac.recv("SIP/2.0 603").await;
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn sequential_fork() {
+        let mut setup = Setup::new().await;
+        let (uac, a, b) = (Peer::bind().await, Peer::bind().await, Peer::bind().await);
+        let proxy = setup.proxy().with_fork_mode(ForkMode::Sequential);
+
+        let request = setup
+            .incoming(&uac, &uac_request("OPTIONS", &uac, "sequential", ""))
+            .await;
+
+        run(
+            proxy.forward(request, vec![a.target(), b.target()]),
+            async {
+                let options_a = a.recv("OPTIONS").await;
+
+                // The next target is only tried once the previous one failed
+                b.assert_not_received("OPTIONS", Duration::from_millis(200))
+                    .await;
+                a.respond(&options_a, 404, "").await;
+
+                let options_b = b.recv("OPTIONS").await;
+                b.respond(&options_b, 200, "").await;
+
+                uac.recv("SIP/2.0 200").await;
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn sequential_fork_stops_on_6xx() {
+        let mut setup = Setup::new().await;
+        let (uac, a, b) = (Peer::bind().await, Peer::bind().await, Peer::bind().await);
+        let proxy = setup.proxy().with_fork_mode(ForkMode::Sequential);
+
+        let request = setup
+            .incoming(&uac, &uac_request("OPTIONS", &uac, "sequential6xx", ""))
+            .await;
+
+        run(
+            proxy.forward(request, vec![a.target(), b.target()]),
+            async {
+                let options_a = a.recv("OPTIONS").await;
+                a.respond(&options_a, 600, "").await;
+
+                uac.recv("SIP/2.0 600").await;
+                b.assert_not_received("OPTIONS", Duration::from_millis(200))
+                    .await;
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn branch_timeout() {
+        let mut setup = Setup::new().await;
+        let (uac, a, b) = (Peer::bind().await, Peer::bind().await, Peer::bind().await);
+        let proxy = setup
+            .proxy()
+            .with_fork_mode(ForkMode::Sequential)
+            .with_branch_timeout(Duration::from_millis(200));
+
+        let request = setup
+            .incoming(&uac, &uac_request("INVITE", &uac, "timeout", ""))
+            .await;
+
+        run(
+            proxy.forward(request, vec![a.target(), b.target()]),
+            async {
+                let invite_a = a.recv("INVITE").await;
+                a.respond(&invite_a, 180, "").await;
+
+                // Ringing doesn't reset the branch timeout
+                let cancel = a.recv("CANCEL").await;
+                let invite_b = b.recv("INVITE").await;
+
+                a.respond(&cancel, 200, "").await;
+                a.respond(&invite_a, 487, "").await;
+                b.respond(&invite_b, 200, "").await;
+
+                uac.recv("SIP/2.0 200").await;
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn cancel_propagated() {
+        let mut setup = Setup::new().await;
+        let (uac, a) = (Peer::bind().await, Peer::bind().await);
+        let proxy = setup.proxy();
+
+        let request = setup
+            .incoming(&uac, &uac_request("INVITE", &uac, "cancel", ""))
+            .await;
+
+        run(proxy.forward(request, vec![a.target()]), async {
+            let invite = a.recv("INVITE").await;
+            a.respond(&invite, 180, "").await;
+            uac.recv("SIP/2.0 180").await;
+
+            uac.send(&uac_request("CANCEL", &uac, "cancel", ""), setup.addr)
+                .await;
+            let response = uac.recv("SIP/2.0 200").await;
+            assert_eq!(response.header("CSeq"), "1 CANCEL");
+
+            let cancel = a.recv("CANCEL").await;
+            a.respond(&cancel, 200, "").await;
+            a.respond(&invite, 487, "").await;
+
+            uac.recv("SIP/2.0 487").await;
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn best_response_selected() {
+        let mut setup = Setup::new().await;
+        let (uac, a, b) = (Peer::bind().await, Peer::bind().await, Peer::bind().await);
+        let proxy = setup.proxy();
+
+        // Challenges of all branches are collected
+        let request = setup
+            .incoming(&uac, &uac_request("OPTIONS", &uac, "challenges", ""))
+            .await;
+
+        run(
+            proxy.forward(request, vec![a.target(), b.target()]),
+            async {
+                let options_a = a.recv("OPTIONS").await;
+                let options_b = b.recv("OPTIONS").await;
+
+                a.respond(&options_a, 404, "").await;
+                b.respond(
+                    &options_b,
+                    407,
+                    "Proxy-Authenticate: Digest realm=\"b.example.org\", nonce=\"b\"\r\n",
+                )
+                .await;
+
+                let response = uac.recv("SIP/2.0 407").await;
+                assert!(response
+                    .header("Proxy-Authenticate")
+                    .contains("b.example.org"));
+            },
+        )
+        .await;
+
+        // The proxy itself isn't unavailable
+        let request = setup
+            .incoming(&uac, &uac_request("OPTIONS", &uac, "unavailable", ""))
+            .await;
+
+        run(proxy.forward(request, vec![a.target()]), async {
+            let options = a.recv("OPTIONS").await;
+            a.respond(&options, 503, "").await;
+
+            uac.recv("SIP/2.0 500").await;
+        })
+        .await;
+    }
+}
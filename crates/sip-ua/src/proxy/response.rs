@@ -0,0 +1,164 @@
+use crate::util::random_string;
+use sip_core::transaction::TsxResponse;
+use sip_core::transport::OutgoingResponse;
+use sip_core::{Endpoint, IncomingRequest};
+use sip_types::header::typed::FromTo;
+use sip_types::{Code, CodeKind, Name};
+
+/// Headers which are taken from the request instead of the relayed response
+const REQUEST_HEADERS: [Name; 5] = [
+    Name::VIA,
+    Name::FROM,
+    Name::CALL_ID,
+    Name::CSEQ,
+    Name::CONTENT_LENGTH,
+];
+
+/// Create the response to the upstream request from a response received by a branch
+///
+/// The branch's Via header is removed, all other headers and the body are copied.
+pub(super) fn relay_response(
+    endpoint: &Endpoint,
+    request: &IncomingRequest,
+    response: &TsxResponse,
+) -> OutgoingResponse {
+    let mut relayed =
+        endpoint.create_response(request, response.line.code, response.line.reason.clone());
+
+    relayed.msg.headers.remove(&Name::TO);
+
+    for (name, value) in response.headers.iter() {
+        if !REQUEST_HEADERS.contains(name) {
+            relayed.msg.headers.insert(name.clone(), value.clone());
+        }
+    }
+
+    relayed.msg.body = response.body.clone();
+    relayed
+}
+
+/// Create a response generated by the proxy itself
+pub(super) fn local_response(
+    endpoint: &Endpoint,
+    request: &IncomingRequest,
+    code: Code,
+) -> OutgoingResponse {
+    let mut response = endpoint.create_response(request, code, None);
+
+    if code != Code::TRYING && request.base_headers.to.tag.is_none() {
+        let _ = response.msg.headers.edit(Name::TO, |to: &mut FromTo| {
+            to.tag = Some(random_string());
+        });
+    }
+
+    response
+}
+
+/// Final response of a branch, `response` is `None` if the branch failed without one
+pub(super) struct BranchResponse {
+    pub(super) code: Code,
+    pub(super) response: Option<TsxResponse>,
+}
+
+/// Order in which final responses are preferred ([RFC3261 Section 16.7](https://datatracker.ietf.org/doc/html/rfc3261#section-16.7))
+fn rank(code: Code) -> (u8, bool) {
+    let class = match code.kind() {
+        CodeKind::GlobalFailure => 0,
+        CodeKind::Redirection => 1,
+        CodeKind::RequestFailure => 2,
+        CodeKind::ServerFailure => 3,
+        _ => 4,
+    };
+
+    // Responses the UAC may be able to act upon are preferred over other 4xx responses
+    let actionable = matches!(code.into_u16(), 401 | 407 | 415 | 420 | 484);
+
+    (class, !actionable)
+}
+
+fn best(responses: &[BranchResponse]) -> Option<&BranchResponse> {
+    responses.iter().min_by_key(|response| rank(response.code))
+}
+
+/// Select the best final response out of all branch responses and create the upstream response
+///
+/// Challenges of all 401 and 407 responses are collected into the selected response and a 503
+/// response is replaced with 500, as the proxy itself isn't unavailable.
+pub(super) fn best_response(
+    endpoint: &Endpoint,
+    request: &IncomingRequest,
+    responses: &[BranchResponse],
+    fallback: Code,
+) -> OutgoingResponse {
+    let Some(best) = best(responses) else {
+        return local_response(endpoint, request, fallback);
+    };
+
+    if best.code == Code::SERVICE_UNAVAILABLE {
+        return local_response(endpoint, request, Code::SERVER_INTERNAL_ERROR);
+    }
+
+    let Some(response) = &best.response else {
+        return local_response(endpoint, request, best.code);
+    };
+
+    let mut relayed = relay_response(endpoint, request, response);
+
+    if matches!(best.code.into_u16(), 401 | 407) {
+        relayed.msg.headers.remove(&Name::WWW_AUTHENTICATE);
+        relayed.msg.headers.remove(&Name::PROXY_AUTHENTICATE);
+
+        let challenges = responses
+            .iter()
+            .filter(|response| matches!(response.code.into_u16(), 401 | 407))
+            .filter_map(|response| response.response.as_ref())
+            .flat_map(|response| response.headers.iter());
+
+        for (name, value) in challenges {
+            if *name == Name::WWW_AUTHENTICATE || *name == Name::PROXY_AUTHENTICATE {
+                relayed.msg.headers.insert(name.clone(), value.clone());
+            }
+        }
+    }
+
+    relayed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn best_code(codes: &[u16]) -> Option<u16> {
+        let responses: Vec<BranchResponse> = codes
+            .iter()
+            .map(|&code| BranchResponse {
+                code: Code::from(code),
+                response: None,
+            })
+            .collect();
+
+        best(&responses).map(|response| response.code.into_u16())
+    }
+
+    #[test]
+    fn response_ranking() {
+        assert_eq!(best_code(&[]), None);
+
+        // 6xx responses are preferred over all others
+        assert_eq!(best_code(&[302, 404, 503, 603]), Some(603));
+
+        // Otherwise the lowest response class
+        assert_eq!(best_code(&[503, 404, 302]), Some(302));
+        assert_eq!(best_code(&[503, 480, 500]), Some(480));
+        assert_eq!(best_code(&[503, 500]), Some(503));
+
+        // 4xx responses the UAC can act upon are preferred
+        for actionable in [401, 407, 415, 420, 484] {
+            assert_eq!(best_code(&[404, actionable, 486]), Some(actionable));
+        }
+
+        // The first one wins for equally ranked responses
+        assert_eq!(best_code(&[486, 404]), Some(486));
+        assert_eq!(best_code(&[407, 401]), Some(407));
+    }
+}
@@ -0,0 +1,311 @@
+//! REFER method and its implicit subscription ([RFC3515](https://datatracker.ietf.org/doc/html/rfc3515))
+//!
+//! The sender of a REFER request receives the progress of the referred request in NOTIFY requests
+//! containing `message/sipfrag` bodies using [`ReferSubscription`]. The recipient accepts the
+//! REFER and reports the progress using [`ReferNotifier`].
+
+use crate::dialog::{Dialog, Usage, UsageGuard};
+use crate::subscription::EventPackage;
+use bytes::Bytes;
+use sip_core::transaction::{ServerTsx, TsxResponse};
+use sip_core::{Endpoint, IncomingRequest, MayTake, Request, Result};
+use sip_types::header::typed::{CSeq, ContentType, Event, ReferTo, SubState, SubscriptionState};
+use sip_types::{Code, CodeKind, Method};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Content type of NOTIFY bodies of the refer event package
+const SIPFRAG: &str = "message/sipfrag;version=2.0";
+
+/// Duration of the implicit subscription in seconds
+const SUBSCRIPTION_EXPIRES: u32 = 60;
+
+/// The `refer` event package, the implicit subscription itself is handled by
+/// [`ReferSubscription`] and [`ReferNotifier`]
+pub struct Refer;
+
+impl EventPackage for Refer {
+    const EVENT: &'static str = "refer";
+    const CONTENT_TYPE: &'static str = SIPFRAG;
+    const DEFAULT_EXPIRES: u32 = SUBSCRIPTION_EXPIRES;
+}
+
+/// Create a REFER request inside the dialog, asking the peer to contact `refer_to`
+pub fn create_refer(dialog: &Dialog, refer_to: &ReferTo) -> Request {
+    let mut request = dialog.create_request(Method::REFER);
+
+    request.headers.insert_named(refer_to);
+    request.headers.insert_named(&dialog.local_contact);
+
+    request
+}
+
+/// Create a `message/sipfrag` body containing the status line of a response with `code`
+pub fn sipfrag(code: Code) -> Bytes {
+    match code.text() {
+        Some(text) => format!("SIP/2.0 {} {}\r\n", code.into_u16(), text).into(),
+        None => format!("SIP/2.0 {}\r\n", code.into_u16()).into(),
+    }
+}
+
+/// Parse the code of the status line inside a `message/sipfrag` body
+pub fn parse_sipfrag(body: &[u8]) -> Option<Code> {
+    let body = std::str::from_utf8(body).ok()?;
+    let code = body.strip_prefix("SIP/2.0 ")?.get(..3)?;
+
+    code.parse().ok()
+}
+
+/// Progress of the referred request, reported by the REFER recipient
+#[derive(Debug, Clone, Copy)]
+pub struct ReferProgress {
+    /// Status code of the latest response the REFER recipient received to the referred request
+    pub code: Code,
+
+    /// The recipient terminated the subscription, no more progress will be reported
+    pub terminated: bool,
+}
+
+/// Usage receiving the NOTIFY requests of a single refer subscription
+struct ReferUsage {
+    /// CSeq of the REFER request which is used as the `id` of the subscription
+    id: u32,
+    notify_sender: mpsc::Sender<IncomingRequest>,
+}
+
+#[async_trait::async_trait]
+impl Usage for ReferUsage {
+    fn name(&self) -> &'static str {
+        "refer-usage"
+    }
+
+    async fn receive(&self, _: &Endpoint, mut request: MayTake<'_, IncomingRequest>) {
+        if request.line.method != Method::NOTIFY {
+            return;
+        }
+
+        let Ok(event) = request.headers.get_named::<Event>() else {
+            return;
+        };
+
+        if !event.event.eq_ignore_ascii_case(Refer::EVENT) {
+            return;
+        }
+
+        if let Some(id) = &event.id {
+            if id.parse() != Ok(self.id) {
+                return;
+            }
+        }
+
+        let notify = request.inner().take().unwrap();
+
+        if let Err(mpsc::error::SendError(notify)) = self.notify_sender.send(notify).await {
+            *request.inner() = Some(notify);
+        }
+    }
+}
+
+/// Implicit subscription created by sending a REFER request
+pub struct ReferSubscription {
+    endpoint: Endpoint,
+    notify_rx: mpsc::Receiver<IncomingRequest>,
+    terminated: bool,
+    _usage_guard: UsageGuard,
+}
+
+impl ReferSubscription {
+    /// Send a REFER request created using [`create_refer`] and return the final response
+    ///
+    /// The subscription only receives progress reports if the response is successful.
+    pub async fn send(dialog: &Dialog, request: Request) -> Result<(Self, TsxResponse)> {
+        let id = request.headers.get_named::<CSeq>()?.cseq;
+
+        // Register the usage before sending the request, as the first NOTIFY may arrive
+        // before the response
+        let (notify_sender, notify_rx) = mpsc::channel(4);
+        let usage_guard = dialog.register_usage(ReferUsage { id, notify_sender });
+
+        let mut target_tp_info = dialog.target_tp_info.lock().await;
+
+        let mut transaction = dialog
+            .endpoint
+            .send_request(request, &mut target_tp_info)
+            .await?;
+
+        drop(target_tp_info);
+
+        let response = transaction.receive_final().await?;
+
+        let subscription = Self {
+            endpoint: dialog.endpoint.clone(),
+            notify_rx,
+            terminated: response.line.code.kind() != CodeKind::Success,
+            _usage_guard: usage_guard,
+        };
+
+        Ok((subscription, response))
+    }
+
+    /// Receive the next progress report, responding to the NOTIFY request
+    ///
+    /// Returns `None` once the subscription is terminated.
+    pub async fn receive(&mut self) -> Result<Option<ReferProgress>> {
+        while !self.terminated {
+            let Some(notify) = self.notify_rx.recv().await else {
+                return Ok(None);
+            };
+
+            let transaction = self.endpoint.create_server_tsx(&notify);
+
+            let Some(code) = parse_sipfrag(&notify.body) else {
+                let response = self
+                    .endpoint
+                    .create_response(&notify, Code::BAD_REQUEST, None);
+                transaction.respond(response).await?;
+                continue;
+            };
+
+            self.terminated = notify
+                .headers
+                .get_named::<SubscriptionState>()
+                .map(|state| state.state == SubState::Terminated)
+                .unwrap_or_default();
+
+            let response = self.endpoint.create_response(&notify, Code::OK, None);
+            transaction.respond(response).await?;
+
+            return Ok(Some(ReferProgress {
+                code,
+                terminated: self.terminated,
+            }));
+        }
+
+        Ok(None)
+    }
+}
+
+/// Notifier of the implicit subscription created by an accepted REFER request
+pub struct ReferNotifier {
+    dialog: Arc<Dialog>,
+    id: u32,
+    refer_to: ReferTo,
+    terminated: bool,
+}
+
+impl ReferNotifier {
+    /// Accept the REFER request with a 202 response and send the initial `100 Trying` NOTIFY
+    ///
+    /// Responds with 400 if the request doesn't contain a valid `Refer-To` header.
+    pub async fn accept(
+        dialog: Arc<Dialog>,
+        refer: &IncomingRequest,
+        transaction: ServerTsx,
+    ) -> Result<Self> {
+        let refer_to = match refer.headers.get_named::<ReferTo>() {
+            Ok(refer_to) => refer_to,
+            Err(e) => {
+                let response = dialog.create_response(refer, Code::BAD_REQUEST, None)?;
+                transaction.respond(response).await?;
+                return Err(e.into());
+            }
+        };
+
+        let response = dialog.create_response(refer, Code::ACCEPTED, None)?;
+        transaction.respond(response).await?;
+
+        let mut notifier = Self {
+            dialog,
+            id: refer.base_headers.cseq.cseq,
+            refer_to,
+            terminated: false,
+        };
+
+        notifier.notify(Code::TRYING).await?;
+
+        Ok(notifier)
+    }
+
+    /// The URI the recipient was asked to contact
+    pub fn refer_to(&self) -> &ReferTo {
+        &self.refer_to
+    }
+
+    /// Report the status code of the latest response received to the referred request
+    ///
+    /// Final status codes terminate the subscription, reporting after that is a no-op.
+    pub async fn notify(&mut self, code: Code) -> Result<()> {
+        if self.terminated {
+            return Ok(());
+        }
+
+        let state = if code.kind() == CodeKind::Provisional {
+            SubscriptionState::active(SUBSCRIPTION_EXPIRES)
+        } else {
+            self.terminated = true;
+            SubscriptionState::terminated("noresource")
+        };
+
+        let mut request = self.dialog.create_request(Method::NOTIFY);
+        request
+            .headers
+            .insert_named(&Event::new(Refer::EVENT).with_id(self.id.to_string()));
+        request.headers.insert_named(&state);
+        request.headers.insert_named(&self.dialog.local_contact);
+        request.headers.insert_named(&ContentType(SIPFRAG.into()));
+        request.body = sipfrag(code);
+
+        let mut target_tp_info = self.dialog.target_tp_info.lock().await;
+
+        let mut transaction = self
+            .dialog
+            .endpoint
+            .send_request(request, &mut target_tp_info)
+            .await?;
+
+        drop(target_tp_info);
+
+        let response = transaction.receive_final().await?;
+
+        if response.line.code == Code::CALL_OR_TRANSACTION_DOES_NOT_EXIST {
+            // The subscriber is no longer interested in the progress
+            self.terminated = true;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sipfrag_round_trip() {
+        assert_eq!(&sipfrag(Code::TRYING)[..], b"SIP/2.0 100 Trying\r\n");
+        assert_eq!(&sipfrag(Code::from(299))[..], b"SIP/2.0 299\r\n");
+
+        for code in [
+            Code::TRYING,
+            Code::RINGING,
+            Code::OK,
+            Code::BUSY_HERE,
+            Code::from(299),
+        ] {
+            assert_eq!(parse_sipfrag(&sipfrag(code)), Some(code));
+        }
+    }
+
+    #[test]
+    fn sipfrag_parse() {
+        assert_eq!(
+            parse_sipfrag(b"SIP/2.0 603 Declined\r\nContent-Length: 0\r\n\r\n"),
+            Some(Code::DECLINE)
+        );
+        assert_eq!(parse_sipfrag(b"SIP/2.0 18"), None);
+        assert_eq!(
+            parse_sipfrag(b"INVITE sip:bob@example.org SIP/2.0\r\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use super::Registration;
+use rand::Rng;
+use sip_auth::RequestParts;
+use sip_core::transaction::TsxResponse;
+use sip_core::transport::resolver::ServerEntry;
+use sip_core::{Endpoint, Error};
+use sip_types::header::typed::RetryAfter;
+use sip_types::{Code, CodeKind};
+use std::time::Duration;
+
+/// Base and maximum time to wait between failed registration attempts
+/// (RFC5626 Section 4.5)
+const BACKOFF_BASE: u64 = 30;
+const BACKOFF_MAX: u64 = 1800;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegisterError {
+    #[error(transparent)]
+    Core(#[from] Error),
+    #[error("registrar rejected the registration with {0:?}")]
+    Rejected(Code),
+    #[error("failed to authenticate: {0}")]
+    Auth(#[from] sip_auth::Error),
+    #[error("none of the resolved registrar servers responded")]
+    Unreachable,
+}
+
+impl Registration {
+    /// Send a REGISTER request and handle its response
+    ///
+    /// The registrar's URI is resolved into a list of servers which are tried in order, starting
+    /// with the server last used successfully. A server is skipped when it can't be reached,
+    /// doesn't respond or responds with 5xx. A `423 Interval Too Brief` response is handled by
+    /// retrying with the `Min-Expires` of the response. A `401` or `407` response is answered
+    /// once using the credentials set with [`Registration::with_credentials`]. Other failure
+    /// responses, or a challenge that can't be answered, are returned as
+    /// [`RegisterError::Rejected`].
+    pub async fn register(&mut self, endpoint: &Endpoint) -> Result<(), RegisterError> {
+        let servers = endpoint.resolve(&*self.registrar).await?;
+
+        for i in 0..servers.len() {
+            let idx = (self.server_idx + i) % servers.len();
+
+            match self.register_with_server(endpoint, &servers[idx]).await {
+                Ok(true) => {
+                    self.server_idx = idx;
+                    self.failures = 0;
+                    self.retry_after = None;
+                    return Ok(());
+                }
+                Ok(false) => {
+                    log::debug!("failing over from registrar {}", servers[idx].address);
+                    self.target_tp_info.transport = None;
+                }
+                Err(e) => {
+                    self.failures += 1;
+                    return Err(e);
+                }
+            }
+        }
+
+        self.failures += 1;
+
+        Err(RegisterError::Unreachable)
+    }
+
+    /// Returns `false` if the next server should be tried
+    async fn register_with_server(
+        &mut self,
+        endpoint: &Endpoint,
+        server: &ServerEntry,
+    ) -> Result<bool, RegisterError> {
+        match endpoint
+            .select_transport_for_server(&*self.registrar, server)
+            .await
+        {
+            Ok(transport) => self.target_tp_info.transport = Some(transport),
+            Err(_) => return Ok(false),
+        }
+
+        // Only answer one challenge per server, to avoid looping with invalid credentials
+        let mut authenticated = false;
+
+        loop {
+            let request = self.create_register(false);
+
+            let mut transaction = match endpoint
+                .send_request(request, &mut self.target_tp_info)
+                .await
+            {
+                Ok(transaction) => transaction,
+                Err(Error::RequestTimedOut | Error::Io(_)) => return Ok(false),
+                Err(e) => return Err(e.into()),
+            };
+
+            let response = match transaction.receive_final().await {
+                Ok(response) => response,
+                Err(Error::RequestTimedOut | Error::Io(_)) => return Ok(false),
+                Err(e) => return Err(e.into()),
+            };
+
+            match response.line.code.kind() {
+                CodeKind::Success => {
+                    self.receive_success_response(response);
+                    return Ok(true);
+                }
+                CodeKind::ServerFailure => {
+                    self.retry_after = response
+                        .headers
+                        .get_named::<RetryAfter>()
+                        .ok()
+                        .map(|retry_after| Duration::from_secs(retry_after.value.into()));
+
+                    return Ok(false);
+                }
+                _ => {
+                    let code = response.line.code;
+
+                    let challenged = matches!(
+                        code,
+                        Code::UNAUTHORIZED | Code::PROXY_AUTHENTICATION_REQUIRED
+                    );
+
+                    let credentials = self.credentials.as_ref().filter(|_| !authenticated);
+
+                    if let (true, Some(credentials)) = (challenged, credentials) {
+                        let request = &transaction.request().msg;
+
+                        self.auth_session.handle_authenticate(
+                            &response.headers,
+                            credentials,
+                            RequestParts {
+                                line: &request.line,
+                                headers: &request.headers,
+                                body: &request.body,
+                            },
+                        )?;
+
+                        authenticated = true;
+                        continue;
+                    }
+
+                    if code == Code::INTERVAL_TOO_BRIEF && self.receive_error_response(response) {
+                        continue;
+                    }
+
+                    return Err(RegisterError::Rejected(code));
+                }
+            }
+        }
+    }
+
+    /// Keep the binding registered until the registrar rejects the registration or
+    /// authentication fails
+    ///
+    /// Refreshes the binding before it expires. Failed attempts are retried after the
+    /// `Retry-After` of the registrar's response or an exponentially growing, randomized backoff.
+    pub async fn run(&mut self, endpoint: &Endpoint) -> Result<(), RegisterError> {
+        loop {
+            match self.register(endpoint).await {
+                Ok(()) => self.wait_for_expiry().await,
+                Err(e @ (RegisterError::Rejected(_) | RegisterError::Auth(_))) => return Err(e),
+                Err(e) => {
+                    let backoff = self.backoff();
+
+                    log::warn!("registration failed, retrying in {backoff:?}, {e}");
+
+                    tokio::time::sleep(backoff).await;
+                }
+            }
+        }
+    }
+
+    /// Remove the binding from the registrar
+    pub async fn unregister(&mut self, endpoint: &Endpoint) -> Result<TsxResponse, RegisterError> {
+        let request = self.create_register(true);
+
+        let mut transaction = endpoint
+            .send_request(request, &mut self.target_tp_info)
+            .await?;
+
+        Ok(transaction.receive_final().await?)
+    }
+
+    /// Time to wait until retrying after consecutive failures
+    ///
+    /// Uses the registrar's `Retry-After` if present, else a random duration between 50% and
+    /// 100% of `min(1800, 30 * 2^failures)` seconds.
+    pub fn backoff(&self) -> Duration {
+        if let Some(retry_after) = self.retry_after {
+            return retry_after;
+        }
+
+        let exp = self.failures.saturating_sub(1).min(6);
+        let max = BACKOFF_MAX.min(BACKOFF_BASE << exp);
+
+        Duration::from_secs(rand::thread_rng().gen_range(max / 2..=max))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sip_auth::digest::DigestCredentials;
+    use sip_auth::CredentialStore;
+    use sip_types::uri::sip::SipUri;
+    use sip_types::uri::NameAddr;
+    use sip_types::{Headers, Name};
+
+    fn registration() -> Registration {
+        let id: SipUri = "sip:alice@example.org".parse().unwrap();
+        let contact: SipUri = "sip:alice@192.0.2.1:5060".parse().unwrap();
+        let registrar: SipUri = "sip:example.org".parse().unwrap();
+
+        Registration::new(
+            NameAddr::uri(id),
+            NameAddr::uri(contact),
+            Box::new(registrar),
+            Duration::from_secs(3600),
+        )
+    }
+
+    #[tokio::test]
+    async fn backoff() {
+        let mut registration = registration();
+
+        for (failures, max) in [(1, 30), (2, 60), (3, 120), (6, 960), (7, 1800), (50, 1800)] {
+            registration.failures = failures;
+
+            for _ in 0..20 {
+                let backoff = registration.backoff().as_secs();
+                assert!((max / 2..=max).contains(&backoff), "{failures}: {backoff}");
+            }
+        }
+
+        registration.retry_after = Some(Duration::from_secs(5));
+        assert_eq!(registration.backoff(), Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn authorize_after_challenge() {
+        let mut credentials = CredentialStore::new();
+        credentials.add_for_realm("example.org", DigestCredentials::new("alice", "secret"));
+
+        let mut registration = registration().with_credentials(credentials);
+
+        let request = registration.create_register(false);
+        assert!(!request.headers.contains(&Name::AUTHORIZATION));
+
+        let mut challenge = Headers::new();
+        challenge.insert(
+            Name::WWW_AUTHENTICATE,
+            "Digest realm=\"example.org\", nonce=\"YWmh5GFpoLjiTDCA1hTSSygkgdj99aHE\"",
+        );
+
+        registration
+            .auth_session
+            .handle_authenticate(
+                &challenge,
+                registration.credentials.as_ref().unwrap(),
+                RequestParts {
+                    line: &request.line,
+                    headers: &request.headers,
+                    body: &request.body,
+                },
+            )
+            .unwrap();
+
+        // Every following REGISTER request carries the credentials
+        for _ in 0..2 {
+            let request = registration.create_register(false);
+            let authorization: Vec<_> = request
+                .headers
+                .iter()
+                .filter(|(name, _)| **name == Name::AUTHORIZATION)
+                .collect();
+
+            assert_eq!(authorization.len(), 1);
+            assert!(authorization[0].1.contains("username=\"alice\""));
+            assert!(authorization[0].1.contains("uri=\"sip:example.org\""));
+        }
+    }
+}
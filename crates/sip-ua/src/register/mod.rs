@@ -1,12 +1,22 @@
 use crate::util::{random_sequence_number, random_string};
+use bytesstr::BytesStr;
+use rand::Rng;
+use sip_auth::{CredentialStore, UacAuthSession};
 use sip_core::transaction::TsxResponse;
+use sip_core::transport::TargetTransportInfo;
 use sip_core::Request;
-use sip_types::header::typed::{CSeq, CallID, Contact, Expires, FromTo, MinExpires};
+use sip_types::header::typed::{
+    CSeq, CallID, Contact, Expires, FlowTimer, FromTo, MinExpires, Require, Routing, Supported,
+};
 use sip_types::uri::{NameAddr, Uri};
-use sip_types::{CodeKind, Method, Name};
+use sip_types::{CodeKind, Headers, Method, Name};
 use std::time::Duration;
 use tokio::time::{interval_at, Instant, Interval};
 
+mod client;
+
+pub use client::RegisterError;
+
 pub struct Registration {
     registrar: Box<dyn Uri>,
 
@@ -22,6 +32,33 @@ pub struct Registration {
 
     /// Re-registration interval, is set to `expires - 10`
     register_interval: Interval,
+
+    /// Request an outbound flow (RFC5626)
+    outbound: bool,
+    /// The registrar confirmed the outbound flow using `Require: outbound`
+    outbound_established: bool,
+    /// Flow-Timer value received from the registrar
+    flow_timer: Option<Duration>,
+
+    /// Path headers echoed by the registrar (RFC3327)
+    path: Vec<Routing>,
+
+    /// Service-Route headers received from the registrar (RFC3608)
+    service_route: Vec<Routing>,
+
+    /// Transport state used by [`Self::register`]
+    target_tp_info: TargetTransportInfo,
+    /// Index of the resolved registrar server last used by [`Self::register`]
+    server_idx: usize,
+    /// Consecutive failed registration attempts, used to calculate the backoff
+    failures: u32,
+    /// Retry-After value of the last failure response
+    retry_after: Option<Duration>,
+
+    /// Credentials used to answer 401 & 407 challenges in [`Self::register`]
+    credentials: Option<CredentialStore>,
+    /// Authorization headers added to every REGISTER request
+    auth_session: UacAuthSession,
 }
 
 impl Registration {
@@ -36,13 +73,50 @@ impl Registration {
 
             expires: expiry,
             register_interval: create_reg_interval(expiry),
+
+            outbound: false,
+            outbound_established: false,
+            flow_timer: None,
+
+            path: vec![],
+            service_route: vec![],
+
+            target_tp_info: TargetTransportInfo::default(),
+            server_idx: 0,
+            failures: 0,
+            retry_after: None,
+
+            credentials: None,
+            auth_session: UacAuthSession::default(),
         }
     }
 
+    /// Answer authentication challenges of the registrar (and proxies) using the given
+    /// credentials
+    pub fn with_credentials(mut self, credentials: CredentialStore) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Register an outbound flow (RFC5626) using the given instance id (e.g. `urn:uuid:...`)
+    /// and registration id.
+    ///
+    /// To register multiple flows (e.g. to different edge proxies) create one registration per
+    /// `reg_id`, all using the same instance id. Once established, the flow must be kept alive
+    /// using [`Endpoint::keep_alive`](sip_core::Endpoint::keep_alive) every
+    /// [`Self::keep_alive_interval`].
+    pub fn with_outbound(mut self, instance: &str, reg_id: u32) -> Self {
+        self.contact = self.contact.with_instance(instance).with_reg_id(reg_id);
+        self.outbound = true;
+        self
+    }
+
     /// Create a new REGISTER request.
     ///
     /// `remove_binding` must be `false` to create a new binding on the registrar.
     /// If the value is `true` the REGISTER request will remove any active bindings.
+    ///
+    /// The request is authorized with the credentials of previously answered challenges.
     pub fn create_register(&mut self, remove_binding: bool) -> Request {
         let mut request = Request::new(Method::REGISTER, self.registrar.clone());
 
@@ -63,6 +137,18 @@ impl Registration {
 
         request.headers.insert_named(&expires);
         request.headers.insert_named(&self.contact);
+        request
+            .headers
+            .insert_named(&Supported(BytesStr::from_static("path")));
+
+        if self.outbound {
+            request
+                .headers
+                .insert_named(&Supported(BytesStr::from_static("outbound")));
+        }
+
+        self.auth_session
+            .authorize_request(&request.line, &mut request.headers, &request.body);
 
         request
     }
@@ -74,7 +160,17 @@ impl Registration {
     pub fn receive_success_response(&mut self, response: TsxResponse) {
         assert_eq!(response.line.code.kind(), CodeKind::Success);
 
-        if let Ok(expires) = response.headers.get_named::<Expires>() {
+        // The expires parameter of the own binding takes precedence over the Expires header
+        let binding_expires = response
+            .headers
+            .get_named::<Vec<Contact>>()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|contact| contact.uri.uri.compare(&*self.contact.uri.uri))
+            .and_then(|contact| contact.params.get_val("expires")?.parse().ok())
+            .map(Expires);
+
+        if let Some(expires) = binding_expires.or(response.headers.get_named::<Expires>().ok()) {
             let expires = Duration::from_secs(expires.0 as _);
 
             if self.expires != expires {
@@ -83,11 +179,71 @@ impl Registration {
             }
         }
 
+        if self.outbound {
+            self.outbound_established = response
+                .headers
+                .get_named::<Vec<Require>>()
+                .unwrap_or_default()
+                .iter()
+                .any(|ext| ext.0 == "outbound");
+
+            self.flow_timer = response
+                .headers
+                .get_named::<FlowTimer>()
+                .ok()
+                .map(|flow_timer| Duration::from_secs(flow_timer.0 as _));
+        }
+
+        self.path = response.headers.get(Name::PATH).unwrap_or_default();
+        self.service_route = response
+            .headers
+            .get(Name::SERVICE_ROUTE)
+            .unwrap_or_default();
+
         if self.to.tag.is_none() {
             self.to.tag = response.base_headers.to.tag;
         }
     }
 
+    /// Returns if the registrar supports outbound and established a flow for this registration
+    pub fn outbound_established(&self) -> bool {
+        self.outbound_established
+    }
+
+    /// Returns the Flow-Timer value received from the registrar
+    pub fn flow_timer(&self) -> Option<Duration> {
+        self.flow_timer
+    }
+
+    /// Returns the proxies between this UA and the registrar which requested to stay on the
+    /// path of requests targeting this registration, as echoed by the registrar
+    pub fn path(&self) -> &[Routing] {
+        &self.path
+    }
+
+    /// Returns the route set which must be preloaded into requests sent outside of a dialog
+    /// using this registration (RFC3608)
+    ///
+    /// It is replaced with every successful registration and empty if the registrar did not
+    /// send any `Service-Route` headers. See [`ClientDialogBuilder::route_set`](crate::dialog::ClientDialogBuilder::route_set).
+    pub fn service_route(&self) -> &[Routing] {
+        &self.service_route
+    }
+
+    /// Returns a randomized interval to send keep-alives at to keep an outbound flow alive
+    ///
+    /// Uses 80-100% of the Flow-Timer if the registrar sent one, else defaults to 95-120 seconds
+    /// for reliable transports and 24-29 seconds for unreliable transports (RFC5626 Section 4.4.1).
+    pub fn keep_alive_interval(&self, reliable: bool) -> Duration {
+        let mut rng = rand::thread_rng();
+
+        match self.flow_timer {
+            Some(flow_timer) => flow_timer.mul_f64(rng.gen_range(0.8..=1.0)),
+            None if reliable => Duration::from_secs(rng.gen_range(95..=120)),
+            None => Duration::from_secs(rng.gen_range(24..=29)),
+        }
+    }
+
     /// Handle an error response received from a registrar
     ///
     /// Returns whether or not to retry the registration
@@ -112,6 +268,31 @@ impl Registration {
     }
 }
 
+/// Insert a Path header for the proxy `uri` into a REGISTER request which is forwarded to the
+/// registrar, so that requests targeting the registered contact are routed through the proxy.
+///
+/// The header is inserted on top of any Path headers added by previous proxies. The `uri` should
+/// contain the `lr` parameter.
+pub fn insert_path(request: &mut Headers, uri: NameAddr) {
+    let path = Routing {
+        uri,
+        params: Default::default(),
+    };
+
+    request.insert_type_front(Name::PATH, &path);
+}
+
+/// Copy the Path headers of a REGISTER request into the success response of a registrar
+///
+/// Returns the path which must be stored with the binding and used as
+/// preloaded route set for requests targeting the registered contact.
+pub fn echo_path(request: &Headers, response: &mut Headers) -> Vec<Routing> {
+    // Fails only if the request contains no Path headers, in which case there's nothing to echo
+    let _ = request.clone_into(response, Name::PATH);
+
+    request.get(Name::PATH).unwrap_or_default()
+}
+
 fn create_reg_interval(period: Duration) -> Interval {
     // Avoid underflow and zero duration intervals by limiting `period` to be at least 20s
     let period = period.max(Duration::from_secs(20));
@@ -122,3 +303,169 @@ fn create_reg_interval(period: Duration) -> Interval {
     register_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
     register_interval
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dialog::{ClientDialogBuilder, DialogLayer};
+    use bytes::Bytes;
+    use sip_core::transport::udp::Udp;
+    use sip_core::transport::MessageTpInfo;
+    use sip_core::{BaseHeaders, Endpoint};
+    use sip_types::header::typed::Via;
+    use sip_types::msg::StatusLine;
+    use sip_types::print::AppendCtx;
+    use sip_types::uri::sip::SipUri;
+    use sip_types::Code;
+    use std::net::SocketAddr;
+    use std::time::SystemTime;
+
+    fn registration() -> Registration {
+        let id: SipUri = "sip:alice@example.org".parse().unwrap();
+        let contact: SipUri = "sip:alice@192.0.2.1:5060".parse().unwrap();
+        let registrar: SipUri = "sip:example.org".parse().unwrap();
+
+        Registration::new(
+            NameAddr::uri(id),
+            NameAddr::uri(contact),
+            Box::new(registrar),
+            Duration::from_secs(3600),
+        )
+    }
+
+    fn supported(request: &Request) -> Vec<Supported> {
+        request.headers.get_named().unwrap()
+    }
+
+    #[tokio::test]
+    async fn register_without_outbound() {
+        let mut registration = registration();
+
+        let request = registration.create_register(false);
+
+        let contact: Contact = request.headers.get_named().unwrap();
+        assert_eq!(contact.instance(), None);
+        assert_eq!(contact.reg_id(), None);
+
+        let supported = supported(&request);
+        assert!(supported.iter().any(|ext| ext.0 == "path"));
+        assert!(!supported.iter().any(|ext| ext.0 == "outbound"));
+    }
+
+    #[tokio::test]
+    async fn register_with_outbound() {
+        let mut registration =
+            registration().with_outbound("urn:uuid:00000000-0000-1000-8000-000A95A0E128", 1);
+
+        let request = registration.create_register(false);
+
+        let contact: Contact = request.headers.get_named().unwrap();
+        assert_eq!(
+            contact.instance(),
+            Some("urn:uuid:00000000-0000-1000-8000-000A95A0E128")
+        );
+        assert_eq!(contact.reg_id(), Some(1));
+
+        let supported = supported(&request);
+        assert!(supported.iter().any(|ext| ext.0 == "path"));
+        assert!(supported.iter().any(|ext| ext.0 == "outbound"));
+
+        assert!(!registration.outbound_established());
+    }
+
+    #[tokio::test]
+    async fn service_route_preloaded() {
+        let mut builder = Endpoint::builder();
+        let dialog_layer = builder.add_layer(DialogLayer::default());
+        let transport = Udp::spawn(&mut builder, "127.0.0.1:0").await.unwrap();
+        let endpoint = builder.build();
+
+        let mut registration = registration();
+        let request = registration.create_register(false);
+
+        let mut headers = Headers::new();
+        headers.insert(
+            Name::SERVICE_ROUTE,
+            "<sip:orig@scscf.example.org;lr>, <sip:pcscf.example.org;lr>",
+        );
+
+        registration.receive_success_response(TsxResponse {
+            tp_info: MessageTpInfo {
+                timestamp: SystemTime::now(),
+                source: "127.0.0.1:5060".parse().unwrap(),
+                buffer: Bytes::new(),
+                transport,
+                deviations: vec![],
+            },
+            line: StatusLine {
+                code: Code::OK,
+                reason: None,
+            },
+            base_headers: BaseHeaders {
+                via: vec![Via::new(
+                    "UDP",
+                    "192.0.2.1:5060".parse::<SocketAddr>().unwrap(),
+                    "z9hG4bK776asdhds",
+                )],
+                from: request.headers.get(Name::FROM).unwrap(),
+                to: request.headers.get(Name::TO).unwrap(),
+                call_id: request.headers.get_named().unwrap(),
+                cseq: request.headers.get_named().unwrap(),
+            },
+            headers,
+            body: Bytes::new(),
+        });
+
+        let service_route = print_routes(registration.service_route());
+        assert_eq!(
+            service_route,
+            [
+                "<sip:orig@scscf.example.org;lr>",
+                "<sip:pcscf.example.org;lr>"
+            ]
+        );
+
+        // A later request outside of the registration preloads the Service-Route as Route set
+        let target: SipUri = "sip:bob@example.org".parse().unwrap();
+        let mut dialog_builder = ClientDialogBuilder::new(
+            endpoint,
+            dialog_layer,
+            registration.from.uri.clone(),
+            registration.contact.clone(),
+            Box::new(target),
+        );
+        dialog_builder.route_set = registration.service_route().to_vec();
+
+        let invite = dialog_builder.create_request(Method::INVITE);
+        let route: Vec<Routing> = invite.headers.get(Name::ROUTE).unwrap();
+        assert_eq!(print_routes(&route), service_route);
+    }
+
+    fn print_routes(routes: &[Routing]) -> Vec<String> {
+        routes
+            .iter()
+            .map(|route| route.default_print_ctx().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn keep_alive_interval() {
+        let mut registration = registration();
+
+        for _ in 0..100 {
+            let reliable = registration.keep_alive_interval(true);
+            assert!((95..=120).contains(&reliable.as_secs()));
+
+            let unreliable = registration.keep_alive_interval(false);
+            assert!((24..=29).contains(&unreliable.as_secs()));
+        }
+
+        registration.flow_timer = Some(Duration::from_secs(100));
+
+        for _ in 0..100 {
+            let interval = registration.keep_alive_interval(false);
+            assert!(interval >= Duration::from_secs(80));
+            assert!(interval <= Duration::from_secs(100));
+        }
+    }
+}
@@ -0,0 +1,675 @@
+//! Server side REGISTER handling ([RFC3261 Section 10.3](https://datatracker.ietf.org/doc/html/rfc3261#section-10.3))
+//!
+//! The [`Registrar`] manages the contact bindings of addresses of record inside a
+//! [`BindingStore`]. It supports digest authentication, the outbound
+//! ([RFC5626](https://datatracker.ietf.org/doc/html/rfc5626)) and public GRUU
+//! ([RFC5627](https://datatracker.ietf.org/doc/html/rfc5627)) extensions.
+
+use crate::register::echo_path;
+use crate::util::random_string;
+use bytesstr::BytesStr;
+use parking_lot as pl;
+use sip_auth::digest::DigestCredentials;
+use sip_auth::RequestParts;
+use sip_core::transport::OutgoingResponse;
+use sip_core::{Endpoint, IncomingRequest, Result};
+use sip_types::header::typed::{
+    Algorithm, Allow, AuthChallenge, AuthResponse, Contact, DigestChallenge, DigestResponse,
+    Expires, MinExpires, QopOption, Require, Routing, Supported, Username,
+};
+use sip_types::print::AppendCtx;
+use sip_types::uri::sip::{SipUri, UserPart};
+use sip_types::uri::Uri;
+use sip_types::{Code, Method, Name};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime};
+
+mod store;
+
+pub use store::{Binding, BindingStore, InMemoryBindingStore, StoreError};
+
+/// Duration after which an issued nonce is considered stale
+const NONCE_LIFETIME: Duration = Duration::from_secs(300);
+
+type CredentialLookup = Box<dyn Fn(&str) -> Option<DigestCredentials> + Send + Sync>;
+type AorOwner = Box<dyn Fn(&str, &str) -> bool + Send + Sync>;
+
+struct Authentication {
+    realm: BytesStr,
+    credentials: CredentialLookup,
+    /// Returns if a username may register an address of record
+    aor_owner: Option<AorOwner>,
+    /// Issued nonces and when they were issued
+    nonces: pl::Mutex<HashMap<BytesStr, Instant>>,
+}
+
+pub struct Registrar<S: BindingStore> {
+    store: S,
+    authentication: Option<Authentication>,
+
+    min_expires: u32,
+    max_expires: u32,
+    default_expires: u32,
+
+    /// Serializes the read-modify-write cycles on the binding store
+    update_lock: tokio::sync::Mutex<()>,
+}
+
+impl<S: BindingStore> Registrar<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            authentication: None,
+            min_expires: 60,
+            max_expires: 7200,
+            default_expires: 3600,
+            update_lock: Default::default(),
+        }
+    }
+
+    /// Require digest authentication in the given realm
+    ///
+    /// `credentials` returns the credentials of a username, or `None` if the user is unknown.
+    ///
+    /// Users may only register the address of record `sip:<username>@<realm>` (or its `sips`
+    /// variant), use [`Self::with_aor_owner`] if usernames and addresses of record differ.
+    pub fn with_authentication<R, F>(mut self, realm: R, credentials: F) -> Self
+    where
+        R: Into<BytesStr>,
+        F: Fn(&str) -> Option<DigestCredentials> + Send + Sync + 'static,
+    {
+        self.authentication = Some(Authentication {
+            realm: realm.into(),
+            credentials: Box::new(credentials),
+            aor_owner: None,
+            nonces: Default::default(),
+        });
+        self
+    }
+
+    /// Decide which addresses of record an authenticated user may register
+    ///
+    /// `owns` is called with the username and the address of record (as returned by
+    /// [`address_of_record`]). Has no effect without [`Self::with_authentication`].
+    pub fn with_aor_owner<F>(mut self, owns: F) -> Self
+    where
+        F: Fn(&str, &str) -> bool + Send + Sync + 'static,
+    {
+        if let Some(authentication) = &mut self.authentication {
+            authentication.aor_owner = Some(Box::new(owns));
+        }
+        self
+    }
+
+    /// Set the minimum, maximum and default duration of bindings in seconds
+    pub fn with_expires(mut self, min: u32, max: u32, default: u32) -> Self {
+        self.min_expires = min;
+        self.max_expires = max.max(min);
+        self.default_expires = default.clamp(min, self.max_expires);
+        self
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the active bindings of the address of record, ordered by descending q-value
+    pub async fn lookup(&self, aor: &str) -> Result<Vec<Binding>, StoreError> {
+        let now = SystemTime::now();
+
+        let mut bindings = self.store.get(aor).await?;
+        bindings.retain(|binding| !binding.is_expired(now));
+        bindings.sort_by(|a, b| b.q().total_cmp(&a.q()));
+
+        Ok(bindings)
+    }
+
+    /// Handle a REGISTER request and respond to it
+    ///
+    /// Failures of the binding store are answered with a 500 response, requests with another
+    /// method than REGISTER with a 405 response.
+    pub async fn handle_register(
+        &self,
+        endpoint: &Endpoint,
+        request: IncomingRequest,
+    ) -> Result<()> {
+        let transaction = endpoint.create_server_tsx(&request);
+
+        if request.line.method != Method::REGISTER {
+            let mut response = endpoint.create_response(&request, Code::METHOD_NOT_ALLOWED, None);
+            response.msg.headers.insert_named(&Allow(Method::REGISTER));
+
+            return transaction.respond(response).await;
+        }
+
+        let response = match self.process_register(endpoint, &request).await {
+            Ok(response) => response,
+            Err(code) => {
+                let mut response = endpoint.create_response(&request, code, None);
+
+                if code == Code::INTERVAL_TOO_BRIEF {
+                    response
+                        .msg
+                        .headers
+                        .insert_named(&MinExpires(self.min_expires));
+                }
+
+                response
+            }
+        };
+
+        transaction.respond(response).await
+    }
+
+    async fn process_register(
+        &self,
+        endpoint: &Endpoint,
+        request: &IncomingRequest,
+    ) -> Result<OutgoingResponse, Code> {
+        let aor = address_of_record(&*request.base_headers.to.uri.uri);
+
+        if let Some(authentication) = &self.authentication {
+            let username = match authentication.authenticate(request) {
+                Ok(username) => username,
+                Err(stale) => {
+                    let mut response = endpoint.create_response(request, Code::UNAUTHORIZED, None);
+                    authentication.challenge(&mut response, stale);
+                    return Ok(response);
+                }
+            };
+
+            // Users may only register their own address of record
+            if !authentication.owns(&username, &aor) {
+                return Err(Code::FORBIDDEN);
+            }
+        }
+
+        let update = BindingUpdate::from_request(request)?;
+
+        let supported: Vec<Supported> = request.headers.get_named().unwrap_or_default();
+        let supports_gruu = supported.iter().any(|s| s.0.eq_ignore_ascii_case("gruu"));
+
+        let _guard = self.update_lock.lock().await;
+
+        let now = SystemTime::now();
+
+        let mut bindings = self.store.get(&aor).await.map_err(|e| {
+            log::warn!("failed to get bindings of {aor}, {e}");
+            Code::SERVER_INTERNAL_ERROR
+        })?;
+
+        let outbound = self.update_bindings(&mut bindings, update, now)?;
+
+        self.store.set(&aor, bindings.clone()).await.map_err(|e| {
+            log::warn!("failed to set bindings of {aor}, {e}");
+            Code::SERVER_INTERNAL_ERROR
+        })?;
+
+        let mut response = endpoint.create_response(request, Code::OK, None);
+
+        for binding in &bindings {
+            let mut contact = binding
+                .contact
+                .clone()
+                .with_value_param("expires", binding.expires_in(now).to_string());
+
+            if supports_gruu {
+                if let Some(instance) = binding.contact.instance() {
+                    let pub_gruu = format!("\"{aor};gr={instance}\"");
+                    contact = contact.with_value_param("pub-gruu", pub_gruu);
+                }
+            }
+
+            response.msg.headers.insert_named(&contact);
+        }
+
+        echo_path(&request.headers, &mut response.msg.headers);
+
+        if outbound {
+            response
+                .msg
+                .headers
+                .insert_named(&Require(BytesStr::from_static("outbound")));
+        }
+
+        Ok(response)
+    }
+
+    /// Apply the REGISTER request to the bindings of its address of record, expired bindings are
+    /// removed
+    ///
+    /// Returns if an outbound binding was created, or the code to reject the request with.
+    fn update_bindings(
+        &self,
+        bindings: &mut Vec<Binding>,
+        update: BindingUpdate,
+        now: SystemTime,
+    ) -> Result<bool, Code> {
+        bindings.retain(|binding| !binding.is_expired(now));
+
+        let is_outdated =
+            |binding: &Binding| binding.call_id == update.call_id && binding.cseq >= update.cseq;
+
+        let Some(contacts) = update.contacts else {
+            // Wildcard is only allowed to remove all bindings
+            if update.expires != Some(0) {
+                return Err(Code::BAD_REQUEST);
+            }
+
+            if bindings.iter().any(is_outdated) {
+                return Err(Code::SERVER_INTERNAL_ERROR);
+            }
+
+            bindings.clear();
+            return Ok(false);
+        };
+
+        let mut outbound = false;
+
+        for contact in contacts {
+            let expires = contact
+                .params
+                .get_val("expires")
+                .and_then(|expires| expires.parse().ok())
+                .or(update.expires)
+                .unwrap_or(self.default_expires);
+
+            if expires != 0 && expires < self.min_expires {
+                return Err(Code::INTERVAL_TOO_BRIEF);
+            }
+
+            let existing = bindings
+                .iter()
+                .position(|binding| binding.matches(&contact));
+
+            if let Some(idx) = existing {
+                if is_outdated(&bindings[idx]) {
+                    // Out of order or retransmitted REGISTER
+                    return Err(Code::SERVER_INTERNAL_ERROR);
+                }
+
+                bindings.remove(idx);
+            }
+
+            if expires == 0 {
+                continue;
+            }
+
+            let is_outbound =
+                update.outbound && contact.instance().is_some() && contact.reg_id().is_some();
+            outbound |= is_outbound;
+
+            let mut contact = contact;
+            contact.params.take("expires");
+
+            bindings.push(Binding {
+                contact,
+                expires_at: now + Duration::from_secs(expires.min(self.max_expires).into()),
+                call_id: update.call_id.clone(),
+                cseq: update.cseq,
+                path: update.path.clone(),
+                source: update.source,
+                outbound: is_outbound,
+            });
+        }
+
+        Ok(outbound)
+    }
+}
+
+/// The parts of a REGISTER request which modify the bindings of its address of record
+struct BindingUpdate {
+    /// `None` if the request contains the `*` wildcard contact
+    contacts: Option<Vec<Contact>>,
+    /// Value of the Expires header
+    expires: Option<u32>,
+    call_id: BytesStr,
+    cseq: u32,
+    path: Vec<Routing>,
+    source: SocketAddr,
+    /// The request contains `Supported: outbound`
+    outbound: bool,
+}
+
+impl BindingUpdate {
+    fn from_request(request: &IncomingRequest) -> Result<Self, Code> {
+        let wildcard = request
+            .headers
+            .iter()
+            .any(|(name, value)| *name == Name::CONTACT && value.trim() == "*");
+
+        let contacts = if wildcard {
+            None
+        } else {
+            let contacts = request.headers.get_named().or_else(|_| {
+                if request.headers.contains(&Name::CONTACT) {
+                    Err(Code::BAD_REQUEST)
+                } else {
+                    Ok(vec![])
+                }
+            })?;
+
+            Some(contacts)
+        };
+
+        let supported: Vec<Supported> = request.headers.get_named().unwrap_or_default();
+
+        Ok(Self {
+            contacts,
+            expires: request
+                .headers
+                .get_named::<Expires>()
+                .ok()
+                .map(|Expires(expires)| expires),
+            call_id: request.base_headers.call_id.0.clone(),
+            cseq: request.base_headers.cseq.cseq,
+            path: request.headers.get(Name::PATH).unwrap_or_default(),
+            source: request.tp_info.source,
+            outbound: supported
+                .iter()
+                .any(|s| s.0.eq_ignore_ascii_case("outbound")),
+        })
+    }
+}
+
+impl Authentication {
+    /// Returns the authenticated username or `Err(stale)` if the request must be challenged
+    fn authenticate(&self, request: &IncomingRequest) -> Result<BytesStr, bool> {
+        let responses: Vec<AuthResponse> =
+            request.headers.get(Name::AUTHORIZATION).unwrap_or_default();
+
+        let Some(response) = responses.into_iter().find_map(|response| match response {
+            AuthResponse::Digest(response) if response.realm == self.realm => Some(response),
+            _ => None,
+        }) else {
+            return Err(false);
+        };
+
+        let issued = self.nonces.lock().get(&response.nonce).copied();
+
+        match issued {
+            Some(issued) if issued.elapsed() < NONCE_LIFETIME => {}
+            // Unknown or expired nonce, the credentials may still be correct
+            _ => return Err(self.verify(request, &response)),
+        }
+
+        if self.verify(request, &response) {
+            match response.username {
+                Username::Username(username) => Ok(username),
+                Username::UsernameNonASCII(_) => Err(false),
+            }
+        } else {
+            Err(false)
+        }
+    }
+
+    fn owns(&self, username: &str, aor: &str) -> bool {
+        if let Some(aor_owner) = &self.aor_owner {
+            return aor_owner(username, aor);
+        }
+
+        let Ok(mut uri) = format!("sip:{username}@{}", self.realm).parse::<SipUri>() else {
+            return false;
+        };
+
+        if address_of_record(&uri) == aor {
+            return true;
+        }
+
+        uri.sips = true;
+        address_of_record(&uri) == aor
+    }
+
+    fn verify(&self, request: &IncomingRequest, response: &DigestResponse) -> bool {
+        let Username::Username(username) = &response.username else {
+            return false;
+        };
+
+        let Some(credentials) = (self.credentials)(username) else {
+            return false;
+        };
+
+        let request_parts = RequestParts {
+            line: &request.line,
+            headers: &request.headers,
+            body: &request.body,
+        };
+
+        credentials
+            .verify(response, request_parts)
+            .unwrap_or_default()
+    }
+
+    fn challenge(&self, response: &mut OutgoingResponse, stale: bool) {
+        let nonce = random_string();
+
+        {
+            let mut nonces = self.nonces.lock();
+            nonces.retain(|_, issued| issued.elapsed() < NONCE_LIFETIME);
+            nonces.insert(nonce.clone(), Instant::now());
+        }
+
+        // Offer SHA-256 first, MD5 for backwards compatibility (RFC8760 Section 2.4)
+        for algorithm in [Algorithm::SHA256, Algorithm::MD5] {
+            let challenge = AuthChallenge::Digest(DigestChallenge {
+                realm: self.realm.clone(),
+                domain: None,
+                nonce: nonce.clone(),
+                opaque: None,
+                stale,
+                algorithm,
+                qop: vec![QopOption::Auth],
+                userhash: false,
+                other: vec![],
+            });
+
+            response
+                .msg
+                .headers
+                .insert_type(Name::WWW_AUTHENTICATE, &challenge);
+        }
+    }
+}
+
+/// Returns the address of record of a URI, SIP URIs are stripped of their parameters
+pub fn address_of_record(uri: &dyn Uri) -> String {
+    if let Some(sip_uri) = uri.downcast_ref::<SipUri>() {
+        let mut aor = SipUri::new(sip_uri.host_port.clone());
+        aor.sips = sip_uri.sips;
+        aor.user_part = match &sip_uri.user_part {
+            UserPart::UserPw(user_pw) => UserPart::User(user_pw.user.clone()),
+            user_part => user_part.clone(),
+        };
+
+        aor.default_print_ctx().to_string()
+    } else {
+        uri.clone_boxed().default_print_ctx().to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sip_types::uri::NameAddr;
+
+    fn registrar() -> Registrar<InMemoryBindingStore> {
+        Registrar::new(InMemoryBindingStore::default()).with_expires(60, 7200, 3600)
+    }
+
+    fn contact(uri: &str) -> Contact {
+        Contact::new(NameAddr::uri(uri.parse::<SipUri>().unwrap()))
+    }
+
+    fn update(contacts: Option<Vec<Contact>>, expires: Option<u32>, cseq: u32) -> BindingUpdate {
+        BindingUpdate {
+            contacts,
+            expires,
+            call_id: "call-id".into(),
+            cseq,
+            path: vec![],
+            source: "192.0.2.1:5060".parse().unwrap(),
+            outbound: false,
+        }
+    }
+
+    fn expires(bindings: &[Binding], now: SystemTime) -> Vec<u32> {
+        bindings
+            .iter()
+            .map(|binding| binding.expires_in(now))
+            .collect()
+    }
+
+    #[test]
+    fn binding_expiry() {
+        let registrar = registrar();
+        let now = SystemTime::now();
+        let mut bindings = vec![];
+
+        let contacts = vec![
+            contact("sip:alice@192.0.2.1"),
+            contact("sip:alice@192.0.2.2").with_value_param("expires", "120"),
+            contact("sip:alice@192.0.2.3").with_value_param("expires", "100000"),
+        ];
+
+        // The expires parameter takes precedence over the Expires header, the maximum is enforced
+        registrar
+            .update_bindings(&mut bindings, update(Some(contacts), Some(600), 1), now)
+            .unwrap();
+        assert_eq!(expires(&bindings, now), [600, 120, 7200]);
+        assert!(bindings[1].contact.params.get_val("expires").is_none());
+
+        // Without Expires header the default is used
+        let contacts = vec![contact("sip:alice@192.0.2.1")];
+        registrar
+            .update_bindings(&mut bindings, update(Some(contacts), None, 2), now)
+            .unwrap();
+        assert_eq!(expires(&bindings, now), [120, 7200, 3600]);
+
+        // Bindings expire
+        let later = now + Duration::from_secs(200);
+        registrar
+            .update_bindings(&mut bindings, update(Some(vec![]), None, 3), later)
+            .unwrap();
+        assert_eq!(expires(&bindings, later), [7000, 3400]);
+
+        // Expires 0 removes a single binding
+        let contacts = vec![contact("sip:alice@192.0.2.3")];
+        registrar
+            .update_bindings(&mut bindings, update(Some(contacts), Some(0), 4), later)
+            .unwrap();
+        assert_eq!(expires(&bindings, later), [3400]);
+    }
+
+    #[test]
+    fn binding_interval_too_brief() {
+        let registrar = registrar();
+        let now = SystemTime::now();
+        let mut bindings = vec![];
+
+        let contacts = vec![contact("sip:alice@192.0.2.1").with_value_param("expires", "30")];
+        let result = registrar.update_bindings(&mut bindings, update(Some(contacts), None, 1), now);
+
+        assert_eq!(result, Err(Code::INTERVAL_TOO_BRIEF));
+    }
+
+    #[test]
+    fn binding_out_of_order() {
+        let registrar = registrar();
+        let now = SystemTime::now();
+        let mut bindings = vec![];
+
+        let contacts = || Some(vec![contact("sip:alice@192.0.2.1")]);
+
+        registrar
+            .update_bindings(&mut bindings, update(contacts(), None, 2), now)
+            .unwrap();
+
+        for cseq in [1, 2] {
+            let result =
+                registrar.update_bindings(&mut bindings, update(contacts(), None, cseq), now);
+            assert_eq!(result, Err(Code::SERVER_INTERNAL_ERROR));
+        }
+
+        registrar
+            .update_bindings(&mut bindings, update(contacts(), Some(0), 3), now)
+            .unwrap();
+        assert!(bindings.is_empty());
+    }
+
+    #[test]
+    fn wildcard() {
+        let registrar = registrar();
+        let now = SystemTime::now();
+        let mut bindings = vec![];
+
+        let contacts = vec![
+            contact("sip:alice@192.0.2.1"),
+            contact("sip:alice@192.0.2.2"),
+        ];
+        registrar
+            .update_bindings(&mut bindings, update(Some(contacts), None, 2), now)
+            .unwrap();
+
+        // The wildcard requires Expires: 0
+        for expires in [None, Some(60)] {
+            let result = registrar.update_bindings(&mut bindings, update(None, expires, 3), now);
+            assert_eq!(result, Err(Code::BAD_REQUEST));
+            assert_eq!(bindings.len(), 2);
+        }
+
+        // Out of order wildcard
+        let result = registrar.update_bindings(&mut bindings, update(None, Some(0), 2), now);
+        assert_eq!(result, Err(Code::SERVER_INTERNAL_ERROR));
+        assert_eq!(bindings.len(), 2);
+
+        registrar
+            .update_bindings(&mut bindings, update(None, Some(0), 3), now)
+            .unwrap();
+        assert!(bindings.is_empty());
+    }
+
+    #[test]
+    fn outbound_binding() {
+        let registrar = registrar();
+        let now = SystemTime::now();
+        let mut bindings = vec![];
+
+        let flow = |reg_id| {
+            contact("sip:alice@192.0.2.1;transport=tcp")
+                .with_instance("urn:uuid:00000000-0000-1000-8000-AABBCCDDEEFF")
+                .with_reg_id(reg_id)
+        };
+
+        let mut update = update(Some(vec![flow(1), flow(2)]), None, 1);
+        update.outbound = true;
+
+        assert_eq!(
+            registrar.update_bindings(&mut bindings, update, now),
+            Ok(true)
+        );
+        assert_eq!(bindings.len(), 2);
+        assert!(bindings.iter().all(|binding| binding.outbound));
+    }
+
+    #[test]
+    fn aor_owner() {
+        let authentication = Authentication {
+            realm: "example.org".into(),
+            credentials: Box::new(|_| None),
+            aor_owner: None,
+            nonces: Default::default(),
+        };
+
+        assert!(authentication.owns("alice", "sip:alice@example.org"));
+        assert!(authentication.owns("alice", "sips:alice@example.org"));
+        assert!(!authentication.owns("alice", "sip:alice@example.com"));
+        assert!(!authentication.owns("alice", "sip:bob@example.org"));
+
+        let address = |uri: &str| address_of_record(&uri.parse::<SipUri>().unwrap());
+        assert_eq!(
+            address("sip:alice:pw@example.org;transport=tcp"),
+            "sip:alice@example.org"
+        );
+    }
+}
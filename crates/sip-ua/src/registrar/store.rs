@@ -0,0 +1,102 @@
+use bytesstr::BytesStr;
+use parking_lot as pl;
+use sip_types::header::typed::{Contact, Routing};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+
+pub type StoreError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A contact registered for an address of record
+#[derive(Debug, Clone)]
+pub struct Binding {
+    /// The registered contact including its parameters (e.g. `q`, `+sip.instance`, `reg-id`)
+    pub contact: Contact,
+    pub expires_at: SystemTime,
+
+    /// Call-ID and CSeq of the last REGISTER request which updated the binding
+    pub call_id: BytesStr,
+    pub cseq: u32,
+
+    /// Path of the REGISTER request, must be used as preloaded route set to reach the contact
+    pub path: Vec<Routing>,
+
+    /// Source address the REGISTER request was received from
+    pub source: SocketAddr,
+    /// The binding is an outbound flow (RFC5626), requests must be sent over the flow it was
+    /// registered with
+    pub outbound: bool,
+}
+
+impl Binding {
+    /// Returns the `q` parameter of the contact, defaults to 1.0
+    pub fn q(&self) -> f32 {
+        self.contact
+            .params
+            .get_val("q")
+            .and_then(|q| q.parse().ok())
+            .unwrap_or(1.0)
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at <= now
+    }
+
+    /// Remaining duration of the binding in seconds
+    pub fn expires_in(&self, now: SystemTime) -> u32 {
+        self.expires_at
+            .duration_since(now)
+            .unwrap_or(Duration::ZERO)
+            .as_secs() as u32
+    }
+
+    /// Returns if the binding is updated by the given contact of a REGISTER request
+    ///
+    /// Outbound bindings match by `+sip.instance` and `reg-id`, all others by URI.
+    pub fn matches(&self, contact: &Contact) -> bool {
+        match (self.contact.instance(), contact.instance()) {
+            (Some(a), Some(b)) if self.contact.reg_id().is_some() => {
+                a == b && self.contact.reg_id() == contact.reg_id()
+            }
+            _ => self.contact.uri.uri.compare(&*contact.uri.uri),
+        }
+    }
+}
+
+/// Storage of the bindings of a [`Registrar`](super::Registrar)
+///
+/// Implementations can persist bindings to a database, so that they are shared between
+/// multiple registrar instances.
+#[async_trait::async_trait]
+pub trait BindingStore: Send + Sync + 'static {
+    /// Return all bindings of the address of record, may include expired bindings
+    async fn get(&self, aor: &str) -> Result<Vec<Binding>, StoreError>;
+
+    /// Replace all bindings of the address of record, an empty list removes the address of record
+    async fn set(&self, aor: &str, bindings: Vec<Binding>) -> Result<(), StoreError>;
+}
+
+/// [`BindingStore`] keeping the bindings in memory
+#[derive(Default)]
+pub struct InMemoryBindingStore {
+    bindings: pl::Mutex<HashMap<String, Vec<Binding>>>,
+}
+
+#[async_trait::async_trait]
+impl BindingStore for InMemoryBindingStore {
+    async fn get(&self, aor: &str) -> Result<Vec<Binding>, StoreError> {
+        Ok(self.bindings.lock().get(aor).cloned().unwrap_or_default())
+    }
+
+    async fn set(&self, aor: &str, bindings: Vec<Binding>) -> Result<(), StoreError> {
+        let mut map = self.bindings.lock();
+
+        if bindings.is_empty() {
+            map.remove(aor);
+        } else {
+            map.insert(aor.into(), bindings);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+//! Generic SIP event framework ([RFC6665](https://datatracker.ietf.org/doc/html/rfc6665))
+//!
+//! Event packages implement [`EventPackage`] and are used with the [`Subscriber`] (client) and
+//! [`Notifier`] (server) roles of a subscription.
+//!
+//! The [`SubscriptionLayer`] must be added to the endpoint after the
+//! [`DialogLayer`](crate::dialog::DialogLayer) to receive the NOTIFY requests creating the
+//! dialogs of a subscription.
+
+use bytesstr::BytesStr;
+use parking_lot as pl;
+use sip_core::{Endpoint, EndpointBuilder, IncomingRequest, Layer, MayTake};
+use sip_types::Method;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+mod notifier;
+mod subscriber;
+
+pub use notifier::{Notifier, NotifierEvent};
+pub use subscriber::{Notification, Subscriber};
+
+/// An event package, e.g. `presence` or `dialog`
+pub trait EventPackage: Send + Sync + 'static {
+    /// Value of the `Event` header
+    const EVENT: &'static str;
+
+    /// Content type of the NOTIFY bodies
+    const CONTENT_TYPE: &'static str;
+
+    /// Duration of a subscription in seconds, used when the subscriber doesn't request one.
+    /// Also the maximum duration a notifier grants.
+    const DEFAULT_EXPIRES: u32 = 3600;
+}
+
+/// Subscriptions are refreshed after 90% of their duration has passed
+fn refresh_interval(expires: u32) -> Duration {
+    Duration::from_secs(u64::from(expires) * 9 / 10)
+}
+
+#[derive(Default)]
+pub struct SubscriptionLayer {
+    /// Subscribers waiting for dialog creating NOTIFYs, keyed by Call-ID and local tag
+    pending: pl::Mutex<HashMap<(BytesStr, BytesStr), mpsc::Sender<IncomingRequest>>>,
+}
+
+#[async_trait::async_trait]
+impl Layer for SubscriptionLayer {
+    fn name(&self) -> &'static str {
+        "subscription"
+    }
+
+    fn init(&mut self, endpoint: &mut EndpointBuilder) {
+        endpoint.add_allow(Method::SUBSCRIBE);
+        endpoint.add_allow(Method::NOTIFY);
+    }
+
+    async fn receive(&self, _: &Endpoint, mut request: MayTake<'_, IncomingRequest>) {
+        if request.line.method != Method::NOTIFY {
+            return;
+        }
+
+        let Some(local_tag) = &request.base_headers.to.tag else {
+            return;
+        };
+
+        let key = (request.base_headers.call_id.0.clone(), local_tag.clone());
+
+        let Some(notify_sender) = self.pending.lock().get(&key).cloned() else {
+            return;
+        };
+
+        let notify = request.inner().take().unwrap();
+
+        if let Err(mpsc::error::SendError(notify)) = notify_sender.send(notify).await {
+            *request.inner() = Some(notify);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use super::*;
+
+    pub(crate) struct TestPackage;
+
+    impl EventPackage for TestPackage {
+        const EVENT: &'static str = "dialog";
+        const CONTENT_TYPE: &'static str = "application/dialog-info+xml";
+        const DEFAULT_EXPIRES: u32 = 600;
+    }
+
+    #[test]
+    fn refresh_after_90_percent() {
+        assert_eq!(refresh_interval(3600), Duration::from_secs(3240));
+        assert_eq!(refresh_interval(600), Duration::from_secs(540));
+        assert_eq!(refresh_interval(1), Duration::ZERO);
+    }
+}
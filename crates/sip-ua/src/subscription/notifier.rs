@@ -0,0 +1,268 @@
+use super::EventPackage;
+use crate::dialog::{Dialog, DialogLayer, Usage, UsageGuard};
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use sip_core::{Endpoint, IncomingRequest, LayerKey, MayTake, Result};
+use sip_types::header::typed::{Contact, ContentType, Event, Expires, SubState, SubscriptionState};
+use sip_types::{Code, Headers, Method};
+use std::marker::PhantomData;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{sleep_until, Instant};
+
+/// Event returned by [`Notifier::receive`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifierEvent {
+    /// The subscriber refreshed the subscription
+    Refreshed,
+
+    /// The subscriber terminated the subscription, a final NOTIFY must be sent using
+    /// [`Notifier::terminate`]
+    Unsubscribed,
+
+    /// The subscription expired without being refreshed, it must be terminated using
+    /// [`Notifier::terminate`] with the reason `timeout`
+    Expired,
+}
+
+/// Usage forwarding SUBSCRIBE requests refreshing a subscription
+struct NotifierUsage {
+    subscribe_sender: mpsc::Sender<IncomingRequest>,
+}
+
+#[async_trait::async_trait]
+impl Usage for NotifierUsage {
+    fn name(&self) -> &'static str {
+        "notifier-usage"
+    }
+
+    async fn receive(&self, _: &Endpoint, mut request: MayTake<'_, IncomingRequest>) {
+        if request.line.method != Method::SUBSCRIBE {
+            return;
+        }
+
+        let subscribe = request.inner().take().unwrap();
+
+        if let Err(mpsc::error::SendError(subscribe)) = self.subscribe_sender.send(subscribe).await
+        {
+            *request.inner() = Some(subscribe);
+        }
+    }
+}
+
+/// Server role of a subscription to the event package `P`
+pub struct Notifier<P: EventPackage> {
+    _usage_guard: UsageGuard,
+    dialog: Dialog,
+
+    /// `id` parameter of the subscription's `Event` header
+    id: Option<BytesStr>,
+
+    subscribe_rx: mpsc::Receiver<IncomingRequest>,
+    expires_at: Instant,
+    terminated: bool,
+
+    _package: PhantomData<P>,
+}
+
+impl<P: EventPackage> Notifier<P> {
+    /// Returns if the incoming SUBSCRIBE request is meant for the event package
+    pub fn matches(subscribe: &IncomingRequest) -> bool {
+        subscribe.line.method == Method::SUBSCRIBE
+            && subscribe
+                .headers
+                .get_named::<Event>()
+                .map(|event| event.event.eq_ignore_ascii_case(P::EVENT))
+                .unwrap_or_default()
+    }
+
+    /// Accept the subscription requested by an initial SUBSCRIBE request
+    ///
+    /// The requested duration is limited to [`EventPackage::DEFAULT_EXPIRES`]. The initial
+    /// NOTIFY must be sent immediately after using [`notify`](Notifier::notify).
+    pub async fn accept(
+        endpoint: Endpoint,
+        dialog_layer: LayerKey<DialogLayer>,
+        subscribe: IncomingRequest,
+        local_contact: Contact,
+    ) -> Result<Self> {
+        let transaction = endpoint.create_server_tsx(&subscribe);
+
+        let dialog = Dialog::new_server(endpoint, dialog_layer, &subscribe, local_contact)?;
+
+        let (subscribe_sender, subscribe_rx) = mpsc::channel(4);
+        let usage_guard = dialog.register_usage(NotifierUsage { subscribe_sender });
+
+        let expires = Self::granted_expires(&subscribe.headers);
+
+        let mut response = dialog.create_response(&subscribe, Code::OK, None)?;
+        response.msg.headers.insert_named(&Expires(expires));
+        transaction.respond(response).await?;
+
+        let id = subscribe
+            .headers
+            .get_named::<Event>()
+            .ok()
+            .and_then(|event| event.id);
+
+        Ok(Self {
+            _usage_guard: usage_guard,
+            dialog,
+            id,
+            subscribe_rx,
+            expires_at: Instant::now() + Duration::from_secs(expires.into()),
+            terminated: false,
+            _package: PhantomData,
+        })
+    }
+
+    fn granted_expires(headers: &Headers) -> u32 {
+        headers
+            .get_named()
+            .map(|Expires(expires)| expires)
+            .unwrap_or(P::DEFAULT_EXPIRES)
+            .min(P::DEFAULT_EXPIRES)
+    }
+
+    pub fn dialog(&self) -> &Dialog {
+        &self.dialog
+    }
+
+    /// Returns if the subscription has been terminated
+    pub fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+
+    /// Receive the next refresh of the subscription, responding to the SUBSCRIBE request
+    pub async fn receive(&mut self) -> Result<NotifierEvent> {
+        loop {
+            let subscribe = tokio::select! {
+                subscribe = self.subscribe_rx.recv() => subscribe.expect("dialog holds the usage"),
+                _ = sleep_until(self.expires_at) => return Ok(NotifierEvent::Expired),
+            };
+
+            let transaction = self.dialog.endpoint.create_server_tsx(&subscribe);
+
+            if !Self::matches(&subscribe) {
+                let response = self
+                    .dialog
+                    .create_response(&subscribe, Code::BAD_EVENT, None)?;
+                transaction.respond(response).await?;
+                continue;
+            }
+
+            let expires = Self::granted_expires(&subscribe.headers);
+            self.expires_at = Instant::now() + Duration::from_secs(expires.into());
+
+            let mut response = self.dialog.create_response(&subscribe, Code::OK, None)?;
+            response.msg.headers.insert_named(&Expires(expires));
+            transaction.respond(response).await?;
+
+            if expires == 0 {
+                return Ok(NotifierEvent::Unsubscribed);
+            } else {
+                return Ok(NotifierEvent::Refreshed);
+            }
+        }
+    }
+
+    /// Send a NOTIFY with the current state of the resource
+    ///
+    /// Terminates the subscription with the reason `timeout` if it has expired.
+    pub async fn notify(&mut self, body: Bytes) -> Result<()> {
+        let now = Instant::now();
+
+        if now >= self.expires_at {
+            return self.terminate("timeout", body).await;
+        }
+
+        let remaining = (self.expires_at - now).as_secs() as u32;
+
+        self.send_notify(SubscriptionState::active(remaining), body)
+            .await
+    }
+
+    /// Send a NOTIFY while the subscription is still pending authorization
+    pub async fn notify_pending(&mut self, body: Bytes) -> Result<()> {
+        let now = Instant::now();
+
+        let mut state = SubscriptionState::new(SubState::Pending);
+        state.expires = Some(self.expires_at.saturating_duration_since(now).as_secs() as u32);
+
+        self.send_notify(state, body).await
+    }
+
+    /// Send the final NOTIFY terminating the subscription
+    pub async fn terminate(&mut self, reason: &'static str, body: Bytes) -> Result<()> {
+        self.send_notify(SubscriptionState::terminated(reason), body)
+            .await?;
+        self.terminated = true;
+
+        Ok(())
+    }
+
+    async fn send_notify(&mut self, state: SubscriptionState, body: Bytes) -> Result<()> {
+        if self.terminated {
+            return Ok(());
+        }
+
+        let mut event = Event::new(P::EVENT);
+        event.id.clone_from(&self.id);
+
+        let mut request = self.dialog.create_request(Method::NOTIFY);
+        request.headers.insert_named(&event);
+        request.headers.insert_named(&state);
+        request.headers.insert_named(&self.dialog.local_contact);
+
+        if !body.is_empty() {
+            request
+                .headers
+                .insert_named(&ContentType(P::CONTENT_TYPE.into()));
+            request.body = body;
+        }
+
+        let mut target_tp_info = self.dialog.target_tp_info.lock().await;
+
+        let mut transaction = self
+            .dialog
+            .endpoint
+            .send_request(request, &mut target_tp_info)
+            .await?;
+
+        drop(target_tp_info);
+
+        let response = transaction.receive_final().await?;
+
+        if response.line.code == Code::CALL_OR_TRANSACTION_DOES_NOT_EXIST {
+            // The subscriber no longer knows the subscription
+            self.terminated = true;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::subscription::test::TestPackage;
+    use sip_types::Name;
+
+    fn granted_expires(expires: Option<&str>) -> u32 {
+        let mut headers = Headers::new();
+
+        if let Some(expires) = expires {
+            headers.insert(Name::EXPIRES, expires);
+        }
+
+        Notifier::<TestPackage>::granted_expires(&headers)
+    }
+
+    #[test]
+    fn expires_limited_to_default() {
+        assert_eq!(granted_expires(None), 600);
+        assert_eq!(granted_expires(Some("120")), 120);
+        assert_eq!(granted_expires(Some("0")), 0);
+        assert_eq!(granted_expires(Some("86400")), 600);
+    }
+}
@@ -0,0 +1,428 @@
+use super::{refresh_interval, EventPackage, SubscriptionLayer};
+use crate::dialog::{ClientDialogBuilder, Dialog, DialogLayer, Usage, UsageGuard};
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use sip_core::transaction::TsxResponse;
+use sip_core::{Endpoint, IncomingRequest, LayerKey, MayTake, Request, Result};
+use sip_types::header::typed::{
+    Accept, Contact, ContentType, Event, Expires, MinExpires, SubState, SubscriptionState,
+};
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::{Code, CodeKind, Method};
+use std::marker::PhantomData;
+use tokio::sync::mpsc;
+use tokio::time::{sleep_until, Instant};
+
+/// Content of a NOTIFY request received by a [`Subscriber`]
+#[derive(Debug)]
+pub struct Notification {
+    pub state: SubscriptionState,
+    pub content_type: Option<ContentType>,
+    pub body: Bytes,
+}
+
+/// Usage forwarding the NOTIFY requests received inside the dialog of a subscription fork
+struct SubscriberUsage {
+    notify_sender: mpsc::Sender<IncomingRequest>,
+}
+
+#[async_trait::async_trait]
+impl Usage for SubscriberUsage {
+    fn name(&self) -> &'static str {
+        "subscriber-usage"
+    }
+
+    async fn receive(&self, _: &Endpoint, mut request: MayTake<'_, IncomingRequest>) {
+        if request.line.method != Method::NOTIFY {
+            return;
+        }
+
+        let notify = request.inner().take().unwrap();
+
+        if let Err(mpsc::error::SendError(notify)) = self.notify_sender.send(notify).await {
+            *request.inner() = Some(notify);
+        }
+    }
+}
+
+/// A dialog created by a NOTIFY, a forked SUBSCRIBE may create multiple
+struct Fork {
+    _usage_guard: UsageGuard,
+    dialog: Dialog,
+    refresh_at: Instant,
+}
+
+/// Client role of a subscription to the event package `P`
+pub struct Subscriber<P: EventPackage> {
+    builder: ClientDialogBuilder,
+    sub_layer: LayerKey<SubscriptionLayer>,
+
+    /// Requested duration of the subscription in seconds
+    expires: u32,
+
+    notify_sender: mpsc::Sender<IncomingRequest>,
+    notify_rx: mpsc::Receiver<IncomingRequest>,
+
+    forks: Vec<Fork>,
+
+    /// At least one fork was created, the subscription ends when all forks are terminated
+    established: bool,
+    unsubscribed: bool,
+
+    _package: PhantomData<P>,
+}
+
+impl<P: EventPackage> Subscriber<P> {
+    pub fn new(
+        endpoint: Endpoint,
+        dialog_layer: LayerKey<DialogLayer>,
+        sub_layer: LayerKey<SubscriptionLayer>,
+        local_addr: NameAddr,
+        local_contact: Contact,
+        target: Box<dyn Uri>,
+    ) -> Self {
+        let builder =
+            ClientDialogBuilder::new(endpoint, dialog_layer, local_addr, local_contact, target);
+
+        let (notify_sender, notify_rx) = mpsc::channel(4);
+
+        builder.endpoint[sub_layer]
+            .pending
+            .lock()
+            .insert(Self::pending_key(&builder), notify_sender.clone());
+
+        Self {
+            builder,
+            sub_layer,
+            expires: P::DEFAULT_EXPIRES,
+            notify_sender,
+            notify_rx,
+            forks: vec![],
+            established: false,
+            unsubscribed: false,
+            _package: PhantomData,
+        }
+    }
+
+    fn pending_key(builder: &ClientDialogBuilder) -> (BytesStr, BytesStr) {
+        (
+            builder.call_id.0.clone(),
+            builder
+                .local_fromto
+                .tag
+                .clone()
+                .expect("builder always sets a tag"),
+        )
+    }
+
+    /// Set the requested duration of the subscription in seconds
+    pub fn with_expires(mut self, expires: u32) -> Self {
+        self.expires = expires;
+        self
+    }
+
+    /// Send the initial SUBSCRIBE request and return the final response
+    ///
+    /// A `423 Interval Too Brief` response is handled by retrying with the `Min-Expires` of the
+    /// response. The subscription's state is delivered in NOTIFY requests using [`receive`].
+    ///
+    /// [`receive`]: Subscriber::receive
+    pub async fn subscribe(&mut self) -> Result<TsxResponse> {
+        loop {
+            let mut request = self.builder.create_request(Method::SUBSCRIBE);
+            self.add_subscribe_headers(&mut request);
+
+            let mut transaction = self
+                .builder
+                .endpoint
+                .send_request(request, &mut self.builder.target_tp_info)
+                .await?;
+
+            let response = transaction.receive_final().await?;
+
+            self.builder.local_cseq += 1;
+
+            if response.line.code == Code::INTERVAL_TOO_BRIEF {
+                if let Ok(MinExpires(min_expires)) = response.headers.get_named() {
+                    if min_expires > self.expires {
+                        self.expires = min_expires;
+                        continue;
+                    }
+                }
+            }
+
+            if let Ok(Expires(expires)) = response.headers.get_named() {
+                self.expires = expires;
+            }
+
+            return Ok(response);
+        }
+    }
+
+    /// Receive the next notification, responding to the NOTIFY request
+    ///
+    /// Refreshes every fork of the subscription in the background while waiting.
+    /// Returns `None` once every fork of the subscription has been terminated.
+    pub async fn receive(&mut self) -> Result<Option<Notification>> {
+        loop {
+            if self.established && self.forks.is_empty() {
+                return Ok(None);
+            }
+
+            let refresh_at = self
+                .forks
+                .iter()
+                .map(|fork| fork.refresh_at)
+                .min()
+                .filter(|_| !self.unsubscribed);
+
+            let notify = tokio::select! {
+                notify = self.notify_rx.recv() => notify.expect("subscriber holds a sender"),
+                _ = sleep_until(refresh_at.unwrap_or_else(Instant::now)), if refresh_at.is_some() => {
+                    self.refresh().await?;
+                    continue;
+                }
+            };
+
+            if let Some(notification) = self.handle_notify(notify).await? {
+                return Ok(Some(notification));
+            }
+        }
+    }
+
+    async fn handle_notify(&mut self, notify: IncomingRequest) -> Result<Option<Notification>> {
+        let endpoint = &self.builder.endpoint;
+        let transaction = endpoint.create_server_tsx(&notify);
+
+        let event_matches = notify
+            .headers
+            .get_named::<Event>()
+            .map(|event| event.event.eq_ignore_ascii_case(P::EVENT))
+            .unwrap_or_default();
+
+        if !event_matches {
+            let response = endpoint.create_response(&notify, Code::BAD_EVENT, None);
+            transaction.respond(response).await?;
+            return Ok(None);
+        }
+
+        let state = match notify.headers.get_named::<SubscriptionState>() {
+            Ok(state) => state,
+            Err(_) => {
+                let response = endpoint.create_response(&notify, Code::BAD_REQUEST, None);
+                transaction.respond(response).await?;
+                return Ok(None);
+            }
+        };
+
+        let peer_tag = &notify.base_headers.from.tag;
+
+        let fork_idx = match self
+            .forks
+            .iter()
+            .position(|fork| &fork.dialog.peer_fromto.tag == peer_tag)
+        {
+            Some(fork_idx) => fork_idx,
+            None => {
+                let dialog = self.builder.create_dialog_from_notify(&notify)?;
+                let usage_guard = dialog.register_usage(SubscriberUsage {
+                    notify_sender: self.notify_sender.clone(),
+                });
+
+                self.forks.push(Fork {
+                    _usage_guard: usage_guard,
+                    dialog,
+                    refresh_at: Instant::now() + refresh_interval(self.expires),
+                });
+                self.established = true;
+
+                self.forks.len() - 1
+            }
+        };
+
+        let fork = &mut self.forks[fork_idx];
+
+        let response = fork.dialog.create_response(&notify, Code::OK, None)?;
+        transaction.respond(response).await?;
+
+        match state.state {
+            SubState::Terminated => {
+                self.forks.remove(fork_idx);
+            }
+            _ => {
+                if let Some(expires) = state.expires {
+                    fork.refresh_at = Instant::now() + refresh_interval(expires);
+                }
+            }
+        }
+
+        Ok(Some(Notification {
+            state,
+            content_type: notify.headers.get_named().ok(),
+            body: notify.body,
+        }))
+    }
+
+    /// Send a refreshing SUBSCRIBE inside every fork which is due
+    async fn refresh(&mut self) -> Result<()> {
+        let now = Instant::now();
+
+        let mut i = 0;
+        while i < self.forks.len() {
+            if self.forks[i].refresh_at > now {
+                i += 1;
+                continue;
+            }
+
+            let mut request = self.forks[i].dialog.create_request(Method::SUBSCRIBE);
+            self.add_subscribe_headers(&mut request);
+
+            let response = send_request(&self.forks[i].dialog, request).await?;
+
+            if response.line.code.kind() == CodeKind::Success {
+                let expires = response
+                    .headers
+                    .get_named()
+                    .map(|Expires(expires)| expires)
+                    .unwrap_or(self.expires);
+
+                self.forks[i].refresh_at = Instant::now() + refresh_interval(expires);
+                i += 1;
+            } else {
+                // The notifier no longer knows the subscription
+                self.forks.remove(i);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Terminate the subscription by sending a SUBSCRIBE with `Expires: 0` to every fork
+    ///
+    /// The final NOTIFY requests are still delivered using [`receive`](Subscriber::receive).
+    pub async fn unsubscribe(&mut self) -> Result<()> {
+        self.expires = 0;
+        self.unsubscribed = true;
+
+        if self.forks.is_empty() {
+            let mut request = self.builder.create_request(Method::SUBSCRIBE);
+            self.add_subscribe_headers(&mut request);
+
+            let mut transaction = self
+                .builder
+                .endpoint
+                .send_request(request, &mut self.builder.target_tp_info)
+                .await?;
+
+            self.builder.local_cseq += 1;
+
+            transaction.receive_final().await?;
+
+            return Ok(());
+        }
+
+        for fork in &self.forks {
+            let mut request = fork.dialog.create_request(Method::SUBSCRIBE);
+            self.add_subscribe_headers(&mut request);
+
+            send_request(&fork.dialog, request).await?;
+        }
+
+        Ok(())
+    }
+
+    fn add_subscribe_headers(&self, request: &mut Request) {
+        request.headers.insert_named(&Event::new(P::EVENT));
+        request
+            .headers
+            .insert_named(&Accept(P::CONTENT_TYPE.into()));
+        request.headers.insert_named(&Expires(self.expires));
+    }
+}
+
+impl<P: EventPackage> Drop for Subscriber<P> {
+    fn drop(&mut self) {
+        self.builder.endpoint[self.sub_layer]
+            .pending
+            .lock()
+            .remove(&Self::pending_key(&self.builder));
+    }
+}
+
+async fn send_request(dialog: &Dialog, request: Request) -> Result<TsxResponse> {
+    let mut target_tp_info = dialog.target_tp_info.lock().await;
+
+    let mut transaction = dialog
+        .endpoint
+        .send_request(request, &mut target_tp_info)
+        .await?;
+
+    drop(target_tp_info);
+
+    transaction.receive_final().await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::subscription::test::TestPackage;
+    use sip_types::uri::sip::SipUri;
+
+    fn subscriber() -> (
+        Endpoint,
+        LayerKey<SubscriptionLayer>,
+        Subscriber<TestPackage>,
+    ) {
+        let mut builder = Endpoint::builder();
+        let dialog_layer = builder.add_layer(DialogLayer::default());
+        let sub_layer = builder.add_layer(SubscriptionLayer::default());
+        let endpoint = builder.build();
+
+        let local: SipUri = "sip:alice@example.org".parse().unwrap();
+        let local_contact: SipUri = "sip:alice@192.0.2.1:5060".parse().unwrap();
+        let target: SipUri = "sip:bob@example.com".parse().unwrap();
+
+        let subscriber = Subscriber::new(
+            endpoint.clone(),
+            dialog_layer,
+            sub_layer,
+            NameAddr::uri(local),
+            Contact::new(NameAddr::uri(local_contact)),
+            Box::new(target),
+        );
+
+        (endpoint, sub_layer, subscriber)
+    }
+
+    #[tokio::test]
+    async fn subscribe_headers() {
+        let (_, _, subscriber) = subscriber();
+        let mut subscriber = subscriber.with_expires(300);
+
+        let mut request = subscriber.builder.create_request(Method::SUBSCRIBE);
+        subscriber.add_subscribe_headers(&mut request);
+
+        let event: Event = request.headers.get_named().unwrap();
+        assert_eq!(event.event, "dialog");
+        assert_eq!(event.id, None);
+
+        let accept: Accept = request.headers.get_named().unwrap();
+        assert_eq!(accept.0, "application/dialog-info+xml");
+
+        let expires: Expires = request.headers.get_named().unwrap();
+        assert_eq!(expires.0, 300);
+    }
+
+    #[tokio::test]
+    async fn pending_until_dropped() {
+        let (endpoint, sub_layer, subscriber) = subscriber();
+        let key = Subscriber::<TestPackage>::pending_key(&subscriber.builder);
+
+        // NOTIFY requests with the subscriber's Call-ID and From-tag are routed to it
+        assert!(endpoint[sub_layer].pending.lock().contains_key(&key));
+
+        drop(subscriber);
+
+        assert!(endpoint[sub_layer].pending.lock().is_empty());
+    }
+}
@@ -41,7 +41,7 @@ fn encode_addr(addr: SocketAddr, buf: &mut Vec<u8>, xor16: u16, xor32: u32, xor1
             buf.put_u8(1);
             buf.put_u16(addr.port() ^ xor16);
 
-            let ip = u32::from_ne_bytes(addr.ip().octets());
+            let ip = u32::from(*addr.ip());
             let ip = ip ^ xor32;
 
             buf.put_u32(ip);
@@ -50,7 +50,7 @@ fn encode_addr(addr: SocketAddr, buf: &mut Vec<u8>, xor16: u16, xor32: u32, xor1
             buf.put_u8(2);
             buf.put_u16(addr.port() ^ xor16);
 
-            let ip = u128::from_ne_bytes(addr.ip().octets());
+            let ip = u128::from(*addr.ip());
             let ip = ip ^ xor128;
 
             buf.put_u128(ip);
@@ -67,7 +67,7 @@ impl Attribute<'_> for MappedAddress {
     const TYPE: u16 = 0x0001;
 
     fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
-        decode_addr(attr.get_value(msg.buffer()), 0, 0, 0).map(Self)
+        decode_addr(attr.get_padded_value(msg.buffer()), 0, 0, 0).map(Self)
     }
 
     fn encode(&self, _: Self::Context, builder: &mut MessageBuilder) -> Result<(), Error> {
@@ -77,8 +77,8 @@ impl Attribute<'_> for MappedAddress {
 
     fn encode_len(&self) -> Result<u16, Error> {
         match self.0 {
-            SocketAddr::V4(_) => Ok(64),
-            SocketAddr::V6(_) => Ok(160),
+            SocketAddr::V4(_) => Ok(8),
+            SocketAddr::V6(_) => Ok(20),
         }
     }
 }
@@ -92,7 +92,7 @@ impl Attribute<'_> for XorMappedAddress {
 
     fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
         let xor128 = msg.id().0;
-        decode_addr(attr.get_value(msg.buffer()), XOR16, COOKIE, xor128).map(Self)
+        decode_addr(attr.get_padded_value(msg.buffer()), XOR16, COOKIE, xor128).map(Self)
     }
 
     fn encode(&self, _: Self::Context, builder: &mut MessageBuilder) -> Result<(), Error> {
@@ -103,8 +103,8 @@ impl Attribute<'_> for XorMappedAddress {
 
     fn encode_len(&self) -> Result<u16, Error> {
         match self.0 {
-            SocketAddr::V4(_) => Ok(64),
-            SocketAddr::V6(_) => Ok(160),
+            SocketAddr::V4(_) => Ok(8),
+            SocketAddr::V6(_) => Ok(20),
         }
     }
 }
@@ -117,7 +117,7 @@ impl Attribute<'_> for AlternateServer {
     const TYPE: u16 = 0x8023;
 
     fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
-        decode_addr(attr.get_value(msg.buffer()), 0, 0, 0).map(Self)
+        decode_addr(attr.get_padded_value(msg.buffer()), 0, 0, 0).map(Self)
     }
 
     fn encode(&self, _: Self::Context, builder: &mut MessageBuilder) -> Result<(), Error> {
@@ -127,8 +127,67 @@ impl Attribute<'_> for AlternateServer {
 
     fn encode_len(&self) -> Result<u16, Error> {
         match self.0 {
-            SocketAddr::V4(_) => Ok(64),
-            SocketAddr::V6(_) => Ok(160),
+            SocketAddr::V4(_) => Ok(8),
+            SocketAddr::V6(_) => Ok(20),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::XorMappedAddress;
+    use crate::builder::MessageBuilder;
+    use crate::header::{Class, Method};
+    use crate::parse::ParsedMessage;
+    use std::net::SocketAddr;
+
+    // Values from https://datatracker.ietf.org/doc/html/rfc5769#section-2.2
+    const TSX_ID: u128 = 0xb7e7a701bc34d686fa87dfae;
+
+    #[test]
+    fn xor_mapped_address_v4() {
+        let addr: SocketAddr = "192.0.2.1:32853".parse().unwrap();
+
+        let mut builder = MessageBuilder::new(Class::Success, Method::Binding, TSX_ID);
+        builder.add_attr(&XorMappedAddress(addr)).unwrap();
+
+        let bytes = builder.finish();
+
+        assert_eq!(
+            &bytes[20..],
+            &[0x00, 0x20, 0x00, 0x08, 0x00, 0x01, 0xa1, 0x47, 0xe1, 0x12, 0xa6, 0x43]
+        );
+
+        let mut parsed = ParsedMessage::parse(bytes).unwrap();
+        assert_eq!(
+            parsed.get_attr::<XorMappedAddress>().unwrap().unwrap().0,
+            addr
+        );
+    }
+
+    #[test]
+    fn xor_mapped_address_v6() {
+        let addr: SocketAddr = "[2001:db8:1234:5678:11:2233:4455:6677]:32853"
+            .parse()
+            .unwrap();
+
+        let mut builder = MessageBuilder::new(Class::Success, Method::Binding, TSX_ID);
+        builder.add_attr(&XorMappedAddress(addr)).unwrap();
+
+        let bytes = builder.finish();
+
+        assert_eq!(
+            &bytes[20..],
+            &[
+                0x00, 0x20, 0x00, 0x14, 0x00, 0x02, 0xa1, 0x47, 0x01, 0x13, 0xa9, 0xfa, 0xa5, 0xd3,
+                0xf1, 0x79, 0xbc, 0x25, 0xf4, 0xb5, 0xbe, 0xd2, 0xb9, 0xd9
+            ]
+        );
+
+        let mut parsed = ParsedMessage::parse(bytes).unwrap();
+        assert_eq!(
+            parsed.get_attr::<XorMappedAddress>().unwrap().unwrap().0,
+            addr
+        );
+    }
+}
@@ -62,7 +62,7 @@ impl Attribute<'_> for Fingerprint {
     const TYPE: u16 = 0x8028;
 
     fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
-        let mut value = attr.get_value(msg.buffer());
+        let mut value = attr.get_padded_value(msg.buffer());
 
         if value.len() != 4 {
             return Err(Error::InvalidData("fingerprint value must be 4 bytes"));
@@ -70,7 +70,7 @@ impl Attribute<'_> for Fingerprint {
 
         let attr_value = value.read_u32::<NE>()?;
 
-        let data = &msg.buffer()[..attr.begin];
+        let data = &msg.buffer()[..attr.begin - 4];
 
         let crc = Self::crc32(data) ^ 0x5354554e;
 
@@ -0,0 +1,184 @@
+use super::Attribute;
+use crate::builder::MessageBuilder;
+use crate::parse::{ParsedAttr, ParsedMessage};
+use crate::{Error, NE};
+use byteorder::ReadBytesExt;
+use bytes::BufMut;
+
+/// [RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-16.1)
+pub struct Priority(pub u32);
+
+impl Attribute<'_> for Priority {
+    type Context = ();
+    const TYPE: u16 = 0x0024;
+
+    fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
+        Ok(Self(attr.get_padded_value(msg.buffer()).read_u32::<NE>()?))
+    }
+
+    fn encode(&self, _: Self::Context, builder: &mut MessageBuilder) -> Result<(), Error> {
+        builder.buffer().put_u32(self.0);
+
+        Ok(())
+    }
+
+    fn encode_len(&self) -> Result<u16, Error> {
+        Ok(4)
+    }
+}
+
+/// [RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-16.1)
+pub struct UseCandidate;
+
+impl Attribute<'_> for UseCandidate {
+    type Context = ();
+    const TYPE: u16 = 0x0025;
+
+    fn decode(_: Self::Context, _: &mut ParsedMessage, _: ParsedAttr) -> Result<Self, Error> {
+        Ok(Self)
+    }
+
+    fn encode(&self, _: Self::Context, _: &mut MessageBuilder) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn encode_len(&self) -> Result<u16, Error> {
+        Ok(0)
+    }
+}
+
+/// [RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-16.1)
+pub struct IceControlled(pub u64);
+
+impl Attribute<'_> for IceControlled {
+    type Context = ();
+    const TYPE: u16 = 0x8029;
+
+    fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
+        Ok(Self(attr.get_padded_value(msg.buffer()).read_u64::<NE>()?))
+    }
+
+    fn encode(&self, _: Self::Context, builder: &mut MessageBuilder) -> Result<(), Error> {
+        builder.buffer().put_u64(self.0);
+
+        Ok(())
+    }
+
+    fn encode_len(&self) -> Result<u16, Error> {
+        Ok(8)
+    }
+}
+
+/// [RFC8445](https://datatracker.ietf.org/doc/html/rfc8445#section-16.1)
+pub struct IceControlling(pub u64);
+
+impl Attribute<'_> for IceControlling {
+    type Context = ();
+    const TYPE: u16 = 0x802A;
+
+    fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
+        Ok(Self(attr.get_padded_value(msg.buffer()).read_u64::<NE>()?))
+    }
+
+    fn encode(&self, _: Self::Context, builder: &mut MessageBuilder) -> Result<(), Error> {
+        builder.buffer().put_u64(self.0);
+
+        Ok(())
+    }
+
+    fn encode_len(&self) -> Result<u16, Error> {
+        Ok(8)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{IceControlled, IceControlling, Priority, UseCandidate};
+    use crate::attributes::{Fingerprint, MessageIntegrity, MessageIntegrityKey, Username};
+    use crate::builder::MessageBuilder;
+    use crate::header::{Class, Method};
+    use crate::parse::ParsedMessage;
+
+    #[test]
+    fn ice_attributes() {
+        let mut builder = MessageBuilder::new(Class::Request, Method::Binding, 1234);
+        builder.add_attr(&Priority(1853824767)).unwrap();
+        builder.add_attr(&UseCandidate).unwrap();
+        builder
+            .add_attr(&IceControlling(0x1122334455667788))
+            .unwrap();
+
+        let bytes = builder.finish();
+
+        let mut parsed = ParsedMessage::parse(bytes).unwrap();
+
+        assert_eq!(
+            parsed.get_attr::<Priority>().unwrap().unwrap().0,
+            1853824767
+        );
+        assert!(parsed.get_attr::<UseCandidate>().is_some());
+        assert_eq!(
+            parsed.get_attr::<IceControlling>().unwrap().unwrap().0,
+            0x1122334455667788
+        );
+    }
+
+    #[test]
+    fn trailing_zero_bytes() {
+        let mut builder = MessageBuilder::new(Class::Request, Method::Binding, 1234);
+        builder.add_attr(&Priority(0x6e000100)).unwrap();
+        builder
+            .add_attr(&IceControlled(0x1122334455660000))
+            .unwrap();
+
+        let bytes = builder.finish();
+
+        let mut parsed = ParsedMessage::parse(bytes).unwrap();
+
+        assert_eq!(
+            parsed.get_attr::<Priority>().unwrap().unwrap().0,
+            0x6e000100
+        );
+        assert_eq!(
+            parsed.get_attr::<IceControlled>().unwrap().unwrap().0,
+            0x1122334455660000
+        );
+    }
+
+    /// [RFC5769](https://datatracker.ietf.org/doc/html/rfc5769#section-2.1)
+    #[test]
+    fn rfc5769_sample_request() {
+        let bytes = vec![
+            0x00, 0x01, 0x00, 0x58, 0x21, 0x12, 0xa4, 0x42, 0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34,
+            0xd6, 0x86, 0xfa, 0x87, 0xdf, 0xae, 0x80, 0x22, 0x00, 0x10, 0x53, 0x54, 0x55, 0x4e,
+            0x20, 0x74, 0x65, 0x73, 0x74, 0x20, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x00, 0x24,
+            0x00, 0x04, 0x6e, 0x00, 0x01, 0xff, 0x80, 0x29, 0x00, 0x08, 0x93, 0x2f, 0xf9, 0xb1,
+            0x51, 0x26, 0x3b, 0x36, 0x00, 0x06, 0x00, 0x09, 0x65, 0x76, 0x74, 0x6a, 0x3a, 0x68,
+            0x36, 0x76, 0x59, 0x20, 0x20, 0x20, 0x00, 0x08, 0x00, 0x14, 0x9a, 0xea, 0xa7, 0x0c,
+            0xbf, 0xd8, 0xcb, 0x56, 0x78, 0x1e, 0xf2, 0xb5, 0xb2, 0xd3, 0xf2, 0x49, 0xc1, 0xb5,
+            0x71, 0xa2, 0x80, 0x28, 0x00, 0x04, 0xe5, 0x7a, 0x3b, 0xcf,
+        ];
+
+        let mut parsed = ParsedMessage::parse(bytes).unwrap();
+
+        assert_eq!(
+            parsed.get_attr::<Priority>().unwrap().unwrap().0,
+            0x6e0001ff
+        );
+        assert_eq!(
+            parsed.get_attr::<IceControlled>().unwrap().unwrap().0,
+            0x932ff9b151263b36
+        );
+        assert_eq!(
+            parsed.get_attr::<Username>().unwrap().unwrap().0,
+            "evtj:h6vY"
+        );
+
+        let key = MessageIntegrityKey::new_short_term("VOkJxbRl1RmTxUk/WvJxBt");
+        parsed
+            .get_attr_with::<MessageIntegrity>(&key)
+            .unwrap()
+            .unwrap();
+        parsed.get_attr::<Fingerprint>().unwrap().unwrap();
+    }
+}
@@ -117,7 +117,7 @@ where
     D: Digest + BlockSizeUser,
 {
     msg.with_msg_len(u16::try_from(attr.padding_end - 20)?, |msg| {
-        let value = attr.get_value(msg.buffer());
+        let value = attr.get_padded_value(msg.buffer());
         let message = &msg.buffer()[..attr.begin - 4];
 
         Update::update(&mut hmac, message);
@@ -9,6 +9,7 @@ use std::str::from_utf8;
 mod addr;
 mod error_code;
 mod fingerprint;
+mod ice;
 mod integrity;
 mod password_algs;
 pub mod turn;
@@ -17,6 +18,7 @@ mod user_hash;
 pub use addr::*;
 pub use error_code::ErrorCode;
 pub use fingerprint::Fingerprint;
+pub use ice::*;
 pub use integrity::*;
 pub use password_algs::*;
 pub use user_hash::*;
@@ -15,7 +15,7 @@ impl Attribute<'_> for ChannelNumber {
     const TYPE: u16 = 0x000C;
 
     fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
-        Ok(Self(attr.get_value(msg.buffer()).read_u16::<NE>()?))
+        Ok(Self(attr.get_padded_value(msg.buffer()).read_u16::<NE>()?))
     }
 
     fn encode(&self, _: Self::Context, builder: &mut MessageBuilder) -> Result<(), Error> {
@@ -38,7 +38,7 @@ impl Attribute<'_> for Lifetime {
     const TYPE: u16 = 0x000D;
 
     fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
-        Ok(Self(attr.get_value(msg.buffer()).read_u32::<NE>()?))
+        Ok(Self(attr.get_padded_value(msg.buffer()).read_u32::<NE>()?))
     }
 
     fn encode(&self, _: Self::Context, builder: &mut MessageBuilder) -> Result<(), Error> {
@@ -111,7 +111,7 @@ impl Attribute<'_> for EvenPort {
     const TYPE: u16 = 0x0018;
 
     fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
-        Ok(Self(attr.get_value(msg.buffer()).read_u8()? == 1))
+        Ok(Self(attr.get_padded_value(msg.buffer()).read_u8()? == 1))
     }
 
     fn encode(&self, _: Self::Context, builder: &mut MessageBuilder) -> Result<(), Error> {
@@ -137,7 +137,7 @@ impl Attribute<'_> for RequestedTransport {
 
     fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
         Ok(Self {
-            protocol_number: attr.get_value(msg.buffer()).read_u8()?,
+            protocol_number: attr.get_padded_value(msg.buffer()).read_u8()?,
         })
     }
 
@@ -182,9 +182,11 @@ impl Attribute<'_> for ReservationToken {
     const TYPE: u16 = 0x0022;
 
     fn decode(_: Self::Context, msg: &mut ParsedMessage, attr: ParsedAttr) -> Result<Self, Error> {
-        Ok(Self(attr.get_value(msg.buffer()).try_into().map_err(
-            |_| Error::InvalidData("reservation token must be 8 bytes"),
-        )?))
+        Ok(Self(
+            attr.get_padded_value(msg.buffer())
+                .try_into()
+                .map_err(|_| Error::InvalidData("reservation token must be 8 bytes"))?,
+        ))
     }
 
     fn encode(&self, _: Self::Context, builder: &mut MessageBuilder) -> Result<(), Error> {
@@ -126,6 +126,12 @@ impl TryFrom<u16> for Method {
     fn try_from(value: u16) -> Result<Self, Self::Error> {
         match value & Self::MASK {
             Self::BINDING => Ok(Self::Binding),
+            Self::ALLOCATE => Ok(Self::Allocate),
+            Self::REFRESH => Ok(Self::Refresh),
+            Self::SEND => Ok(Self::Send),
+            Self::DATA => Ok(Self::Data),
+            Self::CREATE_PERMISSION => Ok(Self::CreatePermission),
+            Self::CHANNEL_BIND => Ok(Self::ChannelBind),
             _ => Err(Error::InvalidData("unknown method")),
         }
     }
@@ -25,6 +25,15 @@ impl ParsedAttr {
     pub fn get_value<'b>(&self, buf: &'b [u8]) -> &'b [u8] {
         &buf[self.begin..self.end]
     }
+
+    /// Returns the value including any trailing zero bytes which might have been
+    /// removed by the parser as padding.
+    ///
+    /// Must be used by attributes with a fixed length which is a multiple of 4,
+    /// as their values can legitimately end with zero bytes.
+    pub fn get_padded_value<'b>(&self, buf: &'b [u8]) -> &'b [u8] {
+        &buf[self.begin..self.padding_end]
+    }
 }
 
 pub struct ParsedMessage {
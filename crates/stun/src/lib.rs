@@ -8,6 +8,8 @@ use tokio::sync::oneshot;
 use tokio::time::timeout;
 
 pub mod auth;
+pub mod turn;
+pub mod turn_rest;
 
 pub trait TransportInfo {
     fn reliable(&self) -> bool;
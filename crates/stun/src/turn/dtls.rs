@@ -0,0 +1,553 @@
+//! TURN over DTLS ([RFC7350](https://datatracker.ietf.org/doc/html/rfc7350)), backed by openssl
+//!
+//! [`DtlsTurnClient`] wraps a [`TurnClient`] and protects everything it exchanges with the server
+//! using DTLS. Every STUN and ChannelData message is sent in its own DTLS record, the datagrams
+//! returned by [`DtlsTurnClient::poll_transmit`] are sent to the server like in plain UDP.
+//!
+//! Retransmissions of the handshake are decided by openssl itself using the system clock, so the
+//! `now` passed to the client must not run ahead of it.
+
+use super::{RelayedData, TurnClient, TurnEvent};
+use openssl::error::ErrorStack;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{
+    ErrorCode, SslConnector, SslConnectorBuilder, SslMethod, SslStream, SslVerifyMode,
+};
+use openssl::x509::X509;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+/// Initial timeout of the handshake, doubled after each retransmission
+const HANDSHAKE_RTO: Duration = Duration::from_secs(1);
+/// Amount of times a handshake flight is sent before the handshake is considered failed
+const HANDSHAKE_MAX_TRANSMISSIONS: u32 = 7;
+
+/// Largest DTLS record which is sent without fragmentation on most paths
+const DEFAULT_MTU: u32 = 1200;
+
+/// Builder for a [`DtlsConnector`]
+pub struct DtlsConnectorBuilder {
+    builder: SslConnectorBuilder,
+    server_name: Option<String>,
+    verify_hostname: bool,
+    mtu: u32,
+}
+
+impl DtlsConnectorBuilder {
+    /// Verify the certificate chain of servers against the trusted roots of the system and the
+    /// server name
+    pub fn new() -> Result<Self, ErrorStack> {
+        Ok(Self {
+            builder: SslConnector::builder(SslMethod::dtls_client())?,
+            server_name: None,
+            verify_hostname: true,
+            mtu: DEFAULT_MTU,
+        })
+    }
+
+    /// Trust the given root certificate in addition to the ones of the system
+    pub fn add_root_certificate(mut self, cert: X509) -> Result<Self, ErrorStack> {
+        self.builder.cert_store_mut().add_cert(cert)?;
+        Ok(self)
+    }
+
+    /// Authenticate using the given client certificate, if requested by the server
+    pub fn client_certificate(
+        mut self,
+        chain: &[X509],
+        key: &PKey<Private>,
+    ) -> Result<Self, ErrorStack> {
+        let (cert, intermediates) = chain.split_first().expect("certificate chain is empty");
+
+        self.builder.set_certificate(cert)?;
+
+        for intermediate in intermediates {
+            self.builder.add_extra_chain_cert(intermediate.clone())?;
+        }
+
+        self.builder.set_private_key(key)?;
+        self.builder.check_private_key()?;
+
+        Ok(self)
+    }
+
+    /// Verify the server certificate against this name and send it using SNI
+    ///
+    /// By default the IP address of the server is verified, which most certificates don't contain.
+    pub fn server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    /// Accept server certificates issued by a trusted root for any name
+    pub fn skip_hostname_verification(mut self) -> Self {
+        self.verify_hostname = false;
+        self
+    }
+
+    /// Accept any server certificate
+    ///
+    /// This makes the connection vulnerable to man-in-the-middle attacks.
+    pub fn dangerous_skip_verification(mut self) -> Self {
+        self.builder.set_verify(SslVerifyMode::NONE);
+        self.verify_hostname = false;
+        self
+    }
+
+    /// Maximum size of the datagrams sent during the handshake, defaults to 1200 bytes
+    pub fn mtu(mut self, mtu: u32) -> Self {
+        self.mtu = mtu;
+        self
+    }
+
+    pub fn build(self) -> DtlsConnector {
+        DtlsConnector {
+            connector: self.builder.build(),
+            server_name: self.server_name,
+            verify_hostname: self.verify_hostname,
+            mtu: self.mtu,
+        }
+    }
+}
+
+/// Configuration of the DTLS connections to TURN servers, created using a [`DtlsConnectorBuilder`]
+#[derive(Clone)]
+pub struct DtlsConnector {
+    connector: SslConnector,
+    server_name: Option<String>,
+    verify_hostname: bool,
+    mtu: u32,
+}
+
+/// In-memory datagram transport below the DTLS connection
+///
+/// Each read returns a single datagram and each write of openssl produces a single datagram.
+#[derive(Default)]
+struct Datagrams {
+    incoming: VecDeque<Vec<u8>>,
+    outgoing: VecDeque<Vec<u8>>,
+}
+
+impl Read for Datagrams {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(datagram) = self.incoming.pop_front() else {
+            return Err(io::ErrorKind::WouldBlock.into());
+        };
+
+        let len = datagram.len().min(buf.len());
+        buf[..len].copy_from_slice(&datagram[..len]);
+
+        Ok(len)
+    }
+}
+
+impl Write for Datagrams {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.outgoing.push_back(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum State {
+    Handshaking {
+        timeout_at: Instant,
+        rto: Duration,
+        transmissions: u32,
+    },
+    Connected,
+    Failed,
+}
+
+/// [`TurnClient`] which talks to the server using DTLS
+pub struct DtlsTurnClient {
+    client: TurnClient,
+    stream: SslStream<Datagrams>,
+    state: State,
+}
+
+impl DtlsTurnClient {
+    /// Start the DTLS handshake with the server of the `client`
+    ///
+    /// Requests of the client are sent once the handshake completed. If it fails, an allocation
+    /// requested using [`TurnClient::allocate`] fails with
+    /// [`TurnError::Transport`](super::TurnError::Transport).
+    pub fn new(
+        now: Instant,
+        connector: &DtlsConnector,
+        client: TurnClient,
+    ) -> Result<Self, ErrorStack> {
+        let mut config = connector.connector.configure()?;
+        config.set_verify_hostname(connector.verify_hostname);
+
+        let mut ssl = match &connector.server_name {
+            Some(name) => config.into_ssl(name)?,
+            None => config.into_ssl(&client.server().ip().to_string())?,
+        };
+        ssl.set_mtu(connector.mtu)?;
+
+        let stream = SslStream::new(ssl, Datagrams::default())?;
+
+        let mut this = Self {
+            client,
+            stream,
+            state: State::Handshaking {
+                timeout_at: now + HANDSHAKE_RTO,
+                rto: HANDSHAKE_RTO,
+                transmissions: 1,
+            },
+        };
+
+        this.handshake();
+
+        Ok(this)
+    }
+
+    pub fn client(&self) -> &TurnClient {
+        &self.client
+    }
+
+    pub fn client_mut(&mut self) -> &mut TurnClient {
+        &mut self.client
+    }
+
+    /// Returns if the handshake completed and the connection is usable
+    pub fn is_connected(&self) -> bool {
+        matches!(self.state, State::Connected)
+    }
+
+    /// Take the next datagram that must be sent to the server
+    pub fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        match self.state {
+            State::Handshaking { .. } => {}
+            State::Connected => {
+                while let Some(msg) = self.client.poll_transmit() {
+                    if let Err(e) = self.stream.ssl_write(&msg) {
+                        self.fail(format!("failed to encrypt message, {e}"));
+                        break;
+                    }
+                }
+            }
+            // Requests made after the connection failed time out
+            State::Failed => while self.client.poll_transmit().is_some() {},
+        }
+
+        self.stream.get_mut().outgoing.pop_front()
+    }
+
+    /// Take the next event emitted by the client
+    pub fn poll_event(&mut self) -> Option<TurnEvent> {
+        self.client.poll_event()
+    }
+
+    /// Returns the point in time [`DtlsTurnClient::handle_timeout`] must be called next
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        match self.state {
+            State::Handshaking { timeout_at, .. } => Some(timeout_at),
+            State::Connected | State::Failed => self.client.poll_timeout(),
+        }
+    }
+
+    /// Drive the timers of the handshake and client, must be called at the time returned by
+    /// [`DtlsTurnClient::poll_timeout`]
+    pub fn handle_timeout(&mut self, now: Instant) {
+        match &mut self.state {
+            State::Handshaking {
+                timeout_at,
+                rto,
+                transmissions,
+            } => {
+                if now < *timeout_at {
+                    return;
+                }
+
+                if *transmissions >= HANDSHAKE_MAX_TRANSMISSIONS {
+                    self.fail("DTLS handshake timed out");
+                    return;
+                }
+
+                *transmissions += 1;
+                *rto *= 2;
+                *timeout_at = now + *rto;
+
+                // Retransmits the last flight if openssl's own timer expired
+                self.handshake();
+            }
+            State::Connected | State::Failed => self.client.handle_timeout(now),
+        }
+    }
+
+    /// Pass a datagram received from the server
+    ///
+    /// Returns data which has been relayed from peers, a datagram may contain multiple DTLS records.
+    pub fn handle_packet(&mut self, now: Instant, datagram: &[u8]) -> Vec<RelayedData> {
+        self.stream.get_mut().incoming.push_back(datagram.to_vec());
+
+        if matches!(self.state, State::Handshaking { .. }) {
+            self.handshake();
+        }
+
+        let mut relayed = vec![];
+
+        if !self.is_connected() {
+            return relayed;
+        }
+
+        let mut buf = vec![0; u16::MAX.into()];
+
+        loop {
+            match self.stream.ssl_read(&mut buf) {
+                Ok(len) => relayed.extend(self.client.handle_packet(now, &buf[..len])),
+                Err(e) if e.code() == ErrorCode::WANT_READ => break,
+                Err(e) if e.code() == ErrorCode::ZERO_RETURN => {
+                    self.fail("connection closed by the server");
+                    break;
+                }
+                Err(e) => {
+                    self.fail(format!("failed to decrypt message, {e}"));
+                    break;
+                }
+            }
+        }
+
+        relayed
+    }
+
+    fn handshake(&mut self) {
+        match self.stream.connect() {
+            Ok(()) => self.state = State::Connected,
+            Err(e) if e.code() == ErrorCode::WANT_READ => {}
+            Err(e) => self.fail(format!("DTLS handshake failed, {e}")),
+        }
+    }
+
+    fn fail(&mut self, reason: impl Into<String>) {
+        self.state = State::Failed;
+        self.client.transport_failed(reason);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::turn::test::{
+        client, data_indication, mapped_addr, peer_addr, relayed_addr, Server,
+    };
+    use crate::turn::TurnError;
+    use openssl::ssl::{Ssl, SslContext};
+
+    const SERVER_NAME: &str = "turn.example.org";
+
+    struct Identity {
+        cert: X509,
+        key: PKey<Private>,
+    }
+
+    impl Identity {
+        fn new() -> Self {
+            let cert = rcgen::generate_simple_self_signed(vec![SERVER_NAME.into()]).unwrap();
+
+            Self {
+                cert: X509::from_der(&cert.serialize_der().unwrap()).unwrap(),
+                key: PKey::private_key_from_der(&cert.serialize_private_key_der()).unwrap(),
+            }
+        }
+    }
+
+    /// [`Server`] behind a DTLS connection
+    struct DtlsServer {
+        stream: SslStream<Datagrams>,
+        turn: Server,
+    }
+
+    impl DtlsServer {
+        fn new(identity: &Identity) -> Self {
+            let mut context = SslContext::builder(SslMethod::dtls_server()).unwrap();
+            context.set_certificate(&identity.cert).unwrap();
+            context.set_private_key(&identity.key).unwrap();
+
+            let ssl = Ssl::new(&context.build()).unwrap();
+
+            Self {
+                stream: SslStream::new(ssl, Datagrams::default()).unwrap(),
+                turn: Server::new(),
+            }
+        }
+
+        fn handle(&mut self, datagram: &[u8]) {
+            self.stream.get_mut().incoming.push_back(datagram.to_vec());
+
+            if !self.stream.ssl().is_init_finished() {
+                // Handshake failures are reported to the client using an alert
+                let _ = self.stream.accept();
+                return;
+            }
+
+            let mut buf = vec![0; u16::MAX.into()];
+
+            while let Ok(len) = self.stream.ssl_read(&mut buf) {
+                if let Some(response) = self.turn.handle(&buf[..len]) {
+                    self.stream.ssl_write(&response).unwrap();
+                }
+            }
+        }
+    }
+
+    /// Exchange datagrams until neither side has anything to send
+    fn exchange(
+        now: Instant,
+        client: &mut DtlsTurnClient,
+        server: &mut DtlsServer,
+    ) -> Vec<RelayedData> {
+        let mut relayed = vec![];
+
+        loop {
+            let mut idle = true;
+
+            while let Some(datagram) = client.poll_transmit() {
+                server.handle(&datagram);
+                idle = false;
+            }
+
+            while let Some(datagram) = server.stream.get_mut().outgoing.pop_front() {
+                relayed.extend(client.handle_packet(now, &datagram));
+                idle = false;
+            }
+
+            if idle {
+                return relayed;
+            }
+        }
+    }
+
+    /// Allocate through a DTLS connection to a server using `identity`
+    fn allocate(
+        connector: DtlsConnectorBuilder,
+        identity: &Identity,
+    ) -> (DtlsTurnClient, DtlsServer) {
+        let now = Instant::now();
+
+        let (mut client, _) = client();
+        client.allocate(now);
+
+        let mut client = DtlsTurnClient::new(now, &connector.build(), client).unwrap();
+        let mut server = DtlsServer::new(identity);
+
+        exchange(now, &mut client, &mut server);
+
+        (client, server)
+    }
+
+    fn trusting(identity: &Identity) -> DtlsConnectorBuilder {
+        DtlsConnectorBuilder::new()
+            .unwrap()
+            .add_root_certificate(identity.cert.clone())
+            .unwrap()
+    }
+
+    fn assert_allocated(client: &mut DtlsTurnClient) {
+        assert!(client.is_connected());
+        assert_eq!(
+            client.poll_event(),
+            Some(TurnEvent::Allocated {
+                relayed: relayed_addr(),
+                mapped: mapped_addr()
+            })
+        );
+    }
+
+    fn assert_failed(client: &mut DtlsTurnClient) {
+        assert!(!client.is_connected());
+        assert!(matches!(
+            client.poll_event(),
+            Some(TurnEvent::AllocationFailed {
+                error: TurnError::Transport(_)
+            })
+        ));
+        assert_eq!(client.poll_timeout(), None);
+    }
+
+    #[test]
+    fn allocate_and_relay() {
+        let identity = Identity::new();
+        let (mut client, mut server) =
+            allocate(trusting(&identity).server_name(SERVER_NAME), &identity);
+
+        assert_allocated(&mut client);
+
+        let now = Instant::now();
+        let peer = peer_addr();
+
+        client.client_mut().create_permission(now, peer).unwrap();
+        client.client_mut().send_to(peer, b"hello").unwrap();
+        exchange(now, &mut client, &mut server);
+
+        assert_eq!(server.turn.relayed, [(peer, b"hello".to_vec())]);
+
+        server
+            .stream
+            .ssl_write(&data_indication(peer, b"world"))
+            .unwrap();
+
+        assert_eq!(
+            exchange(now, &mut client, &mut server),
+            [RelayedData {
+                peer,
+                data: b"world".to_vec()
+            }]
+        );
+
+        // Every request went through the DTLS connection
+        assert_eq!(server.turn.requests.len(), 3);
+    }
+
+    #[test]
+    fn untrusted_certificate() {
+        let identity = Identity::new();
+        let connector = DtlsConnectorBuilder::new()
+            .unwrap()
+            .server_name(SERVER_NAME);
+
+        let (mut client, server) = allocate(connector, &identity);
+
+        assert_failed(&mut client);
+        assert!(server.turn.requests.is_empty());
+    }
+
+    #[test]
+    fn server_name_mismatch() {
+        let identity = Identity::new();
+
+        let (mut client, _) = allocate(
+            trusting(&identity).server_name("other.example.org"),
+            &identity,
+        );
+        assert_failed(&mut client);
+
+        // Without a server name the IP address of the server is verified
+        let (mut client, _) = allocate(trusting(&identity), &identity);
+        assert_failed(&mut client);
+
+        let connector = trusting(&identity)
+            .server_name("other.example.org")
+            .skip_hostname_verification();
+
+        let (mut client, _) = allocate(connector, &identity);
+        assert_allocated(&mut client);
+    }
+
+    #[test]
+    fn skip_verification() {
+        let identity = Identity::new();
+        let connector = DtlsConnectorBuilder::new()
+            .unwrap()
+            .dangerous_skip_verification();
+
+        let (mut client, _) = allocate(connector, &identity);
+        assert_allocated(&mut client);
+    }
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+/// Receives metrics of [`TurnClient`](super::TurnClient)s, e.g. to export them to Prometheus
+///
+/// A single implementation can be shared by all clients of a deployment using
+/// [`TurnClient::with_metrics`](super::TurnClient::with_metrics). Counters are reported as
+/// increments, gauges as a pair of methods which must be added up. All methods do nothing by
+/// default.
+pub trait Metrics: Send + Sync {
+    /// An allocation has been created, increments the active allocations
+    fn allocation_created(&self) {}
+
+    /// An allocation has been released or has expired, decrements the active allocations
+    fn allocation_closed(&self) {}
+
+    /// A channel has been bound to a peer, increments the channel count
+    fn channel_bound(&self) {}
+
+    /// A channel is no longer bound, because it or its allocation expired or the allocation has
+    /// been released. Decrements the channel count.
+    fn channel_closed(&self) {}
+
+    /// Payload bytes sent to peers through the relay
+    fn relayed_bytes_sent(&self, bytes: usize) {
+        let _ = bytes;
+    }
+
+    /// Payload bytes received from peers through the relay
+    fn relayed_bytes_received(&self, bytes: usize) {
+        let _ = bytes;
+    }
+
+    /// Refreshing the allocation, a permission or a channel binding failed
+    fn refresh_failed(&self) {}
+
+    /// Round trip time of a request to the TURN server
+    ///
+    /// Only measured for requests which haven't been retransmitted.
+    fn rtt(&self, rtt: Duration) {
+        let _ = rtt;
+    }
+}
@@ -0,0 +1,1364 @@
+//! Sans-IO TURN client ([RFC8656](https://datatracker.ietf.org/doc/html/rfc8656))
+//!
+//! A [`TurnClient`] manages a single UDP allocation on a TURN server. It does not perform any IO:
+//! every packet returned by [`TurnClient::poll_transmit`] must be sent to the server and every
+//! packet received from the server passed to [`TurnClient::handle_packet`]. Timers are driven
+//! using [`TurnClient::poll_timeout`] and [`TurnClient::handle_timeout`].
+//!
+//! The allocation, permissions and channel bindings are refreshed automatically until the
+//! allocation is released using [`TurnClient::release`].
+
+use crate::turn_rest::TurnRestCredentials;
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use stun_types::attributes::turn::{
+    ChannelNumber, Data, Lifetime, RequestedTransport, XorPeerAddress, XorRelayedAddress,
+};
+use stun_types::attributes::{
+    Attribute, ErrorCode, MessageIntegrity, MessageIntegrityKey, Nonce, Realm, Username,
+    XorMappedAddress,
+};
+use stun_types::builder::MessageBuilder;
+use stun_types::header::{Class, Method};
+use stun_types::parse::ParsedMessage;
+use stun_types::{is_stun_message, transaction_id, IsStunMessageInfo};
+
+#[cfg(feature = "dtls")]
+pub mod dtls;
+mod metrics;
+
+pub use metrics::Metrics;
+
+/// Initial retransmission timeout of requests, doubled after each retransmission
+const INITIAL_RTO: Duration = Duration::from_millis(500);
+/// Amount of times a request is sent before it is considered failed
+const MAX_TRANSMISSIONS: u32 = 7;
+
+/// Lifetime of an allocation if the server doesn't send one
+const DEFAULT_LIFETIME: Duration = Duration::from_secs(600);
+/// Permissions expire after 5 minutes, channel bindings after 10 minutes
+const PERMISSION_LIFETIME: Duration = Duration::from_secs(300);
+const CHANNEL_LIFETIME: Duration = Duration::from_secs(600);
+/// Everything is refreshed two minutes before it expires, which leaves time to retry a request
+/// after it timed out
+const REFRESH_MARGIN: Duration = Duration::from_secs(120);
+/// Delay until a failed refresh is retried
+const REFRESH_RETRY: Duration = Duration::from_secs(5);
+
+/// Channel numbers which can be bound by clients
+const FIRST_CHANNEL: u16 = 0x4000;
+const LAST_CHANNEL: u16 = 0x4FFF;
+
+/// IANA protocol number of UDP, used in the REQUESTED-TRANSPORT attribute
+const UDP: u8 = 17;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TurnError {
+    #[error("there is no allocation on the TURN server")]
+    NoAllocation,
+    #[error("all channel numbers are in use")]
+    NoChannelAvailable,
+    #[error("data does not fit into a single message")]
+    DataTooLarge,
+    #[error("TURN server did not respond")]
+    Timeout,
+    #[error("TURN server responded with {code} {reason}")]
+    ErrorResponse { code: u32, reason: String },
+    #[error("invalid response from the TURN server, {0}")]
+    InvalidResponse(&'static str),
+    #[error("connection to the TURN server failed, {0}")]
+    Transport(String),
+}
+
+/// Long-term credentials used to authenticate with the TURN server
+#[derive(Debug, Clone)]
+pub struct TurnCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl From<TurnRestCredentials> for TurnCredentials {
+    fn from(credentials: TurnRestCredentials) -> Self {
+        Self {
+            username: credentials.username,
+            password: credentials.password,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TurnEvent {
+    /// The allocation has been created. `relayed` can be signaled to peers as relayed candidate,
+    /// `mapped` is the server reflexive address of the client.
+    Allocated {
+        relayed: SocketAddr,
+        mapped: SocketAddr,
+    },
+    /// Creating the allocation failed
+    AllocationFailed { error: TurnError },
+    /// Creating or refreshing the permission or channel binding for the peer failed, the server
+    /// drops data from and to the peer
+    PeerFailed { peer: SocketAddr, error: TurnError },
+    /// The allocation has been released or has expired, no more data is relayed
+    Closed,
+}
+
+/// Data received from a peer through the relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayedData {
+    pub peer: SocketAddr,
+    pub data: Vec<u8>,
+}
+
+enum State {
+    New,
+    Allocating,
+    Allocated {
+        relayed: SocketAddr,
+        expires: Instant,
+        /// `None` while a refresh is in progress
+        refresh_at: Option<Instant>,
+    },
+    Releasing,
+    Closed,
+}
+
+/// Realm and nonce received from the server, used for all requests once known
+struct Auth {
+    realm: String,
+    nonce: Vec<u8>,
+    key: MessageIntegrityKey<'static>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RequestKind {
+    Allocate,
+    Refresh { release: bool },
+    CreatePermission { peer: SocketAddr },
+    ChannelBind { peer: SocketAddr, channel: u16 },
+}
+
+struct Transaction {
+    tsx_id: u128,
+    kind: RequestKind,
+    request: Vec<u8>,
+
+    sent_at: Instant,
+    timeout_at: Instant,
+    rto: Duration,
+    transmissions: u32,
+
+    /// The request is a retry with new credentials after a 401 or 438 response
+    reauthenticated: bool,
+}
+
+/// Permission or channel binding which must be refreshed before it expires
+struct Binding {
+    /// `None` until the server confirmed the first request
+    expires: Option<Instant>,
+    /// `None` while a request is in progress
+    refresh_at: Option<Instant>,
+}
+
+struct Permission {
+    /// Peer the permission was created for, reported in [`TurnEvent::PeerFailed`]
+    peer: SocketAddr,
+    binding: Binding,
+}
+
+struct Channel {
+    number: u16,
+    binding: Binding,
+}
+
+/// Client of a single allocation on a TURN server
+pub struct TurnClient {
+    server: SocketAddr,
+    credentials: TurnCredentials,
+    metrics: Option<Arc<dyn Metrics>>,
+
+    state: State,
+    auth: Option<Auth>,
+    transactions: Vec<Transaction>,
+
+    permissions: HashMap<IpAddr, Permission>,
+    channels: HashMap<SocketAddr, Channel>,
+    next_channel: u16,
+
+    transmits: VecDeque<Vec<u8>>,
+    events: VecDeque<TurnEvent>,
+}
+
+impl TurnClient {
+    pub fn new(server: SocketAddr, credentials: TurnCredentials) -> Self {
+        Self {
+            server,
+            credentials,
+            metrics: None,
+            state: State::New,
+            auth: None,
+            transactions: vec![],
+            permissions: HashMap::new(),
+            channels: HashMap::new(),
+            next_channel: FIRST_CHANNEL,
+            transmits: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Report the metrics of this client to the given implementation
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Address of the TURN server all packets must be sent to
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// Returns the relayed address if the allocation has been created
+    pub fn relayed_addr(&self) -> Option<SocketAddr> {
+        match self.state {
+            State::Allocated { relayed, .. } => Some(relayed),
+            _ => None,
+        }
+    }
+
+    /// Request the allocation from the server
+    ///
+    /// Does nothing if the allocation is already being created or has been created. Can be called
+    /// again after the allocation failed or was closed.
+    pub fn allocate(&mut self, now: Instant) {
+        if !matches!(self.state, State::New | State::Closed) {
+            return;
+        }
+
+        self.state = State::Allocating;
+        self.send_request(now, RequestKind::Allocate, false);
+    }
+
+    /// Release the allocation, [`TurnEvent::Closed`] is emitted once the server confirmed it
+    pub fn release(&mut self, now: Instant) {
+        match self.state {
+            State::Allocated { .. } => {
+                self.transactions
+                    .retain(|transaction| !matches!(transaction.kind, RequestKind::Refresh { .. }));
+
+                self.state = State::Releasing;
+                self.send_request(now, RequestKind::Refresh { release: true }, false);
+            }
+            State::Allocating => {
+                self.transactions.clear();
+                self.state = State::Closed;
+            }
+            State::New | State::Releasing | State::Closed => {}
+        }
+    }
+
+    /// Allow the peer to send data to the relayed address
+    ///
+    /// The permission is installed for the IP address of the peer, the port is ignored.
+    pub fn create_permission(&mut self, now: Instant, peer: SocketAddr) -> Result<(), TurnError> {
+        self.relayed_addr().ok_or(TurnError::NoAllocation)?;
+
+        if self.permissions.contains_key(&peer.ip()) {
+            return Ok(());
+        }
+
+        self.permissions.insert(
+            peer.ip(),
+            Permission {
+                peer,
+                binding: Binding::new(),
+            },
+        );
+        self.send_request(now, RequestKind::CreatePermission { peer }, false);
+
+        Ok(())
+    }
+
+    /// Bind a channel to the peer, which also installs a permission for it
+    ///
+    /// Once bound, data from and to the peer is exchanged using the more compact ChannelData
+    /// messages.
+    pub fn bind_channel(&mut self, now: Instant, peer: SocketAddr) -> Result<(), TurnError> {
+        self.relayed_addr().ok_or(TurnError::NoAllocation)?;
+
+        if self.channels.contains_key(&peer) {
+            return Ok(());
+        }
+
+        if self.next_channel > LAST_CHANNEL {
+            return Err(TurnError::NoChannelAvailable);
+        }
+
+        let channel = self.next_channel;
+        self.next_channel += 1;
+
+        self.channels.insert(
+            peer,
+            Channel {
+                number: channel,
+                binding: Binding::new(),
+            },
+        );
+        self.send_request(now, RequestKind::ChannelBind { peer, channel }, false);
+
+        Ok(())
+    }
+
+    /// Send data to the peer through the relay
+    ///
+    /// Uses the channel bound to the peer, or a Send indication if there is none yet. The server
+    /// drops the data if it has no permission for the peer, see [`TurnClient::create_permission`].
+    pub fn send_to(&mut self, peer: SocketAddr, data: &[u8]) -> Result<(), TurnError> {
+        self.relayed_addr().ok_or(TurnError::NoAllocation)?;
+
+        let channel = self
+            .channels
+            .get(&peer)
+            .filter(|channel| channel.binding.expires.is_some());
+
+        let packet = match channel {
+            Some(channel) => {
+                let len = u16::try_from(data.len()).map_err(|_| TurnError::DataTooLarge)?;
+
+                let mut packet = Vec::with_capacity(4 + data.len());
+                packet.extend_from_slice(&channel.number.to_be_bytes());
+                packet.extend_from_slice(&len.to_be_bytes());
+                packet.extend_from_slice(data);
+                packet
+            }
+            None => {
+                let mut msg =
+                    MessageBuilder::new(Class::Indication, Method::Send, transaction_id());
+                msg.padding_in_value_len(false);
+                msg.add_attr(&XorPeerAddress(peer)).unwrap();
+                msg.add_attr(&Data::new(data)).unwrap();
+                msg.finish()
+            }
+        };
+
+        if let Some(metrics) = &self.metrics {
+            metrics.relayed_bytes_sent(data.len());
+        }
+
+        self.transmits.push_back(packet);
+
+        Ok(())
+    }
+
+    /// Report that the connection to the server has been lost
+    ///
+    /// Used by transports which wrap the client, e.g. the DTLS transport. An allocation in progress
+    /// fails, an existing allocation is closed.
+    pub fn transport_failed(&mut self, reason: impl Into<String>) {
+        let error = TurnError::Transport(reason.into());
+
+        match self.state {
+            State::Allocating => {
+                self.transactions.clear();
+                self.state = State::Closed;
+                self.events.push_back(TurnEvent::AllocationFailed { error });
+            }
+            State::Allocated { .. } | State::Releasing => {
+                log::debug!("lost TURN allocation, {error}");
+                self.close();
+            }
+            State::New | State::Closed => {}
+        }
+    }
+
+    /// Take the next packet that must be sent to the server
+    pub fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        self.transmits.pop_front()
+    }
+
+    /// Take the next event emitted by the client
+    pub fn poll_event(&mut self) -> Option<TurnEvent> {
+        self.events.pop_front()
+    }
+
+    /// Returns the point in time [`TurnClient::handle_timeout`] must be called next
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        let transactions = self.transactions.iter().map(|t| t.timeout_at);
+
+        let allocation = match &self.state {
+            State::Allocated {
+                expires,
+                refresh_at,
+                ..
+            } => Some(refresh_at.map_or(*expires, |at| at.min(*expires))),
+            _ => None,
+        };
+
+        let bindings = self
+            .permissions
+            .values()
+            .map(|permission| &permission.binding)
+            .chain(self.channels.values().map(|channel| &channel.binding))
+            .filter_map(Binding::timeout);
+
+        transactions.chain(allocation).chain(bindings).min()
+    }
+
+    /// Drive the timers of the client, must be called at the time returned by
+    /// [`TurnClient::poll_timeout`]
+    pub fn handle_timeout(&mut self, now: Instant) {
+        self.poll_transactions(now);
+
+        if let State::Allocated {
+            expires,
+            refresh_at,
+            ..
+        } = &mut self.state
+        {
+            if now >= *expires {
+                self.close();
+                return;
+            }
+
+            if refresh_at.is_some_and(|at| now >= at) {
+                *refresh_at = None;
+                self.send_request(now, RequestKind::Refresh { release: false }, false);
+            }
+        }
+
+        self.poll_bindings(now);
+    }
+
+    /// Pass a packet received from the server
+    ///
+    /// Returns data which has been relayed from a peer. STUN responses are consumed by the client.
+    pub fn handle_packet(&mut self, now: Instant, packet: &[u8]) -> Option<RelayedData> {
+        if !matches!(is_stun_message(packet), IsStunMessageInfo::Yes { .. }) {
+            return self.handle_channel_data(packet);
+        }
+
+        let mut msg = ParsedMessage::parse(packet.to_vec()).ok()?;
+
+        match msg.class {
+            Class::Indication if msg.method == Method::Data => {
+                let peer = msg.get_attr::<XorPeerAddress>()?.ok()?.0;
+                let data = data_attr(&msg)?.to_vec();
+
+                if let Some(metrics) = &self.metrics {
+                    metrics.relayed_bytes_received(data.len());
+                }
+
+                Some(RelayedData { peer, data })
+            }
+            Class::Success | Class::Error => {
+                self.handle_response(now, msg);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn handle_channel_data(&mut self, packet: &[u8]) -> Option<RelayedData> {
+        let header = packet.get(..4)?;
+        let number = u16::from_be_bytes([header[0], header[1]]);
+        let len = usize::from(u16::from_be_bytes([header[2], header[3]]));
+
+        let data = packet.get(4..4 + len)?;
+
+        let (peer, _) = self
+            .channels
+            .iter()
+            .find(|(_, channel)| channel.number == number && channel.binding.expires.is_some())?;
+
+        if let Some(metrics) = &self.metrics {
+            metrics.relayed_bytes_received(len);
+        }
+
+        Some(RelayedData {
+            peer: *peer,
+            data: data.to_vec(),
+        })
+    }
+
+    fn handle_response(&mut self, now: Instant, mut msg: ParsedMessage) {
+        let Some(idx) = self
+            .transactions
+            .iter()
+            .position(|transaction| transaction.tsx_id == msg.tsx_id)
+        else {
+            return;
+        };
+
+        // Responses to authenticated requests are authenticated as well, except errors
+        // which may be sent before the server verified the request
+        if let Some(auth) = &self.auth {
+            if let Some(Err(_)) = msg.get_attr_with::<MessageIntegrity>(&auth.key) {
+                log::debug!("discarding TURN response with invalid message integrity");
+                return;
+            }
+        }
+
+        let transaction = self.transactions.swap_remove(idx);
+
+        if transaction.transmissions == 1 {
+            if let Some(metrics) = &self.metrics {
+                metrics.rtt(now - transaction.sent_at);
+            }
+        }
+
+        if msg.class == Class::Success {
+            self.handle_success(now, transaction.kind, msg);
+            return;
+        }
+
+        let Some(Ok(ErrorCode { number, reason })) = msg.get_attr::<ErrorCode>() else {
+            let error = TurnError::InvalidResponse("error response without error code");
+            self.handle_failure(now, transaction.kind, error);
+            return;
+        };
+
+        let error = TurnError::ErrorResponse {
+            code: number,
+            reason: reason.into(),
+        };
+
+        // Unauthorized or Stale Nonce, retry once with the realm and nonce of the response
+        if matches!(number, 401 | 438) && !transaction.reauthenticated {
+            let realm = match msg.get_attr::<Realm>() {
+                Some(Ok(realm)) => Some(realm.0.to_string()),
+                _ => self.auth.as_ref().map(|auth| auth.realm.clone()),
+            };
+            let nonce = msg
+                .get_attr::<Nonce>()
+                .and_then(Result::ok)
+                .map(|n| n.0.to_vec());
+
+            if let (Some(realm), Some(nonce)) = (realm, nonce) {
+                let key = MessageIntegrityKey::new_long_term_md5(
+                    &self.credentials.username,
+                    &realm,
+                    &self.credentials.password,
+                );
+
+                self.auth = Some(Auth { realm, nonce, key });
+                self.send_request(now, transaction.kind, true);
+                return;
+            }
+        }
+
+        self.handle_failure(now, transaction.kind, error);
+    }
+
+    fn handle_success(&mut self, now: Instant, kind: RequestKind, mut msg: ParsedMessage) {
+        let lifetime = msg
+            .get_attr::<Lifetime>()
+            .and_then(Result::ok)
+            .map(|Lifetime(secs)| Duration::from_secs(secs.into()));
+
+        match kind {
+            RequestKind::Allocate => {
+                if !matches!(self.state, State::Allocating) {
+                    return;
+                }
+
+                let relayed = msg.get_attr::<XorRelayedAddress>().and_then(Result::ok);
+                let mapped = msg.get_attr::<XorMappedAddress>().and_then(Result::ok);
+
+                let (Some(XorRelayedAddress(relayed)), Some(XorMappedAddress(mapped))) =
+                    (relayed, mapped)
+                else {
+                    let error =
+                        TurnError::InvalidResponse("missing addresses in allocate response");
+                    self.handle_failure(now, kind, error);
+                    return;
+                };
+
+                let lifetime = lifetime.unwrap_or(DEFAULT_LIFETIME);
+
+                self.state = State::Allocated {
+                    relayed,
+                    expires: now + lifetime,
+                    refresh_at: Some(now + refresh_delay(lifetime)),
+                };
+
+                if let Some(metrics) = &self.metrics {
+                    metrics.allocation_created();
+                }
+
+                self.events
+                    .push_back(TurnEvent::Allocated { relayed, mapped });
+            }
+            RequestKind::Refresh { release: true } => self.close(),
+            RequestKind::Refresh { release: false } => {
+                let lifetime = lifetime.unwrap_or(DEFAULT_LIFETIME);
+
+                if lifetime.is_zero() {
+                    self.close();
+                } else if let State::Allocated {
+                    expires,
+                    refresh_at,
+                    ..
+                } = &mut self.state
+                {
+                    *expires = now + lifetime;
+                    *refresh_at = Some(now + refresh_delay(lifetime));
+                }
+            }
+            RequestKind::CreatePermission { peer } => {
+                if let Some(permission) = self.permissions.get_mut(&peer.ip()) {
+                    permission.binding.refreshed(now, PERMISSION_LIFETIME);
+                }
+            }
+            RequestKind::ChannelBind { peer, .. } => {
+                if let Some(channel) = self.channels.get_mut(&peer) {
+                    if channel.binding.expires.is_none() {
+                        if let Some(metrics) = &self.metrics {
+                            metrics.channel_bound();
+                        }
+                    }
+
+                    channel.binding.refreshed(now, CHANNEL_LIFETIME);
+                }
+            }
+        }
+    }
+
+    fn handle_failure(&mut self, now: Instant, kind: RequestKind, error: TurnError) {
+        match kind {
+            RequestKind::Allocate => {
+                if matches!(self.state, State::Allocating) {
+                    self.state = State::Closed;
+                    self.events.push_back(TurnEvent::AllocationFailed { error });
+                }
+            }
+            RequestKind::Refresh { release: true } => self.close(),
+            RequestKind::Refresh { release: false } => {
+                log::debug!("failed to refresh TURN allocation, {error}");
+
+                if let Some(metrics) = &self.metrics {
+                    metrics.refresh_failed();
+                }
+
+                // The server doesn't know the allocation anymore
+                let mismatch = matches!(error, TurnError::ErrorResponse { code: 437, .. });
+
+                match &mut self.state {
+                    State::Allocated { .. } if mismatch => self.close(),
+                    State::Allocated { refresh_at, .. } => {
+                        *refresh_at = Some(now + REFRESH_RETRY);
+                    }
+                    _ => {}
+                }
+            }
+            RequestKind::CreatePermission { peer } => {
+                let Some(permission) = self.permissions.get_mut(&peer.ip()) else {
+                    return;
+                };
+
+                if !permission.binding.failed(now, self.metrics.as_deref()) {
+                    let peer = permission.peer;
+                    self.permissions.remove(&peer.ip());
+                    self.events.push_back(TurnEvent::PeerFailed { peer, error });
+                }
+            }
+            RequestKind::ChannelBind { peer, .. } => {
+                let Some(channel) = self.channels.get_mut(&peer) else {
+                    return;
+                };
+
+                let was_bound = channel.binding.expires.is_some();
+
+                if !channel.binding.failed(now, self.metrics.as_deref()) {
+                    self.remove_channel(peer, was_bound);
+                    self.events.push_back(TurnEvent::PeerFailed { peer, error });
+                }
+            }
+        }
+    }
+
+    fn poll_transactions(&mut self, now: Instant) {
+        let mut timed_out = vec![];
+
+        self.transactions.retain_mut(|transaction| {
+            if now < transaction.timeout_at {
+                return true;
+            }
+
+            if transaction.transmissions >= MAX_TRANSMISSIONS {
+                timed_out.push(transaction.kind);
+                return false;
+            }
+
+            self.transmits.push_back(transaction.request.clone());
+
+            transaction.transmissions += 1;
+            transaction.rto *= 2;
+            transaction.timeout_at = now + transaction.rto;
+
+            true
+        });
+
+        for kind in timed_out {
+            self.handle_failure(now, kind, TurnError::Timeout);
+        }
+    }
+
+    fn poll_bindings(&mut self, now: Instant) {
+        let mut refresh = vec![];
+        let mut expired_permissions = vec![];
+        let mut expired_channels = vec![];
+
+        for (ip, permission) in &mut self.permissions {
+            match permission.binding.poll(now) {
+                Some(true) => refresh.push(RequestKind::CreatePermission {
+                    peer: permission.peer,
+                }),
+                Some(false) => expired_permissions.push(*ip),
+                None => {}
+            }
+        }
+
+        for (peer, channel) in &mut self.channels {
+            match channel.binding.poll(now) {
+                Some(true) => refresh.push(RequestKind::ChannelBind {
+                    peer: *peer,
+                    channel: channel.number,
+                }),
+                Some(false) => expired_channels.push(*peer),
+                None => {}
+            }
+        }
+
+        for kind in refresh {
+            self.send_request(now, kind, false);
+        }
+
+        for ip in expired_permissions {
+            if let Some(Permission { peer, .. }) = self.permissions.remove(&ip) {
+                self.events.push_back(TurnEvent::PeerFailed {
+                    peer,
+                    error: TurnError::Timeout,
+                });
+            }
+        }
+
+        for peer in expired_channels {
+            self.remove_channel(peer, true);
+            self.events.push_back(TurnEvent::PeerFailed {
+                peer,
+                error: TurnError::Timeout,
+            });
+        }
+    }
+
+    fn remove_channel(&mut self, peer: SocketAddr, was_bound: bool) {
+        if self.channels.remove(&peer).is_some() && was_bound {
+            if let Some(metrics) = &self.metrics {
+                metrics.channel_closed();
+            }
+        }
+    }
+
+    /// The allocation is gone, together with all its permissions and channels
+    fn close(&mut self) {
+        let was_allocated = matches!(self.state, State::Allocated { .. } | State::Releasing);
+
+        self.state = State::Closed;
+        self.transactions.clear();
+        self.permissions.clear();
+
+        for (_, channel) in self.channels.drain() {
+            if channel.binding.expires.is_some() {
+                if let Some(metrics) = &self.metrics {
+                    metrics.channel_closed();
+                }
+            }
+        }
+
+        if was_allocated {
+            if let Some(metrics) = &self.metrics {
+                metrics.allocation_closed();
+            }
+
+            self.events.push_back(TurnEvent::Closed);
+        }
+    }
+
+    fn send_request(&mut self, now: Instant, kind: RequestKind, reauthenticated: bool) {
+        let tsx_id = transaction_id();
+        let request = self.make_request(tsx_id, kind);
+
+        self.transmits.push_back(request.clone());
+        self.transactions.push(Transaction {
+            tsx_id,
+            kind,
+            request,
+            sent_at: now,
+            timeout_at: now + INITIAL_RTO,
+            rto: INITIAL_RTO,
+            transmissions: 1,
+            reauthenticated,
+        });
+    }
+
+    fn make_request(&self, tsx_id: u128, kind: RequestKind) -> Vec<u8> {
+        let method = match kind {
+            RequestKind::Allocate => Method::Allocate,
+            RequestKind::Refresh { .. } => Method::Refresh,
+            RequestKind::CreatePermission { .. } => Method::CreatePermission,
+            RequestKind::ChannelBind { .. } => Method::ChannelBind,
+        };
+
+        let mut msg = MessageBuilder::new(Class::Request, method, tsx_id);
+        msg.padding_in_value_len(false);
+
+        match kind {
+            RequestKind::Allocate => msg
+                .add_attr(&RequestedTransport {
+                    protocol_number: UDP,
+                })
+                .unwrap(),
+            RequestKind::Refresh { release } => {
+                if release {
+                    msg.add_attr(&Lifetime(0)).unwrap();
+                }
+            }
+            RequestKind::CreatePermission { peer } => {
+                msg.add_attr(&XorPeerAddress(peer)).unwrap();
+            }
+            RequestKind::ChannelBind { peer, channel } => {
+                msg.add_attr(&ChannelNumber(channel)).unwrap();
+                msg.add_attr(&XorPeerAddress(peer)).unwrap();
+            }
+        }
+
+        // The first allocate request is sent without credentials to learn realm and nonce
+        if let Some(auth) = &self.auth {
+            msg.add_attr(&Username::new(&self.credentials.username))
+                .unwrap();
+            msg.add_attr(&Realm::new(&auth.realm)).unwrap();
+            msg.add_attr(&Nonce::new(&auth.nonce)).unwrap();
+            msg.add_attr_with(&MessageIntegrity::default(), &auth.key)
+                .unwrap();
+        }
+
+        msg.finish()
+    }
+}
+
+impl Binding {
+    fn new() -> Self {
+        Self {
+            expires: None,
+            refresh_at: None,
+        }
+    }
+
+    fn timeout(&self) -> Option<Instant> {
+        match (self.refresh_at, self.expires) {
+            (Some(refresh_at), Some(expires)) => Some(refresh_at.min(expires)),
+            (refresh_at, expires) => refresh_at.or(expires),
+        }
+    }
+
+    fn refreshed(&mut self, now: Instant, lifetime: Duration) {
+        self.expires = Some(now + lifetime);
+        self.refresh_at = Some(now + lifetime - REFRESH_MARGIN);
+    }
+
+    /// Schedule a retry of the failed request, returns false if the binding is lost
+    fn failed(&mut self, now: Instant, metrics: Option<&dyn Metrics>) -> bool {
+        let Some(expires) = self.expires else {
+            return false;
+        };
+
+        if let Some(metrics) = metrics {
+            metrics.refresh_failed();
+        }
+
+        let retry_at = now + REFRESH_RETRY;
+
+        if retry_at >= expires {
+            return false;
+        }
+
+        self.refresh_at = Some(retry_at);
+        true
+    }
+
+    /// Returns `Some(true)` if the binding must be refreshed, `Some(false)` if it expired
+    fn poll(&mut self, now: Instant) -> Option<bool> {
+        if self.expires.is_some_and(|expires| now >= expires) {
+            return Some(false);
+        }
+
+        if self.refresh_at.is_some_and(|refresh_at| now >= refresh_at) {
+            self.refresh_at = None;
+            return Some(true);
+        }
+
+        None
+    }
+}
+
+/// Refresh the allocation two minutes before it expires, or after half its lifetime if it's short
+fn refresh_delay(lifetime: Duration) -> Duration {
+    lifetime
+        .checked_sub(REFRESH_MARGIN)
+        .unwrap_or_default()
+        .max(lifetime / 2)
+}
+
+/// Returns the value of the DATA attribute
+///
+/// Read using the length in the attribute header, as [`ParsedMessage`] removes trailing zero
+/// bytes from values to support peers which include the padding in the length. TURN messages
+/// are always built without it, see [`MessageBuilder::padding_in_value_len`].
+fn data_attr(msg: &ParsedMessage) -> Option<&[u8]> {
+    let attr = msg
+        .attributes
+        .iter()
+        .find(|attr| attr.typ == <Data as Attribute>::TYPE)?;
+
+    let buffer = msg.buffer();
+    let len = u16::from_be_bytes([buffer[attr.begin - 2], buffer[attr.begin - 1]]);
+
+    buffer.get(attr.begin..attr.begin + usize::from(len))
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+
+    const REALM: &str = "example.org";
+
+    pub(super) fn server_addr() -> SocketAddr {
+        "192.0.2.1:3478".parse().unwrap()
+    }
+
+    pub(super) fn relayed_addr() -> SocketAddr {
+        "192.0.2.1:49152".parse().unwrap()
+    }
+
+    pub(super) fn mapped_addr() -> SocketAddr {
+        "198.51.100.7:40000".parse().unwrap()
+    }
+
+    pub(super) fn peer_addr() -> SocketAddr {
+        "203.0.113.9:5000".parse().unwrap()
+    }
+
+    #[derive(Default)]
+    pub(super) struct TestMetrics {
+        allocations: AtomicIsize,
+        channels: AtomicIsize,
+        bytes_sent: AtomicUsize,
+        bytes_received: AtomicUsize,
+        refresh_failures: AtomicUsize,
+        rtts: AtomicUsize,
+    }
+
+    impl Metrics for TestMetrics {
+        fn allocation_created(&self) {
+            self.allocations.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn allocation_closed(&self) {
+            self.allocations.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        fn channel_bound(&self) {
+            self.channels.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn channel_closed(&self) {
+            self.channels.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        fn relayed_bytes_sent(&self, bytes: usize) {
+            self.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+        }
+
+        fn relayed_bytes_received(&self, bytes: usize) {
+            self.bytes_received.fetch_add(bytes, Ordering::Relaxed);
+        }
+
+        fn refresh_failed(&self) {
+            self.refresh_failures.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn rtt(&self, _: Duration) {
+            self.rtts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    impl TestMetrics {
+        pub(super) fn get(counter: &AtomicUsize) -> usize {
+            counter.load(Ordering::Relaxed)
+        }
+
+        pub(super) fn gauge(gauge: &AtomicIsize) -> isize {
+            gauge.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Minimal TURN server which authenticates requests with long-term credentials
+    pub(super) struct Server {
+        key: MessageIntegrityKey<'static>,
+        nonce: Vec<u8>,
+
+        /// Respond to the next authenticated request with 438 Stale Nonce
+        stale: bool,
+        /// Reject allocate requests with this error code
+        reject: Option<u32>,
+        /// Don't respond to refresh requests
+        drop_refresh: bool,
+
+        pub(super) requests: Vec<Method>,
+        channels: HashMap<u16, SocketAddr>,
+        /// Data received from the client, to be relayed to peers
+        pub(super) relayed: Vec<(SocketAddr, Vec<u8>)>,
+    }
+
+    impl Server {
+        pub(super) fn new() -> Self {
+            Self {
+                key: MessageIntegrityKey::new_long_term_md5("user", REALM, "pass"),
+                nonce: b"nonce-1".to_vec(),
+                stale: false,
+                reject: None,
+                drop_refresh: false,
+                requests: vec![],
+                channels: HashMap::new(),
+                relayed: vec![],
+            }
+        }
+
+        pub(super) fn handle(&mut self, packet: &[u8]) -> Option<Vec<u8>> {
+            if !matches!(is_stun_message(packet), IsStunMessageInfo::Yes { .. }) {
+                let number = u16::from_be_bytes([packet[0], packet[1]]);
+                let peer = self.channels[&number];
+                self.relayed.push((peer, packet[4..].to_vec()));
+                return None;
+            }
+
+            let mut msg = ParsedMessage::parse(packet.to_vec()).unwrap();
+
+            if msg.class == Class::Indication {
+                assert_eq!(msg.method, Method::Send);
+                let peer = msg.get_attr::<XorPeerAddress>().unwrap().unwrap().0;
+                self.relayed.push((peer, data_attr(&msg).unwrap().to_vec()));
+                return None;
+            }
+
+            self.requests.push(msg.method);
+
+            let integrity = <MessageIntegrity as Attribute>::TYPE;
+
+            if !msg.attributes.iter().any(|attr| attr.typ == integrity) {
+                return Some(self.error(&msg, 401, true));
+            }
+
+            assert!(matches!(
+                msg.get_attr_with::<MessageIntegrity>(&self.key),
+                Some(Ok(_))
+            ));
+
+            let nonce = msg.get_attr::<Nonce>().unwrap().unwrap().0.to_vec();
+
+            if self.stale || nonce != self.nonce {
+                self.stale = false;
+                self.nonce = b"nonce-2".to_vec();
+                return Some(self.error(&msg, 438, false));
+            }
+
+            let mut response = MessageBuilder::new(Class::Success, msg.method, msg.tsx_id);
+
+            match msg.method {
+                Method::Allocate => {
+                    if let Some(code) = self.reject {
+                        return Some(self.error(&msg, code, false));
+                    }
+
+                    response
+                        .add_attr(&XorRelayedAddress(relayed_addr()))
+                        .unwrap();
+                    response.add_attr(&XorMappedAddress(mapped_addr())).unwrap();
+                    response.add_attr(&Lifetime(600)).unwrap();
+                }
+                Method::Refresh => {
+                    if self.drop_refresh {
+                        return None;
+                    }
+
+                    let lifetime = msg.get_attr::<Lifetime>().map_or(600, |l| l.unwrap().0);
+                    response.add_attr(&Lifetime(lifetime)).unwrap();
+                }
+                Method::ChannelBind => {
+                    let number = msg.get_attr::<ChannelNumber>().unwrap().unwrap().0;
+                    let peer = msg.get_attr::<XorPeerAddress>().unwrap().unwrap().0;
+                    self.channels.insert(number, peer);
+                }
+                _ => {}
+            }
+
+            response
+                .add_attr_with(&MessageIntegrity::default(), &self.key)
+                .unwrap();
+
+            Some(response.finish())
+        }
+
+        fn error(&self, msg: &ParsedMessage, number: u32, realm: bool) -> Vec<u8> {
+            let mut response = MessageBuilder::new(Class::Error, msg.method, msg.tsx_id);
+            response
+                .add_attr(&ErrorCode {
+                    number,
+                    reason: "error",
+                })
+                .unwrap();
+
+            if realm {
+                response.add_attr(&Realm::new(REALM)).unwrap();
+            }
+
+            response.add_attr(&Nonce::new(&self.nonce)).unwrap();
+            response.finish()
+        }
+    }
+
+    pub(super) fn client() -> (TurnClient, Arc<TestMetrics>) {
+        let metrics = Arc::new(TestMetrics::default());
+
+        let credentials = TurnCredentials {
+            username: "user".into(),
+            password: "pass".into(),
+        };
+
+        let client = TurnClient::new(server_addr(), credentials).with_metrics(metrics.clone());
+
+        (client, metrics)
+    }
+
+    /// Deliver all pending packets of the client to the server and the responses back
+    pub(super) fn exchange(client: &mut TurnClient, server: &mut Server, now: Instant) {
+        while let Some(packet) = client.poll_transmit() {
+            if let Some(response) = server.handle(&packet) {
+                assert!(client.handle_packet(now, &response).is_none());
+            }
+        }
+    }
+
+    /// Advance to the next timeout of the client
+    fn advance(client: &mut TurnClient, server: &mut Server) -> Instant {
+        let now = client.poll_timeout().unwrap();
+        client.handle_timeout(now);
+        exchange(client, server, now);
+        now
+    }
+
+    fn allocated(now: Instant) -> (TurnClient, Arc<TestMetrics>, Server) {
+        let (mut client, metrics) = client();
+        let mut server = Server::new();
+
+        client.allocate(now);
+        exchange(&mut client, &mut server, now);
+
+        assert_eq!(
+            client.poll_event(),
+            Some(TurnEvent::Allocated {
+                relayed: relayed_addr(),
+                mapped: mapped_addr()
+            })
+        );
+
+        (client, metrics, server)
+    }
+
+    pub(super) fn data_indication(peer: SocketAddr, data: &[u8]) -> Vec<u8> {
+        let mut msg = MessageBuilder::new(Class::Indication, Method::Data, transaction_id());
+        msg.padding_in_value_len(false);
+        msg.add_attr(&XorPeerAddress(peer)).unwrap();
+        msg.add_attr(&Data::new(data)).unwrap();
+        msg.finish()
+    }
+
+    #[test]
+    fn allocate_and_relay() {
+        let now = Instant::now();
+        let (mut client, metrics, mut server) = allocated(now);
+
+        // Unauthenticated allocate, then with the realm and nonce of the 401
+        assert_eq!(server.requests, [Method::Allocate, Method::Allocate]);
+        assert_eq!(client.relayed_addr(), Some(relayed_addr()));
+        assert_eq!(TestMetrics::gauge(&metrics.allocations), 1);
+        assert_eq!(TestMetrics::get(&metrics.rtts), 2);
+
+        let peer = peer_addr();
+
+        client.create_permission(now, peer).unwrap();
+        exchange(&mut client, &mut server, now);
+
+        client.send_to(peer, b"hello").unwrap();
+        exchange(&mut client, &mut server, now);
+        assert_eq!(server.relayed, [(peer, b"hello".to_vec())]);
+
+        // Trailing zero bytes must survive the DATA attribute
+        let received = client.handle_packet(now, &data_indication(peer, &[1, 2, 0, 0]));
+        assert_eq!(
+            received,
+            Some(RelayedData {
+                peer,
+                data: vec![1, 2, 0, 0]
+            })
+        );
+
+        client.bind_channel(now, peer).unwrap();
+        exchange(&mut client, &mut server, now);
+        assert_eq!(TestMetrics::gauge(&metrics.channels), 1);
+
+        client.send_to(peer, b"channel").unwrap();
+        exchange(&mut client, &mut server, now);
+        assert_eq!(server.relayed[1], (peer, b"channel".to_vec()));
+
+        let received = client.handle_packet(now, &[0x40, 0x00, 0x00, 0x03, 7, 8, 9, 0]);
+        assert_eq!(
+            received,
+            Some(RelayedData {
+                peer,
+                data: vec![7, 8, 9]
+            })
+        );
+
+        assert_eq!(TestMetrics::get(&metrics.bytes_sent), 12);
+        assert_eq!(TestMetrics::get(&metrics.bytes_received), 7);
+        assert_eq!(TestMetrics::get(&metrics.refresh_failures), 0);
+
+        // Data for unknown channels is ignored
+        assert!(client
+            .handle_packet(now, &[0x40, 0x01, 0x00, 0x00])
+            .is_none());
+        assert_eq!(TestMetrics::get(&metrics.bytes_received), 7);
+    }
+
+    #[test]
+    fn requests_require_allocation() {
+        let (mut client, _) = client();
+
+        assert_eq!(
+            client.create_permission(Instant::now(), peer_addr()),
+            Err(TurnError::NoAllocation)
+        );
+        assert_eq!(
+            client.send_to(peer_addr(), b"data"),
+            Err(TurnError::NoAllocation)
+        );
+    }
+
+    #[test]
+    fn refresh() {
+        let start = Instant::now();
+        let (mut client, metrics, mut server) = allocated(start);
+
+        // Refreshed 2 minutes before the lifetime of 600 seconds ends, retried with the new nonce
+        server.stale = true;
+
+        let now = advance(&mut client, &mut server);
+        assert_eq!(now - start, Duration::from_secs(480));
+        assert_eq!(server.requests[2..], [Method::Refresh, Method::Refresh]);
+        assert_eq!(server.nonce, b"nonce-2");
+        assert_eq!(TestMetrics::get(&metrics.refresh_failures), 0);
+
+        // Refresh times out after 7 transmissions, retried 5 seconds later
+        server.drop_refresh = true;
+
+        let refresh_at = advance(&mut client, &mut server);
+        assert_eq!(refresh_at - now, Duration::from_secs(480));
+
+        while TestMetrics::get(&metrics.refresh_failures) == 0 {
+            advance(&mut client, &mut server);
+        }
+
+        let failed_at = client.poll_timeout().unwrap() - REFRESH_RETRY;
+        assert_eq!(failed_at - refresh_at, Duration::from_millis(63500));
+
+        server.drop_refresh = false;
+        let requests = server.requests.len();
+
+        advance(&mut client, &mut server);
+        assert_eq!(server.requests.len(), requests + 1);
+
+        assert_eq!(client.poll_event(), None);
+        assert_eq!(client.relayed_addr(), Some(relayed_addr()));
+        assert_eq!(TestMetrics::gauge(&metrics.allocations), 1);
+    }
+
+    #[test]
+    fn expires_without_refresh() {
+        let start = Instant::now();
+        let (mut client, metrics, mut server) = allocated(start);
+
+        client.bind_channel(start, peer_addr()).unwrap();
+        exchange(&mut client, &mut server, start);
+        assert_eq!(TestMetrics::gauge(&metrics.channels), 1);
+
+        server.drop_refresh = true;
+
+        let now = loop {
+            let now = advance(&mut client, &mut server);
+
+            if client.relayed_addr().is_none() {
+                break now;
+            }
+        };
+
+        assert_eq!(now - start, Duration::from_secs(600));
+        assert_eq!(client.poll_event(), Some(TurnEvent::Closed));
+        assert_eq!(TestMetrics::gauge(&metrics.allocations), 0);
+        assert_eq!(TestMetrics::gauge(&metrics.channels), 0);
+        assert!(TestMetrics::get(&metrics.refresh_failures) > 0);
+        assert_eq!(client.poll_timeout(), None);
+    }
+
+    #[test]
+    fn allocation_rejected() {
+        let (mut client, metrics) = client();
+        let mut server = Server::new();
+        server.reject = Some(486);
+
+        client.allocate(Instant::now());
+        exchange(&mut client, &mut server, Instant::now());
+
+        assert_eq!(
+            client.poll_event(),
+            Some(TurnEvent::AllocationFailed {
+                error: TurnError::ErrorResponse {
+                    code: 486,
+                    reason: "error".into()
+                }
+            })
+        );
+        assert_eq!(client.relayed_addr(), None);
+        assert_eq!(TestMetrics::gauge(&metrics.allocations), 0);
+    }
+
+    #[test]
+    fn release() {
+        let now = Instant::now();
+        let (mut client, metrics, mut server) = allocated(now);
+
+        client.bind_channel(now, peer_addr()).unwrap();
+        exchange(&mut client, &mut server, now);
+
+        client.release(now);
+        exchange(&mut client, &mut server, now);
+
+        assert_eq!(server.requests.last(), Some(&Method::Refresh));
+        assert_eq!(client.poll_event(), Some(TurnEvent::Closed));
+        assert_eq!(client.relayed_addr(), None);
+        assert_eq!(TestMetrics::gauge(&metrics.allocations), 0);
+        assert_eq!(TestMetrics::gauge(&metrics.channels), 0);
+    }
+}
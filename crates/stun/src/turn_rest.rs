@@ -0,0 +1,204 @@
+//! Ephemeral TURN credentials using the "TURN REST API" convention
+//! ([draft-uberti-behave-turn-rest](https://datatracker.ietf.org/doc/html/draft-uberti-behave-turn-rest-00))
+//! as implemented by coturn and used by most WebRTC deployments.
+//!
+//! The username is `<expiry-timestamp>:<user>` and the password is
+//! `base64(HMAC-SHA1(shared-secret, username))`. Both sides only need to know the shared secret,
+//! the server derives the password from the received username and rejects expired ones.
+
+use crate::auth::StunCredential;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Mac, SimpleHmac};
+use sha1::Sha1;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("username does not start with a timestamp")]
+    InvalidUsername,
+    #[error("credentials expired")]
+    Expired,
+    #[error("password does not match")]
+    InvalidPassword,
+}
+
+/// Shared secret between the TURN server and the service handing out credentials.
+#[derive(Clone)]
+pub struct TurnRestSecret {
+    secret: Vec<u8>,
+}
+
+impl TurnRestSecret {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into(),
+        }
+    }
+
+    /// Generate credentials for `user` which are valid for the given `ttl`.
+    pub fn generate(&self, user: Option<&str>, ttl: Duration) -> TurnRestCredentials {
+        self.generate_at(user, SystemTime::now() + ttl)
+    }
+
+    /// Generate credentials for `user` which expire at the given point in time.
+    pub fn generate_at(&self, user: Option<&str>, expires: SystemTime) -> TurnRestCredentials {
+        let timestamp = unix_timestamp(expires);
+
+        let username = match user {
+            Some(user) => format!("{timestamp}:{user}"),
+            None => timestamp.to_string(),
+        };
+
+        let password = STANDARD.encode(self.hmac(&username).finalize().into_bytes());
+
+        TurnRestCredentials {
+            username,
+            password,
+            expires,
+        }
+    }
+
+    /// Server side: check the expiry of `username` and return the password it must authenticate with.
+    ///
+    /// The returned password is meant to be used as long-term credential password to verify the
+    /// message integrity of the received request.
+    pub fn password(&self, username: &str) -> Result<String, Error> {
+        self.password_at(username, SystemTime::now())
+    }
+
+    /// Same as [`TurnRestSecret::password`] but with an explicit current time.
+    pub fn password_at(&self, username: &str, now: SystemTime) -> Result<String, Error> {
+        check_expiry(username, now)?;
+
+        Ok(STANDARD.encode(self.hmac(username).finalize().into_bytes()))
+    }
+
+    /// Server side: check the expiry of `username` and verify the given `password`.
+    pub fn verify(&self, username: &str, password: &str) -> Result<(), Error> {
+        self.verify_at(username, password, SystemTime::now())
+    }
+
+    /// Same as [`TurnRestSecret::verify`] but with an explicit current time.
+    pub fn verify_at(&self, username: &str, password: &str, now: SystemTime) -> Result<(), Error> {
+        check_expiry(username, now)?;
+
+        let password = STANDARD
+            .decode(password)
+            .map_err(|_| Error::InvalidPassword)?;
+
+        // verify_slice compares in constant time
+        self.hmac(username)
+            .verify_slice(&password)
+            .map_err(|_| Error::InvalidPassword)
+    }
+
+    fn hmac(&self, username: &str) -> SimpleHmac<Sha1> {
+        let mut hmac: SimpleHmac<Sha1> =
+            SimpleHmac::new_from_slice(&self.secret).expect("hmac accepts keys of any length");
+        hmac.update(username.as_bytes());
+        hmac
+    }
+}
+
+/// Time limited credentials generated by [`TurnRestSecret`]
+#[derive(Debug, Clone)]
+pub struct TurnRestCredentials {
+    pub username: String,
+    pub password: String,
+    pub expires: SystemTime,
+}
+
+impl TurnRestCredentials {
+    /// Returns the remaining time until the credentials expire
+    pub fn time_to_expiry(&self) -> Option<Duration> {
+        self.expires.duration_since(SystemTime::now()).ok()
+    }
+
+    /// Convert into a long-term [`StunCredential`] for the given `realm`
+    pub fn into_credential(self, realm: impl Into<String>) -> StunCredential {
+        StunCredential::LongTerm {
+            realm: realm.into(),
+            username: self.username,
+            password: self.password,
+        }
+    }
+}
+
+/// Parse the timestamp part of a TURN REST username and check if it has expired at `now`
+pub fn check_expiry(username: &str, now: SystemTime) -> Result<(), Error> {
+    let timestamp = username.split(':').next().unwrap_or(username);
+    let timestamp: u64 = timestamp.parse().map_err(|_| Error::InvalidUsername)?;
+
+    if unix_timestamp(now) > timestamp {
+        Err(Error::Expired)
+    } else {
+        Ok(())
+    }
+}
+
+fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn generate() {
+        let secret = TurnRestSecret::new("north");
+
+        let credentials = secret.generate_at(Some("alice"), at(1433895918));
+
+        assert_eq!(credentials.username, "1433895918:alice");
+        assert_eq!(credentials.password, "RqMvcGPYTJMGThVLSi4amT4zFtI=");
+    }
+
+    #[test]
+    fn generate_without_user() {
+        let secret = TurnRestSecret::new("secret");
+
+        let credentials = secret.generate_at(None, at(1700000000));
+
+        assert_eq!(credentials.username, "1700000000");
+        assert_eq!(credentials.password, "WGw37+g43pfwVUmrc9tgArn/juE=");
+    }
+
+    #[test]
+    fn verify() {
+        let secret = TurnRestSecret::new("secret");
+
+        let credentials = secret.generate_at(Some("bob"), at(1700000000));
+
+        secret
+            .verify_at(&credentials.username, &credentials.password, at(1699999999))
+            .unwrap();
+
+        assert_eq!(
+            secret
+                .password_at(&credentials.username, at(1700000000))
+                .unwrap(),
+            credentials.password
+        );
+
+        assert!(matches!(
+            secret.verify_at(&credentials.username, &credentials.password, at(1700000001)),
+            Err(Error::Expired)
+        ));
+        assert!(matches!(
+            secret.verify_at(&credentials.username, "YWJj", at(1699999999)),
+            Err(Error::InvalidPassword)
+        ));
+        assert!(matches!(
+            secret.verify_at("bob", &credentials.password, at(1699999999)),
+            Err(Error::InvalidUsername)
+        ));
+    }
+}
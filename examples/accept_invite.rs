@@ -56,9 +56,22 @@ impl Layer for InviteAcceptLayer {
 
                     event.respond_success(response).await.unwrap();
                 }
+                Event::UpdateReceived(event) => {
+                    event.process_default().await.unwrap();
+                }
+                Event::ReferReceived(event) => {
+                    event.decline().await.unwrap();
+                }
+                Event::InfoReceived(event) => {
+                    event.process_default().await.unwrap();
+                }
                 Event::Bye(event) => {
                     event.process_default().await.unwrap();
                 }
+                Event::Replaced(session) => {
+                    session.terminate().await.unwrap();
+                    break;
+                }
                 Event::Terminated => {
                     break;
                 }
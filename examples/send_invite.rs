@@ -41,7 +41,7 @@ async fn main() -> Result<()> {
     loop {
         let mut invite = initiator.create_invite();
 
-        auth_sess.authorize_request(&mut invite.headers);
+        auth_sess.authorize_request(&invite.line, &mut invite.headers, &invite.body);
 
         initiator.send_invite(invite).await?;
 